@@ -0,0 +1,145 @@
+//! 尽力而为的审计日志写入
+//!
+//! 房间设置修改、清除用户内容这类操作本身已经完成，审计记录只是附带的痕迹：
+//! 写入放到后台任务里，既不拖慢主操作，写失败也不让主操作报错。
+//! 写失败的记录只打日志并计入指标，由监控发现持续丢失。
+//!
+//! 踢人、调整角色、删除房间等操作的审计记录和操作本身在同一个事务里提交，不经过这里。
+
+use std::sync::Arc;
+
+use domain::AuditLogEntry;
+use tokio::sync::watch;
+
+use crate::repository::AuditLogRepository;
+
+/// 后台写入审计记录
+#[derive(Clone)]
+pub struct AuditLogger {
+    repository: Arc<dyn AuditLogRepository>,
+    /// 还没写完的记录数
+    pending: Arc<watch::Sender<usize>>,
+}
+
+impl AuditLogger {
+    pub fn new(repository: Arc<dyn AuditLogRepository>) -> Self {
+        Self {
+            repository,
+            pending: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// 提交一条审计记录后立即返回
+    pub fn record(&self, entry: AuditLogEntry) {
+        let repository = Arc::clone(&self.repository);
+        let pending = Arc::clone(&self.pending);
+        pending.send_modify(|pending| *pending += 1);
+        tokio::spawn(async move {
+            let action = entry.action.clone();
+            if let Err(error) = repository.record(entry).await {
+                metrics::counter!(
+                    crate::metrics::AUDIT_LOG_WRITES_DROPPED_TOTAL,
+                    "action" => action.clone()
+                )
+                .increment(1);
+                tracing::warn!(%error, action, "审计记录写入失败，已丢弃");
+            }
+            pending.send_modify(|pending| *pending -= 1);
+        });
+    }
+
+    /// 等待已提交的记录全部写完（或失败），停机时调用
+    pub async fn flush(&self) {
+        let mut pending = self.pending.subscribe();
+        let _ = pending.wait_for(|pending| *pending == 0).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use domain::{RepositoryError, RoomId, UserId};
+    use uuid::Uuid;
+
+    use crate::repository::{AuditLogQuery, PaginatedResult};
+
+    #[derive(Default)]
+    struct RecordingAuditLog {
+        entries: Mutex<Vec<AuditLogEntry>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl AuditLogRepository for RecordingAuditLog {
+        async fn record(&self, entry: AuditLogEntry) -> Result<(), RepositoryError> {
+            if self.fail {
+                return Err(RepositoryError::storage("connection refused"));
+            }
+            self.entries.lock().unwrap().push(entry);
+            Ok(())
+        }
+
+        async fn find_by_target_user(
+            &self,
+            _user_id: UserId,
+            _limit: i64,
+        ) -> Result<Vec<AuditLogEntry>, RepositoryError> {
+            unreachable!()
+        }
+
+        async fn find_by_target_room(
+            &self,
+            _room_id: RoomId,
+            _action: &str,
+            _page: u32,
+            _page_size: u32,
+        ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError> {
+            unreachable!()
+        }
+
+        async fn search(
+            &self,
+            _query: &AuditLogQuery,
+        ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError> {
+            unreachable!()
+        }
+    }
+
+    fn entry() -> AuditLogEntry {
+        AuditLogEntry::record(
+            UserId::from(Uuid::new_v4()),
+            AuditLogEntry::ROOM_SETTINGS_UPDATED,
+            None,
+            serde_json::json!({}),
+            time::OffsetDateTime::now_utc(),
+        )
+    }
+
+    #[tokio::test]
+    async fn writes_in_background() {
+        let repository = Arc::new(RecordingAuditLog::default());
+        let logger = AuditLogger::new(repository.clone());
+
+        let entry = entry();
+        logger.record(entry.clone());
+        logger.flush().await;
+
+        assert_eq!(*repository.entries.lock().unwrap(), vec![entry]);
+    }
+
+    #[tokio::test]
+    async fn failed_write_is_dropped() {
+        let repository = Arc::new(RecordingAuditLog {
+            fail: true,
+            ..Default::default()
+        });
+        let logger = AuditLogger::new(repository.clone());
+
+        logger.record(entry());
+        logger.flush().await;
+        assert!(repository.entries.lock().unwrap().is_empty());
+    }
+}
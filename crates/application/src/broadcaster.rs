@@ -1,7 +1,11 @@
 use async_trait::async_trait;
-use domain::{Message, RoomId};
+use config::BroadcastConfig;
+use domain::{Message, MessageId, ReactionSummary, RoomId, RoomRole, Timestamp, UserId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::error::TryRecvError;
@@ -16,9 +20,89 @@ pub enum WebSocketMessage {
     /// 聊天消息
     #[serde(rename = "chat_message")]
     ChatMessage(Message),
+    /// 连接建立时补发的未送达消息，`replayed` 恒为 true，方便客户端与实时消息区分
+    #[serde(rename = "replayed_message")]
+    ReplayedMessage {
+        #[serde(flatten)]
+        message: Message,
+        replayed: bool,
+    },
+    /// 消息已被编辑
+    #[serde(rename = "message_edited")]
+    MessageEdited {
+        message_id: MessageId,
+        content: String,
+        edited_at: Timestamp,
+        version: i32,
+    },
+    /// 消息已被删除，客户端应从界面移除
+    #[serde(rename = "message_deleted")]
+    MessageDeleted { message_id: MessageId },
+    /// 已删除的消息被管理员恢复，客户端应重新显示
+    #[serde(rename = "message_restored")]
+    MessageRestored(Message),
+    /// 消息回应变化，携带该消息最新的完整聚合
+    #[serde(rename = "reaction_changed")]
+    ReactionChanged {
+        message_id: MessageId,
+        reactions: Vec<ReactionSummary>,
+    },
+    /// 已读回执
+    #[serde(rename = "read_receipt")]
+    ReadReceipt {
+        room_id: RoomId,
+        user_id: UserId,
+        message_id: MessageId,
+    },
+    /// 成员角色变化，客户端据此更新成员列表
+    #[serde(rename = "member_role_changed")]
+    MemberRoleChanged {
+        room_id: RoomId,
+        user_id: UserId,
+        role: RoomRole,
+    },
     /// 在线统计更新
     #[serde(rename = "online_stats")]
     OnlineStatsUpdate(OnlineStats),
+    /// 房间在线成员变化，每个房间每秒最多推送一次
+    #[serde(rename = "room_presence")]
+    RoomPresence {
+        room_id: RoomId,
+        online_count: u64,
+        user_ids: Vec<UserId>,
+    },
+    /// 成员上线/下线的增量变化，配合连接时的 roster 快照使用
+    #[serde(rename = "presence")]
+    Presence {
+        user_id: UserId,
+        event: PresenceChange,
+    },
+    /// 消息被置顶
+    #[serde(rename = "message_pinned")]
+    MessagePinned {
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: Timestamp,
+    },
+    /// 消息被取消置顶
+    #[serde(rename = "message_unpinned")]
+    MessageUnpinned {
+        room_id: RoomId,
+        message_id: MessageId,
+        unpinned_by: UserId,
+    },
+    /// 成员在消息中被 @ 提及，只发给 `user_ids` 里的用户
+    #[serde(rename = "mention")]
+    Mention {
+        room_id: RoomId,
+        message_id: MessageId,
+        sender_id: UserId,
+        user_ids: Vec<UserId>,
+    },
+    /// 用户的会话被强制结束（例如账号被停用），只发给该用户，随后服务端关闭连接
+    #[serde(rename = "session_revoked")]
+    SessionRevoked { user_id: UserId, reason: String },
     /// 系统通知
     #[serde(rename = "system_notification")]
     SystemNotification {
@@ -28,6 +112,14 @@ pub enum WebSocketMessage {
     },
 }
 
+/// 成员在线状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceChange {
+    Connected,
+    Disconnected,
+}
+
 /// 通用广播消息，可以携带不同类型的WebSocket消息
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct MessageBroadcast {
@@ -44,6 +136,132 @@ impl MessageBroadcast {
         }
     }
 
+    /// 创建消息编辑广播
+    pub fn message_edited(room_id: RoomId, message: &Message) -> Self {
+        let edited_at = message
+            .last_revision
+            .as_ref()
+            .map_or(message.created_at, |revision| revision.updated_at);
+        Self {
+            room_id,
+            message: WebSocketMessage::MessageEdited {
+                message_id: message.id,
+                content: message.content.as_str().to_owned(),
+                edited_at,
+                version: message.version,
+            },
+        }
+    }
+
+    /// 创建强制下线广播，`reason` 为机器可读的原因（例如 `ACCOUNT_SUSPENDED`）
+    pub fn session_revoked(room_id: RoomId, user_id: UserId, reason: &str) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::SessionRevoked {
+                user_id,
+                reason: reason.to_string(),
+            },
+        }
+    }
+
+    /// 创建提及提醒广播，房间里只有被提及的用户会收到
+    pub fn mention(message: &Message, user_ids: Vec<UserId>) -> Self {
+        Self {
+            room_id: message.room_id,
+            message: WebSocketMessage::Mention {
+                room_id: message.room_id,
+                message_id: message.id,
+                sender_id: message.sender_id,
+                user_ids,
+            },
+        }
+    }
+
+    /// 创建消息置顶广播
+    pub fn message_pinned(
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: Timestamp,
+    ) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::MessagePinned {
+                room_id,
+                message_id,
+                pinned_by,
+                pinned_at,
+            },
+        }
+    }
+
+    /// 创建取消置顶广播
+    pub fn message_unpinned(room_id: RoomId, message_id: MessageId, unpinned_by: UserId) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::MessageUnpinned {
+                room_id,
+                message_id,
+                unpinned_by,
+            },
+        }
+    }
+
+    /// 创建消息删除广播
+    pub fn message_deleted(room_id: RoomId, message_id: MessageId) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::MessageDeleted { message_id },
+        }
+    }
+
+    /// 创建消息恢复广播
+    pub fn message_restored(message: Message) -> Self {
+        Self {
+            room_id: message.room_id,
+            message: WebSocketMessage::MessageRestored(message),
+        }
+    }
+
+    /// 创建回应变化广播
+    pub fn reaction_changed(
+        room_id: RoomId,
+        message_id: MessageId,
+        reactions: Vec<ReactionSummary>,
+    ) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::ReactionChanged {
+                message_id,
+                reactions,
+            },
+        }
+    }
+
+    /// 创建已读回执广播
+    pub fn read_receipt(room_id: RoomId, user_id: UserId, message_id: MessageId) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::ReadReceipt {
+                room_id,
+                user_id,
+                message_id,
+            },
+        }
+    }
+
+    /// 创建成员角色变化广播
+    pub fn member_role_changed(room_id: RoomId, user_id: UserId, role: RoomRole) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::MemberRoleChanged {
+                room_id,
+                user_id,
+                role,
+            },
+        }
+    }
+
     /// 创建统计更新广播
     pub fn stats(room_id: RoomId, stats: OnlineStats) -> Self {
         Self {
@@ -52,6 +270,26 @@ impl MessageBroadcast {
         }
     }
 
+    /// 创建房间在线成员广播
+    pub fn room_presence(room_id: RoomId, user_ids: Vec<UserId>) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::RoomPresence {
+                room_id,
+                online_count: user_ids.len() as u64,
+                user_ids,
+            },
+        }
+    }
+
+    /// 创建成员上线/下线广播
+    pub fn presence(room_id: RoomId, user_id: UserId, event: PresenceChange) -> Self {
+        Self {
+            room_id,
+            message: WebSocketMessage::Presence { user_id, event },
+        }
+    }
+
     /// 创建系统通知广播
     pub fn system_notification(room_id: RoomId, message: String) -> Self {
         Self {
@@ -68,30 +306,58 @@ impl MessageBroadcast {
 pub enum BroadcastError {
     #[error("broadcast failed: {0}")]
     Failed(String),
+    /// 广播通道暂时不可用（例如 Redis 重启），重试次数用尽
+    #[error("broadcast unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl BroadcastError {
     pub fn failed(message: impl Into<String>) -> Self {
         Self::Failed(message.into())
     }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::Unavailable(message.into())
+    }
+}
+
+/// 订阅流里的事件
+// 事件逐条产出后立即消费，不值得为 Gap 把消息装箱
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Message(MessageBroadcast),
+    /// 订阅中断后重新订阅，或订阅者落后缓冲被覆盖；期间的广播可能丢失，订阅方应从存储补齐
+    Gap,
 }
 
 #[async_trait]
 pub trait MessageBroadcaster: Send + Sync {
     async fn broadcast(&self, payload: MessageBroadcast) -> Result<(), BroadcastError>;
     async fn subscribe(&self, room_id: RoomId) -> Result<MessageStream, BroadcastError>;
+
+    /// 停机时调用：结束已有的订阅流，释放后台连接；没有后台任务的实现不用处理
+    async fn shutdown(&self) {}
 }
 
 enum MessageStreamKind {
     Local {
         receiver: broadcast::Receiver<MessageBroadcast>,
         room_id: RoomId,
+        /// 由 [`LocalMessageBroadcaster`] 创建的订阅才有，落后时借此扩容并换到新通道
+        channel: Option<LocalSubscription>,
     },
     Remote {
-        stream: Pin<Box<dyn Stream<Item = Result<MessageBroadcast, BroadcastError>> + Send>>,
+        stream: Pin<Box<dyn Stream<Item = Result<StreamEvent, BroadcastError>> + Send>>,
     },
 }
 
+/// 订阅流当前所在的进程内通道
+struct LocalSubscription {
+    channels: Arc<LocalChannels>,
+    capacity: usize,
+}
+
 pub struct MessageStream {
     kind: MessageStreamKind,
 }
@@ -99,30 +365,78 @@ pub struct MessageStream {
 impl MessageStream {
     pub fn local(receiver: broadcast::Receiver<MessageBroadcast>, room_id: RoomId) -> Self {
         Self {
-            kind: MessageStreamKind::Local { receiver, room_id },
+            kind: MessageStreamKind::Local {
+                receiver,
+                room_id,
+                channel: None,
+            },
         }
     }
 
     pub fn remote(
-        stream: Pin<Box<dyn Stream<Item = Result<MessageBroadcast, BroadcastError>> + Send>>,
+        stream: Pin<Box<dyn Stream<Item = Result<StreamEvent, BroadcastError>> + Send>>,
     ) -> Self {
         Self {
             kind: MessageStreamKind::Remote { stream },
         }
     }
 
+    /// 下一条广播，跳过订阅中断的标记；需要据此补齐消息的订阅方用 [`Self::recv_event`]
     pub async fn recv(&mut self) -> Option<MessageBroadcast> {
+        loop {
+            match self.recv_event().await? {
+                StreamEvent::Message(broadcast) => return Some(broadcast),
+                StreamEvent::Gap => continue,
+            }
+        }
+    }
+
+    /// 下一个事件：广播或订阅中断的标记
+    pub async fn recv_event(&mut self) -> Option<StreamEvent> {
         match &mut self.kind {
-            MessageStreamKind::Local { receiver, room_id } => loop {
+            MessageStreamKind::Local {
+                receiver,
+                room_id,
+                channel,
+            } => loop {
                 match receiver.recv().await {
-                    Ok(broadcast) if broadcast.room_id == *room_id => return Some(broadcast),
+                    Ok(broadcast) if broadcast.room_id == *room_id => {
+                        return Some(StreamEvent::Message(broadcast))
+                    }
                     Ok(_) => continue,
-                    Err(_) => return None,
+                    // 突发流量下慢订阅者会落后：被覆盖的消息已经丢失，产出 Gap 让订阅方从存储补齐，
+                    // 然后继续消费，而不是断开连接
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, room_id = %room_id, "local broadcast lagged");
+                        metrics::counter!(
+                            crate::metrics::BROADCAST_LAGGED_TOTAL,
+                            "room_id" => room_id.to_string()
+                        )
+                        .increment(1);
+                        // 扩容后立刻换到新通道，旧缓冲里剩下的消息也由订阅方按 Gap 补齐
+                        if let Some(channel) = channel {
+                            if let Some((next, capacity)) =
+                                channel.channels.grow(*room_id, channel.capacity)
+                            {
+                                *receiver = next;
+                                channel.capacity = capacity;
+                            }
+                        }
+                        return Some(StreamEvent::Gap);
+                    }
+                    // 通道因扩容被替换时换到新通道，换通道期间的广播由订阅方补齐；停机后流结束
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let channel = channel.as_mut()?;
+                        let (next, capacity) = channel.channels.resubscribe(*room_id)?;
+                        *receiver = next;
+                        channel.capacity = capacity;
+                        return Some(StreamEvent::Gap);
+                    }
                 }
             },
             MessageStreamKind::Remote { stream } => loop {
                 match stream.next().await {
-                    Some(Ok(broadcast)) => return Some(broadcast),
+                    Some(Ok(event)) => return Some(event),
                     Some(Err(err)) => {
                         tracing::warn!(error = %err, "message stream error");
                         continue;
@@ -135,7 +449,9 @@ impl MessageStream {
 
     pub fn try_recv(&mut self) -> Result<Option<MessageBroadcast>, BroadcastError> {
         match &mut self.kind {
-            MessageStreamKind::Local { receiver, room_id } => loop {
+            MessageStreamKind::Local {
+                receiver, room_id, ..
+            } => loop {
                 match receiver.try_recv() {
                     Ok(broadcast) if broadcast.room_id == *room_id => return Ok(Some(broadcast)),
                     Ok(_) => continue,
@@ -150,3 +466,236 @@ impl MessageStream {
         }
     }
 }
+
+/// 进程内广播（单实例部署，未配置 Redis 时使用）
+///
+/// 每个房间一个独立的 broadcast 通道，消息多的房间不会覆盖其他房间订阅者的缓冲。
+/// 房间没有订阅者时不保留通道，广播直接丢弃。
+///
+/// 订阅者落后时该房间的缓冲翻倍，不超过 `max_capacity`；扩容后 `shrink_after` 内没有
+/// 订阅者再落后，下一条广播之后缩回初始缓冲。broadcast 通道建好后不能改容量，
+/// 扩容和缩容都是换一个新通道：落后的订阅者立刻换过去，其他订阅者读完旧通道后跟着换，
+/// 两边都产出 Gap 由订阅方从存储补齐。
+pub struct LocalMessageBroadcaster {
+    channels: Arc<LocalChannels>,
+}
+
+struct LocalChannels {
+    default_capacity: usize,
+    max_capacity: usize,
+    shrink_after: Duration,
+    state: Mutex<LocalChannelState>,
+}
+
+#[derive(Default)]
+struct LocalChannelState {
+    rooms: HashMap<RoomId, RoomChannel>,
+    /// 单独配置的缓冲条数，即该房间的初始缓冲
+    capacities: HashMap<RoomId, usize>,
+    closed: bool,
+}
+
+struct RoomChannel {
+    sender: broadcast::Sender<MessageBroadcast>,
+    capacity: usize,
+    /// 最近一次有订阅者落后导致扩容（或已到上限仍落后）的时间，未扩容时为 None；
+    /// 扩容状态跟着通道走，房间没有订阅者、通道释放后随之清除
+    grown_at: Option<Instant>,
+}
+
+impl LocalChannels {
+    fn lock(&self) -> MutexGuard<'_, LocalChannelState> {
+        self.state.lock().expect("broadcast channels poisoned")
+    }
+
+    fn initial_capacity(&self, state: &LocalChannelState, room_id: RoomId) -> usize {
+        state
+            .capacities
+            .get(&room_id)
+            .copied()
+            .unwrap_or(self.default_capacity)
+    }
+
+    fn subscribe_locked(
+        &self,
+        state: &mut LocalChannelState,
+        room_id: RoomId,
+    ) -> (broadcast::Receiver<MessageBroadcast>, usize) {
+        let capacity = self.initial_capacity(state, room_id);
+        let channel = state.rooms.entry(room_id).or_insert_with(|| RoomChannel {
+            sender: broadcast::channel(capacity).0,
+            capacity,
+            grown_at: None,
+        });
+        (channel.sender.subscribe(), channel.capacity)
+    }
+
+    /// 订阅者在容量为 `lagged_capacity` 的通道上落后：还没扩容过就把房间缓冲翻倍换成新通道，
+    /// 已被其他订阅者扩容过就直接换过去；已到上限或已停机时返回 None，订阅者留在原通道
+    fn grow(
+        &self,
+        room_id: RoomId,
+        lagged_capacity: usize,
+    ) -> Option<(broadcast::Receiver<MessageBroadcast>, usize)> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        let now = Instant::now();
+        let current = state
+            .rooms
+            .get(&room_id)
+            .map_or(lagged_capacity, |channel| channel.capacity);
+        if current <= lagged_capacity {
+            if lagged_capacity >= self.max_capacity {
+                // 到上限后仍在落后，推迟缩容
+                if let Some(channel) = state.rooms.get_mut(&room_id) {
+                    channel.grown_at = channel.grown_at.map(|_| now);
+                }
+                return None;
+            }
+            let capacity = lagged_capacity.saturating_mul(2).min(self.max_capacity);
+            tracing::info!(room_id = %room_id, capacity, "local broadcast buffer grown");
+            set_room_capacity_gauge(room_id, capacity);
+            // 替换掉旧通道的发送端，其余订阅者读完旧缓冲后收到 Closed 再换过来
+            state.rooms.insert(
+                room_id,
+                RoomChannel {
+                    sender: broadcast::channel(capacity).0,
+                    capacity,
+                    grown_at: Some(now),
+                },
+            );
+        }
+        Some(self.subscribe_locked(&mut state, room_id))
+    }
+
+    /// 旧通道关闭后换到房间当前的通道；停机后返回 None
+    fn resubscribe(
+        &self,
+        room_id: RoomId,
+    ) -> Option<(broadcast::Receiver<MessageBroadcast>, usize)> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        Some(self.subscribe_locked(&mut state, room_id))
+    }
+
+    /// 发送一条广播；没有订阅者时释放通道，扩容后安静了 `shrink_after` 的通道缩回初始缓冲
+    fn send(&self, payload: MessageBroadcast) {
+        let mut state = self.lock();
+        let room_id = payload.room_id;
+        let Some(channel) = state.rooms.get(&room_id) else {
+            return;
+        };
+        let delivered = channel.sender.send(payload).is_ok();
+        let grown = channel.grown_at.is_some();
+        let quiet = channel
+            .grown_at
+            .is_some_and(|grown_at| grown_at.elapsed() >= self.shrink_after);
+        if delivered && !quiet {
+            return;
+        }
+
+        // 这条广播已经进了旧通道，订阅者读完后收到 Closed，换到按初始缓冲新建的通道
+        state.rooms.remove(&room_id);
+        if grown {
+            let capacity = self.initial_capacity(&state, room_id);
+            if delivered {
+                tracing::info!(room_id = %room_id, capacity, "local broadcast buffer shrunk");
+            }
+            set_room_capacity_gauge(room_id, capacity);
+        }
+    }
+}
+
+fn set_room_capacity_gauge(room_id: RoomId, capacity: usize) {
+    metrics::gauge!(
+        crate::metrics::BROADCAST_ROOM_CAPACITY,
+        "room_id" => room_id.to_string()
+    )
+    .set(capacity as f64);
+}
+
+impl LocalMessageBroadcaster {
+    /// 每个房间固定 `default_capacity` 条缓冲，不扩容
+    pub fn new(default_capacity: usize) -> Self {
+        Self::with_growth(default_capacity, default_capacity, Duration::ZERO)
+    }
+
+    /// 订阅者落后时缓冲翻倍扩容到 `max_capacity` 为止，扩容后 `shrink_after` 内
+    /// 没有订阅者再落后就缩回初始缓冲
+    pub fn with_growth(
+        default_capacity: usize,
+        max_capacity: usize,
+        shrink_after: Duration,
+    ) -> Self {
+        let default_capacity = default_capacity.max(1);
+        Self {
+            channels: Arc::new(LocalChannels {
+                default_capacity,
+                max_capacity: max_capacity.max(default_capacity),
+                shrink_after,
+                state: Mutex::new(LocalChannelState::default()),
+            }),
+        }
+    }
+
+    /// 为指定房间设置初始缓冲条数，只影响之后新建的通道
+    pub fn with_room_capacity(self, room_id: RoomId, capacity: usize) -> Self {
+        self.channels
+            .lock()
+            .capacities
+            .insert(room_id, capacity.max(1));
+        self
+    }
+
+    /// 按配置创建；房间 ID 写错的覆盖项只记日志并忽略
+    pub fn from_config(config: &BroadcastConfig) -> Self {
+        config.room_capacities.iter().fold(
+            Self::with_growth(
+                config.capacity,
+                config.max_capacity,
+                Duration::from_secs(config.shrink_after_secs),
+            ),
+            |broadcaster, (room_id, &capacity)| match room_id.parse::<uuid::Uuid>() {
+                Ok(room_id) => broadcaster.with_room_capacity(RoomId::from(room_id), capacity),
+                Err(err) => {
+                    tracing::warn!(%room_id, error = %err, "忽略无效的房间广播缓冲配置");
+                    broadcaster
+                }
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl MessageBroadcaster for LocalMessageBroadcaster {
+    async fn broadcast(&self, payload: MessageBroadcast) -> Result<(), BroadcastError> {
+        self.channels.send(payload);
+        Ok(())
+    }
+
+    async fn subscribe(&self, room_id: RoomId) -> Result<MessageStream, BroadcastError> {
+        let mut state = self.channels.lock();
+        let (receiver, capacity) = self.channels.subscribe_locked(&mut state, room_id);
+        Ok(MessageStream {
+            kind: MessageStreamKind::Local {
+                receiver,
+                room_id,
+                channel: Some(LocalSubscription {
+                    channels: self.channels.clone(),
+                    capacity,
+                }),
+            },
+        })
+    }
+
+    /// 释放所有通道，已有的订阅流随之结束
+    async fn shutdown(&self) {
+        let mut state = self.channels.lock();
+        state.closed = true;
+        state.rooms.clear();
+    }
+}
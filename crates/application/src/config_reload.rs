@@ -0,0 +1,135 @@
+//! 运行时配置重载
+//!
+//! 只有不影响已建立资源的配置才能热更新（目前是限流默认策略）。
+//! 数据库地址、监听地址、JWT 密钥等在启动时就固化进连接池和服务里，
+//! 改了也只会在重启后生效，重载时只记录警告。
+//!
+//! 触发方式：向进程发送 SIGHUP，或由系统管理员调用重载接口。
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use config::{AppConfig, ConfigError, RateLimitConfig};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::rate_limiter::MessageRateLimiter;
+
+/// 运行时可替换的配置子集
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloadableConfig {
+    pub rate_limit: RateLimitConfig,
+}
+
+impl From<&AppConfig> for ReloadableConfig {
+    fn from(config: &AppConfig) -> Self {
+        Self {
+            rate_limit: config.rate_limit.clone(),
+        }
+    }
+}
+
+/// 一次重载的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReloadReport {
+    /// 可重载的配置是否有变化
+    pub changed: bool,
+    /// 新配置中修改了、但要重启才生效的配置项
+    pub restart_required: Vec<&'static str>,
+}
+
+type ConfigLoader = Box<dyn Fn() -> Result<AppConfig, ConfigError> + Send + Sync>;
+
+/// 配置重载器：重新读取配置文件，校验通过后原子替换可重载部分
+pub struct ConfigReloader {
+    startup: AppConfig,
+    current: ArcSwap<ReloadableConfig>,
+    rate_limiter: Arc<MessageRateLimiter>,
+    loader: ConfigLoader,
+    /// 串行化重载，SIGHUP 和接口同时触发时不会交错替换
+    reload_lock: Mutex<()>,
+}
+
+impl ConfigReloader {
+    /// `startup` 为启动时加载的配置，之后以它为基准判断哪些修改需要重启
+    pub fn new(startup: AppConfig, rate_limiter: Arc<MessageRateLimiter>) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(ReloadableConfig::from(&startup)),
+            startup,
+            rate_limiter,
+            loader: Box::new(AppConfig::load),
+            reload_lock: Mutex::new(()),
+        }
+    }
+
+    /// 替换配置来源（测试用）
+    pub fn with_loader(
+        mut self,
+        loader: impl Fn() -> Result<AppConfig, ConfigError> + Send + Sync + 'static,
+    ) -> Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    /// 当前生效的可重载配置
+    pub fn current(&self) -> Arc<ReloadableConfig> {
+        self.current.load_full()
+    }
+
+    /// 重新加载配置；加载或校验失败时保持原配置不变
+    pub async fn reload(&self) -> Result<ReloadReport, ConfigError> {
+        let _guard = self.reload_lock.lock().await;
+
+        // 先完整校验再替换，任何一项不合法都整体拒绝
+        let loaded = (self.loader)()?;
+        loaded.validate()?;
+        let next = ReloadableConfig::from(&loaded);
+        let restart_required = self.restart_required(&loaded);
+
+        let previous = self.current.swap(Arc::new(next.clone()));
+        let changed = *previous != next;
+        if changed {
+            self.rate_limiter.reload(&next.rate_limit);
+        }
+
+        if !restart_required.is_empty() {
+            tracing::warn!(
+                settings = ?restart_required,
+                "配置已修改但需要重启才能生效"
+            );
+        }
+        tracing::info!(changed, "配置重载完成");
+
+        Ok(ReloadReport {
+            changed,
+            restart_required,
+        })
+    }
+
+    /// 与启动配置相比修改了的不可重载项；只返回名称，不记录值（可能含密钥）
+    fn restart_required(&self, loaded: &AppConfig) -> Vec<&'static str> {
+        let startup = &self.startup;
+        [
+            ("database.url", startup.database.url != loaded.database.url),
+            (
+                "database.max_connections",
+                startup.database.max_connections != loaded.database.max_connections,
+            ),
+            ("server.host", startup.server.host != loaded.server.host),
+            ("server.port", startup.server.port != loaded.server.port),
+            ("redis.url", startup.redis.url != loaded.redis.url),
+            (
+                "broadcast.redis_url",
+                startup.broadcast.redis_url != loaded.broadcast.redis_url,
+            ),
+            ("jwt.secret", startup.jwt.secret != loaded.jwt.secret),
+            (
+                "rate_limit.http",
+                startup.rate_limit.http != loaded.rate_limit.http,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+}
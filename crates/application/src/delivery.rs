@@ -1,6 +1,11 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use domain::{MessageId, RepositoryError, UserId};
 
+use crate::repository::MessageDeliveryRepository;
+
 /// 消息传递追踪器 - 确保消息可靠传输
 /// 每条消息分配唯一ID，追踪送达状态，确保可靠传输
 #[async_trait]
@@ -25,3 +30,28 @@ pub trait DeliveryTracker: Send + Sync {
     /// 清理已送达的旧记录（用于定期清理）
     async fn cleanup_delivered(&self, older_than_hours: u32) -> Result<u64, RepositoryError>;
 }
+
+/// 启动后台清理任务：定期删除送达时间早于保留期的投递记录
+///
+/// 未送达的记录不删，离线用户重连时还要靠它们补发
+pub fn spawn_delivery_cleanup(
+    delivery_repository: Arc<dyn MessageDeliveryRepository>,
+    interval: Duration,
+    retention: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let retention = chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let Some(before) = chrono::Utc::now().checked_sub_signed(retention) else {
+                continue;
+            };
+            match delivery_repository.cleanup_delivered_before(before).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "清理已送达的投递记录"),
+                Err(e) => tracing::warn!(error = %e, "清理投递记录失败"),
+            }
+        }
+    })
+}
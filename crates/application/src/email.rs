@@ -0,0 +1,37 @@
+use async_trait::async_trait;
+use domain::{UserEmail, Username};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EmailSenderError {
+    #[error("send error: {0}")]
+    Send(String),
+}
+
+impl EmailSenderError {
+    pub fn send_error(message: impl Into<String>) -> Self {
+        Self::Send(message.into())
+    }
+}
+
+/// 发送账号相关邮件
+///
+/// 生产环境接入 SMTP 或邮件服务商，开发环境可以只打印日志
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    /// 发送邮箱验证链接
+    async fn send_verification(
+        &self,
+        to: &UserEmail,
+        username: &Username,
+        verification_url: &str,
+    ) -> Result<(), EmailSenderError>;
+
+    /// 发送密码重置链接
+    async fn send_password_reset(
+        &self,
+        to: &UserEmail,
+        username: &Username,
+        reset_url: &str,
+    ) -> Result<(), EmailSenderError>;
+}
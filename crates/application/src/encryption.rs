@@ -0,0 +1,679 @@
+//! 消息静态加密（信封加密）
+//!
+//! 每个加密房间有自己的数据密钥（AES-256-GCM），数据密钥由配置中的主密钥加密后随房间保存。
+//! 消息内容在写库之前加密、读出之后解密，上层服务和客户端看到的始终是明文。
+//!
+//! 密文以 `enc:v1:` 加十六进制的 nonce、密文和认证标签存进原来的 content 列，
+//! 行上的 `encrypted` 标记说明内容是否为密文，不按前缀判断；房间开启加密之前写入的消息保持明文。
+//! 解不开的内容（密文损坏、密钥不对）以占位文本返回，不影响同一页的其他消息。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use config::EncryptionConfig;
+use domain::{
+    MentionedMessage, Message, MessageContent, MessageId, MessageRevision, PinnedMessage,
+    RepositoryError, RoomId, ThreadMessage, Timestamp, UserId,
+};
+use rand::Rng;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use tokio_stream::StreamExt;
+
+use crate::repository::{
+    ChatRoomRepository, ExportedMessageStream, MentionRepository, MessageRepository,
+    PaginatedResult, PaginationParams, TimeRangeParams,
+};
+
+/// 加密后消息内容的前缀，带版本号方便以后更换算法
+pub const CIPHERTEXT_PREFIX: &str = "enc:v1:";
+
+/// 无法解密的消息返回给客户端的内容
+pub const UNDECRYPTABLE_CONTENT: &str = "[message could not be decrypted]";
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("master key must be 32 bytes encoded as 64 hex characters")]
+    InvalidMasterKey,
+    #[error("failed to encrypt data")]
+    Seal,
+    #[error("failed to decrypt data: wrong key or corrupted ciphertext")]
+    Open,
+}
+
+/// 加解密算法本身，不涉及存储
+pub struct MessageCipher {
+    master_key: LessSafeKey,
+}
+
+impl MessageCipher {
+    pub fn new(master_key: &[u8]) -> Result<Self, EncryptionError> {
+        Ok(Self {
+            master_key: aes_key(master_key).ok_or(EncryptionError::InvalidMasterKey)?,
+        })
+    }
+
+    pub fn from_hex(master_key: &str) -> Result<Self, EncryptionError> {
+        Self::new(&from_hex(master_key).ok_or(EncryptionError::InvalidMasterKey)?)
+    }
+
+    /// 没有配置主密钥时返回 None，此时不能为房间开启加密
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>, EncryptionError> {
+        config.master_key.as_deref().map(Self::from_hex).transpose()
+    }
+
+    /// 为房间生成新的数据密钥，返回用主密钥加密后的结果
+    ///
+    /// 房间 ID 作为附加认证数据，加密后的密钥挪到别的房间无法解开
+    pub fn generate_data_key(&self, room_id: RoomId) -> Result<Vec<u8>, EncryptionError> {
+        let data_key: [u8; 32] = rand::rng().random();
+        seal(&self.master_key, room_id, &data_key)
+    }
+
+    fn unwrap_data_key(
+        &self,
+        room_id: RoomId,
+        wrapped_key: &[u8],
+    ) -> Result<LessSafeKey, EncryptionError> {
+        let data_key = open(&self.master_key, room_id, wrapped_key)?;
+        aes_key(&data_key).ok_or(EncryptionError::Open)
+    }
+
+    pub fn encrypt_content(
+        data_key: &LessSafeKey,
+        room_id: RoomId,
+        content: &MessageContent,
+    ) -> Result<MessageContent, EncryptionError> {
+        let sealed = seal(data_key, room_id, content.as_str().as_bytes())?;
+        MessageContent::new(format!("{CIPHERTEXT_PREFIX}{}", to_hex(&sealed)))
+            .map_err(|_| EncryptionError::Seal)
+    }
+
+    /// 只用于标记为密文的内容；没有密文前缀视为损坏
+    pub fn decrypt_content(
+        data_key: &LessSafeKey,
+        room_id: RoomId,
+        content: &MessageContent,
+    ) -> Result<MessageContent, EncryptionError> {
+        let encoded = content
+            .as_str()
+            .strip_prefix(CIPHERTEXT_PREFIX)
+            .ok_or(EncryptionError::Open)?;
+        let sealed = from_hex(encoded).ok_or(EncryptionError::Open)?;
+        let plaintext = open(data_key, room_id, &sealed)?;
+        let plaintext = String::from_utf8(plaintext).map_err(|_| EncryptionError::Open)?;
+        MessageContent::new(plaintext).map_err(|_| EncryptionError::Open)
+    }
+}
+
+/// 房间数据密钥的管理和消息加解密
+///
+/// 解开的数据密钥缓存在内存里（密钥一经设置不再变化）；未加密的房间不缓存，
+/// 其他实例为房间开启加密后这里立即生效，不会继续写入明文
+pub struct RoomEncryption {
+    cipher: MessageCipher,
+    room_repository: Arc<dyn ChatRoomRepository>,
+    keys: Mutex<HashMap<RoomId, Arc<LessSafeKey>>>,
+}
+
+impl RoomEncryption {
+    pub fn new(cipher: MessageCipher, room_repository: Arc<dyn ChatRoomRepository>) -> Self {
+        Self {
+            cipher,
+            room_repository,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 为房间开启加密，已经开启时返回 false
+    pub async fn enable(&self, room_id: RoomId) -> Result<bool, RepositoryError> {
+        let wrapped_key = self
+            .cipher
+            .generate_data_key(room_id)
+            .map_err(encryption_failed)?;
+        self.room_repository
+            .set_data_key(room_id, &wrapped_key)
+            .await
+    }
+
+    async fn room_key(&self, room_id: RoomId) -> Result<Option<Arc<LessSafeKey>>, RepositoryError> {
+        let cached = self.keys.lock().unwrap().get(&room_id).cloned();
+        if cached.is_some() {
+            return Ok(cached);
+        }
+
+        let Some(wrapped_key) = self.room_repository.find_data_key(room_id).await? else {
+            return Ok(None);
+        };
+        let key = Arc::new(
+            self.cipher
+                .unwrap_data_key(room_id, &wrapped_key)
+                .map_err(encryption_failed)?,
+        );
+        self.keys.lock().unwrap().insert(room_id, Arc::clone(&key));
+        Ok(Some(key))
+    }
+
+    /// 加密房间的消息写库之前调用，连同编辑前的内容一起加密并打上标记
+    pub async fn seal_message(&self, message: &mut Message) -> Result<(), RepositoryError> {
+        let Some(key) = self.room_key(message.room_id).await? else {
+            return Ok(());
+        };
+        if !message.encrypted {
+            message.content =
+                MessageCipher::encrypt_content(&key, message.room_id, &message.content)
+                    .map_err(encryption_failed)?;
+            message.encrypted = true;
+        }
+        if let Some(revision) = message.last_revision.as_mut().filter(|r| !r.encrypted) {
+            revision.content =
+                MessageCipher::encrypt_content(&key, message.room_id, &revision.content)
+                    .map_err(encryption_failed)?;
+            revision.encrypted = true;
+        }
+        Ok(())
+    }
+
+    pub async fn open_message(&self, message: &mut Message) -> Result<(), RepositoryError> {
+        self.open_messages(std::slice::from_mut(message)).await
+    }
+
+    /// 解密一批消息，每个房间只查一次密钥；没有加密标记的消息不查密钥
+    pub async fn open_messages<'a>(
+        &self,
+        messages: impl IntoIterator<Item = &'a mut Message>,
+    ) -> Result<(), RepositoryError> {
+        let mut keys: HashMap<RoomId, Option<Arc<LessSafeKey>>> = HashMap::new();
+        for message in messages {
+            let revision_encrypted = message
+                .last_revision
+                .as_ref()
+                .is_some_and(|revision| revision.encrypted);
+            if !message.encrypted && !revision_encrypted {
+                continue;
+            }
+            let key = match keys.get(&message.room_id) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = self.room_key(message.room_id).await?;
+                    keys.insert(message.room_id, key.clone());
+                    key
+                }
+            };
+            if message.encrypted {
+                message.content = open_or_placeholder(
+                    key.as_deref(),
+                    message.room_id,
+                    message.id,
+                    &message.content,
+                );
+                message.encrypted = false;
+            }
+            if let Some(revision) = message.last_revision.as_mut().filter(|r| r.encrypted) {
+                revision.content = open_or_placeholder(
+                    key.as_deref(),
+                    message.room_id,
+                    message.id,
+                    &revision.content,
+                );
+                revision.encrypted = false;
+            }
+        }
+        Ok(())
+    }
+
+    async fn open_revisions(
+        &self,
+        message_id: MessageId,
+        room_id: RoomId,
+        revisions: &mut [MessageRevision],
+    ) -> Result<(), RepositoryError> {
+        if !revisions.iter().any(|revision| revision.encrypted) {
+            return Ok(());
+        }
+        let key = self.room_key(room_id).await?;
+        for revision in revisions.iter_mut().filter(|revision| revision.encrypted) {
+            revision.content =
+                open_or_placeholder(key.as_deref(), room_id, message_id, &revision.content);
+            revision.encrypted = false;
+        }
+        Ok(())
+    }
+}
+
+/// 在消息存储外面包一层加解密，服务层无需感知房间是否加密
+///
+/// 加密房间的全文检索没有意义（索引的是密文），直接返回空结果
+pub struct EncryptedMessageRepository {
+    inner: Arc<dyn MessageRepository>,
+    encryption: Arc<RoomEncryption>,
+}
+
+impl EncryptedMessageRepository {
+    pub fn new(inner: Arc<dyn MessageRepository>, encryption: Arc<RoomEncryption>) -> Self {
+        Self { inner, encryption }
+    }
+
+    async fn opened(&self, mut messages: Vec<Message>) -> Result<Vec<Message>, RepositoryError> {
+        self.encryption.open_messages(messages.iter_mut()).await?;
+        Ok(messages)
+    }
+}
+
+#[async_trait]
+impl MessageRepository for EncryptedMessageRepository {
+    async fn create(&self, mut message: Message) -> Result<MessageId, RepositoryError> {
+        self.encryption.seal_message(&mut message).await?;
+        self.inner.create(message).await
+    }
+
+    async fn create_batch(
+        &self,
+        mut messages: Vec<Message>,
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        for message in &mut messages {
+            self.encryption.seal_message(message).await?;
+        }
+        self.inner.create_batch(messages).await
+    }
+
+    async fn create_with_outbox(&self, mut message: Message) -> Result<MessageId, RepositoryError> {
+        self.encryption.seal_message(&mut message).await?;
+        self.inner.create_with_outbox(message).await
+    }
+
+    async fn find_by_id(&self, id: MessageId) -> Result<Option<Message>, RepositoryError> {
+        let mut message = self.inner.find_by_id(id).await?;
+        if let Some(message) = message.as_mut() {
+            self.encryption.open_message(message).await?;
+        }
+        Ok(message)
+    }
+
+    async fn find_by_ids(&self, ids: &[MessageId]) -> Result<Vec<Message>, RepositoryError> {
+        let messages = self.inner.find_by_ids(ids).await?;
+        self.opened(messages).await
+    }
+
+    async fn find_recent_by_room(
+        &self,
+        room_id: RoomId,
+        pagination: PaginationParams,
+        before: Option<MessageId>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let messages = self
+            .inner
+            .find_recent_by_room(room_id, pagination, before)
+            .await?;
+        self.opened(messages).await
+    }
+
+    async fn find_since_timestamp(
+        &self,
+        room_id: RoomId,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let messages = self.inner.find_since_timestamp(room_id, timestamp).await?;
+        self.opened(messages).await
+    }
+
+    async fn find_after_sequence(
+        &self,
+        room_id: RoomId,
+        after_seq: i64,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let messages = self
+            .inner
+            .find_after_sequence(room_id, after_seq, pagination)
+            .await?;
+        self.opened(messages).await
+    }
+
+    async fn count_after(
+        &self,
+        room_id: RoomId,
+        message_id: Option<MessageId>,
+    ) -> Result<i64, RepositoryError> {
+        self.inner.count_after(room_id, message_id).await
+    }
+
+    async fn count_unread_for_member(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<i64, RepositoryError> {
+        self.inner.count_unread_for_member(room_id, user_id).await
+    }
+
+    async fn search_in_room(
+        &self,
+        room_id: RoomId,
+        query: &str,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        if self.encryption.room_key(room_id).await?.is_some() {
+            return Ok(Vec::new());
+        }
+        self.inner.search_in_room(room_id, query, pagination).await
+    }
+
+    async fn find_thread(
+        &self,
+        root_id: MessageId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<ThreadMessage>, RepositoryError> {
+        let mut replies = self.inner.find_thread(root_id, pagination).await?;
+        let messages: Vec<&mut Message> =
+            replies.iter_mut().map(|reply| &mut reply.message).collect();
+        self.encryption.open_messages(messages).await?;
+        Ok(replies)
+    }
+
+    async fn thread_reply_count(&self, root_id: MessageId) -> Result<i64, RepositoryError> {
+        self.inner.thread_reply_count(root_id).await
+    }
+
+    async fn find_by_time_range(
+        &self,
+        room_id: RoomId,
+        time_range: TimeRangeParams,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let messages = self
+            .inner
+            .find_by_time_range(room_id, time_range, pagination)
+            .await?;
+        self.opened(messages).await
+    }
+
+    async fn stream_by_time_range(
+        &self,
+        room_id: RoomId,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    ) -> Result<ExportedMessageStream, RepositoryError> {
+        let rows = self.inner.stream_by_time_range(room_id, from, to).await?;
+        // 整个流只属于一个房间，开始前取一次密钥，之后逐行解密带标记的内容
+        let key = self.encryption.room_key(room_id).await?;
+        Ok(Box::pin(rows.map(move |row| {
+            let mut row = row?;
+            if row.encrypted {
+                row.content = open_or_placeholder(key.as_deref(), room_id, row.id, &row.content);
+                row.encrypted = false;
+            }
+            Ok(row)
+        })))
+    }
+
+    async fn update(
+        &self,
+        mut message: Message,
+        expected_version: i32,
+    ) -> Result<(), RepositoryError> {
+        self.encryption.seal_message(&mut message).await?;
+        self.inner.update(message, expected_version).await
+    }
+
+    async fn revisions(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageRevision>, RepositoryError> {
+        let mut revisions = self.inner.revisions(message_id).await?;
+        if revisions.is_empty() {
+            return Ok(revisions);
+        }
+        if let Some(message) = self.inner.find_by_id(message_id).await? {
+            self.encryption
+                .open_revisions(message_id, message.room_id, &mut revisions)
+                .await?;
+        }
+        Ok(revisions)
+    }
+
+    async fn soft_delete(&self, id: MessageId) -> Result<(), RepositoryError> {
+        self.inner.soft_delete(id).await
+    }
+
+    async fn soft_delete_many(
+        &self,
+        room_id: RoomId,
+        ids: &[MessageId],
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        self.inner.soft_delete_many(room_id, ids).await
+    }
+
+    async fn soft_delete_batch_by_sender(
+        &self,
+        sender_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError> {
+        self.inner
+            .soft_delete_batch_by_sender(sender_id, limit)
+            .await
+    }
+
+    async fn restore(&self, id: MessageId) -> Result<bool, RepositoryError> {
+        self.inner.restore(id).await
+    }
+
+    async fn purge_older_than(&self, cutoff: Timestamp) -> Result<u64, RepositoryError> {
+        self.inner.purge_older_than(cutoff).await
+    }
+
+    async fn pin(
+        &self,
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: Timestamp,
+    ) -> Result<bool, RepositoryError> {
+        self.inner
+            .pin(room_id, message_id, pinned_by, pinned_at)
+            .await
+    }
+
+    async fn unpin(&self, room_id: RoomId, message_id: MessageId) -> Result<bool, RepositoryError> {
+        self.inner.unpin(room_id, message_id).await
+    }
+
+    async fn list_pinned(&self, room_id: RoomId) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        let mut pinned = self.inner.list_pinned(room_id).await?;
+        let messages: Vec<&mut Message> = pinned.iter_mut().map(|pin| &mut pin.message).collect();
+        self.encryption.open_messages(messages).await?;
+        Ok(pinned)
+    }
+}
+
+/// 提及列表里带着消息内容，同样需要解密
+pub struct EncryptedMentionRepository {
+    inner: Arc<dyn MentionRepository>,
+    encryption: Arc<RoomEncryption>,
+}
+
+impl EncryptedMentionRepository {
+    pub fn new(inner: Arc<dyn MentionRepository>, encryption: Arc<RoomEncryption>) -> Self {
+        Self { inner, encryption }
+    }
+}
+
+#[async_trait]
+impl MentionRepository for EncryptedMentionRepository {
+    async fn record_mentions(
+        &self,
+        message: &Message,
+        usernames: &[String],
+    ) -> Result<Vec<UserId>, RepositoryError> {
+        self.inner.record_mentions(message, usernames).await
+    }
+
+    async fn record_everyone_mention(
+        &self,
+        message: &Message,
+    ) -> Result<Vec<UserId>, RepositoryError> {
+        self.inner.record_everyone_mention(message).await
+    }
+
+    async fn find_for_user(
+        &self,
+        user_id: UserId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<MentionedMessage>, RepositoryError> {
+        let mut result = self.inner.find_for_user(user_id, page, page_size).await?;
+        let messages: Vec<&mut Message> = result
+            .items
+            .iter_mut()
+            .map(|item| &mut item.message)
+            .collect();
+        self.encryption.open_messages(messages).await?;
+        Ok(result)
+    }
+}
+
+fn encryption_failed(error: EncryptionError) -> RepositoryError {
+    RepositoryError::storage_with_source(error.to_string(), error)
+}
+
+/// 解开标记为密文的内容；房间没有密钥或密文解不开时记日志并换成占位文本
+fn open_or_placeholder(
+    key: Option<&LessSafeKey>,
+    room_id: RoomId,
+    message_id: MessageId,
+    content: &MessageContent,
+) -> MessageContent {
+    key.ok_or(EncryptionError::Open)
+        .and_then(|key| MessageCipher::decrypt_content(key, room_id, content))
+        .unwrap_or_else(|error| {
+            tracing::warn!(%room_id, %message_id, %error, "消息无法解密，以占位内容返回");
+            MessageContent::new(UNDECRYPTABLE_CONTENT).expect("placeholder is valid content")
+        })
+}
+
+fn aes_key(bytes: &[u8]) -> Option<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .ok()
+        .map(LessSafeKey::new)
+}
+
+/// 输出格式：nonce || 密文 || 认证标签
+fn seal(key: &LessSafeKey, room_id: RoomId, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let nonce: [u8; NONCE_LEN] = rand::rng().random();
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(room_id.0.as_bytes()),
+        &mut in_out,
+    )
+    .map_err(|_| EncryptionError::Seal)?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&in_out);
+    Ok(sealed)
+}
+
+fn open(key: &LessSafeKey, room_id: RoomId, sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(EncryptionError::Open);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| EncryptionError::Open)?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(room_id.0.as_bytes()), &mut in_out)
+        .map_err(|_| EncryptionError::Open)?;
+    Ok(plaintext.to_vec())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    const MASTER_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn data_key(cipher: &MessageCipher, room_id: RoomId) -> LessSafeKey {
+        let wrapped = cipher.generate_data_key(room_id).unwrap();
+        cipher.unwrap_data_key(room_id, &wrapped).unwrap()
+    }
+
+    #[test]
+    fn content_round_trips_and_is_not_stored_in_plaintext() {
+        let cipher = MessageCipher::from_hex(MASTER_KEY).unwrap();
+        let room_id = RoomId::from(Uuid::new_v4());
+        let key = data_key(&cipher, room_id);
+        let content = MessageContent::new("meet at 10, password is hunter2").unwrap();
+
+        let sealed = MessageCipher::encrypt_content(&key, room_id, &content).unwrap();
+        assert!(sealed.as_str().starts_with(CIPHERTEXT_PREFIX));
+        assert!(!sealed.as_str().contains("hunter2"));
+
+        let opened = MessageCipher::decrypt_content(&key, room_id, &sealed).unwrap();
+        assert_eq!(opened, content);
+    }
+
+    #[test]
+    fn same_content_encrypts_differently_each_time() {
+        let cipher = MessageCipher::from_hex(MASTER_KEY).unwrap();
+        let room_id = RoomId::from(Uuid::new_v4());
+        let key = data_key(&cipher, room_id);
+        let content = MessageContent::new("hello").unwrap();
+
+        let first = MessageCipher::encrypt_content(&key, room_id, &content).unwrap();
+        let second = MessageCipher::encrypt_content(&key, room_id, &content).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn ciphertext_cannot_be_opened_in_another_room_or_with_another_key() {
+        let cipher = MessageCipher::from_hex(MASTER_KEY).unwrap();
+        let room_id = RoomId::from(Uuid::new_v4());
+        let other_room = RoomId::from(Uuid::new_v4());
+        let key = data_key(&cipher, room_id);
+        let sealed =
+            MessageCipher::encrypt_content(&key, room_id, &MessageContent::new("x").unwrap())
+                .unwrap();
+
+        assert!(MessageCipher::decrypt_content(&key, other_room, &sealed).is_err());
+        let other_key = data_key(&cipher, room_id);
+        assert!(MessageCipher::decrypt_content(&other_key, room_id, &sealed).is_err());
+
+        // 数据密钥只能用原房间解开
+        let wrapped = cipher.generate_data_key(room_id).unwrap();
+        assert!(cipher.unwrap_data_key(other_room, &wrapped).is_err());
+    }
+
+    #[test]
+    fn content_without_ciphertext_prefix_is_rejected() {
+        let cipher = MessageCipher::from_hex(MASTER_KEY).unwrap();
+        let room_id = RoomId::from(Uuid::new_v4());
+        let key = data_key(&cipher, room_id);
+        let plaintext = MessageContent::new("not encrypted").unwrap();
+
+        assert!(MessageCipher::decrypt_content(&key, room_id, &plaintext).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_master_key() {
+        assert!(MessageCipher::from_hex("abcd").is_err());
+        assert!(MessageCipher::from_hex(&"zz".repeat(32)).is_err());
+        assert!(MessageCipher::from_config(&EncryptionConfig::default())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -4,6 +4,7 @@ use domain::{DomainError, RepositoryError};
 use thiserror::Error;
 
 use crate::password::PasswordHasherError;
+use crate::rate_limiter::RateLimitError;
 
 #[derive(Debug, Error)]
 pub enum ApplicationError {
@@ -15,6 +16,8 @@ pub enum ApplicationError {
     Password(#[from] PasswordHasherError),
     #[error("broadcast error: {0}")]
     Broadcast(#[from] crate::broadcaster::BroadcastError),
+    #[error("rate limited: {0}")]
+    RateLimited(#[from] RateLimitError),
     #[error("infrastructure error: {message}")]
     Infrastructure {
         message: String,
@@ -25,6 +28,8 @@ pub enum ApplicationError {
     Authentication,
     #[error("authorization failed")]
     Authorization,
+    #[error("service is shutting down")]
+    ShuttingDown,
 }
 
 impl ApplicationError {
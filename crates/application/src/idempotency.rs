@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use domain::DomainError;
+
+use crate::repository::IdempotencyKeyRepository;
+
+/// 幂等键的最大长度
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// 校验客户端提供的幂等键：非空、不超过上限、只含可见 ASCII 字符
+pub fn validate_idempotency_key(key: &str) -> Result<(), DomainError> {
+    if key.is_empty() {
+        return Err(DomainError::invalid_argument(
+            "idempotency_key",
+            "must not be empty",
+        ));
+    }
+    if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(DomainError::invalid_argument(
+            "idempotency_key",
+            "must be at most 255 characters",
+        ));
+    }
+    if !key.bytes().all(|byte| byte.is_ascii_graphic()) {
+        return Err(DomainError::invalid_argument(
+            "idempotency_key",
+            "must contain only visible ASCII characters",
+        ));
+    }
+    Ok(())
+}
+
+/// 启动后台清理任务：定期删除过期的幂等键
+pub fn spawn_idempotency_key_cleanup(
+    repository: Arc<dyn IdempotencyKeyRepository>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match repository
+                .delete_expired(time::OffsetDateTime::now_utc())
+                .await
+            {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "清理过期的幂等键"),
+                Err(e) => tracing::warn!(error = %e, "清理幂等键失败"),
+            }
+        }
+    })
+}
@@ -3,26 +3,56 @@
 //! 这里提供围绕领域模型的用例服务，处理输入校验、事务边界、
 //! 以及对外部适配器（例如密码哈希、消息广播）的抽象。
 
+pub mod audit;
 pub mod broadcaster;
 pub mod clock;
+pub mod config_reload;
 pub mod delivery;
+pub mod email;
+pub mod encryption;
 pub mod error;
+pub mod export;
+pub mod idempotency;
+pub mod metrics;
 pub mod password;
+pub mod pipeline_control;
 pub mod presence;
 pub mod rate_limiter;
 pub mod repository;
 pub mod sequencer;
 pub mod services;
+pub mod shutdown;
+pub mod startup;
+pub mod storage;
 
-pub use broadcaster::{MessageBroadcast, MessageBroadcaster, MessageStream, WebSocketMessage};
+pub use audit::AuditLogger;
+pub use broadcaster::{
+    LocalMessageBroadcaster, MessageBroadcast, MessageBroadcaster, MessageStream, PresenceChange,
+    StreamEvent, WebSocketMessage,
+};
 pub use clock::{Clock, SystemClock};
-pub use delivery::DeliveryTracker;
+pub use config_reload::{ConfigReloader, ReloadReport, ReloadableConfig};
+pub use delivery::{spawn_delivery_cleanup, DeliveryTracker};
+pub use email::{EmailSender, EmailSenderError};
+pub use encryption::{
+    EncryptedMentionRepository, EncryptedMessageRepository, EncryptionError, MessageCipher,
+    RoomEncryption,
+};
 pub use error::ApplicationError;
+pub use export::{encode_export, ExportFormat};
+pub use idempotency::spawn_idempotency_key_cleanup;
 pub use password::{PasswordHasher, PasswordHasherError};
+pub use pipeline_control::{PipelineControlMetrics, StatsPipelineControl};
 pub use presence::{
-    OnlineStats, PresenceEventType, PresenceManager, RedisPresenceManager, UserPresenceEvent,
+    spawn_heartbeat_sweeper, OnlineStats, PresenceEventType, PresenceManager, PresenceSnapshot,
+    RedisPresenceManager, RoomPresenceNotifier, UserPresenceEvent,
+};
+pub use rate_limiter::{
+    MessageRateLimiter, RateLimitError, RoomRateLimit, SendRateLimiter, TokenBucketLimit,
 };
-pub use rate_limiter::{MessageRateLimiter, RateLimitError};
 pub use repository::{ChatRoomRepository, MessageRepository, RoomMemberRepository, UserRepository};
-pub use sequencer::{MessageSequencer, SequencedMessage};
+pub use sequencer::{MessageSequencer, SequenceCheck, SequenceTracker, SequencedMessage};
 pub use services::{ChatService, ChatServiceDependencies, UserService, UserServiceDependencies};
+pub use shutdown::{InFlightGuard, ShutdownBarrier};
+pub use startup::{wait_for_dependency, DependencyUnavailable, Readiness};
+pub use storage::{ByteStream, ObjectStorage, StorageError};
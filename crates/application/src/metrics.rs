@@ -0,0 +1,21 @@
+//! 应用层记录的指标名称
+//!
+//! 只通过 `metrics` 门面记录，导出方式由上层决定；没有安装 recorder 时记录是空操作
+
+/// 成功发送（已落库并广播，或已写入发件箱）的消息数
+pub const MESSAGES_SENT_TOTAL: &str = "chat_messages_sent_total";
+
+/// 单条消息广播扇出耗时（秒）
+pub const BROADCAST_FANOUT_SECONDS: &str = "chat_broadcast_fanout_seconds";
+
+/// 进程内广播的订阅者落后被覆盖消息的次数，按房间（`room_id` 标签）统计
+pub const BROADCAST_LAGGED_TOTAL: &str = "chat_broadcast_lagged_total";
+
+/// 进程内广播按落后情况扩容后的房间缓冲条数（`room_id` 标签），只记录扩容过的房间
+pub const BROADCAST_ROOM_CAPACITY: &str = "chat_broadcast_room_capacity";
+
+/// 写入失败被丢弃的审计记录数
+pub const AUDIT_LOG_WRITES_DROPPED_TOTAL: &str = "chat_audit_log_writes_dropped_total";
+
+/// 统计消费者转入死信流的用户状态事件数，`cause` 标签区分无法解析（`parse`）和无法写库（`storage`）
+pub const STATS_EVENTS_DEAD_LETTERED_TOTAL: &str = "stats_presence_events_dead_lettered_total";
@@ -0,0 +1,136 @@
+//! 统计管道运行时暂停/恢复控制
+//!
+//! 事故期间运维需要临时停止 stats-consumer / stats-aggregator 写库，
+//! 但不想杀进程。暂停状态存放在 Redis 标志位里，所有实例共享：
+//!
+//! ```text
+//! SET stats:pipeline:paused "<原因>"   # 暂停
+//! DEL stats:pipeline:paused            # 恢复
+//! ```
+//!
+//! 暂停期间消费者不读取/不确认 Stream 消息，聚合器不推进水位线，
+//! 恢复后从原位置继续，数据不丢失。
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::error::ApplicationError;
+
+/// Redis 中的暂停标志键
+pub const STATS_PIPELINE_PAUSED_KEY: &str = "stats:pipeline:paused";
+
+/// 暂停状态指标快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineControlMetrics {
+    /// 最近一次观测到的暂停状态（gauge：1 = 暂停，0 = 运行）
+    pub paused: bool,
+    /// 因暂停而跳过的工作周期数（counter）
+    pub skipped_cycles: u64,
+}
+
+/// 统计管道控制器
+///
+/// Redis 不可用时沿用最近一次观测到的状态，避免 Redis 抖动导致管道来回启停
+pub struct StatsPipelineControl {
+    redis_client: Arc<redis::Client>,
+    key: String,
+    paused: AtomicBool,
+    skipped_cycles: AtomicU64,
+}
+
+impl StatsPipelineControl {
+    pub fn new(redis_client: Arc<redis::Client>) -> Self {
+        Self::with_key(redis_client, STATS_PIPELINE_PAUSED_KEY.to_string())
+    }
+
+    /// 使用自定义标志键（测试隔离用）
+    pub fn with_key(redis_client: Arc<redis::Client>, key: String) -> Self {
+        Self {
+            redis_client,
+            key,
+            paused: AtomicBool::new(false),
+            skipped_cycles: AtomicU64::new(0),
+        }
+    }
+
+    async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, ApplicationError> {
+        self.redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| {
+                let message = format!("Redis connection failed: {e}");
+                ApplicationError::infrastructure_with_source(message, e)
+            })
+    }
+
+    /// 暂停管道
+    pub async fn pause(&self, reason: &str) -> Result<(), ApplicationError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("SET")
+            .arg(&self.key)
+            .arg(reason)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                ApplicationError::infrastructure_with_source(format!("Redis SET failed: {e}"), e)
+            })?;
+        self.paused.store(true, Ordering::Relaxed);
+        tracing::warn!(reason, "统计管道已暂停");
+        Ok(())
+    }
+
+    /// 恢复管道
+    pub async fn resume(&self) -> Result<(), ApplicationError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("DEL")
+            .arg(&self.key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                ApplicationError::infrastructure_with_source(format!("Redis DEL failed: {e}"), e)
+            })?;
+        self.paused.store(false, Ordering::Relaxed);
+        tracing::info!("统计管道已恢复");
+        Ok(())
+    }
+
+    /// 查询当前是否暂停，并刷新本地 gauge
+    pub async fn is_paused(&self) -> bool {
+        let observed = match self.get_connection().await {
+            Ok(mut conn) => redis::cmd("EXISTS")
+                .arg(&self.key)
+                .query_async::<bool>(&mut conn)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match observed {
+            Ok(paused) => {
+                let previous = self.paused.swap(paused, Ordering::Relaxed);
+                if previous != paused {
+                    tracing::info!(paused, "统计管道暂停状态变化");
+                }
+                paused
+            }
+            Err(error) => {
+                let last_known = self.paused.load(Ordering::Relaxed);
+                tracing::warn!(%error, last_known, "读取暂停标志失败，沿用上次状态");
+                last_known
+            }
+        }
+    }
+
+    /// 记录一次因暂停而跳过的工作周期
+    pub fn record_skipped_cycle(&self) {
+        self.skipped_cycles.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 当前指标快照
+    pub fn metrics(&self) -> PipelineControlMetrics {
+        PipelineControlMetrics {
+            paused: self.paused.load(Ordering::Relaxed),
+            skipped_cycles: self.skipped_cycles.load(Ordering::Relaxed),
+        }
+    }
+}
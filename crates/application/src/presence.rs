@@ -1,8 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use crate::broadcaster::{MessageBroadcast, MessageBroadcaster, PresenceChange};
 use crate::error::ApplicationError;
 use domain::{RoomId, UserId};
 
@@ -14,6 +17,37 @@ pub struct OnlineStats {
     pub timestamp: DateTime<Utc>,
 }
 
+/// 全站实时在线概况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceSnapshot {
+    /// 至少在一个房间在线的用户数
+    pub total_online_users: u64,
+    /// 仍在心跳的连接数，同一用户的多个连接分别计数
+    pub connection_count: u64,
+    /// 在线人数最多的房间，按人数从多到少排列
+    pub top_rooms: Vec<OnlineStats>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 房间按在线人数从多到少排列，人数相同时按房间 ID，只保留前 `top_rooms` 个
+fn rank_rooms(mut rooms: Vec<(RoomId, u64)>, top_rooms: usize) -> Vec<OnlineStats> {
+    rooms.retain(|(_, count)| *count > 0);
+    rooms.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| Uuid::from(a.0).cmp(&Uuid::from(b.0)))
+    });
+    let timestamp = Utc::now();
+    rooms
+        .into_iter()
+        .take(top_rooms)
+        .map(|(room_id, online_count)| OnlineStats {
+            room_id,
+            online_count,
+            timestamp,
+        })
+        .collect()
+}
+
 /// 用户状态变化事件类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
@@ -92,6 +126,36 @@ pub trait PresenceManager: Send + Sync {
     /// 获取房间实时统计信息
     async fn get_online_stats(&self, room_id: RoomId) -> Result<OnlineStats, ApplicationError>;
 
+    /// 全站在线概况：在线用户数、连接数和在线人数最多的 `top_rooms` 个房间
+    async fn snapshot(&self, top_rooms: usize) -> Result<PresenceSnapshot, ApplicationError>;
+
+    // === 心跳与掉线检测 ===
+
+    /// 刷新会话心跳；首次调用即登记会话
+    ///
+    /// WebSocket 非正常断开时不会走 `user_disconnected`，靠心跳超时兜底
+    async fn heartbeat(
+        &self,
+        _room_id: RoomId,
+        _user_id: UserId,
+        _session_id: Uuid,
+    ) -> Result<(), ApplicationError> {
+        // 默认实现：不跟踪心跳
+        Ok(())
+    }
+
+    /// 会话正常结束，取消心跳跟踪
+    async fn end_session(&self, _session_id: Uuid) -> Result<(), ApplicationError> {
+        Ok(())
+    }
+
+    /// 清理心跳超时的会话，返回产生的 Disconnected 事件
+    ///
+    /// 同一用户在该房间还有存活会话时只结束超时会话，不把用户标记为离线
+    async fn sweep_expired_sessions(&self) -> Result<Vec<UserPresenceEvent>, ApplicationError> {
+        Ok(Vec::new())
+    }
+
     /// 记录用户状态变化事件（用于历史数据采集）
     async fn record_presence_event(
         &self,
@@ -110,20 +174,206 @@ pub trait PresenceManager: Send + Sync {
     }
 }
 
+/// 默认心跳超时
+const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// 心跳超时产生的断开事件
+fn expired_session_event(room_id: RoomId, user_id: UserId, session_id: Uuid) -> UserPresenceEvent {
+    UserPresenceEvent {
+        event_id: Uuid::new_v4(),
+        user_id,
+        room_id,
+        event_type: PresenceEventType::Disconnected,
+        timestamp: Utc::now(),
+        session_id,
+        user_ip: None,
+        user_agent: None,
+    }
+}
+
+/// 解析会话登记表中的一项：session_id -> "room_id:user_id"
+fn parse_session_entry(session_id: &str, pair: &str) -> Option<(Uuid, RoomId, UserId)> {
+    let (room, user) = pair.split_once(':')?;
+    Some((
+        session_id.parse().ok()?,
+        RoomId::from(room.parse::<Uuid>().ok()?),
+        UserId::from(user.parse::<Uuid>().ok()?),
+    ))
+}
+
+/// 启动后台清扫任务：定期把心跳超时的会话标记为离线
+///
+/// 受影响的房间会广播成员下线和一次在线统计更新，客户端看到的在线人数随之下降
+pub fn spawn_heartbeat_sweeper(
+    presence_manager: Arc<dyn PresenceManager>,
+    broadcaster: Arc<dyn MessageBroadcaster>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match presence_manager.sweep_expired_sessions().await {
+                Ok(events) if !events.is_empty() => {
+                    tracing::info!(count = events.len(), "清理心跳超时的会话");
+                    for event in &events {
+                        let broadcast = MessageBroadcast::presence(
+                            event.room_id,
+                            event.user_id,
+                            PresenceChange::Disconnected,
+                        );
+                        if let Err(e) = broadcaster.broadcast(broadcast).await {
+                            tracing::warn!(error = %e, room_id = %event.room_id, "广播成员下线失败");
+                        }
+                    }
+                    let rooms: HashSet<RoomId> = events.iter().map(|event| event.room_id).collect();
+                    for room_id in rooms {
+                        broadcast_online_stats(
+                            presence_manager.as_ref(),
+                            broadcaster.as_ref(),
+                            room_id,
+                        )
+                        .await;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "心跳超时清扫失败"),
+            }
+        }
+    })
+}
+
+async fn broadcast_online_stats(
+    presence_manager: &dyn PresenceManager,
+    broadcaster: &dyn MessageBroadcaster,
+    room_id: RoomId,
+) {
+    let stats = match presence_manager.get_online_stats(room_id).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            tracing::warn!(error = %e, room_id = %room_id, "获取在线统计失败");
+            return;
+        }
+    };
+    if let Err(e) = broadcaster
+        .broadcast(MessageBroadcast::stats(room_id, stats))
+        .await
+    {
+        tracing::warn!(error = %e, room_id = %room_id, "广播在线统计失败");
+    }
+}
+
+/// 房间在线成员推送：有人进出房间时广播 `room_presence`，每个房间按最小间隔去抖
+///
+/// 间隔内的多次变化合并成一次推送，推送时再读取最新状态，保证最后一次变化不会丢
+pub struct RoomPresenceNotifier {
+    presence_manager: Arc<dyn PresenceManager>,
+    broadcaster: Arc<dyn MessageBroadcaster>,
+    min_interval: Duration,
+    rooms: std::sync::Mutex<HashMap<RoomId, PresenceSlot>>,
+}
+
+#[derive(Default)]
+struct PresenceSlot {
+    last_sent: Option<Instant>,
+    scheduled: bool,
+}
+
+impl RoomPresenceNotifier {
+    /// 默认每个房间每秒最多推送一次
+    pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(
+        presence_manager: Arc<dyn PresenceManager>,
+        broadcaster: Arc<dyn MessageBroadcaster>,
+    ) -> Self {
+        Self::with_min_interval(presence_manager, broadcaster, Self::DEFAULT_MIN_INTERVAL)
+    }
+
+    pub fn with_min_interval(
+        presence_manager: Arc<dyn PresenceManager>,
+        broadcaster: Arc<dyn MessageBroadcaster>,
+        min_interval: Duration,
+    ) -> Self {
+        Self {
+            presence_manager,
+            broadcaster,
+            min_interval,
+            rooms: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 房间在线成员有变化；已经排上的推送会带上这次变化，不重复排
+    pub fn notify(self: &Arc<Self>, room_id: RoomId) {
+        let delay = {
+            let mut rooms = self.rooms.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = rooms.entry(room_id).or_default();
+            if slot.scheduled {
+                return;
+            }
+            slot.scheduled = true;
+            slot.last_sent
+                .map(|sent| self.min_interval.saturating_sub(sent.elapsed()))
+                .unwrap_or_default()
+        };
+
+        let notifier = Arc::clone(self);
+        tokio::spawn(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            // 先解除排队再读状态：读取期间的新变化会另排一次，不会被吞掉
+            {
+                let mut rooms = notifier.rooms.lock().unwrap_or_else(|e| e.into_inner());
+                let slot = rooms.entry(room_id).or_default();
+                slot.scheduled = false;
+                slot.last_sent = Some(Instant::now());
+            }
+            notifier.push(room_id).await;
+        });
+    }
+
+    async fn push(&self, room_id: RoomId) {
+        let user_ids = match self.presence_manager.get_online_users(room_id).await {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                tracing::warn!(error = %e, room_id = %room_id, "获取房间在线成员失败");
+                return;
+            }
+        };
+        if user_ids.is_empty() {
+            // 房间空了就不再记录节流状态，避免空房间的条目一直留在内存里
+            let mut rooms = self.rooms.lock().unwrap_or_else(|e| e.into_inner());
+            if rooms.get(&room_id).is_some_and(|slot| !slot.scheduled) {
+                rooms.remove(&room_id);
+            }
+        }
+        if let Err(e) = self
+            .broadcaster
+            .broadcast(MessageBroadcast::room_presence(room_id, user_ids))
+            .await
+        {
+            tracing::warn!(error = %e, room_id = %room_id, "广播房间在线成员失败");
+        }
+    }
+}
+
 /// Redis实现的在线状态管理器
 /// 直接查询Redis，确保数据强一致性
 /// 事件通过Redis Stream进行异步处理
+///
+/// 心跳：每个会话一个带 TTL 的键，键过期即心跳超时；
+/// 会话登记在一个 Hash 里，清扫时据此找出已过期的会话。
+/// 在线集合本身也随心跳续期，整个节点崩溃时不靠清扫也能自愈
 pub struct RedisPresenceManager {
     redis_client: Arc<redis::Client>,
     stream_name: String, // Redis Stream 名称
+    heartbeat_timeout: Duration,
 }
 
 impl RedisPresenceManager {
     pub fn new(redis_client: Arc<redis::Client>) -> Self {
-        Self {
-            redis_client,
-            stream_name: "presence_events".to_string(),
-        }
+        Self::with_stream_name(redis_client, "presence_events".to_string())
     }
 
     /// 创建带自定义流名称的 RedisPresenceManager
@@ -131,15 +381,43 @@ impl RedisPresenceManager {
         Self {
             redis_client,
             stream_name,
+            heartbeat_timeout: DEFAULT_HEARTBEAT_TIMEOUT,
         }
     }
 
+    /// 设置心跳超时
+    pub fn with_heartbeat_timeout(mut self, heartbeat_timeout: Duration) -> Self {
+        self.heartbeat_timeout = heartbeat_timeout;
+        self
+    }
+
     /// 从应用配置创建 RedisPresenceManager
     pub fn from_app_config(
         redis_client: Arc<redis::Client>,
         app_config: &config::AppConfig,
     ) -> Self {
         Self::with_stream_name(redis_client, app_config.presence.stream_name.clone())
+            .with_heartbeat_timeout(Duration::from_secs(
+                app_config.presence.heartbeat_timeout_secs,
+            ))
+    }
+
+    /// 在线集合的 TTL（秒），每次心跳续期
+    ///
+    /// 取两倍心跳超时：清扫任务先有机会产生 Disconnected 事件，
+    /// 没有任何实例存活时集合再自然过期
+    fn presence_ttl_secs(&self) -> i64 {
+        (self.heartbeat_timeout.as_secs() * 2).max(1) as i64
+    }
+
+    /// 生成会话心跳键（带 TTL）
+    fn heartbeat_key(&self, session_id: Uuid) -> String {
+        format!("presence:heartbeat:{}", session_id)
+    }
+
+    /// 会话登记表：session_id -> "room_id:user_id"
+    fn sessions_key(&self) -> String {
+        "presence:sessions".to_string()
     }
 
     /// 生成房间在线用户集合的Redis键
@@ -152,6 +430,85 @@ impl RedisPresenceManager {
         format!("user:{}:rooms", user_id)
     }
 
+    /// 有用户在线的房间索引，全站概况按它逐个取房间人数，不用扫描键空间
+    fn rooms_index_key(&self) -> String {
+        "presence:rooms".to_string()
+    }
+
+    /// 在线用户索引
+    fn users_index_key(&self) -> String {
+        "presence:users".to_string()
+    }
+
+    /// 集合已经空了（或已过期）时把成员移出索引
+    ///
+    /// 检查和移除在一个脚本里完成；上线时先写集合再写索引，不会误删刚上线的成员
+    async fn unindex_if_empty(
+        conn: &mut redis::aio::MultiplexedConnection,
+        set_key: &str,
+        index_key: &str,
+        member: &str,
+    ) -> Result<(), redis::RedisError> {
+        let script = redis::Script::new(
+            r#"
+            if redis.call('SCARD', KEYS[1]) == 0 then
+                return redis.call('SREM', KEYS[2], ARGV[1])
+            end
+            return 0
+            "#,
+        );
+        let _: i64 = script
+            .key(set_key)
+            .key(index_key)
+            .arg(member)
+            .invoke_async(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 用户离开房间后，房间或用户不再有在线记录时移出索引
+    async fn unindex_departed(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<(), redis::RedisError> {
+        Self::unindex_if_empty(
+            conn,
+            &self.room_online_key(room_id),
+            &self.rooms_index_key(),
+            &room_id.to_string(),
+        )
+        .await?;
+        Self::unindex_if_empty(
+            conn,
+            &self.user_rooms_key(user_id),
+            &self.users_index_key(),
+            &user_id.to_string(),
+        )
+        .await
+    }
+
+    /// 读取索引里的成员，无法解析的条目忽略
+    async fn indexed_members<T: From<Uuid>>(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        index_key: &str,
+    ) -> Result<Vec<T>, ApplicationError> {
+        let members: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(index_key)
+            .query_async(conn)
+            .await
+            .map_err(|e| {
+                let message = format!("Redis operation failed: {e}");
+                ApplicationError::infrastructure_with_source(message, e)
+            })?;
+        Ok(members
+            .iter()
+            .filter_map(|member| Uuid::parse_str(member).ok().map(T::from))
+            .collect())
+    }
+
     /// 获取连接
     async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, ApplicationError> {
         self.redis_client
@@ -179,8 +536,10 @@ impl PresenceManager for RedisPresenceManager {
         let _: () = redis::pipe()
             .sadd(&room_key, user_id.to_string()) // 将用户添加到房间在线用户集合
             .sadd(&user_key, room_id.to_string()) // 将房间添加到用户在线房间集合
-            .expire(&room_key, 86400) // 设置过期时间24小时，防止内存泄漏
-            .expire(&user_key, 86400)
+            .expire(&room_key, self.presence_ttl_secs()) // 由心跳续期，节点崩溃后自然过期
+            .expire(&user_key, self.presence_ttl_secs())
+            .sadd(self.rooms_index_key(), room_id.to_string()) // 先写集合再写索引
+            .sadd(self.users_index_key(), user_id.to_string())
             .query_async(&mut conn)
             .await
             .map_err(|e| {
@@ -238,6 +597,12 @@ impl PresenceManager for RedisPresenceManager {
                 let message = format!("Redis operation failed: {e}");
                 ApplicationError::infrastructure_with_source(message, e)
             })?;
+        self.unindex_departed(&mut conn, room_id, user_id)
+            .await
+            .map_err(|e| {
+                let message = format!("Redis operation failed: {e}");
+                ApplicationError::infrastructure_with_source(message, e)
+            })?;
 
         // 记录用户断开事件
         let event = UserPresenceEvent {
@@ -360,8 +725,8 @@ impl PresenceManager for RedisPresenceManager {
         let mut pipe = redis::pipe();
 
         // 从每个房间的在线用户集合中移除这个用户
-        for room_id in room_ids {
-            let room_key = self.room_online_key(room_id);
+        for room_id in &room_ids {
+            let room_key = self.room_online_key(*room_id);
             pipe.srem(&room_key, user_id.to_string());
         }
 
@@ -373,6 +738,15 @@ impl PresenceManager for RedisPresenceManager {
             ApplicationError::infrastructure_with_source(message, e)
         })?;
 
+        for room_id in room_ids {
+            self.unindex_departed(&mut conn, room_id, user_id)
+                .await
+                .map_err(|e| {
+                    let message = format!("Redis operation failed: {e}");
+                    ApplicationError::infrastructure_with_source(message, e)
+                })?;
+        }
+
         tracing::info!(
             user_id = %user_id,
             "清理用户在线状态"
@@ -399,6 +773,155 @@ impl PresenceManager for RedisPresenceManager {
         Ok(count)
     }
 
+    async fn heartbeat(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        session_id: Uuid,
+    ) -> Result<(), ApplicationError> {
+        let mut conn = self.get_connection().await?;
+
+        let _: () = redis::pipe()
+            .cmd("SET")
+            .arg(self.heartbeat_key(session_id))
+            .arg(1)
+            .arg("PX")
+            .arg(self.heartbeat_timeout.as_millis() as u64)
+            .ignore()
+            .hset(
+                self.sessions_key(),
+                session_id.to_string(),
+                format!("{}:{}", room_id, user_id),
+            )
+            .ignore()
+            .expire(self.room_online_key(room_id), self.presence_ttl_secs())
+            .ignore()
+            .expire(self.user_rooms_key(user_id), self.presence_ttl_secs())
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                let message = format!("Redis operation failed: {e}");
+                ApplicationError::infrastructure_with_source(message, e)
+            })?;
+
+        Ok(())
+    }
+
+    async fn end_session(&self, session_id: Uuid) -> Result<(), ApplicationError> {
+        let mut conn = self.get_connection().await?;
+
+        let _: () = redis::pipe()
+            .del(self.heartbeat_key(session_id))
+            .ignore()
+            .hdel(self.sessions_key(), session_id.to_string())
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                let message = format!("Redis operation failed: {e}");
+                ApplicationError::infrastructure_with_source(message, e)
+            })?;
+
+        Ok(())
+    }
+
+    async fn sweep_expired_sessions(&self) -> Result<Vec<UserPresenceEvent>, ApplicationError> {
+        let mut conn = self.get_connection().await?;
+        let redis_err = |e: redis::RedisError| {
+            let message = format!("Redis operation failed: {e}");
+            ApplicationError::infrastructure_with_source(message, e)
+        };
+
+        let registered: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(self.sessions_key())
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_err)?;
+        if registered.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::with_capacity(registered.len());
+        for (session_id, pair) in registered {
+            match parse_session_entry(&session_id, &pair) {
+                Some(entry) => sessions.push(entry),
+                None => {
+                    tracing::warn!(session_id = %session_id, pair = %pair, "无法解析的会话登记，直接丢弃");
+                    let _: () = redis::cmd("HDEL")
+                        .arg(self.sessions_key())
+                        .arg(&session_id)
+                        .query_async(&mut conn)
+                        .await
+                        .map_err(redis_err)?;
+                }
+            }
+        }
+
+        let mut pipe = redis::pipe();
+        for (session_id, _, _) in &sessions {
+            pipe.exists(self.heartbeat_key(*session_id));
+        }
+        let alive: Vec<bool> = pipe.query_async(&mut conn).await.map_err(redis_err)?;
+
+        let mut live_pairs = HashSet::new();
+        let mut expired = Vec::new();
+        for (session, alive) in sessions.into_iter().zip(alive) {
+            if alive {
+                live_pairs.insert((session.1, session.2));
+            } else {
+                expired.push(session);
+            }
+        }
+
+        let mut events = Vec::new();
+        for (session_id, room_id, user_id) in expired {
+            // HDEL 的返回值作为认领标记：多个实例同时清扫时只有一个会产生事件
+            let claimed: i64 = redis::cmd("HDEL")
+                .arg(self.sessions_key())
+                .arg(session_id.to_string())
+                .query_async(&mut conn)
+                .await
+                .map_err(redis_err)?;
+            if claimed == 0 {
+                continue;
+            }
+
+            if !live_pairs.contains(&(room_id, user_id)) {
+                let _: () = redis::pipe()
+                    .srem(self.room_online_key(room_id), user_id.to_string())
+                    .ignore()
+                    .srem(self.user_rooms_key(user_id), room_id.to_string())
+                    .ignore()
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(redis_err)?;
+                self.unindex_departed(&mut conn, room_id, user_id)
+                    .await
+                    .map_err(redis_err)?;
+            }
+
+            let event = expired_session_event(room_id, user_id, session_id);
+            if let Err(e) = self.record_presence_event(event.clone()).await {
+                tracing::warn!(
+                    error = %e,
+                    user_id = %user_id,
+                    room_id = %room_id,
+                    "Failed to record presence event for expired session"
+                );
+            }
+            tracing::info!(
+                room_id = %room_id,
+                user_id = %user_id,
+                session_id = %session_id,
+                "会话心跳超时，标记为离线"
+            );
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
     async fn get_online_stats(&self, room_id: RoomId) -> Result<OnlineStats, ApplicationError> {
         // 直接查询Redis，确保数据强一致性
         let count = self.get_online_count(room_id).await?;
@@ -411,6 +934,86 @@ impl PresenceManager for RedisPresenceManager {
         Ok(stats)
     }
 
+    async fn snapshot(&self, top_rooms: usize) -> Result<PresenceSnapshot, ApplicationError> {
+        let mut conn = self.get_connection().await?;
+        let redis_err = |e: redis::RedisError| {
+            let message = format!("Redis operation failed: {e}");
+            ApplicationError::infrastructure_with_source(message, e)
+        };
+
+        // 按索引逐个取房间人数和用户在线记录，各用一个管道一次取回
+        let rooms: Vec<RoomId> = self
+            .indexed_members(&mut conn, &self.rooms_index_key())
+            .await?;
+        let room_counts: Vec<u64> = if rooms.is_empty() {
+            Vec::new()
+        } else {
+            let mut pipe = redis::pipe();
+            for room_id in &rooms {
+                pipe.scard(self.room_online_key(*room_id));
+            }
+            pipe.query_async(&mut conn).await.map_err(redis_err)?
+        };
+
+        let users: Vec<UserId> = self
+            .indexed_members(&mut conn, &self.users_index_key())
+            .await?;
+        let user_online: Vec<bool> = if users.is_empty() {
+            Vec::new()
+        } else {
+            let mut pipe = redis::pipe();
+            for user_id in &users {
+                pipe.exists(self.user_rooms_key(*user_id));
+            }
+            pipe.query_async(&mut conn).await.map_err(redis_err)?
+        };
+
+        // 集合过期（节点崩溃没来得及下线）后索引里会留下空成员，顺手移除
+        let mut online_rooms = Vec::with_capacity(rooms.len());
+        for (room_id, count) in rooms.into_iter().zip(room_counts) {
+            if count > 0 {
+                online_rooms.push((room_id, count));
+            } else {
+                Self::unindex_if_empty(
+                    &mut conn,
+                    &self.room_online_key(room_id),
+                    &self.rooms_index_key(),
+                    &room_id.to_string(),
+                )
+                .await
+                .map_err(redis_err)?;
+            }
+        }
+        let mut total_online_users = 0u64;
+        for (user_id, online) in users.into_iter().zip(user_online) {
+            if online {
+                total_online_users += 1;
+            } else {
+                Self::unindex_if_empty(
+                    &mut conn,
+                    &self.user_rooms_key(user_id),
+                    &self.users_index_key(),
+                    &user_id.to_string(),
+                )
+                .await
+                .map_err(redis_err)?;
+            }
+        }
+
+        let connection_count: u64 = redis::cmd("HLEN")
+            .arg(self.sessions_key())
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_err)?;
+
+        Ok(PresenceSnapshot {
+            total_online_users,
+            connection_count,
+            top_rooms: rank_rooms(online_rooms, top_rooms),
+            timestamp: Utc::now(),
+        })
+    }
+
     async fn record_presence_event(
         &self,
         event: UserPresenceEvent,
@@ -471,12 +1074,22 @@ impl PresenceManager for RedisPresenceManager {
 /// 内存实现的在线状态管理器（用于测试）
 pub mod memory {
     use super::*;
-    use std::collections::{HashMap, HashSet};
+    use std::collections::HashMap;
+    use std::time::Instant;
     use tokio::sync::RwLock;
 
+    /// 会话最近一次心跳
+    struct HeartbeatSession {
+        room_id: RoomId,
+        user_id: UserId,
+        last_seen: Instant,
+    }
+
     pub struct MemoryPresenceManager {
         room_users: RwLock<HashMap<RoomId, HashSet<UserId>>>,
         user_rooms: RwLock<HashMap<UserId, HashSet<RoomId>>>,
+        sessions: RwLock<HashMap<Uuid, HeartbeatSession>>,
+        heartbeat_timeout: Duration,
     }
 
     impl Default for MemoryPresenceManager {
@@ -487,9 +1100,15 @@ pub mod memory {
 
     impl MemoryPresenceManager {
         pub fn new() -> Self {
+            Self::with_heartbeat_timeout(DEFAULT_HEARTBEAT_TIMEOUT)
+        }
+
+        pub fn with_heartbeat_timeout(heartbeat_timeout: Duration) -> Self {
             Self {
                 room_users: RwLock::new(HashMap::new()),
                 user_rooms: RwLock::new(HashMap::new()),
+                sessions: RwLock::new(HashMap::new()),
+                heartbeat_timeout,
             }
         }
     }
@@ -587,6 +1206,59 @@ pub mod memory {
             Ok(count)
         }
 
+        async fn heartbeat(
+            &self,
+            room_id: RoomId,
+            user_id: UserId,
+            session_id: Uuid,
+        ) -> Result<(), ApplicationError> {
+            self.sessions.write().await.insert(
+                session_id,
+                HeartbeatSession {
+                    room_id,
+                    user_id,
+                    last_seen: Instant::now(),
+                },
+            );
+            Ok(())
+        }
+
+        async fn end_session(&self, session_id: Uuid) -> Result<(), ApplicationError> {
+            self.sessions.write().await.remove(&session_id);
+            Ok(())
+        }
+
+        async fn sweep_expired_sessions(&self) -> Result<Vec<UserPresenceEvent>, ApplicationError> {
+            let (expired, live_pairs) = {
+                let mut sessions = self.sessions.write().await;
+                let expired: Vec<(Uuid, RoomId, UserId)> = sessions
+                    .iter()
+                    .filter(|(_, session)| session.last_seen.elapsed() > self.heartbeat_timeout)
+                    .map(|(id, session)| (*id, session.room_id, session.user_id))
+                    .collect();
+                for (session_id, _, _) in &expired {
+                    sessions.remove(session_id);
+                }
+                let live_pairs: HashSet<(RoomId, UserId)> = sessions
+                    .values()
+                    .map(|session| (session.room_id, session.user_id))
+                    .collect();
+                (expired, live_pairs)
+            };
+
+            let mut events = Vec::new();
+            for (session_id, room_id, user_id) in expired {
+                if !live_pairs.contains(&(room_id, user_id)) {
+                    self.user_disconnected(room_id, user_id).await?;
+                }
+                let event = expired_session_event(room_id, user_id, session_id);
+                self.record_presence_event(event.clone()).await?;
+                events.push(event);
+            }
+
+            Ok(events)
+        }
+
         async fn get_online_stats(&self, room_id: RoomId) -> Result<OnlineStats, ApplicationError> {
             let count = self.get_online_count(room_id).await?;
             Ok(OnlineStats {
@@ -596,6 +1268,22 @@ pub mod memory {
             })
         }
 
+        async fn snapshot(&self, top_rooms: usize) -> Result<PresenceSnapshot, ApplicationError> {
+            let rooms = self
+                .room_users
+                .read()
+                .await
+                .iter()
+                .map(|(room_id, users)| (*room_id, users.len() as u64))
+                .collect();
+            Ok(PresenceSnapshot {
+                total_online_users: self.user_rooms.read().await.len() as u64,
+                connection_count: self.sessions.read().await.len() as u64,
+                top_rooms: rank_rooms(rooms, top_rooms),
+                timestamp: Utc::now(),
+            })
+        }
+
         async fn record_presence_event(
             &self,
             event: UserPresenceEvent,
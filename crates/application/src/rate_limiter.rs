@@ -1,7 +1,77 @@
-use domain::UserId;
+use arc_swap::ArcSwap;
+use config::RateLimitConfig;
+use domain::{RoomId, UserId};
 use std::sync::Arc;
 use std::time::Duration;
 
+/// 单个用户在单个房间内的发送配额（令牌桶）
+///
+/// 实现方负责按 (房间, 用户) 分桶；单实例部署可用内存实现，
+/// 多实例部署必须用 Redis 实现，否则每个实例各算各的配额
+#[async_trait::async_trait]
+pub trait SendRateLimiter: Send + Sync {
+    /// 消耗一个令牌，桶已空时返回 [`RateLimitError::MessageRateLimited`]
+    async fn acquire(&self, room_id: RoomId, user_id: UserId) -> Result<(), RateLimitError>;
+
+    /// 房间级限制：房间策略（如慢速模式）和房间内所有用户的合计速率，默认不限制
+    ///
+    /// 发送者是房间 owner/admin 时 `is_room_admin` 为 true，实现方按配置豁免合计速率
+    async fn check_room(
+        &self,
+        _room_id: RoomId,
+        _user_id: UserId,
+        _is_room_admin: bool,
+    ) -> Result<(), RateLimitError> {
+        Ok(())
+    }
+}
+
+/// 令牌桶参数：每分钟补充 `per_minute` 个令牌，桶容量为 `burst`
+///
+/// `per_minute` 为 0 表示不限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBucketLimit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl TokenBucketLimit {
+    fn is_unlimited(&self) -> bool {
+        self.per_minute == 0
+    }
+
+    /// 桶容量至少为 1，否则永远发不出消息
+    fn capacity(&self) -> f64 {
+        self.burst.max(1) as f64
+    }
+
+    fn refill_per_ms(&self) -> f64 {
+        self.per_minute as f64 / 60_000.0
+    }
+
+    /// 令牌数补到 1 还需要的毫秒数
+    fn wait_ms(&self, tokens: f64) -> u64 {
+        ((1.0 - tokens) * 60_000.0 / self.per_minute as f64).ceil() as u64
+    }
+
+    fn exceeded(&self, wait_ms: u64) -> RateLimitError {
+        RateLimitError::MessageRateLimited {
+            per_minute: self.per_minute,
+            burst: self.burst,
+            retry_after_secs: wait_ms.div_ceil(1000).max(1),
+        }
+    }
+}
+
+impl From<&RateLimitConfig> for TokenBucketLimit {
+    fn from(config: &RateLimitConfig) -> Self {
+        Self {
+            per_minute: config.messages_per_minute,
+            burst: config.message_burst,
+        }
+    }
+}
+
 /// Redis-based用户消息配额管理
 /// 使用Redis的原子操作实现高效的分布式限流
 /// 限流错误类型
@@ -10,6 +80,57 @@ pub enum RateLimitError {
     #[error("Rate limit exceeded: {current}/{max} messages per minute")]
     RateLimitExceeded { current: u32, max: u32 },
 
+    #[error(
+        "Sending too fast: {per_minute} messages per minute with bursts of {burst}, retry after {retry_after_secs}s"
+    )]
+    MessageRateLimited {
+        per_minute: u32,
+        burst: u32,
+        retry_after_secs: u64,
+    },
+
+    #[error(
+        "Room rate limit exceeded: {max} messages per {window_secs}s, retry after {retry_after_secs}s"
+    )]
+    RoomRateLimitExceeded {
+        max: u32,
+        window_secs: u64,
+        retry_after_secs: u64,
+    },
+
+    #[error("Room is receiving too many messages: {max_per_sec} per second, retry after {retry_after_secs}s")]
+    RoomRateLimited {
+        max_per_sec: u32,
+        retry_after_secs: u64,
+    },
+
+    #[error(
+        "Too many failed join attempts: {max} per {window_secs}s, retry after {retry_after_secs}s"
+    )]
+    TooManyJoinAttempts {
+        max: u32,
+        window_secs: u64,
+        retry_after_secs: u64,
+    },
+
+    #[error(
+        "Too many password reset requests: {max} per {window_secs}s, retry after {retry_after_secs}s"
+    )]
+    TooManyPasswordResetRequests {
+        max: u32,
+        window_secs: u64,
+        retry_after_secs: u64,
+    },
+
+    #[error(
+        "Too many requests from this address: {max} per {window_secs}s, retry after {retry_after_secs}s"
+    )]
+    TooManyRequests {
+        max: u32,
+        window_secs: u64,
+        retry_after_secs: u64,
+    },
+
     #[error("Too many connections: {current}/{max} connections per user")]
     TooManyConnections { current: u32, max: u32 },
 
@@ -20,13 +141,71 @@ pub enum RateLimitError {
     Redis(#[from] redis::RedisError),
 }
 
-/// Redis-based消息限流器
-/// 使用Redis原子操作实现分布式限流，支持水平扩展
-pub struct MessageRateLimiter {
+/// 房间级限流策略：每个用户在 `window` 内最多发送 `max_per_window` 条消息
+///
+/// 慢速模式就是 `max_per_window = 1` 的特例
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoomRateLimit {
+    pub max_per_window: u32,
+    pub window: Duration,
+}
+
+impl RoomRateLimit {
+    /// 慢速模式：每个用户每 `interval` 只能发一条消息
+    pub fn slow_mode(interval: Duration) -> Self {
+        Self {
+            max_per_window: 1,
+            window: interval,
+        }
+    }
+}
+
+/// 默认限流参数，整体替换以支持运行时重载
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DefaultLimits {
     /// 每分钟最大消息数
     max_messages_per_minute: u32,
+    /// 单个房间内允许的突发消息数（令牌桶容量）
+    message_burst: u32,
     /// 每用户最大连接数
     max_connections_per_user: u32,
+    /// 房间内所有用户合计每秒最大消息数，0 表示不限制
+    room_messages_per_sec: u32,
+    /// 房间 owner/admin 是否不受房间合计限流约束
+    exempt_room_admins: bool,
+    /// 窗口内允许的房间密码错误次数
+    max_join_attempts: u32,
+    /// 密码错误次数的统计窗口
+    join_attempt_window: Duration,
+    /// 窗口内同一来源允许申请密码重置的次数
+    max_password_reset_requests: u32,
+    /// 密码重置申请次数的统计窗口
+    password_reset_window: Duration,
+}
+
+impl From<&RateLimitConfig> for DefaultLimits {
+    fn from(config: &RateLimitConfig) -> Self {
+        Self {
+            max_messages_per_minute: config.messages_per_minute,
+            message_burst: config.message_burst,
+            max_connections_per_user: config.connections_per_user,
+            room_messages_per_sec: config.room_messages_per_sec,
+            exempt_room_admins: config.exempt_room_admins,
+            max_join_attempts: config.join_attempts,
+            join_attempt_window: Duration::from_secs(config.join_attempt_window_secs),
+            max_password_reset_requests: config.password_reset_requests,
+            password_reset_window: Duration::from_secs(config.password_reset_window_secs),
+        }
+    }
+}
+
+/// Redis-based消息限流器
+/// 使用Redis原子操作实现分布式限流，支持水平扩展
+///
+/// 房间级策略同样存放在 Redis，所有实例共享，管理员设置后立即生效；
+/// 默认策略放在 `ArcSwap` 里，配置重载时原子替换，进行中的检查仍用旧值
+pub struct MessageRateLimiter {
+    limits: ArcSwap<DefaultLimits>,
     /// 时间窗口大小（秒）
     window_duration: Duration,
     /// Redis客户端
@@ -41,13 +220,62 @@ impl MessageRateLimiter {
         max_connections_per_user: u32,
     ) -> Self {
         Self {
-            max_messages_per_minute,
-            max_connections_per_user,
+            limits: ArcSwap::from_pointee(DefaultLimits {
+                max_messages_per_minute,
+                message_burst: RateLimitConfig::default().message_burst,
+                max_connections_per_user,
+                room_messages_per_sec: 0,
+                exempt_room_admins: true,
+                max_join_attempts: 5,
+                join_attempt_window: Duration::from_secs(15 * 60),
+                max_password_reset_requests: 5,
+                password_reset_window: Duration::from_secs(60 * 60),
+            }),
             window_duration: Duration::from_secs(60), // 1分钟
             redis_client,
         }
     }
 
+    /// 按配置创建限流器
+    pub fn from_config(redis_client: Arc<redis::Client>, config: &RateLimitConfig) -> Self {
+        Self {
+            limits: ArcSwap::from_pointee(DefaultLimits::from(config)),
+            window_duration: Duration::from_secs(60),
+            redis_client,
+        }
+    }
+
+    /// 开启房间合计限流：单个房间所有用户每秒最多 `messages_per_sec` 条消息
+    pub fn with_room_total_limit(self, messages_per_sec: u32, exempt_room_admins: bool) -> Self {
+        self.update_limits(|limits| {
+            limits.room_messages_per_sec = messages_per_sec;
+            limits.exempt_room_admins = exempt_room_admins;
+        });
+        self
+    }
+
+    /// 房间密码尝试限制：`window` 内最多输错 `max_attempts` 次，超过后拒绝继续尝试
+    pub fn with_join_attempt_limit(self, max_attempts: u32, window: Duration) -> Self {
+        self.update_limits(|limits| {
+            limits.max_join_attempts = max_attempts;
+            limits.join_attempt_window = window;
+        });
+        self
+    }
+
+    /// 运行时替换默认策略，返回是否有变化；房间级策略不受影响
+    pub fn reload(&self, config: &RateLimitConfig) -> bool {
+        let next = DefaultLimits::from(config);
+        let previous = self.limits.swap(Arc::new(next.clone()));
+        *previous != next
+    }
+
+    fn update_limits(&self, update: impl FnOnce(&mut DefaultLimits)) {
+        let mut next = DefaultLimits::clone(&self.limits.load());
+        update(&mut next);
+        self.limits.store(Arc::new(next));
+    }
+
     /// 获取Redis连接
     async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, RateLimitError> {
         self.redis_client
@@ -63,6 +291,36 @@ impl MessageRateLimiter {
         format!("rate_limit:{}", user_id)
     }
 
+    /// 生成房间内用户限流键
+    fn room_rate_limit_key(&self, room_id: RoomId, user_id: UserId) -> String {
+        format!("rate_limit:room:{}:{}", room_id, user_id)
+    }
+
+    /// 生成房间内用户令牌桶键
+    fn send_bucket_key(&self, room_id: RoomId, user_id: UserId) -> String {
+        format!("rate_limit:bucket:{}:{}", room_id, user_id)
+    }
+
+    /// 生成房间合计限流键
+    fn room_total_key(&self, room_id: RoomId) -> String {
+        format!("rate_limit:room_total:{}", room_id)
+    }
+
+    /// 生成房间限流策略键
+    fn room_policy_key(&self, room_id: RoomId) -> String {
+        format!("rate_limit:room_policy:{}", room_id)
+    }
+
+    /// 生成房间密码错误计数键
+    fn join_failures_key(&self, user_id: UserId) -> String {
+        format!("rate_limit:join_failures:{}", user_id)
+    }
+
+    /// 生成密码重置申请计数键，`scope` 区分按 IP 还是按邮箱计数
+    fn password_reset_key(&self, scope: &str) -> String {
+        format!("rate_limit:password_reset:{}", scope)
+    }
+
     /// 生成用户连接数键
     fn connection_count_key(&self, user_id: UserId) -> String {
         format!("connection_count:{}", user_id)
@@ -71,6 +329,7 @@ impl MessageRateLimiter {
     /// 检查用户是否可以发送消息
     /// 使用Redis原子操作实现分布式限流
     pub async fn check_message_rate(&self, user_id: UserId) -> Result<(), RateLimitError> {
+        let max_messages_per_minute = self.limits.load().max_messages_per_minute;
         let mut conn = self.get_connection().await?;
         let key = self.rate_limit_key(user_id);
 
@@ -96,7 +355,7 @@ impl MessageRateLimiter {
 
         let result: Vec<i64> = script
             .key(&key)
-            .arg(self.max_messages_per_minute as i64)
+            .arg(max_messages_per_minute as i64)
             .arg(self.window_duration.as_secs() as i64)
             .invoke_async(&mut conn)
             .await
@@ -107,7 +366,290 @@ impl MessageRateLimiter {
         if result[0] == 0 {
             return Err(RateLimitError::RateLimitExceeded {
                 current: result[1] as u32,
-                max: self.max_messages_per_minute,
+                max: max_messages_per_minute,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// 设置房间级限流策略，覆盖默认策略
+    pub async fn set_room_limit(
+        &self,
+        room_id: RoomId,
+        max_per_window: u32,
+        window: Duration,
+    ) -> Result<(), RateLimitError> {
+        if max_per_window == 0 || window.is_zero() {
+            return self.clear_room_limit(room_id).await;
+        }
+
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("HSET")
+            .arg(self.room_policy_key(room_id))
+            .arg("max")
+            .arg(max_per_window)
+            .arg("window_ms")
+            .arg(window.as_millis() as u64)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 开启慢速模式；`interval` 为 0 表示关闭
+    pub async fn set_slow_mode(
+        &self,
+        room_id: RoomId,
+        interval: Duration,
+    ) -> Result<(), RateLimitError> {
+        let policy = RoomRateLimit::slow_mode(interval);
+        self.set_room_limit(room_id, policy.max_per_window, policy.window)
+            .await
+    }
+
+    /// 移除房间级策略，恢复默认策略
+    pub async fn clear_room_limit(&self, room_id: RoomId) -> Result<(), RateLimitError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("DEL")
+            .arg(self.room_policy_key(room_id))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 查询房间级策略，None 表示使用默认策略
+    pub async fn room_limit(
+        &self,
+        room_id: RoomId,
+    ) -> Result<Option<RoomRateLimit>, RateLimitError> {
+        let mut conn = self.get_connection().await?;
+        let (max, window_ms): (Option<u32>, Option<u64>) = redis::cmd("HMGET")
+            .arg(self.room_policy_key(room_id))
+            .arg("max")
+            .arg("window_ms")
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(match (max, window_ms) {
+            (Some(max_per_window), Some(window_ms)) => Some(RoomRateLimit {
+                max_per_window,
+                window: Duration::from_millis(window_ms),
+            }),
+            _ => None,
+        })
+    }
+
+    /// 检查用户能否在房间内发送消息
+    ///
+    /// 优先使用房间级策略，没有设置时回退到全局默认策略
+    pub async fn check(&self, room_id: RoomId, user_id: UserId) -> Result<(), RateLimitError> {
+        match self.room_limit(room_id).await? {
+            Some(policy) => self.check_room_rate(room_id, user_id, policy).await,
+            None => self.check_message_rate(user_id).await,
+        }
+    }
+
+    /// 只检查房间级策略（如慢速模式），没有设置时直接放行
+    ///
+    /// 默认配额由 ChatService 通过 [`SendRateLimiter`] 按房间令牌桶执行，
+    /// 这里不再重复计数
+    pub async fn check_room_policy(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<(), RateLimitError> {
+        match self.room_limit(room_id).await? {
+            Some(policy) => self.check_room_rate(room_id, user_id, policy).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 检查房间内所有用户的合计发送速率
+    ///
+    /// 与单用户限流互补：防止大量用户同时涌入把单个房间刷爆，拖垮广播和统计链路。
+    /// `is_room_admin` 为 true 且配置了豁免时直接放行
+    pub async fn check_room_total(
+        &self,
+        room_id: RoomId,
+        is_room_admin: bool,
+    ) -> Result<(), RateLimitError> {
+        let limits = self.limits.load_full();
+        if limits.room_messages_per_sec == 0 || (is_room_admin && limits.exempt_room_admins) {
+            return Ok(());
+        }
+
+        let key = self.room_total_key(room_id);
+        if let Some(remaining_ms) = self
+            .incr_fixed_window(&key, limits.room_messages_per_sec, Duration::from_secs(1))
+            .await?
+        {
+            return Err(RateLimitError::RoomRateLimited {
+                max_per_sec: limits.room_messages_per_sec,
+                retry_after_secs: remaining_ms.div_ceil(1000),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn check_room_rate(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        policy: RoomRateLimit,
+    ) -> Result<(), RateLimitError> {
+        let key = self.room_rate_limit_key(room_id, user_id);
+
+        if let Some(remaining_ms) = self
+            .incr_fixed_window(&key, policy.max_per_window, policy.window)
+            .await?
+        {
+            // 向上取整：剩余 0.2 秒也要告诉客户端等 1 秒
+            return Err(RateLimitError::RoomRateLimitExceeded {
+                max: policy.max_per_window,
+                window_secs: policy.window.as_secs(),
+                retry_after_secs: remaining_ms.div_ceil(1000),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// 固定窗口计数：超限时返回窗口剩余毫秒数，未超限返回 None
+    async fn incr_fixed_window(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> Result<Option<u64>, RateLimitError> {
+        let mut conn = self.get_connection().await?;
+
+        // 与全局限流相同的 INCR + EXPIRE，超限时额外返回窗口剩余毫秒数
+        let script = redis::Script::new(
+            r#"
+            local key = KEYS[1]
+            local limit = tonumber(ARGV[1])
+            local window_ms = tonumber(ARGV[2])
+
+            local current = redis.call('INCR', key)
+            if current == 1 then
+                redis.call('PEXPIRE', key, window_ms)
+            end
+
+            if current > limit then
+                return {0, redis.call('PTTL', key)}
+            else
+                return {1, 0}
+            end
+            "#,
+        );
+
+        let result: Vec<i64> = script
+            .key(key)
+            .arg(limit as i64)
+            .arg(window.as_millis() as i64)
+            .invoke_async(&mut conn)
+            .await?;
+
+        if result[0] == 0 {
+            Ok(Some(result[1].max(0) as u64))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// 检查用户是否还能尝试房间密码
+    ///
+    /// 按用户计数而不是按房间：换房间猜密码同样受限
+    pub async fn check_join_attempts(&self, user_id: UserId) -> Result<(), RateLimitError> {
+        let limits = self.limits.load_full();
+        let mut conn = self.get_connection().await?;
+        let key = self.join_failures_key(user_id);
+
+        let (failures, ttl_ms): (Option<u32>, i64) = redis::pipe()
+            .cmd("GET")
+            .arg(&key)
+            .cmd("PTTL")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        if failures.unwrap_or(0) >= limits.max_join_attempts {
+            return Err(RateLimitError::TooManyJoinAttempts {
+                max: limits.max_join_attempts,
+                window_secs: limits.join_attempt_window.as_secs(),
+                retry_after_secs: (ttl_ms.max(0) as u64).div_ceil(1000),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// 记录一次密码错误，返回窗口内剩余的尝试次数
+    ///
+    /// 剩余 0 次之后的尝试由 [`Self::check_join_attempts`] 拒绝
+    pub async fn record_failed_join(&self, user_id: UserId) -> Result<u32, RateLimitError> {
+        let limits = self.limits.load_full();
+        let mut conn = self.get_connection().await?;
+        let key = self.join_failures_key(user_id);
+
+        // 窗口从第一次输错开始计算
+        let script = redis::Script::new(
+            r#"
+            local key = KEYS[1]
+            local window_ms = tonumber(ARGV[1])
+
+            local current = redis.call('INCR', key)
+            if current == 1 then
+                redis.call('PEXPIRE', key, window_ms)
+            end
+
+            return current
+            "#,
+        );
+
+        let failures: u32 = script
+            .key(&key)
+            .arg(limits.join_attempt_window.as_millis() as i64)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(limits.max_join_attempts.saturating_sub(failures))
+    }
+
+    /// 加入成功后清空错误计数
+    pub async fn clear_join_attempts(&self, user_id: UserId) -> Result<(), RateLimitError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("DEL")
+            .arg(self.join_failures_key(user_id))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 记录一次密码重置申请，窗口内超过次数时拒绝
+    ///
+    /// `scope` 由调用方给出，例如 `ip:1.2.3.4`、`email:a@example.com`；
+    /// 同时按 IP 和邮箱计数，既防止轰炸单个邮箱，也防止单个来源遍历邮箱
+    pub async fn check_password_reset_request(&self, scope: &str) -> Result<(), RateLimitError> {
+        let limits = self.limits.load_full();
+        let key = self.password_reset_key(scope);
+
+        if let Some(remaining_ms) = self
+            .incr_fixed_window(
+                &key,
+                limits.max_password_reset_requests,
+                limits.password_reset_window,
+            )
+            .await?
+        {
+            return Err(RateLimitError::TooManyPasswordResetRequests {
+                max: limits.max_password_reset_requests,
+                window_secs: limits.password_reset_window.as_secs(),
+                retry_after_secs: remaining_ms.div_ceil(1000),
             });
         }
 
@@ -116,6 +658,7 @@ impl MessageRateLimiter {
 
     /// 检查用户连接数限制
     pub async fn check_connection_limit(&self, user_id: UserId) -> Result<(), RateLimitError> {
+        let max_connections_per_user = self.limits.load().max_connections_per_user;
         let mut conn = self.get_connection().await?;
         let key = self.connection_count_key(user_id);
 
@@ -125,10 +668,10 @@ impl MessageRateLimiter {
             .await
             .unwrap_or(0);
 
-        if count >= self.max_connections_per_user as i64 {
+        if count >= max_connections_per_user as i64 {
             return Err(RateLimitError::TooManyConnections {
                 current: count as u32,
-                max: self.max_connections_per_user,
+                max: max_connections_per_user,
             });
         }
 
@@ -230,6 +773,215 @@ impl MessageRateLimiter {
     }
 }
 
+/// Redis 令牌桶：所有实例共享同一个桶，时间取 Redis 服务器时间，避免各实例时钟偏差
+#[async_trait::async_trait]
+impl SendRateLimiter for MessageRateLimiter {
+    async fn acquire(&self, room_id: RoomId, user_id: UserId) -> Result<(), RateLimitError> {
+        let limits = self.limits.load_full();
+        let limit = TokenBucketLimit {
+            per_minute: limits.max_messages_per_minute,
+            burst: limits.message_burst,
+        };
+        if limit.is_unlimited() {
+            return Ok(());
+        }
+
+        let mut conn = self.get_connection().await?;
+        let script = redis::Script::new(
+            r#"
+            local key = KEYS[1]
+            local capacity = tonumber(ARGV[1])
+            local refill_per_ms = tonumber(ARGV[2])
+
+            local time = redis.call('TIME')
+            local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+            local state = redis.call('HMGET', key, 'tokens', 'ts')
+            local tokens = tonumber(state[1]) or capacity
+            local ts = tonumber(state[2]) or now
+            tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill_per_ms)
+
+            local wait_ms = 0
+            if tokens >= 1 then
+                tokens = tokens - 1
+            else
+                wait_ms = math.ceil((1 - tokens) / refill_per_ms)
+            end
+
+            redis.call('HSET', key, 'tokens', tostring(tokens), 'ts', tostring(now))
+            -- 桶补满之后状态和新桶一样，可以直接过期
+            redis.call('PEXPIRE', key, math.ceil(capacity / refill_per_ms))
+            return wait_ms
+            "#,
+        );
+
+        let wait_ms: i64 = script
+            .key(self.send_bucket_key(room_id, user_id))
+            .arg(limit.capacity())
+            .arg(limit.refill_per_ms())
+            .invoke_async(&mut conn)
+            .await?;
+
+        if wait_ms > 0 {
+            return Err(limit.exceeded(wait_ms as u64));
+        }
+
+        Ok(())
+    }
+
+    async fn check_room(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        is_room_admin: bool,
+    ) -> Result<(), RateLimitError> {
+        self.check_room_policy(room_id, user_id).await?;
+        self.check_room_total(room_id, is_room_admin).await
+    }
+}
+
+pub mod memory {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    /// 超过这么多个桶时清掉已经补满的桶，防止长时间运行后内存无限增长
+    const MAX_IDLE_BUCKETS: usize = 10_000;
+
+    #[derive(Debug, Clone, Copy)]
+    struct Bucket {
+        tokens: f64,
+        updated_at: Instant,
+    }
+
+    /// 内存令牌桶，只适用于单实例部署和测试
+    pub struct MemorySendRateLimiter {
+        limit: TokenBucketLimit,
+        buckets: Mutex<HashMap<(RoomId, UserId), Bucket>>,
+    }
+
+    impl MemorySendRateLimiter {
+        pub fn new(limit: TokenBucketLimit) -> Self {
+            Self {
+                limit,
+                buckets: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn take(&self, room_id: RoomId, user_id: UserId, now: Instant) -> Result<(), u64> {
+            let capacity = self.limit.capacity();
+            let refill_per_ms = self.limit.refill_per_ms();
+            let refill = |bucket: &Bucket| {
+                let elapsed_ms = now
+                    .saturating_duration_since(bucket.updated_at)
+                    .as_secs_f64()
+                    * 1000.0;
+                (bucket.tokens + elapsed_ms * refill_per_ms).min(capacity)
+            };
+
+            let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+            if buckets.len() > MAX_IDLE_BUCKETS {
+                buckets.retain(|_, bucket| refill(bucket) < capacity);
+            }
+
+            let bucket = buckets.entry((room_id, user_id)).or_insert(Bucket {
+                tokens: capacity,
+                updated_at: now,
+            });
+            bucket.tokens = refill(bucket);
+            bucket.updated_at = now;
+
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                Ok(())
+            } else {
+                Err(self.limit.wait_ms(bucket.tokens))
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl SendRateLimiter for MemorySendRateLimiter {
+        async fn acquire(&self, room_id: RoomId, user_id: UserId) -> Result<(), RateLimitError> {
+            if self.limit.is_unlimited() {
+                return Ok(());
+            }
+            self.take(room_id, user_id, Instant::now())
+                .map_err(|wait_ms| self.limit.exceeded(wait_ms))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use uuid::Uuid;
+
+        fn ids() -> (RoomId, UserId) {
+            (RoomId::from(Uuid::new_v4()), UserId::from(Uuid::new_v4()))
+        }
+
+        #[test]
+        fn burst_then_refill() {
+            // 每分钟 60 条，即每秒补充一个令牌
+            let limiter = MemorySendRateLimiter::new(TokenBucketLimit {
+                per_minute: 60,
+                burst: 3,
+            });
+            let (room_id, user_id) = ids();
+            let start = Instant::now();
+
+            for _ in 0..3 {
+                assert!(limiter.take(room_id, user_id, start).is_ok());
+            }
+            assert_eq!(limiter.take(room_id, user_id, start), Err(1000));
+
+            let later = start + Duration::from_millis(400);
+            assert!(matches!(
+                limiter.take(room_id, user_id, later),
+                Err(wait_ms) if (600..=601).contains(&wait_ms)
+            ));
+
+            let refilled = start + Duration::from_millis(1001);
+            assert!(limiter.take(room_id, user_id, refilled).is_ok());
+            assert!(limiter.take(room_id, user_id, refilled).is_err());
+        }
+
+        #[test]
+        fn buckets_are_per_room_and_user() {
+            let limiter = MemorySendRateLimiter::new(TokenBucketLimit {
+                per_minute: 60,
+                burst: 1,
+            });
+            let (room_id, user_id) = ids();
+            let (other_room, other_user) = ids();
+            let now = Instant::now();
+
+            assert!(limiter.take(room_id, user_id, now).is_ok());
+            assert!(limiter.take(room_id, user_id, now).is_err());
+            assert!(limiter.take(other_room, user_id, now).is_ok());
+            assert!(limiter.take(room_id, other_user, now).is_ok());
+        }
+
+        #[tokio::test]
+        async fn retry_after_is_rounded_up_to_seconds() {
+            let limiter = MemorySendRateLimiter::new(TokenBucketLimit {
+                per_minute: 6,
+                burst: 1,
+            });
+            let (room_id, user_id) = ids();
+
+            limiter.acquire(room_id, user_id).await.unwrap();
+            match limiter.acquire(room_id, user_id).await {
+                Err(RateLimitError::MessageRateLimited {
+                    retry_after_secs, ..
+                }) => assert_eq!(retry_after_secs, 10),
+                other => panic!("expected MessageRateLimited, got {:?}", other),
+            }
+        }
+    }
+}
+
 // 移除Default实现，因为需要Redis客户端
 // 使用者必须显式提供Redis客户端
 
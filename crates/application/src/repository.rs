@@ -1,8 +1,21 @@
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+
 use async_trait::async_trait;
 use domain::{
-    ChatRoom, Message, MessageDelivery, MessageId, OrgId, Organization, RepositoryError, RoomId,
-    RoomMember, User, UserEmail, UserId,
+    AuditLogEntry, ChatRoom, EmailVerification, ExportedMessage, FileUpload, InvitationStatus,
+    MemberRoom, MentionedMessage, Message, MessageDelivery, MessageId, MessageRevision,
+    Notification, OrgId, Organization, OutboxEntry, PasswordReset, PinnedMessage, ReactionEmoji,
+    ReactionSummary, RefreshToken, RepositoryError, RoomBan, RoomId, RoomInvitation, RoomInvite,
+    RoomMember, RoomMemberListing, RoomMemberRestriction, RoomSummary, ThreadMessage, Timestamp,
+    User, UserEmail, UserId, UserProfile,
 };
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+/// 逐行读出的导出消息，不会一次把整个房间的消息读进内存
+pub type ExportedMessageStream =
+    Pin<Box<dyn Stream<Item = Result<ExportedMessage, RepositoryError>> + Send>>;
 
 /// 简单直接的事务管理：使用一个单独的service层来管理事务
 pub struct TransactionScope;
@@ -42,6 +55,72 @@ impl PaginationParams {
     }
 }
 
+/// 分页查询结果，带总数方便客户端渲染分页控件
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PaginatedResult<T> {
+    pub items: Vec<T>,
+    pub total_count: i64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// 游标分页结果：`next_cursor` 作为下一页请求的 `before` 参数
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// 本页最旧一条的 ID，没有更多数据时为 None
+    pub next_cursor: Option<MessageId>,
+    pub has_more: bool,
+}
+
+/// 公开房间排序方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSortOrder {
+    /// 最新创建的在前
+    #[default]
+    CreatedAt,
+    /// 成员最多的在前
+    MemberCount,
+}
+
+/// 公开房间发现查询
+#[derive(Debug, Clone)]
+pub struct PublicRoomQuery {
+    /// 房间名子串，不区分大小写
+    pub search: Option<String>,
+    pub sort: RoomSortOrder,
+    /// 是否包含已关闭的房间（仅管理员）
+    pub include_closed: bool,
+    /// 页码，从 1 开始
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl PublicRoomQuery {
+    pub fn pagination(&self) -> PaginationParams {
+        let page = self.page.max(1);
+        PaginationParams::with_offset(
+            self.page_size as i64,
+            (page as i64 - 1) * self.page_size as i64,
+        )
+    }
+}
+
+/// 审计日志检索条件，未设置的条件不参与过滤
+#[derive(Debug, Clone)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<UserId>,
+    pub action: Option<String>,
+    /// 起始时间（含）
+    pub from: Option<Timestamp>,
+    /// 截止时间（不含）
+    pub to: Option<Timestamp>,
+    /// 页码，从 1 开始
+    pub page: u32,
+    pub page_size: u32,
+}
+
 /// 时间范围查询参数
 #[derive(Debug, Clone, Default)]
 pub struct TimeRangeParams {
@@ -58,6 +137,13 @@ pub trait UserRepository: Send + Sync {
     /// 更新用户信息
     async fn update(&self, user: User) -> Result<User, RepositoryError>;
 
+    /// 更新用户信息并在同一事务里写入审计记录
+    async fn update_audited(
+        &self,
+        user: User,
+        audit: AuditLogEntry,
+    ) -> Result<User, RepositoryError>;
+
     /// 根据ID查找用户
     async fn find_by_id(&self, id: UserId) -> Result<Option<User>, RepositoryError>;
 
@@ -87,18 +173,33 @@ pub trait ChatRoomRepository: Send + Sync {
     /// 删除房间
     async fn delete(&self, id: RoomId) -> Result<(), RepositoryError>;
 
+    /// 删除房间并在同一事务里写入审计记录
+    async fn delete_audited(&self, id: RoomId, audit: AuditLogEntry)
+        -> Result<(), RepositoryError>;
+
+    /// 在一个事务里转让所有权：改房间的 owner_id，新 Owner 升为 Owner，原 Owner 降为 Admin，并写入审计记录
+    ///
+    /// 房间的 Owner 已经不是 `from` 或 `to` 已经不在房间里时返回 Conflict，不做任何修改
+    async fn transfer_ownership(
+        &self,
+        room_id: RoomId,
+        from: UserId,
+        to: UserId,
+        now: Timestamp,
+        audit: AuditLogEntry,
+    ) -> Result<ChatRoom, RepositoryError>;
+
     /// 根据所有者查询房间列表
     async fn find_by_owner(&self, owner_id: UserId) -> Result<Vec<ChatRoom>, RepositoryError>;
 
-    /// 查询公开房间列表（支持分页）
-    async fn find_public_rooms(
+    /// 用户所在的全部房间：用户置顶的在前，其余按最近活动倒序
+    async fn find_member_rooms(&self, user_id: UserId) -> Result<Vec<MemberRoom>, RepositoryError>;
+
+    /// 公开房间发现：私有房间永远不返回，已关闭房间按需包含
+    async fn find_public(
         &self,
-        pagination: PaginationParams,
-    ) -> Result<Vec<ChatRoom>, RepositoryError> {
-        // 默认实现：返回空列表（需要具体实现来支持）
-        let _ = pagination;
-        Ok(Vec::new())
-    }
+        query: &PublicRoomQuery,
+    ) -> Result<PaginatedResult<RoomSummary>, RepositoryError>;
 
     /// 原子性创建房间和owner成员 - Linus式简化版本
     /// 这个方法解决了create_room的核心事务问题，无需额外抽象
@@ -107,6 +208,16 @@ pub trait ChatRoomRepository: Send + Sync {
         room: ChatRoom,
         owner: RoomMember,
     ) -> Result<ChatRoom, RepositoryError>;
+
+    /// 房间的数据密钥（已被主密钥加密），房间未加密时返回 None
+    async fn find_data_key(&self, room_id: RoomId) -> Result<Option<Vec<u8>>, RepositoryError>;
+
+    /// 为房间保存数据密钥，只在房间还没有密钥时写入；返回是否写入
+    async fn set_data_key(
+        &self,
+        room_id: RoomId,
+        wrapped_key: &[u8],
+    ) -> Result<bool, RepositoryError>;
 }
 
 #[async_trait]
@@ -124,9 +235,40 @@ pub trait RoomMemberRepository: Send + Sync {
     /// 移除房间成员
     async fn delete_member(&self, room_id: RoomId, user_id: UserId) -> Result<(), RepositoryError>;
 
+    /// 更新成员并在同一事务里写入审计记录（管理员调整角色）
+    async fn upsert_audited(
+        &self,
+        member: RoomMember,
+        audit: AuditLogEntry,
+    ) -> Result<RoomMember, RepositoryError>;
+
+    /// 移除成员并在同一事务里写入审计记录（管理员踢人）
+    async fn delete_member_audited(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        audit: AuditLogEntry,
+    ) -> Result<(), RepositoryError>;
+
     /// 查询房间所有成员
     async fn find_by_room(&self, room_id: RoomId) -> Result<Vec<RoomMember>, RepositoryError>;
 
+    /// 分页列出房间成员及用户名，按角色（Owner、Admin、Member）再按加入时间排列
+    async fn list_by_room_paginated(
+        &self,
+        room_id: RoomId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<RoomMemberListing>, RepositoryError>;
+
+    /// 更新成员的已读位置（只前进不后退，旧回执不会覆盖新回执）
+    async fn update_last_read(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        message_id: MessageId,
+    ) -> Result<(), RepositoryError>;
+
     /// 查询用户参与的所有房间
     async fn find_by_user(&self, user_id: UserId) -> Result<Vec<RoomMember>, RepositoryError> {
         // 默认实现：返回空列表（需要具体实现来支持）
@@ -134,6 +276,58 @@ pub trait RoomMemberRepository: Send + Sync {
         Ok(Vec::new())
     }
 
+    /// 查询用户在房间内的限制，没有记录返回 None
+    async fn find_restriction(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<Option<RoomMemberRestriction>, RepositoryError>;
+
+    /// 设置或清除（传 None）禁言到期时间
+    async fn set_muted_until(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        muted_until: Option<Timestamp>,
+    ) -> Result<RoomMemberRestriction, RepositoryError>;
+
+    /// 把房间置顶到用户自己的房间列表，已置顶时保持原置顶时间
+    async fn pin_room(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        pinned_at: Timestamp,
+    ) -> Result<(), RepositoryError>;
+
+    /// 取消置顶，未置顶时什么也不做
+    async fn unpin_room(&self, room_id: RoomId, user_id: UserId) -> Result<(), RepositoryError>;
+
+    /// 在一条语句里把用户的多个房间（`None` 表示全部房间）标记为已读到最新消息
+    ///
+    /// 返回已读位置有前进的房间及其新的已读消息
+    async fn mark_rooms_read(
+        &self,
+        user_id: UserId,
+        room_ids: Option<&[RoomId]>,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError>;
+
+    /// 封禁用户并在同一事务里把他移出房间；已封禁时更新封禁人和原因
+    async fn ban(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        banned_by: UserId,
+        reason: Option<String>,
+    ) -> Result<RoomBan, RepositoryError>;
+
+    /// 解除封禁，没有被封禁时返回 false
+    async fn unban(&self, room_id: RoomId, user_id: UserId) -> Result<bool, RepositoryError>;
+
+    async fn is_banned(&self, room_id: RoomId, user_id: UserId) -> Result<bool, RepositoryError>;
+
+    /// 房间的封禁名单，新的在前
+    async fn list_bans(&self, room_id: RoomId) -> Result<Vec<RoomBan>, RepositoryError>;
+
     // === 向后兼容的方法别名 ===
 
     /// @deprecated 使用 find_member 替代
@@ -161,9 +355,21 @@ pub trait MessageRepository: Send + Sync {
     /// 保存消息到数据库，返回消息ID（使用连接池）
     async fn create(&self, message: Message) -> Result<MessageId, RepositoryError>;
 
+    /// 一次写入多条消息，全部成功或全部失败；返回的 ID 与传入顺序一致
+    ///
+    /// 同一房间内按传入顺序分配序列号
+    async fn create_batch(&self, messages: Vec<Message>)
+        -> Result<Vec<MessageId>, RepositoryError>;
+
+    /// 在同一事务里保存消息并写入发件箱记录，由后台分发任务负责广播
+    async fn create_with_outbox(&self, message: Message) -> Result<MessageId, RepositoryError>;
+
     /// 根据ID查找消息
     async fn find_by_id(&self, id: MessageId) -> Result<Option<Message>, RepositoryError>;
 
+    /// 批量按ID查询消息（包括已删除的），按序列号升序；不存在的ID直接忽略
+    async fn find_by_ids(&self, ids: &[MessageId]) -> Result<Vec<Message>, RepositoryError>;
+
     /// 获取房间的最近消息（支持分页）
     async fn find_recent_by_room(
         &self,
@@ -172,6 +378,34 @@ pub trait MessageRepository: Send + Sync {
         before: Option<MessageId>,
     ) -> Result<Vec<Message>, RepositoryError>;
 
+    /// 按游标分页获取房间消息（新的在前）
+    ///
+    /// 多取一条判断是否还有更早的消息，省掉一次 COUNT 查询
+    async fn find_page_by_room(
+        &self,
+        room_id: RoomId,
+        limit: u32,
+        before: Option<MessageId>,
+    ) -> Result<CursorPage<Message>, RepositoryError> {
+        let mut items = self
+            .find_recent_by_room(room_id, PaginationParams::new(limit as i64 + 1), before)
+            .await?;
+
+        let has_more = items.len() > limit as usize;
+        items.truncate(limit as usize);
+        let next_cursor = if has_more {
+            items.last().map(|message| message.id)
+        } else {
+            None
+        };
+
+        Ok(CursorPage {
+            items,
+            next_cursor,
+            has_more,
+        })
+    }
+
     /// 获取指定时间之后的消息（用于用户重连）
     async fn find_since_timestamp(
         &self,
@@ -179,6 +413,52 @@ pub trait MessageRepository: Send + Sync {
         timestamp: chrono::DateTime<chrono::Utc>,
     ) -> Result<Vec<Message>, RepositoryError>;
 
+    /// 获取序列号之后的消息（按序列号升序，用于断线补偿）
+    ///
+    /// 序列号由数据库分配，不受各实例时钟偏差影响
+    async fn find_after_sequence(
+        &self,
+        room_id: RoomId,
+        after_seq: i64,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError>;
+
+    /// 统计房间内某条消息之后（按序列号）的未删除消息数；None 表示统计全部
+    async fn count_after(
+        &self,
+        room_id: RoomId,
+        message_id: Option<MessageId>,
+    ) -> Result<i64, RepositoryError>;
+
+    /// 统计成员未读消息数：序列号大于其已读消息的未删除消息
+    ///
+    /// 从未标记已读的成员，房间内所有消息都算未读
+    async fn count_unread_for_member(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<i64, RepositoryError>;
+
+    /// 房间内全文检索，按 ts_rank 降序排列，排除已删除消息
+    async fn search_in_room(
+        &self,
+        room_id: RoomId,
+        query: &str,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError>;
+
+    /// 线程中的全部回复（按序列号升序），排除已删除的回复
+    ///
+    /// 父消息被删除的回复仍然返回，并标记 `parent_deleted`
+    async fn find_thread(
+        &self,
+        root_id: MessageId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<ThreadMessage>, RepositoryError>;
+
+    /// 线程中未删除的回复数
+    async fn thread_reply_count(&self, root_id: MessageId) -> Result<i64, RepositoryError>;
+
     /// 根据时间范围查询消息（管理员功能）
     async fn find_by_time_range(
         &self,
@@ -187,16 +467,76 @@ pub trait MessageRepository: Send + Sync {
         pagination: PaginationParams,
     ) -> Result<Vec<Message>, RepositoryError>;
 
-    /// 更新消息内容（编辑功能）
-    async fn update(&self, message: Message) -> Result<(), RepositoryError>;
+    /// 按序列号升序逐行读出房间在时间范围内的全部消息（含已删除的消息），用于导出聊天记录
+    ///
+    /// `from` 含，`to` 不含；返回的流在读取过程中一直占用一个数据库连接
+    async fn stream_by_time_range(
+        &self,
+        room_id: RoomId,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    ) -> Result<ExportedMessageStream, RepositoryError>;
+
+    /// 更新消息内容（编辑功能），版本号加一
+    ///
+    /// 数据库中的版本不等于 `expected_version`（已被别人编辑过）时返回 `RepositoryError::Conflict`
+    async fn update(&self, message: Message, expected_version: i32) -> Result<(), RepositoryError>;
+
+    /// 消息的全部编辑历史（每次编辑前的内容），按编辑先后排列
+    async fn revisions(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageRevision>, RepositoryError>;
+
+    /// 软删除消息（幂等：已删除的消息再次删除视为成功，不存在返回 NotFound）
+    async fn soft_delete(&self, id: MessageId) -> Result<(), RepositoryError>;
+
+    /// 批量软删除房间内的消息，一条语句原子完成
+    ///
+    /// 不属于该房间的ID和已删除的消息被忽略，返回本次实际删除的消息ID
+    async fn soft_delete_many(
+        &self,
+        room_id: RoomId,
+        ids: &[MessageId],
+    ) -> Result<Vec<MessageId>, RepositoryError>;
+
+    /// 软删除某个用户在所有房间的一批消息（最多 `limit` 条，单条语句即一个事务）
+    ///
+    /// 返回本批实际删除的 (房间, 消息)；返回条数小于 `limit` 表示已经删完
+    async fn soft_delete_batch_by_sender(
+        &self,
+        sender_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError>;
+
+    /// 恢复软删除的消息；只有当前处于删除状态时才会修改，返回是否恢复
+    async fn restore(&self, id: MessageId) -> Result<bool, RepositoryError>;
+
+    /// 物理删除早于 `cutoff` 的消息（含已软删除的），返回删除条数
+    ///
+    /// 投递记录、回应、提及随外键级联删除；引用被删消息的回复保留，引用置空
+    async fn purge_older_than(&self, cutoff: Timestamp) -> Result<u64, RepositoryError>;
+
+    /// 置顶房间内的消息；已经置顶时不变并返回 false
+    ///
+    /// 消息不存在、已删除或不属于该房间时返回 NotFound
+    async fn pin(
+        &self,
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: Timestamp,
+    ) -> Result<bool, RepositoryError>;
+
+    /// 取消置顶，返回是否确实取消了
+    async fn unpin(&self, room_id: RoomId, message_id: MessageId) -> Result<bool, RepositoryError>;
 
-    /// 软删除消息
+    /// 房间的置顶消息，最近置顶的在前；已删除的消息不列出
+    async fn list_pinned(&self, room_id: RoomId) -> Result<Vec<PinnedMessage>, RepositoryError>;
+
+    /// @deprecated 使用 soft_delete 替代
     async fn delete(&self, id: MessageId) -> Result<(), RepositoryError> {
-        // 默认实现：不支持删除消息
-        let _ = id;
-        Err(RepositoryError::storage(
-            "Message deletion not supported".to_string(),
-        ))
+        self.soft_delete(id).await
     }
 
     // === 向后兼容的方法别名 ===
@@ -263,6 +603,11 @@ pub trait MessageDeliveryRepository: Send + Sync {
     /// 记录消息发送状态
     async fn record_sent(&self, delivery: MessageDelivery) -> Result<(), RepositoryError>;
 
+    /// 为房间内除发送者外的所有成员登记待送达记录，返回登记条数
+    ///
+    /// 离线成员也有记录，重新连接时据此补发
+    async fn record_sent_to_room(&self, message: &Message) -> Result<u64, RepositoryError>;
+
     /// 标记消息已送达
     async fn mark_delivered(
         &self,
@@ -271,10 +616,11 @@ pub trait MessageDeliveryRepository: Send + Sync {
         delivered_at: chrono::DateTime<chrono::Utc>,
     ) -> Result<(), RepositoryError>;
 
-    /// 查找用户的未送达消息
+    /// 查找用户最近 `limit` 条未送达消息，按发送时间先后排列
     async fn find_undelivered_for_user(
         &self,
         user_id: UserId,
+        limit: u32,
     ) -> Result<Vec<MessageDelivery>, RepositoryError>;
 
     /// 查找特定消息的传递状态
@@ -338,3 +684,389 @@ pub trait OrganizationRepository: Send + Sync {
         params: PaginationParams,
     ) -> Result<Vec<Organization>, RepositoryError>;
 }
+
+/// 刷新令牌Repository
+/// 对应数据库表：refresh_tokens
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    /// 保存新签发的刷新令牌
+    async fn create(&self, token: RefreshToken) -> Result<(), RepositoryError>;
+
+    /// 根据jti查找刷新令牌
+    async fn find_by_jti(&self, jti: Uuid) -> Result<Option<RefreshToken>, RepositoryError>;
+
+    /// 原子轮换：吊销旧令牌并保存新令牌
+    ///
+    /// 旧令牌已被吊销（并发重放）时返回 `RepositoryError::Conflict`
+    async fn rotate(&self, old_jti: Uuid, new_token: RefreshToken) -> Result<(), RepositoryError>;
+
+    /// 吊销整个令牌家族（检测到重放时调用）
+    async fn revoke_family(&self, family_id: Uuid) -> Result<u64, RepositoryError>;
+
+    /// 吊销用户所有未吊销的刷新令牌（修改密码后退出全部会话）
+    async fn revoke_all_for_user(&self, user_id: UserId) -> Result<u64, RepositoryError>;
+}
+
+#[async_trait]
+pub trait EmailVerificationRepository: Send + Sync {
+    /// 保存新签发的验证令牌
+    async fn create(&self, verification: EmailVerification) -> Result<(), RepositoryError>;
+
+    /// 根据令牌哈希查找
+    async fn find_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<EmailVerification>, RepositoryError>;
+
+    /// 原子地把令牌标记为已使用；已被使用过时返回 false
+    async fn consume(&self, token_hash: &str, now: Timestamp) -> Result<bool, RepositoryError>;
+
+    /// 用户最近一次签发的验证令牌（用于重发限流）
+    async fn find_latest_for_user(
+        &self,
+        user_id: UserId,
+    ) -> Result<Option<EmailVerification>, RepositoryError>;
+}
+
+#[async_trait]
+pub trait PasswordResetRepository: Send + Sync {
+    /// 保存新签发的重置令牌
+    async fn create(&self, reset: PasswordReset) -> Result<(), RepositoryError>;
+
+    /// 根据令牌哈希查找
+    async fn find_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<PasswordReset>, RepositoryError>;
+
+    /// 原子地把令牌标记为已使用；已被使用过时返回 false
+    async fn consume(&self, token_hash: &str, now: Timestamp) -> Result<bool, RepositoryError>;
+}
+
+#[async_trait]
+pub trait ReactionRepository: Send + Sync {
+    /// 添加回应（幂等），返回是否真的新增了记录
+    async fn add_reaction(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        emoji: &ReactionEmoji,
+    ) -> Result<bool, RepositoryError>;
+
+    /// 移除回应，返回是否真的删除了记录；不存在时不报错
+    async fn remove_reaction(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        emoji: &ReactionEmoji,
+    ) -> Result<bool, RepositoryError>;
+
+    /// 查询用户在某条消息上已有的回应
+    async fn find_user_reactions(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+    ) -> Result<Vec<ReactionEmoji>, RepositoryError>;
+
+    /// 批量查询消息的回应聚合，没有回应的消息不出现在结果中
+    async fn reactions_for_messages(
+        &self,
+        message_ids: &[MessageId],
+    ) -> Result<HashMap<MessageId, Vec<ReactionSummary>>, RepositoryError>;
+
+    /// 删除某个用户的一批回应（最多 `limit` 条），每条删除的回应返回其消息ID
+    ///
+    /// 返回条数小于 `limit` 表示已经删完
+    async fn remove_batch_by_user(
+        &self,
+        user_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<MessageId>, RepositoryError>;
+}
+
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    /// 追加一条审计记录
+    async fn record(&self, entry: AuditLogEntry) -> Result<(), RepositoryError>;
+
+    /// 针对某个用户的管理操作记录，最近的在前
+    async fn find_by_target_user(
+        &self,
+        user_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<AuditLogEntry>, RepositoryError>;
+
+    /// 针对某个房间的某类操作记录，按时间先后排列
+    async fn find_by_target_room(
+        &self,
+        room_id: RoomId,
+        action: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError>;
+
+    /// 按条件检索审计记录，最近的在前
+    async fn search(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError>;
+}
+
+/// 用户通知
+/// 对应数据库表：notifications
+#[async_trait]
+pub trait NotificationRepository: Send + Sync {
+    /// 批量保存通知
+    async fn create_many(&self, notifications: &[Notification]) -> Result<(), RepositoryError>;
+
+    /// 分页列出用户的通知，最新的在前
+    async fn find_for_user(
+        &self,
+        user_id: UserId,
+        unread_only: bool,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<Notification>, RepositoryError>;
+
+    /// 用户自己的一条通知；别人的通知和不存在的一样返回 None
+    async fn find_for_user_by_id(
+        &self,
+        user_id: UserId,
+        id: Uuid,
+    ) -> Result<Option<Notification>, RepositoryError>;
+
+    /// 把用户的通知标为已读，`ids` 为空时标记全部；返回本次标记的条数
+    async fn mark_read(
+        &self,
+        user_id: UserId,
+        ids: &[Uuid],
+        now: Timestamp,
+    ) -> Result<u64, RepositoryError>;
+
+    /// 删除用户自己的一条通知，返回是否删除
+    async fn delete(&self, user_id: UserId, id: Uuid) -> Result<bool, RepositoryError>;
+
+    /// 用户的未读通知数
+    async fn count_unread(&self, user_id: UserId) -> Result<i64, RepositoryError>;
+}
+
+/// 发送消息的幂等键，按用户隔离
+/// 对应数据库表：message_idempotency_keys
+#[async_trait]
+pub trait IdempotencyKeyRepository: Send + Sync {
+    /// 未过期的键对应的消息
+    async fn find(
+        &self,
+        user_id: UserId,
+        key: &str,
+        now: Timestamp,
+    ) -> Result<Option<MessageId>, RepositoryError>;
+
+    /// 占用键并关联到 `message_id`；键已被占用且未过期时不变
+    ///
+    /// 返回键当前关联的消息，等于 `message_id` 表示占用成功
+    async fn claim(
+        &self,
+        user_id: UserId,
+        key: &str,
+        message_id: MessageId,
+        now: Timestamp,
+        expires_at: Timestamp,
+    ) -> Result<MessageId, RepositoryError>;
+
+    /// 消息没有发出去时释放键，客户端重试时可以重新占用
+    async fn release(
+        &self,
+        user_id: UserId,
+        key: &str,
+        message_id: MessageId,
+    ) -> Result<(), RepositoryError>;
+
+    /// 删除已过期的键，返回删除条数
+    async fn delete_expired(&self, now: Timestamp) -> Result<u64, RepositoryError>;
+}
+
+/// 消息广播发件箱
+/// 对应数据库表：message_outbox
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    /// 领取最多 `limit` 条到期的待发布记录，按写入顺序返回
+    ///
+    /// 领取时尝试次数加一，并把下次可尝试时间推迟到 `lease_until`：多个实例同时领取时互不重复，
+    /// 领取后进程退出的记录在租约到期后被重新领取
+    async fn claim_due(
+        &self,
+        now: Timestamp,
+        lease_until: Timestamp,
+        limit: i64,
+    ) -> Result<Vec<OutboxEntry>, RepositoryError>;
+
+    /// 发布成功
+    async fn mark_dispatched(&self, id: i64, now: Timestamp) -> Result<(), RepositoryError>;
+
+    /// 发布失败：`retry_at` 为 None 时进入死信，否则等到 `retry_at` 再重试
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<Timestamp>,
+    ) -> Result<(), RepositoryError>;
+
+    /// 分页列出死信记录，最早的在前
+    async fn list_dead_letters(
+        &self,
+        pagination: PaginationParams,
+    ) -> Result<Vec<OutboxEntry>, RepositoryError>;
+
+    /// 死信重新入队并清零尝试次数；记录不存在或不是死信时返回 None
+    async fn requeue(
+        &self,
+        id: i64,
+        now: Timestamp,
+    ) -> Result<Option<OutboxEntry>, RepositoryError>;
+
+    /// 删除发布时间早于 `before` 的记录，返回删除条数
+    async fn delete_dispatched_before(&self, before: Timestamp) -> Result<u64, RepositoryError>;
+}
+
+/// 入房邀请
+/// 对应数据库表：room_invitations
+#[async_trait]
+pub trait RoomInvitationRepository: Send + Sync {
+    /// 保存新邀请；同一用户在同一房间已有未过期的待处理邀请时不保存，返回 false
+    async fn create(&self, invitation: &RoomInvitation) -> Result<bool, RepositoryError>;
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<RoomInvitation>, RepositoryError>;
+
+    /// 用户收到的、尚未过期的待处理邀请，新的在前
+    async fn list_pending_for_user(
+        &self,
+        user_id: UserId,
+        now: Timestamp,
+    ) -> Result<Vec<RoomInvitation>, RepositoryError>;
+
+    /// 在同一事务里接受邀请并加入房间；邀请已不是待处理或已过期时返回 None
+    ///
+    /// 被邀请人已经是成员时保留原有角色
+    async fn accept(
+        &self,
+        id: Uuid,
+        member: RoomMember,
+        now: Timestamp,
+    ) -> Result<Option<RoomInvitation>, RepositoryError>;
+
+    /// 拒绝或撤回待处理的邀请；邀请已不是待处理时返回 None
+    async fn resolve(
+        &self,
+        id: Uuid,
+        status: InvitationStatus,
+        now: Timestamp,
+    ) -> Result<Option<RoomInvitation>, RepositoryError>;
+}
+
+/// 入房邀请链接
+/// 对应数据库表：invites
+#[async_trait]
+pub trait RoomInviteRepository: Send + Sync {
+    async fn create(&self, invite: &RoomInvite) -> Result<(), RepositoryError>;
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<RoomInvite>, RepositoryError>;
+
+    /// 在同一事务里加入房间并占用一次使用次数；链接已过期或次数已用完时返回 None
+    ///
+    /// 用户已经是成员时返回 `RepositoryError::Conflict`，不占用次数
+    async fn redeem(
+        &self,
+        token: &str,
+        member: RoomMember,
+        now: Timestamp,
+    ) -> Result<Option<RoomInvite>, RepositoryError>;
+}
+
+/// 上传文件的元数据
+/// 对应数据库表：file_uploads
+#[async_trait]
+pub trait FileUploadRepository: Send + Sync {
+    /// 保存上传文件的元数据
+    async fn create(&self, upload: FileUpload) -> Result<FileUpload, RepositoryError>;
+
+    /// 用户自己上传、还没有被消息引用的文件
+    async fn find_unattached(
+        &self,
+        id: Uuid,
+        uploader_id: UserId,
+    ) -> Result<Option<FileUpload>, RepositoryError>;
+
+    /// 把文件关联到消息；只有上传者本人发送、且文件还没被引用时才关联，返回是否关联
+    async fn attach(
+        &self,
+        id: Uuid,
+        uploader_id: UserId,
+        message_id: MessageId,
+    ) -> Result<bool, RepositoryError>;
+
+    /// 用户能查看的文件：自己上传的，或者引用它的消息（未删除）所在房间的成员
+    async fn find_visible(
+        &self,
+        id: Uuid,
+        viewer_id: UserId,
+    ) -> Result<Option<FileUpload>, RepositoryError>;
+
+    /// 删除早于 `before` 上传、仍未被引用的文件元数据（最多 `limit` 条），返回删除的记录
+    ///
+    /// 返回条数小于 `limit` 表示已经删完
+    async fn delete_orphans(
+        &self,
+        before: Timestamp,
+        limit: i64,
+    ) -> Result<Vec<FileUpload>, RepositoryError>;
+}
+
+#[async_trait]
+pub trait UserBlockRepository: Send + Sync {
+    /// 屏蔽用户（幂等），返回是否真的新增了记录
+    async fn block(&self, blocker: UserId, blocked: UserId) -> Result<bool, RepositoryError>;
+
+    /// 取消屏蔽，返回是否真的删除了记录；不存在时不报错
+    async fn unblock(&self, blocker: UserId, blocked: UserId) -> Result<bool, RepositoryError>;
+
+    /// 屏蔽者屏蔽的全部用户ID（用于消息过滤）
+    async fn blocked_ids(&self, blocker: UserId) -> Result<HashSet<UserId>, RepositoryError>;
+
+    /// 分页列出被屏蔽用户的公开资料，最近屏蔽的在前
+    async fn list_blocked(
+        &self,
+        blocker: UserId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<UserProfile>, RepositoryError>;
+}
+
+/// @提及收件箱
+/// 对应数据库表：message_mentions
+#[async_trait]
+pub trait MentionRepository: Send + Sync {
+    /// 记录消息中的提及，返回实际记录的用户
+    ///
+    /// 只记录当前仍是房间成员的用户（按用户名不区分大小写匹配），发送者自己不记录
+    async fn record_mentions(
+        &self,
+        message: &Message,
+        usernames: &[String],
+    ) -> Result<Vec<UserId>, RepositoryError>;
+
+    /// 记录 `@everyone`：提及房间当前的所有成员（发送者除外），返回新记录的用户
+    ///
+    /// 已经按用户名记录过的成员不会重复返回
+    async fn record_everyone_mention(
+        &self,
+        message: &Message,
+    ) -> Result<Vec<UserId>, RepositoryError>;
+
+    /// 分页列出提及该用户的消息，最新的在前；已退出房间和已删除的消息不返回
+    async fn find_for_user(
+        &self,
+        user_id: UserId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<MentionedMessage>, RepositoryError>;
+}
@@ -1,10 +1,16 @@
 use domain::{MessageId, RoomId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use time::OffsetDateTime;
 
+use crate::clock::{Clock, SystemClock};
+use crate::repository::{MessageRepository, PaginationParams};
+
 /// 带序列号的消息
 /// 每个房间维护递增序列号，确保消息有序且不重复
+///
+/// 排序只看 `sequence_id`；`timestamp` 来自本实例时钟，仅用于展示
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SequencedMessage {
     pub sequence_id: u64,
@@ -18,12 +24,31 @@ pub struct SequencedMessage {
 pub struct MessageSequencer {
     /// Redis客户端
     redis_client: Arc<redis::Client>,
+    /// 展示时间戳的时钟来源
+    clock: Arc<dyn Clock>,
+    /// 消息存储；Redis 中没有房间计数（首次使用或 Redis 重启）时从库中最大序列号续上
+    storage: Option<Arc<dyn MessageRepository>>,
 }
 
 impl MessageSequencer {
     /// 创建新的Redis-based序列化器
     pub fn new(redis_client: Arc<redis::Client>) -> Self {
-        Self { redis_client }
+        Self::with_clock(redis_client, Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建序列化器（时钟只影响展示时间戳，不影响顺序）
+    pub fn with_clock(redis_client: Arc<redis::Client>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            redis_client,
+            clock,
+            storage: None,
+        }
+    }
+
+    /// 计数从消息存储续上，重启后分配的序列号不会回退到已落库的序列号之下
+    pub fn with_storage(mut self, storage: Arc<dyn MessageRepository>) -> Self {
+        self.storage = Some(storage);
+        self
     }
 
     /// 获取Redis连接
@@ -54,6 +79,8 @@ impl MessageSequencer {
         let mut conn = self.get_connection().await?;
         let room_key = self.room_sequence_key(room_id);
         let processed_key = self.processed_messages_key();
+        self.seed_from_storage(&mut conn, room_id, &room_key)
+            .await?;
 
         // 使用Lua脚本确保原子性：去重 + 序列号分配
         let script = redis::Script::new(
@@ -94,7 +121,7 @@ impl MessageSequencer {
             sequence_id,
             room_id,
             message_id,
-            timestamp: OffsetDateTime::now_utc(),
+            timestamp: self.clock.now(),
         })
     }
 
@@ -113,10 +140,19 @@ impl MessageSequencer {
         Ok(exists)
     }
 
+    /// 房间下一条消息应有的序列号
+    ///
+    /// 客户端收到的序列号大于它时说明中间有缺口，应从它开始请求重放
+    pub async fn expected_next(&self, room_id: RoomId) -> Result<u64, String> {
+        Ok(self.get_room_sequence(room_id).await? + 1)
+    }
+
     /// 获取房间的当前序列号
     pub async fn get_room_sequence(&self, room_id: RoomId) -> Result<u64, String> {
         let mut conn = self.get_connection().await?;
         let room_key = self.room_sequence_key(room_id);
+        self.seed_from_storage(&mut conn, room_id, &room_key)
+            .await?;
 
         let sequence: i64 = redis::cmd("GET")
             .arg(&room_key)
@@ -126,6 +162,152 @@ impl MessageSequencer {
 
         Ok(sequence as u64)
     }
+
+    /// Redis 中没有房间计数时用库中最大序列号初始化
+    ///
+    /// 用 `SET NX` 写入，多个实例同时初始化时只有第一个生效，不会覆盖已经递增过的计数
+    async fn seed_from_storage(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        room_id: RoomId,
+        room_key: &str,
+    ) -> Result<(), String> {
+        let Some(storage) = &self.storage else {
+            return Ok(());
+        };
+
+        let exists: bool = redis::cmd("EXISTS")
+            .arg(room_key)
+            .query_async(conn)
+            .await
+            .map_err(|e| format!("Redis EXISTS failed: {}", e))?;
+        if exists {
+            return Ok(());
+        }
+
+        let latest = storage
+            .find_recent_by_room(room_id, PaginationParams::new(1), None)
+            .await
+            .map_err(|e| format!("Failed to load latest sequence: {}", e))?;
+        let latest_seq = latest.first().map_or(0, |message| message.seq.max(0));
+
+        let _: Option<String> = redis::cmd("SET")
+            .arg(room_key)
+            .arg(latest_seq)
+            .arg("NX")
+            .query_async(conn)
+            .await
+            .map_err(|e| format!("Redis SET NX failed: {}", e))?;
+        Ok(())
+    }
+}
+
+/// 客户端收到一条消息后的序列号检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// 正是期望的下一条
+    InOrder,
+    /// 已经收到过，丢弃即可
+    Duplicate,
+    /// 中间缺了消息，应从 `from_seq` 开始请求重放
+    Gap { from_seq: u64 },
+}
+
+/// 按房间记录期望的下一个序列号，用于发现缺口
+///
+/// 出现缺口时不前移期望值：重放补齐后用 [`SequenceTracker::skip_to`] 跳到重放返回的下一个序列号
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    expected: HashMap<RoomId, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从已知位置开始跟踪房间，`next_seq` 通常来自 [`MessageSequencer::expected_next`]
+    pub fn starting_at(&mut self, room_id: RoomId, next_seq: u64) {
+        self.expected.insert(room_id, next_seq);
+    }
+
+    /// 房间期望的下一个序列号；尚未跟踪的房间返回 `None`
+    pub fn expected_next(&self, room_id: RoomId) -> Option<u64> {
+        self.expected.get(&room_id).copied()
+    }
+
+    /// 检查收到的序列号；按序到达时期望值前移
+    ///
+    /// 尚未跟踪的房间以第一条消息为起点
+    pub fn observe(&mut self, room_id: RoomId, sequence_id: u64) -> SequenceCheck {
+        let expected = self.expected.entry(room_id).or_insert(sequence_id);
+        if sequence_id < *expected {
+            SequenceCheck::Duplicate
+        } else if sequence_id == *expected {
+            *expected += 1;
+            SequenceCheck::InOrder
+        } else {
+            SequenceCheck::Gap {
+                from_seq: *expected,
+            }
+        }
+    }
+
+    /// 重放补齐后跳到 `next_seq`；不会让期望值回退
+    pub fn skip_to(&mut self, room_id: RoomId, next_seq: u64) {
+        let expected = self.expected.entry(room_id).or_insert(next_seq);
+        *expected = (*expected).max(next_seq);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_tracker_reports_gap_until_resynced() {
+        let room_id = RoomId::from(Uuid::new_v4());
+        let mut tracker = SequenceTracker::new();
+        tracker.starting_at(room_id, 5);
+
+        assert_eq!(tracker.observe(room_id, 5), SequenceCheck::InOrder);
+        assert_eq!(tracker.observe(room_id, 5), SequenceCheck::Duplicate);
+        // 7 先到：缺了 6，期望值不前移
+        assert_eq!(
+            tracker.observe(room_id, 7),
+            SequenceCheck::Gap { from_seq: 6 }
+        );
+        assert_eq!(
+            tracker.observe(room_id, 8),
+            SequenceCheck::Gap { from_seq: 6 }
+        );
+        assert_eq!(tracker.expected_next(room_id), Some(6));
+
+        // 重放补齐 6..=8 后从 9 继续
+        tracker.skip_to(room_id, 9);
+        assert_eq!(tracker.observe(room_id, 8), SequenceCheck::Duplicate);
+        assert_eq!(tracker.observe(room_id, 9), SequenceCheck::InOrder);
+        tracker.skip_to(room_id, 3);
+        assert_eq!(tracker.expected_next(room_id), Some(10));
+    }
+
+    #[test]
+    fn test_tracker_keeps_rooms_independent() {
+        let first = RoomId::from(Uuid::new_v4());
+        let second = RoomId::from(Uuid::new_v4());
+        let mut tracker = SequenceTracker::new();
+
+        assert_eq!(tracker.observe(first, 3), SequenceCheck::InOrder);
+        assert_eq!(tracker.observe(second, 1), SequenceCheck::InOrder);
+        assert_eq!(tracker.observe(first, 4), SequenceCheck::InOrder);
+        assert_eq!(
+            tracker.observe(second, 3),
+            SequenceCheck::Gap { from_seq: 2 }
+        );
+        assert_eq!(tracker.expected_next(first), Some(5));
+        assert_eq!(tracker.expected_next(second), Some(2));
+    }
 }
 
 // 移除Default实现，因为需要Redis客户端
@@ -1,4 +1,4 @@
-use domain::{OrgId, RepositoryError, UserEmail, UserId, Username};
+use domain::{AuditLogEntry, OrgId, RepositoryError, UserEmail, UserId, Username};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -45,7 +45,8 @@ impl BulkUserService {
             completed_at: None,
         };
 
-        // 插入任务记录
+        // 任务记录和审计记录一起提交
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
         sqlx::query(
             r#"
             INSERT INTO bulk_tasks (
@@ -65,10 +66,37 @@ impl BulkUserService {
         .bind(task.success_count)
         .bind(task.failed_count)
         .bind(task.created_at)
-        .execute(self.pool.as_ref())
+        .execute(&mut *tx)
         .await
         .map_err(map_sqlx_err)?;
 
+        let audit = AuditLogEntry::record(
+            request.created_by,
+            AuditLogEntry::USERS_BULK_CREATED,
+            None,
+            serde_json::json!({
+                "task_id": task.id,
+                "org_id": request.org_id,
+                "user_count": task.total_count,
+            }),
+            now,
+        );
+        sqlx::query(
+            r#"
+            INSERT INTO admin_audit_log (id, actor_id, action, target_user_id, target_room_id, details, created_at)
+            VALUES ($1, $2, $3, NULL, NULL, $4, $5)
+            "#,
+        )
+        .bind(audit.id)
+        .bind(Uuid::from(audit.actor_id))
+        .bind(&audit.action)
+        .bind(&audit.details)
+        .bind(audit.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+
         // 启动异步任务处理
         let pool = self.pool.clone();
         let task_id_clone = task_id;
@@ -1,17 +1,36 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 
+use config::MessageConfig;
 use domain::{
-    self, ChatRoom, ChatRoomVisibility, DomainError, Message, MessageContent, MessageId,
-    MessageType, RoomId, RoomMember, RoomRole, UserId,
+    self, extract_mentions, AuditLogEntry, ChatRoom, ChatRoomVisibility, DomainError, FileUpload,
+    InvitationStatus, MemberRoom, MentionedMessage, Message, MessageContent, MessageContentLimits,
+    MessageId, MessageRevision, MessageType, Notification, PinnedMessage, ReactionEmoji,
+    ReactionPolicy, ReactionSummary, RepositoryError, RoomBan, RoomId, RoomInvitation, RoomInvite,
+    RoomMember, RoomMemberListing, RoomMemberRestriction, RoomRole, RoomSettingDiff, RoomSummary,
+    ThreadMessage, Timestamp, User, UserId, UserProfile, UserStatus, EVERYONE_MENTION,
 };
+use rand::Rng;
 use uuid::Uuid;
 
 use crate::{
+    audit::AuditLogger,
     broadcaster::{MessageBroadcast, MessageBroadcaster},
     clock::Clock,
+    encryption::RoomEncryption,
     error::ApplicationError,
+    idempotency::validate_idempotency_key,
     password::PasswordHasher,
-    repository::{ChatRoomRepository, MessageRepository, RoomMemberRepository, UserRepository},
+    rate_limiter::SendRateLimiter,
+    repository::{
+        AuditLogQuery, AuditLogRepository, ChatRoomRepository, CursorPage, ExportedMessageStream,
+        FileUploadRepository, IdempotencyKeyRepository, MentionRepository,
+        MessageDeliveryRepository, MessageRepository, NotificationRepository, PaginatedResult,
+        PaginationParams, PublicRoomQuery, ReactionRepository, RoomInvitationRepository,
+        RoomInviteRepository, RoomMemberRepository, UserBlockRepository, UserRepository,
+    },
+    shutdown::ShutdownBarrier,
 };
 
 // 删除了垃圾的TransactionManager trait - 过度抽象的典型例子
@@ -30,6 +49,13 @@ pub struct LeaveRoomRequest {
     pub user_id: Uuid,
 }
 
+#[derive(Debug, Clone)]
+pub struct JoinRoomRequest {
+    pub room_id: Uuid,
+    pub user_id: Uuid,
+    pub password: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct InviteMemberRequest {
     pub room_id: Uuid,
@@ -38,6 +64,31 @@ pub struct InviteMemberRequest {
     pub password: Option<String>,
 }
 
+#[derive(Debug, Clone)]
+pub struct CreateInvitationRequest {
+    pub room_id: Uuid,
+    pub inviter_id: Uuid, // 邀请人（从JWT获取）
+    pub invitee_id: Uuid, // 被邀请人
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateInviteRequest {
+    pub room_id: Uuid,
+    pub creator_id: Uuid, // 创建人（从JWT获取）
+    /// 最多可使用的次数，为空表示不限
+    pub max_uses: Option<u32>,
+    /// 多少秒后过期，为空表示永不过期
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateMemberRoleRequest {
+    pub room_id: Uuid,
+    pub operator_id: Uuid,    // 操作者（从JWT获取）
+    pub target_user_id: Uuid, // 被调整角色的用户
+    pub role: RoomRole,       // 只能是 Admin 或 Member
+}
+
 #[derive(Debug, Clone)]
 pub struct RemoveMemberRequest {
     pub room_id: Uuid,
@@ -45,6 +96,43 @@ pub struct RemoveMemberRequest {
     pub target_user_id: Uuid, // 被踢的用户
 }
 
+/// 单次禁言的最长时长（365 天）
+const MAX_MUTE_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// 封禁原因的最大长度（字符数）
+const MAX_BAN_REASON_CHARS: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct MuteMemberRequest {
+    pub room_id: Uuid,
+    pub operator_id: Uuid,    // 操作者（从JWT获取）
+    pub target_user_id: Uuid, // 被禁言的用户
+    pub duration_secs: u64,
+}
+
+/// 解除禁言、封禁、解除封禁共用的请求
+#[derive(Debug, Clone)]
+pub struct MemberRestrictionRequest {
+    pub room_id: Uuid,
+    pub operator_id: Uuid,    // 操作者（从JWT获取）
+    pub target_user_id: Uuid, // 被限制的用户
+}
+
+#[derive(Debug, Clone)]
+pub struct BanMemberRequest {
+    pub room_id: Uuid,
+    pub operator_id: Uuid,    // 操作者（从JWT获取）
+    pub target_user_id: Uuid, // 被封禁的用户
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferOwnershipRequest {
+    pub room_id: Uuid,
+    pub operator_id: Uuid,  // 当前 Owner（从JWT获取）
+    pub new_owner_id: Uuid, // 接手的成员
+}
+
 #[derive(Debug, Clone)]
 pub struct UpdateRoomRequest {
     pub room_id: Uuid,
@@ -52,6 +140,7 @@ pub struct UpdateRoomRequest {
     pub name: Option<String>,
     pub visibility: Option<ChatRoomVisibility>,
     pub password: Option<String>,
+    pub reaction_policy: Option<ReactionPolicy>,
 }
 
 #[derive(Debug, Clone)]
@@ -67,26 +156,238 @@ pub struct SendMessageRequest {
     pub content: String,
     pub message_type: MessageType,
     pub reply_to: Option<Uuid>,
+    /// 客户端提供的幂等键，重试时带同一个键不会产生重复消息
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EditMessageRequest {
+    pub room_id: Uuid,
+    pub message_id: Uuid,
+    pub operator_id: Uuid, // 操作者（从JWT获取）
+    pub content: String,
+    /// 客户端读到的消息版本；不传则以服务端刚读到的版本为准
+    pub expected_version: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResumeRoomRequest {
+    pub room_id: Uuid,
+    pub user_id: Uuid, // 从JWT获取
+    /// 客户端已收到的最后一个序列号（从未收到过传 0）
+    pub last_seq: i64,
+    /// 本次最多补发的消息条数
+    pub limit: u32,
+}
+
+/// 断线重连时的房间快照：错过的消息、未读数、当前房间设置和成员角色
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RoomResumeState {
+    pub room: ChatRoom,
+    pub role: RoomRole,
+    /// 序列号大于 last_seq 的消息，按序列号升序
+    pub missed_messages: Vec<Message>,
+    /// 超出 limit 时为 true，客户端应继续用 last_seq 分页补齐
+    pub has_more: bool,
+    pub unread_count: i64,
+}
+
+/// 客户端发现序列号缺口后请求的重放结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SequenceReplay {
+    /// 序列号不小于 from_seq 的消息，按序列号升序
+    pub messages: Vec<Message>,
+    /// 超出 limit 时为 true，客户端应从 next_seq 继续请求
+    pub has_more: bool,
+    /// 本次重放覆盖到的下一个序列号；已删除或被屏蔽的消息不返回，
+    /// 客户端应直接把期望的序列号设为它，而不是把这些空缺当成丢帧
+    pub next_seq: i64,
+}
+
+/// WebSocket 重连时的补发结果
+#[derive(Debug, Clone)]
+pub enum ReconnectBackfill {
+    /// `last_message_id` 之后的消息，按序列号升序
+    Messages(Vec<Message>),
+    /// 缺口超过补发上限，或找不到 `last_message_id`，客户端需要全量重新同步
+    ResyncRequired { missed: Option<i64> },
+}
+
+/// 消息线程：根消息下的回复及未删除回复总数
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MessageThread {
+    pub root_id: MessageId,
+    pub replies: Vec<ThreadMessage>,
+    pub reply_count: i64,
+}
+
+/// 单个房间成员的详情：角色、加入时间、已读位置和公开资料
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RoomMemberDetails {
+    pub user: UserProfile,
+    pub role: RoomRole,
+    pub joined_at: domain::Timestamp,
+    pub last_read_message_id: Option<MessageId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkReadRequest {
+    pub room_id: Uuid,
+    pub user_id: Uuid, // 从JWT获取
+    pub message_id: Uuid,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkRoomsReadRequest {
+    pub user_id: Uuid, // 从JWT获取
+    /// 要标记的房间，`None` 表示用户所在的全部房间
+    pub room_ids: Option<Vec<Uuid>>,
+}
+
+/// 单次批量删除最多接受的消息数
+const MAX_BULK_DELETE: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct BulkDeleteMessagesRequest {
+    pub room_id: Uuid,
+    pub message_ids: Vec<Uuid>,
+    pub operator_id: Uuid, // 操作者（从JWT获取）
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteMessageRequest {
+    pub room_id: Uuid,
+    pub message_id: Uuid,
+    pub operator_id: Uuid, // 操作者（从JWT获取）
+}
+
+#[derive(Debug, Clone)]
+pub struct RestoreMessageRequest {
+    pub message_id: Uuid,
+    pub operator_id: Uuid, // 操作者（从JWT获取）
+}
+
+#[derive(Debug, Clone)]
+pub struct PinMessageRequest {
+    pub room_id: Uuid,
+    pub message_id: Uuid,
+    pub operator_id: Uuid, // 操作者（从JWT获取）
+}
+
+/// 清除用户内容时每批处理的条数，每批一个事务，避免长时间锁表
+const PURGE_BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone)]
+pub struct PurgeUserContentRequest {
+    pub operator_id: Uuid, // 操作者（从JWT获取，必须是系统管理员）
+    pub user_id: Uuid,
+    /// 同时移出所有房间并停用账号
+    pub ban: bool,
+}
+
+/// 清除用户内容的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PurgeUserContentReport {
+    pub messages_deleted: u64,
+    pub reactions_removed: u64,
+    /// 有消息被删除的房间数
+    pub rooms_affected: u64,
+    /// 被移出的房间数（只在 ban 时非零）
+    pub rooms_left: u64,
+    pub banned: bool,
+}
+
+/// 房间设置修改历史中的一条：谁在什么时候改了哪些设置
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RoomSettingsChange {
+    pub changed_by: UserId,
+    pub changed_at: Timestamp,
+    pub changes: Vec<RoomSettingDiff>,
+}
+
+impl From<AuditLogEntry> for RoomSettingsChange {
+    fn from(entry: AuditLogEntry) -> Self {
+        let changes = entry
+            .details
+            .get("changes")
+            .cloned()
+            .and_then(|changes| serde_json::from_value(changes).ok())
+            .unwrap_or_default();
+        Self {
+            changed_by: entry.actor_id,
+            changed_at: entry.created_at,
+            changes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReactionRequest {
+    pub message_id: Uuid,
+    pub user_id: Uuid, // 从JWT获取
+    pub emoji: String,
 }
 
 pub struct ChatServiceDependencies {
     pub room_repository: Arc<dyn ChatRoomRepository>,
     pub member_repository: Arc<dyn RoomMemberRepository>,
     pub message_repository: Arc<dyn MessageRepository>,
+    pub reaction_repository: Arc<dyn ReactionRepository>,
+    pub user_block_repository: Arc<dyn UserBlockRepository>,
+    pub mention_repository: Arc<dyn MentionRepository>,
+    pub notification_repository: Arc<dyn NotificationRepository>,
+    pub idempotency_key_repository: Arc<dyn IdempotencyKeyRepository>,
+    pub invitation_repository: Arc<dyn RoomInvitationRepository>,
+    pub invite_repository: Arc<dyn RoomInviteRepository>,
+    pub delivery_repository: Arc<dyn MessageDeliveryRepository>,
     pub user_repository: Arc<dyn UserRepository>,
+    pub audit_log_repository: Arc<dyn AuditLogRepository>,
+    pub file_upload_repository: Arc<dyn FileUploadRepository>,
     pub password_hasher: Arc<dyn PasswordHasher>,
     pub clock: Arc<dyn Clock>,
     pub broadcaster: Arc<dyn MessageBroadcaster>,
+    pub message_config: MessageConfig,
+    /// 消息静态加密，未配置主密钥时为 None（不能为房间开启加密）
+    pub room_encryption: Option<Arc<RoomEncryption>>,
+    /// 发送限流：房间策略、房间合计速率和按 (房间, 用户) 的配额，None 表示不限流
+    pub send_rate_limiter: Option<Arc<dyn SendRateLimiter>>,
     // 删除了垃圾的 transaction_manager - 原子操作现在是Repository的自然功能
 }
 
 pub struct ChatService {
     deps: ChatServiceDependencies,
+    shutdown: Arc<ShutdownBarrier>,
+    /// 不在事务里的审计记录，后台写入
+    audit: AuditLogger,
 }
 
 impl ChatService {
     pub fn new(deps: ChatServiceDependencies) -> Self {
-        Self { deps }
+        Self {
+            audit: AuditLogger::new(deps.audit_log_repository.clone()),
+            deps,
+            shutdown: Arc::new(ShutdownBarrier::new()),
+        }
+    }
+
+    /// 消息发送的停机屏障，停机时用它等待进行中的发送完成
+    pub fn shutdown_barrier(&self) -> Arc<ShutdownBarrier> {
+        Arc::clone(&self.shutdown)
+    }
+
+    /// 后台写入的审计记录，停机时等待它们写完
+    pub fn audit_logger(&self) -> &AuditLogger {
+        &self.audit
+    }
+
+    /// 配置中的长度上限映射为领域规则
+    fn content_limits(&self) -> MessageContentLimits {
+        let limits = &self.deps.message_config.content_limits;
+        MessageContentLimits {
+            text: limits.text,
+            image: limits.image,
+            file: limits.file,
+        }
     }
 
     // 权限检查方法
@@ -155,18 +456,104 @@ impl ChatService {
             .map_err(ApplicationError::from)
     }
 
+    /// 查询房间信息；私有房间只对成员可见
+    pub async fn get_room(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ChatRoom, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        if room.visibility == ChatRoomVisibility::Private {
+            self.deps
+                .member_repository
+                .find(room_id, UserId::from(user_id))
+                .await?
+                .ok_or(DomainError::UserNotInRoom)?;
+        }
+
+        Ok(room)
+    }
+
+    /// WebSocket 订阅前的房间检查：权限规则与 `get_room` 相同
+    ///
+    /// 已删除的房间返回 `RoomNotFound`；已关闭的房间按配置允许只读订阅，
+    /// 发送仍由 `send_message` 以 `RoomClosed` 拒绝
+    pub async fn authorize_subscription(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ChatRoom, ApplicationError> {
+        let room = self.get_room(room_id, user_id).await?;
+        if room.is_closed && !self.deps.message_config.allow_closed_room_subscriptions {
+            return Err(DomainError::RoomClosed.into());
+        }
+        Ok(room)
+    }
+
+    /// 自助加入房间：公开房间直接加入，私有房间凭密码加入，无需邀请
+    ///
+    /// 密码错误返回 `InvalidRoomPassword`，尝试次数由调用方限流
+    pub async fn join_room(
+        &self,
+        request: JoinRoomRequest,
+    ) -> Result<RoomMember, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let user_id = UserId::from(request.user_id);
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
+        }
+
+        if self
+            .deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .is_some()
+        {
+            return Err(DomainError::UserAlreadyInRoom.into());
+        }
+        self.ensure_not_banned(room_id, user_id).await?;
+
+        if room.visibility == ChatRoomVisibility::Private {
+            let password = request.password.ok_or(DomainError::RoomIsPrivate)?;
+            let hashed = room.password.as_ref().ok_or(DomainError::RoomIsPrivate)?;
+            if !self.deps.password_hasher.verify(&password, hashed).await? {
+                return Err(DomainError::InvalidRoomPassword.into());
+            }
+        }
+
+        let member = RoomMember::new(room_id, user_id, RoomRole::Member, self.deps.clock.now());
+        Ok(self.deps.member_repository.upsert(member).await?)
+    }
+
     pub async fn leave_room(&self, request: LeaveRoomRequest) -> Result<(), ApplicationError> {
         let room_id = RoomId::from(request.room_id);
         let user_id = UserId::from(request.user_id);
 
-        let member_exists = self
+        let member = self
             .deps
             .member_repository
             .find(room_id, user_id)
             .await?
-            .is_some();
-        if !member_exists {
-            return Err(DomainError::UserNotInRoom.into());
+            .ok_or(DomainError::UserNotInRoom)?;
+        // Owner 离开后房间就没人能管理了，先转让所有权
+        if member.role == RoomRole::Owner {
+            return Err(DomainError::OwnerCannotLeave.into());
         }
 
         self.deps.member_repository.remove(room_id, user_id).await?;
@@ -177,9 +564,29 @@ impl ChatService {
         &self,
         request: SendMessageRequest,
     ) -> Result<Message, ApplicationError> {
+        // 守卫覆盖写库和广播的全过程
+        let _in_flight = self
+            .shutdown
+            .enter()
+            .ok_or(ApplicationError::ShuttingDown)?;
+
         let room_id = RoomId::from(request.room_id);
         let sender_id = UserId::from(request.sender_id);
 
+        // 同一个幂等键已经发过消息时直接返回那条消息，不再做限流等检查
+        let idempotency_key = request.idempotency_key.clone();
+        if let Some(key) = &idempotency_key {
+            validate_idempotency_key(key)?;
+            if let Some(existing) = self
+                .deps
+                .idempotency_key_repository
+                .find(sender_id, key, self.deps.clock.now())
+                .await?
+            {
+                return self.idempotent_replay(existing, room_id).await;
+            }
+        }
+
         let room = self
             .deps
             .room_repository
@@ -191,27 +598,120 @@ impl ChatService {
             return Err(DomainError::RoomClosed.into());
         }
 
-        self.deps
+        let sender = self
+            .deps
             .member_repository
             .find(room_id, sender_id)
             .await?
             .ok_or(DomainError::UserNotInRoom)?;
 
+        if let Some(remaining_secs) = self
+            .deps
+            .member_repository
+            .find_restriction(room_id, sender_id)
+            .await?
+            .and_then(|restriction| restriction.mute_remaining_secs(self.deps.clock.now()))
+        {
+            return Err(DomainError::UserMuted { remaining_secs }.into());
+        }
+
+        // 幂等重放和禁言之后才计数，重试和被拒的请求不消耗配额
+        if let Some(limiter) = &self.deps.send_rate_limiter {
+            limiter
+                .check_room(room_id, sender_id, sender.role.has_admin_access())
+                .await?;
+            limiter.acquire(room_id, sender_id).await?;
+        }
+
         let content = MessageContent::new(request.content)?;
-        let reply_to = request.reply_to.map(MessageId::from);
+        self.content_limits()
+            .validate(&request.message_type, &content)?;
+        let attachment = self
+            .find_attachment(sender_id, &request.message_type, &content)
+            .await?;
+        let parent = match request.reply_to {
+            Some(parent_id) => Some(
+                self.deps
+                    .message_repository
+                    .find_by_id(MessageId::from(parent_id))
+                    .await?
+                    .filter(|parent| parent.room_id == room_id)
+                    .ok_or(DomainError::MessageNotFound)?,
+            ),
+            None => None,
+        };
         let now = self.deps.clock.now();
 
-        let message = Message::new(
+        let mut message = Message::new(
             MessageId::from(Uuid::new_v4()),
             room_id,
             sender_id,
             content,
             request.message_type,
-            reply_to,
+            None,
             now,
         )?;
+        // 回复带上线程根，广播给客户端后可以直接渲染嵌套视图
+        if let Some(parent) = &parent {
+            if parent.is_deleted {
+                return Err(DomainError::MessageDeleted.into());
+            }
+            message.reply_in_thread(parent);
+        }
+
+        // 先占用幂等键再写消息，并发的重试只有一个能写入
+        if let Some(key) = &idempotency_key {
+            let ttl =
+                time::Duration::seconds(self.deps.message_config.idempotency_key_ttl_secs as i64);
+            let owner = self
+                .deps
+                .idempotency_key_repository
+                .claim(sender_id, key, message.id, now, now + ttl)
+                .await?;
+            if owner != message.id {
+                return self.idempotent_replay(owner, room_id).await;
+            }
+        }
 
-        let message_id = self.deps.message_repository.create(message.clone()).await?;
+        // 开启发件箱时消息和待广播记录一起写入，由后台分发任务广播
+        let use_outbox = self.deps.message_config.outbox.enabled;
+        let created = if use_outbox {
+            self.deps
+                .message_repository
+                .create_with_outbox(message.clone())
+                .await
+        } else {
+            self.deps.message_repository.create(message.clone()).await
+        };
+        let message_id = match created {
+            Ok(message_id) => message_id,
+            Err(err) => {
+                // 消息没写进去，释放幂等键让客户端可以重试
+                if let Some(key) = &idempotency_key {
+                    if let Err(release_err) = self
+                        .deps
+                        .idempotency_key_repository
+                        .release(sender_id, key, message.id)
+                        .await
+                    {
+                        tracing::warn!(error = %release_err, "释放幂等键失败");
+                    }
+                }
+                return Err(err.into());
+            }
+        };
+
+        if let Some(attachment) = attachment {
+            // 校验之后被同一用户的另一条消息抢先引用时，文件只归属先发送的消息
+            if !self
+                .deps
+                .file_upload_repository
+                .attach(attachment.id, sender_id, message_id)
+                .await?
+            {
+                tracing::warn!(%message_id, file_id = %attachment.id, "上传文件已被其他消息引用");
+            }
+        }
 
         // 获取保存后的完整消息对象
         let stored = self
@@ -221,13 +721,36 @@ impl ChatService {
             .await?
             .ok_or(domain::DomainError::MessageNotFound)?;
 
+        // 提及只是收件箱和通知的索引，记录失败不影响消息本身
+        if let Err(err) = self.notify_mentions(&stored, &sender.role).await {
+            tracing::warn!(message_id = %stored.id, error = %err, "记录消息提及失败");
+        }
+
+        // 每个成员一条待送达记录，客户端 ack 前一直算未送达，离线成员重连时补发
+        if let Err(err) = self
+            .deps
+            .delivery_repository
+            .record_sent_to_room(&stored)
+            .await
+        {
+            tracing::warn!(message_id = %stored.id, error = %err, "登记消息投递记录失败");
+        }
+
+        if use_outbox {
+            metrics::counter!(crate::metrics::MESSAGES_SENT_TOTAL).increment(1);
+            return Ok(stored);
+        }
+
         // 广播消息给房间内所有用户
-        if let Err(broadcast_error) = self
+        let fanout_started = Instant::now();
+        let broadcast_result = self
             .deps
             .broadcaster
             .broadcast(MessageBroadcast::chat(room_id, stored.clone()))
-            .await
-        {
+            .await;
+        metrics::histogram!(crate::metrics::BROADCAST_FANOUT_SECONDS)
+            .record(fanout_started.elapsed());
+        if let Err(broadcast_error) = broadcast_result {
             // 记录关键错误并传播给调用者
             tracing::error!(
                 room_id = %room_id,
@@ -235,140 +758,2188 @@ impl ChatService {
                 error = %broadcast_error,
                 "消息已保存到数据库，但广播失败"
             );
-            return Err(ApplicationError::infrastructure_with_source(
-                "消息广播失败",
-                broadcast_error,
-            ));
+            // Redis 不可用且重试用尽时返回 Unavailable，由接口层映射成 503
+            return Err(broadcast_error.into());
         }
 
+        metrics::counter!(crate::metrics::MESSAGES_SENT_TOTAL).increment(1);
         Ok(stored)
     }
 
-    pub async fn get_history(
+    /// 幂等键对应的消息；键已被占用但消息还没写入时说明第一次请求仍在处理
+    ///
+    /// 键按用户划分，同一个键换了房间重发不是重试，不能把别的房间的消息返回给客户端
+    async fn idempotent_replay(
         &self,
-        room_id: Uuid,
-        limit: u32,
-        before: Option<Uuid>,
-    ) -> Result<Vec<Message>, ApplicationError> {
-        let room_id = RoomId::from(room_id);
-        let before = before.map(MessageId::from);
-
-        let records = self
+        message_id: MessageId,
+        room_id: RoomId,
+    ) -> Result<Message, ApplicationError> {
+        let message = self
             .deps
             .message_repository
-            .list_recent(room_id, limit, before)
-            .await?;
-
-        Ok(records)
+            .find_by_id(message_id)
+            .await?
+            .ok_or(DomainError::IdempotencyKeyInUse)?;
+        if message.room_id != room_id {
+            return Err(DomainError::IdempotencyKeyReused.into());
+        }
+        Ok(message)
     }
 
-    /// 邀请用户加入房间 - 唯一的加入房间方法
+    /// 记录消息里的提及，给被提及的成员发通知
     ///
-    /// Linus式"单一职责"：一个功能只有一个入口点
-    /// 统一处理所有加入房间的场景：管理员邀请、用户自助加入等
-    pub async fn invite_member(
+    /// 不是房间成员的用户名直接忽略；`@everyone` 只对房间 Owner/Admin 生效，且成员数超过
+    /// 配置上限的房间里不生效。通知先落库（离线成员下次登录后查看），再推送给在线的成员。
+    async fn notify_mentions(
         &self,
-        request: InviteMemberRequest,
+        message: &Message,
+        sender_role: &RoomRole,
     ) -> Result<(), ApplicationError> {
-        let room_id = RoomId::from(request.room_id);
-        let inviter_id = UserId::from(request.inviter_id);
-        let invitee_id = UserId::from(request.invitee_id);
+        let mut usernames = extract_mentions(message.content.as_str());
+        let everyone = usernames.iter().any(|name| name == EVERYONE_MENTION);
+        usernames.retain(|name| name != EVERYONE_MENTION);
 
-        // 检查房间是否存在且开放
-        let room = self
-            .deps
-            .room_repository
-            .find_by_id(room_id)
-            .await?
-            .ok_or(DomainError::RoomNotFound)?;
-
-        if room.is_closed {
-            return Err(DomainError::RoomClosed.into());
+        let mut mentioned = if usernames.is_empty() {
+            Vec::new()
+        } else {
+            self.deps
+                .mention_repository
+                .record_mentions(message, &usernames)
+                .await?
+        };
+        if everyone
+            && self
+                .allows_everyone_mention(message.room_id, sender_role)
+                .await?
+        {
+            mentioned.extend(
+                self.deps
+                    .mention_repository
+                    .record_everyone_mention(message)
+                    .await?,
+            );
+        }
+        if mentioned.is_empty() {
+            return Ok(());
+        }
+
+        let now = self.deps.clock.now();
+        let notifications: Vec<Notification> = mentioned
+            .iter()
+            .map(|user_id| {
+                Notification::mention(
+                    *user_id,
+                    message.sender_id,
+                    message.room_id,
+                    message.id,
+                    now,
+                )
+            })
+            .collect();
+        self.deps
+            .notification_repository
+            .create_many(&notifications)
+            .await?;
+
+        self.deps
+            .broadcaster
+            .broadcast(MessageBroadcast::mention(message, mentioned))
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("提及提醒广播失败", err))
+    }
+
+    /// 保存一条通知；失败只记日志，不影响已经完成的操作
+    async fn notify(&self, notification: Notification) {
+        if let Err(err) = self
+            .deps
+            .notification_repository
+            .create_many(std::slice::from_ref(&notification))
+            .await
+        {
+            tracing::warn!(
+                user_id = %notification.user_id,
+                kind = %notification.kind,
+                error = %err,
+                "保存通知失败"
+            );
+        }
+    }
+
+    /// 发送者能否在这个房间使用 `@everyone`
+    async fn allows_everyone_mention(
+        &self,
+        room_id: RoomId,
+        sender_role: &RoomRole,
+    ) -> Result<bool, ApplicationError> {
+        let max_members = self.deps.message_config.everyone_mention_max_members;
+        if !sender_role.has_admin_access() || max_members == 0 {
+            return Ok(false);
+        }
+        let members = self.deps.member_repository.find_by_room(room_id).await?;
+        Ok(members.len() <= max_members)
+    }
+
+    /// 编辑消息
+    ///
+    /// 发送者只能在配置的时间窗口内编辑；房间 Owner/Admin 不受时间限制。
+    /// 已删除的消息不可编辑。
+    pub async fn edit_message(
+        &self,
+        request: EditMessageRequest,
+    ) -> Result<Message, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let operator_id = UserId::from(request.operator_id);
+
+        let mut message = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(request.message_id))
+            .await?
+            .filter(|message| message.room_id == room_id)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, operator_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        let now = self.deps.clock.now();
+        if !operator.role.has_admin_access() {
+            if message.sender_id != operator_id {
+                return Err(DomainError::InsufficientPermissions.into());
+            }
+            let window = time::Duration::seconds(self.deps.message_config.edit_window_secs as i64);
+            if now - message.created_at > window {
+                return Err(DomainError::OperationNotAllowed.into());
+            }
+        }
+
+        // 客户端基于旧版本编辑，直接拒绝，让它先拿到最新内容
+        let expected_version = request.expected_version.unwrap_or(message.version);
+        if expected_version != message.version {
+            return Err(DomainError::MessageVersionConflict {
+                current_version: message.version,
+            }
+            .into());
+        }
+
+        // 图片/文件消息的内容是文件ID，不能改成别的内容
+        if message.message_type != MessageType::Text {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        let content = MessageContent::new(request.content)?;
+        self.content_limits()
+            .validate(&message.message_type, &content)?;
+        message.edit(content, now)?;
+        match self
+            .deps
+            .message_repository
+            .update(message.clone(), expected_version)
+            .await
+        {
+            Ok(()) => {}
+            // 读取之后被别人抢先编辑，查出最新版本返回给客户端
+            Err(RepositoryError::Conflict) => {
+                let current_version = self
+                    .deps
+                    .message_repository
+                    .find_by_id(message.id)
+                    .await?
+                    .ok_or(DomainError::MessageNotFound)?
+                    .version;
+                return Err(DomainError::MessageVersionConflict { current_version }.into());
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        if let Err(broadcast_error) = self
+            .deps
+            .broadcaster
+            .broadcast(MessageBroadcast::message_edited(room_id, &message))
+            .await
+        {
+            tracing::error!(
+                room_id = %room_id,
+                message_id = %message.id,
+                error = %broadcast_error,
+                "消息已更新，但编辑广播失败"
+            );
+            return Err(ApplicationError::infrastructure_with_source(
+                "消息编辑广播失败",
+                broadcast_error,
+            ));
+        }
+
+        Ok(message)
+    }
+
+    /// 软删除消息：发送者本人、房间管理员或所有者可以删除
+    ///
+    /// 幂等：已删除的消息再次删除直接成功，不重复广播
+    pub async fn delete_message(
+        &self,
+        request: DeleteMessageRequest,
+    ) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let operator_id = UserId::from(request.operator_id);
+
+        let message = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(request.message_id))
+            .await?
+            .filter(|message| message.room_id == room_id)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, operator_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        if message.sender_id != operator_id && !operator.role.can_delete_messages() {
+            return Err(DomainError::InsufficientPermissions.into());
+        }
+
+        if message.is_deleted {
+            return Ok(());
+        }
+
+        self.deps.message_repository.soft_delete(message.id).await?;
+
+        if let Err(broadcast_error) = self
+            .deps
+            .broadcaster
+            .broadcast(MessageBroadcast::message_deleted(room_id, message.id))
+            .await
+        {
+            tracing::error!(
+                room_id = %room_id,
+                message_id = %message.id,
+                error = %broadcast_error,
+                "消息已删除，但删除广播失败"
+            );
+            return Err(ApplicationError::infrastructure_with_source(
+                "消息删除广播失败",
+                broadcast_error,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 批量删除房间内的消息（只有房间 owner/admin 可以），返回实际删除的条数
+    ///
+    /// 其他房间的消息ID和已删除的消息直接忽略；每条删除单独广播一次
+    pub async fn bulk_delete_messages(
+        &self,
+        request: BulkDeleteMessagesRequest,
+    ) -> Result<usize, ApplicationError> {
+        if request.message_ids.is_empty() {
+            return Err(DomainError::invalid_argument("message_ids", "cannot be empty").into());
+        }
+        if request.message_ids.len() > MAX_BULK_DELETE {
+            return Err(DomainError::invalid_argument(
+                "message_ids",
+                "at most 100 ids per request",
+            )
+            .into());
+        }
+
+        let room_id = RoomId::from(request.room_id);
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, UserId::from(request.operator_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if !operator.role.can_delete_messages() {
+            return Err(DomainError::InsufficientPermissions.into());
+        }
+
+        let ids: Vec<MessageId> = request
+            .message_ids
+            .into_iter()
+            .map(MessageId::from)
+            .collect();
+        let deleted = self
+            .deps
+            .message_repository
+            .soft_delete_many(room_id, &ids)
+            .await?;
+
+        // 删除已经落库，单条广播失败不打断其余广播，最后统一报告
+        let mut broadcast_failed = None;
+        for message_id in &deleted {
+            if let Err(broadcast_error) = self
+                .deps
+                .broadcaster
+                .broadcast(MessageBroadcast::message_deleted(room_id, *message_id))
+                .await
+            {
+                tracing::error!(
+                    room_id = %room_id,
+                    message_id = %message_id,
+                    error = %broadcast_error,
+                    "消息已删除，但删除广播失败"
+                );
+                broadcast_failed = Some(broadcast_error);
+            }
+        }
+        if let Some(broadcast_error) = broadcast_failed {
+            return Err(ApplicationError::infrastructure_with_source(
+                "消息删除广播失败",
+                broadcast_error,
+            ));
+        }
+
+        Ok(deleted.len())
+    }
+
+    /// 恢复误删的消息（只有房间 owner/admin 可以），恢复后广播完整消息
+    pub async fn restore_message(
+        &self,
+        request: RestoreMessageRequest,
+    ) -> Result<Message, ApplicationError> {
+        let operator_id = UserId::from(request.operator_id);
+
+        let mut message = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(request.message_id))
+            .await?
+            .ok_or(DomainError::MessageNotFound)?;
+
+        let operator = self
+            .deps
+            .member_repository
+            .find(message.room_id, operator_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if !operator.role.can_delete_messages() {
+            return Err(DomainError::InsufficientPermissions.into());
+        }
+
+        // 并发恢复时只有一个请求真正生效，其余按未删除处理
+        if !message.is_deleted || !self.deps.message_repository.restore(message.id).await? {
+            return Err(DomainError::MessageNotDeleted.into());
+        }
+        message.is_deleted = false;
+
+        if let Err(broadcast_error) = self
+            .deps
+            .broadcaster
+            .broadcast(MessageBroadcast::message_restored(message.clone()))
+            .await
+        {
+            tracing::error!(
+                room_id = %message.room_id,
+                message_id = %message.id,
+                error = %broadcast_error,
+                "消息已恢复，但恢复广播失败"
+            );
+            return Err(ApplicationError::infrastructure_with_source(
+                "消息恢复广播失败",
+                broadcast_error,
+            ));
+        }
+
+        Ok(message)
+    }
+
+    /// 置顶房间内的消息（房间 Owner/Admin），已经置顶时直接返回
+    ///
+    /// 每个房间的置顶数受 `message.max_pinned_per_room` 限制；其他房间的消息视为不存在
+    pub async fn pin_message(
+        &self,
+        request: PinMessageRequest,
+    ) -> Result<PinnedMessage, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let message_id = MessageId::from(request.message_id);
+        let operator_id = UserId::from(request.operator_id);
+        self.check_pin_permission(room_id, operator_id).await?;
+
+        let pinned = self.deps.message_repository.list_pinned(room_id).await?;
+        if let Some(existing) = pinned.iter().find(|pin| pin.message.id == message_id) {
+            return Ok(existing.clone());
+        }
+        let limit = self.deps.message_config.max_pinned_per_room;
+        if pinned.len() >= limit {
+            return Err(DomainError::PinLimitExceeded { limit }.into());
+        }
+
+        let message = self
+            .deps
+            .message_repository
+            .find_by_id(message_id)
+            .await?
+            .filter(|message| message.room_id == room_id && !message.is_deleted)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        let now = self.deps.clock.now();
+        match self
+            .deps
+            .message_repository
+            .pin(room_id, message_id, operator_id, now)
+            .await
+        {
+            Ok(true) => {}
+            // 并发置顶同一条消息，另一个请求已经置顶并广播过
+            Ok(false) => {
+                return self
+                    .deps
+                    .message_repository
+                    .list_pinned(room_id)
+                    .await?
+                    .into_iter()
+                    .find(|pin| pin.message.id == message_id)
+                    .ok_or_else(|| DomainError::MessageNotFound.into());
+            }
+            // 查询之后消息被删除
+            Err(RepositoryError::NotFound) => return Err(DomainError::MessageNotFound.into()),
+            Err(err) => return Err(err.into()),
+        }
+
+        self.deps
+            .broadcaster
+            .broadcast(MessageBroadcast::message_pinned(
+                room_id,
+                message_id,
+                operator_id,
+                now,
+            ))
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("置顶广播失败", err))?;
+
+        Ok(PinnedMessage {
+            message,
+            pinned_by: Some(operator_id),
+            pinned_at: now,
+        })
+    }
+
+    /// 取消置顶（房间 Owner/Admin）；消息没有置顶时是无操作
+    pub async fn unpin_message(&self, request: PinMessageRequest) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let message_id = MessageId::from(request.message_id);
+        let operator_id = UserId::from(request.operator_id);
+        self.check_pin_permission(room_id, operator_id).await?;
+
+        if !self
+            .deps
+            .message_repository
+            .unpin(room_id, message_id)
+            .await?
+        {
+            return Ok(());
+        }
+
+        self.deps
+            .broadcaster
+            .broadcast(MessageBroadcast::message_unpinned(
+                room_id,
+                message_id,
+                operator_id,
+            ))
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("取消置顶广播失败", err))
+    }
+
+    /// 房间的置顶消息（房间成员可见），最近置顶的在前
+    pub async fn list_pinned_messages(
+        &self,
+        room_id: Uuid,
+        viewer_id: Uuid,
+    ) -> Result<Vec<PinnedMessage>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        self.deps
+            .member_repository
+            .find(room_id, UserId::from(viewer_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        let pinned = self.deps.message_repository.list_pinned(room_id).await?;
+        self.without_blocked(UserId::from(viewer_id), pinned, |pin| pin.message.sender_id)
+            .await
+    }
+
+    /// 置顶和取消置顶要求操作者是房间 Owner/Admin
+    async fn check_pin_permission(
+        &self,
+        room_id: RoomId,
+        operator_id: UserId,
+    ) -> Result<(), ApplicationError> {
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, operator_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if !operator.role.has_admin_access() {
+            return Err(DomainError::InsufficientPermissions.into());
+        }
+        Ok(())
+    }
+
+    /// 断线重连补偿：一次查询拿到客户端重新同步所需的房间状态
+    pub async fn resume_room(
+        &self,
+        request: ResumeRoomRequest,
+    ) -> Result<RoomResumeState, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let user_id = UserId::from(request.user_id);
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        let member = self
+            .deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 多取一条用于判断是否还有剩余
+        let limit = i64::from(request.limit);
+        let mut missed_messages = self
+            .deps
+            .message_repository
+            .find_after_sequence(
+                room_id,
+                request.last_seq.max(0),
+                PaginationParams::new(limit + 1),
+            )
+            .await?;
+        let has_more = missed_messages.len() as i64 > limit;
+        missed_messages.truncate(request.limit as usize);
+        let missed_messages = self
+            .without_blocked(user_id, missed_messages, |message| message.sender_id)
+            .await?;
+
+        let unread_count = self
+            .deps
+            .message_repository
+            .count_unread_for_member(room_id, user_id)
+            .await?;
+
+        Ok(RoomResumeState {
+            room,
+            role: member.role,
+            missed_messages,
+            has_more,
+            unread_count,
+        })
+    }
+
+    /// 从序列号 `from_seq`（含）开始重放消息，最多 `limit` 条
+    pub async fn replay_from_seq(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        from_seq: i64,
+        limit: u32,
+    ) -> Result<SequenceReplay, ApplicationError> {
+        let room = RoomId::from(room_id);
+        let user = UserId::from(user_id);
+
+        self.deps
+            .member_repository
+            .find(room, user)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 序列号从 1 开始；多取一条用于判断是否还有剩余
+        let after_seq = from_seq.max(1) - 1;
+        let mut messages = self
+            .deps
+            .message_repository
+            .find_after_sequence(room, after_seq, PaginationParams::new(i64::from(limit) + 1))
+            .await?;
+        let has_more = messages.len() > limit as usize;
+        messages.truncate(limit as usize);
+
+        let next_seq = if has_more {
+            messages.last().map_or(after_seq, |message| message.seq) + 1
+        } else {
+            self.latest_seq(room_id).await?.max(after_seq) + 1
+        };
+        let messages = self
+            .without_blocked(user, messages, |message| message.sender_id)
+            .await?;
+
+        Ok(SequenceReplay {
+            messages,
+            has_more,
+            next_seq,
+        })
+    }
+
+    /// 重连补发：取出 `last_message_id` 之后的全部消息
+    ///
+    /// 缺口超过 `max_reconnect_backfill` 时不补发，让客户端走全量同步，
+    /// 避免一次连接灌入海量历史
+    pub async fn backfill_after(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        last_message_id: Uuid,
+    ) -> Result<ReconnectBackfill, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, UserId::from(user_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 锚点消息不在本房间时无法确定缺口，直接要求重新同步
+        let Some(anchor) = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(last_message_id))
+            .await?
+            .filter(|message| message.room_id == room_id)
+        else {
+            return Ok(ReconnectBackfill::ResyncRequired { missed: None });
+        };
+
+        let limit = self.deps.message_config.max_reconnect_backfill;
+        let missed = self
+            .deps
+            .message_repository
+            .count_after(room_id, Some(anchor.id))
+            .await?;
+        if missed > i64::from(limit) {
+            return Ok(ReconnectBackfill::ResyncRequired {
+                missed: Some(missed),
+            });
+        }
+
+        let messages = self
+            .deps
+            .message_repository
+            .find_after_sequence(room_id, anchor.seq, PaginationParams::new(i64::from(limit)))
+            .await?;
+        let messages = self
+            .without_blocked(UserId::from(user_id), messages, |message| message.sender_id)
+            .await?;
+
+        Ok(ReconnectBackfill::Messages(messages))
+    }
+
+    /// 房间内最新一条未删除消息的序列号，没有消息时为 0
+    pub async fn latest_seq(&self, room_id: Uuid) -> Result<i64, ApplicationError> {
+        let latest = self
+            .deps
+            .message_repository
+            .find_recent_by_room(RoomId::from(room_id), PaginationParams::new(1), None)
+            .await?;
+        Ok(latest.first().map_or(0, |message| message.seq))
+    }
+
+    /// 广播流出现缺口（订阅中断或落后）后补齐：取出序列号 `after_seq` 之后的全部消息
+    ///
+    /// 与重连补发共用 `max_reconnect_backfill` 上限，超过时让客户端走全量同步
+    pub async fn backfill_after_seq(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        after_seq: i64,
+    ) -> Result<ReconnectBackfill, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, UserId::from(user_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 多取一条判断是否超过上限
+        let limit = self.deps.message_config.max_reconnect_backfill;
+        let messages = self
+            .deps
+            .message_repository
+            .find_after_sequence(
+                room_id,
+                after_seq,
+                PaginationParams::new(i64::from(limit) + 1),
+            )
+            .await?;
+        if messages.len() > limit as usize {
+            return Ok(ReconnectBackfill::ResyncRequired { missed: None });
+        }
+        let messages = self
+            .without_blocked(UserId::from(user_id), messages, |message| message.sender_id)
+            .await?;
+
+        Ok(ReconnectBackfill::Messages(messages))
+    }
+
+    /// 添加表情回应（幂等），返回该消息最新的回应聚合
+    pub async fn add_reaction(
+        &self,
+        request: ReactionRequest,
+    ) -> Result<Vec<ReactionSummary>, ApplicationError> {
+        let emoji = ReactionEmoji::new(request.emoji)?;
+        let user_id = UserId::from(request.user_id);
+        let message = self
+            .reactable_message(MessageId::from(request.message_id), user_id)
+            .await?;
+
+        // 策略只限制新增；策略收紧前留下的文字标签仍可撤销
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(message.room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if !room.reaction_policy.allows(emoji.kind()) {
+            return Err(DomainError::ReactionNotAllowed.into());
+        }
+
+        // 重复添加已有回应是幂等的，不占新名额
+        let limit = self.deps.message_config.max_reactions_per_user_per_message;
+        let existing = self
+            .deps
+            .reaction_repository
+            .find_user_reactions(message.id, user_id)
+            .await?;
+        if existing.len() >= limit && !existing.contains(&emoji) {
+            return Err(DomainError::ReactionLimitExceeded { limit }.into());
+        }
+
+        let added = self
+            .deps
+            .reaction_repository
+            .add_reaction(message.id, user_id, &emoji)
+            .await?;
+
+        self.publish_reactions(&message, added).await
+    }
+
+    /// 移除表情回应；移除不存在的回应是无操作
+    pub async fn remove_reaction(&self, request: ReactionRequest) -> Result<(), ApplicationError> {
+        let emoji = ReactionEmoji::new(request.emoji)?;
+        let user_id = UserId::from(request.user_id);
+        let message = self
+            .reactable_message(MessageId::from(request.message_id), user_id)
+            .await?;
+
+        let removed = self
+            .deps
+            .reaction_repository
+            .remove_reaction(message.id, user_id, &emoji)
+            .await?;
+
+        self.publish_reactions(&message, removed).await?;
+        Ok(())
+    }
+
+    /// 回应的前置检查：消息存在且未删除，操作者是房间成员
+    async fn reactable_message(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+    ) -> Result<Message, ApplicationError> {
+        let message = self
+            .deps
+            .message_repository
+            .find_by_id(message_id)
+            .await?
+            .filter(|message| !message.is_deleted)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        self.deps
+            .member_repository
+            .find(message.room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        Ok(message)
+    }
+
+    /// 查询最新聚合，只有真正发生变化时才广播
+    async fn publish_reactions(
+        &self,
+        message: &Message,
+        changed: bool,
+    ) -> Result<Vec<ReactionSummary>, ApplicationError> {
+        let reactions = self
+            .deps
+            .reaction_repository
+            .reactions_for_messages(&[message.id])
+            .await?
+            .remove(&message.id)
+            .unwrap_or_default();
+
+        if changed {
+            if let Err(broadcast_error) = self
+                .deps
+                .broadcaster
+                .broadcast(MessageBroadcast::reaction_changed(
+                    message.room_id,
+                    message.id,
+                    reactions.clone(),
+                ))
+                .await
+            {
+                tracing::warn!(
+                    room_id = %message.room_id,
+                    message_id = %message.id,
+                    error = %broadcast_error,
+                    "回应已保存，但广播失败"
+                );
+            }
+        }
+
+        Ok(reactions)
+    }
+
+    /// 标记已读并广播已读回执
+    pub async fn mark_read(&self, request: MarkReadRequest) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let user_id = UserId::from(request.user_id);
+        let message_id = MessageId::from(request.message_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        self.deps
+            .message_repository
+            .find_by_id(message_id)
+            .await?
+            .filter(|message| message.room_id == room_id)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        self.deps
+            .member_repository
+            .update_last_read(room_id, user_id, message_id)
+            .await?;
+
+        // 回执只是提示信息，广播失败不影响已读位置的持久化
+        if let Err(broadcast_error) = self
+            .deps
+            .broadcaster
+            .broadcast(MessageBroadcast::read_receipt(room_id, user_id, message_id))
+            .await
+        {
+            tracing::warn!(
+                room_id = %room_id,
+                user_id = %user_id,
+                error = %broadcast_error,
+                "已读位置已更新，但回执广播失败"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 批量标记已读到各房间的最新消息，一次写库完成；返回已读位置有前进的房间
+    ///
+    /// 指定的房间里只要有一个不是成员就整体拒绝
+    pub async fn mark_rooms_read(
+        &self,
+        request: MarkRoomsReadRequest,
+    ) -> Result<Vec<RoomId>, ApplicationError> {
+        let user_id = UserId::from(request.user_id);
+
+        let room_ids = match request.room_ids {
+            Some(room_ids) => {
+                let memberships: HashSet<RoomId> = self
+                    .deps
+                    .member_repository
+                    .find_by_user(user_id)
+                    .await?
+                    .into_iter()
+                    .map(|member| member.room_id)
+                    .collect();
+                let requested: HashSet<RoomId> = room_ids.into_iter().map(RoomId::from).collect();
+                if !requested.is_subset(&memberships) {
+                    return Err(DomainError::UserNotInRoom.into());
+                }
+                Some(requested.into_iter().collect::<Vec<_>>())
+            }
+            None => None,
+        };
+        if room_ids.as_ref().is_some_and(Vec::is_empty) {
+            return Ok(Vec::new());
+        }
+
+        let marked = self
+            .deps
+            .member_repository
+            .mark_rooms_read(user_id, room_ids.as_deref())
+            .await?;
+
+        // 回执只是提示信息，广播失败不影响已读位置的持久化
+        for (room_id, message_id) in &marked {
+            if let Err(broadcast_error) = self
+                .deps
+                .broadcaster
+                .broadcast(MessageBroadcast::read_receipt(
+                    *room_id,
+                    user_id,
+                    *message_id,
+                ))
+                .await
+            {
+                tracing::warn!(
+                    room_id = %room_id,
+                    user_id = %user_id,
+                    error = %broadcast_error,
+                    "已读位置已更新，但回执广播失败"
+                );
+            }
+        }
+
+        Ok(marked.into_iter().map(|(room_id, _)| room_id).collect())
+    }
+
+    /// 我所在的房间：我置顶的在前，其余按最近活动倒序，附带未读数
+    pub async fn list_my_rooms(&self, user_id: Uuid) -> Result<Vec<MemberRoom>, ApplicationError> {
+        Ok(self
+            .deps
+            .room_repository
+            .find_member_rooms(UserId::from(user_id))
+            .await?)
+    }
+
+    /// 把房间置顶到自己的房间列表，只有成员可以
+    pub async fn pin_room(&self, room_id: Uuid, user_id: Uuid) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let user_id = UserId::from(user_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        self.deps
+            .member_repository
+            .pin_room(room_id, user_id, self.deps.clock.now())
+            .await?;
+        Ok(())
+    }
+
+    /// 取消置顶，幂等
+    pub async fn unpin_room(&self, room_id: Uuid, user_id: Uuid) -> Result<(), ApplicationError> {
+        self.deps
+            .member_repository
+            .unpin_room(RoomId::from(room_id), UserId::from(user_id))
+            .await?;
+        Ok(())
+    }
+
+    /// 查询用户所在全部房间的未读消息数
+    pub async fn unread_counts(
+        &self,
+        user_id: Uuid,
+    ) -> Result<HashMap<RoomId, i64>, ApplicationError> {
+        let memberships = self
+            .deps
+            .member_repository
+            .find_by_user(UserId::from(user_id))
+            .await?;
+
+        let mut counts = HashMap::with_capacity(memberships.len());
+        for member in memberships {
+            let unread = self
+                .deps
+                .message_repository
+                .count_after(member.room_id, member.last_read_message)
+                .await?;
+            counts.insert(member.room_id, unread);
+        }
+
+        Ok(counts)
+    }
+
+    /// 查询成员在房间内的未读消息数
+    pub async fn unread_count(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<i64, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let user_id = UserId::from(user_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        Ok(self
+            .deps
+            .message_repository
+            .count_unread_for_member(room_id, user_id)
+            .await?)
+    }
+
+    /// 公开房间发现；包含已关闭房间需要系统管理员权限
+    pub async fn discover_rooms(
+        &self,
+        user_id: Uuid,
+        query: PublicRoomQuery,
+    ) -> Result<PaginatedResult<RoomSummary>, ApplicationError> {
+        if query.include_closed {
+            self.check_admin_access(UserId::from(user_id), None).await?;
+        }
+
+        Ok(self.deps.room_repository.find_public(&query).await?)
+    }
+
+    /// 我创建的房间，包括私有和已关闭的，按创建时间倒序分页
+    pub async fn list_owned_rooms(
+        &self,
+        user_id: Uuid,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<ChatRoom>, ApplicationError> {
+        let mut rooms = self
+            .deps
+            .room_repository
+            .find_by_owner(UserId::from(user_id))
+            .await?;
+        rooms.sort_by_key(|room| std::cmp::Reverse(room.created_at));
+
+        let total_count = rooms.len() as i64;
+        let offset = (page.saturating_sub(1) as usize).saturating_mul(page_size as usize);
+        let items = rooms
+            .into_iter()
+            .skip(offset)
+            .take(page_size as usize)
+            .collect();
+
+        Ok(PaginatedResult {
+            items,
+            total_count,
+            page,
+            page_size,
+        })
+    }
+
+    /// 提及当前用户的消息，最新的在前
+    pub async fn list_mentions(
+        &self,
+        user_id: Uuid,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<MentionedMessage>, ApplicationError> {
+        Ok(self
+            .deps
+            .mention_repository
+            .find_for_user(UserId::from(user_id), page, page_size)
+            .await?)
+    }
+
+    /// 房间内全文检索，仅房间成员可用
+    pub async fn search_messages(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<Message>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(DomainError::invalid_argument("q", "cannot be empty").into());
+        }
+
+        let user_id = UserId::from(user_id);
+        self.deps
+            .member_repository
+            .find(room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 加密房间的索引里只有密文，不提供检索
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.encrypted {
+            return Err(DomainError::EncryptedRoomNotSearchable.into());
+        }
+
+        let messages = self
+            .deps
+            .message_repository
+            .search_in_room(room_id, query, PaginationParams::new(limit as i64))
+            .await?;
+        self.without_blocked(user_id, messages, |message| message.sender_id)
+            .await
+    }
+
+    /// 按 ID 批量读取消息（包括已删除的），供离线补发等内部流程使用，不做权限检查
+    pub async fn messages_by_ids(
+        &self,
+        ids: &[MessageId],
+    ) -> Result<Vec<Message>, ApplicationError> {
+        Ok(self.deps.message_repository.find_by_ids(ids).await?)
+    }
+
+    /// 去掉查看者屏蔽的用户发出的消息，只影响屏蔽者自己看到的内容
+    ///
+    /// 屏蔽关系每次从数据库读取，屏蔽/取消屏蔽对后续查询立即生效
+    pub async fn without_blocked<T>(
+        &self,
+        viewer_id: UserId,
+        mut items: Vec<T>,
+        sender_of: impl Fn(&T) -> UserId,
+    ) -> Result<Vec<T>, ApplicationError> {
+        if items.is_empty() {
+            return Ok(items);
+        }
+
+        let blocked = self.blocked_user_ids(viewer_id).await?;
+        if !blocked.is_empty() {
+            items.retain(|item| !blocked.contains(&sender_of(item)));
+        }
+        Ok(items)
+    }
+
+    /// 查看者屏蔽的全部用户，供 WebSocket 推送等路径过滤使用
+    pub async fn blocked_user_ids(
+        &self,
+        viewer_id: UserId,
+    ) -> Result<HashSet<UserId>, ApplicationError> {
+        Ok(self
+            .deps
+            .user_block_repository
+            .blocked_ids(viewer_id)
+            .await?)
+    }
+
+    /// 屏蔽用户（幂等）
+    ///
+    /// 屏蔽关系每次从数据库读取，没有缓存，变更对后续请求立即生效
+    pub async fn block_user(
+        &self,
+        blocker_id: Uuid,
+        blocked_id: Uuid,
+    ) -> Result<(), ApplicationError> {
+        if blocker_id == blocked_id {
+            return Err(DomainError::invalid_argument("user_id", "cannot block yourself").into());
+        }
+
+        let blocked_id = UserId::from(blocked_id);
+        self.deps
+            .user_repository
+            .find_by_id(blocked_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+
+        self.deps
+            .user_block_repository
+            .block(UserId::from(blocker_id), blocked_id)
+            .await?;
+        Ok(())
+    }
+
+    /// 取消屏蔽（幂等）
+    pub async fn unblock_user(
+        &self,
+        blocker_id: Uuid,
+        blocked_id: Uuid,
+    ) -> Result<(), ApplicationError> {
+        self.deps
+            .user_block_repository
+            .unblock(UserId::from(blocker_id), UserId::from(blocked_id))
+            .await?;
+        Ok(())
+    }
+
+    /// 列出自己屏蔽的用户；只能查询自己的屏蔽列表
+    pub async fn list_blocked_users(
+        &self,
+        blocker_id: Uuid,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<UserProfile>, ApplicationError> {
+        Ok(self
+            .deps
+            .user_block_repository
+            .list_blocked(
+                UserId::from(blocker_id),
+                PaginationParams::with_offset(limit as i64, offset as i64),
+            )
+            .await?)
+    }
+
+    /// 房间历史消息，按游标分页；查看者屏蔽的用户发出的消息不返回
+    ///
+    /// 过滤发生在分页之后，游标仍按原始消息推进，某一页可能少于 limit 条
+    pub async fn get_history(
+        &self,
+        room_id: Uuid,
+        viewer_id: Uuid,
+        limit: u32,
+        before: Option<Uuid>,
+    ) -> Result<CursorPage<Message>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let before = before.map(MessageId::from);
+
+        let mut page = self
+            .deps
+            .message_repository
+            .find_page_by_room(room_id, limit, before)
+            .await?;
+        page.items = self
+            .without_blocked(UserId::from(viewer_id), page.items, |message| {
+                message.sender_id
+            })
+            .await?;
+
+        Ok(page)
+    }
+
+    /// 邀请用户加入房间 - 唯一的加入房间方法
+    ///
+    /// Linus式"单一职责"：一个功能只有一个入口点
+    /// 统一处理所有加入房间的场景：管理员邀请、用户自助加入等
+    pub async fn invite_member(
+        &self,
+        request: InviteMemberRequest,
+    ) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let inviter_id = UserId::from(request.inviter_id);
+        let invitee_id = UserId::from(request.invitee_id);
+
+        // 检查房间是否存在且开放
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
         }
 
         // 检查被邀请人是否已经在房间里
         if self
             .deps
             .member_repository
-            .find(room_id, invitee_id)
+            .find(room_id, invitee_id)
+            .await?
+            .is_some()
+        {
+            return Err(DomainError::UserAlreadyInRoom.into());
+        }
+
+        // 被封禁的用户既不能自助加入也不能被邀请
+        self.ensure_not_banned(room_id, invitee_id).await?;
+
+        // 权限检查：自助加入 vs 管理员邀请
+        if inviter_id == invitee_id {
+            // 自助加入：验证私有房间密码
+            if matches!(room.visibility, ChatRoomVisibility::Private) {
+                let password = request.password.ok_or(DomainError::RoomIsPrivate)?;
+                let hashed = room.password.as_ref().ok_or(DomainError::RoomIsPrivate)?;
+                let valid = self.deps.password_hasher.verify(&password, hashed).await?;
+                if !valid {
+                    return Err(DomainError::RoomIsPrivate.into());
+                }
+            }
+        } else {
+            // 管理员邀请：检查邀请人权限
+            self.check_admin_permission(room_id, inviter_id).await?;
+
+            // 管理员邀请私有房间用户也需要密码
+            if matches!(room.visibility, ChatRoomVisibility::Private) {
+                let password = request.password.ok_or(DomainError::RoomIsPrivate)?;
+                let hashed = room.password.as_ref().ok_or(DomainError::RoomIsPrivate)?;
+                let valid = self.deps.password_hasher.verify(&password, hashed).await?;
+                if !valid {
+                    return Err(DomainError::RoomIsPrivate.into());
+                }
+            }
+        }
+
+        let now = self.deps.clock.now();
+        let member = RoomMember::new(room_id, invitee_id, RoomRole::Member, now);
+        self.deps.member_repository.upsert(member).await?;
+
+        // 被管理员拉进房间时通知本人；通知失败不影响已经完成的加入
+        if inviter_id != invitee_id {
+            self.notify(Notification::room_invite(
+                invitee_id, inviter_id, room_id, now,
+            ))
+            .await;
+        }
+        Ok(())
+    }
+
+    /// 管理员邀请用户入房，被邀请人接受后才加入
+    pub async fn invite(
+        &self,
+        request: CreateInvitationRequest,
+    ) -> Result<RoomInvitation, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let inviter_id = UserId::from(request.inviter_id);
+        let invitee_id = UserId::from(request.invitee_id);
+
+        if inviter_id == invitee_id {
+            return Err(
+                DomainError::invalid_argument("invitee_id", "cannot invite yourself").into(),
+            );
+        }
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
+        }
+
+        self.check_admin_permission(room_id, inviter_id).await?;
+
+        self.deps
+            .user_repository
+            .find_by_id(invitee_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        if self
+            .deps
+            .member_repository
+            .find(room_id, invitee_id)
+            .await?
+            .is_some()
+        {
+            return Err(DomainError::UserAlreadyInRoom.into());
+        }
+        self.ensure_not_banned(room_id, invitee_id).await?;
+
+        let now = self.deps.clock.now();
+        let ttl = time::Duration::seconds(self.deps.message_config.invitation_ttl_secs as i64);
+        let invitation = RoomInvitation::new(room_id, inviter_id, invitee_id, now, now + ttl);
+        if !self.deps.invitation_repository.create(&invitation).await? {
+            return Err(DomainError::InvitationAlreadyPending.into());
+        }
+
+        self.notify(Notification::room_invitation(
+            invitee_id,
+            inviter_id,
+            room_id,
+            invitation.id,
+            now,
+        ))
+        .await;
+        Ok(invitation)
+    }
+
+    /// 当前用户收到的待处理邀请
+    pub async fn list_invitations(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<RoomInvitation>, ApplicationError> {
+        Ok(self
+            .deps
+            .invitation_repository
+            .list_pending_for_user(UserId::from(user_id), self.deps.clock.now())
+            .await?)
+    }
+
+    /// 被邀请人接受邀请，在同一事务里加入房间
+    pub async fn accept_invitation(
+        &self,
+        invitation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<RoomMember, ApplicationError> {
+        let user_id = UserId::from(user_id);
+        let now = self.deps.clock.now();
+        let invitation = self
+            .pending_invitation_for_invitee(invitation_id, user_id, now)
+            .await?;
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(invitation.room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
+        }
+        // 发出邀请之后才被封禁的用户不能凭旧邀请进房
+        self.ensure_not_banned(invitation.room_id, user_id).await?;
+
+        let member = RoomMember::new(invitation.room_id, user_id, RoomRole::Member, now);
+        self.deps
+            .invitation_repository
+            .accept(invitation_id, member, now)
+            .await?
+            .ok_or(DomainError::InvitationNotPending)?;
+
+        Ok(self
+            .deps
+            .member_repository
+            .find(invitation.room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?)
+    }
+
+    /// 被邀请人拒绝邀请
+    pub async fn decline_invitation(
+        &self,
+        invitation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<RoomInvitation, ApplicationError> {
+        let now = self.deps.clock.now();
+        self.pending_invitation_for_invitee(invitation_id, UserId::from(user_id), now)
+            .await?;
+
+        Ok(self
+            .deps
+            .invitation_repository
+            .resolve(invitation_id, InvitationStatus::Declined, now)
+            .await?
+            .ok_or(DomainError::InvitationNotPending)?)
+    }
+
+    /// 邀请人或房间管理员撤回待处理的邀请
+    pub async fn revoke_invitation(
+        &self,
+        invitation_id: Uuid,
+        operator_id: Uuid,
+    ) -> Result<RoomInvitation, ApplicationError> {
+        let operator_id = UserId::from(operator_id);
+        let invitation = self
+            .deps
+            .invitation_repository
+            .find_by_id(invitation_id)
+            .await?
+            .ok_or(DomainError::InvitationNotFound)?;
+        if invitation.inviter_id != operator_id {
+            self.check_admin_permission(invitation.room_id, operator_id)
+                .await?;
+        }
+
+        let now = self.deps.clock.now();
+        if invitation.is_expired(now) {
+            return Err(DomainError::InvitationExpired.into());
+        }
+        Ok(self
+            .deps
+            .invitation_repository
+            .resolve(invitation_id, InvitationStatus::Revoked, now)
+            .await?
+            .ok_or(DomainError::InvitationNotPending)?)
+    }
+
+    /// 房间管理员创建可分享的邀请链接
+    pub async fn create_invite(
+        &self,
+        request: CreateInviteRequest,
+    ) -> Result<RoomInvite, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let creator_id = UserId::from(request.creator_id);
+
+        let max_uses = match request.max_uses {
+            Some(0) => {
+                return Err(DomainError::invalid_argument("max_uses", "must be positive").into())
+            }
+            Some(max_uses) => Some(i32::try_from(max_uses).map_err(|_| {
+                DomainError::invalid_argument("max_uses", "must be at most 2147483647")
+            })?),
+            None => None,
+        };
+        let now = self.deps.clock.now();
+        let expires_at = match request.expires_in_secs {
+            Some(0) => {
+                return Err(
+                    DomainError::invalid_argument("expires_in_secs", "must be positive").into(),
+                )
+            }
+            Some(secs) => Some(
+                i64::try_from(secs)
+                    .ok()
+                    .and_then(|secs| now.checked_add(time::Duration::seconds(secs)))
+                    .ok_or(DomainError::invalid_argument(
+                        "expires_in_secs",
+                        "is too large",
+                    ))?,
+            ),
+            None => None,
+        };
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
+        }
+        self.check_admin_permission(room_id, creator_id).await?;
+
+        let invite = RoomInvite::new(
+            generate_invite_token(),
+            room_id,
+            creator_id,
+            max_uses,
+            expires_at,
+            now,
+        );
+        self.deps.invite_repository.create(&invite).await?;
+        Ok(invite)
+    }
+
+    /// 凭邀请链接加入房间，私密房间也不需要密码
+    pub async fn redeem_invite(
+        &self,
+        token: &str,
+        user_id: Uuid,
+    ) -> Result<RoomMember, ApplicationError> {
+        let user_id = UserId::from(user_id);
+        let now = self.deps.clock.now();
+        let invite = self
+            .deps
+            .invite_repository
+            .find_by_token(token)
+            .await?
+            .ok_or(DomainError::InviteNotFound)?;
+        check_invite_usable(&invite, now)?;
+
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(invite.room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.is_closed {
+            return Err(DomainError::RoomClosed.into());
+        }
+        if self
+            .deps
+            .member_repository
+            .find(invite.room_id, user_id)
             .await?
             .is_some()
         {
             return Err(DomainError::UserAlreadyInRoom.into());
         }
+        self.ensure_not_banned(invite.room_id, user_id).await?;
+
+        let member = RoomMember::new(invite.room_id, user_id, RoomRole::Member, now);
+        match self.deps.invite_repository.redeem(token, member, now).await {
+            Ok(Some(_)) => {}
+            // 检查之后被别人用掉了最后一次，或者刚好过期
+            Ok(None) => {
+                let invite = self
+                    .deps
+                    .invite_repository
+                    .find_by_token(token)
+                    .await?
+                    .ok_or(DomainError::InviteNotFound)?;
+                check_invite_usable(&invite, now)?;
+                return Err(DomainError::InviteExhausted.into());
+            }
+            Err(RepositoryError::Conflict) => return Err(DomainError::UserAlreadyInRoom.into()),
+            Err(err) => return Err(err.into()),
+        }
 
-        // 权限检查：自助加入 vs 管理员邀请
-        if inviter_id == invitee_id {
-            // 自助加入：验证私有房间密码
-            if matches!(room.visibility, ChatRoomVisibility::Private) {
-                let password = request.password.ok_or(DomainError::RoomIsPrivate)?;
-                let hashed = room.password.as_ref().ok_or(DomainError::RoomIsPrivate)?;
-                let valid = self.deps.password_hasher.verify(&password, hashed).await?;
-                if !valid {
-                    return Err(DomainError::RoomIsPrivate.into());
-                }
+        Ok(self
+            .deps
+            .member_repository
+            .find(invite.room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?)
+    }
+
+    /// 发给 `user_id` 且仍可处理的邀请；发给别人的邀请按不存在处理
+    async fn pending_invitation_for_invitee(
+        &self,
+        invitation_id: Uuid,
+        user_id: UserId,
+        now: Timestamp,
+    ) -> Result<RoomInvitation, ApplicationError> {
+        let invitation = self
+            .deps
+            .invitation_repository
+            .find_by_id(invitation_id)
+            .await?
+            .filter(|invitation| invitation.invitee_id == user_id)
+            .ok_or(DomainError::InvitationNotFound)?;
+        if invitation.is_expired(now) {
+            return Err(DomainError::InvitationExpired.into());
+        }
+        if !invitation.is_pending(now) {
+            return Err(DomainError::InvitationNotPending.into());
+        }
+        Ok(invitation)
+    }
+
+    // 踢出用户（只有owner和admin可以）
+    pub async fn remove_member(
+        &self,
+        request: RemoveMemberRequest,
+    ) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let operator_id = UserId::from(request.operator_id);
+        let target_user_id = UserId::from(request.target_user_id);
+
+        // 检查操作者权限
+        let operator = self.check_admin_permission(room_id, operator_id).await?;
+
+        // 检查目标用户是否在房间里
+        let target_member = self
+            .deps
+            .member_repository
+            .find(room_id, target_user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 权限检查：admin不能踢owner
+        if matches!(target_member.role, RoomRole::Owner) {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        // admin不能踢admin，只有owner可以踢admin
+        if matches!(target_member.role, RoomRole::Admin) && matches!(operator.role, RoomRole::Admin)
+        {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        // 不能踢自己
+        if operator_id == target_user_id {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        let audit = AuditLogEntry::record(
+            operator_id,
+            AuditLogEntry::MEMBER_REMOVED,
+            Some(target_user_id),
+            serde_json::json!({ "role": target_member.role }),
+            self.deps.clock.now(),
+        )
+        .for_room(room_id);
+        self.deps
+            .member_repository
+            .delete_member_audited(room_id, target_user_id, audit)
+            .await?;
+        Ok(())
+    }
+
+    /// 禁言成员：到期前发送消息会被拒绝
+    pub async fn mute_member(
+        &self,
+        request: MuteMemberRequest,
+    ) -> Result<RoomMemberRestriction, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let target_user_id = UserId::from(request.target_user_id);
+
+        if request.duration_secs == 0 || request.duration_secs > MAX_MUTE_SECS {
+            return Err(DomainError::invalid_argument(
+                "duration_secs",
+                "must be between 1 second and 365 days",
+            )
+            .into());
+        }
+
+        self.check_restriction_target(room_id, request.operator_id, target_user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        let muted_until =
+            self.deps.clock.now() + time::Duration::seconds(request.duration_secs as i64);
+        Ok(self
+            .deps
+            .member_repository
+            .set_muted_until(room_id, target_user_id, Some(muted_until))
+            .await?)
+    }
+
+    pub async fn unmute_member(
+        &self,
+        request: MemberRestrictionRequest,
+    ) -> Result<RoomMemberRestriction, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let target_user_id = UserId::from(request.target_user_id);
+
+        self.check_restriction_target(room_id, request.operator_id, target_user_id)
+            .await?;
+
+        Ok(self
+            .deps
+            .member_repository
+            .set_muted_until(room_id, target_user_id, None)
+            .await?)
+    }
+
+    /// 封禁用户：移出房间，并且在解封前不能重新加入或被邀请
+    ///
+    /// 目标不必是当前成员，可以提前封禁
+    /// 封禁用户：移出房间并禁止通过加入、邀请或邀请链接回来，踢出则可以立即重新加入
+    pub async fn ban_member(&self, request: BanMemberRequest) -> Result<RoomBan, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let target_user_id = UserId::from(request.target_user_id);
+
+        let reason = match request.reason.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(reason) if reason.chars().count() > MAX_BAN_REASON_CHARS => {
+                return Err(DomainError::invalid_argument(
+                    "reason",
+                    "must be at most 500 characters",
+                )
+                .into())
             }
-        } else {
-            // 管理员邀请：检查邀请人权限
-            self.check_admin_permission(room_id, inviter_id).await?;
+            Some(reason) => Some(reason.to_string()),
+        };
 
-            // 管理员邀请私有房间用户也需要密码
-            if matches!(room.visibility, ChatRoomVisibility::Private) {
-                let password = request.password.ok_or(DomainError::RoomIsPrivate)?;
-                let hashed = room.password.as_ref().ok_or(DomainError::RoomIsPrivate)?;
-                let valid = self.deps.password_hasher.verify(&password, hashed).await?;
-                if !valid {
-                    return Err(DomainError::RoomIsPrivate.into());
-                }
+        let target_member = self
+            .check_restriction_target(room_id, request.operator_id, target_user_id)
+            .await?;
+        if target_member.is_none() {
+            self.deps
+                .user_repository
+                .find_by_id(target_user_id)
+                .await?
+                .ok_or(DomainError::UserNotFound)?;
+        }
+
+        Ok(self
+            .deps
+            .member_repository
+            .ban(
+                room_id,
+                target_user_id,
+                UserId::from(request.operator_id),
+                reason,
+            )
+            .await?)
+    }
+
+    /// 解除封禁，用户可以重新加入；没有被封禁时什么也不做
+    pub async fn unban_member(
+        &self,
+        request: MemberRestrictionRequest,
+    ) -> Result<(), ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let target_user_id = UserId::from(request.target_user_id);
+
+        self.check_restriction_target(room_id, request.operator_id, target_user_id)
+            .await?;
+
+        self.deps
+            .member_repository
+            .unban(room_id, target_user_id)
+            .await?;
+        Ok(())
+    }
+
+    /// 房间的封禁名单（能管理成员的人可见）
+    pub async fn list_bans(
+        &self,
+        room_id: Uuid,
+        operator_id: Uuid,
+    ) -> Result<Vec<RoomBan>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, UserId::from(operator_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if !operator.role.can_manage_members() {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        Ok(self.deps.member_repository.list_bans(room_id).await?)
+    }
+
+    /// 禁言/封禁的权限检查，返回目标当前的成员记录（不在房间时为 None）
+    ///
+    /// 操作者必须能管理成员；房主不能被限制，不能限制自己，admin 不能限制 admin
+    async fn check_restriction_target(
+        &self,
+        room_id: RoomId,
+        operator_id: Uuid,
+        target_user_id: UserId,
+    ) -> Result<Option<RoomMember>, ApplicationError> {
+        let operator_id = UserId::from(operator_id);
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        let operator = self
+            .deps
+            .member_repository
+            .find(room_id, operator_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if !operator.role.can_manage_members() {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        if operator_id == target_user_id || room.owner_id == target_user_id {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        let target_member = self
+            .deps
+            .member_repository
+            .find(room_id, target_user_id)
+            .await?;
+        if let Some(target) = &target_member {
+            if target.role.is_owner()
+                || (matches!(target.role, RoomRole::Admin)
+                    && matches!(operator.role, RoomRole::Admin))
+            {
+                return Err(DomainError::OperationNotAllowed.into());
             }
         }
 
-        let member = RoomMember::new(room_id, invitee_id, RoomRole::Member, self.deps.clock.now());
-        self.deps.member_repository.upsert(member).await?;
-        Ok(())
+        Ok(target_member)
+    }
+
+    async fn ensure_not_banned(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<(), ApplicationError> {
+        if self
+            .deps
+            .member_repository
+            .is_banned(room_id, user_id)
+            .await?
+        {
+            return Err(DomainError::UserBannedFromRoom.into());
+        }
+        Ok(())
+    }
+
+    /// 获取消息所在线程；传入线程中任意一条消息都会解析到根消息
+    pub async fn get_thread(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        limit: u32,
+        offset: u32,
+    ) -> Result<MessageThread, ApplicationError> {
+        let message = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(message_id))
+            .await?
+            .ok_or(DomainError::MessageNotFound)?;
+
+        self.deps
+            .member_repository
+            .find(message.room_id, UserId::from(user_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        // 根消息被删除时线程仍然可以访问，回复不会成为孤儿
+        let root_id = message.thread_root();
+        let replies = self
+            .deps
+            .message_repository
+            .find_thread(
+                root_id,
+                PaginationParams::with_offset(limit as i64, offset as i64),
+            )
+            .await?;
+        let replies = self
+            .without_blocked(UserId::from(user_id), replies, |reply| {
+                reply.message.sender_id
+            })
+            .await?;
+        let reply_count = self
+            .deps
+            .message_repository
+            .thread_reply_count(root_id)
+            .await?;
+
+        Ok(MessageThread {
+            root_id,
+            replies,
+            reply_count,
+        })
+    }
+
+    /// 消息的完整编辑历史，只有发送者本人和房间 owner/admin 可以查看
+    pub async fn message_revisions(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, ApplicationError> {
+        let user_id = UserId::from(user_id);
+        let message = self
+            .deps
+            .message_repository
+            .find_by_id(MessageId::from(message_id))
+            .await?
+            .filter(|message| !message.is_deleted)
+            .ok_or(DomainError::MessageNotFound)?;
+
+        let member = self
+            .deps
+            .member_repository
+            .find(message.room_id, user_id)
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+        if message.sender_id != user_id && !member.role.has_admin_access() {
+            return Err(DomainError::InsufficientPermissions.into());
+        }
+
+        Ok(self.deps.message_repository.revisions(message.id).await?)
+    }
+
+    /// 查询单个成员详情，调用者必须是房间成员
+    ///
+    /// 目标不是成员时统一返回 MemberNotFound，不区分用户是否存在
+    pub async fn get_member(
+        &self,
+        room_id: Uuid,
+        requester_id: Uuid,
+        target_user_id: Uuid,
+    ) -> Result<RoomMemberDetails, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let target_user_id = UserId::from(target_user_id);
+
+        self.deps
+            .member_repository
+            .find(room_id, UserId::from(requester_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        let member = self
+            .deps
+            .member_repository
+            .find(room_id, target_user_id)
+            .await?
+            .ok_or(DomainError::MemberNotFound)?;
+
+        let user = self
+            .deps
+            .user_repository
+            .find_by_id(target_user_id)
+            .await?
+            .ok_or(DomainError::MemberNotFound)?;
+
+        Ok(RoomMemberDetails {
+            user: UserProfile::from(user),
+            role: member.role,
+            joined_at: member.joined_at,
+            last_read_message_id: member.last_read_message,
+        })
+    }
+
+    /// 分页列出房间成员，调用者必须是房间成员
+    pub async fn list_members(
+        &self,
+        room_id: Uuid,
+        requester_id: Uuid,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<RoomMemberListing>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        self.deps
+            .member_repository
+            .find(room_id, UserId::from(requester_id))
+            .await?
+            .ok_or(DomainError::UserNotInRoom)?;
+
+        Ok(self
+            .deps
+            .member_repository
+            .list_by_room_paginated(room_id, page, page_size)
+            .await?)
     }
 
-    // 踢出用户（只有owner和admin可以）
-    pub async fn remove_member(
+    /// 调整成员角色：只有 Owner 可以授予/撤销 Admin
+    ///
+    /// Owner 角色不能通过这里转让，也不能调整自己的角色
+    pub async fn update_member_role(
         &self,
-        request: RemoveMemberRequest,
-    ) -> Result<(), ApplicationError> {
+        request: UpdateMemberRoleRequest,
+    ) -> Result<RoomMember, ApplicationError> {
         let room_id = RoomId::from(request.room_id);
         let operator_id = UserId::from(request.operator_id);
         let target_user_id = UserId::from(request.target_user_id);
 
-        // 检查操作者权限
-        let operator = self.check_admin_permission(room_id, operator_id).await?;
+        if matches!(request.role, RoomRole::Owner) {
+            return Err(DomainError::RoleChangeNotAllowed {
+                reason: "owner role cannot be assigned",
+            }
+            .into());
+        }
+        if operator_id == target_user_id {
+            return Err(DomainError::RoleChangeNotAllowed {
+                reason: "cannot change your own role",
+            }
+            .into());
+        }
 
-        // 检查目标用户是否在房间里
-        let target_member = self
+        let mut target_member = self
             .deps
             .member_repository
             .find(room_id, target_user_id)
             .await?
             .ok_or(DomainError::UserNotInRoom)?;
 
-        // 权限检查：admin不能踢owner
         if matches!(target_member.role, RoomRole::Owner) {
-            return Err(DomainError::OperationNotAllowed.into());
+            return Err(DomainError::RoleChangeNotAllowed {
+                reason: "cannot change the owner's role",
+            }
+            .into());
         }
 
-        // admin不能踢admin，只有owner可以踢admin
-        if matches!(target_member.role, RoomRole::Admin) && matches!(operator.role, RoomRole::Admin)
+        self.check_owner_permission(room_id, operator_id).await?;
+
+        if target_member.role == request.role {
+            return Ok(target_member);
+        }
+
+        let audit = AuditLogEntry::record(
+            operator_id,
+            AuditLogEntry::MEMBER_ROLE_CHANGED,
+            Some(target_user_id),
+            serde_json::json!({ "old_role": target_member.role, "new_role": request.role }),
+            self.deps.clock.now(),
+        )
+        .for_room(room_id);
+        target_member.role = request.role;
+        let updated = self
+            .deps
+            .member_repository
+            .upsert_audited(target_member, audit)
+            .await?;
+
+        // 角色已持久化，广播失败只影响实时刷新，客户端重连后会重新拉取
+        if let Err(broadcast_error) = self
+            .deps
+            .broadcaster
+            .broadcast(MessageBroadcast::member_role_changed(
+                room_id,
+                target_user_id,
+                updated.role.clone(),
+            ))
+            .await
         {
-            return Err(DomainError::OperationNotAllowed.into());
+            tracing::warn!(
+                room_id = %room_id,
+                user_id = %target_user_id,
+                error = %broadcast_error,
+                "成员角色已更新，但角色变化广播失败"
+            );
         }
+        self.notify(Notification::role_changed(
+            target_user_id,
+            operator_id,
+            room_id,
+            &updated.role,
+            self.deps.clock.now(),
+        ))
+        .await;
 
-        // 不能踢自己
-        if operator_id == target_user_id {
-            return Err(DomainError::OperationNotAllowed.into());
+        Ok(updated)
+    }
+
+    /// 把房间转让给另一个成员：新 Owner 升为 Owner，原 Owner 降为 Admin，之后原 Owner 可以离开
+    pub async fn transfer_ownership(
+        &self,
+        request: TransferOwnershipRequest,
+    ) -> Result<ChatRoom, ApplicationError> {
+        let room_id = RoomId::from(request.room_id);
+        let operator_id = UserId::from(request.operator_id);
+        let new_owner_id = UserId::from(request.new_owner_id);
+
+        if operator_id == new_owner_id {
+            return Err(DomainError::RoleChangeNotAllowed {
+                reason: "cannot transfer ownership to yourself",
+            }
+            .into());
         }
 
         self.deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        self.check_owner_permission(room_id, operator_id).await?;
+        let new_owner = self
+            .deps
             .member_repository
-            .remove(room_id, target_user_id)
+            .find(room_id, new_owner_id)
+            .await?
+            .ok_or(DomainError::MemberNotFound)?;
+
+        let now = self.deps.clock.now();
+        let audit = AuditLogEntry::record(
+            operator_id,
+            AuditLogEntry::ROOM_OWNERSHIP_TRANSFERRED,
+            Some(new_owner_id),
+            serde_json::json!({
+                "previous_owner": operator_id,
+                "new_owner": new_owner_id,
+                "new_owner_previous_role": new_owner.role,
+            }),
+            now,
+        )
+        .for_room(room_id);
+        let room = self
+            .deps
+            .room_repository
+            .transfer_ownership(room_id, operator_id, new_owner_id, now, audit)
             .await?;
-        Ok(())
+
+        // 所有权已持久化，广播失败只影响实时刷新
+        for (user_id, role) in [
+            (new_owner_id, RoomRole::Owner),
+            (operator_id, RoomRole::Admin),
+        ] {
+            if let Err(broadcast_error) = self
+                .deps
+                .broadcaster
+                .broadcast(MessageBroadcast::member_role_changed(
+                    room_id, user_id, role,
+                ))
+                .await
+            {
+                tracing::warn!(
+                    room_id = %room_id,
+                    user_id = %user_id,
+                    error = %broadcast_error,
+                    "房间所有权已转让，但角色变化广播失败"
+                );
+            }
+        }
+        self.notify(Notification::role_changed(
+            new_owner_id,
+            operator_id,
+            room_id,
+            &RoomRole::Owner,
+            now,
+        ))
+        .await;
+
+        Ok(room)
     }
 
     // 更新房间信息（只有owner和admin可以）
@@ -390,8 +2961,12 @@ impl ChatService {
             .await?
             .ok_or(DomainError::RoomNotFound)?;
 
-        // 更新房间信息
+        // 更新房间信息，同时记下每项实际变化，写入审计日志
+        let mut changes = Vec::new();
         if let Some(name) = request.name {
+            if name != room.name {
+                changes.push(RoomSettingDiff::new("name", &room.name, &name));
+            }
             room.name = name;
         }
 
@@ -400,18 +2975,181 @@ impl ChatService {
             if matches!(visibility, ChatRoomVisibility::Private) && request.password.is_none() {
                 return Err(DomainError::RoomIsPrivate.into());
             }
+            if visibility != room.visibility {
+                changes.push(RoomSettingDiff::new(
+                    "visibility",
+                    &room.visibility,
+                    &visibility,
+                ));
+            }
             room.visibility = visibility;
         }
 
         if let Some(password) = request.password {
             let hashed = self.deps.password_hasher.hash(&password).await?;
             room.password = Some(hashed);
+            changes.push(RoomSettingDiff::redacted("password"));
+        }
+
+        let now = self.deps.clock.now();
+        if let Some(policy) = request.reaction_policy {
+            if policy != room.reaction_policy {
+                changes.push(RoomSettingDiff::new(
+                    "reaction_policy",
+                    room.reaction_policy,
+                    policy,
+                ));
+            }
+            room.set_reaction_policy(policy, now);
         }
 
         let updated = self.deps.room_repository.update(room).await?;
+
+        if !changes.is_empty() {
+            self.audit.record(
+                AuditLogEntry::record(
+                    operator_id,
+                    AuditLogEntry::ROOM_SETTINGS_UPDATED,
+                    None,
+                    serde_json::json!({ "changes": changes }),
+                    now,
+                )
+                .for_room(room_id),
+            );
+        }
+
         Ok(updated)
     }
 
+    /// 为房间开启消息静态加密（房间管理员），之后的新消息加密存储，开启后不能关闭
+    ///
+    /// 已经开启时直接返回房间
+    pub async fn enable_room_encryption(
+        &self,
+        room_id: Uuid,
+        operator_id: Uuid,
+    ) -> Result<ChatRoom, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let operator_id = UserId::from(operator_id);
+        self.check_admin_permission(room_id, operator_id).await?;
+
+        let encryption = self
+            .deps
+            .room_encryption
+            .as_ref()
+            .ok_or(DomainError::EncryptionNotConfigured)?;
+        let room = self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        if room.encrypted {
+            return Ok(room);
+        }
+
+        if encryption.enable(room_id).await? {
+            let changes = vec![RoomSettingDiff::new("encrypted", false, true)];
+            self.audit.record(
+                AuditLogEntry::record(
+                    operator_id,
+                    AuditLogEntry::ROOM_SETTINGS_UPDATED,
+                    None,
+                    serde_json::json!({ "changes": changes }),
+                    self.deps.clock.now(),
+                )
+                .for_room(room_id),
+            );
+        }
+
+        Ok(self
+            .deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?)
+    }
+
+    /// 房间设置的修改历史，按时间先后排列（房间管理员或系统管理员）
+    pub async fn room_settings_history(
+        &self,
+        room_id: Uuid,
+        operator_id: Uuid,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<RoomSettingsChange>, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        self.deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+        self.check_admin_access(UserId::from(operator_id), Some(room_id))
+            .await?;
+
+        let entries = self
+            .deps
+            .audit_log_repository
+            .find_by_target_room(
+                room_id,
+                AuditLogEntry::ROOM_SETTINGS_UPDATED,
+                page,
+                page_size,
+            )
+            .await?;
+
+        Ok(PaginatedResult {
+            items: entries
+                .items
+                .into_iter()
+                .map(RoomSettingsChange::from)
+                .collect(),
+            total_count: entries.total_count,
+            page: entries.page,
+            page_size: entries.page_size,
+        })
+    }
+
+    /// 逐行导出房间在时间范围内的聊天记录，含已删除的消息（房间 owner 或系统管理员）
+    ///
+    /// `from` 含，`to` 不含，都为空时导出整个房间
+    pub async fn export_messages(
+        &self,
+        room_id: Uuid,
+        operator_id: Uuid,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    ) -> Result<ExportedMessageStream, ApplicationError> {
+        let room_id = RoomId::from(room_id);
+        let operator_id = UserId::from(operator_id);
+        if let (Some(from), Some(to)) = (from, to) {
+            if from >= to {
+                return Err(DomainError::invalid_argument("to", "must be after from").into());
+            }
+        }
+        self.deps
+            .room_repository
+            .find_by_id(room_id)
+            .await?
+            .ok_or(DomainError::RoomNotFound)?;
+
+        let operator = self
+            .deps
+            .user_repository
+            .find_by_id(operator_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        if !operator.is_system_admin() {
+            self.check_owner_permission(room_id, operator_id).await?;
+        }
+
+        Ok(self
+            .deps
+            .message_repository
+            .stream_by_time_range(room_id, from, to)
+            .await?)
+    }
+
     // 删除房间（只有owner可以）
     pub async fn delete_room(&self, request: DeleteRoomRequest) -> Result<(), ApplicationError> {
         let room_id = RoomId::from(request.room_id);
@@ -421,14 +3159,26 @@ impl ChatService {
         self.check_owner_permission(room_id, operator_id).await?;
 
         // 验证房间存在
-        self.deps
+        let room = self
+            .deps
             .room_repository
             .find_by_id(room_id)
             .await?
             .ok_or(DomainError::RoomNotFound)?;
 
         // 删除房间（这会级联删除成员和消息）
-        self.deps.room_repository.delete(room_id).await?;
+        let audit = AuditLogEntry::record(
+            operator_id,
+            AuditLogEntry::ROOM_DELETED,
+            None,
+            serde_json::json!({ "name": room.name }),
+            self.deps.clock.now(),
+        )
+        .for_room(room_id);
+        self.deps
+            .room_repository
+            .delete_audited(room_id, audit)
+            .await?;
         Ok(())
     }
 
@@ -444,6 +3194,288 @@ impl ChatService {
         }
     }
 
+    /// 停用账号（只有系统管理员可以）
+    ///
+    /// 令牌版本递增，已签发的令牌全部失效；刷新令牌和在线连接由调用方清理
+    pub async fn suspend_user(
+        &self,
+        operator_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<User, ApplicationError> {
+        self.set_user_suspended(UserId::from(operator_id), UserId::from(user_id), true)
+            .await
+    }
+
+    /// 重新启用被停用的账号（只有系统管理员可以）
+    pub async fn activate_user(
+        &self,
+        operator_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<User, ApplicationError> {
+        self.set_user_suspended(UserId::from(operator_id), UserId::from(user_id), false)
+            .await
+    }
+
+    async fn set_user_suspended(
+        &self,
+        operator_id: UserId,
+        user_id: UserId,
+        suspended: bool,
+    ) -> Result<User, ApplicationError> {
+        self.check_admin_access(operator_id, None).await?;
+        // 不能停用自己，避免把最后一个管理员锁在外面
+        if operator_id == user_id {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        let is_suspended = user.status == UserStatus::Suspended;
+        // 重复操作直接返回，不重复递增令牌版本和写审计日志
+        if is_suspended == suspended {
+            return Ok(user);
+        }
+
+        let now = self.deps.clock.now();
+        let previous_status = user.status.clone();
+        let action = if suspended {
+            user.suspend(now);
+            AuditLogEntry::USER_SUSPENDED
+        } else {
+            user.activate(now);
+            AuditLogEntry::USER_ACTIVATED
+        };
+        let audit = AuditLogEntry::record(
+            operator_id,
+            action,
+            Some(user_id),
+            serde_json::json!({ "previous_status": previous_status }),
+            now,
+        );
+        let updated = self
+            .deps
+            .user_repository
+            .update_audited(user, audit)
+            .await?;
+
+        tracing::info!(%operator_id, %user_id, action, "已修改账号状态");
+        Ok(updated)
+    }
+
+    /// 授予或撤销系统管理员权限（只有系统管理员可以）
+    ///
+    /// 不能修改自己的权限，避免把最后一个管理员锁在外面
+    pub async fn set_superuser(
+        &self,
+        operator_id: Uuid,
+        user_id: Uuid,
+        superuser: bool,
+    ) -> Result<User, ApplicationError> {
+        let operator_id = UserId::from(operator_id);
+        let user_id = UserId::from(user_id);
+        self.check_admin_access(operator_id, None).await?;
+        if operator_id == user_id {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        if user.is_superuser == superuser {
+            return Ok(user);
+        }
+
+        let now = self.deps.clock.now();
+        let action = if superuser {
+            user.grant_superuser(now);
+            AuditLogEntry::SUPERUSER_GRANTED
+        } else {
+            user.revoke_superuser(now);
+            AuditLogEntry::SUPERUSER_REVOKED
+        };
+        let audit = AuditLogEntry::record(
+            operator_id,
+            action,
+            Some(user_id),
+            serde_json::json!({}),
+            now,
+        );
+        let updated = self
+            .deps
+            .user_repository
+            .update_audited(user, audit)
+            .await?;
+
+        tracing::info!(%operator_id, %user_id, action, "已修改系统管理员权限");
+        Ok(updated)
+    }
+
+    /// 检索管理操作审计日志（只有系统管理员可以）
+    pub async fn search_audit_log(
+        &self,
+        operator_id: Uuid,
+        query: AuditLogQuery,
+    ) -> Result<PaginatedResult<AuditLogEntry>, ApplicationError> {
+        self.check_admin_access(UserId::from(operator_id), None)
+            .await?;
+        Ok(self.deps.audit_log_repository.search(&query).await?)
+    }
+
+    /// 清除用户在所有房间的内容（只有系统管理员可以）
+    ///
+    /// 分批软删除消息、移除回应，每条删除和每条受影响消息的最新回应都会广播；
+    /// `ban` 时再把用户移出所有非自己创建的房间并停用账号。操作记入审计日志
+    pub async fn purge_user_content(
+        &self,
+        request: PurgeUserContentRequest,
+    ) -> Result<PurgeUserContentReport, ApplicationError> {
+        let operator_id = UserId::from(request.operator_id);
+        let user_id = UserId::from(request.user_id);
+        self.check_admin_access(operator_id, None).await?;
+
+        // 不能清除自己
+        if operator_id == user_id {
+            return Err(DomainError::OperationNotAllowed.into());
+        }
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+
+        let mut report = PurgeUserContentReport::default();
+
+        // 1. 分批软删除消息；删除已经落库，广播失败只记录日志
+        let mut rooms_affected = HashSet::new();
+        loop {
+            let deleted = self
+                .deps
+                .message_repository
+                .soft_delete_batch_by_sender(user_id, PURGE_BATCH_SIZE)
+                .await?;
+            for (room_id, message_id) in &deleted {
+                rooms_affected.insert(*room_id);
+                if let Err(broadcast_error) = self
+                    .deps
+                    .broadcaster
+                    .broadcast(MessageBroadcast::message_deleted(*room_id, *message_id))
+                    .await
+                {
+                    tracing::warn!(
+                        room_id = %room_id,
+                        message_id = %message_id,
+                        error = %broadcast_error,
+                        "消息已删除，但删除广播失败"
+                    );
+                }
+            }
+            report.messages_deleted += deleted.len() as u64;
+            if (deleted.len() as i64) < PURGE_BATCH_SIZE {
+                break;
+            }
+        }
+        report.rooms_affected = rooms_affected.len() as u64;
+
+        // 2. 分批移除回应，再给仍然可见的消息广播最新聚合
+        let mut reacted = HashSet::new();
+        loop {
+            let removed = self
+                .deps
+                .reaction_repository
+                .remove_batch_by_user(user_id, PURGE_BATCH_SIZE)
+                .await?;
+            report.reactions_removed += removed.len() as u64;
+            let done = (removed.len() as i64) < PURGE_BATCH_SIZE;
+            reacted.extend(removed);
+            if done {
+                break;
+            }
+        }
+        let reacted: Vec<MessageId> = reacted.into_iter().collect();
+        for chunk in reacted.chunks(PURGE_BATCH_SIZE as usize) {
+            let messages = self.deps.message_repository.find_by_ids(chunk).await?;
+            for message in messages.iter().filter(|message| !message.is_deleted) {
+                self.publish_reactions(message, true).await?;
+            }
+        }
+
+        // 3. 封禁：移出所有房间（自己创建的房间保留），停用账号使令牌失效
+        if request.ban {
+            let memberships = self.deps.member_repository.find_by_user(user_id).await?;
+            for member in memberships
+                .into_iter()
+                .filter(|member| !matches!(member.role, RoomRole::Owner))
+            {
+                self.deps
+                    .member_repository
+                    .delete_member(member.room_id, user_id)
+                    .await?;
+                report.rooms_left += 1;
+            }
+
+            user.suspend(self.deps.clock.now());
+            self.deps.user_repository.update(user).await?;
+            report.banned = true;
+        }
+
+        self.audit.record(AuditLogEntry::record(
+            operator_id,
+            AuditLogEntry::USER_CONTENT_PURGED,
+            Some(user_id),
+            serde_json::to_value(&report).unwrap_or_default(),
+            self.deps.clock.now(),
+        ));
+
+        tracing::info!(
+            operator_id = %operator_id,
+            user_id = %user_id,
+            messages_deleted = report.messages_deleted,
+            reactions_removed = report.reactions_removed,
+            banned = report.banned,
+            "已清除用户内容"
+        );
+
+        Ok(report)
+    }
+
+    /// 非文本消息引用的上传文件
+    ///
+    /// 图片/文件消息的内容是上传接口返回的文件ID，只能引用自己上传、还没发送过的文件
+    async fn find_attachment(
+        &self,
+        sender_id: UserId,
+        message_type: &MessageType,
+        content: &MessageContent,
+    ) -> Result<Option<FileUpload>, ApplicationError> {
+        if *message_type == MessageType::Text {
+            return Ok(None);
+        }
+
+        let file_id = content.file_id()?;
+        let upload = self
+            .deps
+            .file_upload_repository
+            .find_unattached(file_id, sender_id)
+            .await?
+            .ok_or(DomainError::FileNotFound)?;
+        if *message_type == MessageType::Image && !upload.is_image() {
+            return Err(DomainError::invalid_argument(
+                "content",
+                "image messages must reference an image",
+            )
+            .into());
+        }
+        Ok(Some(upload))
+    }
+
     /// 统一的管理员权限检查 - Linus式业务逻辑集中化
     ///
     /// 权限规则：
@@ -489,3 +3521,20 @@ impl ChatService {
         }
     }
 }
+
+/// 邀请链接的令牌：16 字节随机数的十六进制表示
+fn generate_invite_token() -> String {
+    let bytes: [u8; 16] = rand::rng().random();
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// 过期和次数用完分别报错，客户端据此提示用户
+fn check_invite_usable(invite: &RoomInvite, now: Timestamp) -> Result<(), DomainError> {
+    if invite.is_expired(now) {
+        return Err(DomainError::InviteExpired);
+    }
+    if invite.is_exhausted() {
+        return Err(DomainError::InviteExhausted);
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
 mod bulk_user_service;
 mod chat_service;
+mod notification_service;
 mod password_service;
 mod stats_service;
+mod upload_service;
 mod user_service;
 
 pub use bulk_user_service::{
@@ -9,14 +11,25 @@ pub use bulk_user_service::{
     UserCredential,
 };
 pub use chat_service::{
-    ChatService, ChatServiceDependencies, CreateRoomRequest, DeleteRoomRequest,
-    InviteMemberRequest, LeaveRoomRequest, RemoveMemberRequest, SendMessageRequest,
-    UpdateRoomRequest,
+    BanMemberRequest, BulkDeleteMessagesRequest, ChatService, ChatServiceDependencies,
+    CreateInvitationRequest, CreateInviteRequest, CreateRoomRequest, DeleteMessageRequest,
+    DeleteRoomRequest, EditMessageRequest, InviteMemberRequest, JoinRoomRequest, LeaveRoomRequest,
+    MarkReadRequest, MarkRoomsReadRequest, MemberRestrictionRequest, MessageThread,
+    MuteMemberRequest, PinMessageRequest, PurgeUserContentReport, PurgeUserContentRequest,
+    ReactionRequest, ReconnectBackfill, RemoveMemberRequest, RestoreMessageRequest,
+    ResumeRoomRequest, RoomMemberDetails, RoomResumeState, RoomSettingsChange, SendMessageRequest,
+    SequenceReplay, TransferOwnershipRequest, UpdateMemberRoleRequest, UpdateRoomRequest,
 };
+pub use notification_service::{NotificationService, NotificationServiceDependencies};
 pub use password_service::PasswordService;
 pub use stats_service::{
-    Dimension, Granularity, RealtimeStats, StatsData, StatsService, TimeRange,
+    ActivityBucket, Dimension, Granularity, RealtimeStats, StatsData, StatsService, TimeRange,
+    MAX_ACTIVITY_BUCKETS,
+};
+pub use upload_service::{
+    spawn_orphan_upload_cleanup, UploadFileRequest, UploadService, UploadServiceDependencies,
 };
 pub use user_service::{
-    AuthenticateUserRequest, RegisterUserRequest, UserService, UserServiceDependencies,
+    AuthenticateUserRequest, RegisterUserRequest, UpdateProfileRequest, UserService,
+    UserServiceDependencies,
 };
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use domain::{DomainError, Notification, UserId};
+use uuid::Uuid;
+
+use crate::{
+    clock::Clock,
+    error::ApplicationError,
+    repository::{NotificationRepository, PaginatedResult},
+};
+
+pub struct NotificationServiceDependencies {
+    pub notification_repository: Arc<dyn NotificationRepository>,
+    pub clock: Arc<dyn Clock>,
+}
+
+/// 通知中心：用户查看、标记已读和删除自己的通知
+///
+/// 通知由提及、邀请、角色调整等流程产生；操作别人的通知一律按不存在处理，不泄露通知是否存在
+pub struct NotificationService {
+    deps: NotificationServiceDependencies,
+}
+
+impl NotificationService {
+    pub fn new(deps: NotificationServiceDependencies) -> Self {
+        Self { deps }
+    }
+
+    /// 当前用户的通知，最新的在前
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        unread_only: bool,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<Notification>, ApplicationError> {
+        Ok(self
+            .deps
+            .notification_repository
+            .find_for_user(UserId::from(user_id), unread_only, page, page_size)
+            .await?)
+    }
+
+    /// 把一条通知标为已读（已读的保持原来的已读时间），返回更新后的通知
+    pub async fn mark_read(
+        &self,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<Notification, ApplicationError> {
+        let user_id = UserId::from(user_id);
+        let notification = self.find(user_id, notification_id).await?;
+        if notification.is_read() {
+            return Ok(notification);
+        }
+
+        self.deps
+            .notification_repository
+            .mark_read(user_id, &[notification_id], self.deps.clock.now())
+            .await?;
+        // 返回库里保存的已读时间
+        self.find(user_id, notification_id).await
+    }
+
+    /// 把当前用户的全部通知标为已读，返回本次标记的条数
+    pub async fn mark_all_read(&self, user_id: Uuid) -> Result<u64, ApplicationError> {
+        Ok(self
+            .deps
+            .notification_repository
+            .mark_read(UserId::from(user_id), &[], self.deps.clock.now())
+            .await?)
+    }
+
+    /// 删除一条通知
+    pub async fn delete(
+        &self,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<(), ApplicationError> {
+        if !self
+            .deps
+            .notification_repository
+            .delete(UserId::from(user_id), notification_id)
+            .await?
+        {
+            return Err(DomainError::NotificationNotFound.into());
+        }
+        Ok(())
+    }
+
+    /// 用户自己的通知，别人的通知按不存在处理
+    async fn find(
+        &self,
+        user_id: UserId,
+        notification_id: Uuid,
+    ) -> Result<Notification, ApplicationError> {
+        Ok(self
+            .deps
+            .notification_repository
+            .find_for_user_by_id(user_id, notification_id)
+            .await?
+            .ok_or(DomainError::NotificationNotFound)?)
+    }
+
+    /// 未读通知数，供客户端轮询显示角标
+    pub async fn unread_count(&self, user_id: Uuid) -> Result<i64, ApplicationError> {
+        Ok(self
+            .deps
+            .notification_repository
+            .count_unread(UserId::from(user_id))
+            .await?)
+    }
+}
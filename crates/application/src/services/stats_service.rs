@@ -1,11 +1,17 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use domain::RepositoryError;
+use chrono::{DateTime, Datelike, DurationRound, Utc};
+use domain::{DomainError, RepositoryError, RoomId};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::error::ApplicationError;
+
+/// 房间活跃度曲线最多返回的桶数（按小时约一个月）
+pub const MAX_ACTIVITY_BUCKETS: usize = 720;
+
 /// 统一的统计数据查询服务
 /// 根据设计文档，这个服务提供统一的查询接口，支持多维度查询
 pub struct StatsService {
@@ -102,6 +108,43 @@ impl StatsService {
         }
     }
 
+    /// 房间消息量曲线：按粒度分桶计数，没有消息的桶补 0，序列连续
+    ///
+    /// 直接从 messages 表统计，不含已删除消息；只支持固定长度的粒度（小时、天、周）
+    pub async fn get_room_activity(
+        &self,
+        room_id: RoomId,
+        time_range: TimeRange,
+        granularity: Granularity,
+    ) -> Result<Vec<ActivityBucket>, ApplicationError> {
+        let buckets = activity_buckets(&time_range, &granularity)?;
+        let Some(&first) = buckets.first() else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query_as::<_, (DateTime<Utc>, i64)>(
+            r#"
+            SELECT date_trunc($1, created_at AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' AS bucket,
+                   COUNT(*)
+            FROM messages
+            WHERE room_id = $2
+              AND created_at >= $3
+              AND created_at < $4
+              AND is_deleted = FALSE
+            GROUP BY bucket
+            "#,
+        )
+        .bind(granularity_to_string(&granularity).to_lowercase())
+        .bind(Uuid::from(room_id))
+        .bind(first)
+        .bind(time_range.end_time)
+        .fetch_all(self.pool.as_ref())
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(fill_activity_gaps(&buckets, &rows.into_iter().collect()))
+    }
+
     fn build_room_query_sql(&self) -> String {
         r#"
         SELECT
@@ -248,6 +291,88 @@ pub struct RealtimeStats {
     pub timestamp: DateTime<Utc>,
 }
 
+/// 消息量曲线上的一个时间桶
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityBucket {
+    /// 桶的起始时间（UTC，按粒度对齐）
+    pub bucket: DateTime<Utc>,
+    pub message_count: i64,
+}
+
+impl Granularity {
+    /// 固定长度粒度的桶宽；月、年长度不固定，返回 None
+    fn fixed_width(&self) -> Option<chrono::Duration> {
+        match self {
+            Granularity::Hour => Some(chrono::Duration::hours(1)),
+            Granularity::Day => Some(chrono::Duration::days(1)),
+            Granularity::Week => Some(chrono::Duration::weeks(1)),
+            Granularity::Month | Granularity::Year => None,
+        }
+    }
+
+    /// 对齐到所在桶的起点，与 Postgres `date_trunc` 一致（周从周一开始）
+    fn truncate(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Granularity::Hour => time.duration_trunc(chrono::Duration::hours(1)).ok(),
+            Granularity::Day => time.duration_trunc(chrono::Duration::days(1)).ok(),
+            Granularity::Week => {
+                let day = time.duration_trunc(chrono::Duration::days(1)).ok()?;
+                Some(day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64))
+            }
+            Granularity::Month | Granularity::Year => None,
+        }
+    }
+}
+
+/// 时间范围覆盖的所有桶起点：从 `start_time` 所在桶开始，到 `end_time` 之前为止
+fn activity_buckets(
+    time_range: &TimeRange,
+    granularity: &Granularity,
+) -> Result<Vec<DateTime<Utc>>, ApplicationError> {
+    let (Some(width), Some(first)) = (
+        granularity.fixed_width(),
+        granularity.truncate(time_range.start_time),
+    ) else {
+        return Err(DomainError::invalid_argument(
+            "granularity",
+            "must be one of: hour, day, week",
+        )
+        .into());
+    };
+    if time_range.start_time >= time_range.end_time {
+        return Err(DomainError::invalid_argument("from", "must be earlier than to").into());
+    }
+
+    let mut buckets = Vec::new();
+    let mut bucket = first;
+    while bucket < time_range.end_time {
+        if buckets.len() == MAX_ACTIVITY_BUCKETS {
+            return Err(DomainError::invalid_argument(
+                "to",
+                "range covers too many buckets; narrow it or use a coarser granularity",
+            )
+            .into());
+        }
+        buckets.push(bucket);
+        bucket += width;
+    }
+    Ok(buckets)
+}
+
+/// 把稀疏的计数填进连续的桶序列，没有消息的桶计 0
+fn fill_activity_gaps(
+    buckets: &[DateTime<Utc>],
+    counts: &HashMap<DateTime<Utc>, i64>,
+) -> Vec<ActivityBucket> {
+    buckets
+        .iter()
+        .map(|bucket| ActivityBucket {
+            bucket: *bucket,
+            message_count: counts.get(bucket).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
 /// 数据库记录类型
 #[derive(Debug, sqlx::FromRow)]
 struct StatsDataRecord {
@@ -339,6 +464,56 @@ mod tests {
         assert_eq!(granularity_to_string(&Granularity::Year), "Year");
     }
 
+    fn at(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_activity_gaps_are_zero_filled() {
+        let range = TimeRange::new(at("2024-03-01T10:25:00Z"), at("2024-03-01T15:10:00Z"));
+        let buckets = activity_buckets(&range, &Granularity::Hour).unwrap();
+
+        let counts = HashMap::from([
+            (at("2024-03-01T10:00:00Z"), 3),
+            (at("2024-03-01T13:00:00Z"), 7),
+        ]);
+        let series = fill_activity_gaps(&buckets, &counts);
+
+        // 10 点到 15 点共 6 个桶，首尾对齐到整点，中间没有消息的桶为 0
+        let counts: Vec<i64> = series.iter().map(|b| b.message_count).collect();
+        assert_eq!(counts, [3, 0, 0, 7, 0, 0]);
+        assert_eq!(series[0].bucket, at("2024-03-01T10:00:00Z"));
+        for pair in series.windows(2) {
+            assert_eq!(pair[1].bucket - pair[0].bucket, chrono::Duration::hours(1));
+        }
+    }
+
+    #[test]
+    fn test_activity_week_buckets_start_on_monday() {
+        // 2024-03-07 是周四
+        let range = TimeRange::new(at("2024-03-07T08:00:00Z"), at("2024-03-20T00:00:00Z"));
+        let buckets = activity_buckets(&range, &Granularity::Week).unwrap();
+        assert_eq!(
+            buckets,
+            [
+                at("2024-03-04T00:00:00Z"),
+                at("2024-03-11T00:00:00Z"),
+                at("2024-03-18T00:00:00Z")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_activity_buckets_are_capped() {
+        let range = TimeRange::last_days(31);
+        assert!(activity_buckets(&range, &Granularity::Hour).is_err());
+        assert!(activity_buckets(&range, &Granularity::Day).is_ok());
+        assert!(activity_buckets(&range, &Granularity::Month).is_err());
+
+        let empty = TimeRange::new(at("2024-03-01T10:00:00Z"), at("2024-03-01T10:00:00Z"));
+        assert!(activity_buckets(&empty, &Granularity::Hour).is_err());
+    }
+
     #[test]
     fn test_time_range_creation() {
         let range = TimeRange::last_hours(24);
@@ -0,0 +1,203 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use config::UploadConfig;
+use domain::{DomainError, FileUpload, UserId};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+use crate::{
+    clock::Clock,
+    error::ApplicationError,
+    repository::FileUploadRepository,
+    storage::{ByteStream, ObjectStorage},
+};
+
+/// 每批清理的未引用文件数
+const ORPHAN_CLEANUP_BATCH: i64 = 100;
+
+/// 直接下载地址的有效期
+const PRESIGN_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+pub struct UploadServiceDependencies {
+    pub file_upload_repository: Arc<dyn FileUploadRepository>,
+    pub storage: Arc<dyn ObjectStorage>,
+    pub clock: Arc<dyn Clock>,
+    pub config: UploadConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadFileRequest {
+    pub uploader_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+}
+
+/// 文件上传与下载
+///
+/// 内容写入对象存储后才保存元数据；元数据保存失败时删掉已写入的内容
+pub struct UploadService {
+    deps: UploadServiceDependencies,
+}
+
+impl UploadService {
+    pub fn new(deps: UploadServiceDependencies) -> Self {
+        Self { deps }
+    }
+
+    /// 上传文件：检查内容类型，边写边计数，超过大小上限立即中止
+    pub async fn upload(
+        &self,
+        request: UploadFileRequest,
+        body: ByteStream,
+    ) -> Result<FileUpload, ApplicationError> {
+        if !self.deps.config.allows(&request.content_type) {
+            return Err(DomainError::UnsupportedContentType {
+                content_type: request.content_type,
+            }
+            .into());
+        }
+
+        let limit = self.deps.config.max_size_bytes;
+        let written = Arc::new(AtomicU64::new(0));
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let body: ByteStream = Box::pin({
+            let written = written.clone();
+            let exceeded = exceeded.clone();
+            body.map(move |chunk| {
+                let chunk = chunk?;
+                let total =
+                    written.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+                if total > limit {
+                    exceeded.store(true, Ordering::Relaxed);
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "file exceeds size limit",
+                    ));
+                }
+                Ok(chunk)
+            })
+        });
+
+        let content_type = request
+            .content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let pending = FileUpload::new(
+            Uuid::new_v4(),
+            UserId::from(request.uploader_id),
+            &request.file_name,
+            &content_type,
+            0,
+            self.deps.clock.now(),
+        );
+
+        let size = match self.deps.storage.put(&pending.storage_key, body).await {
+            Ok(size) => size,
+            Err(_) if exceeded.load(Ordering::Relaxed) => {
+                return Err(DomainError::FileTooLarge { limit }.into());
+            }
+            Err(err) => {
+                return Err(ApplicationError::infrastructure_with_source(
+                    "写入上传文件失败",
+                    err,
+                ));
+            }
+        };
+
+        let storage_key = pending.storage_key.clone();
+        let upload = FileUpload {
+            size_bytes: size as i64,
+            ..pending
+        };
+        match self.deps.file_upload_repository.create(upload).await {
+            Ok(upload) => Ok(upload),
+            Err(err) => {
+                if let Err(delete_err) = self.deps.storage.delete(&storage_key).await {
+                    tracing::warn!(%storage_key, error = %delete_err, "删除未登记的上传文件失败");
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    /// 下载文件：只有上传者和引用它的消息所在房间的成员可以下载
+    pub async fn download(
+        &self,
+        viewer_id: Uuid,
+        file_id: Uuid,
+    ) -> Result<(FileUpload, ByteStream), ApplicationError> {
+        let upload = self
+            .deps
+            .file_upload_repository
+            .find_visible(file_id, UserId::from(viewer_id))
+            .await?
+            .ok_or(DomainError::FileNotFound)?;
+        let body = self
+            .deps
+            .storage
+            .get(&upload.storage_key)
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("读取上传文件失败", err))?;
+        Ok((upload, body))
+    }
+
+    /// 文件的下载地址：存储支持时给有时效的直接地址，否则由 API 转发
+    pub async fn download_url(&self, upload: &FileUpload) -> Result<String, ApplicationError> {
+        let presigned = self
+            .deps
+            .storage
+            .presign(&upload.storage_key, PRESIGN_EXPIRY)
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("生成下载地址失败", err))?;
+        Ok(presigned.unwrap_or_else(|| format!("/api/v1/uploads/{}", upload.id)))
+    }
+
+    /// 清理上传后超过保留期仍未被消息引用的文件，返回清理的个数
+    ///
+    /// 先删元数据再删内容：元数据删掉后文件就不能再被消息引用，不会和发送消息冲突
+    pub async fn cleanup_orphans(&self) -> Result<u64, ApplicationError> {
+        let retention = time::Duration::hours(self.deps.config.orphan_retention_hours as i64);
+        let before = self.deps.clock.now() - retention;
+
+        let mut removed = 0;
+        loop {
+            let batch = self
+                .deps
+                .file_upload_repository
+                .delete_orphans(before, ORPHAN_CLEANUP_BATCH)
+                .await?;
+            for upload in &batch {
+                if let Err(err) = self.deps.storage.delete(&upload.storage_key).await {
+                    tracing::warn!(file_id = %upload.id, error = %err, "删除未引用的上传文件失败");
+                }
+            }
+            removed += batch.len() as u64;
+            if (batch.len() as i64) < ORPHAN_CLEANUP_BATCH {
+                return Ok(removed);
+            }
+        }
+    }
+}
+
+/// 启动后台清理任务：定期删除一直没有被消息引用的上传文件
+pub fn spawn_orphan_upload_cleanup(
+    service: Arc<UploadService>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.cleanup_orphans().await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "清理未引用的上传文件"),
+                Err(e) => tracing::warn!(error = %e, "清理未引用的上传文件失败"),
+            }
+        }
+    })
+}
@@ -1,11 +1,21 @@
 use std::sync::Arc;
 
-use domain::{User, UserEmail, UserId, UserStatus, Username};
+use config::RegistrationConfig;
+use domain::{
+    DomainError, EmailVerification, PasswordReset, ProfileChanges, User, UserEmail, UserId,
+    UserProfile, UserStatus, Username,
+};
+use rand::Rng;
+use ring::digest;
 use uuid::Uuid;
 
 use crate::{
-    clock::Clock, error::ApplicationError, password::PasswordHasher, presence::PresenceManager,
-    repository::UserRepository,
+    clock::Clock,
+    email::EmailSender,
+    error::ApplicationError,
+    password::PasswordHasher,
+    presence::PresenceManager,
+    repository::{EmailVerificationRepository, PasswordResetRepository, UserRepository},
 };
 
 #[derive(Debug, Clone)]
@@ -21,26 +31,100 @@ pub struct AuthenticateUserRequest {
     pub password: String,
 }
 
+/// 修改个人资料：字段为 None 表示不修改，空字符串表示清空
+///
+/// 用户名和邮箱不能通过这里修改
+#[derive(Debug, Clone, Default)]
+pub struct UpdateProfileRequest {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+}
+
 pub struct UserServiceDependencies {
     pub user_repository: Arc<dyn UserRepository>,
     pub password_hasher: Arc<dyn PasswordHasher>,
     pub clock: Arc<dyn Clock>,
     pub presence_manager: Arc<dyn PresenceManager>,
+    pub email_verification_repository: Arc<dyn EmailVerificationRepository>,
+    pub password_reset_repository: Arc<dyn PasswordResetRepository>,
+    pub email_sender: Arc<dyn EmailSender>,
+    pub registration_config: RegistrationConfig,
+}
+
+/// 注册邮箱域名策略
+///
+/// 拒绝列表优先；允许列表为空时放行所有未被拒绝的域名。
+/// `*.example.com` 只匹配子域名，不匹配 `example.com` 本身。
+#[derive(Debug, Clone, Default)]
+pub struct EmailDomainPolicy {
+    allowed: Vec<String>,
+    denied: Vec<String>,
+}
+
+impl EmailDomainPolicy {
+    pub fn from_config(config: &RegistrationConfig) -> Self {
+        let normalize = |domains: &[String]| {
+            domains
+                .iter()
+                .map(|domain| domain.trim().to_ascii_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect()
+        };
+        Self {
+            allowed: normalize(&config.allowed_email_domains),
+            denied: normalize(&config.denied_email_domains),
+        }
+    }
+
+    pub fn is_allowed(&self, domain: &str) -> bool {
+        let domain = domain.trim().to_ascii_lowercase();
+        if self
+            .denied
+            .iter()
+            .any(|pattern| matches_domain(pattern, &domain))
+        {
+            return false;
+        }
+        self.allowed.is_empty()
+            || self
+                .allowed
+                .iter()
+                .any(|pattern| matches_domain(pattern, &domain))
+    }
+}
+
+fn matches_domain(pattern: &str, domain: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(parent) => domain
+            .strip_suffix(parent)
+            .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+        None => pattern == domain,
+    }
 }
 
 pub struct UserService {
     deps: UserServiceDependencies,
+    email_policy: EmailDomainPolicy,
 }
 
 impl UserService {
     pub fn new(deps: UserServiceDependencies) -> Self {
-        Self { deps }
+        let email_policy = EmailDomainPolicy::from_config(&deps.registration_config);
+        Self { deps, email_policy }
     }
 
     pub async fn register(&self, request: RegisterUserRequest) -> Result<User, ApplicationError> {
         let username = Username::parse(request.username)?;
         let email = UserEmail::parse(request.email.clone())?;
 
+        if !self.email_policy.is_allowed(email.domain()) {
+            return Err(ApplicationError::Domain(
+                domain::DomainError::EmailDomainNotAllowed,
+            ));
+        }
+
         if self
             .deps
             .user_repository
@@ -53,6 +137,7 @@ impl UserService {
             ));
         }
 
+        User::validate_password(&request.password)?;
         let password_hash = self.deps.password_hasher.hash(&request.password).await?;
 
         let now = self.deps.clock.now();
@@ -63,12 +148,119 @@ impl UserService {
             password_hash,
             now,
         );
-        user.activate(now);
+        if self.deps.registration_config.require_email_verification {
+            user.require_verification(now);
+        } else {
+            user.activate(now);
+        }
 
         let stored = self.deps.user_repository.create(user).await?;
+        if stored.is_pending_verification() {
+            // 邮件发送失败不影响注册，用户可以稍后重发
+            if let Err(err) = self.send_verification(&stored).await {
+                tracing::warn!(error = %err, user_id = %stored.id, "发送验证邮件失败");
+            }
+        }
         Ok(stored)
     }
 
+    /// 用验证链接里的令牌激活账号；令牌不存在、过期或已使用都视为无效
+    pub async fn verify_email(&self, token: &str) -> Result<User, ApplicationError> {
+        let token_hash = hash_verification_token(token);
+        let verification = self
+            .deps
+            .email_verification_repository
+            .find_by_token_hash(&token_hash)
+            .await?
+            .ok_or(DomainError::InvalidVerificationToken)?;
+
+        let now = self.deps.clock.now();
+        if verification.is_used() || verification.is_expired(now) {
+            return Err(DomainError::InvalidVerificationToken.into());
+        }
+        if !self
+            .deps
+            .email_verification_repository
+            .consume(&token_hash, now)
+            .await?
+        {
+            return Err(DomainError::InvalidVerificationToken.into());
+        }
+
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(verification.user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        // 已激活（或被停用）的账号不因为旧链接而改变状态
+        if !user.is_pending_verification() {
+            return Ok(user);
+        }
+        user.activate(now);
+        let updated = self.deps.user_repository.update(user).await?;
+        Ok(updated)
+    }
+
+    /// 重发验证邮件
+    ///
+    /// 邮箱不存在或已验证时同样返回成功，不向调用方暴露账号是否存在
+    pub async fn resend_verification(&self, email: String) -> Result<(), ApplicationError> {
+        let email = UserEmail::parse(email)?;
+        let Some(user) = self.deps.user_repository.find_by_email(email).await? else {
+            return Ok(());
+        };
+        if !user.is_pending_verification() {
+            return Ok(());
+        }
+
+        let interval = time::Duration::seconds(
+            self.deps
+                .registration_config
+                .resend_verification_interval_secs as i64,
+        );
+        if let Some(latest) = self
+            .deps
+            .email_verification_repository
+            .find_latest_for_user(user.id)
+            .await?
+        {
+            let next_allowed = latest.created_at + interval;
+            let now = self.deps.clock.now();
+            if now < next_allowed {
+                let retry_after_secs = (next_allowed - now).whole_seconds().max(1) as u64;
+                return Err(DomainError::VerificationResendTooSoon { retry_after_secs }.into());
+            }
+        }
+
+        self.send_verification(&user).await
+    }
+
+    /// 签发新的验证令牌并发送邮件，只保存令牌哈希
+    async fn send_verification(&self, user: &User) -> Result<(), ApplicationError> {
+        let config = &self.deps.registration_config;
+        let token = generate_verification_token();
+        let now = self.deps.clock.now();
+        let expires_at = now + time::Duration::seconds(config.verification_token_ttl_secs as i64);
+
+        self.deps
+            .email_verification_repository
+            .create(EmailVerification::issue(
+                hash_verification_token(&token),
+                user.id,
+                expires_at,
+                now,
+            ))
+            .await?;
+
+        let url = format!("{}?token={}", config.verification_url, token);
+        self.deps
+            .email_sender
+            .send_verification(&user.email, &user.username, &url)
+            .await
+            .map_err(|err| ApplicationError::infrastructure_with_source("发送验证邮件失败", err))
+    }
+
     pub async fn authenticate(
         &self,
         request: AuthenticateUserRequest,
@@ -90,6 +282,13 @@ impl UserService {
             return Err(ApplicationError::Authentication);
         }
 
+        // 密码正确后才区分未验证，前端据此提示去验证邮箱
+        if user.is_pending_verification() {
+            return Err(DomainError::EmailNotVerified.into());
+        }
+        if user.status == UserStatus::Suspended {
+            return Err(DomainError::AccountSuspended.into());
+        }
         if user.status != UserStatus::Active {
             return Err(ApplicationError::Authentication);
         }
@@ -106,6 +305,169 @@ impl UserService {
             .map_err(ApplicationError::Repository)
     }
 
+    /// 其他用户可见的公开资料
+    pub async fn get_profile(&self, user_id: Uuid) -> Result<UserProfile, ApplicationError> {
+        let user = self
+            .deps
+            .user_repository
+            .find_by_id(UserId::from(user_id))
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+        Ok(UserProfile::from(user))
+    }
+
+    /// 修改自己的展示名、头像和简介，返回修改后的完整用户信息
+    pub async fn update_profile(
+        &self,
+        request: UpdateProfileRequest,
+    ) -> Result<User, ApplicationError> {
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(UserId::from(request.user_id))
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+
+        user.update_details(
+            ProfileChanges {
+                display_name: request.display_name,
+                avatar_url: request.avatar_url,
+                bio: request.bio,
+            },
+            self.deps.clock.now(),
+        )?;
+
+        let updated = self.deps.user_repository.update(user).await?;
+        Ok(updated)
+    }
+
+    /// 修改密码：先验证当前密码，新密码沿用注册时的强度规则且不能与当前密码相同
+    ///
+    /// 成功后令牌版本递增，之前签发的所有令牌都会失效
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<User, ApplicationError> {
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(UserId::from(user_id))
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+
+        let current_ok = self
+            .deps
+            .password_hasher
+            .verify(current_password, &user.password)
+            .await?;
+        if !current_ok {
+            return Err(ApplicationError::Authentication);
+        }
+
+        User::validate_password(new_password)?;
+        if new_password == current_password {
+            return Err(DomainError::invalid_argument(
+                "new_password",
+                "must differ from the current password",
+            )
+            .into());
+        }
+
+        let password_hash = self.deps.password_hasher.hash(new_password).await?;
+        user.set_password(password_hash, self.deps.clock.now());
+
+        let updated = self.deps.user_repository.update(user).await?;
+        Ok(updated)
+    }
+
+    /// 申请重置密码：给邮箱发送一次性重置链接
+    ///
+    /// 邮箱不存在或账号不可用时同样返回成功，不向调用方暴露账号是否存在；
+    /// 邮件发送失败只记录日志，否则响应差异同样会泄露账号存在
+    pub async fn request_password_reset(&self, email: String) -> Result<(), ApplicationError> {
+        let email = UserEmail::parse(email)?;
+        let Some(user) = self.deps.user_repository.find_by_email(email).await? else {
+            return Ok(());
+        };
+        if user.status == UserStatus::Suspended {
+            return Ok(());
+        }
+
+        let config = &self.deps.registration_config;
+        let token = generate_verification_token();
+        let now = self.deps.clock.now();
+        let expires_at = now + time::Duration::seconds(config.password_reset_token_ttl_secs as i64);
+
+        self.deps
+            .password_reset_repository
+            .create(PasswordReset::issue(
+                hash_verification_token(&token),
+                user.id,
+                expires_at,
+                now,
+            ))
+            .await?;
+
+        let url = format!("{}?token={}", config.password_reset_url, token);
+        if let Err(err) = self
+            .deps
+            .email_sender
+            .send_password_reset(&user.email, &user.username, &url)
+            .await
+        {
+            tracing::warn!(error = %err, user_id = %user.id, "发送密码重置邮件失败");
+        }
+        Ok(())
+    }
+
+    /// 用重置链接里的令牌设置新密码；令牌不存在、过期或已使用都视为无效
+    ///
+    /// 成功后令牌版本递增，之前签发的访问令牌全部失效；刷新令牌由调用方吊销
+    pub async fn reset_password(
+        &self,
+        token: &str,
+        new_password: &str,
+    ) -> Result<User, ApplicationError> {
+        let token_hash = hash_verification_token(token);
+        let reset = self
+            .deps
+            .password_reset_repository
+            .find_by_token_hash(&token_hash)
+            .await?
+            .ok_or(DomainError::InvalidPasswordResetToken)?;
+
+        let now = self.deps.clock.now();
+        if reset.is_used() || reset.is_expired(now) {
+            return Err(DomainError::InvalidPasswordResetToken.into());
+        }
+        // 先校验新密码，密码不合格时令牌还能继续使用
+        User::validate_password(new_password)?;
+
+        if !self
+            .deps
+            .password_reset_repository
+            .consume(&token_hash, now)
+            .await?
+        {
+            return Err(DomainError::InvalidPasswordResetToken.into());
+        }
+
+        let mut user = self
+            .deps
+            .user_repository
+            .find_by_id(reset.user_id)
+            .await?
+            .ok_or(DomainError::UserNotFound)?;
+
+        let password_hash = self.deps.password_hasher.hash(new_password).await?;
+        user.set_password(password_hash, now);
+
+        let updated = self.deps.user_repository.update(user).await?;
+        Ok(updated)
+    }
+
     pub async fn logout(&self, user_id: Uuid) -> Result<(), ApplicationError> {
         let user_id = UserId::from(user_id);
         self.deps
@@ -114,3 +476,74 @@ impl UserService {
             .await
     }
 }
+
+/// 生成验证令牌：32 字节随机数的十六进制表示
+fn generate_verification_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    to_hex(&bytes)
+}
+
+/// 数据库里只保存令牌的 SHA-256
+fn hash_verification_token(token: &str) -> String {
+    to_hex(digest::digest(&digest::SHA256, token.as_bytes()).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed: &[&str], denied: &[&str]) -> EmailDomainPolicy {
+        EmailDomainPolicy::from_config(&RegistrationConfig {
+            allowed_email_domains: allowed.iter().map(|d| d.to_string()).collect(),
+            denied_email_domains: denied.iter().map(|d| d.to_string()).collect(),
+            ..RegistrationConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_empty_allowlist_allows_all_but_denied() {
+        let policy = policy(&[], &["spam.com"]);
+
+        assert!(policy.is_allowed("example.com"));
+        assert!(!policy.is_allowed("spam.com"));
+        assert!(!policy.is_allowed("SPAM.com"));
+    }
+
+    #[test]
+    fn test_allowlist_restricts_domains() {
+        let policy = policy(&["example.com"], &[]);
+
+        assert!(policy.is_allowed("example.com"));
+        assert!(policy.is_allowed("Example.COM"));
+        assert!(!policy.is_allowed("other.com"));
+        assert!(!policy.is_allowed("mail.example.com"));
+    }
+
+    #[test]
+    fn test_denylist_wins_over_allowlist() {
+        let policy = policy(&["*.example.com"], &["bad.example.com"]);
+
+        assert!(policy.is_allowed("good.example.com"));
+        assert!(!policy.is_allowed("bad.example.com"));
+    }
+
+    #[test]
+    fn test_wildcard_matches_subdomains_only() {
+        let policy = policy(&["*.corp.com"], &[]);
+
+        assert!(policy.is_allowed("mail.corp.com"));
+        assert!(policy.is_allowed("a.b.corp.com"));
+        assert!(!policy.is_allowed("corp.com"));
+        assert!(!policy.is_allowed("evilcorp.com"));
+    }
+
+    #[test]
+    fn test_email_domain_extraction() {
+        let email = UserEmail::parse("alice@Mail.Example.com").unwrap();
+        assert!(policy(&["*.example.com"], &[]).is_allowed(email.domain()));
+    }
+}
@@ -0,0 +1,81 @@
+//! 停机屏障
+//!
+//! 停机时消息可能已经写库但还没广播，直接退出会丢掉这次广播。
+//! 屏障关闭后拒绝新的发送，并等待（带超时）进行中的发送完成写库和广播。
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+pub struct ShutdownBarrier {
+    closing: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl ShutdownBarrier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一次进行中的操作；屏障已关闭时返回 None
+    pub fn enter(self: &Arc<Self>) -> Option<InFlightGuard> {
+        // 先计数再检查：shutdown 看到 closing 之后，计数里一定包含了所有放行的操作
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard {
+            barrier: Arc::clone(self),
+        };
+        if self.closing.load(Ordering::SeqCst) {
+            return None;
+        }
+        Some(guard)
+    }
+
+    /// 当前进行中的操作数
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+    }
+
+    /// 关闭屏障并等待进行中的操作完成
+    ///
+    /// 全部完成返回 true；超时返回 false，剩余数量见 [`Self::in_flight`]
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.closing.store(true, Ordering::SeqCst);
+
+        tokio::time::timeout(timeout, async {
+            loop {
+                let idle = self.idle.notified();
+                tokio::pin!(idle);
+                // 先登记等待再检查计数，避免错过最后一个操作的通知
+                idle.as_mut().enable();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+/// 进行中操作的计数守卫，drop 时计数减一
+#[derive(Debug)]
+pub struct InFlightGuard {
+    barrier: Arc<ShutdownBarrier>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.barrier.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.barrier.idle.notify_waiters();
+        }
+    }
+}
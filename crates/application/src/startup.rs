@@ -0,0 +1,97 @@
+//! 启动顺序与就绪状态
+//!
+//! 数据库或 Redis 还没起来时直接开始服务，早到的请求会全部失败。
+//! 启动时先（有限次数地）等待关键依赖可连接，全部就绪后才监听端口并标记为就绪；
+//! 统计等非关键依赖按需连接，不阻塞启动。
+
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use config::StartupConfig;
+use thiserror::Error;
+
+/// 关键依赖在重试次数用尽后仍不可用
+#[derive(Debug, Error)]
+#[error("{dependency} still unavailable after {attempts} attempts: {last_error}")]
+pub struct DependencyUnavailable {
+    pub dependency: &'static str,
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+/// 等待一个关键依赖就绪
+///
+/// `connect` 每次尝试建立连接（或探测一次），成功即返回其结果；
+/// 单次尝试超时或失败后间隔一段时间重试，次数用尽返回 [`DependencyUnavailable`]
+pub async fn wait_for_dependency<T, E, F, Fut>(
+    dependency: &'static str,
+    config: &StartupConfig,
+    mut connect: F,
+) -> Result<T, DependencyUnavailable>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let attempt_timeout = Duration::from_secs(config.dependency_attempt_timeout_secs);
+    let retry_interval = Duration::from_millis(config.dependency_retry_interval_ms);
+    let max_attempts = config.dependency_max_attempts.max(1);
+
+    let mut last_error = String::new();
+    for attempt in 1..=max_attempts {
+        match tokio::time::timeout(attempt_timeout, connect()).await {
+            Ok(Ok(value)) => {
+                tracing::info!(dependency, attempt, "依赖已就绪");
+                return Ok(value);
+            }
+            Ok(Err(err)) => last_error = err.to_string(),
+            Err(_) => last_error = format!("timed out after {attempt_timeout:?}"),
+        }
+
+        tracing::warn!(
+            dependency,
+            attempt,
+            max_attempts,
+            error = %last_error,
+            "依赖尚未就绪，稍后重试"
+        );
+        if attempt < max_attempts {
+            tokio::time::sleep(retry_interval).await;
+        }
+    }
+
+    Err(DependencyUnavailable {
+        dependency,
+        attempts: max_attempts,
+        last_error,
+    })
+}
+
+/// 服务就绪状态，供就绪探针查询；初始为未就绪
+#[derive(Debug, Clone, Default)]
+pub struct Readiness {
+    ready: Arc<AtomicBool>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    /// 关键依赖全部就绪、开始监听后调用
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::SeqCst);
+    }
+
+    /// 开始停机时调用，让负载均衡先摘掉本实例
+    pub fn mark_not_ready(&self) {
+        self.ready.store(false, Ordering::SeqCst);
+    }
+}
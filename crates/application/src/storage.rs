@@ -0,0 +1,43 @@
+//! 对象存储抽象：上传文件的内容放在这里，元数据放在数据库
+//!
+//! 内容以字节流读写，大文件不会整个读进内存
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use thiserror::Error;
+use tokio_stream::Stream;
+
+/// 对象内容的字节流
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("object not found: {0}")]
+    NotFound(String),
+    #[error("invalid object key: {0}")]
+    InvalidKey(String),
+    #[error("storage io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[async_trait]
+pub trait ObjectStorage: Send + Sync {
+    /// 写入对象，返回写入的字节数；字节流出错时不留下写了一半的对象
+    async fn put(&self, key: &str, body: ByteStream) -> Result<u64, StorageError>;
+
+    /// 读取对象内容
+    async fn get(&self, key: &str) -> Result<ByteStream, StorageError>;
+
+    /// 删除对象；对象不存在时不报错
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// 生成有时效的直接下载地址；存储本身不能对外提供下载时返回 None，由服务端转发
+    async fn presign(
+        &self,
+        key: &str,
+        expires_in: Duration,
+    ) -> Result<Option<String>, StorageError>;
+}
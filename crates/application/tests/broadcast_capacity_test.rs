@@ -0,0 +1,161 @@
+//! 进程内广播缓冲扩容测试
+//!
+//! 验证：订阅者落后时房间缓冲翻倍到上限，落后次数和扩容后的缓冲记录到指标；
+//! 扩容后同样的突发流量不再落后，其他订阅者换到新通道后继续收到消息；
+//! 扩容后安静一段时间缩回初始缓冲
+
+use application::{
+    metrics::{BROADCAST_LAGGED_TOTAL, BROADCAST_ROOM_CAPACITY},
+    LocalMessageBroadcaster, MessageBroadcast, MessageBroadcaster, MessageStream, StreamEvent,
+    WebSocketMessage,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use domain::RoomId;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use uuid::Uuid;
+
+/// 全局 recorder 只能装一次，同一个测试进程里的测试共用，指标按房间区分
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| PrometheusBuilder::new().install_recorder().unwrap())
+}
+
+fn notification_text(event: StreamEvent) -> String {
+    match event {
+        StreamEvent::Message(MessageBroadcast {
+            message: WebSocketMessage::SystemNotification { message, .. },
+            ..
+        }) => message,
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+async fn burst(broadcaster: &LocalMessageBroadcaster, room_id: RoomId, label: &str, count: usize) {
+    for i in 0..count {
+        broadcaster
+            .broadcast(MessageBroadcast::system_notification(
+                room_id,
+                format!("{label} {i}"),
+            ))
+            .await
+            .unwrap();
+    }
+}
+
+async fn expect_burst(stream: &mut MessageStream, label: &str, count: usize) {
+    for i in 0..count {
+        assert_eq!(
+            notification_text(stream.recv_event().await.unwrap()),
+            format!("{label} {i}")
+        );
+    }
+}
+
+fn metric_value(handle: &PrometheusHandle, name: &str, room_id: RoomId) -> Option<f64> {
+    let prefix = format!("{name}{{room_id=\"{room_id}\"}} ");
+    handle
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix)?.parse().ok())
+}
+
+#[tokio::test]
+async fn lagging_subscriber_grows_room_buffer_up_to_max() {
+    let handle = recorder();
+    // 缩容间隔足够长，测试期间不会缩回
+    let broadcaster = LocalMessageBroadcaster::with_growth(2, 8, Duration::from_secs(3600));
+    let room_id = RoomId::from(Uuid::new_v4());
+    let mut slow = broadcaster.subscribe(room_id).await.unwrap();
+    let mut fast = broadcaster.subscribe(room_id).await.unwrap();
+
+    // 缓冲 2 条时突发 5 条：慢订阅者落后，缓冲扩到 4
+    for i in 0..5 {
+        burst(&broadcaster, room_id, &format!("first {i}"), 1).await;
+        assert_eq!(
+            notification_text(fast.recv_event().await.unwrap()),
+            format!("first {i} 0")
+        );
+    }
+    assert!(matches!(slow.recv_event().await, Some(StreamEvent::Gap)));
+    assert_eq!(
+        metric_value(handle, BROADCAST_LAGGED_TOTAL, room_id),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(handle, BROADCAST_ROOM_CAPACITY, room_id),
+        Some(4.0)
+    );
+
+    // 没落后的订阅者读完旧通道后换到新通道，中间的广播由订阅方补齐
+    assert!(matches!(fast.recv_event().await, Some(StreamEvent::Gap)));
+
+    // 扩容后 4 条以内的突发不再落后，两个订阅者都按顺序收到全部消息
+    burst(&broadcaster, room_id, "second", 4).await;
+    expect_burst(&mut slow, "second", 4).await;
+    expect_burst(&mut fast, "second", 4).await;
+
+    // 继续落后时翻倍到上限 8 为止
+    for _ in 0..2 {
+        burst(&broadcaster, room_id, "flood", 20).await;
+        assert!(matches!(slow.recv_event().await, Some(StreamEvent::Gap)));
+        assert!(matches!(fast.recv_event().await, Some(StreamEvent::Gap)));
+        while fast.try_recv().unwrap().is_some() {}
+        while slow.try_recv().unwrap().is_some() {}
+    }
+    assert_eq!(
+        metric_value(handle, BROADCAST_ROOM_CAPACITY, room_id),
+        Some(8.0)
+    );
+    assert_eq!(
+        metric_value(handle, BROADCAST_LAGGED_TOTAL, room_id),
+        Some(5.0)
+    );
+
+    burst(&broadcaster, room_id, "third", 8).await;
+    expect_burst(&mut slow, "third", 8).await;
+    expect_burst(&mut fast, "third", 8).await;
+
+    broadcaster.shutdown().await;
+    assert!(slow.recv_event().await.is_none());
+    assert!(fast.recv_event().await.is_none());
+}
+
+#[tokio::test]
+async fn quiet_room_buffer_shrinks_back_to_initial_capacity() {
+    let handle = recorder();
+    let broadcaster = LocalMessageBroadcaster::with_growth(2, 8, Duration::from_millis(50));
+    let room_id = RoomId::from(Uuid::new_v4());
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    burst(&broadcaster, room_id, "flood", 5).await;
+    assert!(matches!(stream.recv_event().await, Some(StreamEvent::Gap)));
+    assert_eq!(
+        metric_value(handle, BROADCAST_ROOM_CAPACITY, room_id),
+        Some(4.0)
+    );
+
+    // 扩容后一直没有再落后：这条广播照常送达，随后缩回初始缓冲，订阅者换通道时产出 Gap
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    burst(&broadcaster, room_id, "quiet", 1).await;
+    expect_burst(&mut stream, "quiet", 1).await;
+    assert!(matches!(stream.recv_event().await, Some(StreamEvent::Gap)));
+    assert_eq!(
+        metric_value(handle, BROADCAST_ROOM_CAPACITY, room_id),
+        Some(2.0)
+    );
+
+    // 缩回后按初始缓冲收发，超过 2 条的突发重新触发扩容
+    burst(&broadcaster, room_id, "small", 2).await;
+    expect_burst(&mut stream, "small", 2).await;
+    burst(&broadcaster, room_id, "again", 3).await;
+    assert!(matches!(stream.recv_event().await, Some(StreamEvent::Gap)));
+    assert_eq!(
+        metric_value(handle, BROADCAST_ROOM_CAPACITY, room_id),
+        Some(4.0)
+    );
+
+    broadcaster.shutdown().await;
+    assert!(stream.recv_event().await.is_none());
+}
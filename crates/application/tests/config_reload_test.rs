@@ -0,0 +1,74 @@
+//! 运行时配置重载测试
+//!
+//! 验证：重载后的限流配置无需重启立即生效；不合法的新配置被拒绝，原配置保持不变
+
+use application::{ConfigReloader, MessageRateLimiter, RateLimitError};
+use config::AppConfig;
+use domain::UserId;
+use redis::Client;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+fn config_with_message_limit(messages_per_minute: u32) -> AppConfig {
+    let mut config = AppConfig::test_config();
+    config.rate_limit.messages_per_minute = messages_per_minute;
+    config
+}
+
+#[tokio::test]
+async fn reloaded_rate_limit_takes_effect_without_restart() {
+    let redis_client = Arc::new(Client::open("redis://127.0.0.1:6379").unwrap());
+    let startup = config_with_message_limit(2);
+    let limiter = Arc::new(MessageRateLimiter::from_config(
+        redis_client,
+        &startup.rate_limit,
+    ));
+
+    // 配置来源可以在测试中随时修改，模拟改写配置文件
+    let source = Arc::new(Mutex::new(startup.clone()));
+    let reloader = ConfigReloader::new(startup, limiter.clone()).with_loader({
+        let source = source.clone();
+        move || Ok(source.lock().unwrap().clone())
+    });
+
+    let user_id = UserId::from(Uuid::new_v4());
+    limiter.check_message_rate(user_id).await.unwrap();
+    limiter.check_message_rate(user_id).await.unwrap();
+    assert!(matches!(
+        limiter.check_message_rate(user_id).await,
+        Err(RateLimitError::RateLimitExceeded { max: 2, .. })
+    ));
+
+    // 1. 调高限额后重载，同一个限流器立即放行（被拒绝的那次也计入窗口，还剩两条）
+    *source.lock().unwrap() = config_with_message_limit(5);
+    let report = reloader.reload().await.unwrap();
+    assert!(report.changed);
+    assert!(report.restart_required.is_empty());
+    assert_eq!(reloader.current().rate_limit.messages_per_minute, 5);
+
+    limiter.check_message_rate(user_id).await.unwrap();
+    limiter.check_message_rate(user_id).await.unwrap();
+    assert!(matches!(
+        limiter.check_message_rate(user_id).await,
+        Err(RateLimitError::RateLimitExceeded { max: 5, .. })
+    ));
+
+    // 2. 不合法的配置被拒绝，继续使用上一次的限额
+    *source.lock().unwrap() = config_with_message_limit(0);
+    assert!(reloader.reload().await.is_err());
+    assert_eq!(reloader.current().rate_limit.messages_per_minute, 5);
+    assert!(matches!(
+        limiter.check_message_rate(user_id).await,
+        Err(RateLimitError::RateLimitExceeded { max: 5, .. })
+    ));
+
+    // 3. 不可重载的配置只报告需要重启，不影响当前运行
+    let mut changed_port = config_with_message_limit(5);
+    changed_port.server.port += 1;
+    *source.lock().unwrap() = changed_port;
+    let report = reloader.reload().await.unwrap();
+    assert!(!report.changed);
+    assert_eq!(report.restart_required, ["server.port"]);
+
+    limiter.reset_user_quota(user_id).await.unwrap();
+}
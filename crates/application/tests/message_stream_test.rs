@@ -0,0 +1,142 @@
+use application::{
+    LocalMessageBroadcaster, MessageBroadcast, MessageBroadcaster, MessageStream, StreamEvent,
+    WebSocketMessage,
+};
+use domain::RoomId;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+fn notification(room_id: RoomId, text: &str) -> MessageBroadcast {
+    MessageBroadcast::system_notification(room_id, text.to_string())
+}
+
+fn notification_text(event: StreamEvent) -> String {
+    match event {
+        StreamEvent::Message(MessageBroadcast {
+            message: WebSocketMessage::SystemNotification { message, .. },
+            ..
+        }) => message,
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+/// 慢订阅者落后于突发流量时，流应跳过被覆盖的旧消息继续工作，而不是直接结束
+#[tokio::test]
+async fn local_stream_survives_lag_burst() {
+    let room_id = RoomId::from(Uuid::new_v4());
+    let (sender, receiver) = broadcast::channel(2);
+    let mut stream = MessageStream::local(receiver, room_id);
+
+    for i in 0..5 {
+        sender
+            .send(MessageBroadcast::system_notification(
+                room_id,
+                format!("burst {i}"),
+            ))
+            .unwrap();
+    }
+
+    // 容量为 2：前 3 条被覆盖，只能收到最后 2 条
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        match stream
+            .recv()
+            .await
+            .expect("stream should survive lag")
+            .message
+        {
+            WebSocketMessage::SystemNotification { message, .. } => received.push(message),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+    assert_eq!(received, ["burst 3", "burst 4"]);
+
+    // 之后的消息正常投递
+    sender
+        .send(MessageBroadcast::system_notification(
+            room_id,
+            "after burst".to_string(),
+        ))
+        .unwrap();
+    assert!(stream.recv().await.is_some());
+
+    drop(sender);
+    assert!(stream.recv().await.is_none());
+}
+
+/// 每个房间独立缓冲：繁忙房间的突发消息不会覆盖安静房间订阅者的消息
+#[tokio::test]
+async fn busy_room_does_not_evict_quiet_room() {
+    let broadcaster = LocalMessageBroadcaster::new(2);
+    let quiet = RoomId::from(Uuid::new_v4());
+    let busy = RoomId::from(Uuid::new_v4());
+    let mut quiet_stream = broadcaster.subscribe(quiet).await.unwrap();
+    let _busy_stream = broadcaster.subscribe(busy).await.unwrap();
+
+    broadcaster
+        .broadcast(notification(quiet, "quiet"))
+        .await
+        .unwrap();
+    for i in 0..10 {
+        broadcaster
+            .broadcast(notification(busy, &format!("busy {i}")))
+            .await
+            .unwrap();
+    }
+
+    let event = quiet_stream.recv_event().await.unwrap();
+    assert_eq!(notification_text(event), "quiet");
+}
+
+/// 订阅者落后时先收到 Gap，再收到缓冲里剩下的消息
+#[tokio::test]
+async fn lagged_subscriber_receives_gap_marker() {
+    let broadcaster = LocalMessageBroadcaster::new(2);
+    let room_id = RoomId::from(Uuid::new_v4());
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    for i in 0..5 {
+        broadcaster
+            .broadcast(notification(room_id, &format!("burst {i}")))
+            .await
+            .unwrap();
+    }
+
+    assert!(matches!(
+        stream.recv_event().await.unwrap(),
+        StreamEvent::Gap
+    ));
+    assert_eq!(
+        notification_text(stream.recv_event().await.unwrap()),
+        "burst 3"
+    );
+    assert_eq!(
+        notification_text(stream.recv_event().await.unwrap()),
+        "burst 4"
+    );
+
+    broadcaster.shutdown().await;
+    assert!(stream.recv_event().await.is_none());
+}
+
+/// 单独配置了更大缓冲的房间不会落后
+#[tokio::test]
+async fn room_capacity_override_applies_to_that_room() {
+    let room_id = RoomId::from(Uuid::new_v4());
+    let broadcaster = LocalMessageBroadcaster::new(2).with_room_capacity(room_id, 8);
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    for i in 0..5 {
+        broadcaster
+            .broadcast(notification(room_id, &format!("burst {i}")))
+            .await
+            .unwrap();
+    }
+
+    for i in 0..5 {
+        assert_eq!(
+            notification_text(stream.recv_event().await.unwrap()),
+            format!("burst {i}")
+        );
+    }
+}
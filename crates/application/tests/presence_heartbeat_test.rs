@@ -0,0 +1,69 @@
+//! 心跳超时检测测试
+//!
+//! 验证：会话心跳过期后清扫任务产生 Disconnected 事件并清理在线状态
+
+use application::presence::memory::MemoryPresenceManager;
+use application::{PresenceEventType, PresenceManager};
+use domain::{RoomId, UserId};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn expired_heartbeat_marks_user_offline() {
+    let presence = MemoryPresenceManager::with_heartbeat_timeout(Duration::from_millis(50));
+    let room_id = RoomId::from(Uuid::new_v4());
+    let user_id = UserId::from(Uuid::new_v4());
+    let session_id = Uuid::new_v4();
+
+    presence.user_connected(room_id, user_id).await.unwrap();
+    presence
+        .heartbeat(room_id, user_id, session_id)
+        .await
+        .unwrap();
+
+    // 心跳未过期时清扫不产生事件
+    assert!(presence.sweep_expired_sessions().await.unwrap().is_empty());
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    let events = presence.sweep_expired_sessions().await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, PresenceEventType::Disconnected);
+    assert_eq!(events[0].session_id, session_id);
+    assert_eq!(events[0].user_id, user_id);
+    assert!(!presence.is_user_online(room_id, user_id).await.unwrap());
+
+    // 同一会话只会被清扫一次
+    assert!(presence.sweep_expired_sessions().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn live_session_keeps_user_online() {
+    let presence = MemoryPresenceManager::with_heartbeat_timeout(Duration::from_millis(50));
+    let room_id = RoomId::from(Uuid::new_v4());
+    let user_id = UserId::from(Uuid::new_v4());
+    let stale_session = Uuid::new_v4();
+    let live_session = Uuid::new_v4();
+
+    presence.user_connected(room_id, user_id).await.unwrap();
+    presence
+        .heartbeat(room_id, user_id, stale_session)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    presence
+        .heartbeat(room_id, user_id, live_session)
+        .await
+        .unwrap();
+
+    // 过期会话被结束，但用户还有另一个存活会话，仍然在线
+    let events = presence.sweep_expired_sessions().await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].session_id, stale_session);
+    assert!(presence.is_user_online(room_id, user_id).await.unwrap());
+
+    // 正常结束的会话不会再被清扫
+    presence.end_session(live_session).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(presence.sweep_expired_sessions().await.unwrap().is_empty());
+}
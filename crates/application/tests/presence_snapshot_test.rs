@@ -0,0 +1,62 @@
+//! 全站在线概况测试
+//!
+//! 验证：Redis 实现按房间在线集合统计人数，用户数按人去重，连接数按会话计数
+
+use std::sync::Arc;
+
+use application::{PresenceManager, RedisPresenceManager};
+use domain::{RoomId, UserId};
+use uuid::Uuid;
+
+#[tokio::test]
+#[ignore = "requires local redis"]
+async fn redis_snapshot_counts_rooms_users_and_connections(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let redis_client = Arc::new(redis::Client::open("redis://127.0.0.1:6379")?);
+    let presence = RedisPresenceManager::with_stream_name(
+        redis_client,
+        format!("presence_snapshot_test_{}", Uuid::new_v4()),
+    );
+    let before = presence.snapshot(0).await?;
+
+    let busy = RoomId::from(Uuid::new_v4());
+    let quiet = RoomId::from(Uuid::new_v4());
+    let alice = UserId::from(Uuid::new_v4());
+    let bob = UserId::from(Uuid::new_v4());
+    let mut sessions = Vec::new();
+    for (room_id, user_id) in [(busy, alice), (busy, bob), (quiet, alice)] {
+        let session_id = Uuid::new_v4();
+        presence.user_connected(room_id, user_id).await?;
+        presence.heartbeat(room_id, user_id, session_id).await?;
+        sessions.push(session_id);
+    }
+
+    // 其他测试可能同时在写在线状态，只核对本测试的房间和增量
+    let snapshot = presence.snapshot(usize::MAX).await?;
+    assert_eq!(snapshot.total_online_users, before.total_online_users + 2);
+    assert_eq!(snapshot.connection_count, before.connection_count + 3);
+    let count_of = |room_id: RoomId| {
+        snapshot
+            .top_rooms
+            .iter()
+            .find(|stats| stats.room_id == room_id)
+            .map(|stats| stats.online_count)
+    };
+    assert_eq!(count_of(busy), Some(2));
+    assert_eq!(count_of(quiet), Some(1));
+    assert!(snapshot
+        .top_rooms
+        .windows(2)
+        .all(|pair| pair[0].online_count >= pair[1].online_count));
+    assert!(presence.snapshot(1).await?.top_rooms.len() <= 1);
+
+    for session_id in sessions {
+        presence.end_session(session_id).await?;
+    }
+    presence.cleanup_user_presence(alice).await?;
+    presence.cleanup_user_presence(bob).await?;
+    let after = presence.snapshot(usize::MAX).await?;
+    assert!(after.top_rooms.iter().all(|stats| stats.room_id != busy));
+
+    Ok(())
+}
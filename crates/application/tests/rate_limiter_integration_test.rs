@@ -1,7 +1,8 @@
-use application::{MessageRateLimiter, RateLimitError};
-use domain::UserId;
+use application::{MessageRateLimiter, RateLimitError, RoomRateLimit};
+use domain::{RoomId, UserId};
 use redis::Client;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[tokio::test]
@@ -152,3 +153,129 @@ async fn test_connection_lifecycle() {
 
     println!("Connection lifecycle test completed");
 }
+
+#[tokio::test]
+async fn test_room_override_takes_precedence_over_default() {
+    let redis_client = Arc::new(Client::open("redis://127.0.0.1:6379").unwrap());
+    let limiter = MessageRateLimiter::new(redis_client.clone(), 2, 5);
+    let default_room = RoomId::from(Uuid::new_v4());
+    let busy_room = RoomId::from(Uuid::new_v4());
+    let user_id = UserId::from(Uuid::new_v4());
+
+    limiter
+        .set_room_limit(busy_room, 5, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(
+        limiter.room_limit(busy_room).await.unwrap(),
+        Some(RoomRateLimit {
+            max_per_window: 5,
+            window: Duration::from_secs(60),
+        })
+    );
+    assert_eq!(limiter.room_limit(default_room).await.unwrap(), None);
+
+    // 覆盖策略的房间：5 条以内都放行，超过默认的 2 条也没关系
+    for _ in 0..5 {
+        limiter.check(busy_room, user_id).await.unwrap();
+    }
+    assert!(matches!(
+        limiter.check(busy_room, user_id).await,
+        Err(RateLimitError::RoomRateLimitExceeded { max: 5, .. })
+    ));
+
+    // 没有覆盖的房间：回退到默认策略
+    limiter.check(default_room, user_id).await.unwrap();
+    limiter.check(default_room, user_id).await.unwrap();
+    assert!(matches!(
+        limiter.check(default_room, user_id).await,
+        Err(RateLimitError::RateLimitExceeded { max: 2, .. })
+    ));
+
+    // 清除覆盖后恢复默认策略
+    limiter.clear_room_limit(busy_room).await.unwrap();
+    assert_eq!(limiter.room_limit(busy_room).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_slow_mode_reports_remaining_cooldown() {
+    let redis_client = Arc::new(Client::open("redis://127.0.0.1:6379").unwrap());
+    let limiter = MessageRateLimiter::new(redis_client.clone(), 100, 5);
+    let room_id = RoomId::from(Uuid::new_v4());
+    let user_id = UserId::from(Uuid::new_v4());
+    let other_user = UserId::from(Uuid::new_v4());
+
+    limiter
+        .set_slow_mode(room_id, Duration::from_secs(30))
+        .await
+        .unwrap();
+
+    limiter.check(room_id, user_id).await.unwrap();
+
+    // 冷却按用户计算，其他用户不受影响
+    limiter.check(room_id, other_user).await.unwrap();
+
+    match limiter.check(room_id, user_id).await {
+        Err(RateLimitError::RoomRateLimitExceeded {
+            max,
+            window_secs,
+            retry_after_secs,
+        }) => {
+            assert_eq!(max, 1);
+            assert_eq!(window_secs, 30);
+            assert_eq!(retry_after_secs, 30);
+        }
+        other => panic!("Expected RoomRateLimitExceeded, got {:?}", other),
+    }
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    match limiter.check(room_id, user_id).await {
+        Err(RateLimitError::RoomRateLimitExceeded {
+            retry_after_secs, ..
+        }) => assert_eq!(retry_after_secs, 29),
+        other => panic!("Expected RoomRateLimitExceeded, got {:?}", other),
+    }
+
+    // interval 为 0 关闭慢速模式
+    limiter
+        .set_slow_mode(room_id, Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(limiter.room_limit(room_id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_room_total_limit_isolates_rooms() {
+    let redis_client = Arc::new(Client::open("redis://127.0.0.1:6379").unwrap());
+    let limiter =
+        MessageRateLimiter::new(redis_client.clone(), 60, 5).with_room_total_limit(3, true);
+    let flooded_room = RoomId::from(Uuid::new_v4());
+    let quiet_room = RoomId::from(Uuid::new_v4());
+
+    // 合计限流按房间计算：三个不同用户各发一条就用完了房间配额
+    for _ in 0..3 {
+        limiter
+            .check(flooded_room, UserId::from(Uuid::new_v4()))
+            .await
+            .unwrap();
+        limiter.check_room_total(flooded_room, false).await.unwrap();
+    }
+    match limiter.check_room_total(flooded_room, false).await {
+        Err(RateLimitError::RoomRateLimited {
+            max_per_sec,
+            retry_after_secs,
+        }) => {
+            assert_eq!(max_per_sec, 3);
+            assert_eq!(retry_after_secs, 1);
+        }
+        other => panic!("Expected RoomRateLimited, got {:?}", other),
+    }
+
+    // 房间管理员豁免，其他房间不受影响
+    limiter.check_room_total(flooded_room, true).await.unwrap();
+    limiter.check_room_total(quiet_room, false).await.unwrap();
+
+    // 窗口过后恢复
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    limiter.check_room_total(flooded_room, false).await.unwrap();
+}
@@ -0,0 +1,83 @@
+//! 房间在线成员推送测试
+//!
+//! 验证：短时间内的多次进出房间合并成一次推送，间隔过后再推送最新状态
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use application::broadcaster::BroadcastError;
+use application::presence::memory::MemoryPresenceManager;
+use application::{
+    MessageBroadcast, MessageBroadcaster, MessageStream, PresenceManager, RoomPresenceNotifier,
+    WebSocketMessage,
+};
+use async_trait::async_trait;
+use domain::{RoomId, UserId};
+use uuid::Uuid;
+
+#[derive(Default)]
+struct RecordingBroadcaster {
+    sent: Mutex<Vec<MessageBroadcast>>,
+}
+
+impl RecordingBroadcaster {
+    /// 已推送的在线人数，按推送顺序
+    fn online_counts(&self) -> Vec<u64> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|broadcast| match &broadcast.message {
+                WebSocketMessage::RoomPresence { online_count, .. } => Some(*online_count),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MessageBroadcaster for RecordingBroadcaster {
+    async fn broadcast(&self, broadcast: MessageBroadcast) -> Result<(), BroadcastError> {
+        self.sent.lock().unwrap().push(broadcast);
+        Ok(())
+    }
+
+    async fn subscribe(&self, _room_id: RoomId) -> Result<MessageStream, BroadcastError> {
+        Err(BroadcastError::failed("Not implemented for test"))
+    }
+}
+
+#[tokio::test]
+async fn presence_updates_are_debounced_per_room() {
+    let presence = Arc::new(MemoryPresenceManager::new());
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let notifier = Arc::new(RoomPresenceNotifier::with_min_interval(
+        presence.clone(),
+        broadcaster.clone(),
+        Duration::from_millis(200),
+    ));
+    let room_id = RoomId::from(Uuid::new_v4());
+
+    // 一连串变化只推送一次
+    presence
+        .user_connected(room_id, UserId::from(Uuid::new_v4()))
+        .await
+        .unwrap();
+    for _ in 0..5 {
+        notifier.notify(room_id);
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(broadcaster.online_counts(), [1]);
+
+    // 间隔内的新变化延后到间隔结束再推送，带上最新人数
+    presence
+        .user_connected(room_id, UserId::from(Uuid::new_v4()))
+        .await
+        .unwrap();
+    notifier.notify(room_id);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(broadcaster.online_counts(), [1]);
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(broadcaster.online_counts(), [1, 2]);
+}
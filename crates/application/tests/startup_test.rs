@@ -0,0 +1,85 @@
+//! 启动等待测试
+//!
+//! 验证：启动慢的依赖在重试次数内就绪时继续启动；一直不可用（失败或卡住）时
+//! 重试用尽后立即失败，不会无限等待
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use application::{wait_for_dependency, Readiness};
+use config::StartupConfig;
+
+fn startup_config(max_attempts: u32) -> StartupConfig {
+    StartupConfig {
+        dependency_max_attempts: max_attempts,
+        dependency_retry_interval_ms: 20,
+        dependency_attempt_timeout_secs: 1,
+    }
+}
+
+#[tokio::test]
+async fn slow_dependency_becomes_ready_within_retries() {
+    let attempts = AtomicU32::new(0);
+
+    // 前三次连接被拒绝，第四次成功
+    let connection = wait_for_dependency("slow-db", &startup_config(5), || async {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt < 4 {
+            Err(format!("connection refused (attempt {attempt})"))
+        } else {
+            Ok("connected")
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(connection, "connected");
+    assert_eq!(attempts.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn unavailable_dependency_fails_after_max_attempts() {
+    let attempts = AtomicU32::new(0);
+
+    let err = wait_for_dependency("down-redis", &startup_config(3), || async {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err::<(), _>("connection refused")
+    })
+    .await
+    .unwrap_err();
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(err.dependency, "down-redis");
+    assert_eq!(err.attempts, 3);
+    assert_eq!(err.last_error, "connection refused");
+}
+
+#[tokio::test]
+async fn hanging_dependency_is_bounded_by_attempt_timeout() {
+    let started = Instant::now();
+
+    // 连接一直没有响应，每次尝试在超时后放弃
+    let err = wait_for_dependency("hanging-db", &startup_config(2), || async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok::<(), String>(())
+    })
+    .await
+    .unwrap_err();
+
+    assert_eq!(err.attempts, 2);
+    assert!(err.last_error.contains("timed out"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn readiness_starts_not_ready_and_is_shared() {
+    let readiness = Readiness::new();
+    let probe = readiness.clone();
+    assert!(!probe.is_ready());
+
+    readiness.mark_ready();
+    assert!(probe.is_ready());
+
+    readiness.mark_not_ready();
+    assert!(!probe.is_ready());
+}
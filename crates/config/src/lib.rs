@@ -16,6 +16,7 @@ use figment::{
     Figment,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// 全局应用配置
@@ -35,6 +36,36 @@ pub struct AppConfig {
     pub stats: StatsConfig,
     /// 用户状态事件配置
     pub presence: PresenceConfig,
+    /// 消息行为配置
+    #[serde(default)]
+    pub message: MessageConfig,
+    /// 用户注册配置
+    #[serde(default)]
+    pub registration: RegistrationConfig,
+    /// 消息限流配置
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// 数据保留配置
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 启动配置
+    #[serde(default)]
+    pub startup: StartupConfig,
+    /// 消息静态加密配置
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    /// 文件上传配置
+    #[serde(default)]
+    pub upload: UploadConfig,
+    /// WebSocket 连接数配置
+    #[serde(default)]
+    pub ws: WsConfig,
+    /// 跨域访问配置
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// 房间和用户按 ID 查询的缓存
+    #[serde(default)]
+    pub cache: CacheConfig,
 }
 
 /// 数据库配置
@@ -48,14 +79,81 @@ pub struct DatabaseConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtConfig {
     pub secret: String,
+    /// 旧版访问令牌有效期（小时），未配置 access_expiration_minutes 时生效
     pub expiration_hours: i64,
+    /// 访问令牌有效期（分钟）
+    #[serde(default)]
+    pub access_expiration_minutes: Option<i64>,
+    /// 刷新令牌有效期（天）
+    #[serde(default = "default_refresh_expiration_days")]
+    pub refresh_expiration_days: i64,
+    /// 校验令牌时缓存用户状态和令牌版本的时间（秒），0 表示每次都查库
+    ///
+    /// 本实例停用用户时会立即清掉缓存；其他实例最多延迟这么久生效
+    #[serde(default = "default_user_cache_ttl_secs")]
+    pub user_cache_ttl_secs: u64,
+}
+
+fn default_refresh_expiration_days() -> i64 {
+    30
+}
+
+fn default_user_cache_ttl_secs() -> u64 {
+    5
+}
+
+impl JwtConfig {
+    /// 访问令牌实际有效期（分钟），兼容只配置了 expiration_hours 的旧配置
+    pub fn access_token_minutes(&self) -> i64 {
+        self.access_expiration_minutes
+            .unwrap_or(self.expiration_hours * 60)
+    }
 }
 
 /// 广播器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BroadcastConfig {
+    /// 未配置 Redis 时进程内广播每个房间的缓冲条数，订阅者落后超过它时从存储补齐
     pub capacity: usize,
+    /// 按房间 ID 覆盖缓冲条数，给消息特别多的房间更大的缓冲
+    #[serde(default)]
+    pub room_capacities: HashMap<String, usize>,
+    /// 订阅者落后时房间缓冲翻倍扩容的上限，等于 `capacity` 时不扩容
+    #[serde(default = "default_broadcast_max_capacity")]
+    pub max_capacity: usize,
+    /// 扩容后多少秒内没有订阅者再落后，就把房间缓冲缩回 `capacity`
+    #[serde(default = "default_broadcast_shrink_after_secs")]
+    pub shrink_after_secs: u64,
     pub redis_url: Option<String>,
+    /// Redis 不可用时发布消息的最多重试次数，用尽后发送失败
+    #[serde(default = "default_broadcast_publish_retries")]
+    pub publish_retries: u32,
+    /// 发布重试和订阅重连的初始等待时间（毫秒），之后每次翻倍
+    #[serde(default = "default_broadcast_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// 重试等待时间的上限（毫秒）
+    #[serde(default = "default_broadcast_max_retry_backoff_ms")]
+    pub max_retry_backoff_ms: u64,
+}
+
+fn default_broadcast_max_capacity() -> usize {
+    4096
+}
+
+fn default_broadcast_shrink_after_secs() -> u64 {
+    300
+}
+
+fn default_broadcast_publish_retries() -> u32 {
+    3
+}
+
+fn default_broadcast_retry_backoff_ms() -> u64 {
+    100
+}
+
+fn default_broadcast_max_retry_backoff_ms() -> u64 {
+    5_000
 }
 
 /// Redis配置
@@ -71,6 +169,30 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub bcrypt_cost: Option<u32>,
+    /// 停机时等待进行中的消息发送完成的最长时间（秒）
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// 停机时等待进行中的 HTTP 请求完成、WebSocket 连接关闭的最长时间（秒）
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// 就绪探针检查每个依赖的超时（毫秒），依赖卡住时探针按失败返回
+    #[serde(default = "default_readiness_check_timeout_ms")]
+    pub readiness_check_timeout_ms: u64,
+    /// 是否开放 Prometheus 指标接口（/metrics）；关闭时不安装指标 recorder
+    #[serde(default)]
+    pub metrics_enabled: bool,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    10
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    30
+}
+
+fn default_readiness_check_timeout_ms() -> u64 {
+    1000
 }
 
 /// 统计聚合配置
@@ -80,8 +202,14 @@ pub struct StatsConfig {
     pub schedule: ScheduleConfig,
     /// 消费者配置
     pub consumer: ConsumerConfig,
+    /// 实时在线概况接口的访问令牌，通过 `X-Stats-Token` 请求头传入；未设置时只有系统管理员可以访问
+    #[serde(default)]
+    pub api_token: Option<String>,
 }
 
+/// 统计接口访问令牌的最短长度
+const MIN_STATS_API_TOKEN_LEN: usize = 16;
+
 /// 定时任务调度配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduleConfig {
@@ -102,12 +230,49 @@ pub struct ScheduleConfig {
 pub struct ConsumerConfig {
     /// 消费者组名称
     pub consumer_group: String,
-    /// 消费者实例名称
+    /// 消费者实例名称；留空时按 `主机名-进程号` 生成，多个实例水平扩展时互不冲突。
+    /// 固定名称的实例重启后能接着处理自己名下未确认的消息，自动生成的名称换了进程号就读不到了
+    #[serde(default)]
     pub consumer_name: String,
     /// 批次大小
     pub batch_size: i64,
     /// 轮询间隔（秒）
     pub poll_interval_secs: u64,
+    /// 批量写入遇到坏事件时逐个隔离并转入死信流；关闭则整批失败、留在流中等待重试
+    #[serde(default = "default_isolate_failures")]
+    pub isolate_failures: bool,
+    /// 死信流名称，保存无法解析或无法写入数据库的事件及失败原因
+    #[serde(default = "default_dead_letter_stream", alias = "dlq_stream")]
+    pub dead_letter_stream: String,
+    /// 整批写库失败后最多重试的次数，用尽后转入死信流并确认，不再占着消费者组的待确认列表
+    #[serde(default = "default_consumer_max_retries")]
+    pub max_retries: u32,
+    /// 其他消费者名下空闲超过该时长（秒）的未确认消息由本实例认领重新处理，0 表示不认领
+    #[serde(default = "default_claim_idle_secs")]
+    pub claim_idle_secs: u64,
+    /// 认领空闲消息的间隔（秒）
+    #[serde(default = "default_claim_interval_secs")]
+    pub claim_interval_secs: u64,
+}
+
+fn default_isolate_failures() -> bool {
+    true
+}
+
+fn default_dead_letter_stream() -> String {
+    "presence_events:dead_letter".to_string()
+}
+
+fn default_consumer_max_retries() -> u32 {
+    3
+}
+
+fn default_claim_idle_secs() -> u64 {
+    300
+}
+
+fn default_claim_interval_secs() -> u64 {
+    60
 }
 
 /// 用户状态事件配置
@@ -115,6 +280,545 @@ pub struct ConsumerConfig {
 pub struct PresenceConfig {
     /// Redis Stream 名称
     pub stream_name: String,
+    /// 会话超过该时长没有心跳（客户端 ping 或对服务端 ping 的 pong）即视为离线
+    #[serde(default = "default_heartbeat_timeout_secs")]
+    pub heartbeat_timeout_secs: u64,
+    /// 服务端向 WebSocket 客户端发送 ping 的间隔，必须小于心跳超时
+    #[serde(default = "default_ping_interval_secs")]
+    pub ping_interval_secs: u64,
+    /// 后台清扫心跳超时会话的间隔
+    #[serde(default = "default_heartbeat_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+}
+
+fn default_heartbeat_timeout_secs() -> u64 {
+    90
+}
+
+fn default_heartbeat_sweep_interval_secs() -> u64 {
+    30
+}
+
+fn default_ping_interval_secs() -> u64 {
+    30
+}
+
+/// 消息行为配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageConfig {
+    /// 发送者可编辑消息的时间窗口（秒），房间管理员不受限制
+    pub edit_window_secs: u64,
+    /// 各消息类型的内容长度上限（字符数）
+    pub content_limits: ContentLimitsConfig,
+    /// 每个用户在单条消息上最多保留的不同回应数
+    pub max_reactions_per_user_per_message: usize,
+    /// WebSocket 重连时最多补发的消息数，缺口更大时要求客户端全量重新同步
+    pub max_reconnect_backfill: u32,
+    /// 已送达的投递记录保留时长（秒），过期后由后台任务删除
+    pub delivery_retention_secs: u64,
+    /// 清理已送达投递记录的间隔（秒）
+    pub delivery_cleanup_interval_secs: u64,
+    /// 已关闭房间是否允许只读订阅 WebSocket；关闭后新订阅直接被拒绝
+    pub allow_closed_room_subscriptions: bool,
+    /// 单个 WebSocket 连接最多允许多少条已推送未 ack 的消息，超过后暂停推送并提示重新同步；0 表示不限制
+    pub max_unacked_frames: u32,
+    /// `@everyone` 只在成员数不超过该值的房间生效，更大的房间按普通文本处理；0 表示禁用
+    pub everyone_mention_max_members: usize,
+    /// 每个房间最多置顶的消息数
+    pub max_pinned_per_room: usize,
+    /// 发送消息的幂等键保留时长（秒），期间用同一个键重试返回第一次创建的消息
+    pub idempotency_key_ttl_secs: u64,
+    /// 入房邀请的有效期（秒），过期未接受需要重新邀请
+    pub invitation_ttl_secs: u64,
+    /// 新消息攒批写库的等待时间（毫秒），攒够 `batch_max` 条提前写入；0 表示逐条写库
+    pub batch_flush_ms: u64,
+    /// 一批最多写入的消息数
+    pub batch_max: usize,
+    /// 通过发件箱可靠广播新消息
+    pub outbox: OutboxConfig,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self {
+            edit_window_secs: 300,
+            content_limits: ContentLimitsConfig::default(),
+            max_reactions_per_user_per_message: 20,
+            max_reconnect_backfill: 1000,
+            delivery_retention_secs: 7 * 24 * 3600,
+            delivery_cleanup_interval_secs: 3600,
+            allow_closed_room_subscriptions: true,
+            max_unacked_frames: 0,
+            everyone_mention_max_members: 100,
+            max_pinned_per_room: 10,
+            idempotency_key_ttl_secs: 24 * 3600,
+            invitation_ttl_secs: 7 * 24 * 3600,
+            batch_flush_ms: 0,
+            batch_max: 500,
+            outbox: OutboxConfig::default(),
+        }
+    }
+}
+
+/// 消息广播发件箱配置
+///
+/// 开启后新消息和发件箱记录在同一个事务里写入，由后台分发任务发布到广播器；
+/// 进程在写库和广播之间退出时，消息在重启后照样会被广播
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    pub enabled: bool,
+    /// 轮询间隔（毫秒）；新记录通过 LISTEN/NOTIFY 立即唤醒分发任务，轮询只兜底
+    pub poll_interval_ms: u64,
+    /// 每次最多领取的记录数
+    pub batch_size: usize,
+    /// 最多尝试发布的次数，用尽后进入死信
+    pub max_attempts: u32,
+    /// 失败后首次重试的等待时间（毫秒），之后逐次翻倍
+    pub retry_backoff_ms: u64,
+    /// 重试等待时间上限（毫秒）
+    pub max_retry_backoff_ms: u64,
+    /// 领取后多久没有结果视为分发任务已退出，记录重新可领（秒）
+    pub claim_timeout_secs: u64,
+    /// 已发布的记录保留时长（秒），过期后由分发任务删除
+    pub retention_secs: u64,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_ms: 1000,
+            batch_size: 100,
+            max_attempts: 10,
+            retry_backoff_ms: 500,
+            max_retry_backoff_ms: 60_000,
+            claim_timeout_secs: 30,
+            retention_secs: 24 * 3600,
+        }
+    }
+}
+
+/// 按消息类型区分的内容长度上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentLimitsConfig {
+    pub text: usize,
+    pub image: usize,
+    pub file: usize,
+}
+
+impl Default for ContentLimitsConfig {
+    fn default() -> Self {
+        Self {
+            text: 5000,
+            image: 1000,
+            file: 1000,
+        }
+    }
+}
+
+/// 用户注册配置
+///
+/// 域名支持通配子域名：`*.example.com` 匹配 `a.example.com`、`a.b.example.com`，
+/// 但不匹配 `example.com` 本身
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistrationConfig {
+    /// 允许注册的邮箱域名，空列表表示全部允许（仍受拒绝列表约束）
+    pub allowed_email_domains: Vec<String>,
+    /// 禁止注册的邮箱域名，优先级高于允许列表
+    pub denied_email_domains: Vec<String>,
+    /// 注册后是否需要验证邮箱才能登录；关闭时注册即激活（测试和自托管部署）
+    pub require_email_verification: bool,
+    /// 验证链接有效期（秒）
+    pub verification_token_ttl_secs: u64,
+    /// 两次重发验证邮件的最小间隔（秒）
+    pub resend_verification_interval_secs: u64,
+    /// 验证链接地址，令牌以 `?token=` 追加在后面
+    pub verification_url: String,
+    /// 密码重置链接有效期（秒）
+    pub password_reset_token_ttl_secs: u64,
+    /// 密码重置页面地址，令牌以 `?token=` 追加在后面
+    pub password_reset_url: String,
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        Self {
+            allowed_email_domains: Vec::new(),
+            denied_email_domains: Vec::new(),
+            require_email_verification: false,
+            verification_token_ttl_secs: 24 * 3600,
+            resend_verification_interval_secs: 60,
+            verification_url: "http://localhost:8080/api/v1/auth/verify".to_string(),
+            password_reset_token_ttl_secs: 30 * 60,
+            password_reset_url: "http://localhost:3000/reset-password".to_string(),
+        }
+    }
+}
+
+/// 消息限流默认策略，房间可单独覆盖（例如慢速模式）
+///
+/// 运行时可重载，修改后无需重启
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// 每用户在单个房间内每分钟最大消息数（令牌补充速率）
+    pub messages_per_minute: u32,
+    /// 每用户在单个房间内允许的突发消息数（令牌桶容量）
+    pub message_burst: u32,
+    /// 每用户最大连接数
+    pub connections_per_user: u32,
+    /// 单个房间所有用户合计每秒最大消息数，0 表示不限制
+    pub room_messages_per_sec: u32,
+    /// 房间 owner/admin 是否不受房间合计限流约束
+    pub exempt_room_admins: bool,
+    /// 窗口内允许输错房间密码的次数
+    pub join_attempts: u32,
+    /// 房间密码错误次数的统计窗口（秒）
+    pub join_attempt_window_secs: u64,
+    /// 窗口内同一 IP 或同一邮箱允许申请密码重置的次数
+    pub password_reset_requests: u32,
+    /// 密码重置申请次数的统计窗口（秒）
+    pub password_reset_window_secs: u64,
+    /// 按客户端 IP 对单个接口限流，修改后需要重启
+    pub http: HttpRateLimitConfig,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            messages_per_minute: 60,
+            message_burst: 10,
+            connections_per_user: 5,
+            room_messages_per_sec: 50,
+            exempt_room_admins: true,
+            join_attempts: 5,
+            join_attempt_window_secs: 900,
+            password_reset_requests: 5,
+            password_reset_window_secs: 3600,
+            http: HttpRateLimitConfig::default(),
+        }
+    }
+}
+
+/// 按客户端 IP 的接口限流（滑动窗口）
+///
+/// 配置了 `broadcast.redis_url` 时计数放在 Redis，多实例共享；否则只在本进程内计数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpRateLimitConfig {
+    /// 是否信任 `X-Forwarded-For`；只有部署在可信反向代理后面时才能开启，否则客户端可以伪造 IP
+    pub trust_forwarded_for: bool,
+    /// 需要限流的接口，未列出的接口不限流
+    pub endpoints: Vec<EndpointRateLimit>,
+}
+
+impl Default for HttpRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            trust_forwarded_for: false,
+            endpoints: vec![
+                EndpointRateLimit::new("/api/v1/auth/login", 10, 60),
+                EndpointRateLimit::new("/api/v1/auth/register", 5, 60),
+                EndpointRateLimit::new("/api/v1/auth/forgot-password", 5, 60),
+            ],
+        }
+    }
+}
+
+/// 单个接口的限流：同一 IP 在 `window_secs` 内最多请求 `max_requests` 次
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointRateLimit {
+    /// 完整请求路径，例如 `/api/v1/auth/login`
+    pub path: String,
+    pub max_requests: u32,
+    pub window_secs: u64,
+}
+
+impl EndpointRateLimit {
+    pub fn new(path: impl Into<String>, max_requests: u32, window_secs: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_requests,
+            window_secs,
+        }
+    }
+}
+
+/// 数据保留配置，由 stats-aggregator 定时清理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// 消息保留天数，超过的消息连同投递记录、回应、提及一起物理删除；0 表示永久保留
+    pub message_days: u32,
+    /// 过期消息清理的时间表达式
+    pub message_purge_schedule: String,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            message_days: 0,
+            message_purge_schedule: "0 30 4 * * *".to_string(),
+        }
+    }
+}
+
+/// 启动配置：关键依赖（数据库、Redis）可连接之后才开始监听端口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupConfig {
+    /// 每个关键依赖最多尝试连接的次数，用尽后启动失败
+    pub dependency_max_attempts: u32,
+    /// 两次连接尝试之间的间隔（毫秒）
+    pub dependency_retry_interval_ms: u64,
+    /// 单次连接尝试的超时（秒）
+    pub dependency_attempt_timeout_secs: u64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            dependency_max_attempts: 30,
+            dependency_retry_interval_ms: 1000,
+            dependency_attempt_timeout_secs: 5,
+        }
+    }
+}
+
+/// 消息静态加密配置：房间开启加密后，消息内容用房间数据密钥加密后再入库，
+/// 房间数据密钥再由主密钥加密保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    /// 主密钥（64 位十六进制，即 256 位）；不配置时不能为房间开启加密
+    pub master_key: Option<String>,
+}
+
+/// 文件上传配置：图片和文件消息引用的上传文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadConfig {
+    /// 本地存储目录，不存在时自动创建
+    pub storage_dir: String,
+    /// 单个文件的最大字节数
+    pub max_size_bytes: u64,
+    /// 允许上传的内容类型（MIME），不区分大小写
+    pub allowed_content_types: Vec<String>,
+    /// 上传后一直没有被消息引用的文件保留多少小时，超过后清理
+    pub orphan_retention_hours: u64,
+    /// 清理未引用文件的间隔（秒）
+    pub orphan_cleanup_interval_secs: u64,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            storage_dir: "data/uploads".to_string(),
+            max_size_bytes: 10 * 1024 * 1024,
+            allowed_content_types: [
+                "image/png",
+                "image/jpeg",
+                "image/gif",
+                "image/webp",
+                "application/pdf",
+                "text/plain",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+            orphan_retention_hours: 24,
+            orphan_cleanup_interval_secs: 3600,
+        }
+    }
+}
+
+impl UploadConfig {
+    /// 内容类型是否在允许列表里（忽略大小写和 `; charset=...` 等参数）
+    pub fn allows(&self, content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        self.allowed_content_types
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(essence))
+    }
+}
+
+/// 单个用户的连接数达到上限后再建立新连接时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WsOverflowPolicy {
+    /// 关闭该用户最早建立的连接，接受新连接
+    EvictOldest,
+    /// 拒绝新连接，已有连接不受影响
+    RejectNew,
+}
+
+/// 客户端接收太慢、连接的发送缓冲写满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WsSlowClientPolicy {
+    /// 丢弃缓冲里最旧的帧，客户端通过 resume 补齐缺失的消息
+    DropOldest,
+    /// 发送关闭帧后断开连接
+    Disconnect,
+}
+
+/// WebSocket 连接数配置（按单个服务实例计算）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsConfig {
+    /// 本实例最多同时保持的连接数，达到上限时拒绝新连接
+    pub max_connections: usize,
+    /// 单个用户在本实例上最多同时保持的连接数（所有房间合计）
+    pub max_connections_per_user: usize,
+    /// 单个用户超过连接上限时的处理方式
+    pub per_user_overflow: WsOverflowPolicy,
+    /// 每个连接等待写出的帧数上限，超过后按 `slow_client_policy` 处理
+    pub send_buffer_capacity: usize,
+    /// 发送缓冲写满时的处理方式
+    pub slow_client_policy: WsSlowClientPolicy,
+    /// 发送缓冲持续写满超过该秒数时断开连接，`drop_oldest` 策略下也生效；0 表示不限制
+    pub slow_client_timeout_secs: u64,
+    /// 自定义的 `chatroom.deflate` 子协议，不是标准的 permessage-deflate 压缩
+    pub deflate_subprotocol: WsDeflateSubprotocolConfig,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 1000,
+            max_connections_per_user: 5,
+            per_user_overflow: WsOverflowPolicy::EvictOldest,
+            send_buffer_capacity: 256,
+            slow_client_policy: WsSlowClientPolicy::DropOldest,
+            slow_client_timeout_secs: 10,
+            deflate_subprotocol: WsDeflateSubprotocolConfig::default(),
+        }
+    }
+}
+
+/// `chatroom.deflate` 子协议配置
+///
+/// 这是本项目自定义的子协议，不是 RFC 7692 的 permessage-deflate：客户端在握手时带上
+/// `chatroom.deflate` 子协议才会启用，出站大消息改为 deflate 压缩后的二进制帧，其余连接照常收发文本帧
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsDeflateSubprotocolConfig {
+    /// 是否允许客户端选用该子协议
+    pub enabled: bool,
+    /// deflate 压缩级别，0（不压缩）到 9（最小体积）
+    pub level: u32,
+    /// 小于该字节数的消息不压缩，压缩收益抵不上开销
+    pub min_size_bytes: usize,
+}
+
+impl Default for WsDeflateSubprotocolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 6,
+            min_size_bytes: 512,
+        }
+    }
+}
+
+/// 房间和用户按 ID 查询的缓存配置
+///
+/// 配置了 `broadcast.redis_url` 时缓存放在 Redis，多个实例共享；否则放在本进程内存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// 是否开启缓存
+    pub enabled: bool,
+    /// 缓存条目的有效期（秒）；其他实例（只用进程内缓存时）的修改最多延迟这么久可见
+    pub ttl_secs: u64,
+    /// 进程内缓存最多保存的条目数，满了之后新查到的结果不再缓存
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 60,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// 跨域（CORS）配置：其他来源的浏览器页面调用接口时需要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// 允许的来源，写完整的协议、主机和端口（如 `https://chat.example.com`）；
+    /// `*` 表示任意来源，只用于本地开发；为空时不返回任何 CORS 头
+    pub allowed_origins: Vec<String>,
+    /// 是否允许浏览器携带 Cookie 等凭据，不能和 `*` 同时使用
+    pub allow_credentials: bool,
+    /// 允许的请求方法
+    pub allowed_methods: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allow_credentials: false,
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl CorsConfig {
+    /// 任意来源模式
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::InvalidCorsConfig(message));
+
+        if self.allows_any_origin() {
+            if self.allowed_origins.len() > 1 {
+                return invalid("'*' cannot be combined with other origins".to_string());
+            }
+            // 浏览器不接受凭据请求配通配来源，配了也是白配
+            if self.allow_credentials {
+                return invalid("allow_credentials cannot be used with '*' origin".to_string());
+            }
+        } else {
+            for origin in &self.allowed_origins {
+                let rest = origin
+                    .strip_prefix("https://")
+                    .or_else(|| origin.strip_prefix("http://"));
+                match rest {
+                    Some(host) if !host.is_empty() && !host.contains(['/', ' ']) => {}
+                    _ => {
+                        return invalid(format!(
+                            "origin '{origin}' must be scheme://host[:port] without a path"
+                        ))
+                    }
+                }
+            }
+        }
+
+        if self.allowed_methods.is_empty() {
+            return invalid("allowed_methods cannot be empty".to_string());
+        }
+        for method in &self.allowed_methods {
+            if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) {
+                return invalid(format!(
+                    "method '{method}' must be an uppercase HTTP method"
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl AppConfig {
@@ -227,6 +931,245 @@ impl AppConfig {
             ));
         }
 
+        // 验证消息长度上限
+        let limits = &self.message.content_limits;
+        if limits.text == 0 || limits.image == 0 || limits.file == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Content limits must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.max_reactions_per_user_per_message == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Max reactions per user per message must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.idempotency_key_ttl_secs == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Idempotency key TTL must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.invitation_ttl_secs == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Invitation TTL must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.max_pinned_per_room == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Max pinned messages per room must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.max_reconnect_backfill == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Max reconnect backfill must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.delivery_retention_secs == 0
+            || self.message.delivery_cleanup_interval_secs == 0
+        {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Delivery retention and cleanup interval must be greater than 0".to_string(),
+            ));
+        }
+        if self.message.batch_flush_ms > 0 && self.message.batch_max == 0 {
+            return Err(ConfigError::InvalidMessageConfig(
+                "Message batch max must be greater than 0 when batching is enabled".to_string(),
+            ));
+        }
+        let outbox = &self.message.outbox;
+        if outbox.enabled {
+            if outbox.poll_interval_ms == 0
+                || outbox.batch_size == 0
+                || outbox.max_attempts == 0
+                || outbox.claim_timeout_secs == 0
+                || outbox.retention_secs == 0
+            {
+                return Err(ConfigError::InvalidMessageConfig(
+                    "Outbox poll interval, batch size, max attempts, claim timeout and retention must be greater than 0"
+                        .to_string(),
+                ));
+            }
+            if outbox.retry_backoff_ms > outbox.max_retry_backoff_ms {
+                return Err(ConfigError::InvalidMessageConfig(
+                    "Outbox retry backoff cannot exceed max retry backoff".to_string(),
+                ));
+            }
+        }
+
+        // 验证统计消费者认领参数
+        if self.stats.consumer.claim_idle_secs > 0 && self.stats.consumer.claim_interval_secs == 0 {
+            return Err(ConfigError::InvalidStatsConfig(
+                "Claim interval must be greater than 0 when claiming idle entries".to_string(),
+            ));
+        }
+        if self
+            .stats
+            .api_token
+            .as_ref()
+            .is_some_and(|token| token.len() < MIN_STATS_API_TOKEN_LEN)
+        {
+            return Err(ConfigError::InvalidStatsConfig(format!(
+                "Stats API token must be at least {MIN_STATS_API_TOKEN_LEN} characters"
+            )));
+        }
+
+        // 验证心跳参数
+        if self.presence.heartbeat_timeout_secs == 0 || self.presence.sweep_interval_secs == 0 {
+            return Err(ConfigError::InvalidPresenceConfig(
+                "Heartbeat timeout and sweep interval must be greater than 0".to_string(),
+            ));
+        }
+        if self.presence.ping_interval_secs == 0
+            || self.presence.ping_interval_secs >= self.presence.heartbeat_timeout_secs
+        {
+            return Err(ConfigError::InvalidPresenceConfig(
+                "Ping interval must be greater than 0 and less than heartbeat timeout".to_string(),
+            ));
+        }
+
+        // 验证限流参数（0 表示不限制的只有房间合计限流）
+        let rate_limit = &self.rate_limit;
+        if rate_limit.messages_per_minute == 0
+            || rate_limit.message_burst == 0
+            || rate_limit.connections_per_user == 0
+            || rate_limit.join_attempts == 0
+            || rate_limit.join_attempt_window_secs == 0
+            || rate_limit.password_reset_requests == 0
+            || rate_limit.password_reset_window_secs == 0
+        {
+            return Err(ConfigError::InvalidRateLimitConfig(
+                "Messages per minute, message burst, connections per user, join attempt and password reset limits must be greater than 0"
+                    .to_string(),
+            ));
+        }
+        if self.ws.send_buffer_capacity == 0 {
+            return Err(ConfigError::InvalidWsConfig(
+                "Send buffer capacity must be greater than 0".to_string(),
+            ));
+        }
+        if self.ws.deflate_subprotocol.level > 9 {
+            return Err(ConfigError::InvalidWsConfig(
+                "Deflate subprotocol level must be between 0 and 9".to_string(),
+            ));
+        }
+        let mut limited_paths = std::collections::HashSet::new();
+        for endpoint in &rate_limit.http.endpoints {
+            if !endpoint.path.starts_with('/') {
+                return Err(ConfigError::InvalidRateLimitConfig(format!(
+                    "Endpoint path must start with '/': {}",
+                    endpoint.path
+                )));
+            }
+            if endpoint.max_requests == 0 || endpoint.window_secs == 0 {
+                return Err(ConfigError::InvalidRateLimitConfig(format!(
+                    "Endpoint limit and window must be greater than 0: {}",
+                    endpoint.path
+                )));
+            }
+            if !limited_paths.insert(endpoint.path.as_str()) {
+                return Err(ConfigError::InvalidRateLimitConfig(format!(
+                    "Endpoint is limited more than once: {}",
+                    endpoint.path
+                )));
+            }
+        }
+
+        // 验证邮箱验证参数
+        if self.registration.require_email_verification
+            && (self.registration.verification_token_ttl_secs == 0
+                || self.registration.verification_url.is_empty())
+        {
+            return Err(ConfigError::InvalidRegistrationConfig(
+                "Verification token TTL must be greater than 0 and verification URL must be set"
+                    .to_string(),
+            ));
+        }
+        if self.registration.password_reset_token_ttl_secs == 0
+            || self.registration.password_reset_url.is_empty()
+        {
+            return Err(ConfigError::InvalidRegistrationConfig(
+                "Password reset token TTL must be greater than 0 and password reset URL must be set"
+                    .to_string(),
+            ));
+        }
+
+        // 验证启动等待参数
+        if self.startup.dependency_max_attempts == 0
+            || self.startup.dependency_attempt_timeout_secs == 0
+        {
+            return Err(ConfigError::InvalidStartupConfig(
+                "Dependency max attempts and attempt timeout must be greater than 0".to_string(),
+            ));
+        }
+
+        // 验证加密主密钥格式
+        if let Some(master_key) = &self.encryption.master_key {
+            if master_key.len() != 64 || !master_key.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                return Err(ConfigError::InvalidEncryptionConfig(
+                    "Master key must be 64 hex characters (256 bits)".to_string(),
+                ));
+            }
+        }
+
+        // 验证上传参数
+        if self.upload.storage_dir.is_empty()
+            || self.upload.max_size_bytes == 0
+            || self.upload.orphan_retention_hours == 0
+            || self.upload.orphan_cleanup_interval_secs == 0
+        {
+            return Err(ConfigError::InvalidUploadConfig(
+                "Storage dir must be set and size limit, orphan retention and cleanup interval must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        // 验证 WebSocket 连接上限
+        if self.ws.max_connections == 0
+            || self.ws.max_connections_per_user == 0
+            || self.ws.max_connections_per_user > self.ws.max_connections
+        {
+            return Err(ConfigError::InvalidWsConfig(
+                "Connection limits must be greater than 0 and the per-user limit cannot exceed the server limit"
+                    .to_string(),
+            ));
+        }
+
+        self.cors.validate()?;
+
+        // 验证广播缓冲配置
+        if self.broadcast.capacity == 0 || self.broadcast.room_capacities.values().any(|&c| c == 0)
+        {
+            return Err(ConfigError::InvalidBroadcastConfig(
+                "Broadcast capacity must be greater than 0".to_string(),
+            ));
+        }
+        if self.broadcast.max_capacity < self.broadcast.capacity {
+            return Err(ConfigError::InvalidBroadcastConfig(
+                "Broadcast max capacity must not be less than capacity".to_string(),
+            ));
+        }
+        if self.broadcast.shrink_after_secs == 0 {
+            return Err(ConfigError::InvalidBroadcastConfig(
+                "Broadcast shrink_after_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        // 验证广播重试配置
+        if self.broadcast.retry_backoff_ms == 0
+            || self.broadcast.max_retry_backoff_ms < self.broadcast.retry_backoff_ms
+        {
+            return Err(ConfigError::InvalidBroadcastConfig(
+                "Broadcast retry backoff must be greater than 0 and not exceed the maximum backoff"
+                    .to_string(),
+            ));
+        }
+
+        // 验证缓存配置
+        if self.cache.enabled && (self.cache.ttl_secs == 0 || self.cache.max_entries == 0) {
+            return Err(ConfigError::InvalidCacheConfig(
+                "Cache TTL and max entries must be greater than 0".to_string(),
+            ));
+        }
+
         // 验证bcrypt cost（如果设置）
         if let Some(cost) = self.server.bcrypt_cost {
             if !(10..=14).contains(&cost) {
@@ -251,10 +1194,19 @@ impl AppConfig {
             jwt: JwtConfig {
                 secret: "test-secret-key-with-at-least-32-characters-for-testing".to_string(),
                 expiration_hours: 24,
+                access_expiration_minutes: Some(15),
+                refresh_expiration_days: 30,
+                user_cache_ttl_secs: 5,
             },
             broadcast: BroadcastConfig {
                 capacity: 256,
+                room_capacities: HashMap::new(),
+                max_capacity: default_broadcast_max_capacity(),
+                shrink_after_secs: default_broadcast_shrink_after_secs(),
                 redis_url: None,
+                publish_retries: default_broadcast_publish_retries(),
+                retry_backoff_ms: default_broadcast_retry_backoff_ms(),
+                max_retry_backoff_ms: default_broadcast_max_retry_backoff_ms(),
             },
             redis: RedisConfig {
                 url: "redis://127.0.0.1:6379".to_string(),
@@ -264,6 +1216,10 @@ impl AppConfig {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
                 bcrypt_cost: None,
+                shutdown_timeout_secs: default_shutdown_timeout_secs(),
+                drain_timeout_secs: default_drain_timeout_secs(),
+                readiness_check_timeout_ms: default_readiness_check_timeout_ms(),
+                metrics_enabled: false,
             },
             stats: StatsConfig {
                 schedule: ScheduleConfig {
@@ -278,11 +1234,37 @@ impl AppConfig {
                     consumer_name: "consumer_1".to_string(),
                     batch_size: 10,
                     poll_interval_secs: 1,
+                    isolate_failures: default_isolate_failures(),
+                    dead_letter_stream: default_dead_letter_stream(),
+                    max_retries: default_consumer_max_retries(),
+                    claim_idle_secs: default_claim_idle_secs(),
+                    claim_interval_secs: default_claim_interval_secs(),
                 },
+                api_token: None,
             },
             presence: PresenceConfig {
                 stream_name: "presence_events".to_string(),
+                heartbeat_timeout_secs: default_heartbeat_timeout_secs(),
+                sweep_interval_secs: default_heartbeat_sweep_interval_secs(),
+                ping_interval_secs: default_ping_interval_secs(),
             },
+            message: MessageConfig::default(),
+            registration: RegistrationConfig::default(),
+            // 测试在同一进程里反复注册登录，接口限流由专门的测试开启
+            rate_limit: RateLimitConfig {
+                http: HttpRateLimitConfig {
+                    trust_forwarded_for: true,
+                    endpoints: Vec::new(),
+                },
+                ..RateLimitConfig::default()
+            },
+            retention: RetentionConfig::default(),
+            startup: StartupConfig::default(),
+            encryption: EncryptionConfig::default(),
+            upload: UploadConfig::default(),
+            ws: WsConfig::default(),
+            cors: CorsConfig::default(),
+            cache: CacheConfig::default(),
         }
     }
 }
@@ -300,6 +1282,30 @@ pub enum ConfigError {
     InvalidDatabaseConfig(String),
     #[error("Invalid server configuration: {0}")]
     InvalidServerConfig(String),
+    #[error("Invalid message configuration: {0}")]
+    InvalidMessageConfig(String),
+    #[error("Invalid presence configuration: {0}")]
+    InvalidPresenceConfig(String),
+    #[error("Invalid stats configuration: {0}")]
+    InvalidStatsConfig(String),
+    #[error("Invalid rate limit configuration: {0}")]
+    InvalidRateLimitConfig(String),
+    #[error("Invalid registration configuration: {0}")]
+    InvalidRegistrationConfig(String),
+    #[error("Invalid startup configuration: {0}")]
+    InvalidStartupConfig(String),
+    #[error("Invalid encryption configuration: {0}")]
+    InvalidEncryptionConfig(String),
+    #[error("Invalid upload configuration: {0}")]
+    InvalidUploadConfig(String),
+    #[error("Invalid WebSocket configuration: {0}")]
+    InvalidWsConfig(String),
+    #[error("Invalid CORS configuration: {0}")]
+    InvalidCorsConfig(String),
+    #[error("Invalid cache configuration: {0}")]
+    InvalidCacheConfig(String),
+    #[error("Invalid broadcast configuration: {0}")]
+    InvalidBroadcastConfig(String),
     #[error("Environment variable error: {0}")]
     EnvVarError(#[from] std::env::VarError),
     #[error("Configuration parsing error: {0}")]
@@ -348,6 +1354,11 @@ mod tests {
         assert!(!config.database.url.is_empty());
         assert!(!config.jwt.secret.is_empty());
         assert!(config.jwt.expiration_hours > 0);
+
+        // 未配置分钟级有效期时回退到 expiration_hours
+        let mut jwt = config.jwt.clone();
+        jwt.access_expiration_minutes = None;
+        assert_eq!(jwt.access_token_minutes(), jwt.expiration_hours * 60);
         assert!(config.server.port > 0);
     }
 
@@ -407,6 +1418,231 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_http_rate_limit_validation() {
+        let mut config = AppConfig::test_config();
+        config.rate_limit.http = HttpRateLimitConfig::default();
+        assert!(config.validate().is_ok());
+
+        // 同一接口重复配置
+        config
+            .rate_limit
+            .http
+            .endpoints
+            .push(EndpointRateLimit::new("/api/v1/auth/login", 1, 1));
+        assert!(config.validate().is_err());
+
+        // 窗口为 0
+        config.rate_limit.http.endpoints = vec![EndpointRateLimit::new("/api/v1/auth/login", 1, 0)];
+        assert!(config.validate().is_err());
+
+        // 路径必须以 / 开头
+        config.rate_limit.http.endpoints = vec![EndpointRateLimit::new("api/v1/auth/login", 1, 1)];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_upload_content_type_allow_list() {
+        let mut config = AppConfig::test_config();
+        assert!(config.upload.allows("image/png"));
+        assert!(config.upload.allows("IMAGE/PNG"));
+        assert!(config.upload.allows("text/plain; charset=utf-8"));
+        assert!(!config.upload.allows("text/html"));
+        assert!(!config.upload.allows(""));
+
+        config.upload.max_size_bytes = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_ws_connection_limits() {
+        let mut config = AppConfig::test_config();
+        assert_eq!(config.ws.per_user_overflow, WsOverflowPolicy::EvictOldest);
+        assert!(config.validate().is_ok());
+
+        config.ws.max_connections_per_user = config.ws.max_connections + 1;
+        assert!(config.validate().is_err());
+
+        config.ws.max_connections_per_user = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = AppConfig::test_config();
+        config.ws.send_buffer_capacity = 0;
+        assert!(config.validate().is_err());
+
+        let ws: WsConfig = Figment::new()
+            .merge(Yaml::string(
+                "ws:\n  per_user_overflow: reject_new\n  slow_client_policy: disconnect\n",
+            ))
+            .extract_inner("ws")
+            .unwrap();
+        assert_eq!(ws.per_user_overflow, WsOverflowPolicy::RejectNew);
+        assert_eq!(ws.slow_client_policy, WsSlowClientPolicy::Disconnect);
+        assert_eq!(ws.max_connections, 1000);
+        assert_eq!(ws.send_buffer_capacity, 256);
+        assert_eq!(ws.max_connections_per_user, 5);
+        assert_eq!(ws.slow_client_timeout_secs, 10);
+        assert!(!ws.deflate_subprotocol.enabled);
+    }
+
+    #[test]
+    fn test_ws_deflate_subprotocol_config() {
+        let mut config = AppConfig::test_config();
+        assert!(!config.ws.deflate_subprotocol.enabled);
+        assert_eq!(config.ws.deflate_subprotocol.level, 6);
+        assert!(config.validate().is_ok());
+
+        config.ws.deflate_subprotocol.level = 10;
+        assert!(config.validate().is_err());
+
+        let ws: WsConfig = Figment::new()
+            .merge(Yaml::string(
+                "ws:\n  deflate_subprotocol:\n    enabled: true\n    level: 1\n",
+            ))
+            .extract_inner("ws")
+            .unwrap();
+        assert!(ws.deflate_subprotocol.enabled);
+        assert_eq!(ws.deflate_subprotocol.level, 1);
+        assert_eq!(ws.deflate_subprotocol.min_size_bytes, 512);
+    }
+
+    #[test]
+    fn test_cors_config() {
+        let mut config = AppConfig::test_config();
+        assert!(config.cors.allowed_origins.is_empty());
+        assert!(config.validate().is_ok());
+
+        config.cors.allowed_origins = vec![
+            "https://chat.example.com".to_string(),
+            "http://localhost:5173".to_string(),
+        ];
+        config.cors.allow_credentials = true;
+        assert!(config.validate().is_ok());
+
+        // 凭据和任意来源不能同时开启
+        config.cors.allowed_origins = vec!["*".to_string()];
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidCorsConfig(_))
+        ));
+        config.cors.allow_credentials = false;
+        assert!(config.validate().is_ok());
+
+        for origins in [
+            vec!["*", "https://chat.example.com"],
+            vec!["chat.example.com"],
+            vec!["https://chat.example.com/app"],
+        ] {
+            config.cors.allowed_origins = origins.into_iter().map(String::from).collect();
+            assert!(config.validate().is_err());
+        }
+
+        let mut config = AppConfig::test_config();
+        config.cors.allowed_methods = vec!["get".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_stats_consumer_dead_letter_config() {
+        let consumer: ConsumerConfig = Figment::new()
+            .merge(Yaml::string(
+                "consumer:\n  consumer_group: g\n  consumer_name: c\n  batch_size: 10\n  poll_interval_secs: 1\n  dlq_stream: presence_events_dlq\n",
+            ))
+            .extract_inner("consumer")
+            .unwrap();
+        assert_eq!(consumer.dead_letter_stream, "presence_events_dlq");
+        assert_eq!(consumer.max_retries, 3);
+        assert!(consumer.isolate_failures);
+    }
+
+    #[test]
+    fn test_stats_consumer_claim_config() {
+        let mut config = AppConfig::test_config();
+        assert_eq!(config.stats.consumer.claim_idle_secs, 300);
+        assert_eq!(config.stats.consumer.claim_interval_secs, 60);
+
+        config.stats.consumer.claim_interval_secs = 0;
+        assert!(config.validate().is_err());
+
+        // 关闭认领时不检查间隔
+        config.stats.consumer.claim_idle_secs = 0;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stats_api_token() {
+        let mut config = AppConfig::test_config();
+        assert!(config.stats.api_token.is_none());
+
+        config.stats.api_token = Some("short".to_string());
+        assert!(config.validate().is_err());
+
+        config.stats.api_token = Some("dashboard-token-0123456789".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_message_batch_config() {
+        let mut config = AppConfig::test_config();
+        assert_eq!(config.message.batch_flush_ms, 0);
+        config.message.batch_max = 0;
+        assert!(config.validate().is_ok());
+
+        config.message.batch_flush_ms = 5;
+        assert!(config.validate().is_err());
+
+        config.message.batch_max = 100;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_message_outbox_config() {
+        let mut config = AppConfig::test_config();
+        assert!(!config.message.outbox.enabled);
+        config.message.outbox.batch_size = 0;
+        assert!(config.validate().is_ok());
+
+        config.message.outbox.enabled = true;
+        assert!(config.validate().is_err());
+
+        config.message.outbox.batch_size = 100;
+        assert!(config.validate().is_ok());
+
+        config.message.outbox.retry_backoff_ms = config.message.outbox.max_retry_backoff_ms + 1;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_broadcast_retry_config() {
+        let mut config = AppConfig::test_config();
+        assert_eq!(config.broadcast.publish_retries, 3);
+        assert!(config.validate().is_ok());
+
+        config
+            .broadcast
+            .room_capacities
+            .insert("00000000-0000-0000-0000-000000000001".to_string(), 0);
+        assert!(config.validate().is_err());
+        config.broadcast.room_capacities.clear();
+
+        config.broadcast.max_capacity = config.broadcast.capacity - 1;
+        assert!(config.validate().is_err());
+        config.broadcast.max_capacity = config.broadcast.capacity;
+        assert!(config.validate().is_ok());
+
+        config.broadcast.shrink_after_secs = 0;
+        assert!(config.validate().is_err());
+        config.broadcast.shrink_after_secs = 60;
+        assert!(config.validate().is_ok());
+
+        config.broadcast.retry_backoff_ms = 0;
+        assert!(config.validate().is_err());
+
+        config.broadcast.retry_backoff_ms = 1_000;
+        config.broadcast.max_retry_backoff_ms = 500;
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_env_var_override() {
         // 测试环境变量覆盖
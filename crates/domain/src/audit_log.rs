@@ -0,0 +1,95 @@
+use uuid::Uuid;
+
+use crate::value_objects::{RoomId, Timestamp, UserId};
+
+/// 管理操作审计记录
+///
+/// 只追加不修改；操作人和目标用户被删除后记录仍然保留
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub actor_id: UserId,
+    pub action: String,
+    pub target_user_id: Option<UserId>,
+    /// 操作针对的房间（房间设置修改等）
+    #[serde(default)]
+    pub target_room_id: Option<RoomId>,
+    /// 操作相关的数据（例如影响的条数）
+    pub details: serde_json::Value,
+    pub created_at: Timestamp,
+}
+
+impl AuditLogEntry {
+    /// 清除用户在所有房间的内容
+    pub const USER_CONTENT_PURGED: &'static str = "user_content_purged";
+    /// 停用账号
+    pub const USER_SUSPENDED: &'static str = "user_suspended";
+    /// 重新启用账号
+    pub const USER_ACTIVATED: &'static str = "user_activated";
+    /// 修改房间设置，`details.changes` 为 [`RoomSettingDiff`] 列表
+    pub const ROOM_SETTINGS_UPDATED: &'static str = "room_settings_updated";
+    /// 调整房间成员角色，`details` 记录修改前后的角色
+    pub const MEMBER_ROLE_CHANGED: &'static str = "member_role_changed";
+    /// 转让房间所有权，`details` 记录原 Owner 和新 Owner
+    pub const ROOM_OWNERSHIP_TRANSFERRED: &'static str = "room_ownership_transferred";
+    /// 把成员踢出房间
+    pub const MEMBER_REMOVED: &'static str = "member_removed";
+    /// 删除房间，`details.name` 为房间名（房间删除后无从查起）
+    pub const ROOM_DELETED: &'static str = "room_deleted";
+    /// 授予系统管理员权限
+    pub const SUPERUSER_GRANTED: &'static str = "superuser_granted";
+    /// 撤销系统管理员权限
+    pub const SUPERUSER_REVOKED: &'static str = "superuser_revoked";
+    /// 提交批量创建用户任务，`details` 记录任务ID和人数
+    pub const USERS_BULK_CREATED: &'static str = "users_bulk_created";
+
+    pub fn record(
+        actor_id: UserId,
+        action: &str,
+        target_user_id: Option<UserId>,
+        details: serde_json::Value,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor_id,
+            action: action.to_string(),
+            target_user_id,
+            target_room_id: None,
+            details,
+            created_at: now,
+        }
+    }
+
+    /// 标记操作针对的房间
+    pub fn for_room(mut self, room_id: RoomId) -> Self {
+        self.target_room_id = Some(room_id);
+        self
+    }
+}
+
+/// 房间设置的一项修改：字段名和修改前后的值
+///
+/// 密码只记录“改过了”，前后的值都写成 [`RoomSettingDiff::REDACTED`]，审计日志里不出现密码哈希
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RoomSettingDiff {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+impl RoomSettingDiff {
+    pub const REDACTED: &'static str = "[redacted]";
+
+    pub fn new(field: &str, old: impl serde::Serialize, new: impl serde::Serialize) -> Self {
+        Self {
+            field: field.to_string(),
+            old: serde_json::to_value(old).unwrap_or_default(),
+            new: serde_json::to_value(new).unwrap_or_default(),
+        }
+    }
+
+    pub fn redacted(field: &str) -> Self {
+        Self::new(field, Self::REDACTED, Self::REDACTED)
+    }
+}
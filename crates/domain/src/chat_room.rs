@@ -1,4 +1,6 @@
 use crate::errors::DomainError;
+use crate::reaction::ReactionPolicy;
+use crate::room_member::RoomRole;
 use crate::value_objects::{PasswordHash, RoomId, Timestamp, UserId};
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -18,6 +20,33 @@ pub struct ChatRoom {
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
     pub is_closed: bool,
+    /// 回应策略，默认允许文字标签
+    #[serde(default)]
+    pub reaction_policy: ReactionPolicy,
+    /// 消息内容是否加密存储；开启后不能关闭，由存储层根据房间数据密钥是否存在得出
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+/// 房间发现列表中的一项：房间信息加成员数
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RoomSummary {
+    #[serde(flatten)]
+    pub room: ChatRoom,
+    pub member_count: i64,
+}
+
+/// 用户自己的房间列表中的一项：置顶的在前，其余按最近活动倒序
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MemberRoom {
+    #[serde(flatten)]
+    pub room: ChatRoom,
+    pub role: RoomRole,
+    /// 用户把房间置顶的时间，未置顶为 None；置顶只对用户自己可见
+    pub pinned_at: Option<Timestamp>,
+    /// 最近一条消息的时间，还没有消息时为加入时间
+    pub last_activity_at: Timestamp,
+    pub unread_count: i64,
 }
 
 impl ChatRoom {
@@ -37,6 +66,8 @@ impl ChatRoom {
             created_at,
             updated_at: created_at,
             is_closed: false,
+            reaction_policy: ReactionPolicy::default(),
+            encrypted: false,
         })
     }
 
@@ -57,6 +88,8 @@ impl ChatRoom {
             created_at,
             updated_at: created_at,
             is_closed: false,
+            reaction_policy: ReactionPolicy::default(),
+            encrypted: false,
         })
     }
 
@@ -84,6 +117,11 @@ impl ChatRoom {
         self.updated_at = now;
     }
 
+    pub fn set_reaction_policy(&mut self, policy: ReactionPolicy, now: Timestamp) {
+        self.reaction_policy = policy;
+        self.updated_at = now;
+    }
+
     pub fn close(&mut self, now: Timestamp) {
         self.is_closed = true;
         self.updated_at = now;
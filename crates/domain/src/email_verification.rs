@@ -0,0 +1,39 @@
+use crate::value_objects::{Timestamp, UserId};
+
+/// 邮箱验证令牌记录
+///
+/// 只保存令牌的哈希，数据库泄露也拿不到可用的验证链接；
+/// 每个令牌只能使用一次。
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EmailVerification {
+    pub token_hash: String,
+    pub user_id: UserId,
+    pub expires_at: Timestamp,
+    pub used_at: Option<Timestamp>,
+    pub created_at: Timestamp,
+}
+
+impl EmailVerification {
+    pub fn issue(
+        token_hash: String,
+        user_id: UserId,
+        expires_at: Timestamp,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            token_hash,
+            user_id,
+            expires_at,
+            used_at: None,
+            created_at: now,
+        }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used_at.is_some()
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now >= self.expires_at
+    }
+}
@@ -2,6 +2,8 @@ use std::{error::Error as StdError, fmt, sync::Arc};
 
 use thiserror::Error;
 
+use crate::message::MessageType;
+
 /// 领域错误定义。
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
 pub enum DomainError {
@@ -12,24 +14,95 @@ pub enum DomainError {
     },
     #[error("user already exists")]
     UserAlreadyExists,
+    #[error("email domain not allowed")]
+    EmailDomainNotAllowed,
     #[error("user not found")]
     UserNotFound,
     #[error("room not found")]
     RoomNotFound,
     #[error("message not found")]
     MessageNotFound,
+    #[error("message already deleted")]
+    MessageDeleted,
+    #[error("message is not deleted")]
+    MessageNotDeleted,
+    #[error("message was modified concurrently, current version is {current_version}")]
+    MessageVersionConflict { current_version: i32 },
+    #[error("{message_type:?} message content exceeds {limit} characters")]
+    MessageTooLong {
+        message_type: MessageType,
+        limit: usize,
+    },
+    #[error("room only allows emoji reactions")]
+    ReactionNotAllowed,
+    #[error("at most {limit} reactions per user on one message")]
+    ReactionLimitExceeded { limit: usize },
+    #[error("at most {limit} pinned messages per room")]
+    PinLimitExceeded { limit: usize },
     #[error("user already in room")]
     UserAlreadyInRoom,
     #[error("user not in room")]
     UserNotInRoom,
+    #[error("member not found")]
+    MemberNotFound,
     #[error("room is private")]
     RoomIsPrivate,
+    #[error("invalid room password")]
+    InvalidRoomPassword,
     #[error("room is closed")]
     RoomClosed,
     #[error("insufficient permissions")]
     InsufficientPermissions,
     #[error("operation not allowed")]
     OperationNotAllowed,
+    #[error("room owner must transfer ownership before leaving")]
+    OwnerCannotLeave,
+    #[error("role change not allowed: {reason}")]
+    RoleChangeNotAllowed { reason: &'static str },
+    #[error("email not verified")]
+    EmailNotVerified,
+    #[error("invalid or expired verification token")]
+    InvalidVerificationToken,
+    #[error("verification email sent recently, retry in {retry_after_secs}s")]
+    VerificationResendTooSoon { retry_after_secs: u64 },
+    #[error("invalid or expired password reset token")]
+    InvalidPasswordResetToken,
+    #[error("account suspended")]
+    AccountSuspended,
+    #[error("user is muted in this room for another {remaining_secs}s")]
+    UserMuted { remaining_secs: u64 },
+    #[error("user is banned from this room")]
+    UserBannedFromRoom,
+    #[error("message encryption is not configured")]
+    EncryptionNotConfigured,
+    #[error("search is not available in encrypted rooms")]
+    EncryptedRoomNotSearchable,
+    #[error("file not found")]
+    FileNotFound,
+    #[error("notification not found")]
+    NotificationNotFound,
+    #[error("a request with this idempotency key is still in progress")]
+    IdempotencyKeyInUse,
+    #[error("this idempotency key was already used for a message in another room")]
+    IdempotencyKeyReused,
+    #[error("invitation not found")]
+    InvitationNotFound,
+    #[error("user already has a pending invitation to this room")]
+    InvitationAlreadyPending,
+    #[error("invitation is no longer pending")]
+    InvitationNotPending,
+    #[error("invitation expired")]
+    InvitationExpired,
+    #[error("invite link not found")]
+    InviteNotFound,
+    #[error("invite link expired")]
+    InviteExpired,
+    #[error("invite link has reached its usage limit")]
+    InviteExhausted,
+    #[error("file exceeds {limit} bytes")]
+    FileTooLarge { limit: u64 },
+    #[error("content type {content_type} is not allowed")]
+    UnsupportedContentType { content_type: String },
 }
 
 impl DomainError {
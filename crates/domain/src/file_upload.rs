@@ -0,0 +1,71 @@
+use uuid::Uuid;
+
+use crate::value_objects::{MessageId, Timestamp, UserId};
+
+/// 上传的文件（图片、附件）的元数据，文件内容在对象存储里
+///
+/// 上传后由图片/文件消息引用；一直没有被消息引用的上传会被定期清理
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FileUpload {
+    pub id: Uuid,
+    pub uploader_id: UserId,
+    /// 客户端提供的原始文件名，只用于展示和下载时的文件名
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// 对象存储中的键
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    /// 引用这个文件的消息，未发送前为 None
+    pub message_id: Option<MessageId>,
+    pub created_at: Timestamp,
+}
+
+impl FileUpload {
+    /// 文件名最多保留的字符数，超出部分截掉
+    pub const MAX_FILE_NAME_CHARS: usize = 255;
+
+    pub fn new(
+        id: Uuid,
+        uploader_id: UserId,
+        file_name: &str,
+        content_type: &str,
+        size_bytes: i64,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            id,
+            uploader_id,
+            file_name: Self::sanitize_file_name(file_name),
+            content_type: content_type.to_string(),
+            size_bytes,
+            storage_key: format!("{}/{}", Uuid::from(uploader_id), id),
+            message_id: None,
+            created_at: now,
+        }
+    }
+
+    /// 是否是图片，图片消息只能引用图片
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+
+    /// 去掉路径部分和控制字符，空文件名用 `file` 代替
+    fn sanitize_file_name(file_name: &str) -> String {
+        let base = file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let name: String = base
+            .chars()
+            .filter(|ch| !ch.is_control())
+            .take(Self::MAX_FILE_NAME_CHARS)
+            .collect();
+        if name.is_empty() {
+            "file".to_string()
+        } else {
+            name
+        }
+    }
+}
@@ -3,22 +3,47 @@
 //! 这里只保留纯业务概念：实体、值对象、领域错误。
 //! 所有外部技术细节（数据库、加密、异步运行时等）都被隔离在其他层。
 
+mod audit_log;
 mod chat_room;
+mod email_verification;
 mod errors;
+mod file_upload;
+mod mention;
 mod message;
 mod message_delivery;
+mod notification;
 mod organization;
+mod outbox;
+mod password_reset;
+mod reaction;
+mod refresh_token;
+mod room_invitation;
+mod room_invite;
 mod room_member;
 mod user;
 mod value_objects;
 
-pub use chat_room::{ChatRoom, ChatRoomVisibility};
+pub use audit_log::{AuditLogEntry, RoomSettingDiff};
+pub use chat_room::{ChatRoom, ChatRoomVisibility, MemberRoom, RoomSummary};
+pub use email_verification::EmailVerification;
 pub use errors::{DomainError, RepositoryError};
-pub use message::{Message, MessageRevision, MessageType};
+pub use file_upload::FileUpload;
+pub use mention::{extract_mentions, MentionedMessage, EVERYONE_MENTION, MAX_MENTIONS_PER_MESSAGE};
+pub use message::{
+    ExportedMessage, Message, MessageContentLimits, MessageRevision, MessageType, PinnedMessage,
+    ThreadMessage,
+};
 pub use message_delivery::MessageDelivery;
+pub use notification::Notification;
 pub use organization::Organization;
-pub use room_member::{RoomMember, RoomRole};
-pub use user::{User, UserStatus};
+pub use outbox::{OutboxEntry, OutboxStatus};
+pub use password_reset::PasswordReset;
+pub use reaction::{ReactionEmoji, ReactionKind, ReactionPolicy, ReactionSummary};
+pub use refresh_token::RefreshToken;
+pub use room_invitation::{InvitationStatus, RoomInvitation};
+pub use room_invite::RoomInvite;
+pub use room_member::{RoomBan, RoomMember, RoomMemberListing, RoomMemberRestriction, RoomRole};
+pub use user::{ProfileChanges, User, UserProfile, UserStatus};
 pub use value_objects::{
     MessageContent, MessageId, OrgId, OrgPath, PasswordHash, RoomId, Timestamp, UserEmail, UserId,
     Username,
@@ -44,6 +69,10 @@ mod rbac_tests {
             created_at: now,
             updated_at: now,
             org_id: None,
+            display_name: None,
+            avatar_url: None,
+            bio: None,
+            token_version: 0,
         };
 
         // 初始状态下不是管理员
@@ -138,3 +167,399 @@ mod rbac_tests {
         assert!(!member.can_delete_messages());
     }
 }
+
+#[cfg(test)]
+mod message_limit_tests {
+    use super::*;
+
+    fn content_of(len: usize) -> MessageContent {
+        MessageContent::new("a".repeat(len)).unwrap()
+    }
+
+    fn limits() -> MessageContentLimits {
+        MessageContentLimits {
+            text: 10,
+            image: 5,
+            file: 3,
+        }
+    }
+
+    /// 每种类型恰好在上限处通过，超出一个字符即失败
+    #[test]
+    fn each_type_is_enforced_at_its_boundary() {
+        let limits = limits();
+        for (message_type, limit) in [
+            (MessageType::Text, 10),
+            (MessageType::Image, 5),
+            (MessageType::File, 3),
+        ] {
+            assert!(limits.check(&message_type, &content_of(limit)).is_ok());
+            assert_eq!(
+                limits.check(&message_type, &content_of(limit + 1)),
+                Err(DomainError::MessageTooLong {
+                    message_type: message_type.clone(),
+                    limit,
+                })
+            );
+        }
+    }
+
+    /// 同一内容在不同类型下结果不同：上限互相独立
+    #[test]
+    fn limits_are_distinct_per_type() {
+        let limits = limits();
+        let content = content_of(5);
+
+        assert!(limits.check(&MessageType::Text, &content).is_ok());
+        assert!(limits.check(&MessageType::Image, &content).is_ok());
+        assert!(limits.check(&MessageType::File, &content).is_err());
+    }
+
+    /// 按字符计数，多字节字符不会被误判超限
+    #[test]
+    fn limit_counts_characters_not_bytes() {
+        let content = MessageContent::new("你好世界你").unwrap();
+        assert!(limits().check(&MessageType::Image, &content).is_ok());
+    }
+
+    #[test]
+    fn error_names_type_and_limit() {
+        let err = limits()
+            .check(&MessageType::Image, &content_of(6))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Image message content exceeds 5 characters"
+        );
+    }
+
+    fn is_invalid_content(result: Result<(), DomainError>) -> bool {
+        matches!(
+            result,
+            Err(DomainError::InvalidArgument {
+                field: "content",
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn validate_rejects_oversized_text() {
+        assert!(limits()
+            .validate(&MessageType::Text, &content_of(10))
+            .is_ok());
+        assert!(matches!(
+            limits().validate(&MessageType::Text, &content_of(11)),
+            Err(DomainError::MessageTooLong { limit: 10, .. })
+        ));
+    }
+
+    /// 换行和制表符允许，其余控制字符拒绝
+    #[test]
+    fn validate_rejects_control_characters() {
+        let limits = MessageContentLimits::default();
+        let multiline = MessageContent::new("第一行\r\n\t第二行").unwrap();
+        assert!(limits.validate(&MessageType::Text, &multiline).is_ok());
+
+        for raw in [
+            "bell\u{7}",
+            "nul\0byte",
+            "esc\u{1b}[31m",
+            "del\u{7f}",
+            "c1\u{85}",
+        ] {
+            let content = MessageContent::new(raw).unwrap();
+            assert!(
+                is_invalid_content(limits.validate(&MessageType::Text, &content)),
+                "{raw:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn whitespace_only_content_is_rejected() {
+        for raw in ["", "   ", "\n\t", "\u{3000}"] {
+            assert!(matches!(
+                MessageContent::new(raw),
+                Err(DomainError::InvalidArgument {
+                    field: "message_content",
+                    ..
+                })
+            ));
+        }
+    }
+
+    /// 图片和文件消息的内容必须是上传文件的ID
+    #[test]
+    fn image_and_file_require_an_uploaded_file_id() {
+        let limits = MessageContentLimits::default();
+        let file_id = uuid::Uuid::new_v4();
+        let reference = MessageContent::new(format!(" {file_id} ")).unwrap();
+        let url = MessageContent::new("https://example.com/cat.png").unwrap();
+
+        for message_type in [MessageType::Image, MessageType::File] {
+            assert!(limits.validate(&message_type, &reference).is_ok());
+            assert!(is_invalid_content(limits.validate(&message_type, &url)));
+        }
+        assert_eq!(reference.file_id(), Ok(file_id));
+        // 文本消息不要求引用文件
+        assert!(limits.validate(&MessageType::Text, &url).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod reaction_tests {
+    use super::*;
+
+    #[test]
+    fn text_label_is_normalized() {
+        let label = ReactionEmoji::new(" LGTM ").unwrap();
+        assert_eq!(label.as_str(), "lgtm");
+        assert_eq!(label.kind(), ReactionKind::Label);
+
+        let plus_one = ReactionEmoji::new("+1").unwrap();
+        assert_eq!(plus_one.kind(), ReactionKind::Label);
+    }
+
+    #[test]
+    fn emoji_keeps_original_form() {
+        for emoji in ["👍", "❤️", "1️⃣", "👍🏽"] {
+            let reaction = ReactionEmoji::new(emoji).unwrap();
+            assert_eq!(reaction.as_str(), emoji);
+            assert_eq!(reaction.kind(), ReactionKind::Emoji);
+        }
+    }
+
+    #[test]
+    fn invalid_labels_are_rejected() {
+        let oversized = "a".repeat(ReactionEmoji::MAX_LABEL_CHARS + 1);
+        assert!(ReactionEmoji::new(oversized).is_err());
+        assert!(ReactionEmoji::new("ship it").is_err());
+        assert!(ReactionEmoji::new("lgtm~").is_err());
+        assert!(ReactionEmoji::new("ok👍").is_err());
+        assert!(ReactionEmoji::new("   ").is_err());
+    }
+
+    #[test]
+    fn emoji_only_policy_rejects_labels() {
+        let policy = ReactionPolicy::EmojiOnly;
+        assert!(policy.allows(ReactionKind::Emoji));
+        assert!(!policy.allows(ReactionKind::Label));
+        assert!(ReactionPolicy::default().allows(ReactionKind::Label));
+    }
+}
+
+#[cfg(test)]
+mod mention_tests {
+    use super::*;
+
+    #[test]
+    fn mentions_are_deduplicated_and_lowercased() {
+        let mentions = extract_mentions("@Alice ping @bob and @alice again");
+        assert_eq!(mentions, ["alice", "bob"]);
+    }
+
+    #[test]
+    fn email_addresses_and_trailing_dots_are_not_mentions() {
+        assert!(extract_mentions("write to alice@example.com").is_empty());
+        assert_eq!(extract_mentions("thanks @carol."), ["carol"]);
+        assert!(extract_mentions("just an @ sign").is_empty());
+    }
+
+    #[test]
+    fn mentions_are_capped_per_message() {
+        let content = (0..MAX_MENTIONS_PER_MESSAGE + 5)
+            .map(|i| format!("@user{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(extract_mentions(&content).len(), MAX_MENTIONS_PER_MESSAGE);
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    fn user() -> User {
+        User::register(
+            UserId::from(Uuid::new_v4()),
+            Username::parse("profile_user").unwrap(),
+            UserEmail::parse("profile@example.com").unwrap(),
+            PasswordHash::new("hashed_password").unwrap(),
+            OffsetDateTime::now_utc(),
+        )
+    }
+
+    #[test]
+    fn details_are_trimmed_and_bump_updated_at() {
+        let mut user = user();
+        let later = user.updated_at + Duration::seconds(5);
+
+        user.update_details(
+            ProfileChanges {
+                display_name: Some("  Alice  ".to_string()),
+                avatar_url: Some("https://cdn.example.com/a.png".to_string()),
+                bio: None,
+            },
+            later,
+        )
+        .unwrap();
+
+        assert_eq!(user.display_name.as_deref(), Some("Alice"));
+        assert_eq!(
+            user.avatar_url.as_deref(),
+            Some("https://cdn.example.com/a.png")
+        );
+        assert_eq!(user.bio, None);
+        assert_eq!(user.updated_at, later);
+    }
+
+    #[test]
+    fn empty_value_clears_field_and_none_keeps_it() {
+        let mut user = user();
+        let now = user.updated_at;
+        user.update_details(
+            ProfileChanges {
+                display_name: Some("Alice".to_string()),
+                bio: Some("hello".to_string()),
+                ..Default::default()
+            },
+            now,
+        )
+        .unwrap();
+
+        user.update_details(
+            ProfileChanges {
+                display_name: Some("   ".to_string()),
+                ..Default::default()
+            },
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(user.display_name, None);
+        assert_eq!(user.bio.as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn invalid_details_leave_user_unchanged() {
+        let mut user = user();
+        let before = user.clone();
+
+        for changes in [
+            ProfileChanges {
+                display_name: Some("名".repeat(User::MAX_DISPLAY_NAME_CHARS + 1)),
+                ..Default::default()
+            },
+            ProfileChanges {
+                display_name: Some("Alice".to_string()),
+                avatar_url: Some("javascript:alert(1)".to_string()),
+                ..Default::default()
+            },
+            ProfileChanges {
+                avatar_url: Some("https://".to_string()),
+                ..Default::default()
+            },
+            ProfileChanges {
+                avatar_url: Some("https://example.com/a b.png".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                user.update_details(changes, before.updated_at + Duration::seconds(1)),
+                Err(DomainError::InvalidArgument { .. })
+            ));
+        }
+        assert_eq!(user, before);
+
+        // 恰好 64 个字符可以通过
+        let name = "名".repeat(User::MAX_DISPLAY_NAME_CHARS);
+        user.update_details(
+            ProfileChanges {
+                display_name: Some(name.clone()),
+                avatar_url: Some("HTTP://example.com".to_string()),
+                ..Default::default()
+            },
+            before.updated_at,
+        )
+        .unwrap();
+        assert_eq!(user.display_name, Some(name));
+    }
+}
+
+#[cfg(test)]
+mod file_upload_tests {
+    use super::*;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    fn upload(file_name: &str, content_type: &str) -> FileUpload {
+        FileUpload::new(
+            Uuid::new_v4(),
+            UserId::from(Uuid::new_v4()),
+            file_name,
+            content_type,
+            42,
+            OffsetDateTime::now_utc(),
+        )
+    }
+
+    #[test]
+    fn file_name_keeps_only_the_base_name() {
+        assert_eq!(upload("../../etc/passwd", "text/plain").file_name, "passwd");
+        assert_eq!(
+            upload("C:\\photos\\cat.png", "image/png").file_name,
+            "cat.png"
+        );
+        assert_eq!(
+            upload("  report\u{0}.pdf ", "application/pdf").file_name,
+            "report.pdf"
+        );
+        assert_eq!(upload("dir/", "text/plain").file_name, "file");
+    }
+
+    #[test]
+    fn storage_key_is_scoped_to_uploader() {
+        let upload = upload("cat.png", "image/png");
+        assert_eq!(
+            upload.storage_key,
+            format!("{}/{}", Uuid::from(upload.uploader_id), upload.id)
+        );
+        assert!(upload.is_image());
+        assert!(!self::upload("a.pdf", "application/pdf").is_image());
+    }
+}
+
+#[cfg(test)]
+mod password_tests {
+    use super::*;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    #[test]
+    fn password_length_is_validated() {
+        assert!(User::validate_password("secret").is_ok());
+        assert!(User::validate_password("短密码").is_err());
+        assert!(User::validate_password(&"a".repeat(User::MAX_PASSWORD_BYTES)).is_ok());
+        assert!(User::validate_password(&"a".repeat(User::MAX_PASSWORD_BYTES + 1)).is_err());
+    }
+
+    #[test]
+    fn set_password_bumps_token_version() {
+        let now = OffsetDateTime::now_utc();
+        let mut user = User::register(
+            UserId::from(Uuid::new_v4()),
+            Username::parse("password_user").unwrap(),
+            UserEmail::parse("password@example.com").unwrap(),
+            PasswordHash::new("hashed_old").unwrap(),
+            now,
+        );
+        assert_eq!(user.token_version, 0);
+
+        user.set_password(PasswordHash::new("hashed_new").unwrap(), now);
+        assert_eq!(user.password.as_str(), "hashed_new");
+        assert_eq!(user.token_version, 1);
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+
+/// 单条消息最多解析的提及人数，防止一条消息刷屏式 @ 全房间
+pub const MAX_MENTIONS_PER_MESSAGE: usize = 20;
+
+/// `@everyone` 提及房间里的所有成员，只有房间 Owner/Admin 可以使用
+pub const EVERYONE_MENTION: &str = "everyone";
+
+/// 提及收件箱中的一条：提及当前用户的消息，以及用户是否已读到这里
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MentionedMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    pub is_read: bool,
+}
+
+/// 从消息内容中解析 `@username`，按首次出现顺序去重，统一小写
+///
+/// `@` 必须位于开头或空白之后，避免把邮箱地址当成提及；
+/// 结尾的句点视为标点而非用户名的一部分
+pub fn extract_mentions(content: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((index, ch)) = chars.next() {
+        let at_boundary = previous.is_none_or(char::is_whitespace);
+        previous = Some(ch);
+        if ch != '@' || !at_boundary {
+            continue;
+        }
+
+        let start = index + ch.len_utf8();
+        let mut end = start;
+        while let Some(&(next_index, next)) = chars.peek() {
+            if !is_username_char(next) {
+                break;
+            }
+            end = next_index + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        let name = content[start..end].trim_end_matches('.').to_lowercase();
+        if name.is_empty() || mentions.contains(&name) {
+            continue;
+        }
+        mentions.push(name);
+        if mentions.len() == MAX_MENTIONS_PER_MESSAGE {
+            break;
+        }
+    }
+
+    mentions
+}
+
+fn is_username_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.')
+}
@@ -13,10 +13,85 @@ pub enum MessageType {
     File,
 }
 
+/// 按消息类型区分的内容长度上限（字符数）
+///
+/// 具体数值由部署配置决定，领域层只负责校验规则本身
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageContentLimits {
+    pub text: usize,
+    pub image: usize,
+    pub file: usize,
+}
+
+impl Default for MessageContentLimits {
+    fn default() -> Self {
+        Self {
+            text: 5000,
+            image: 1000,
+            file: 1000,
+        }
+    }
+}
+
+impl MessageContentLimits {
+    pub fn limit_for(&self, message_type: &MessageType) -> usize {
+        match message_type {
+            MessageType::Text => self.text,
+            MessageType::Image => self.image,
+            MessageType::File => self.file,
+        }
+    }
+
+    /// 校验内容长度，超限时返回带类型和上限的错误
+    pub fn check(
+        &self,
+        message_type: &MessageType,
+        content: &MessageContent,
+    ) -> Result<(), DomainError> {
+        let limit = self.limit_for(message_type);
+        if content.as_str().chars().count() > limit {
+            return Err(DomainError::MessageTooLong {
+                message_type: message_type.clone(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// 发送和编辑时的完整校验：长度上限、控制字符，图片/文件消息必须是上传文件的ID
+    ///
+    /// 只在写入新内容时调用；从存储读出的历史内容不重新校验
+    pub fn validate(
+        &self,
+        message_type: &MessageType,
+        content: &MessageContent,
+    ) -> Result<(), DomainError> {
+        self.check(message_type, content)?;
+        // 换行和制表符是正常排版，其余控制字符会破坏客户端渲染和导出
+        if content
+            .as_str()
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+        {
+            return Err(DomainError::invalid_argument(
+                "content",
+                "must not contain control characters",
+            ));
+        }
+        if *message_type != MessageType::Text {
+            content.file_id()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct MessageRevision {
     pub content: MessageContent,
     pub updated_at: Timestamp,
+    /// `content` 是否为密文；由存储层按行上的标记填写，解密后清除，不暴露给客户端
+    #[serde(skip)]
+    pub encrypted: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -27,10 +102,23 @@ pub struct Message {
     pub content: MessageContent,
     pub message_type: MessageType,
     pub reply_to: Option<MessageId>,
+    /// 所在线程的根消息，顶层消息为 None；由回复链推导，写入后不变
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_root_id: Option<MessageId>,
+    /// 房间内序列号，由存储层分配，是消息排序的唯一依据（0 表示尚未持久化）
+    #[serde(default)]
+    pub seq: i64,
+    /// 创建时间，仅用于展示；各实例时钟可能存在偏差，不能用于排序
     pub created_at: Timestamp,
     pub last_revision: Option<MessageRevision>,
+    /// 乐观锁版本号，新消息为 1，每次编辑加一；编辑时带上读到的版本防止互相覆盖
+    #[serde(default = "Message::initial_version")]
+    pub version: i32,
     #[serde(skip_serializing)] // 删除标记不暴露给客户端
     pub is_deleted: bool,
+    /// `content` 是否为密文；由存储层按行上的标记填写，解密后清除，不暴露给客户端
+    #[serde(skip)]
+    pub encrypted: bool,
 }
 
 impl Message {
@@ -51,25 +139,79 @@ impl Message {
             content,
             message_type,
             reply_to,
+            thread_root_id: None,
+            seq: 0,
             created_at,
             last_revision: None,
+            version: Self::initial_version(),
             is_deleted: false,
+            encrypted: false,
         })
     }
 
+    fn initial_version() -> i32 {
+        1
+    }
+
     pub fn edit(&mut self, new_content: MessageContent, at: Timestamp) -> Result<(), DomainError> {
         if self.is_deleted {
-            return Err(DomainError::OperationNotAllowed);
+            return Err(DomainError::MessageDeleted);
         }
         self.last_revision = Some(MessageRevision {
             content: self.content.clone(),
             updated_at: at,
+            encrypted: self.encrypted,
         });
         self.content = new_content;
+        self.version += 1;
         Ok(())
     }
 
     pub fn mark_deleted(&mut self) {
         self.is_deleted = true;
     }
+
+    /// 本消息所在线程的根：回复返回线程根，顶层消息返回自己
+    pub fn thread_root(&self) -> MessageId {
+        self.thread_root_id.unwrap_or(self.id)
+    }
+
+    /// 作为 `parent` 的回复加入其线程
+    pub fn reply_in_thread(&mut self, parent: &Message) {
+        self.reply_to = Some(parent.id);
+        self.thread_root_id = Some(parent.thread_root());
+    }
+}
+
+/// 线程中的一条回复；父消息被删除时回复仍然保留，并带上标记
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ThreadMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    pub parent_deleted: bool,
+}
+
+/// 导出聊天记录时的一行：带上发送者用户名，已删除的消息也导出并带上标记
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedMessage {
+    pub id: MessageId,
+    pub sender_username: String,
+    pub content: MessageContent,
+    pub message_type: MessageType,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: Timestamp,
+    pub is_deleted: bool,
+    /// `content` 是否为密文，解密后清除
+    #[serde(skip)]
+    pub encrypted: bool,
+}
+
+/// 房间置顶的消息，带上置顶人和置顶时间
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PinnedMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    /// 置顶人注销后为空
+    pub pinned_by: Option<UserId>,
+    pub pinned_at: Timestamp,
 }
@@ -0,0 +1,127 @@
+use uuid::Uuid;
+
+use crate::room_member::RoomRole;
+use crate::value_objects::{MessageId, RoomId, Timestamp, UserId};
+
+/// 发给单个用户的通知，离线期间产生的通知在下次登录后通过通知列表查看
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Notification {
+    pub id: Uuid,
+    /// 接收通知的用户
+    pub user_id: UserId,
+    pub kind: String,
+    /// 触发通知的用户
+    pub actor_id: Option<UserId>,
+    pub room_id: Option<RoomId>,
+    pub message_id: Option<MessageId>,
+    /// 各类通知的附加信息，例如角色变化后的新角色
+    pub data: serde_json::Value,
+    pub created_at: Timestamp,
+    /// 已读时间，未读为空
+    pub read_at: Option<Timestamp>,
+}
+
+impl Notification {
+    /// 在消息中被 @ 提及
+    pub const MENTION: &'static str = "mention";
+    /// 被房间管理员拉进房间
+    pub const ROOM_INVITE: &'static str = "room_invite";
+    /// 收到入房邀请，`data.invitation_id` 是邀请 ID
+    pub const ROOM_INVITATION: &'static str = "room_invitation";
+    /// 在房间里的角色被调整
+    pub const ROLE_CHANGED: &'static str = "role_changed";
+
+    fn new(
+        user_id: UserId,
+        kind: &str,
+        actor_id: UserId,
+        room_id: RoomId,
+        data: serde_json::Value,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            kind: kind.to_string(),
+            actor_id: Some(actor_id),
+            room_id: Some(room_id),
+            message_id: None,
+            data,
+            created_at: now,
+            read_at: None,
+        }
+    }
+
+    /// 用户在 `message_id` 这条消息里被 `actor_id` 提及
+    pub fn mention(
+        user_id: UserId,
+        actor_id: UserId,
+        room_id: RoomId,
+        message_id: MessageId,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            message_id: Some(message_id),
+            ..Self::new(
+                user_id,
+                Self::MENTION,
+                actor_id,
+                room_id,
+                serde_json::json!({}),
+                now,
+            )
+        }
+    }
+
+    /// 用户被 `actor_id` 拉进房间
+    pub fn room_invite(user_id: UserId, actor_id: UserId, room_id: RoomId, now: Timestamp) -> Self {
+        Self::new(
+            user_id,
+            Self::ROOM_INVITE,
+            actor_id,
+            room_id,
+            serde_json::json!({}),
+            now,
+        )
+    }
+
+    /// 用户收到 `actor_id` 发出的入房邀请
+    pub fn room_invitation(
+        user_id: UserId,
+        actor_id: UserId,
+        room_id: RoomId,
+        invitation_id: Uuid,
+        now: Timestamp,
+    ) -> Self {
+        Self::new(
+            user_id,
+            Self::ROOM_INVITATION,
+            actor_id,
+            room_id,
+            serde_json::json!({ "invitation_id": invitation_id }),
+            now,
+        )
+    }
+
+    /// 用户在房间里的角色被 `actor_id` 调整为 `role`
+    pub fn role_changed(
+        user_id: UserId,
+        actor_id: UserId,
+        room_id: RoomId,
+        role: &RoomRole,
+        now: Timestamp,
+    ) -> Self {
+        Self::new(
+            user_id,
+            Self::ROLE_CHANGED,
+            actor_id,
+            room_id,
+            serde_json::json!({ "role": role }),
+            now,
+        )
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.read_at.is_some()
+    }
+}
@@ -0,0 +1,30 @@
+use crate::value_objects::{MessageId, RoomId, Timestamp};
+
+/// 发件箱记录的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "outbox_status", rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// 等待发布（包括失败后等待重试）
+    Pending,
+    Dispatched,
+    /// 重试次数用尽，等待管理员重新入队
+    DeadLetter,
+}
+
+/// 待广播的新消息，和消息本身在同一个事务里写入
+/// 对应数据库表：message_outbox
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub message_id: MessageId,
+    pub room_id: RoomId,
+    pub status: OutboxStatus,
+    /// 已经尝试发布的次数
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    /// 下一次可以尝试发布的时间
+    pub next_attempt_at: Timestamp,
+    pub created_at: Timestamp,
+    pub dispatched_at: Option<Timestamp>,
+}
@@ -0,0 +1,102 @@
+use crate::errors::DomainError;
+use crate::value_objects::UserId;
+
+/// 回应的种类：emoji 或简短的文字标签（如 "+1"、"LGTM"）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionKind {
+    Emoji,
+    Label,
+}
+
+/// 房间的回应策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionPolicy {
+    /// emoji 和文字标签都允许
+    #[default]
+    Any,
+    /// 只允许 emoji
+    EmojiOnly,
+}
+
+impl ReactionPolicy {
+    pub fn allows(self, kind: ReactionKind) -> bool {
+        match self {
+            ReactionPolicy::Any => true,
+            ReactionPolicy::EmojiOnly => kind == ReactionKind::Emoji,
+        }
+    }
+}
+
+/// 回应内容：emoji 保存客户端发来的原始字符串，文字标签规范化为小写
+///
+/// 种类由内容本身决定，不单独存储：全部由字母、数字和 `+-_!?` 组成的是文字标签，
+/// 其余不含字母的是 emoji
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ReactionEmoji(String);
+
+impl ReactionEmoji {
+    /// 组合 emoji（肤色、ZWJ 序列）可能由多个字符组成，这里只限制总长度
+    pub const MAX_CHARS: usize = 32;
+    /// 文字标签的最大字符数
+    pub const MAX_LABEL_CHARS: usize = 16;
+
+    pub fn new(value: impl Into<String>) -> Result<Self, DomainError> {
+        let value = value.into();
+        let value = value.trim();
+        if value.is_empty() || value.chars().any(char::is_whitespace) {
+            return Err(DomainError::invalid_argument(
+                "reaction",
+                "cannot be empty or contain whitespace",
+            ));
+        }
+
+        if Self::is_label(value) {
+            if value.chars().count() > Self::MAX_LABEL_CHARS {
+                return Err(DomainError::invalid_argument("reaction", "label too long"));
+            }
+            // "LGTM" 和 "lgtm" 聚合到同一个回应
+            return Ok(Self(value.to_lowercase()));
+        }
+
+        // 夹杂字母的不是 emoji，也不是合法标签（如 "a👍"、"LGTM!!~"）
+        if value.chars().any(char::is_alphabetic) {
+            return Err(DomainError::invalid_argument(
+                "reaction",
+                "must be an emoji or a label of letters, digits and +-_!?",
+            ));
+        }
+        if value.chars().count() > Self::MAX_CHARS {
+            return Err(DomainError::invalid_argument("reaction", "too long"));
+        }
+        Ok(Self(value.to_owned()))
+    }
+
+    pub fn kind(&self) -> ReactionKind {
+        if Self::is_label(&self.0) {
+            ReactionKind::Label
+        } else {
+            ReactionKind::Emoji
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn is_label(value: &str) -> bool {
+        value
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '+' | '-' | '_' | '!' | '?'))
+    }
+}
+
+/// 单条消息上某个 emoji 的聚合结果
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: i64,
+    /// 回应过的用户，按回应时间先后排列
+    pub user_ids: Vec<UserId>,
+}
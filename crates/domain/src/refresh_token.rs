@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use crate::value_objects::{Timestamp, UserId};
+
+/// 刷新令牌记录
+///
+/// 同一次登录派生出的所有刷新令牌共享 `family_id`，
+/// 轮换时旧令牌被吊销并指向新令牌；吊销后的令牌再次出现即视为泄露。
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RefreshToken {
+    pub jti: Uuid,
+    pub user_id: UserId,
+    pub family_id: Uuid,
+    pub expires_at: Timestamp,
+    pub revoked_at: Option<Timestamp>,
+    pub replaced_by: Option<Uuid>,
+    pub created_at: Timestamp,
+}
+
+impl RefreshToken {
+    pub fn issue(
+        jti: Uuid,
+        user_id: UserId,
+        family_id: Uuid,
+        expires_at: Timestamp,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            jti,
+            user_id,
+            family_id,
+            expires_at,
+            revoked_at: None,
+            replaced_by: None,
+            created_at: now,
+        }
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now >= self.expires_at
+    }
+}
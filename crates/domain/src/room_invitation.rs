@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+use crate::value_objects::{RoomId, Timestamp, UserId};
+
+/// 房间邀请的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "invitation_status", rename_all = "lowercase")]
+pub enum InvitationStatus {
+    /// 等待被邀请人处理
+    Pending,
+    Accepted,
+    Declined,
+    /// 邀请人或房间管理员撤回
+    Revoked,
+    /// 过期未处理，发新邀请时才会落库
+    Expired,
+}
+
+/// 房间管理员发给用户的入房邀请，被邀请人接受后才加入房间
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoomInvitation {
+    pub id: Uuid,
+    pub room_id: RoomId,
+    pub inviter_id: UserId,
+    pub invitee_id: UserId,
+    pub status: InvitationStatus,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    /// 接受、拒绝或撤回的时间
+    pub responded_at: Option<Timestamp>,
+}
+
+impl RoomInvitation {
+    pub fn new(
+        room_id: RoomId,
+        inviter_id: UserId,
+        invitee_id: UserId,
+        now: Timestamp,
+        expires_at: Timestamp,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            room_id,
+            inviter_id,
+            invitee_id,
+            status: InvitationStatus::Pending,
+            created_at: now,
+            expires_at,
+            responded_at: None,
+        }
+    }
+
+    /// 过期的邀请在库里可能仍是 pending，按时间判断
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.status == InvitationStatus::Expired
+            || (self.status == InvitationStatus::Pending && now >= self.expires_at)
+    }
+
+    /// 还能被接受或拒绝
+    pub fn is_pending(&self, now: Timestamp) -> bool {
+        self.status == InvitationStatus::Pending && now < self.expires_at
+    }
+}
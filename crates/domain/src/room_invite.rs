@@ -0,0 +1,47 @@
+use crate::value_objects::{RoomId, Timestamp, UserId};
+
+/// 可分享的入房邀请链接：拿到令牌的用户直接加入房间，可限制有效期和使用次数
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoomInvite {
+    /// 链接里的随机令牌
+    pub token: String,
+    pub room_id: RoomId,
+    pub creator_id: UserId,
+    /// 最多可使用的次数，为空表示不限
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+    /// 为空表示永不过期
+    pub expires_at: Option<Timestamp>,
+    pub created_at: Timestamp,
+}
+
+impl RoomInvite {
+    pub fn new(
+        token: String,
+        room_id: RoomId,
+        creator_id: UserId,
+        max_uses: Option<i32>,
+        expires_at: Option<Timestamp>,
+        now: Timestamp,
+    ) -> Self {
+        Self {
+            token,
+            room_id,
+            creator_id,
+            max_uses,
+            used_count: 0,
+            expires_at,
+            created_at: now,
+        }
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// 使用次数已经用完
+    pub fn is_exhausted(&self) -> bool {
+        self.max_uses
+            .is_some_and(|max_uses| self.used_count >= max_uses)
+    }
+}
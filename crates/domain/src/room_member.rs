@@ -62,3 +62,59 @@ impl RoomMember {
         self.last_read_message = Some(message_id);
     }
 }
+
+/// 成员在房间内受到的限制：禁言到期时间和是否被封禁（封禁详情见 [`RoomBan`]）
+///
+/// 独立于成员记录保存：被封禁的用户已经不在房间里，限制仍然要生效
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoomMemberRestriction {
+    pub room_id: RoomId,
+    pub user_id: UserId,
+    pub muted_until: Option<Timestamp>,
+    pub banned: bool,
+}
+
+impl RoomMemberRestriction {
+    pub fn none(room_id: RoomId, user_id: UserId) -> Self {
+        Self {
+            room_id,
+            user_id,
+            muted_until: None,
+            banned: false,
+        }
+    }
+
+    /// 禁言剩余秒数（向上取整），未禁言或已到期返回 None
+    pub fn mute_remaining_secs(&self, now: Timestamp) -> Option<u64> {
+        let remaining = self.muted_until? - now;
+        if !remaining.is_positive() {
+            return None;
+        }
+        let secs = remaining.whole_seconds() as u64;
+        Some(if remaining.subsec_nanoseconds() > 0 {
+            secs + 1
+        } else {
+            secs
+        })
+    }
+}
+
+/// 成员列表中的一项：成员关系加上用户名，列表页不需要逐个查询用户资料
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoomMemberListing {
+    pub user_id: UserId,
+    pub username: String,
+    pub role: RoomRole,
+    pub joined_at: Timestamp,
+}
+
+/// 房间封禁记录：被封禁的用户不在房间里，也不能通过加入、邀请或邀请链接回来
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoomBan {
+    pub room_id: RoomId,
+    pub user_id: UserId,
+    /// 执行封禁的管理员，其账号注销后为空
+    pub banned_by: Option<UserId>,
+    pub reason: Option<String>,
+    pub created_at: Timestamp,
+}
@@ -1,3 +1,4 @@
+use crate::errors::DomainError;
 use crate::value_objects::{OrgId, PasswordHash, Timestamp, UserEmail, UserId, Username};
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, sqlx::Type)]
@@ -10,6 +11,9 @@ pub enum UserStatus {
     Inactive,
     #[sqlx(rename = "suspended")]
     Suspended,
+    /// 已注册但还没有验证邮箱，不能登录
+    #[sqlx(rename = "pending_verification")]
+    PendingVerification,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -23,11 +27,39 @@ pub struct User {
     pub is_superuser: bool, // 系统级管理员标识
     // 新增组织字段（仅引用，不冗余path）
     pub org_id: Option<OrgId>,
+    /// 展示名，未设置时客户端显示用户名
+    pub display_name: Option<String>,
+    /// 头像地址，只接受 http(s) URL
+    pub avatar_url: Option<String>,
+    /// 个人简介
+    pub bio: Option<String>,
+    /// 令牌版本，修改密码后递增，之前签发的令牌随之失效
+    #[serde(default, skip_serializing)]
+    pub token_version: i32,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
 }
 
+/// 个人资料修改：字段为 None 表示不修改，空字符串（去掉首尾空白后）表示清空
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileChanges {
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+}
+
 impl User {
+    /// 展示名的最大字符数
+    pub const MAX_DISPLAY_NAME_CHARS: usize = 64;
+    /// 头像地址的最大长度
+    pub const MAX_AVATAR_URL_LEN: usize = 2048;
+    /// 个人简介的最大字符数
+    pub const MAX_BIO_CHARS: usize = 500;
+    /// 密码的最少字符数
+    pub const MIN_PASSWORD_CHARS: usize = 6;
+    /// 密码的最大字节数（bcrypt 只使用前 72 字节，再长就被悄悄截断了）
+    pub const MAX_PASSWORD_BYTES: usize = 72;
+
     pub fn register(
         id: UserId,
         username: Username,
@@ -43,6 +75,10 @@ impl User {
             status: UserStatus::Inactive,
             is_superuser: false, // 新注册用户默认不是超级用户
             org_id: None,        // 新注册用户默认不属于任何组织
+            display_name: None,
+            avatar_url: None,
+            bio: None,
+            token_version: 0,
             created_at: now,
             updated_at: now,
         }
@@ -53,8 +89,20 @@ impl User {
         self.updated_at = now;
     }
 
+    /// 等待邮箱验证，验证通过后再调用 `activate`
+    pub fn require_verification(&mut self, now: Timestamp) {
+        self.status = UserStatus::PendingVerification;
+        self.updated_at = now;
+    }
+
+    pub fn is_pending_verification(&self) -> bool {
+        self.status == UserStatus::PendingVerification
+    }
+
+    /// 停用账号，之前签发的令牌随之失效
     pub fn suspend(&mut self, now: Timestamp) {
         self.status = UserStatus::Suspended;
+        self.token_version += 1;
         self.updated_at = now;
     }
 
@@ -73,8 +121,78 @@ impl User {
         self.updated_at = now;
     }
 
+    /// 修改展示名、头像和简介；先校验全部字段，任一不合法则整体不修改
+    ///
+    /// 用户名和邮箱不在这里修改
+    pub fn update_details(
+        &mut self,
+        changes: ProfileChanges,
+        now: Timestamp,
+    ) -> Result<(), DomainError> {
+        let display_name = changes.display_name.map(normalize_optional);
+        if let Some(Some(name)) = &display_name {
+            if name.chars().count() > Self::MAX_DISPLAY_NAME_CHARS {
+                return Err(DomainError::invalid_argument(
+                    "display_name",
+                    "must be at most 64 characters",
+                ));
+            }
+        }
+
+        let avatar_url = changes.avatar_url.map(normalize_optional);
+        if let Some(Some(url)) = &avatar_url {
+            if url.len() > Self::MAX_AVATAR_URL_LEN || !is_http_url(url) {
+                return Err(DomainError::invalid_argument(
+                    "avatar_url",
+                    "must be a valid http(s) URL",
+                ));
+            }
+        }
+
+        let bio = changes.bio.map(normalize_optional);
+        if let Some(Some(bio)) = &bio {
+            if bio.chars().count() > Self::MAX_BIO_CHARS {
+                return Err(DomainError::invalid_argument(
+                    "bio",
+                    "must be at most 500 characters",
+                ));
+            }
+        }
+
+        if let Some(display_name) = display_name {
+            self.display_name = display_name;
+        }
+        if let Some(avatar_url) = avatar_url {
+            self.avatar_url = avatar_url;
+        }
+        if let Some(bio) = bio {
+            self.bio = bio;
+        }
+        self.updated_at = now;
+        Ok(())
+    }
+
+    /// 注册和修改密码共用的强度规则
+    pub fn validate_password(raw: &str) -> Result<(), DomainError> {
+        if raw.chars().count() < Self::MIN_PASSWORD_CHARS {
+            return Err(DomainError::invalid_argument(
+                "password",
+                "must be at least 6 characters",
+            ));
+        }
+        if raw.len() > Self::MAX_PASSWORD_BYTES {
+            return Err(DomainError::invalid_argument(
+                "password",
+                "must be at most 72 bytes",
+            ));
+        }
+        Ok(())
+    }
+
+    /// 替换密码，同时递增令牌版本让所有已签发的令牌失效
     pub fn set_password(&mut self, password: PasswordHash, now: Timestamp) {
         self.password = password;
+        self.token_version += 1;
         self.updated_at = now;
     }
 
@@ -120,3 +238,47 @@ impl User {
         self.org_id.is_some()
     }
 }
+
+fn normalize_optional(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// 只做结构检查：http(s) 协议、主机名非空、不含空白和控制字符
+fn is_http_url(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    let Some(rest) = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    !host.is_empty()
+        && !host.starts_with('@')
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// 用户公开资料：不含邮箱、组织等私有字段，用于展示其他用户
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UserProfile {
+    pub id: UserId,
+    pub username: Username,
+    pub status: UserStatus,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+}
+
+impl From<User> for UserProfile {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            status: user.status,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            bio: user.bio,
+        }
+    }
+}
@@ -228,6 +228,14 @@ impl UserEmail {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// '@' 之后的域名部分
+    pub fn domain(&self) -> &str {
+        self.0
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or("")
+    }
 }
 
 impl fmt::Display for UserEmail {
@@ -276,4 +284,11 @@ impl MessageContent {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// 图片/文件消息的内容是上传文件的ID
+    pub fn file_id(&self) -> Result<Uuid, DomainError> {
+        Uuid::parse_str(self.0.trim()).map_err(|_| {
+            DomainError::invalid_argument("content", "must be the id of an uploaded file")
+        })
+    }
 }
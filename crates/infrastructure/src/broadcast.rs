@@ -1,23 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use application::{
-    broadcaster::BroadcastError, MessageBroadcast, MessageBroadcaster, MessageStream,
+    broadcaster::BroadcastError, MessageBroadcast, MessageBroadcaster, MessageStream, StreamEvent,
 };
 use async_trait::async_trait;
+use config::BroadcastConfig;
 use domain::RoomId;
 use redis::{aio::PubSub, AsyncCommands, Client as RedisClient, Msg};
+use tokio::sync::watch;
 use tokio_stream::{Stream, StreamExt};
 use uuid::Uuid;
 
+/// 发布重试和订阅重连的退避策略：等待时间从初始值开始翻倍，不超过上限
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 发布失败后最多重试的次数
+    pub publish_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &BroadcastConfig) -> Self {
+        Self {
+            publish_retries: config.publish_retries,
+            initial_backoff: Duration::from_millis(config.retry_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_retry_backoff_ms),
+        }
+    }
+
+    /// 第 `attempt` 次重试（从 0 开始）之前的等待时间
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            publish_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
 // Redis 消息流，用于 WebSocket 处理
 pub struct RedisMessageStream {
+    client: RedisClient,
     pubsub: PubSub,
-    _room_id: domain::RoomId,
+    room_id: domain::RoomId,
+    /// 广播器停机时变为 true，流随之结束并断开订阅连接
+    closing: watch::Receiver<bool>,
+    retry: RetryPolicy,
 }
 
 impl RedisMessageStream {
     pub async fn new(
         redis_client: &RedisClient,
         room_id: domain::RoomId,
+        closing: watch::Receiver<bool>,
+        retry: RetryPolicy,
     ) -> Result<Self, BroadcastError> {
+        let pubsub = Self::subscribe(redis_client, room_id).await?;
+        Ok(Self {
+            client: redis_client.clone(),
+            pubsub,
+            room_id,
+            closing,
+            retry,
+        })
+    }
+
+    async fn subscribe(
+        redis_client: &RedisClient,
+        room_id: domain::RoomId,
+    ) -> Result<PubSub, BroadcastError> {
         let mut pubsub = redis_client
             .get_async_pubsub()
             .await
@@ -29,22 +90,52 @@ impl RedisMessageStream {
         })?;
 
         tracing::debug!(channel = %channel, "Subscribed to Redis channel");
+        Ok(pubsub)
+    }
 
-        Ok(Self {
-            pubsub,
-            _room_id: room_id,
-        })
+    /// 订阅连接断开后按退避策略重连并重新订阅同一房间；广播器停机时放弃，返回 None
+    async fn resubscribe(
+        client: &RedisClient,
+        room_id: domain::RoomId,
+        retry: RetryPolicy,
+        closing: &mut watch::Receiver<bool>,
+    ) -> Option<PubSub> {
+        let mut attempt = 0u32;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(retry.backoff(attempt)) => {}
+                _ = closing.wait_for(|closing| *closing) => return None,
+            }
+            match Self::subscribe(client, room_id).await {
+                Ok(pubsub) => {
+                    tracing::info!(room_id = %Uuid::from(room_id), attempt, "Redis PubSub 重新订阅成功");
+                    return Some(pubsub);
+                }
+                Err(err) => {
+                    tracing::warn!(room_id = %Uuid::from(room_id), attempt, error = %err, "Redis PubSub 重连失败");
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+        }
     }
 
-    // 将 Redis PubSub 转换为异步流
-    pub fn into_stream(mut self) -> impl Stream<Item = Result<MessageBroadcast, BroadcastError>> {
+    // 将 Redis PubSub 转换为异步流；订阅连接断开后自动重连，重连成功时产出 Gap 标记
+    pub fn into_stream(self) -> impl Stream<Item = Result<StreamEvent, BroadcastError>> {
         async_stream::stream! {
-            let mut pubsub_stream = self.pubsub.on_message();
+            let Self { client, pubsub, room_id, mut closing, retry } = self;
+            let mut pubsub_stream = pubsub.into_on_message();
             loop {
-                match pubsub_stream.next().await {
+                let next = tokio::select! {
+                    next = pubsub_stream.next() => next,
+                    _ = async { let _ = closing.wait_for(|closing| *closing).await; } => {
+                        tracing::debug!("Broadcaster shutting down, closing Redis PubSub stream");
+                        break;
+                    }
+                };
+                match next {
                     Some(msg) => {
                         match Self::parse_message(msg) {
-                            Ok(broadcast) => yield Ok(broadcast),
+                            Ok(broadcast) => yield Ok(StreamEvent::Message(broadcast)),
                             Err(err) => {
                                 tracing::warn!(error = %err, "Failed to parse Redis message");
                                 continue;
@@ -52,8 +143,15 @@ impl RedisMessageStream {
                         }
                     }
                     None => {
-                        tracing::warn!("Redis PubSub stream ended");
-                        break;
+                        tracing::warn!(room_id = %Uuid::from(room_id), "Redis PubSub stream ended, reconnecting");
+                        let Some(pubsub) =
+                            Self::resubscribe(&client, room_id, retry, &mut closing).await
+                        else {
+                            break;
+                        };
+                        pubsub_stream = pubsub.into_on_message();
+                        // 断线期间的广播已经丢失，由订阅方从存储补齐
+                        yield Ok(StreamEvent::Gap);
                     }
                 }
             }
@@ -76,11 +174,22 @@ impl RedisMessageStream {
 #[derive(Clone)]
 pub struct RedisMessageBroadcaster {
     client: RedisClient,
+    closing: Arc<watch::Sender<bool>>,
+    retry: RetryPolicy,
 }
 
 impl RedisMessageBroadcaster {
     pub fn new(client: RedisClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            closing: Arc::new(watch::Sender::new(false)),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     pub(crate) fn channel_name(room_id: domain::RoomId) -> String {
@@ -92,7 +201,14 @@ impl RedisMessageBroadcaster {
         &self,
         room_id: domain::RoomId,
     ) -> Result<RedisMessageStream, BroadcastError> {
-        RedisMessageStream::new(&self.client, room_id).await
+        RedisMessageStream::new(&self.client, room_id, self.closing.subscribe(), self.retry).await
+    }
+
+    async fn publish(&self, channel: &str, payload: &str) -> Result<(), redis::RedisError> {
+        // 获取普通连接用于发布
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: i32 = conn.publish(channel, payload).await?;
+        Ok(())
     }
 }
 
@@ -105,18 +221,19 @@ impl MessageBroadcaster for RedisMessageBroadcaster {
         let serialized_payload = serde_json::to_string(&payload)
             .map_err(|err| BroadcastError::failed(format!("Serialization failed: {}", err)))?;
 
-        // 获取普通连接用于发布
-        let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
-            .await
-            .map_err(|err| BroadcastError::failed(format!("Redis connection failed: {}", err)))?;
-
-        // 发布消息到 Redis 频道
-        let _: i32 = conn
-            .publish(&channel, serialized_payload)
-            .await
-            .map_err(|err| BroadcastError::failed(format!("Redis publish failed: {}", err)))?;
+        // 发布消息到 Redis 频道；Redis 暂时不可用时按退避策略重试，用尽后发送失败
+        let mut attempt = 0u32;
+        while let Err(err) = self.publish(&channel, &serialized_payload).await {
+            if attempt >= self.retry.publish_retries {
+                return Err(BroadcastError::unavailable(format!(
+                    "Redis publish failed after {} retries: {}",
+                    attempt, err
+                )));
+            }
+            tracing::warn!(channel = %channel, attempt, error = %err, "Redis publish failed, retrying");
+            tokio::time::sleep(self.retry.backoff(attempt)).await;
+            attempt += 1;
+        }
 
         tracing::debug!(channel = %channel, "Message broadcasted to Redis");
         Ok(())
@@ -126,4 +243,8 @@ impl MessageBroadcaster for RedisMessageBroadcaster {
         let stream = self.create_stream(room_id).await?.into_stream();
         Ok(MessageStream::remote(Box::pin(stream)))
     }
+
+    async fn shutdown(&self) {
+        self.closing.send_replace(true);
+    }
 }
@@ -1,11 +1,11 @@
 use std::sync::Arc;
 
-use application::{MessageBroadcaster, PasswordHasher};
+use application::{LocalMessageBroadcaster, MessageBroadcaster, PasswordHasher};
 use config::AppConfig;
 use thiserror::Error;
 
 use crate::{
-    broadcast::RedisMessageBroadcaster,
+    broadcast::{RedisMessageBroadcaster, RetryPolicy},
     migrations::MIGRATOR,
     password::BcryptPasswordHasher,
     repository::{create_pg_pool, PgStorage},
@@ -38,15 +38,18 @@ impl Infrastructure {
         let storage = Arc::new(PgStorage::new(pool));
         let password_hasher = Arc::new(BcryptPasswordHasher::new(config.server.bcrypt_cost));
 
-        // 创建 Redis 广播器
-        let redis_url = config
-            .broadcast
-            .redis_url
-            .as_ref()
-            .ok_or_else(|| InfrastructureError::Config("Redis URL is required".to_string()))?;
-        let client = redis::Client::open(redis_url.clone()).map_err(InfrastructureError::Redis)?;
-        let broadcaster: Arc<dyn MessageBroadcaster> =
-            Arc::new(RedisMessageBroadcaster::new(client));
+        // 配置了 Redis 时创建 Redis 广播器，否则在进程内广播
+        let broadcaster: Arc<dyn MessageBroadcaster> = match &config.broadcast.redis_url {
+            Some(redis_url) => {
+                let client =
+                    redis::Client::open(redis_url.clone()).map_err(InfrastructureError::Redis)?;
+                Arc::new(
+                    RedisMessageBroadcaster::new(client)
+                        .with_retry_policy(RetryPolicy::from_config(&config.broadcast)),
+                )
+            }
+            None => Arc::new(LocalMessageBroadcaster::from_config(&config.broadcast)),
+        };
 
         Ok(Self {
             storage,
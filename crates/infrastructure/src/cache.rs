@@ -0,0 +1,528 @@
+//! 房间和用户按 ID 查询的缓存
+//!
+//! [`CachedChatRoomRepository`] 和 [`CachedUserRepository`] 包在任意存储实现外面，
+//! 只缓存 `find_by_id` 的结果。修改经过包装层时先写库，再把新值写进缓存（删除时清掉），
+//! 同一进程内修改之后不会再读到旧值；只用进程内缓存时，其他实例的修改最多延迟一个有效期可见。
+//!
+//! 缓存只是加速手段：缓存读写失败按未命中处理，只打日志，不影响查询结果。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use application::repository::{
+    ChatRoomRepository, PaginatedResult, PublicRoomQuery, UserRepository,
+};
+use async_trait::async_trait;
+use config::CacheConfig;
+use domain::{
+    AuditLogEntry, ChatRoom, ChatRoomVisibility, MemberRoom, OrgId, PasswordHash, ReactionPolicy,
+    RepositoryError, RoomId, RoomMember, RoomSummary, Timestamp, User, UserEmail, UserId,
+    UserStatus, Username,
+};
+use redis::{AsyncCommands, Client as RedisClient};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 缓存存储：按键保存序列化后的实体
+#[async_trait]
+pub trait EntityCache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, RepositoryError>;
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), RepositoryError>;
+
+    async fn remove(&self, key: &str) -> Result<(), RepositoryError>;
+}
+
+/// 按配置选择缓存存储：有 Redis 时多个实例共享，否则放在本进程内存
+pub fn entity_cache(config: &CacheConfig, redis: Option<Arc<RedisClient>>) -> Arc<dyn EntityCache> {
+    match redis {
+        Some(client) => Arc::new(RedisEntityCache::new(client)),
+        None => Arc::new(MemoryEntityCache::new(config.max_entries)),
+    }
+}
+
+/// 放在 Redis 里的缓存，过期交给 Redis 处理
+pub struct RedisEntityCache {
+    client: Arc<RedisClient>,
+}
+
+impl RedisEntityCache {
+    pub fn new(client: Arc<RedisClient>) -> Self {
+        Self { client }
+    }
+
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, RepositoryError> {
+        self.client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(redis_err)
+    }
+}
+
+fn redis_err(error: redis::RedisError) -> RepositoryError {
+    RepositoryError::storage_with_source(error.to_string(), error)
+}
+
+#[async_trait]
+impl EntityCache for RedisEntityCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, RepositoryError> {
+        self.connection().await?.get(key).await.map_err(redis_err)
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), RepositoryError> {
+        self.connection()
+            .await?
+            .set_ex(key, value, ttl.as_secs().max(1))
+            .await
+            .map_err(redis_err)
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), RepositoryError> {
+        self.connection().await?.del(key).await.map_err(redis_err)
+    }
+}
+
+/// 进程内缓存，条目数有上限，满了之后先清掉过期条目，仍然满就不再缓存新条目
+pub struct MemoryEntityCache {
+    entries: Mutex<HashMap<String, (Instant, String)>>,
+    max_entries: usize,
+}
+
+impl MemoryEntityCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries,
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, String)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl EntityCache for MemoryEntityCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, RepositoryError> {
+        let mut entries = self.entries();
+        match entries.get(key) {
+            Some((expires_at, value)) if *expires_at > Instant::now() => Ok(Some(value.clone())),
+            Some(_) => {
+                entries.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), RepositoryError> {
+        let mut entries = self.entries();
+        let now = Instant::now();
+        if entries.len() >= self.max_entries && !entries.contains_key(key) {
+            entries.retain(|_, (expires_at, _)| *expires_at > now);
+            if entries.len() >= self.max_entries {
+                return Ok(());
+            }
+        }
+        entries.insert(key.to_string(), (now + ttl, value));
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), RepositoryError> {
+        self.entries().remove(key);
+        Ok(())
+    }
+}
+
+/// 一类实体的缓存读写
+///
+/// 查库和写缓存之间如果有修改经过包装层，查到的可能已经是旧值，这时不写缓存：
+/// 读之前记下修改计数，写缓存前后各检查一次，变了就放弃（写完才发现就删掉）
+struct CacheSlot {
+    cache: Arc<dyn EntityCache>,
+    ttl: Duration,
+    prefix: &'static str,
+    writes: AtomicU64,
+}
+
+impl CacheSlot {
+    fn new(cache: Arc<dyn EntityCache>, ttl: Duration, prefix: &'static str) -> Self {
+        Self {
+            cache,
+            ttl,
+            prefix,
+            writes: AtomicU64::new(0),
+        }
+    }
+
+    fn key(&self, id: Uuid) -> String {
+        format!("cache:{}:{}", self.prefix, id)
+    }
+
+    async fn get<D: DeserializeOwned>(&self, id: Uuid) -> Option<D> {
+        let key = self.key(id);
+        match self.cache.get(&key).await {
+            Ok(Some(value)) => match serde_json::from_str(&value) {
+                Ok(entity) => Some(entity),
+                Err(error) => {
+                    tracing::warn!(%error, key, "缓存内容无法解析，按未命中处理");
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(error) => {
+                tracing::warn!(%error, key, "读取缓存失败，按未命中处理");
+                None
+            }
+        }
+    }
+
+    /// 查库前调用，返回当前的修改计数
+    fn snapshot(&self) -> u64 {
+        self.writes.load(Ordering::SeqCst)
+    }
+
+    /// 把查库结果写进缓存；查库期间有修改时不写
+    async fn fill<D: Serialize>(&self, id: Uuid, entity: &D, snapshot: u64) {
+        if self.snapshot() != snapshot {
+            return;
+        }
+        self.store(id, entity).await;
+        if self.snapshot() != snapshot {
+            self.invalidate(id).await;
+        }
+    }
+
+    /// 修改写库成功后调用，新值直接写进缓存
+    async fn write_through<D: Serialize>(&self, id: Uuid, entity: &D) {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.store(id, entity).await;
+    }
+
+    /// 删除写库成功后调用
+    async fn evict(&self, id: Uuid) {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.invalidate(id).await;
+    }
+
+    async fn store<D: Serialize>(&self, id: Uuid, entity: &D) {
+        let key = self.key(id);
+        let value = match serde_json::to_string(entity) {
+            Ok(value) => value,
+            Err(error) => {
+                tracing::warn!(%error, key, "实体无法序列化，不写缓存");
+                return self.invalidate(id).await;
+            }
+        };
+        if let Err(error) = self.cache.set(&key, value, self.ttl).await {
+            tracing::warn!(%error, key, "写缓存失败，改为清除旧条目");
+            self.invalidate(id).await;
+        }
+    }
+
+    async fn invalidate(&self, id: Uuid) {
+        let key = self.key(id);
+        if let Err(error) = self.cache.remove(&key).await {
+            tracing::warn!(%error, key, "清除缓存失败，旧条目到期后失效");
+        }
+    }
+}
+
+/// 缓存里的房间：完整保存房间密码哈希（对外序列化时会跳过）
+#[derive(Serialize, Deserialize)]
+struct CachedRoom {
+    id: RoomId,
+    name: String,
+    owner_id: UserId,
+    visibility: ChatRoomVisibility,
+    password: Option<PasswordHash>,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+    is_closed: bool,
+    reaction_policy: ReactionPolicy,
+    encrypted: bool,
+}
+
+impl From<&ChatRoom> for CachedRoom {
+    fn from(room: &ChatRoom) -> Self {
+        Self {
+            id: room.id,
+            name: room.name.clone(),
+            owner_id: room.owner_id,
+            visibility: room.visibility.clone(),
+            password: room.password.clone(),
+            created_at: room.created_at,
+            updated_at: room.updated_at,
+            is_closed: room.is_closed,
+            reaction_policy: room.reaction_policy,
+            encrypted: room.encrypted,
+        }
+    }
+}
+
+impl From<CachedRoom> for ChatRoom {
+    fn from(room: CachedRoom) -> Self {
+        Self {
+            id: room.id,
+            name: room.name,
+            owner_id: room.owner_id,
+            visibility: room.visibility,
+            password: room.password,
+            created_at: room.created_at,
+            updated_at: room.updated_at,
+            is_closed: room.is_closed,
+            reaction_policy: room.reaction_policy,
+            encrypted: room.encrypted,
+        }
+    }
+}
+
+/// 缓存里的用户：完整保存密码哈希和令牌版本（对外序列化时会跳过）
+#[derive(Serialize, Deserialize)]
+struct CachedUser {
+    id: UserId,
+    username: Username,
+    email: UserEmail,
+    password: PasswordHash,
+    status: UserStatus,
+    is_superuser: bool,
+    org_id: Option<OrgId>,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    token_version: i32,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+}
+
+impl From<&User> for CachedUser {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            password: user.password.clone(),
+            status: user.status.clone(),
+            is_superuser: user.is_superuser,
+            org_id: user.org_id,
+            display_name: user.display_name.clone(),
+            avatar_url: user.avatar_url.clone(),
+            bio: user.bio.clone(),
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+impl From<CachedUser> for User {
+    fn from(user: CachedUser) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            password: user.password,
+            status: user.status,
+            is_superuser: user.is_superuser,
+            org_id: user.org_id,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            bio: user.bio,
+            token_version: user.token_version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// 缓存 `find_by_id` 的房间存储
+pub struct CachedChatRoomRepository {
+    inner: Arc<dyn ChatRoomRepository>,
+    slot: CacheSlot,
+}
+
+impl CachedChatRoomRepository {
+    pub fn new(
+        inner: Arc<dyn ChatRoomRepository>,
+        cache: Arc<dyn EntityCache>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            slot: CacheSlot::new(cache, ttl, "room"),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatRoomRepository for CachedChatRoomRepository {
+    async fn create(&self, room: ChatRoom) -> Result<ChatRoom, RepositoryError> {
+        self.inner.create(room).await
+    }
+
+    async fn update(&self, room: ChatRoom) -> Result<ChatRoom, RepositoryError> {
+        let updated = self.inner.update(room).await?;
+        self.slot
+            .write_through(updated.id.into(), &CachedRoom::from(&updated))
+            .await;
+        Ok(updated)
+    }
+
+    async fn find_by_id(&self, id: RoomId) -> Result<Option<ChatRoom>, RepositoryError> {
+        if let Some(room) = self.slot.get::<CachedRoom>(id.into()).await {
+            return Ok(Some(room.into()));
+        }
+        let snapshot = self.slot.snapshot();
+        let room = self.inner.find_by_id(id).await?;
+        if let Some(room) = &room {
+            self.slot
+                .fill(id.into(), &CachedRoom::from(room), snapshot)
+                .await;
+        }
+        Ok(room)
+    }
+
+    async fn delete(&self, id: RoomId) -> Result<(), RepositoryError> {
+        self.inner.delete(id).await?;
+        self.slot.evict(id.into()).await;
+        Ok(())
+    }
+
+    async fn delete_audited(
+        &self,
+        id: RoomId,
+        audit: AuditLogEntry,
+    ) -> Result<(), RepositoryError> {
+        self.inner.delete_audited(id, audit).await?;
+        self.slot.evict(id.into()).await;
+        Ok(())
+    }
+
+    async fn transfer_ownership(
+        &self,
+        room_id: RoomId,
+        from: UserId,
+        to: UserId,
+        now: Timestamp,
+        audit: AuditLogEntry,
+    ) -> Result<ChatRoom, RepositoryError> {
+        let room = self
+            .inner
+            .transfer_ownership(room_id, from, to, now, audit)
+            .await?;
+        self.slot
+            .write_through(room_id.into(), &CachedRoom::from(&room))
+            .await;
+        Ok(room)
+    }
+
+    async fn find_by_owner(&self, owner_id: UserId) -> Result<Vec<ChatRoom>, RepositoryError> {
+        self.inner.find_by_owner(owner_id).await
+    }
+
+    async fn find_member_rooms(&self, user_id: UserId) -> Result<Vec<MemberRoom>, RepositoryError> {
+        self.inner.find_member_rooms(user_id).await
+    }
+
+    async fn find_public(
+        &self,
+        query: &PublicRoomQuery,
+    ) -> Result<PaginatedResult<RoomSummary>, RepositoryError> {
+        self.inner.find_public(query).await
+    }
+
+    async fn create_with_owner(
+        &self,
+        room: ChatRoom,
+        owner: RoomMember,
+    ) -> Result<ChatRoom, RepositoryError> {
+        self.inner.create_with_owner(room, owner).await
+    }
+
+    async fn find_data_key(&self, room_id: RoomId) -> Result<Option<Vec<u8>>, RepositoryError> {
+        self.inner.find_data_key(room_id).await
+    }
+
+    async fn set_data_key(
+        &self,
+        room_id: RoomId,
+        wrapped_key: &[u8],
+    ) -> Result<bool, RepositoryError> {
+        let stored = self.inner.set_data_key(room_id, wrapped_key).await?;
+        // 房间的 encrypted 由数据密钥是否存在得出
+        if stored {
+            self.slot.evict(room_id.into()).await;
+        }
+        Ok(stored)
+    }
+}
+
+/// 缓存 `find_by_id` 的用户存储
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    slot: CacheSlot,
+}
+
+impl CachedUserRepository {
+    pub fn new(inner: Arc<dyn UserRepository>, cache: Arc<dyn EntityCache>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            slot: CacheSlot::new(cache, ttl, "user"),
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn create(&self, user: User) -> Result<User, RepositoryError> {
+        self.inner.create(user).await
+    }
+
+    async fn update(&self, user: User) -> Result<User, RepositoryError> {
+        let updated = self.inner.update(user).await?;
+        self.slot
+            .write_through(updated.id.into(), &CachedUser::from(&updated))
+            .await;
+        Ok(updated)
+    }
+
+    async fn update_audited(
+        &self,
+        user: User,
+        audit: AuditLogEntry,
+    ) -> Result<User, RepositoryError> {
+        let updated = self.inner.update_audited(user, audit).await?;
+        self.slot
+            .write_through(updated.id.into(), &CachedUser::from(&updated))
+            .await;
+        Ok(updated)
+    }
+
+    async fn find_by_id(&self, id: UserId) -> Result<Option<User>, RepositoryError> {
+        if let Some(user) = self.slot.get::<CachedUser>(id.into()).await {
+            return Ok(Some(user.into()));
+        }
+        let snapshot = self.slot.snapshot();
+        let user = self.inner.find_by_id(id).await?;
+        if let Some(user) = &user {
+            self.slot
+                .fill(id.into(), &CachedUser::from(user), snapshot)
+                .await;
+        }
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: UserEmail) -> Result<Option<User>, RepositoryError> {
+        self.inner.find_by_email(email).await
+    }
+
+    async fn delete(&self, id: UserId) -> Result<(), RepositoryError> {
+        self.inner.delete(id).await?;
+        self.slot.evict(id.into()).await;
+        Ok(())
+    }
+}
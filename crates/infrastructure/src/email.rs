@@ -0,0 +1,40 @@
+use application::{EmailSender, EmailSenderError};
+use async_trait::async_trait;
+use domain::{UserEmail, Username};
+
+/// 只打印日志的邮件发送器，用于开发环境和没有配置邮件服务的部署
+#[derive(Clone, Default)]
+pub struct LoggingEmailSender;
+
+#[async_trait]
+impl EmailSender for LoggingEmailSender {
+    async fn send_verification(
+        &self,
+        to: &UserEmail,
+        username: &Username,
+        verification_url: &str,
+    ) -> Result<(), EmailSenderError> {
+        tracing::info!(
+            to = %to.as_str(),
+            username = %username.as_str(),
+            verification_url,
+            "邮箱验证邮件（未配置邮件服务，仅记录日志）"
+        );
+        Ok(())
+    }
+
+    async fn send_password_reset(
+        &self,
+        to: &UserEmail,
+        username: &Username,
+        reset_url: &str,
+    ) -> Result<(), EmailSenderError> {
+        tracing::info!(
+            to = %to.as_str(),
+            username = %username.as_str(),
+            reset_url,
+            "密码重置邮件（未配置邮件服务，仅记录日志）"
+        );
+        Ok(())
+    }
+}
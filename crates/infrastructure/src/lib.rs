@@ -4,21 +4,39 @@
 
 pub mod broadcast;
 pub mod builder;
+pub mod cache;
 pub mod delivery;
+pub mod email;
+pub mod message_batch;
 pub mod migrations;
+pub mod outbox;
 pub mod password;
 pub mod repository;
 pub mod stats_aggregation;
+pub mod storage;
 
-pub use broadcast::{RedisMessageBroadcaster, RedisMessageStream};
+pub use broadcast::{RedisMessageBroadcaster, RedisMessageStream, RetryPolicy};
 pub use builder::{Infrastructure, InfrastructureError};
+pub use cache::{
+    entity_cache, CachedChatRoomRepository, CachedUserRepository, EntityCache, MemoryEntityCache,
+    RedisEntityCache,
+};
 pub use delivery::PgDeliveryTracker;
+pub use email::LoggingEmailSender;
+pub use message_batch::BatchingMessageRepository;
 pub use migrations::MIGRATOR;
+pub use outbox::OutboxDispatcher;
 pub use password::BcryptPasswordHasher;
 pub use repository::{
-    create_pg_pool, PgChatRoomRepository, PgMessageRepository, PgOrganizationRepository,
-    PgRoomMemberRepository, PgStorage, PgUserRepository,
+    create_pg_pool, PgAuditLogRepository, PgChatRoomRepository, PgEmailVerificationRepository,
+    PgFileUploadRepository, PgIdempotencyKeyRepository, PgMentionRepository,
+    PgMessageDeliveryRepository, PgMessageRepository, PgNotificationRepository,
+    PgOrganizationRepository, PgOutboxRepository, PgPasswordResetRepository, PgReactionRepository,
+    PgRefreshTokenRepository, PgRoomInvitationRepository, PgRoomInviteRepository,
+    PgRoomMemberRepository, PgStorage, PgUserBlockRepository, PgUserRepository,
 };
 pub use stats_aggregation::{
-    OnlineStatsSummary, RoomStats, StatsAggregationService, StatsQuery, TimeGranularity,
+    IncrementalAggregation, OnlineStatsSummary, RoomStats, StatsAggregationService, StatsQuery,
+    TimeGranularity, UserOnlineTime,
 };
+pub use storage::LocalObjectStorage;
@@ -0,0 +1,291 @@
+//! 新消息攒批写库
+//!
+//! [`BatchingMessageRepository`] 包在消息存储外面：`create` 把消息交给后台写入任务，
+//! 任务等待最多一个攒批间隔（或攒够上限条数）后用 `create_batch` 一次写入，再把结果逐条返回。
+//! 写入任务只有一个，批次按到达顺序依次写入，同一房间内的序列号顺序与调用 `create` 的顺序一致。
+//! `create` 在消息真正写库之后才返回，调用方随后的查询能读到这条消息。
+//!
+//! 其余读写（包括带发件箱的写入）直接转给内层存储。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::repository::{
+    ExportedMessageStream, MessageRepository, PaginationParams, TimeRangeParams,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use config::MessageConfig;
+use domain::{
+    Message, MessageId, MessageRevision, PinnedMessage, RepositoryError, RoomId, ThreadMessage,
+    Timestamp, UserId,
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// 等待写入的消息和回传结果的通道
+struct PendingMessage {
+    message: Message,
+    reply: oneshot::Sender<Result<MessageId, RepositoryError>>,
+}
+
+pub struct BatchingMessageRepository {
+    inner: Arc<dyn MessageRepository>,
+    pending: mpsc::UnboundedSender<PendingMessage>,
+}
+
+impl BatchingMessageRepository {
+    /// 启动后台写入任务；所有包装层被释放后任务写完剩余的消息再退出
+    pub fn new(
+        inner: Arc<dyn MessageRepository>,
+        flush_interval: Duration,
+        max_batch: usize,
+    ) -> Self {
+        let (pending, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(Self::run(
+            inner.clone(),
+            receiver,
+            flush_interval,
+            max_batch.max(1),
+        ));
+        Self { inner, pending }
+    }
+
+    /// 按配置包装；未开启攒批时原样返回
+    pub fn from_config(
+        inner: Arc<dyn MessageRepository>,
+        config: &MessageConfig,
+    ) -> Arc<dyn MessageRepository> {
+        if config.batch_flush_ms == 0 {
+            return inner;
+        }
+        Arc::new(Self::new(
+            inner,
+            Duration::from_millis(config.batch_flush_ms),
+            config.batch_max,
+        ))
+    }
+
+    async fn run(
+        inner: Arc<dyn MessageRepository>,
+        mut receiver: mpsc::UnboundedReceiver<PendingMessage>,
+        flush_interval: Duration,
+        max_batch: usize,
+    ) {
+        while let Some(first) = receiver.recv().await {
+            // 从第一条消息到达开始计时，到时间或攒够上限就写入
+            let deadline = Instant::now() + flush_interval;
+            let mut batch = vec![first];
+            while batch.len() < max_batch {
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(pending)) => batch.push(pending),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            Self::flush(inner.as_ref(), batch).await;
+        }
+    }
+
+    async fn flush(inner: &dyn MessageRepository, batch: Vec<PendingMessage>) {
+        let messages = batch
+            .iter()
+            .map(|pending| pending.message.clone())
+            .collect();
+        match inner.create_batch(messages).await {
+            Ok(ids) => {
+                for (pending, id) in batch.into_iter().zip(ids) {
+                    // 调用方已经放弃等待时结果无人接收，消息照样已经写入
+                    let _ = pending.reply.send(Ok(id));
+                }
+            }
+            Err(err) => {
+                // 整批写入失败时逐条重写，一条消息的问题（比如回复的消息刚被清理）不连累同批的其他消息
+                tracing::warn!(error = %err, size = batch.len(), "消息批量写入失败，改为逐条写入");
+                for pending in batch {
+                    let _ = pending.reply.send(inner.create(pending.message).await);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MessageRepository for BatchingMessageRepository {
+    async fn create(&self, message: Message) -> Result<MessageId, RepositoryError> {
+        let (reply, result) = oneshot::channel();
+        self.pending
+            .send(PendingMessage { message, reply })
+            .map_err(|_| RepositoryError::storage("message batch writer stopped"))?;
+        result
+            .await
+            .map_err(|_| RepositoryError::storage("message batch writer stopped"))?
+    }
+
+    async fn create_batch(
+        &self,
+        messages: Vec<Message>,
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        self.inner.create_batch(messages).await
+    }
+
+    /// 发件箱写入需要自己的事务，不进攒批队列
+    async fn create_with_outbox(&self, message: Message) -> Result<MessageId, RepositoryError> {
+        self.inner.create_with_outbox(message).await
+    }
+
+    async fn find_by_id(&self, id: MessageId) -> Result<Option<Message>, RepositoryError> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn find_by_ids(&self, ids: &[MessageId]) -> Result<Vec<Message>, RepositoryError> {
+        self.inner.find_by_ids(ids).await
+    }
+
+    async fn find_recent_by_room(
+        &self,
+        room_id: RoomId,
+        pagination: PaginationParams,
+        before: Option<MessageId>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        self.inner
+            .find_recent_by_room(room_id, pagination, before)
+            .await
+    }
+
+    async fn find_since_timestamp(
+        &self,
+        room_id: RoomId,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        self.inner.find_since_timestamp(room_id, timestamp).await
+    }
+
+    async fn find_after_sequence(
+        &self,
+        room_id: RoomId,
+        after_seq: i64,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        self.inner
+            .find_after_sequence(room_id, after_seq, pagination)
+            .await
+    }
+
+    async fn count_after(
+        &self,
+        room_id: RoomId,
+        message_id: Option<MessageId>,
+    ) -> Result<i64, RepositoryError> {
+        self.inner.count_after(room_id, message_id).await
+    }
+
+    async fn count_unread_for_member(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<i64, RepositoryError> {
+        self.inner.count_unread_for_member(room_id, user_id).await
+    }
+
+    async fn search_in_room(
+        &self,
+        room_id: RoomId,
+        query: &str,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        self.inner.search_in_room(room_id, query, pagination).await
+    }
+
+    async fn find_thread(
+        &self,
+        root_id: MessageId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<ThreadMessage>, RepositoryError> {
+        self.inner.find_thread(root_id, pagination).await
+    }
+
+    async fn thread_reply_count(&self, root_id: MessageId) -> Result<i64, RepositoryError> {
+        self.inner.thread_reply_count(root_id).await
+    }
+
+    async fn find_by_time_range(
+        &self,
+        room_id: RoomId,
+        time_range: TimeRangeParams,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        self.inner
+            .find_by_time_range(room_id, time_range, pagination)
+            .await
+    }
+
+    async fn stream_by_time_range(
+        &self,
+        room_id: RoomId,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    ) -> Result<ExportedMessageStream, RepositoryError> {
+        self.inner.stream_by_time_range(room_id, from, to).await
+    }
+
+    async fn update(&self, message: Message, expected_version: i32) -> Result<(), RepositoryError> {
+        self.inner.update(message, expected_version).await
+    }
+
+    async fn revisions(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageRevision>, RepositoryError> {
+        self.inner.revisions(message_id).await
+    }
+
+    async fn soft_delete(&self, id: MessageId) -> Result<(), RepositoryError> {
+        self.inner.soft_delete(id).await
+    }
+
+    async fn soft_delete_many(
+        &self,
+        room_id: RoomId,
+        ids: &[MessageId],
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        self.inner.soft_delete_many(room_id, ids).await
+    }
+
+    async fn soft_delete_batch_by_sender(
+        &self,
+        sender_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError> {
+        self.inner
+            .soft_delete_batch_by_sender(sender_id, limit)
+            .await
+    }
+
+    async fn restore(&self, id: MessageId) -> Result<bool, RepositoryError> {
+        self.inner.restore(id).await
+    }
+
+    async fn purge_older_than(&self, cutoff: Timestamp) -> Result<u64, RepositoryError> {
+        self.inner.purge_older_than(cutoff).await
+    }
+
+    async fn pin(
+        &self,
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: Timestamp,
+    ) -> Result<bool, RepositoryError> {
+        self.inner
+            .pin(room_id, message_id, pinned_by, pinned_at)
+            .await
+    }
+
+    async fn unpin(&self, room_id: RoomId, message_id: MessageId) -> Result<bool, RepositoryError> {
+        self.inner.unpin(room_id, message_id).await
+    }
+
+    async fn list_pinned(&self, room_id: RoomId) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        self.inner.list_pinned(room_id).await
+    }
+}
@@ -0,0 +1,204 @@
+//! 消息广播发件箱分发任务
+//!
+//! 开启发件箱后，新消息和发件箱记录在同一个事务里写入（见 `MessageRepository::create_with_outbox`），
+//! [`OutboxDispatcher`] 在后台领取到期的记录，读出消息发布到广播器，成功后标记为已发布。
+//! 发布失败按退避重试，尝试次数用尽后进入死信，由管理员查看后重新入队。
+//!
+//! 新记录提交时触发 `message_outbox` 通知，分发任务通过 LISTEN 立即醒来；
+//! 监听连接断开时退回到按 `poll_interval_ms` 轮询。
+//!
+//! 投递语义是至少一次：发布成功后、标记之前进程退出的记录会在领取租约到期后再发一次，
+//! 客户端按消息 ID / 序列号去重。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::repository::{MessageRepository, OutboxRepository};
+use application::{MessageBroadcast, MessageBroadcaster};
+use config::OutboxConfig;
+use domain::{OutboxEntry, RepositoryError};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+/// 发件箱新记录的通知频道，与迁移里的触发器一致
+const OUTBOX_CHANNEL: &str = "message_outbox";
+
+/// 清理已发布记录的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+pub struct OutboxDispatcher {
+    outbox: Arc<dyn OutboxRepository>,
+    messages: Arc<dyn MessageRepository>,
+    broadcaster: Arc<dyn MessageBroadcaster>,
+    config: OutboxConfig,
+}
+
+impl OutboxDispatcher {
+    /// `messages` 应与发送消息时用的存储一致，加密房间的消息读出后才是明文
+    pub fn new(
+        outbox: Arc<dyn OutboxRepository>,
+        messages: Arc<dyn MessageRepository>,
+        broadcaster: Arc<dyn MessageBroadcaster>,
+        config: OutboxConfig,
+    ) -> Self {
+        Self {
+            outbox,
+            messages,
+            broadcaster,
+            config,
+        }
+    }
+
+    /// 启动后台分发任务，通过 `pool` 监听新记录通知
+    pub fn spawn(self, pool: PgPool) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+            let mut listener = None;
+            let mut last_cleanup = tokio::time::Instant::now();
+            loop {
+                if listener.is_none() {
+                    listener = Self::listen(&pool).await;
+                }
+
+                self.dispatch_all_due().await;
+                if last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+                    self.cleanup().await;
+                    last_cleanup = tokio::time::Instant::now();
+                }
+
+                // 有通知立即处理，否则等到下一次轮询；失败的记录也靠轮询重试
+                match listener.as_mut() {
+                    Some(active) => {
+                        tokio::select! {
+                            notification = active.recv() => {
+                                if let Err(err) = notification {
+                                    tracing::warn!(error = %err, "发件箱通知连接断开，改为轮询");
+                                    listener = None;
+                                }
+                            }
+                            _ = tokio::time::sleep(poll_interval) => {}
+                        }
+                    }
+                    None => tokio::time::sleep(poll_interval).await,
+                }
+            }
+        })
+    }
+
+    async fn listen(pool: &PgPool) -> Option<PgListener> {
+        let mut listener = match PgListener::connect_with(pool).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::warn!(error = %err, "连接发件箱通知失败，改为轮询");
+                return None;
+            }
+        };
+        match listener.listen(OUTBOX_CHANNEL).await {
+            Ok(()) => Some(listener),
+            Err(err) => {
+                tracing::warn!(error = %err, "监听发件箱通知失败，改为轮询");
+                None
+            }
+        }
+    }
+
+    /// 一直领取直到没有到期的记录
+    async fn dispatch_all_due(&self) {
+        loop {
+            match self.dispatch_due().await {
+                Ok(claimed) if claimed >= self.config.batch_size => continue,
+                Ok(_) => break,
+                Err(err) => {
+                    tracing::warn!(error = %err, "领取发件箱记录失败");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// 领取一批到期的记录并逐条发布，返回领取的条数
+    pub async fn dispatch_due(&self) -> Result<usize, RepositoryError> {
+        let now = OffsetDateTime::now_utc();
+        let lease_until = now + Duration::from_secs(self.config.claim_timeout_secs);
+        let entries = self
+            .outbox
+            .claim_due(now, lease_until, self.config.batch_size as i64)
+            .await?;
+        let claimed = entries.len();
+
+        // 按写入顺序逐条发布，同一房间内的广播顺序与发送顺序一致
+        for entry in entries {
+            self.dispatch(entry).await;
+        }
+        Ok(claimed)
+    }
+
+    async fn dispatch(&self, entry: OutboxEntry) {
+        let result = match self.messages.find_by_id(entry.message_id).await {
+            // 广播之前已被删除的消息不再推送，否则客户端会在删除之后又看到它
+            Ok(Some(message)) if message.is_deleted => Ok(()),
+            Ok(Some(message)) => self
+                .broadcaster
+                .broadcast(MessageBroadcast::chat(entry.room_id, message))
+                .await
+                .map_err(|err| err.to_string()),
+            Ok(None) => Err("message not found".to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        let recorded = match result {
+            Ok(()) => {
+                self.outbox
+                    .mark_dispatched(entry.id, OffsetDateTime::now_utc())
+                    .await
+            }
+            Err(error) => {
+                let retry_at = (entry.attempts < self.config.max_attempts as i32)
+                    .then(|| OffsetDateTime::now_utc() + self.retry_delay(entry.attempts));
+                if retry_at.is_none() {
+                    tracing::error!(
+                        outbox_id = entry.id,
+                        message_id = %entry.message_id,
+                        attempts = entry.attempts,
+                        error = %error,
+                        "消息广播重试用尽，进入死信"
+                    );
+                } else {
+                    tracing::warn!(
+                        outbox_id = entry.id,
+                        message_id = %entry.message_id,
+                        attempts = entry.attempts,
+                        error = %error,
+                        "消息广播失败，稍后重试"
+                    );
+                }
+                self.outbox.mark_failed(entry.id, &error, retry_at).await
+            }
+        };
+        // 结果没记下来时租约到期后重新领取，最多重复广播一次
+        if let Err(err) = recorded {
+            tracing::warn!(outbox_id = entry.id, error = %err, "记录发件箱分发结果失败");
+        }
+    }
+
+    /// 第 `attempts` 次失败后的等待时间：逐次翻倍，不超过上限
+    fn retry_delay(&self, attempts: i32) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 31) as u32;
+        let delay = self
+            .config
+            .retry_backoff_ms
+            .saturating_mul(1u64 << exponent)
+            .min(self.config.max_retry_backoff_ms);
+        Duration::from_millis(delay)
+    }
+
+    async fn cleanup(&self) {
+        let before = OffsetDateTime::now_utc() - Duration::from_secs(self.config.retention_secs);
+        match self.outbox.delete_dispatched_before(before).await {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!(removed, "清理已发布的发件箱记录"),
+            Err(err) => tracing::warn!(error = %err, "清理发件箱记录失败"),
+        }
+    }
+}
@@ -1,17 +1,27 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use application::repository::{
-    ChatRoomRepository, MessageDeliveryRepository, MessageRepository, PaginationParams,
-    RoomMemberRepository, TimeRangeParams, UserRepository,
+    AuditLogQuery, AuditLogRepository, ChatRoomRepository, EmailVerificationRepository,
+    ExportedMessageStream, FileUploadRepository, IdempotencyKeyRepository, MentionRepository,
+    MessageDeliveryRepository, MessageRepository, NotificationRepository, OutboxRepository,
+    PaginatedResult, PaginationParams, PasswordResetRepository, PublicRoomQuery,
+    ReactionRepository, RefreshTokenRepository, RoomInvitationRepository, RoomInviteRepository,
+    RoomMemberRepository, RoomSortOrder, TimeRangeParams, UserBlockRepository, UserRepository,
 };
 use async_trait::async_trait;
 use domain::{
-    ChatRoom, ChatRoomVisibility, Message, MessageContent, MessageDelivery, MessageId, MessageType,
-    OrgId, Organization, RepositoryError, RoomId, RoomMember, RoomRole, User, UserEmail, UserId,
-    UserStatus,
+    AuditLogEntry, ChatRoom, ChatRoomVisibility, EmailVerification, ExportedMessage, FileUpload,
+    InvitationStatus, MemberRoom, MentionedMessage, Message, MessageContent, MessageDelivery,
+    MessageId, MessageType, Notification, OrgId, Organization, OutboxEntry, OutboxStatus,
+    PasswordReset, PinnedMessage, ReactionEmoji, ReactionPolicy, ReactionSummary, RefreshToken,
+    RepositoryError, RoomBan, RoomId, RoomInvitation, RoomInvite, RoomMember, RoomMemberListing,
+    RoomMemberRestriction, RoomRole, RoomSummary, ThreadMessage, User, UserEmail, UserId,
+    UserProfile, UserStatus,
 };
 use sqlx::{postgres::PgPoolOptions, types::chrono, FromRow, PgPool};
 use time::OffsetDateTime;
+use tokio_stream::StreamExt;
 use uuid::Uuid;
 
 pub fn map_sqlx_err(err: sqlx::Error) -> RepositoryError {
@@ -41,6 +51,20 @@ pub struct PgStorage {
     pub member_repository: Arc<PgRoomMemberRepository>,
     pub message_repository: Arc<PgMessageRepository>,
     pub organization_repository: Arc<PgOrganizationRepository>,
+    pub refresh_token_repository: Arc<PgRefreshTokenRepository>,
+    pub reaction_repository: Arc<PgReactionRepository>,
+    pub user_block_repository: Arc<PgUserBlockRepository>,
+    pub delivery_repository: Arc<PgMessageDeliveryRepository>,
+    pub mention_repository: Arc<PgMentionRepository>,
+    pub email_verification_repository: Arc<PgEmailVerificationRepository>,
+    pub password_reset_repository: Arc<PgPasswordResetRepository>,
+    pub audit_log_repository: Arc<PgAuditLogRepository>,
+    pub file_upload_repository: Arc<PgFileUploadRepository>,
+    pub notification_repository: Arc<PgNotificationRepository>,
+    pub idempotency_key_repository: Arc<PgIdempotencyKeyRepository>,
+    pub invitation_repository: Arc<PgRoomInvitationRepository>,
+    pub invite_repository: Arc<PgRoomInviteRepository>,
+    pub outbox_repository: Arc<PgOutboxRepository>,
 }
 
 impl PgStorage {
@@ -50,6 +74,21 @@ impl PgStorage {
         let member_repository = Arc::new(PgRoomMemberRepository::new(pool.clone()));
         let message_repository = Arc::new(PgMessageRepository::new(pool.clone()));
         let organization_repository = Arc::new(PgOrganizationRepository::new(pool.clone()));
+        let refresh_token_repository = Arc::new(PgRefreshTokenRepository::new(pool.clone()));
+        let reaction_repository = Arc::new(PgReactionRepository::new(pool.clone()));
+        let user_block_repository = Arc::new(PgUserBlockRepository::new(pool.clone()));
+        let delivery_repository = Arc::new(PgMessageDeliveryRepository::new(pool.clone()));
+        let mention_repository = Arc::new(PgMentionRepository::new(pool.clone()));
+        let email_verification_repository =
+            Arc::new(PgEmailVerificationRepository::new(pool.clone()));
+        let password_reset_repository = Arc::new(PgPasswordResetRepository::new(pool.clone()));
+        let audit_log_repository = Arc::new(PgAuditLogRepository::new(pool.clone()));
+        let file_upload_repository = Arc::new(PgFileUploadRepository::new(pool.clone()));
+        let notification_repository = Arc::new(PgNotificationRepository::new(pool.clone()));
+        let idempotency_key_repository = Arc::new(PgIdempotencyKeyRepository::new(pool.clone()));
+        let invitation_repository = Arc::new(PgRoomInvitationRepository::new(pool.clone()));
+        let invite_repository = Arc::new(PgRoomInviteRepository::new(pool.clone()));
+        let outbox_repository = Arc::new(PgOutboxRepository::new(pool.clone()));
 
         Self {
             user_repository,
@@ -57,6 +96,20 @@ impl PgStorage {
             member_repository,
             message_repository,
             organization_repository,
+            refresh_token_repository,
+            reaction_repository,
+            user_block_repository,
+            delivery_repository,
+            mention_repository,
+            email_verification_repository,
+            password_reset_repository,
+            audit_log_repository,
+            file_upload_repository,
+            notification_repository,
+            idempotency_key_repository,
+            invitation_repository,
+            invite_repository,
+            outbox_repository,
         }
     }
 }
@@ -70,6 +123,10 @@ struct UserRecord {
     status: UserStatus,
     is_superuser: bool,
     org_id: Option<Uuid>,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    token_version: i32,
     created_at: OffsetDateTime,
     updated_at: OffsetDateTime,
 }
@@ -90,6 +147,10 @@ impl TryFrom<UserRecord> for User {
             status: value.status,
             is_superuser: value.is_superuser,
             org_id: value.org_id.map(OrgId::from),
+            display_name: value.display_name,
+            avatar_url: value.avatar_url,
+            bio: value.bio,
+            token_version: value.token_version,
             created_at: value.created_at,
             updated_at: value.updated_at,
         })
@@ -107,14 +168,45 @@ impl PgUserRepository {
     }
 }
 
+async fn update_user(
+    executor: impl sqlx::PgExecutor<'_>,
+    user: &User,
+) -> Result<User, RepositoryError> {
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        UPDATE users
+        SET username = $2, email = $3, password_hash = $4, status = $5, is_superuser = $6,
+            display_name = $7, avatar_url = $8, bio = $9, token_version = $10, updated_at = $11
+        WHERE id = $1
+        RETURNING id, username, email, password_hash, status, is_superuser, org_id, display_name, avatar_url, bio, token_version, created_at, updated_at
+        "#,
+    )
+    .bind(Uuid::from(user.id))
+    .bind(user.username.as_str())
+    .bind(user.email.as_str())
+    .bind(user.password.as_str())
+    .bind(&user.status)
+    .bind(user.is_superuser)
+    .bind(user.display_name.as_deref())
+    .bind(user.avatar_url.as_deref())
+    .bind(user.bio.as_deref())
+    .bind(user.token_version)
+    .bind(user.updated_at)
+    .fetch_one(executor)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    User::try_from(record)
+}
+
 #[async_trait]
 impl UserRepository for PgUserRepository {
     async fn create(&self, user: User) -> Result<User, RepositoryError> {
         let record = sqlx::query_as::<_, UserRecord>(
             r#"
-            INSERT INTO users (id, username, email, password_hash, status, is_superuser, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, username, email, password_hash, status, is_superuser, created_at, updated_at
+            INSERT INTO users (id, username, email, password_hash, status, is_superuser, display_name, avatar_url, bio, token_version, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id, username, email, password_hash, status, is_superuser, org_id, display_name, avatar_url, bio, token_version, created_at, updated_at
             "#,
         )
         .bind(Uuid::from(user.id))
@@ -123,6 +215,10 @@ impl UserRepository for PgUserRepository {
         .bind(user.password.as_str())
         .bind(&user.status)
         .bind(user.is_superuser)
+        .bind(user.display_name.as_deref())
+        .bind(user.avatar_url.as_deref())
+        .bind(user.bio.as_deref())
+        .bind(user.token_version)
         .bind(user.created_at)
         .bind(user.updated_at)
         .fetch_one(&self.pool)
@@ -133,31 +229,24 @@ impl UserRepository for PgUserRepository {
     }
 
     async fn update(&self, user: User) -> Result<User, RepositoryError> {
-        let record = sqlx::query_as::<_, UserRecord>(
-            r#"
-            UPDATE users
-            SET username = $2, email = $3, password_hash = $4, status = $5, is_superuser = $6, updated_at = $7
-            WHERE id = $1
-            RETURNING id, username, email, password_hash, status, is_superuser, created_at, updated_at
-            "#,
-        )
-        .bind(Uuid::from(user.id))
-        .bind(user.username.as_str())
-        .bind(user.email.as_str())
-        .bind(user.password.as_str())
-        .bind(&user.status)
-        .bind(user.is_superuser)
-        .bind(user.updated_at)
-        .fetch_one(&self.pool)
-        .await
-        .map_err(map_sqlx_err)?;
+        update_user(&self.pool, &user).await
+    }
 
-        User::try_from(record)
+    async fn update_audited(
+        &self,
+        user: User,
+        audit: AuditLogEntry,
+    ) -> Result<User, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        let updated = update_user(&mut *tx, &user).await?;
+        insert_audit_entry(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(updated)
     }
 
     async fn find_by_id(&self, id: UserId) -> Result<Option<User>, RepositoryError> {
         let record = sqlx::query_as::<_, UserRecord>(
-            r#"SELECT id, username, email, password_hash, status, is_superuser, created_at, updated_at FROM users WHERE id = $1"#,
+            r#"SELECT id, username, email, password_hash, status, is_superuser, org_id, display_name, avatar_url, bio, token_version, created_at, updated_at FROM users WHERE id = $1"#,
         )
         .bind(Uuid::from(id))
         .fetch_optional(&self.pool)
@@ -169,7 +258,7 @@ impl UserRepository for PgUserRepository {
 
     async fn find_by_email(&self, email: UserEmail) -> Result<Option<User>, RepositoryError> {
         let record = sqlx::query_as::<_, UserRecord>(
-            r#"SELECT id, username, email, password_hash, status, is_superuser, created_at, updated_at FROM users WHERE email = $1"#,
+            r#"SELECT id, username, email, password_hash, status, is_superuser, org_id, display_name, avatar_url, bio, token_version, created_at, updated_at FROM users WHERE email = $1"#,
         )
         .bind(email.as_str())
         .fetch_optional(&self.pool)
@@ -190,6 +279,37 @@ struct RoomRecord {
     created_at: OffsetDateTime,
     updated_at: OffsetDateTime,
     is_closed: bool,
+    emoji_reactions_only: bool,
+    encrypted: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct RoomSummaryRecord {
+    #[sqlx(flatten)]
+    room: RoomRecord,
+    member_count: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct MemberRoomRecord {
+    #[sqlx(flatten)]
+    room: RoomRecord,
+    role: RoomRole,
+    pinned_at: Option<OffsetDateTime>,
+    last_activity_at: OffsetDateTime,
+    unread_count: i64,
+}
+
+/// 转义 LIKE 通配符，用户输入的 `%`、`_` 按字面匹配
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for ch in input.chars() {
+        if matches!(ch, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
 }
 
 impl TryFrom<RoomRecord> for ChatRoom {
@@ -214,6 +334,12 @@ impl TryFrom<RoomRecord> for ChatRoom {
             created_at: value.created_at,
             updated_at: value.updated_at,
             is_closed: value.is_closed,
+            reaction_policy: if value.emoji_reactions_only {
+                ReactionPolicy::EmojiOnly
+            } else {
+                ReactionPolicy::Any
+            },
+            encrypted: value.encrypted,
         })
     }
 }
@@ -235,9 +361,9 @@ impl PgChatRoomRepository {
         room: &ChatRoom,
     ) -> Result<ChatRoom, RepositoryError> {
         let record = sqlx::query_as::<_, RoomRecord>(
-            "INSERT INTO chat_rooms (id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-             RETURNING *"
+            "INSERT INTO chat_rooms (id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted"
         )
         .bind(Uuid::from(room.id))
         .bind(&room.name)
@@ -247,6 +373,7 @@ impl PgChatRoomRepository {
         .bind(room.created_at)
         .bind(room.updated_at)
         .bind(room.is_closed)
+        .bind(room.reaction_policy == ReactionPolicy::EmojiOnly)
         .fetch_one(&mut **tx)
         .await
         .map_err(map_sqlx_err)?;
@@ -282,9 +409,9 @@ impl ChatRoomRepository for PgChatRoomRepository {
     async fn create(&self, room: ChatRoom) -> Result<ChatRoom, RepositoryError> {
         let record = sqlx::query_as::<_, RoomRecord>(
             r#"
-            INSERT INTO chat_rooms (id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed
+            INSERT INTO chat_rooms (id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted
             "#,
         )
         .bind(Uuid::from(room.id))
@@ -295,6 +422,7 @@ impl ChatRoomRepository for PgChatRoomRepository {
         .bind(room.created_at)
         .bind(room.updated_at)
         .bind(room.is_closed)
+        .bind(room.reaction_policy == ReactionPolicy::EmojiOnly)
         .fetch_one(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
@@ -306,9 +434,9 @@ impl ChatRoomRepository for PgChatRoomRepository {
         let record = sqlx::query_as::<_, RoomRecord>(
             r#"
             UPDATE chat_rooms
-            SET name = $2, owner_id = $3, is_private = $4, password_hash = $5, updated_at = $6, is_closed = $7
+            SET name = $2, owner_id = $3, is_private = $4, password_hash = $5, updated_at = $6, is_closed = $7, emoji_reactions_only = $8
             WHERE id = $1
-            RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed
+            RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted
             "#,
         )
         .bind(Uuid::from(room.id))
@@ -318,6 +446,7 @@ impl ChatRoomRepository for PgChatRoomRepository {
         .bind(room.password.as_ref().map(|hash| hash.as_str()))
         .bind(room.updated_at)
         .bind(room.is_closed)
+        .bind(room.reaction_policy == ReactionPolicy::EmojiOnly)
         .fetch_one(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
@@ -327,7 +456,7 @@ impl ChatRoomRepository for PgChatRoomRepository {
 
     async fn find_by_id(&self, id: RoomId) -> Result<Option<ChatRoom>, RepositoryError> {
         let record = sqlx::query_as::<_, RoomRecord>(
-            r#"SELECT id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed FROM chat_rooms WHERE id = $1"#,
+            r#"SELECT id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted FROM chat_rooms WHERE id = $1"#,
         )
         .bind(Uuid::from(id))
         .fetch_optional(&self.pool)
@@ -347,9 +476,82 @@ impl ChatRoomRepository for PgChatRoomRepository {
         Ok(())
     }
 
+    async fn delete_audited(
+        &self,
+        id: RoomId,
+        audit: AuditLogEntry,
+    ) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        sqlx::query("DELETE FROM chat_rooms WHERE id = $1")
+            .bind(Uuid::from(id))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+        insert_audit_entry(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn transfer_ownership(
+        &self,
+        room_id: RoomId,
+        from: UserId,
+        to: UserId,
+        now: OffsetDateTime,
+        audit: AuditLogEntry,
+    ) -> Result<ChatRoom, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 条件更新：并发的另一次转让已经生效时这里匹配不到
+        let record = sqlx::query_as::<_, RoomRecord>(
+            r#"
+            UPDATE chat_rooms
+            SET owner_id = $3, updated_at = $4
+            WHERE id = $1 AND owner_id = $2
+            RETURNING id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(from))
+        .bind(Uuid::from(to))
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?
+        .ok_or(RepositoryError::Conflict)?;
+
+        // 新 Owner 在检查之后离开了房间，整个转让回滚
+        let promoted = sqlx::query(
+            "UPDATE room_members SET role = 'owner'::room_role WHERE room_id = $1 AND user_id = $2",
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(to))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+        if promoted.rows_affected() == 0 {
+            return Err(RepositoryError::Conflict);
+        }
+
+        sqlx::query(
+            "UPDATE room_members SET role = 'admin'::room_role WHERE room_id = $1 AND user_id = $2",
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(from))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        insert_audit_entry(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        ChatRoom::try_from(record)
+    }
+
     async fn find_by_owner(&self, owner_id: UserId) -> Result<Vec<ChatRoom>, RepositoryError> {
         let records = sqlx::query_as::<_, RoomRecord>(
-            r#"SELECT id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed FROM chat_rooms WHERE owner_id = $1"#,
+            r#"SELECT id, name, owner_id, is_private, password_hash, created_at, updated_at, is_closed, emoji_reactions_only, encrypted_data_key IS NOT NULL AS encrypted FROM chat_rooms WHERE owner_id = $1"#,
         )
         .bind(Uuid::from(owner_id))
         .fetch_all(&self.pool)
@@ -359,6 +561,118 @@ impl ChatRoomRepository for PgChatRoomRepository {
         records.into_iter().map(ChatRoom::try_from).collect()
     }
 
+    async fn find_member_rooms(&self, user_id: UserId) -> Result<Vec<MemberRoom>, RepositoryError> {
+        // 未读数与 count_unread_for_member 口径一致：已读位置之后未删除的消息
+        let records = sqlx::query_as::<_, MemberRoomRecord>(
+            r#"
+            SELECT r.id, r.name, r.owner_id, r.is_private, r.password_hash, r.created_at, r.updated_at, r.is_closed, r.emoji_reactions_only, r.encrypted_data_key IS NOT NULL AS encrypted,
+                   rm.role,
+                   p.pinned_at,
+                   COALESCE(activity.last_message_at, rm.joined_at) AS last_activity_at,
+                   activity.unread_count
+            FROM room_members rm
+            JOIN chat_rooms r ON r.id = rm.room_id
+            LEFT JOIN room_pins p ON p.room_id = rm.room_id AND p.user_id = rm.user_id
+            LEFT JOIN messages last_read ON last_read.id = rm.last_read_message_id
+            CROSS JOIN LATERAL (
+                SELECT MAX(m.created_at) AS last_message_at,
+                       COUNT(*) FILTER (WHERE m.seq > COALESCE(last_read.seq, 0)) AS unread_count
+                FROM messages m
+                WHERE m.room_id = rm.room_id AND m.is_deleted = FALSE
+            ) activity
+            WHERE rm.user_id = $1
+            ORDER BY p.pinned_at IS NULL, last_activity_at DESC, r.id
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(MemberRoom {
+                    room: ChatRoom::try_from(record.room)?,
+                    role: record.role,
+                    pinned_at: record.pinned_at,
+                    last_activity_at: record.last_activity_at,
+                    unread_count: record.unread_count,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_public(
+        &self,
+        query: &PublicRoomQuery,
+    ) -> Result<PaginatedResult<RoomSummary>, RepositoryError> {
+        let pattern = query
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(|search| format!("%{}%", escape_like(search)));
+        let order_by = match query.sort {
+            RoomSortOrder::CreatedAt => "r.created_at DESC, r.id",
+            RoomSortOrder::MemberCount => "member_count DESC, r.created_at DESC, r.id",
+        };
+        let pagination = query.pagination();
+
+        let records = sqlx::query_as::<_, RoomSummaryRecord>(&format!(
+            r#"
+            SELECT r.id, r.name, r.owner_id, r.is_private, r.password_hash, r.created_at, r.updated_at, r.is_closed, r.emoji_reactions_only, r.encrypted_data_key IS NOT NULL AS encrypted,
+                   COUNT(m.user_id) AS member_count
+            FROM chat_rooms r
+            LEFT JOIN room_members m ON m.room_id = r.id
+            WHERE r.is_private = FALSE
+              AND ($1 OR r.is_closed = FALSE)
+              AND ($2::TEXT IS NULL OR r.name ILIKE $2)
+            GROUP BY r.id
+            ORDER BY {order_by}
+            LIMIT $3 OFFSET $4
+            "#
+        ))
+        .bind(query.include_closed)
+        .bind(pattern.as_deref())
+        .bind(pagination.limit)
+        .bind(pagination.offset.unwrap_or(0))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let total_count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM chat_rooms r
+            WHERE r.is_private = FALSE
+              AND ($1 OR r.is_closed = FALSE)
+              AND ($2::TEXT IS NULL OR r.name ILIKE $2)
+            "#,
+        )
+        .bind(query.include_closed)
+        .bind(pattern.as_deref())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let items = records
+            .into_iter()
+            .map(|record| {
+                Ok(RoomSummary {
+                    room: ChatRoom::try_from(record.room)?,
+                    member_count: record.member_count,
+                })
+            })
+            .collect::<Result<Vec<_>, RepositoryError>>()?;
+
+        Ok(PaginatedResult {
+            items,
+            total_count,
+            page: query.page.max(1),
+            page_size: query.page_size,
+        })
+    }
+
     /// Linus式原子操作：消除特殊情况，直接解决问题
     /// 不需要什么该死的TransactionManager抽象
     async fn create_with_owner(
@@ -380,6 +694,35 @@ impl ChatRoomRepository for PgChatRoomRepository {
         Ok(created_room)
     }
 
+    async fn find_data_key(&self, room_id: RoomId) -> Result<Option<Vec<u8>>, RepositoryError> {
+        let key: Option<Option<Vec<u8>>> =
+            sqlx::query_scalar("SELECT encrypted_data_key FROM chat_rooms WHERE id = $1")
+                .bind(Uuid::from(room_id))
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+
+        Ok(key.flatten())
+    }
+
+    async fn set_data_key(
+        &self,
+        room_id: RoomId,
+        wrapped_key: &[u8],
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE chat_rooms SET encrypted_data_key = $2
+             WHERE id = $1 AND encrypted_data_key IS NULL",
+        )
+        .bind(Uuid::from(room_id))
+        .bind(wrapped_key)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     // === 向后兼容方法 ===
     // 注意：向后兼容方法已移动到 trait 的默认实现中
 }
@@ -405,6 +748,65 @@ impl From<MemberRecord> for RoomMember {
     }
 }
 
+#[derive(Debug, FromRow)]
+struct RestrictionRecord {
+    room_id: Uuid,
+    user_id: Uuid,
+    muted_until: Option<OffsetDateTime>,
+    banned: bool,
+}
+
+impl From<RestrictionRecord> for RoomMemberRestriction {
+    fn from(value: RestrictionRecord) -> Self {
+        Self {
+            room_id: RoomId::from(value.room_id),
+            user_id: UserId::from(value.user_id),
+            muted_until: value.muted_until,
+            banned: value.banned,
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct RoomBanRecord {
+    room_id: Uuid,
+    user_id: Uuid,
+    banned_by: Option<Uuid>,
+    reason: Option<String>,
+    created_at: OffsetDateTime,
+}
+
+impl From<RoomBanRecord> for RoomBan {
+    fn from(value: RoomBanRecord) -> Self {
+        Self {
+            room_id: RoomId::from(value.room_id),
+            user_id: UserId::from(value.user_id),
+            banned_by: value.banned_by.map(UserId::from),
+            reason: value.reason,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct MemberListingRecord {
+    user_id: Uuid,
+    username: String,
+    role: RoomRole,
+    joined_at: OffsetDateTime,
+}
+
+impl From<MemberListingRecord> for RoomMemberListing {
+    fn from(value: MemberListingRecord) -> Self {
+        Self {
+            user_id: UserId::from(value.user_id),
+            username: value.username,
+            role: value.role,
+            joined_at: value.joined_at,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct PgRoomMemberRepository {
     pool: PgPool,
@@ -416,28 +818,47 @@ impl PgRoomMemberRepository {
     }
 }
 
+async fn upsert_member(
+    executor: impl sqlx::PgExecutor<'_>,
+    member: &RoomMember,
+) -> Result<RoomMember, RepositoryError> {
+    let record = sqlx::query_as::<_, MemberRecord>(
+        r#"
+        INSERT INTO room_members (room_id, user_id, role, joined_at, last_read_message_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (room_id, user_id)
+        DO UPDATE SET role = $3, joined_at = $4, last_read_message_id = $5
+        RETURNING room_id, user_id, role, joined_at, last_read_message_id
+        "#,
+    )
+    .bind(Uuid::from(member.room_id))
+    .bind(Uuid::from(member.user_id))
+    .bind(&member.role)
+    .bind(member.joined_at)
+    .bind(member.last_read_message.map(Uuid::from))
+    .fetch_one(executor)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(RoomMember::from(record))
+}
+
 #[async_trait]
 impl RoomMemberRepository for PgRoomMemberRepository {
     async fn upsert(&self, member: RoomMember) -> Result<RoomMember, RepositoryError> {
-        let record = sqlx::query_as::<_, MemberRecord>(
-            r#"
-            INSERT INTO room_members (room_id, user_id, role, joined_at, last_read_message_id)
-            VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (room_id, user_id)
-            DO UPDATE SET role = $3, joined_at = $4, last_read_message_id = $5
-            RETURNING room_id, user_id, role, joined_at, last_read_message_id
-            "#,
-        )
-        .bind(Uuid::from(member.room_id))
-        .bind(Uuid::from(member.user_id))
-        .bind(&member.role)
-        .bind(member.joined_at)
-        .bind(member.last_read_message.map(Uuid::from))
-        .fetch_one(&self.pool)
-        .await
-        .map_err(map_sqlx_err)?;
+        upsert_member(&self.pool, &member).await
+    }
 
-        Ok(RoomMember::from(record))
+    async fn upsert_audited(
+        &self,
+        member: RoomMember,
+        audit: AuditLogEntry,
+    ) -> Result<RoomMember, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        let updated = upsert_member(&mut *tx, &member).await?;
+        insert_audit_entry(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(updated)
     }
 
     async fn find_member(
@@ -468,6 +889,25 @@ impl RoomMemberRepository for PgRoomMemberRepository {
         Ok(())
     }
 
+    async fn delete_member_audited(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        audit: AuditLogEntry,
+    ) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        sqlx::query("DELETE FROM room_members WHERE room_id = $1 AND user_id = $2")
+            .bind(Uuid::from(room_id))
+            .bind(Uuid::from(user_id))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+        insert_audit_entry(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
     async fn find_by_room(&self, room_id: RoomId) -> Result<Vec<RoomMember>, RepositoryError> {
         let records = sqlx::query_as::<_, MemberRecord>(
             r#"SELECT room_id, user_id, role, joined_at, last_read_message_id FROM room_members WHERE room_id = $1"#,
@@ -480,57 +920,408 @@ impl RoomMemberRepository for PgRoomMemberRepository {
         Ok(records.into_iter().map(RoomMember::from).collect())
     }
 
-    // === 向后兼容方法 ===
-    // 注意：向后兼容方法已移动到 trait 的默认实现中
-}
-
-#[derive(Debug, FromRow)]
-struct MessageRecord {
-    id: Uuid,
-    room_id: Uuid,
-    user_id: Uuid,
-    content: String,
-    message_type: MessageType,
-    reply_to_message_id: Option<Uuid>,
-    created_at: OffsetDateTime,
-    updated_at: Option<OffsetDateTime>, // 对应SQL schema中的updated_at字段
-    is_deleted: bool,
-}
+    async fn list_by_room_paginated(
+        &self,
+        room_id: RoomId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<RoomMemberListing>, RepositoryError> {
+        let page = page.max(1);
+        let offset = (page as i64 - 1) * page_size as i64;
+
+        // room_role 枚举按声明顺序比较：owner < admin < member；user_id 保证同一时间加入的成员顺序稳定
+        let records = sqlx::query_as::<_, MemberListingRecord>(
+            r#"
+            SELECT m.user_id, u.username, m.role, m.joined_at
+            FROM room_members m
+            JOIN users u ON u.id = m.user_id
+            WHERE m.room_id = $1
+            ORDER BY m.role, m.joined_at, m.user_id
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(page_size as i64)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
 
-impl TryFrom<MessageRecord> for Message {
-    type Error = RepositoryError;
+        let total_count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM room_members WHERE room_id = $1")
+                .bind(Uuid::from(room_id))
+                .fetch_one(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+
+        Ok(PaginatedResult {
+            items: records.into_iter().map(RoomMemberListing::from).collect(),
+            total_count,
+            page,
+            page_size,
+        })
+    }
 
-    fn try_from(value: MessageRecord) -> Result<Self, Self::Error> {
-        let content = MessageContent::new(value.content).map_err(invalid_data)?;
+    async fn find_by_user(&self, user_id: UserId) -> Result<Vec<RoomMember>, RepositoryError> {
+        let records = sqlx::query_as::<_, MemberRecord>(
+            r#"SELECT room_id, user_id, role, joined_at, last_read_message_id FROM room_members WHERE user_id = $1"#,
+        )
+        .bind(Uuid::from(user_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
 
-        // 如果消息被编辑过（updated_at存在且不同于created_at），创建revision记录
-        let last_revision = if let Some(updated_at) = value.updated_at {
-            if updated_at != value.created_at {
-                Some(domain::MessageRevision {
-                    content: content.clone(), // 当前存储的是最新内容，原始内容已丢失
-                    updated_at,
-                })
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+        Ok(records.into_iter().map(RoomMember::from).collect())
+    }
 
-        Ok(Message {
-            id: MessageId::from(value.id),
-            room_id: RoomId::from(value.room_id),
+    async fn update_last_read(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        message_id: MessageId,
+    ) -> Result<(), RepositoryError> {
+        // 按序列号比较：只有更新的消息才能推进已读位置
+        sqlx::query(
+            r#"
+            UPDATE room_members rm
+            SET last_read_message_id = $3
+            WHERE rm.room_id = $1
+              AND rm.user_id = $2
+              AND (
+                  rm.last_read_message_id IS NULL
+                  OR (SELECT seq FROM messages WHERE id = $3)
+                     > (SELECT seq FROM messages WHERE id = rm.last_read_message_id)
+              )
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .bind(Uuid::from(message_id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_restriction(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<Option<RoomMemberRestriction>, RepositoryError> {
+        let record = sqlx::query_as::<_, RestrictionRecord>(
+            r#"
+            SELECT r.room_id, r.user_id, r.muted_until,
+                   EXISTS (SELECT 1 FROM room_bans b WHERE b.room_id = r.room_id AND b.user_id = r.user_id) AS banned
+            FROM room_member_restrictions r
+            WHERE r.room_id = $1 AND r.user_id = $2
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(RoomMemberRestriction::from))
+    }
+
+    async fn set_muted_until(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        muted_until: Option<OffsetDateTime>,
+    ) -> Result<RoomMemberRestriction, RepositoryError> {
+        let record = sqlx::query_as::<_, RestrictionRecord>(
+            r#"
+            INSERT INTO room_member_restrictions (room_id, user_id, muted_until)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (room_id, user_id)
+            DO UPDATE SET muted_until = $3, updated_at = NOW()
+            RETURNING room_id, user_id, muted_until,
+                EXISTS (SELECT 1 FROM room_bans WHERE room_id = $1 AND user_id = $2) AS banned
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .bind(muted_until)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(RoomMemberRestriction::from(record))
+    }
+
+    async fn pin_room(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        pinned_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO room_pins (user_id, room_id, pinned_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, room_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(Uuid::from(room_id))
+        .bind(pinned_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn unpin_room(&self, room_id: RoomId, user_id: UserId) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM room_pins WHERE user_id = $1 AND room_id = $2")
+            .bind(Uuid::from(user_id))
+            .bind(Uuid::from(room_id))
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn mark_rooms_read(
+        &self,
+        user_id: UserId,
+        room_ids: Option<&[RoomId]>,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError> {
+        let room_ids: Option<Vec<Uuid>> =
+            room_ids.map(|ids| ids.iter().copied().map(Uuid::from).collect());
+
+        // 单条语句完成，所有房间要么一起前进要么都不动；已读位置只前进不后退
+        let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            r#"
+            WITH latest AS (
+                SELECT DISTINCT ON (m.room_id) m.room_id, m.id, m.seq
+                FROM messages m
+                JOIN room_members rm ON rm.room_id = m.room_id AND rm.user_id = $1
+                WHERE $2::UUID[] IS NULL OR m.room_id = ANY($2)
+                ORDER BY m.room_id, m.seq DESC
+            )
+            UPDATE room_members rm
+            SET last_read_message_id = latest.id
+            FROM latest
+            WHERE rm.room_id = latest.room_id
+              AND rm.user_id = $1
+              AND (
+                  rm.last_read_message_id IS NULL
+                  OR latest.seq > (SELECT seq FROM messages WHERE id = rm.last_read_message_id)
+              )
+            RETURNING rm.room_id, latest.id
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(room_ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(room_id, message_id)| (RoomId::from(room_id), MessageId::from(message_id)))
+            .collect())
+    }
+
+    async fn ban(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        banned_by: UserId,
+        reason: Option<String>,
+    ) -> Result<RoomBan, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 先写封禁再移出，同一事务内完成，中间不会被重新加入
+        let record = sqlx::query_as::<_, RoomBanRecord>(
+            r#"
+            INSERT INTO room_bans (room_id, user_id, banned_by, reason)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (room_id, user_id)
+            DO UPDATE SET banned_by = EXCLUDED.banned_by, reason = EXCLUDED.reason
+            RETURNING room_id, user_id, banned_by, reason, created_at
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .bind(Uuid::from(banned_by))
+        .bind(reason)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        sqlx::query("DELETE FROM room_members WHERE room_id = $1 AND user_id = $2")
+            .bind(Uuid::from(room_id))
+            .bind(Uuid::from(user_id))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(RoomBan::from(record))
+    }
+
+    async fn unban(&self, room_id: RoomId, user_id: UserId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM room_bans WHERE room_id = $1 AND user_id = $2")
+            .bind(Uuid::from(room_id))
+            .bind(Uuid::from(user_id))
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn is_banned(&self, room_id: RoomId, user_id: UserId) -> Result<bool, RepositoryError> {
+        let banned: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM room_bans WHERE room_id = $1 AND user_id = $2)",
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(banned)
+    }
+
+    async fn list_bans(&self, room_id: RoomId) -> Result<Vec<RoomBan>, RepositoryError> {
+        let records = sqlx::query_as::<_, RoomBanRecord>(
+            r#"
+            SELECT room_id, user_id, banned_by, reason, created_at
+            FROM room_bans
+            WHERE room_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(RoomBan::from).collect())
+    }
+
+    // === 向后兼容方法 ===
+    // 注意：向后兼容方法已移动到 trait 的默认实现中
+}
+
+#[derive(Debug, FromRow)]
+struct MessageRecord {
+    id: Uuid,
+    room_id: Uuid,
+    user_id: Uuid,
+    content: String,
+    message_type: MessageType,
+    reply_to_message_id: Option<Uuid>,
+    thread_root_id: Option<Uuid>,
+    seq: i64,
+    created_at: OffsetDateTime,
+    updated_at: Option<OffsetDateTime>, // 对应SQL schema中的updated_at字段
+    previous_content: Option<String>,
+    version: i32,
+    is_deleted: bool,
+    encrypted: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct ThreadMessageRecord {
+    #[sqlx(flatten)]
+    message: MessageRecord,
+    parent_deleted: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct PinnedMessageRecord {
+    #[sqlx(flatten)]
+    message: MessageRecord,
+    pinned_by: Option<Uuid>,
+    pinned_at: OffsetDateTime,
+}
+
+#[derive(Debug, FromRow)]
+struct MentionedMessageRecord {
+    #[sqlx(flatten)]
+    message: MessageRecord,
+    is_read: bool,
+}
+
+impl TryFrom<MessageRecord> for Message {
+    type Error = RepositoryError;
+
+    fn try_from(value: MessageRecord) -> Result<Self, Self::Error> {
+        let content = MessageContent::new(value.content).map_err(invalid_data)?;
+
+        // 如果消息被编辑过（updated_at存在且不同于created_at），创建revision记录
+        let last_revision = match value.updated_at {
+            Some(updated_at) if updated_at != value.created_at => {
+                // 旧数据没有保存编辑前内容，只能退化为当前内容
+                let previous = match value.previous_content {
+                    Some(previous) => MessageContent::new(previous).map_err(invalid_data)?,
+                    None => content.clone(),
+                };
+                Some(domain::MessageRevision {
+                    content: previous,
+                    updated_at,
+                    encrypted: value.encrypted,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(Message {
+            id: MessageId::from(value.id),
+            room_id: RoomId::from(value.room_id),
             sender_id: UserId::from(value.user_id),
             content,
             message_type: value.message_type,
             reply_to: value.reply_to_message_id.map(MessageId::from),
+            thread_root_id: value.thread_root_id.map(MessageId::from),
+            seq: value.seq,
             created_at: value.created_at,
             last_revision,
+            version: value.version,
+            is_deleted: value.is_deleted,
+            encrypted: value.encrypted,
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct ExportedMessageRecord {
+    id: Uuid,
+    sender_username: String,
+    content: String,
+    message_type: MessageType,
+    created_at: OffsetDateTime,
+    is_deleted: bool,
+    encrypted: bool,
+}
+
+impl TryFrom<ExportedMessageRecord> for ExportedMessage {
+    type Error = RepositoryError;
+
+    fn try_from(value: ExportedMessageRecord) -> Result<Self, Self::Error> {
+        Ok(ExportedMessage {
+            id: MessageId::from(value.id),
+            sender_username: value.sender_username,
+            content: MessageContent::new(value.content).map_err(invalid_data)?,
+            message_type: value.message_type,
+            created_at: value.created_at,
             is_deleted: value.is_deleted,
+            encrypted: value.encrypted,
         })
     }
 }
 
+/// 过期消息每批删除的条数
+const PURGE_BATCH_SIZE: i64 = 5000;
+
 #[derive(Clone)]
 pub struct PgMessageRepository {
     pool: PgPool,
@@ -542,683 +1333,3169 @@ impl PgMessageRepository {
     }
 }
 
+async fn insert_message(
+    executor: impl sqlx::PgExecutor<'_>,
+    message: &Message,
+) -> Result<MessageId, RepositoryError> {
+    let record = sqlx::query_as::<_, MessageRecord>(
+        r#"
+        INSERT INTO messages (id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, created_at, updated_at, is_deleted, encrypted)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+        "#,
+    )
+    .bind(Uuid::from(message.id))
+    .bind(Uuid::from(message.room_id))
+    .bind(Uuid::from(message.sender_id))
+    .bind(message.content.as_str())
+    .bind(&message.message_type)
+    .bind(message.reply_to.map(Uuid::from))
+    .bind(message.thread_root_id.map(Uuid::from))
+    .bind(message.created_at)
+    .bind(message.created_at) // 新消息的updated_at初始值等于created_at
+    .bind(message.is_deleted)
+    .bind(message.encrypted)
+    .fetch_one(executor)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(MessageId::from(record.id))
+}
+
 #[async_trait]
 impl MessageRepository for PgMessageRepository {
     // 新的核心方法：保存消息并返回消息ID
     async fn create(&self, message: Message) -> Result<MessageId, RepositoryError> {
-        let record = sqlx::query_as::<_, MessageRecord>(
-            r#"
-            INSERT INTO messages (id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-            RETURNING id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-            "#,
+        insert_message(&self.pool, &message).await
+    }
+
+    async fn create_with_outbox(&self, message: Message) -> Result<MessageId, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        let message_id = insert_message(&mut *tx, &message).await?;
+        sqlx::query(
+            "INSERT INTO message_outbox (message_id, room_id, created_at) VALUES ($1, $2, $3)",
         )
-        .bind(Uuid::from(message.id))
+        .bind(Uuid::from(message_id))
         .bind(Uuid::from(message.room_id))
-        .bind(Uuid::from(message.sender_id))
-        .bind(message.content.as_str())
-        .bind(&message.message_type)
-        .bind(message.reply_to.map(Uuid::from))
         .bind(message.created_at)
-        .bind(message.created_at) // 新消息的updated_at初始值等于created_at
-        .bind(message.is_deleted)
-        .fetch_one(&self.pool)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(message_id)
+    }
+
+    async fn create_batch(
+        &self,
+        messages: Vec<Message>,
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        if messages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let count = messages.len();
+        let mut ids = Vec::with_capacity(count);
+        let mut room_ids = Vec::with_capacity(count);
+        let mut sender_ids = Vec::with_capacity(count);
+        let mut contents = Vec::with_capacity(count);
+        let mut message_types = Vec::with_capacity(count);
+        let mut reply_tos = Vec::with_capacity(count);
+        let mut thread_root_ids = Vec::with_capacity(count);
+        let mut created_ats = Vec::with_capacity(count);
+        let mut deleted = Vec::with_capacity(count);
+        let mut encrypted = Vec::with_capacity(count);
+        for message in messages {
+            ids.push(Uuid::from(message.id));
+            room_ids.push(Uuid::from(message.room_id));
+            sender_ids.push(Uuid::from(message.sender_id));
+            contents.push(message.content.as_str().to_owned());
+            message_types.push(message.message_type);
+            reply_tos.push(message.reply_to.map(Uuid::from));
+            thread_root_ids.push(message.thread_root_id.map(Uuid::from));
+            created_ats.push(message.created_at);
+            deleted.push(message.is_deleted);
+            encrypted.push(message.encrypted);
+        }
+
+        // 单条语句写入整批；按传入顺序插入，序列号触发器逐行按这个顺序分配
+        let inserted: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO messages (id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, created_at, updated_at, is_deleted, encrypted)
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, created_at, created_at, is_deleted, encrypted
+            FROM UNNEST($1::uuid[], $2::uuid[], $3::uuid[], $4::text[], $5::message_type[], $6::uuid[], $7::uuid[], $8::timestamptz[], $9::boolean[], $10::boolean[])
+                WITH ORDINALITY AS batch(id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, created_at, is_deleted, encrypted, position)
+            ORDER BY position
+            RETURNING id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&room_ids)
+        .bind(&sender_ids)
+        .bind(&contents)
+        .bind(&message_types)
+        .bind(&reply_tos)
+        .bind(&thread_root_ids)
+        .bind(&created_ats)
+        .bind(&deleted)
+        .bind(&encrypted)
+        .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(MessageId::from(record.id))
+        if inserted.len() != ids.len() {
+            return Err(RepositoryError::storage(format!(
+                "batch insert wrote {} of {} messages",
+                inserted.len(),
+                ids.len()
+            )));
+        }
+        Ok(ids.into_iter().map(MessageId::from).collect())
     }
 
-    async fn update(&self, message: Message) -> Result<(), RepositoryError> {
-        // 计算updated_at时间：如果有编辑历史则使用编辑时间，否则使用当前时间
-        let updated_at = if let Some(revision) = &message.last_revision {
-            revision.updated_at
-        } else {
-            message.created_at // 如果没有编辑历史，保持原创建时间
+    async fn update(&self, message: Message, expected_version: i32) -> Result<(), RepositoryError> {
+        // 有编辑历史则使用编辑时间并保存编辑前内容，否则保持原创建时间
+        let (updated_at, previous_content) = match &message.last_revision {
+            Some(revision) => (revision.updated_at, Some(revision.content.as_str())),
+            None => (message.created_at, None),
         };
 
-        sqlx::query(
+        // 更新内容和追加编辑历史在同一个事务里，不会出现只改了一半
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        let result = sqlx::query(
             r#"
             UPDATE messages
-            SET content = $2, updated_at = $3
-            WHERE id = $1 AND is_deleted = FALSE
+            SET content = $2, updated_at = $3, previous_content = $4, encrypted = $6, version = version + 1
+            WHERE id = $1 AND is_deleted = FALSE AND version = $5
             "#,
         )
         .bind(Uuid::from(message.id))
         .bind(message.content.as_str())
         .bind(updated_at)
-        .execute(&self.pool)
+        .bind(previous_content)
+        .bind(expected_version)
+        .bind(message.encrypted)
+        .execute(&mut *tx)
         .await
         .map_err(map_sqlx_err)?;
 
+        if result.rows_affected() == 0 {
+            // 区分消息不存在和版本已被别人改过
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM messages WHERE id = $1 AND is_deleted = FALSE)",
+            )
+            .bind(Uuid::from(message.id))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+            return Err(if exists {
+                RepositoryError::Conflict
+            } else {
+                RepositoryError::NotFound
+            });
+        }
+
+        if let Some(revision) = &message.last_revision {
+            sqlx::query(
+                "INSERT INTO message_revisions (message_id, content, updated_at, encrypted) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(Uuid::from(message.id))
+            .bind(revision.content.as_str())
+            .bind(revision.updated_at)
+            .bind(revision.encrypted)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+        }
+
+        tx.commit().await.map_err(map_sqlx_err)?;
         Ok(())
     }
 
-    async fn find_by_id(&self, id: MessageId) -> Result<Option<Message>, RepositoryError> {
-        let record = sqlx::query_as::<_, MessageRecord>(
-            r#"SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted FROM messages WHERE id = $1"#,
+    async fn revisions(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Vec<domain::MessageRevision>, RepositoryError> {
+        let records: Vec<(String, OffsetDateTime, bool)> = sqlx::query_as(
+            r#"
+            SELECT content, updated_at, encrypted
+            FROM message_revisions
+            WHERE message_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(Uuid::from(message_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records
+            .into_iter()
+            .map(|(content, updated_at, encrypted)| {
+                Ok(domain::MessageRevision {
+                    content: MessageContent::new(content).map_err(invalid_data)?,
+                    updated_at,
+                    encrypted,
+                })
+            })
+            .collect()
+    }
+
+    async fn restore(&self, id: MessageId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE messages SET is_deleted = FALSE WHERE id = $1 AND is_deleted = TRUE",
+        )
+        .bind(Uuid::from(id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn purge_older_than(&self, cutoff: OffsetDateTime) -> Result<u64, RepositoryError> {
+        // 分批删除，避免一次长事务锁住大量行、级联删除撑爆 WAL
+        let mut purged = 0;
+        loop {
+            let result = sqlx::query(
+                r#"
+                DELETE FROM messages
+                WHERE id IN (
+                    SELECT id FROM messages
+                    WHERE created_at < $1
+                    LIMIT $2
+                )
+                "#,
+            )
+            .bind(cutoff)
+            .bind(PURGE_BATCH_SIZE)
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+            purged += result.rows_affected();
+            if result.rows_affected() < PURGE_BATCH_SIZE as u64 {
+                return Ok(purged);
+            }
+        }
+    }
+
+    async fn count_after(
+        &self,
+        room_id: RoomId,
+        message_id: Option<MessageId>,
+    ) -> Result<i64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM messages
+            WHERE room_id = $1
+              AND is_deleted = FALSE
+              AND seq > COALESCE((SELECT seq FROM messages WHERE id = $2 AND room_id = $1), 0)
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(message_id.map(Uuid::from))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(count)
+    }
+
+    async fn count_unread_for_member(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+    ) -> Result<i64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM messages m
+            WHERE m.room_id = $1
+              AND m.is_deleted = FALSE
+              AND m.seq > COALESCE(
+                  (
+                      SELECT last_read.seq
+                      FROM room_members rm
+                      JOIN messages last_read ON last_read.id = rm.last_read_message_id
+                      WHERE rm.room_id = $1 AND rm.user_id = $2
+                  ),
+                  0
+              )
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(user_id))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(count)
+    }
+
+    async fn soft_delete(&self, id: MessageId) -> Result<(), RepositoryError> {
+        let result = sqlx::query("UPDATE messages SET is_deleted = TRUE WHERE id = $1")
+            .bind(Uuid::from(id))
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn soft_delete_many(
+        &self,
+        room_id: RoomId,
+        ids: &[MessageId],
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 单条 UPDATE 即一个事务：要么全部删除，要么全部不变
+        let ids: Vec<Uuid> = ids.iter().copied().map(Uuid::from).collect();
+        let deleted: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE messages
+            SET is_deleted = TRUE
+            WHERE room_id = $1 AND id = ANY($2) AND is_deleted = FALSE
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(deleted.into_iter().map(MessageId::from).collect())
+    }
+
+    async fn soft_delete_batch_by_sender(
+        &self,
+        sender_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<(RoomId, MessageId)>, RepositoryError> {
+        let deleted: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            r#"
+            UPDATE messages
+            SET is_deleted = TRUE
+            WHERE id IN (
+                SELECT id FROM messages
+                WHERE user_id = $1 AND is_deleted = FALSE
+                ORDER BY seq
+                LIMIT $2
+            )
+            RETURNING room_id, id
+            "#,
+        )
+        .bind(Uuid::from(sender_id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(deleted
+            .into_iter()
+            .map(|(room_id, id)| (RoomId::from(room_id), MessageId::from(id)))
+            .collect())
+    }
+
+    async fn find_by_id(&self, id: MessageId) -> Result<Option<Message>, RepositoryError> {
+        let record = sqlx::query_as::<_, MessageRecord>(
+            r#"SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted FROM messages WHERE id = $1"#,
+        )
+        .bind(Uuid::from(id))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        record.map(Message::try_from).transpose()
+    }
+
+    async fn find_by_ids(&self, ids: &[MessageId]) -> Result<Vec<Message>, RepositoryError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<Uuid> = ids.iter().copied().map(Uuid::from).collect();
+        let records = sqlx::query_as::<_, MessageRecord>(
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages
+            WHERE id = ANY($1)
+            ORDER BY seq ASC
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    // 增强的获取最近消息方法
+    async fn find_recent_by_room(
+        &self,
+        room_id: RoomId,
+        pagination: PaginationParams,
+        before: Option<MessageId>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let records = if let Some(before_id) = before {
+            sqlx::query_as::<_, MessageRecord>(
+                r#"
+                SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+                FROM messages
+                WHERE room_id = $1
+                    AND seq < (SELECT seq FROM messages WHERE id = $2)
+                    AND is_deleted = FALSE
+                ORDER BY seq DESC
+                LIMIT $3
+                "#,
+            )
+            .bind(Uuid::from(room_id))
+            .bind(Uuid::from(before_id))
+            .bind(pagination.limit)
+            .fetch_all(&self.pool)
+            .await
+        } else {
+            sqlx::query_as::<_, MessageRecord>(
+                r#"
+                SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+                FROM messages
+                WHERE room_id = $1 AND is_deleted = FALSE
+                ORDER BY seq DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(Uuid::from(room_id))
+            .bind(pagination.limit)
+            .fetch_all(&self.pool)
+            .await
+        }
+        .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    // 新方法：获取指定时间之后的消息
+    async fn find_since_timestamp(
+        &self,
+        room_id: RoomId,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        // 精确转换时间类型（保留纳秒精度）
+        let timestamp_nanos = timestamp
+            .timestamp_nanos_opt()
+            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
+
+        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
+            .map_err(|e| {
+                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
+            })?;
+
+        let records = sqlx::query_as::<_, MessageRecord>(
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages
+            WHERE room_id = $1 AND created_at > $2 AND is_deleted = FALSE
+            ORDER BY seq ASC
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(time_offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    // 按序列号补偿：只依赖数据库分配的顺序，与时钟无关
+    async fn find_after_sequence(
+        &self,
+        room_id: RoomId,
+        after_seq: i64,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let records = sqlx::query_as::<_, MessageRecord>(
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages
+            WHERE room_id = $1 AND seq > $2 AND is_deleted = FALSE
+            ORDER BY seq ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(after_seq)
+        .bind(pagination.limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    async fn search_in_room(
+        &self,
+        room_id: RoomId,
+        query: &str,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        // to_tsvector 表达式必须与 0015 迁移中的 GIN 索引一致
+        let records = sqlx::query_as::<_, MessageRecord>(
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages, plainto_tsquery('simple', $2) AS query
+            WHERE room_id = $1
+              AND is_deleted = FALSE
+              AND to_tsvector('simple', content) @@ query
+            ORDER BY ts_rank(to_tsvector('simple', content), query) DESC, seq DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(query)
+        .bind(pagination.limit)
+        .bind(pagination.offset.unwrap_or(0))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    async fn find_thread(
+        &self,
+        root_id: MessageId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<ThreadMessage>, RepositoryError> {
+        let records = sqlx::query_as::<_, ThreadMessageRecord>(
+            r#"
+            SELECT m.id, m.room_id, m.user_id, m.content, m.message_type, m.reply_to_message_id, m.thread_root_id, m.seq, m.created_at, m.updated_at, m.previous_content, m.version, m.is_deleted, m.encrypted,
+                   COALESCE(parent.is_deleted, FALSE) AS parent_deleted
+            FROM messages m
+            LEFT JOIN messages parent ON parent.id = m.reply_to_message_id
+            WHERE m.thread_root_id = $1 AND m.is_deleted = FALSE
+            ORDER BY m.seq ASC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(Uuid::from(root_id))
+        .bind(pagination.limit)
+        .bind(pagination.offset.unwrap_or(0))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(ThreadMessage {
+                    message: Message::try_from(record.message)?,
+                    parent_deleted: record.parent_deleted,
+                })
+            })
+            .collect()
+    }
+
+    async fn thread_reply_count(&self, root_id: MessageId) -> Result<i64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM messages WHERE thread_root_id = $1 AND is_deleted = FALSE",
+        )
+        .bind(Uuid::from(root_id))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(count)
+    }
+
+    // 管理员专用：按时间范围获取历史消息（包含已删除消息）
+    async fn find_by_time_range(
+        &self,
+        room_id: RoomId,
+        time_range: TimeRangeParams,
+        pagination: PaginationParams,
+    ) -> Result<Vec<Message>, RepositoryError> {
+        let query = if time_range.include_deleted {
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages
+            WHERE room_id = $1
+                AND ($2::timestamptz IS NULL OR created_at >= $2)
+                AND ($3::timestamptz IS NULL OR created_at <= $3)
+            ORDER BY seq DESC
+            LIMIT $4
+            "#
+        } else {
+            r#"
+            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, thread_root_id, seq, created_at, updated_at, previous_content, version, is_deleted, encrypted
+            FROM messages
+            WHERE room_id = $1
+                AND ($2::timestamptz IS NULL OR created_at >= $2)
+                AND ($3::timestamptz IS NULL OR created_at <= $3)
+                AND is_deleted = FALSE
+            ORDER BY seq DESC
+            LIMIT $4
+            "#
+        };
+
+        // 转换时间参数
+        let start_time = if let Some(timestamp) = time_range.start {
+            let timestamp_nanos = timestamp.timestamp_nanos_opt().ok_or_else(|| {
+                RepositoryError::storage("Start timestamp out of range".to_string())
+            })?;
+            Some(
+                time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128).map_err(
+                    |e| {
+                        RepositoryError::storage_with_source(
+                            "Invalid start timestamp".to_string(),
+                            e,
+                        )
+                    },
+                )?,
+            )
+        } else {
+            None
+        };
+
+        let end_time = if let Some(timestamp) = time_range.end {
+            let timestamp_nanos = timestamp.timestamp_nanos_opt().ok_or_else(|| {
+                RepositoryError::storage("End timestamp out of range".to_string())
+            })?;
+            Some(
+                time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128).map_err(
+                    |e| {
+                        RepositoryError::storage_with_source("Invalid end timestamp".to_string(), e)
+                    },
+                )?,
+            )
+        } else {
+            None
+        };
+
+        let records = sqlx::query_as::<_, MessageRecord>(query)
+            .bind(Uuid::from(room_id))
+            .bind(start_time)
+            .bind(end_time)
+            .bind(pagination.limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        records.into_iter().map(Message::try_from).collect()
+    }
+
+    async fn stream_by_time_range(
+        &self,
+        room_id: RoomId,
+        from: Option<OffsetDateTime>,
+        to: Option<OffsetDateTime>,
+    ) -> Result<ExportedMessageStream, RepositoryError> {
+        let pool = self.pool.clone();
+        // sqlx 的 fetch 边收边解析结果行，消费得慢时由连接的背压拖住数据库，不会把结果全部缓存在内存里
+        Ok(Box::pin(async_stream::try_stream! {
+            let mut records = sqlx::query_as::<_, ExportedMessageRecord>(
+                r#"
+                SELECT m.id, u.username AS sender_username, m.content, m.message_type,
+                       m.created_at, m.is_deleted, m.encrypted
+                FROM messages m
+                JOIN users u ON u.id = m.user_id
+                WHERE m.room_id = $1
+                    AND ($2::timestamptz IS NULL OR m.created_at >= $2)
+                    AND ($3::timestamptz IS NULL OR m.created_at < $3)
+                ORDER BY m.seq ASC
+                "#,
+            )
+            .bind(Uuid::from(room_id))
+            .bind(from)
+            .bind(to)
+            .fetch(&pool);
+
+            while let Some(record) = records.next().await {
+                let record = record.map_err(map_sqlx_err)?;
+                yield ExportedMessage::try_from(record)?;
+            }
+        }))
+    }
+
+    async fn pin(
+        &self,
+        room_id: RoomId,
+        message_id: MessageId,
+        pinned_by: UserId,
+        pinned_at: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        // 一条语句同时判断消息是否属于该房间和是否新置顶
+        let (found, pinned): (bool, bool) = sqlx::query_as(
+            r#"
+            WITH target AS (
+                SELECT id FROM messages
+                WHERE id = $2 AND room_id = $1 AND is_deleted = FALSE
+            ),
+            inserted AS (
+                INSERT INTO pinned_messages (room_id, message_id, pinned_by, pinned_at)
+                SELECT $1, id, $3, $4 FROM target
+                ON CONFLICT (room_id, message_id) DO NOTHING
+                RETURNING 1
+            )
+            SELECT EXISTS(SELECT 1 FROM target), EXISTS(SELECT 1 FROM inserted)
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(Uuid::from(message_id))
+        .bind(Uuid::from(pinned_by))
+        .bind(pinned_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        if !found {
+            return Err(RepositoryError::NotFound);
+        }
+        Ok(pinned)
+    }
+
+    async fn unpin(&self, room_id: RoomId, message_id: MessageId) -> Result<bool, RepositoryError> {
+        let result =
+            sqlx::query("DELETE FROM pinned_messages WHERE room_id = $1 AND message_id = $2")
+                .bind(Uuid::from(room_id))
+                .bind(Uuid::from(message_id))
+                .execute(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_pinned(&self, room_id: RoomId) -> Result<Vec<PinnedMessage>, RepositoryError> {
+        let records = sqlx::query_as::<_, PinnedMessageRecord>(
+            r#"
+            SELECT m.id, m.room_id, m.user_id, m.content, m.message_type, m.reply_to_message_id, m.thread_root_id, m.seq, m.created_at, m.updated_at, m.previous_content, m.version, m.is_deleted, m.encrypted,
+                   p.pinned_by, p.pinned_at
+            FROM pinned_messages p
+            JOIN messages m ON m.id = p.message_id
+            WHERE p.room_id = $1 AND m.is_deleted = FALSE
+            ORDER BY p.pinned_at DESC, m.seq DESC
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        records
+            .into_iter()
+            .map(|record| {
+                Ok(PinnedMessage {
+                    message: Message::try_from(record.message)?,
+                    pinned_by: record.pinned_by.map(UserId::from),
+                    pinned_at: record.pinned_at,
+                })
+            })
+            .collect()
+    }
+
+    // === 向后兼容方法 ===
+    // 注意：向后兼容方法已移动到 trait 的默认实现中
+}
+
+pub async fn create_pg_pool(
+    database_url: &str,
+    max_connections: u32,
+) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(database_url)
+        .await
+}
+
+// MessageDelivery相关的实现
+
+#[derive(Debug, FromRow)]
+struct MessageDeliveryRecord {
+    message_id: Uuid,
+    user_id: Uuid,
+    sent_at: OffsetDateTime,
+    delivered_at: Option<OffsetDateTime>,
+}
+
+impl From<MessageDeliveryRecord> for MessageDelivery {
+    fn from(value: MessageDeliveryRecord) -> Self {
+        Self {
+            message_id: MessageId::from(value.message_id),
+            user_id: UserId::from(value.user_id),
+            sent_at: value.sent_at,
+            delivered_at: value.delivered_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgMessageDeliveryRepository {
+    pool: PgPool,
+}
+
+impl PgMessageDeliveryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MessageDeliveryRepository for PgMessageDeliveryRepository {
+    async fn record_sent(&self, delivery: MessageDelivery) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO message_deliveries (message_id, user_id, sent_at, delivered_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (message_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::from(delivery.message_id))
+        .bind(Uuid::from(delivery.user_id))
+        .bind(delivery.sent_at)
+        .bind(delivery.delivered_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn record_sent_to_room(&self, message: &Message) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO message_deliveries (message_id, user_id, sent_at)
+            SELECT $1, rm.user_id, $3
+            FROM room_members rm
+            WHERE rm.room_id = $2 AND rm.user_id <> $4
+            ON CONFLICT (message_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::from(message.id))
+        .bind(Uuid::from(message.room_id))
+        .bind(message.created_at)
+        .bind(Uuid::from(message.sender_id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn mark_delivered(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        delivered_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), RepositoryError> {
+        // 精确转换时间类型
+        let timestamp_nanos = delivered_at
+            .timestamp_nanos_opt()
+            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
+
+        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
+            .map_err(|e| {
+                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
+            })?;
+
+        sqlx::query(
+            r#"
+            UPDATE message_deliveries
+            SET delivered_at = $3
+            WHERE message_id = $1 AND user_id = $2 AND delivered_at IS NULL
+            "#,
+        )
+        .bind(Uuid::from(message_id))
+        .bind(Uuid::from(user_id))
+        .bind(time_offset)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_undelivered_for_user(
+        &self,
+        user_id: UserId,
+        limit: u32,
+    ) -> Result<Vec<MessageDelivery>, RepositoryError> {
+        // 只取最近的 limit 条，长时间离线的用户不会一次被灌入全部积压
+        let records = sqlx::query_as::<_, MessageDeliveryRecord>(
+            r#"
+            SELECT message_id, user_id, sent_at, delivered_at
+            FROM (
+                SELECT message_id, user_id, sent_at, delivered_at
+                FROM message_deliveries
+                WHERE user_id = $1 AND delivered_at IS NULL
+                ORDER BY sent_at DESC
+                LIMIT $2
+            ) recent
+            ORDER BY sent_at ASC
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(MessageDelivery::from).collect())
+    }
+
+    async fn find_by_message(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageDelivery>, RepositoryError> {
+        let records = sqlx::query_as::<_, MessageDeliveryRecord>(
+            r#"
+            SELECT message_id, user_id, sent_at, delivered_at
+            FROM message_deliveries
+            WHERE message_id = $1
+            ORDER BY sent_at ASC
+            "#,
+        )
+        .bind(Uuid::from(message_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(MessageDelivery::from).collect())
+    }
+
+    async fn cleanup_delivered_before(
+        &self,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, RepositoryError> {
+        // 精确转换时间类型
+        let timestamp_nanos = before
+            .timestamp_nanos_opt()
+            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
+
+        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
+            .map_err(|e| {
+                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
+            })?;
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM message_deliveries
+            WHERE delivered_at IS NOT NULL AND delivered_at < $1
+            "#,
+        )
+        .bind(time_offset)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// Organization related types and implementations
+
+#[derive(Debug, FromRow)]
+struct OrganizationRecord {
+    id: Uuid,
+    name: String,
+    path: String,
+    metadata: Option<serde_json::Value>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+}
+
+impl TryFrom<OrganizationRecord> for Organization {
+    type Error = RepositoryError;
+
+    fn try_from(value: OrganizationRecord) -> Result<Self, Self::Error> {
+        let org_path = domain::OrgPath::parse(value.path).map_err(invalid_data)?;
+
+        Ok(Organization {
+            id: OrgId::from(value.id),
+            name: value.name,
+            path: org_path,
+            metadata: value.metadata,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct PgOrganizationRepository {
+    pool: PgPool,
+}
+
+impl PgOrganizationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl application::repository::OrganizationRepository for PgOrganizationRepository {
+    async fn create(&self, organization: &Organization) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO organizations (id, name, path, metadata, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::from(organization.id))
+        .bind(&organization.name)
+        .bind(organization.path.to_string())
+        .bind(&organization.metadata)
+        .bind(organization.created_at)
+        .bind(organization.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: OrgId) -> Result<Option<Organization>, RepositoryError> {
+        let record = sqlx::query_as::<_, OrganizationRecord>(
+            r#"
+            SELECT id, name, path, metadata, created_at, updated_at
+            FROM organizations
+            WHERE id = $1
+            "#,
+        )
+        .bind(Uuid::from(id))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        match record {
+            Some(record) => Ok(Some(Organization::try_from(record)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_path(&self, path: &str) -> Result<Option<Organization>, RepositoryError> {
+        let record = sqlx::query_as::<_, OrganizationRecord>(
+            r#"
+            SELECT id, name, path, metadata, created_at, updated_at
+            FROM organizations
+            WHERE path = $1
+            "#,
+        )
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        match record {
+            Some(record) => Ok(Some(Organization::try_from(record)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_children(&self, parent_path: &str) -> Result<Vec<Organization>, RepositoryError> {
+        let records = sqlx::query_as::<_, OrganizationRecord>(
+            r#"
+            SELECT id, name, path, metadata, created_at, updated_at
+            FROM organizations
+            WHERE path <@ $1 AND nlevel(path) = nlevel($1) + 1
+            ORDER BY path
+            "#,
+        )
+        .bind(format!("{}.*", parent_path))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let mut organizations = Vec::new();
+        for record in records {
+            organizations.push(Organization::try_from(record)?);
+        }
+
+        Ok(organizations)
+    }
+
+    async fn find_descendants(
+        &self,
+        ancestor_path: &str,
+    ) -> Result<Vec<Organization>, RepositoryError> {
+        let records = sqlx::query_as::<_, OrganizationRecord>(
+            r#"
+            SELECT id, name, path, metadata, created_at, updated_at
+            FROM organizations
+            WHERE path <@ $1
+            ORDER BY path
+            "#,
+        )
+        .bind(format!("{}.*", ancestor_path))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let mut organizations = Vec::new();
+        for record in records {
+            organizations.push(Organization::try_from(record)?);
+        }
+
+        Ok(organizations)
+    }
+
+    async fn update(&self, organization: &Organization) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            UPDATE organizations
+            SET name = $1, path = $2, metadata = $3, updated_at = $4
+            WHERE id = $5
+            "#,
+        )
+        .bind(&organization.name)
+        .bind(organization.path.to_string())
+        .bind(&organization.metadata)
+        .bind(organization.updated_at)
+        .bind(Uuid::from(organization.id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: OrgId) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM organizations WHERE id = $1")
+            .bind(Uuid::from(id))
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn move_organization(
+        &self,
+        id: OrgId,
+        new_parent_path: &str,
+    ) -> Result<(), RepositoryError> {
+        // 这个操作比较复杂，需要更新整个子树的path
+        // 首先获取当前组织信息
+        let org = self.find_by_id(id).await?;
+        let org = org.ok_or(RepositoryError::NotFound)?;
+
+        let _old_path_base = format!("{}.", org.path);
+        let new_path_base = format!("{}.{}", new_parent_path, org.name.to_lowercase());
+
+        // 开始事务
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 更新当前组织的path
+        sqlx::query("UPDATE organizations SET path = $1, updated_at = NOW() WHERE id = $2")
+            .bind(&new_path_base)
+            .bind(Uuid::from(id))
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        // 更新所有子组织的path
+        sqlx::query(
+            r#"
+            UPDATE organizations
+            SET path = $1 || subpath(path, nlevel($2)) || '.' || subpath(path, nlevel($2) + 1),
+                updated_at = NOW()
+            WHERE path <@ $3
+            "#,
+        )
+        .bind(format!("{}.", new_parent_path))
+        .bind(org.path.to_string())
+        .bind(format!("{}.*", org.path))
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        // 提交事务
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn path_exists(&self, path: &str) -> Result<bool, RepositoryError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM organizations WHERE path = $1")
+            .bind(path)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(count > 0)
+    }
+
+    async fn find_users_in_organization(
+        &self,
+        org_id: OrgId,
+    ) -> Result<Vec<User>, RepositoryError> {
+        let records = sqlx::query_as::<_, UserRecord>(
+            r#"
+            SELECT u.id, u.username, u.email, u.password_hash, u.status, u.is_superuser,
+                   u.org_id, u.display_name, u.avatar_url, u.bio, u.token_version, u.created_at, u.updated_at
+            FROM users u
+            WHERE u.org_id = $1
+            ORDER BY u.username
+            "#,
+        )
+        .bind(Uuid::from(org_id))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let mut users = Vec::new();
+        for record in records {
+            users.push(User::try_from(record)?);
+        }
+
+        Ok(users)
+    }
+
+    async fn list_with_pagination(
+        &self,
+        params: PaginationParams,
+    ) -> Result<Vec<Organization>, RepositoryError> {
+        let query = if let Some(offset) = params.offset {
+            sqlx::query_as::<_, OrganizationRecord>(
+                r#"
+                SELECT id, name, path, metadata, created_at, updated_at
+                FROM organizations
+                ORDER BY path
+                LIMIT $1 OFFSET $2
+                "#,
+            )
+            .bind(params.limit)
+            .bind(offset)
+        } else {
+            sqlx::query_as::<_, OrganizationRecord>(
+                r#"
+                SELECT id, name, path, metadata, created_at, updated_at
+                FROM organizations
+                ORDER BY path
+                LIMIT $1
+                "#,
+            )
+            .bind(params.limit)
+        };
+
+        let records = query.fetch_all(&self.pool).await.map_err(map_sqlx_err)?;
+
+        let mut organizations = Vec::new();
+        for record in records {
+            organizations.push(Organization::try_from(record)?);
+        }
+
+        Ok(organizations)
+    }
+}
+
+// RefreshToken相关的实现
+
+#[derive(Debug, FromRow)]
+struct RefreshTokenRecord {
+    jti: Uuid,
+    user_id: Uuid,
+    family_id: Uuid,
+    expires_at: OffsetDateTime,
+    revoked_at: Option<OffsetDateTime>,
+    replaced_by: Option<Uuid>,
+    created_at: OffsetDateTime,
+}
+
+impl From<RefreshTokenRecord> for RefreshToken {
+    fn from(value: RefreshTokenRecord) -> Self {
+        Self {
+            jti: value.jti,
+            user_id: UserId::from(value.user_id),
+            family_id: value.family_id,
+            expires_at: value.expires_at,
+            revoked_at: value.revoked_at,
+            replaced_by: value.replaced_by,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgRefreshTokenRepository {
+    pool: PgPool,
+}
+
+impl PgRefreshTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn insert_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        token: &RefreshToken,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (jti, user_id, family_id, expires_at, revoked_at, replaced_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(token.jti)
+        .bind(Uuid::from(token.user_id))
+        .bind(token.family_id)
+        .bind(token.expires_at)
+        .bind(token.revoked_at)
+        .bind(token.replaced_by)
+        .bind(token.created_at)
+        .execute(&mut **tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for PgRefreshTokenRepository {
+    async fn create(&self, token: RefreshToken) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        Self::insert_tx(&mut tx, &token).await?;
+        tx.commit().await.map_err(map_sqlx_err)
+    }
+
+    async fn find_by_jti(&self, jti: Uuid) -> Result<Option<RefreshToken>, RepositoryError> {
+        let record = sqlx::query_as::<_, RefreshTokenRecord>(
+            r#"SELECT jti, user_id, family_id, expires_at, revoked_at, replaced_by, created_at FROM refresh_tokens WHERE jti = $1"#,
+        )
+        .bind(jti)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(RefreshToken::from))
+    }
+
+    async fn rotate(&self, old_jti: Uuid, new_token: RefreshToken) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 条件更新保证同一个令牌只能被轮换一次
+        let revoked = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = $2, replaced_by = $3
+            WHERE jti = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(old_jti)
+        .bind(new_token.created_at)
+        .bind(new_token.jti)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        if revoked.rows_affected() == 0 {
+            return Err(RepositoryError::Conflict);
+        }
+
+        Self::insert_tx(&mut tx, &new_token).await?;
+        tx.commit().await.map_err(map_sqlx_err)
+    }
+
+    async fn revoke_family(&self, family_id: Uuid) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE family_id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(family_id)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn revoke_all_for_user(&self, user_id: UserId) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// EmailVerification相关的实现
+
+#[derive(Debug, FromRow)]
+struct EmailVerificationRecord {
+    token_hash: String,
+    user_id: Uuid,
+    expires_at: OffsetDateTime,
+    used_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+}
+
+impl From<EmailVerificationRecord> for EmailVerification {
+    fn from(value: EmailVerificationRecord) -> Self {
+        Self {
+            token_hash: value.token_hash,
+            user_id: UserId::from(value.user_id),
+            expires_at: value.expires_at,
+            used_at: value.used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgEmailVerificationRepository {
+    pool: PgPool,
+}
+
+impl PgEmailVerificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmailVerificationRepository for PgEmailVerificationRepository {
+    async fn create(&self, verification: EmailVerification) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_verifications (token_hash, user_id, expires_at, used_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&verification.token_hash)
+        .bind(Uuid::from(verification.user_id))
+        .bind(verification.expires_at)
+        .bind(verification.used_at)
+        .bind(verification.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<EmailVerification>, RepositoryError> {
+        let record = sqlx::query_as::<_, EmailVerificationRecord>(
+            r#"SELECT token_hash, user_id, expires_at, used_at, created_at FROM email_verifications WHERE token_hash = $1"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(EmailVerification::from))
+    }
+
+    async fn consume(
+        &self,
+        token_hash: &str,
+        now: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        // 条件更新保证并发验证时只有一个请求成功
+        let result = sqlx::query(
+            r#"
+            UPDATE email_verifications
+            SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_latest_for_user(
+        &self,
+        user_id: UserId,
+    ) -> Result<Option<EmailVerification>, RepositoryError> {
+        let record = sqlx::query_as::<_, EmailVerificationRecord>(
+            r#"
+            SELECT token_hash, user_id, expires_at, used_at, created_at
+            FROM email_verifications
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(EmailVerification::from))
+    }
+}
+
+// PasswordReset相关的实现
+
+#[derive(Debug, FromRow)]
+struct PasswordResetRecord {
+    token_hash: String,
+    user_id: Uuid,
+    expires_at: OffsetDateTime,
+    used_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+}
+
+impl From<PasswordResetRecord> for PasswordReset {
+    fn from(value: PasswordResetRecord) -> Self {
+        Self {
+            token_hash: value.token_hash,
+            user_id: UserId::from(value.user_id),
+            expires_at: value.expires_at,
+            used_at: value.used_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgPasswordResetRepository {
+    pool: PgPool,
+}
+
+impl PgPasswordResetRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PasswordResetRepository for PgPasswordResetRepository {
+    async fn create(&self, reset: PasswordReset) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO password_resets (token_hash, user_id, expires_at, used_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&reset.token_hash)
+        .bind(Uuid::from(reset.user_id))
+        .bind(reset.expires_at)
+        .bind(reset.used_at)
+        .bind(reset.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<PasswordReset>, RepositoryError> {
+        let record = sqlx::query_as::<_, PasswordResetRecord>(
+            r#"SELECT token_hash, user_id, expires_at, used_at, created_at FROM password_resets WHERE token_hash = $1"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(PasswordReset::from))
+    }
+
+    async fn consume(
+        &self,
+        token_hash: &str,
+        now: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        // 条件更新保证同一令牌并发重置时只有一个请求成功
+        let result = sqlx::query(
+            r#"
+            UPDATE password_resets
+            SET used_at = $2
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+            "#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// AuditLog相关的实现
+
+#[derive(Debug, FromRow)]
+struct AuditLogRecord {
+    id: Uuid,
+    actor_id: Uuid,
+    action: String,
+    target_user_id: Option<Uuid>,
+    target_room_id: Option<Uuid>,
+    details: serde_json::Value,
+    created_at: OffsetDateTime,
+}
+
+impl From<AuditLogRecord> for AuditLogEntry {
+    fn from(value: AuditLogRecord) -> Self {
+        Self {
+            id: value.id,
+            actor_id: UserId::from(value.actor_id),
+            action: value.action,
+            target_user_id: value.target_user_id.map(UserId::from),
+            target_room_id: value.target_room_id.map(RoomId::from),
+            details: value.details,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgAuditLogRepository {
+    pool: PgPool,
+}
+
+impl PgAuditLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// 写入一条审计记录；传入事务即可和被审计的操作一起提交
+async fn insert_audit_entry(
+    executor: impl sqlx::PgExecutor<'_>,
+    entry: &AuditLogEntry,
+) -> Result<(), RepositoryError> {
+    sqlx::query(
+        r#"
+        INSERT INTO admin_audit_log (id, actor_id, action, target_user_id, target_room_id, details, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(entry.id)
+    .bind(Uuid::from(entry.actor_id))
+    .bind(&entry.action)
+    .bind(entry.target_user_id.map(Uuid::from))
+    .bind(entry.target_room_id.map(Uuid::from))
+    .bind(&entry.details)
+    .bind(entry.created_at)
+    .execute(executor)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl AuditLogRepository for PgAuditLogRepository {
+    async fn record(&self, entry: AuditLogEntry) -> Result<(), RepositoryError> {
+        insert_audit_entry(&self.pool, &entry).await
+    }
+
+    async fn find_by_target_user(
+        &self,
+        user_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<AuditLogEntry>, RepositoryError> {
+        let records = sqlx::query_as::<_, AuditLogRecord>(
+            r#"
+            SELECT id, actor_id, action, target_user_id, target_room_id, details, created_at
+            FROM admin_audit_log
+            WHERE target_user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(AuditLogEntry::from).collect())
+    }
+
+    async fn find_by_target_room(
+        &self,
+        room_id: RoomId,
+        action: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError> {
+        let page = page.max(1);
+        let offset = (page as i64 - 1) * page_size as i64;
+
+        let records = sqlx::query_as::<_, AuditLogRecord>(
+            r#"
+            SELECT id, actor_id, action, target_user_id, target_room_id, details, created_at
+            FROM admin_audit_log
+            WHERE target_room_id = $1 AND action = $2
+            ORDER BY created_at, id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(action)
+        .bind(page_size as i64)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let total_count: i64 = sqlx::query_scalar(
+            r#"SELECT COUNT(*) FROM admin_audit_log WHERE target_room_id = $1 AND action = $2"#,
+        )
+        .bind(Uuid::from(room_id))
+        .bind(action)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(PaginatedResult {
+            items: records.into_iter().map(AuditLogEntry::from).collect(),
+            total_count,
+            page,
+            page_size,
+        })
+    }
+
+    async fn search(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<PaginatedResult<AuditLogEntry>, RepositoryError> {
+        let page = query.page.max(1);
+        let offset = (page as i64 - 1) * query.page_size as i64;
+        // 条件为 NULL 时不参与过滤
+        let filter = r#"
+            WHERE ($1::uuid IS NULL OR actor_id = $1)
+              AND ($2::text IS NULL OR action = $2)
+              AND ($3::timestamptz IS NULL OR created_at >= $3)
+              AND ($4::timestamptz IS NULL OR created_at < $4)
+        "#;
+
+        let records = sqlx::query_as::<_, AuditLogRecord>(&format!(
+            r#"
+            SELECT id, actor_id, action, target_user_id, target_room_id, details, created_at
+            FROM admin_audit_log
+            {filter}
+            ORDER BY created_at DESC, id
+            LIMIT $5 OFFSET $6
+            "#
+        ))
+        .bind(query.actor_id.map(Uuid::from))
+        .bind(query.action.as_deref())
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.page_size as i64)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let total_count: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM admin_audit_log {filter}"))
+                .bind(query.actor_id.map(Uuid::from))
+                .bind(query.action.as_deref())
+                .bind(query.from)
+                .bind(query.to)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+
+        Ok(PaginatedResult {
+            items: records.into_iter().map(AuditLogEntry::from).collect(),
+            total_count,
+            page,
+            page_size: query.page_size,
+        })
+    }
+}
+
+// Notification相关的实现
+#[derive(Debug, FromRow)]
+struct NotificationRecord {
+    id: Uuid,
+    user_id: Uuid,
+    kind: String,
+    actor_id: Option<Uuid>,
+    room_id: Option<Uuid>,
+    message_id: Option<Uuid>,
+    data: serde_json::Value,
+    created_at: OffsetDateTime,
+    read_at: Option<OffsetDateTime>,
+}
+
+impl From<NotificationRecord> for Notification {
+    fn from(value: NotificationRecord) -> Self {
+        Self {
+            id: value.id,
+            user_id: UserId::from(value.user_id),
+            kind: value.kind,
+            actor_id: value.actor_id.map(UserId::from),
+            room_id: value.room_id.map(RoomId::from),
+            message_id: value.message_id.map(MessageId::from),
+            data: value.data,
+            created_at: value.created_at,
+            read_at: value.read_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgNotificationRepository {
+    pool: PgPool,
+}
+
+impl PgNotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for PgNotificationRepository {
+    async fn create_many(&self, notifications: &[Notification]) -> Result<(), RepositoryError> {
+        if notifications.is_empty() {
+            return Ok(());
+        }
+
+        let mut ids = Vec::with_capacity(notifications.len());
+        let mut user_ids = Vec::with_capacity(notifications.len());
+        let mut kinds = Vec::with_capacity(notifications.len());
+        let mut actor_ids = Vec::with_capacity(notifications.len());
+        let mut room_ids = Vec::with_capacity(notifications.len());
+        let mut message_ids = Vec::with_capacity(notifications.len());
+        let mut data = Vec::with_capacity(notifications.len());
+        let mut created_ats = Vec::with_capacity(notifications.len());
+        for notification in notifications {
+            ids.push(notification.id);
+            user_ids.push(Uuid::from(notification.user_id));
+            kinds.push(notification.kind.clone());
+            actor_ids.push(notification.actor_id.map(Uuid::from));
+            room_ids.push(notification.room_id.map(Uuid::from));
+            message_ids.push(notification.message_id.map(Uuid::from));
+            data.push(notification.data.clone());
+            created_ats.push(notification.created_at);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO notifications (id, user_id, kind, actor_id, room_id, message_id, data, created_at)
+            SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::text[], $4::uuid[], $5::uuid[], $6::uuid[], $7::jsonb[], $8::timestamptz[])
+            "#,
+        )
+        .bind(&ids)
+        .bind(&user_ids)
+        .bind(&kinds)
+        .bind(&actor_ids)
+        .bind(&room_ids)
+        .bind(&message_ids)
+        .bind(&data)
+        .bind(&created_ats)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_for_user(
+        &self,
+        user_id: UserId,
+        unread_only: bool,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<Notification>, RepositoryError> {
+        let page = page.max(1);
+        let offset = (page as i64 - 1) * page_size as i64;
+
+        let records = sqlx::query_as::<_, NotificationRecord>(
+            r#"
+            SELECT id, user_id, kind, actor_id, room_id, message_id, data, created_at, read_at
+            FROM notifications
+            WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+            ORDER BY created_at DESC, id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(unread_only)
+        .bind(page_size as i64)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let total_count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)",
+        )
+        .bind(Uuid::from(user_id))
+        .bind(unread_only)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(PaginatedResult {
+            items: records.into_iter().map(Notification::from).collect(),
+            total_count,
+            page,
+            page_size,
+        })
+    }
+
+    async fn find_for_user_by_id(
+        &self,
+        user_id: UserId,
+        id: Uuid,
+    ) -> Result<Option<Notification>, RepositoryError> {
+        let record = sqlx::query_as::<_, NotificationRecord>(
+            r#"
+            SELECT id, user_id, kind, actor_id, room_id, message_id, data, created_at, read_at
+            FROM notifications
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(Uuid::from(user_id))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(Notification::from))
+    }
+
+    async fn mark_read(
+        &self,
+        user_id: UserId,
+        ids: &[Uuid],
+        now: OffsetDateTime,
+    ) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE notifications
+            SET read_at = $3
+            WHERE user_id = $1 AND read_at IS NULL
+              AND (cardinality($2::uuid[]) = 0 OR id = ANY($2))
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(ids)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete(&self, user_id: UserId, id: Uuid) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM notifications WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(Uuid::from(user_id))
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_unread(&self, user_id: UserId) -> Result<i64, RepositoryError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(Uuid::from(user_id))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(count)
+    }
+}
+
+// 发送消息幂等键的实现
+#[derive(Clone)]
+pub struct PgIdempotencyKeyRepository {
+    pool: PgPool,
+}
+
+impl PgIdempotencyKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyRepository for PgIdempotencyKeyRepository {
+    async fn find(
+        &self,
+        user_id: UserId,
+        key: &str,
+        now: OffsetDateTime,
+    ) -> Result<Option<MessageId>, RepositoryError> {
+        let message_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT message_id FROM message_idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2 AND expires_at > $3
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(key)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(message_id.map(MessageId::from))
+    }
+
+    async fn claim(
+        &self,
+        user_id: UserId,
+        key: &str,
+        message_id: MessageId,
+        now: OffsetDateTime,
+        expires_at: OffsetDateTime,
+    ) -> Result<MessageId, RepositoryError> {
+        // 过期的键直接被新请求接管；未过期时 DO UPDATE 不生效，再查出当前占用者
+        let claimed: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO message_idempotency_keys (user_id, idempotency_key, message_id, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, idempotency_key) DO UPDATE
+            SET message_id = EXCLUDED.message_id,
+                created_at = EXCLUDED.created_at,
+                expires_at = EXCLUDED.expires_at
+            WHERE message_idempotency_keys.expires_at <= EXCLUDED.created_at
+            RETURNING message_id
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(key)
+        .bind(Uuid::from(message_id))
+        .bind(now)
+        .bind(expires_at)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        if let Some(claimed) = claimed {
+            return Ok(MessageId::from(claimed));
+        }
+
+        let owner: Uuid = sqlx::query_scalar(
+            "SELECT message_id FROM message_idempotency_keys WHERE user_id = $1 AND idempotency_key = $2",
+        )
+        .bind(Uuid::from(user_id))
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(MessageId::from(owner))
+    }
+
+    async fn release(
+        &self,
+        user_id: UserId,
+        key: &str,
+        message_id: MessageId,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            DELETE FROM message_idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2 AND message_id = $3
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(key)
+        .bind(Uuid::from(message_id))
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: OffsetDateTime) -> Result<u64, RepositoryError> {
+        let result = sqlx::query("DELETE FROM message_idempotency_keys WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// 消息广播发件箱的实现
+#[derive(Debug, FromRow)]
+struct OutboxRecord {
+    id: i64,
+    message_id: Uuid,
+    room_id: Uuid,
+    status: OutboxStatus,
+    attempts: i32,
+    last_error: Option<String>,
+    next_attempt_at: OffsetDateTime,
+    created_at: OffsetDateTime,
+    dispatched_at: Option<OffsetDateTime>,
+}
+
+impl From<OutboxRecord> for OutboxEntry {
+    fn from(value: OutboxRecord) -> Self {
+        Self {
+            id: value.id,
+            message_id: MessageId::from(value.message_id),
+            room_id: RoomId::from(value.room_id),
+            status: value.status,
+            attempts: value.attempts,
+            last_error: value.last_error,
+            next_attempt_at: value.next_attempt_at,
+            created_at: value.created_at,
+            dispatched_at: value.dispatched_at,
+        }
+    }
+}
+
+const OUTBOX_COLUMNS: &str = "id, message_id, room_id, status, attempts, last_error, next_attempt_at, created_at, dispatched_at";
+
+#[derive(Clone)]
+pub struct PgOutboxRepository {
+    pool: PgPool,
+}
+
+impl PgOutboxRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for PgOutboxRepository {
+    async fn claim_due(
+        &self,
+        now: OffsetDateTime,
+        lease_until: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<OutboxEntry>, RepositoryError> {
+        // SKIP LOCKED：其他实例正在领取的记录直接跳过，不互相等待
+        let mut records = sqlx::query_as::<_, OutboxRecord>(&format!(
+            r#"
+            UPDATE message_outbox SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM message_outbox
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY id
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {OUTBOX_COLUMNS}
+            "#
+        ))
+        .bind(now)
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        // RETURNING 不保证顺序
+        records.sort_by_key(|record| record.id);
+        Ok(records.into_iter().map(OutboxEntry::from).collect())
+    }
+
+    async fn mark_dispatched(&self, id: i64, now: OffsetDateTime) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE message_outbox SET status = 'dispatched', dispatched_at = $2, last_error = NULL WHERE id = $1",
+        )
+        .bind(id)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<OffsetDateTime>,
+    ) -> Result<(), RepositoryError> {
+        let status = match retry_at {
+            Some(_) => OutboxStatus::Pending,
+            None => OutboxStatus::DeadLetter,
+        };
+        sqlx::query(
+            r#"
+            UPDATE message_outbox
+            SET status = $2, last_error = $3, next_attempt_at = COALESCE($4, next_attempt_at)
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn list_dead_letters(
+        &self,
+        pagination: PaginationParams,
+    ) -> Result<Vec<OutboxEntry>, RepositoryError> {
+        let records = sqlx::query_as::<_, OutboxRecord>(&format!(
+            "SELECT {OUTBOX_COLUMNS} FROM message_outbox WHERE status = 'dead_letter' ORDER BY id LIMIT $1 OFFSET $2"
+        ))
+        .bind(pagination.limit)
+        .bind(pagination.offset.unwrap_or(0))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(OutboxEntry::from).collect())
+    }
+
+    async fn requeue(
+        &self,
+        id: i64,
+        now: OffsetDateTime,
+    ) -> Result<Option<OutboxEntry>, RepositoryError> {
+        let record = sqlx::query_as::<_, OutboxRecord>(&format!(
+            r#"
+            UPDATE message_outbox SET status = 'pending', attempts = 0, next_attempt_at = $2
+            WHERE id = $1 AND status = 'dead_letter'
+            RETURNING {OUTBOX_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        // 重新入队后通知分发任务，不必等下一次轮询
+        if record.is_some() {
+            sqlx::query("SELECT pg_notify('message_outbox', $1)")
+                .bind(id.to_string())
+                .execute(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+        }
+
+        Ok(record.map(OutboxEntry::from))
+    }
+
+    async fn delete_dispatched_before(
+        &self,
+        before: OffsetDateTime,
+    ) -> Result<u64, RepositoryError> {
+        let result = sqlx::query(
+            "DELETE FROM message_outbox WHERE status = 'dispatched' AND dispatched_at < $1",
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// 入房邀请的实现
+#[derive(Debug, FromRow)]
+struct RoomInvitationRecord {
+    id: Uuid,
+    room_id: Uuid,
+    inviter_id: Uuid,
+    invitee_id: Uuid,
+    status: InvitationStatus,
+    created_at: OffsetDateTime,
+    expires_at: OffsetDateTime,
+    responded_at: Option<OffsetDateTime>,
+}
+
+impl From<RoomInvitationRecord> for RoomInvitation {
+    fn from(value: RoomInvitationRecord) -> Self {
+        Self {
+            id: value.id,
+            room_id: RoomId::from(value.room_id),
+            inviter_id: UserId::from(value.inviter_id),
+            invitee_id: UserId::from(value.invitee_id),
+            status: value.status,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            responded_at: value.responded_at,
+        }
+    }
+}
+
+const ROOM_INVITATION_COLUMNS: &str =
+    "id, room_id, inviter_id, invitee_id, status, created_at, expires_at, responded_at";
+
+#[derive(Clone)]
+pub struct PgRoomInvitationRepository {
+    pool: PgPool,
+}
+
+impl PgRoomInvitationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RoomInvitationRepository for PgRoomInvitationRepository {
+    async fn create(&self, invitation: &RoomInvitation) -> Result<bool, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 已过期的待处理邀请先改为 expired，不占用唯一索引
+        sqlx::query(
+            r#"
+            UPDATE room_invitations SET status = 'expired'
+            WHERE room_id = $1 AND invitee_id = $2 AND status = 'pending' AND expires_at <= $3
+            "#,
+        )
+        .bind(Uuid::from(invitation.room_id))
+        .bind(Uuid::from(invitation.invitee_id))
+        .bind(invitation.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO room_invitations (id, room_id, inviter_id, invitee_id, status, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (room_id, invitee_id) WHERE status = 'pending' DO NOTHING
+            "#,
+        )
+        .bind(invitation.id)
+        .bind(Uuid::from(invitation.room_id))
+        .bind(Uuid::from(invitation.inviter_id))
+        .bind(Uuid::from(invitation.invitee_id))
+        .bind(invitation.status)
+        .bind(invitation.created_at)
+        .bind(invitation.expires_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?
+        .rows_affected()
+            > 0;
+
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(inserted)
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<RoomInvitation>, RepositoryError> {
+        let record = sqlx::query_as::<_, RoomInvitationRecord>(&format!(
+            "SELECT {ROOM_INVITATION_COLUMNS} FROM room_invitations WHERE id = $1"
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(RoomInvitation::from))
+    }
+
+    async fn list_pending_for_user(
+        &self,
+        user_id: UserId,
+        now: OffsetDateTime,
+    ) -> Result<Vec<RoomInvitation>, RepositoryError> {
+        let records = sqlx::query_as::<_, RoomInvitationRecord>(&format!(
+            r#"
+            SELECT {ROOM_INVITATION_COLUMNS} FROM room_invitations
+            WHERE invitee_id = $1 AND status = 'pending' AND expires_at > $2
+            ORDER BY created_at DESC
+            "#
+        ))
+        .bind(Uuid::from(user_id))
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(records.into_iter().map(RoomInvitation::from).collect())
+    }
+
+    async fn accept(
+        &self,
+        id: Uuid,
+        member: RoomMember,
+        now: OffsetDateTime,
+    ) -> Result<Option<RoomInvitation>, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        let record = sqlx::query_as::<_, RoomInvitationRecord>(&format!(
+            r#"
+            UPDATE room_invitations SET status = 'accepted', responded_at = $2
+            WHERE id = $1 AND status = 'pending' AND expires_at > $2
+            RETURNING {ROOM_INVITATION_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let Some(record) = record else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO room_members (room_id, user_id, role, joined_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (room_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::from(member.room_id))
+        .bind(Uuid::from(member.user_id))
+        .bind(&member.role)
+        .bind(member.joined_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(Some(RoomInvitation::from(record)))
+    }
+
+    async fn resolve(
+        &self,
+        id: Uuid,
+        status: InvitationStatus,
+        now: OffsetDateTime,
+    ) -> Result<Option<RoomInvitation>, RepositoryError> {
+        let record = sqlx::query_as::<_, RoomInvitationRecord>(&format!(
+            r#"
+            UPDATE room_invitations SET status = $2, responded_at = $3
+            WHERE id = $1 AND status = 'pending'
+            RETURNING {ROOM_INVITATION_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(status)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(RoomInvitation::from))
+    }
+}
+
+// 入房邀请链接的实现
+#[derive(Debug, FromRow)]
+struct RoomInviteRecord {
+    token: String,
+    room_id: Uuid,
+    creator_id: Uuid,
+    max_uses: Option<i32>,
+    used_count: i32,
+    expires_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+}
+
+impl From<RoomInviteRecord> for RoomInvite {
+    fn from(value: RoomInviteRecord) -> Self {
+        Self {
+            token: value.token,
+            room_id: RoomId::from(value.room_id),
+            creator_id: UserId::from(value.creator_id),
+            max_uses: value.max_uses,
+            used_count: value.used_count,
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+const ROOM_INVITE_COLUMNS: &str =
+    "token, room_id, creator_id, max_uses, used_count, expires_at, created_at";
+
+#[derive(Clone)]
+pub struct PgRoomInviteRepository {
+    pool: PgPool,
+}
+
+impl PgRoomInviteRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RoomInviteRepository for PgRoomInviteRepository {
+    async fn create(&self, invite: &RoomInvite) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO invites (token, room_id, creator_id, max_uses, used_count, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&invite.token)
+        .bind(Uuid::from(invite.room_id))
+        .bind(Uuid::from(invite.creator_id))
+        .bind(invite.max_uses)
+        .bind(invite.used_count)
+        .bind(invite.expires_at)
+        .bind(invite.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(())
+    }
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<RoomInvite>, RepositoryError> {
+        let record = sqlx::query_as::<_, RoomInviteRecord>(&format!(
+            "SELECT {ROOM_INVITE_COLUMNS} FROM invites WHERE token = $1"
+        ))
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(record.map(RoomInvite::from))
+    }
+
+    async fn redeem(
+        &self,
+        token: &str,
+        member: RoomMember,
+        now: OffsetDateTime,
+    ) -> Result<Option<RoomInvite>, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        // 先加入房间：已经是成员时回滚，不占用次数
+        let joined = sqlx::query(
+            r#"
+            INSERT INTO room_members (room_id, user_id, role, joined_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (room_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::from(member.room_id))
+        .bind(Uuid::from(member.user_id))
+        .bind(&member.role)
+        .bind(member.joined_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?
+        .rows_affected()
+            > 0;
+        if !joined {
+            return Err(RepositoryError::Conflict);
+        }
+
+        // 条件更新保证并发兑换时不会超过次数上限
+        let record = sqlx::query_as::<_, RoomInviteRecord>(&format!(
+            r#"
+            UPDATE invites SET used_count = used_count + 1
+            WHERE token = $1 AND room_id = $2
+                AND (expires_at IS NULL OR expires_at > $3)
+                AND (max_uses IS NULL OR used_count < max_uses)
+            RETURNING {ROOM_INVITE_COLUMNS}
+            "#
+        ))
+        .bind(token)
+        .bind(Uuid::from(member.room_id))
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let Some(record) = record else {
+            return Ok(None);
+        };
+
+        tx.commit().await.map_err(map_sqlx_err)?;
+        Ok(Some(RoomInvite::from(record)))
+    }
+}
+
+// FileUpload相关的实现
+#[derive(Debug, FromRow)]
+struct FileUploadRecord {
+    id: Uuid,
+    uploader_id: Uuid,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+    storage_key: String,
+    message_id: Option<Uuid>,
+    created_at: OffsetDateTime,
+}
+
+impl From<FileUploadRecord> for FileUpload {
+    fn from(value: FileUploadRecord) -> Self {
+        Self {
+            id: value.id,
+            uploader_id: UserId::from(value.uploader_id),
+            file_name: value.file_name,
+            content_type: value.content_type,
+            size_bytes: value.size_bytes,
+            storage_key: value.storage_key,
+            message_id: value.message_id.map(MessageId::from),
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgFileUploadRepository {
+    pool: PgPool,
+}
+
+impl PgFileUploadRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl FileUploadRepository for PgFileUploadRepository {
+    async fn create(&self, upload: FileUpload) -> Result<FileUpload, RepositoryError> {
+        let record = sqlx::query_as::<_, FileUploadRecord>(
+            r#"
+            INSERT INTO file_uploads (id, uploader_id, file_name, content_type, size_bytes, storage_key, message_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, uploader_id, file_name, content_type, size_bytes, storage_key, message_id, created_at
+            "#,
+        )
+        .bind(upload.id)
+        .bind(Uuid::from(upload.uploader_id))
+        .bind(&upload.file_name)
+        .bind(&upload.content_type)
+        .bind(upload.size_bytes)
+        .bind(&upload.storage_key)
+        .bind(upload.message_id.map(Uuid::from))
+        .bind(upload.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(FileUpload::from(record))
+    }
+
+    async fn find_unattached(
+        &self,
+        id: Uuid,
+        uploader_id: UserId,
+    ) -> Result<Option<FileUpload>, RepositoryError> {
+        let record = sqlx::query_as::<_, FileUploadRecord>(
+            r#"
+            SELECT id, uploader_id, file_name, content_type, size_bytes, storage_key, message_id, created_at
+            FROM file_uploads
+            WHERE id = $1 AND uploader_id = $2 AND message_id IS NULL
+            "#,
         )
-        .bind(Uuid::from(id))
+        .bind(id)
+        .bind(Uuid::from(uploader_id))
         .fetch_optional(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        record.map(Message::try_from).transpose()
+        Ok(record.map(FileUpload::from))
     }
 
-    // 增强的获取最近消息方法
-    async fn find_recent_by_room(
+    async fn attach(
         &self,
-        room_id: RoomId,
-        pagination: PaginationParams,
-        before: Option<MessageId>,
-    ) -> Result<Vec<Message>, RepositoryError> {
-        let records = if let Some(before_id) = before {
-            sqlx::query_as::<_, MessageRecord>(
-                r#"
-                SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-                FROM messages
-                WHERE room_id = $1 AND id < $2 AND is_deleted = FALSE
-                ORDER BY created_at DESC
-                LIMIT $3
-                "#,
-            )
-            .bind(Uuid::from(room_id))
-            .bind(Uuid::from(before_id))
-            .bind(pagination.limit)
-            .fetch_all(&self.pool)
-            .await
-        } else {
-            sqlx::query_as::<_, MessageRecord>(
-                r#"
-                SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-                FROM messages
-                WHERE room_id = $1 AND is_deleted = FALSE
-                ORDER BY created_at DESC
-                LIMIT $2
-                "#,
-            )
-            .bind(Uuid::from(room_id))
-            .bind(pagination.limit)
-            .fetch_all(&self.pool)
-            .await
-        }
+        id: Uuid,
+        uploader_id: UserId,
+        message_id: MessageId,
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE file_uploads SET message_id = $3
+             WHERE id = $1 AND uploader_id = $2 AND message_id IS NULL",
+        )
+        .bind(id)
+        .bind(Uuid::from(uploader_id))
+        .bind(Uuid::from(message_id))
+        .execute(&self.pool)
+        .await
         .map_err(map_sqlx_err)?;
 
-        records.into_iter().map(Message::try_from).collect()
+        Ok(result.rows_affected() > 0)
     }
 
-    // 新方法：获取指定时间之后的消息
-    async fn find_since_timestamp(
+    async fn find_visible(
         &self,
-        room_id: RoomId,
-        timestamp: chrono::DateTime<chrono::Utc>,
-    ) -> Result<Vec<Message>, RepositoryError> {
-        // 精确转换时间类型（保留纳秒精度）
-        let timestamp_nanos = timestamp
-            .timestamp_nanos_opt()
-            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
-
-        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
-            .map_err(|e| {
-                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
-            })?;
-
-        let records = sqlx::query_as::<_, MessageRecord>(
+        id: Uuid,
+        viewer_id: UserId,
+    ) -> Result<Option<FileUpload>, RepositoryError> {
+        let record = sqlx::query_as::<_, FileUploadRecord>(
             r#"
-            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-            FROM messages
-            WHERE room_id = $1 AND created_at > $2 AND is_deleted = FALSE
-            ORDER BY created_at ASC
+            SELECT f.id, f.uploader_id, f.file_name, f.content_type, f.size_bytes, f.storage_key, f.message_id, f.created_at
+            FROM file_uploads f
+            LEFT JOIN messages m ON m.id = f.message_id AND NOT m.is_deleted
+            WHERE f.id = $1
+              AND (f.uploader_id = $2
+                   OR EXISTS (
+                       SELECT 1 FROM room_members rm
+                       WHERE rm.room_id = m.room_id AND rm.user_id = $2
+                   ))
             "#,
         )
-        .bind(Uuid::from(room_id))
-        .bind(time_offset)
-        .fetch_all(&self.pool)
+        .bind(id)
+        .bind(Uuid::from(viewer_id))
+        .fetch_optional(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        records.into_iter().map(Message::try_from).collect()
+        Ok(record.map(FileUpload::from))
     }
 
-    // 管理员专用：按时间范围获取历史消息（包含已删除消息）
-    async fn find_by_time_range(
+    async fn delete_orphans(
         &self,
-        room_id: RoomId,
-        time_range: TimeRangeParams,
-        pagination: PaginationParams,
-    ) -> Result<Vec<Message>, RepositoryError> {
-        let query = if time_range.include_deleted {
-            r#"
-            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-            FROM messages
-            WHERE room_id = $1
-                AND ($2::timestamptz IS NULL OR created_at >= $2)
-                AND ($3::timestamptz IS NULL OR created_at <= $3)
-            ORDER BY created_at DESC
-            LIMIT $4
-            "#
-        } else {
+        before: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<FileUpload>, RepositoryError> {
+        let records = sqlx::query_as::<_, FileUploadRecord>(
             r#"
-            SELECT id, room_id, user_id, content, message_type, reply_to_message_id, created_at, updated_at, is_deleted
-            FROM messages
-            WHERE room_id = $1
-                AND ($2::timestamptz IS NULL OR created_at >= $2)
-                AND ($3::timestamptz IS NULL OR created_at <= $3)
-                AND is_deleted = FALSE
-            ORDER BY created_at DESC
-            LIMIT $4
-            "#
-        };
-
-        // 转换时间参数
-        let start_time = if let Some(timestamp) = time_range.start {
-            let timestamp_nanos = timestamp.timestamp_nanos_opt().ok_or_else(|| {
-                RepositoryError::storage("Start timestamp out of range".to_string())
-            })?;
-            Some(
-                time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128).map_err(
-                    |e| {
-                        RepositoryError::storage_with_source(
-                            "Invalid start timestamp".to_string(),
-                            e,
-                        )
-                    },
-                )?,
-            )
-        } else {
-            None
-        };
-
-        let end_time = if let Some(timestamp) = time_range.end {
-            let timestamp_nanos = timestamp.timestamp_nanos_opt().ok_or_else(|| {
-                RepositoryError::storage("End timestamp out of range".to_string())
-            })?;
-            Some(
-                time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128).map_err(
-                    |e| {
-                        RepositoryError::storage_with_source("Invalid end timestamp".to_string(), e)
-                    },
-                )?,
+            DELETE FROM file_uploads
+            WHERE id IN (
+                SELECT id FROM file_uploads
+                WHERE message_id IS NULL AND created_at < $1
+                ORDER BY created_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
             )
-        } else {
-            None
-        };
-
-        let records = sqlx::query_as::<_, MessageRecord>(query)
-            .bind(Uuid::from(room_id))
-            .bind(start_time)
-            .bind(end_time)
-            .bind(pagination.limit)
-            .fetch_all(&self.pool)
-            .await
-            .map_err(map_sqlx_err)?;
+            AND message_id IS NULL
+            RETURNING id, uploader_id, file_name, content_type, size_bytes, storage_key, message_id, created_at
+            "#,
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
 
-        records.into_iter().map(Message::try_from).collect()
+        Ok(records.into_iter().map(FileUpload::from).collect())
     }
-
-    // === 向后兼容方法 ===
-    // 注意：向后兼容方法已移动到 trait 的默认实现中
-}
-
-pub async fn create_pg_pool(
-    database_url: &str,
-    max_connections: u32,
-) -> Result<PgPool, sqlx::Error> {
-    PgPoolOptions::new()
-        .max_connections(max_connections)
-        .connect(database_url)
-        .await
 }
 
-// MessageDelivery相关的实现
-
 #[derive(Debug, FromRow)]
-struct MessageDeliveryRecord {
+struct ReactionSummaryRecord {
     message_id: Uuid,
-    user_id: Uuid,
-    sent_at: OffsetDateTime,
-    delivered_at: Option<OffsetDateTime>,
-}
-
-impl From<MessageDeliveryRecord> for MessageDelivery {
-    fn from(value: MessageDeliveryRecord) -> Self {
-        Self {
-            message_id: MessageId::from(value.message_id),
-            user_id: UserId::from(value.user_id),
-            sent_at: value.sent_at,
-            delivered_at: value.delivered_at,
-        }
-    }
+    emoji: String,
+    count: i64,
+    user_ids: Vec<Uuid>,
 }
 
 #[derive(Clone)]
-pub struct PgMessageDeliveryRepository {
+pub struct PgReactionRepository {
     pool: PgPool,
 }
 
-impl PgMessageDeliveryRepository {
+impl PgReactionRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
 }
 
 #[async_trait]
-impl MessageDeliveryRepository for PgMessageDeliveryRepository {
-    async fn record_sent(&self, delivery: MessageDelivery) -> Result<(), RepositoryError> {
-        sqlx::query(
+impl ReactionRepository for PgReactionRepository {
+    async fn add_reaction(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        emoji: &ReactionEmoji,
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
             r#"
-            INSERT INTO message_deliveries (message_id, user_id, sent_at, delivered_at)
-            VALUES ($1, $2, $3, $4)
-            ON CONFLICT (message_id, user_id) DO NOTHING
+            INSERT INTO message_reactions (message_id, user_id, emoji)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (message_id, user_id, emoji) DO NOTHING
             "#,
         )
-        .bind(Uuid::from(delivery.message_id))
-        .bind(Uuid::from(delivery.user_id))
-        .bind(delivery.sent_at)
-        .bind(delivery.delivered_at)
+        .bind(Uuid::from(message_id))
+        .bind(Uuid::from(user_id))
+        .bind(emoji.as_str())
         .execute(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn mark_delivered(
+    async fn remove_reaction(
         &self,
         message_id: MessageId,
         user_id: UserId,
-        delivered_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), RepositoryError> {
-        // 精确转换时间类型
-        let timestamp_nanos = delivered_at
-            .timestamp_nanos_opt()
-            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
-
-        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
-            .map_err(|e| {
-                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
-            })?;
-
-        sqlx::query(
-            r#"
-            UPDATE message_deliveries
-            SET delivered_at = $3
-            WHERE message_id = $1 AND user_id = $2 AND delivered_at IS NULL
-            "#,
+        emoji: &ReactionEmoji,
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
+            "DELETE FROM message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3",
         )
         .bind(Uuid::from(message_id))
         .bind(Uuid::from(user_id))
-        .bind(time_offset)
+        .bind(emoji.as_str())
         .execute(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn find_undelivered_for_user(
+    async fn find_user_reactions(
         &self,
+        message_id: MessageId,
         user_id: UserId,
-    ) -> Result<Vec<MessageDelivery>, RepositoryError> {
-        let records = sqlx::query_as::<_, MessageDeliveryRecord>(
-            r#"
-            SELECT message_id, user_id, sent_at, delivered_at
-            FROM message_deliveries
-            WHERE user_id = $1 AND delivered_at IS NULL
-            ORDER BY sent_at ASC
-            "#,
+    ) -> Result<Vec<ReactionEmoji>, RepositoryError> {
+        let emojis: Vec<String> = sqlx::query_scalar(
+            "SELECT emoji FROM message_reactions WHERE message_id = $1 AND user_id = $2 ORDER BY created_at",
         )
+        .bind(Uuid::from(message_id))
         .bind(Uuid::from(user_id))
         .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(records.into_iter().map(MessageDelivery::from).collect())
+        emojis
+            .into_iter()
+            .map(|emoji| ReactionEmoji::new(emoji).map_err(invalid_data))
+            .collect()
     }
 
-    async fn find_by_message(
+    async fn reactions_for_messages(
         &self,
-        message_id: MessageId,
-    ) -> Result<Vec<MessageDelivery>, RepositoryError> {
-        let records = sqlx::query_as::<_, MessageDeliveryRecord>(
+        message_ids: &[MessageId],
+    ) -> Result<HashMap<MessageId, Vec<ReactionSummary>>, RepositoryError> {
+        if message_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let ids: Vec<Uuid> = message_ids.iter().copied().map(Uuid::from).collect();
+        // 同一消息内按最早回应时间排序，保证 emoji 顺序稳定
+        let records = sqlx::query_as::<_, ReactionSummaryRecord>(
             r#"
-            SELECT message_id, user_id, sent_at, delivered_at
-            FROM message_deliveries
-            WHERE message_id = $1
-            ORDER BY sent_at ASC
+            SELECT message_id,
+                   emoji,
+                   COUNT(*) AS count,
+                   ARRAY_AGG(user_id ORDER BY created_at) AS user_ids
+            FROM message_reactions
+            WHERE message_id = ANY($1)
+            GROUP BY message_id, emoji
+            ORDER BY message_id, MIN(created_at), emoji
             "#,
         )
-        .bind(Uuid::from(message_id))
+        .bind(&ids)
         .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(records.into_iter().map(MessageDelivery::from).collect())
+        let mut reactions: HashMap<MessageId, Vec<ReactionSummary>> = HashMap::new();
+        for record in records {
+            reactions
+                .entry(MessageId::from(record.message_id))
+                .or_default()
+                .push(ReactionSummary {
+                    emoji: record.emoji,
+                    count: record.count,
+                    user_ids: record.user_ids.into_iter().map(UserId::from).collect(),
+                });
+        }
+
+        Ok(reactions)
     }
 
-    async fn cleanup_delivered_before(
+    async fn remove_batch_by_user(
         &self,
-        before: chrono::DateTime<chrono::Utc>,
-    ) -> Result<u64, RepositoryError> {
-        // 精确转换时间类型
-        let timestamp_nanos = before
-            .timestamp_nanos_opt()
-            .ok_or_else(|| RepositoryError::storage("Timestamp out of range".to_string()))?;
-
-        let time_offset = time::OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
-            .map_err(|e| {
-                RepositoryError::storage_with_source("Invalid timestamp".to_string(), e)
-            })?;
-
-        let result = sqlx::query(
+        user_id: UserId,
+        limit: i64,
+    ) -> Result<Vec<MessageId>, RepositoryError> {
+        let message_ids: Vec<Uuid> = sqlx::query_scalar(
             r#"
-            DELETE FROM message_deliveries
-            WHERE delivered_at IS NOT NULL AND delivered_at < $1
+            WITH batch AS (
+                SELECT message_id, emoji
+                FROM message_reactions
+                WHERE user_id = $1
+                LIMIT $2
+            )
+            DELETE FROM message_reactions r
+            USING batch
+            WHERE r.user_id = $1 AND r.message_id = batch.message_id AND r.emoji = batch.emoji
+            RETURNING r.message_id
             "#,
         )
-        .bind(time_offset)
-        .execute(&self.pool)
+        .bind(Uuid::from(user_id))
+        .bind(limit)
+        .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(result.rows_affected())
+        Ok(message_ids.into_iter().map(MessageId::from).collect())
     }
 }
 
-// Organization related types and implementations
-
 #[derive(Debug, FromRow)]
-struct OrganizationRecord {
+struct UserProfileRecord {
     id: Uuid,
-    name: String,
-    path: String,
-    metadata: Option<serde_json::Value>,
-    created_at: OffsetDateTime,
-    updated_at: OffsetDateTime,
+    username: String,
+    status: UserStatus,
+    display_name: Option<String>,
+    avatar_url: Option<String>,
+    bio: Option<String>,
 }
 
-impl TryFrom<OrganizationRecord> for Organization {
+impl TryFrom<UserProfileRecord> for UserProfile {
     type Error = RepositoryError;
 
-    fn try_from(value: OrganizationRecord) -> Result<Self, Self::Error> {
-        let org_path = domain::OrgPath::parse(value.path).map_err(invalid_data)?;
-
-        Ok(Organization {
-            id: OrgId::from(value.id),
-            name: value.name,
-            path: org_path,
-            metadata: value.metadata,
-            created_at: value.created_at,
-            updated_at: value.updated_at,
+    fn try_from(value: UserProfileRecord) -> Result<Self, Self::Error> {
+        Ok(UserProfile {
+            id: UserId::from(value.id),
+            username: domain::Username::parse(value.username).map_err(invalid_data)?,
+            status: value.status,
+            display_name: value.display_name,
+            avatar_url: value.avatar_url,
+            bio: value.bio,
         })
     }
 }
 
 #[derive(Clone)]
-pub struct PgOrganizationRepository {
+pub struct PgUserBlockRepository {
     pool: PgPool,
 }
 
-impl PgOrganizationRepository {
+impl PgUserBlockRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
 }
 
 #[async_trait]
-impl application::repository::OrganizationRepository for PgOrganizationRepository {
-    async fn create(&self, organization: &Organization) -> Result<(), RepositoryError> {
-        sqlx::query(
+impl UserBlockRepository for PgUserBlockRepository {
+    async fn block(&self, blocker: UserId, blocked: UserId) -> Result<bool, RepositoryError> {
+        let result = sqlx::query(
             r#"
-            INSERT INTO organizations (id, name, path, metadata, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO user_blocks (blocker_id, blocked_id)
+            VALUES ($1, $2)
+            ON CONFLICT (blocker_id, blocked_id) DO NOTHING
             "#,
         )
-        .bind(Uuid::from(organization.id))
-        .bind(&organization.name)
-        .bind(organization.path.to_string())
-        .bind(&organization.metadata)
-        .bind(organization.created_at)
-        .bind(organization.updated_at)
+        .bind(Uuid::from(blocker))
+        .bind(Uuid::from(blocked))
         .execute(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn find_by_id(&self, id: OrgId) -> Result<Option<Organization>, RepositoryError> {
-        let record = sqlx::query_as::<_, OrganizationRecord>(
-            r#"
-            SELECT id, name, path, metadata, created_at, updated_at
-            FROM organizations
-            WHERE id = $1
-            "#,
-        )
-        .bind(Uuid::from(id))
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(map_sqlx_err)?;
+    async fn unblock(&self, blocker: UserId, blocked: UserId) -> Result<bool, RepositoryError> {
+        let result =
+            sqlx::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
+                .bind(Uuid::from(blocker))
+                .bind(Uuid::from(blocked))
+                .execute(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
 
-        match record {
-            Some(record) => Ok(Some(Organization::try_from(record)?)),
-            None => Ok(None),
-        }
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn find_by_path(&self, path: &str) -> Result<Option<Organization>, RepositoryError> {
-        let record = sqlx::query_as::<_, OrganizationRecord>(
-            r#"
-            SELECT id, name, path, metadata, created_at, updated_at
-            FROM organizations
-            WHERE path = $1
-            "#,
-        )
-        .bind(path)
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(map_sqlx_err)?;
+    async fn blocked_ids(&self, blocker: UserId) -> Result<HashSet<UserId>, RepositoryError> {
+        let ids: Vec<Uuid> =
+            sqlx::query_scalar("SELECT blocked_id FROM user_blocks WHERE blocker_id = $1")
+                .bind(Uuid::from(blocker))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
 
-        match record {
-            Some(record) => Ok(Some(Organization::try_from(record)?)),
-            None => Ok(None),
-        }
+        Ok(ids.into_iter().map(UserId::from).collect())
     }
 
-    async fn find_children(&self, parent_path: &str) -> Result<Vec<Organization>, RepositoryError> {
-        let records = sqlx::query_as::<_, OrganizationRecord>(
+    async fn list_blocked(
+        &self,
+        blocker: UserId,
+        pagination: PaginationParams,
+    ) -> Result<Vec<UserProfile>, RepositoryError> {
+        let records = sqlx::query_as::<_, UserProfileRecord>(
             r#"
-            SELECT id, name, path, metadata, created_at, updated_at
-            FROM organizations
-            WHERE path <@ $1 AND nlevel(path) = nlevel($1) + 1
-            ORDER BY path
+            SELECT u.id, u.username, u.status, u.display_name, u.avatar_url, u.bio
+            FROM user_blocks b
+            JOIN users u ON u.id = b.blocked_id
+            WHERE b.blocker_id = $1
+            ORDER BY b.created_at DESC, u.id
+            LIMIT $2 OFFSET $3
             "#,
         )
-        .bind(format!("{}.*", parent_path))
+        .bind(Uuid::from(blocker))
+        .bind(pagination.limit)
+        .bind(pagination.offset.unwrap_or(0))
         .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        let mut organizations = Vec::new();
-        for record in records {
-            organizations.push(Organization::try_from(record)?);
-        }
+        records.into_iter().map(UserProfile::try_from).collect()
+    }
+}
 
-        Ok(organizations)
+#[derive(Clone)]
+pub struct PgMentionRepository {
+    pool: PgPool,
+}
+
+impl PgMentionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
     }
+}
 
-    async fn find_descendants(
+#[async_trait]
+impl MentionRepository for PgMentionRepository {
+    async fn record_mentions(
         &self,
-        ancestor_path: &str,
-    ) -> Result<Vec<Organization>, RepositoryError> {
-        let records = sqlx::query_as::<_, OrganizationRecord>(
+        message: &Message,
+        usernames: &[String],
+    ) -> Result<Vec<UserId>, RepositoryError> {
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 用户名解析和成员过滤在一条语句里完成，不是成员的名字自然被丢弃
+        let user_ids: Vec<Uuid> = sqlx::query_scalar(
             r#"
-            SELECT id, name, path, metadata, created_at, updated_at
-            FROM organizations
-            WHERE path <@ $1
-            ORDER BY path
+            INSERT INTO message_mentions (message_id, mentioned_user_id, room_id, created_at)
+            SELECT $1, u.id, $2, $3
+            FROM users u
+            JOIN room_members rm ON rm.user_id = u.id AND rm.room_id = $2
+            WHERE LOWER(u.username) = ANY($4) AND u.id <> $5
+            ON CONFLICT (message_id, mentioned_user_id) DO NOTHING
+            RETURNING mentioned_user_id
             "#,
         )
-        .bind(format!("{}.*", ancestor_path))
+        .bind(Uuid::from(message.id))
+        .bind(Uuid::from(message.room_id))
+        .bind(message.created_at)
+        .bind(usernames)
+        .bind(Uuid::from(message.sender_id))
         .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        let mut organizations = Vec::new();
-        for record in records {
-            organizations.push(Organization::try_from(record)?);
-        }
-
-        Ok(organizations)
+        Ok(user_ids.into_iter().map(UserId::from).collect())
     }
 
-    async fn update(&self, organization: &Organization) -> Result<(), RepositoryError> {
-        sqlx::query(
+    async fn record_everyone_mention(
+        &self,
+        message: &Message,
+    ) -> Result<Vec<UserId>, RepositoryError> {
+        let user_ids: Vec<Uuid> = sqlx::query_scalar(
             r#"
-            UPDATE organizations
-            SET name = $1, path = $2, metadata = $3, updated_at = $4
-            WHERE id = $5
+            INSERT INTO message_mentions (message_id, mentioned_user_id, room_id, created_at)
+            SELECT $1, rm.user_id, $2, $3
+            FROM room_members rm
+            WHERE rm.room_id = $2 AND rm.user_id <> $4
+            ON CONFLICT (message_id, mentioned_user_id) DO NOTHING
+            RETURNING mentioned_user_id
             "#,
         )
-        .bind(&organization.name)
-        .bind(organization.path.to_string())
-        .bind(&organization.metadata)
-        .bind(organization.updated_at)
-        .bind(Uuid::from(organization.id))
-        .execute(&self.pool)
+        .bind(Uuid::from(message.id))
+        .bind(Uuid::from(message.room_id))
+        .bind(message.created_at)
+        .bind(Uuid::from(message.sender_id))
+        .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        Ok(())
-    }
-
-    async fn delete(&self, id: OrgId) -> Result<(), RepositoryError> {
-        sqlx::query("DELETE FROM organizations WHERE id = $1")
-            .bind(Uuid::from(id))
-            .execute(&self.pool)
-            .await
-            .map_err(map_sqlx_err)?;
-
-        Ok(())
+        Ok(user_ids.into_iter().map(UserId::from).collect())
     }
 
-    async fn move_organization(
+    async fn find_for_user(
         &self,
-        id: OrgId,
-        new_parent_path: &str,
-    ) -> Result<(), RepositoryError> {
-        // 这个操作比较复杂，需要更新整个子树的path
-        // 首先获取当前组织信息
-        let org = self.find_by_id(id).await?;
-        let org = org.ok_or(RepositoryError::NotFound)?;
-
-        let _old_path_base = format!("{}.", org.path);
-        let new_path_base = format!("{}.{}", new_parent_path, org.name.to_lowercase());
-
-        // 开始事务
-        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
-
-        // 更新当前组织的path
-        sqlx::query("UPDATE organizations SET path = $1, updated_at = NOW() WHERE id = $2")
-            .bind(&new_path_base)
-            .bind(Uuid::from(id))
-            .execute(&mut *tx)
-            .await
-            .map_err(map_sqlx_err)?;
-
-        // 更新所有子组织的path
-        sqlx::query(
+        user_id: UserId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<PaginatedResult<MentionedMessage>, RepositoryError> {
+        let page = page.max(1);
+        let offset = (page as i64 - 1) * page_size as i64;
+
+        // 与 room_members 内连接：退出房间后提及随之消失；
+        // 已读以成员的 last_read_message 序列号为界
+        let records = sqlx::query_as::<_, MentionedMessageRecord>(
             r#"
-            UPDATE organizations
-            SET path = $1 || subpath(path, nlevel($2)) || '.' || subpath(path, nlevel($2) + 1),
-                updated_at = NOW()
-            WHERE path <@ $3
+            SELECT m.id, m.room_id, m.user_id, m.content, m.message_type, m.reply_to_message_id, m.thread_root_id, m.seq, m.created_at, m.updated_at, m.previous_content, m.version, m.is_deleted, m.encrypted,
+                   COALESCE(m.seq <= lr.seq, FALSE) AS is_read
+            FROM message_mentions mm
+            JOIN room_members rm ON rm.room_id = mm.room_id AND rm.user_id = mm.mentioned_user_id
+            JOIN messages m ON m.id = mm.message_id
+            LEFT JOIN messages lr ON lr.id = rm.last_read_message_id
+            WHERE mm.mentioned_user_id = $1 AND m.is_deleted = FALSE
+            ORDER BY mm.created_at DESC, m.id
+            LIMIT $2 OFFSET $3
             "#,
         )
-        .bind(format!("{}.", new_parent_path))
-        .bind(org.path.to_string())
-        .bind(format!("{}.*", org.path))
-        .execute(&mut *tx)
+        .bind(Uuid::from(user_id))
+        .bind(page_size as i64)
+        .bind(offset)
+        .fetch_all(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        // 提交事务
-        tx.commit().await.map_err(map_sqlx_err)?;
-
-        Ok(())
-    }
-
-    async fn path_exists(&self, path: &str) -> Result<bool, RepositoryError> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM organizations WHERE path = $1")
-            .bind(path)
-            .fetch_one(&self.pool)
-            .await
-            .map_err(map_sqlx_err)?;
-
-        Ok(count > 0)
-    }
-
-    async fn find_users_in_organization(
-        &self,
-        org_id: OrgId,
-    ) -> Result<Vec<User>, RepositoryError> {
-        let records = sqlx::query_as::<_, UserRecord>(
+        let total_count: i64 = sqlx::query_scalar(
             r#"
-            SELECT u.id, u.username, u.email, u.password_hash, u.status, u.is_superuser,
-                   u.org_id, u.created_at, u.updated_at
-            FROM users u
-            WHERE u.org_id = $1
-            ORDER BY u.username
+            SELECT COUNT(*)
+            FROM message_mentions mm
+            JOIN room_members rm ON rm.room_id = mm.room_id AND rm.user_id = mm.mentioned_user_id
+            JOIN messages m ON m.id = mm.message_id
+            WHERE mm.mentioned_user_id = $1 AND m.is_deleted = FALSE
             "#,
         )
-        .bind(Uuid::from(org_id))
-        .fetch_all(&self.pool)
+        .bind(Uuid::from(user_id))
+        .fetch_one(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
 
-        let mut users = Vec::new();
-        for record in records {
-            users.push(User::try_from(record)?);
-        }
-
-        Ok(users)
-    }
-
-    async fn list_with_pagination(
-        &self,
-        params: PaginationParams,
-    ) -> Result<Vec<Organization>, RepositoryError> {
-        let query = if let Some(offset) = params.offset {
-            sqlx::query_as::<_, OrganizationRecord>(
-                r#"
-                SELECT id, name, path, metadata, created_at, updated_at
-                FROM organizations
-                ORDER BY path
-                LIMIT $1 OFFSET $2
-                "#,
-            )
-            .bind(params.limit)
-            .bind(offset)
-        } else {
-            sqlx::query_as::<_, OrganizationRecord>(
-                r#"
-                SELECT id, name, path, metadata, created_at, updated_at
-                FROM organizations
-                ORDER BY path
-                LIMIT $1
-                "#,
-            )
-            .bind(params.limit)
-        };
-
-        let records = query.fetch_all(&self.pool).await.map_err(map_sqlx_err)?;
-
-        let mut organizations = Vec::new();
-        for record in records {
-            organizations.push(Organization::try_from(record)?);
-        }
-
-        Ok(organizations)
+        let items = records
+            .into_iter()
+            .map(|record| {
+                Ok(MentionedMessage {
+                    message: Message::try_from(record.message)?,
+                    is_read: record.is_read,
+                })
+            })
+            .collect::<Result<Vec<_>, RepositoryError>>()?;
+
+        Ok(PaginatedResult {
+            items,
+            total_count,
+            page,
+            page_size,
+        })
     }
 }
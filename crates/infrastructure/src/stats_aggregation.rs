@@ -1,6 +1,8 @@
+use std::collections::{BTreeMap, HashMap};
+
 use application::ApplicationError;
-use chrono::{Datelike, Duration, Timelike};
-use domain::RoomId;
+use chrono::{Datelike, Duration, Months, Timelike};
+use domain::{RoomId, UserId};
 use sqlx::{
     types::chrono::{DateTime, NaiveDate, Utc},
     PgPool, Row,
@@ -10,7 +12,7 @@ use uuid::Uuid;
 use crate::repository::map_sqlx_err;
 
 /// 时间粒度枚举
-#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub enum TimeGranularity {
     Hour,
@@ -47,6 +49,71 @@ impl std::str::FromStr for TimeGranularity {
     }
 }
 
+impl TimeGranularity {
+    /// 时间所在桶的起点（周从周一开始）
+    pub fn bucket_start(self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeGranularity::Hour => ts
+                .with_minute(0)
+                .unwrap()
+                .with_second(0)
+                .unwrap()
+                .with_nanosecond(0)
+                .unwrap(),
+            TimeGranularity::Day => ts.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            TimeGranularity::Week => {
+                let days_since_monday = ts.weekday().num_days_from_monday();
+                (ts - Duration::days(days_since_monday as i64))
+                    .date_naive()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            }
+            TimeGranularity::Month => ts
+                .date_naive()
+                .with_day(1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc(),
+            TimeGranularity::Year => ts
+                .date_naive()
+                .with_month(1)
+                .unwrap()
+                .with_day(1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc(),
+        }
+    }
+
+    /// 下一个桶的起点
+    pub fn next_bucket(self, bucket_start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeGranularity::Hour => bucket_start + Duration::hours(1),
+            TimeGranularity::Day => bucket_start + Duration::days(1),
+            TimeGranularity::Week => bucket_start + Duration::weeks(1),
+            TimeGranularity::Month => bucket_start + Months::new(1),
+            TimeGranularity::Year => bucket_start + Months::new(12),
+        }
+    }
+}
+
+/// 增量聚合只处理这么多秒之前写入的事件，等进行中的写入事务提交
+const INGESTION_SETTLE_SECS: f64 = 30.0;
+
+/// 一次增量聚合的结果
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalAggregation {
+    /// 本次写入的聚合统计
+    pub stats: Vec<RoomStats>,
+    /// 本次聚合的事件数
+    pub events_processed: i64,
+    /// 聚合之后的水位线（事件写入时间）；一个事件都没有时为 `None`
+    pub watermark: Option<DateTime<Utc>>,
+}
+
 /// 房间统计数据
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct RoomStats {
@@ -80,6 +147,93 @@ pub struct OnlineStatsSummary {
     pub peak_concurrent_users: i64,
 }
 
+/// 用户在某个时间桶内的在线时长
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct UserOnlineTime {
+    pub user_id: UserId,
+    pub time_bucket: DateTime<Utc>,
+    pub granularity: TimeGranularity,
+    pub online_seconds: i64,
+}
+
+/// 会话的连接/断开事件，心跳不参与在线时长计算
+#[derive(Debug, Clone, Copy)]
+struct SessionEvent {
+    user_id: Uuid,
+    session_id: Uuid,
+    connected: bool,
+    timestamp: DateTime<Utc>,
+}
+
+/// 一段在线时间：(上线时间, 下线时间)
+type OnlineSpan = (DateTime<Utc>, DateTime<Utc>);
+
+/// 按会话配对 Connected/Disconnected，得到每个用户合并后的在线区间
+///
+/// 事件需按时间排序。窗口内只有 Connected 的会话算到窗口结束；
+/// 没有 Connected 的 Disconnected 直接忽略。同一用户多个会话重叠的时间只算一次。
+fn online_intervals(
+    events: &[SessionEvent],
+    window_end: DateTime<Utc>,
+) -> HashMap<Uuid, Vec<OnlineSpan>> {
+    let mut open: HashMap<(Uuid, Uuid), DateTime<Utc>> = HashMap::new();
+    let mut intervals: HashMap<Uuid, Vec<OnlineSpan>> = HashMap::new();
+
+    for event in events {
+        let key = (event.user_id, event.session_id);
+        if event.connected {
+            // 重复的 Connected 以最早的为准
+            open.entry(key).or_insert(event.timestamp);
+        } else if let Some(connected_at) = open.remove(&key) {
+            intervals
+                .entry(event.user_id)
+                .or_default()
+                .push((connected_at, event.timestamp));
+        }
+    }
+
+    for ((user_id, _), connected_at) in open {
+        intervals
+            .entry(user_id)
+            .or_default()
+            .push((connected_at, window_end));
+    }
+
+    for spans in intervals.values_mut() {
+        spans.sort();
+        let mut merged: Vec<OnlineSpan> = Vec::with_capacity(spans.len());
+        for &(start, end) in spans.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *spans = merged;
+    }
+
+    intervals
+}
+
+/// 把在线区间切分到时间桶，返回每个桶的在线秒数
+fn split_into_buckets(
+    granularity: TimeGranularity,
+    spans: &[(DateTime<Utc>, DateTime<Utc>)],
+) -> BTreeMap<DateTime<Utc>, i64> {
+    let mut buckets = BTreeMap::new();
+    for &(start, end) in spans {
+        let mut bucket = granularity.bucket_start(start);
+        while bucket < end {
+            let next = granularity.next_bucket(bucket);
+            let overlap = end.min(next) - start.max(bucket);
+            if overlap > Duration::zero() {
+                *buckets.entry(bucket).or_insert(0) += overlap.num_seconds();
+            }
+            bucket = next;
+        }
+    }
+    buckets
+}
+
 /// PostgreSQL统计聚合服务
 ///
 /// 负责：
@@ -98,135 +252,133 @@ impl StatsAggregationService {
     }
 
     /// 增量聚合统计 - Linus式简单直接的解决方案
-    /// 只处理上次聚合后的新增事件，避免重复计算
+    ///
+    /// 水位线按事件写入时间（`ingested_at`）推进，而不是事件自身的时间戳：消费者积压、
+    /// 暂停后恢复或从死信重新注入的事件带着旧时间戳晚到，下一次聚合同样能看到。
+    /// 找出水位线之后写入的事件落在哪些 (房间, 桶)，按桶内全部事件重新计算并覆盖旧结果；
+    /// 进行中的桶先得到部分结果，之后再有事件写入时重算。聚合结果和新水位线在同一事务里写入
     pub async fn incremental_aggregate_stats(
         &self,
         granularity: TimeGranularity,
-    ) -> Result<Vec<RoomStats>, ApplicationError> {
-        // 1. 获取上次处理的时间戳
-        let last_processed = self.get_last_processed_timestamp(granularity).await?;
+    ) -> Result<IncrementalAggregation, ApplicationError> {
+        // 1. 获取水位线；一个事件都没有时无事可做
+        let Some(since) = self.watermark(granularity).await? else {
+            return Ok(IncrementalAggregation::default());
+        };
+
+        // 2. 写入时间在插入时确定，事务可能稍后才提交，只处理留出余量之前写入的事件；
+        //    用数据库时钟，和 ingested_at 的默认值同一个时钟
+        let until: DateTime<Utc> = sqlx::query_scalar("SELECT NOW() - make_interval(secs => $1)")
+            .bind(INGESTION_SETTLE_SECS)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(map_sqlx_err)?;
+        if since >= until {
+            return Ok(IncrementalAggregation {
+                watermark: Some(since),
+                ..IncrementalAggregation::default()
+            });
+        }
 
         tracing::info!(
             granularity = ?granularity,
-            last_processed = %last_processed,
+            since = %since,
+            until = %until,
             "Starting incremental aggregation"
         );
 
-        // 2. 计算当前时间的桶边界
-        let now = Utc::now();
-        let bucket_start = match granularity {
-            TimeGranularity::Hour => now
-                .with_minute(0)
-                .unwrap()
-                .with_second(0)
-                .unwrap()
-                .with_nanosecond(0)
-                .unwrap(),
-            TimeGranularity::Day => now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            TimeGranularity::Week => {
-                let days_since_monday = now.weekday().num_days_from_monday();
-                (now - Duration::days(days_since_monday as i64))
-                    .date_naive()
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap()
-                    .and_utc()
-            }
-            TimeGranularity::Month => now
-                .date_naive()
-                .with_day(1)
-                .unwrap()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_utc(),
-            TimeGranularity::Year => now
-                .date_naive()
-                .with_month(1)
-                .unwrap()
-                .with_day(1)
-                .unwrap()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_utc(),
-        };
-
         // 3. 只计算增量数据
         let incremental_stats = self
-            .calculate_incremental_stats(granularity, last_processed, bucket_start)
+            .calculate_incremental_stats(granularity, since, until)
             .await?;
+        let events_processed: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM presence_events WHERE ingested_at >= $1 AND ingested_at < $2",
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
 
         // 4. 与现有聚合数据合并
         let merged_stats = self
             .merge_with_existing_stats(incremental_stats, granularity)
             .await?;
 
-        // 5. 更新处理时间戳
-        self.update_last_processed_timestamp(granularity, bucket_start)
-            .await?;
+        // 5. 推进水位线并保存结果；水位线已被其他实例推进时放弃本次结果，避免重复写入
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        let advanced = sqlx::query(
+            r#"
+            INSERT INTO aggregation_watermarks (granularity, watermark)
+            VALUES ($1::time_granularity, $3)
+            ON CONFLICT (granularity) DO UPDATE SET
+                watermark = EXCLUDED.watermark,
+                updated_at = NOW()
+            WHERE aggregation_watermarks.watermark = $2
+            "#,
+        )
+        .bind(granularity.to_string())
+        .bind(since)
+        .bind(until)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?
+        .rows_affected();
+        if advanced == 0 {
+            tracing::warn!(
+                granularity = ?granularity,
+                since = %since,
+                "Watermark moved concurrently, discarding incremental aggregation"
+            );
+            return Ok(IncrementalAggregation {
+                watermark: self.watermark(granularity).await?,
+                ..IncrementalAggregation::default()
+            });
+        }
+        Self::insert_stats(&mut tx, &merged_stats).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
 
         tracing::info!(
             stats_count = merged_stats.len(),
-            new_last_processed = %bucket_start,
+            events_processed,
+            new_watermark = %until,
             "Incremental aggregation completed"
         );
 
-        Ok(merged_stats)
-    }
-
-    /// 获取上次处理的时间戳
-    async fn get_last_processed_timestamp(
-        &self,
-        granularity: TimeGranularity,
-    ) -> Result<DateTime<Utc>, ApplicationError> {
-        let key = format!("last_processed_{}", granularity.to_string().to_lowercase());
-
-        let row = sqlx::query("SELECT value FROM stats_config WHERE key = $1")
-            .bind(&key)
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(map_sqlx_err)?;
-
-        if let Some(row) = row {
-            let timestamp_str: String = row.get("value");
-            timestamp_str
-                .parse::<DateTime<Utc>>()
-                .map_err(|e| ApplicationError::infrastructure(format!("Invalid timestamp: {}", e)))
-        } else {
-            // 如果没有记录，返回30天前（初始运行）
-            Ok(Utc::now() - Duration::days(30))
-        }
+        Ok(IncrementalAggregation {
+            stats: merged_stats,
+            events_processed,
+            watermark: Some(until),
+        })
     }
 
-    /// 更新最后处理时间戳
-    async fn update_last_processed_timestamp(
+    /// 增量聚合的水位线：该粒度已经聚合到的事件写入时间（不含）
+    ///
+    /// 还没有记录时从最早写入的事件开始；一个事件都没有时返回 `None`
+    pub async fn watermark(
         &self,
         granularity: TimeGranularity,
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), ApplicationError> {
-        let key = format!("last_processed_{}", granularity.to_string().to_lowercase());
-
-        sqlx::query(
-            r#"
-            INSERT INTO stats_config (key, value, description)
-            VALUES ($1, $2, $3)
-            ON CONFLICT (key) DO UPDATE SET
-                value = EXCLUDED.value,
-                updated_at = NOW()
-            "#,
+    ) -> Result<Option<DateTime<Utc>>, ApplicationError> {
+        let stored: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT watermark FROM aggregation_watermarks WHERE granularity = $1::time_granularity",
         )
-        .bind(&key)
-        .bind(timestamp.to_rfc3339())
-        .bind(format!(
-            "Last processed timestamp for {} granularity",
-            granularity
-        ))
-        .execute(&self.pool)
+        .bind(granularity.to_string())
+        .fetch_optional(&self.pool)
         .await
         .map_err(map_sqlx_err)?;
+        if stored.is_some() {
+            return Ok(stored);
+        }
 
-        Ok(())
+        let earliest: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT MIN(ingested_at) FROM presence_events")
+                .fetch_one(&self.pool)
+                .await
+                .map_err(map_sqlx_err)?;
+        Ok(earliest)
     }
 
-    /// 计算增量统计数据（只处理新事件）
+    /// 计算增量统计数据：写入时间在 `[since, until)` 内的事件涉及的 (房间, 桶)，按桶内全部事件计算
     async fn calculate_incremental_stats(
         &self,
         granularity: TimeGranularity,
@@ -247,16 +399,24 @@ impl StatsAggregationService {
 
         let sql = format!(
             r#"
-            WITH time_buckets AS (
-                SELECT
-                    room_id,
-                    date_trunc('{}', timestamp) as time_bucket,
-                    user_id,
-                    session_id,
-                    event_type,
-                    timestamp
+            WITH touched AS (
+                SELECT DISTINCT room_id, date_trunc('{unit}', timestamp) as time_bucket
                 FROM presence_events
-                WHERE timestamp > $1 AND timestamp <= $2
+                WHERE ingested_at >= $1 AND ingested_at < $2
+            ),
+            time_buckets AS (
+                SELECT
+                    e.room_id,
+                    t.time_bucket,
+                    e.user_id,
+                    e.session_id,
+                    e.event_type,
+                    e.timestamp
+                FROM touched t
+                JOIN presence_events e
+                    ON e.room_id = t.room_id
+                    AND e.timestamp >= t.time_bucket
+                    AND e.timestamp < t.time_bucket + INTERVAL '1 {unit}'
             ),
             session_durations AS (
                 SELECT
@@ -294,7 +454,7 @@ impl StatsAggregationService {
             FROM incremental_stats
             ORDER BY room_id, time_bucket
             "#,
-            match granularity {
+            unit = match granularity {
                 TimeGranularity::Hour => "hour",
                 TimeGranularity::Day => "day",
                 TimeGranularity::Week => "week",
@@ -460,7 +620,22 @@ impl StatsAggregationService {
         }
 
         let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        Self::insert_stats(&mut tx, stats).await?;
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        tracing::info!(
+            stats_count = stats.len(),
+            "Successfully saved aggregated statistics"
+        );
+
+        Ok(())
+    }
 
+    /// 在调用方的事务里写入聚合统计，同一桶已有的结果被覆盖
+    async fn insert_stats(
+        conn: &mut sqlx::PgConnection,
+        stats: &[RoomStats],
+    ) -> Result<(), ApplicationError> {
         for stat in stats {
             let granularity_str = stat.granularity.to_string();
             sqlx::query(
@@ -468,7 +643,7 @@ impl StatsAggregationService {
                 INSERT INTO stats_aggregated (
                     room_id, time_bucket, granularity, peak_online_count,
                     avg_online_count, total_connections, unique_users, avg_session_duration
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ) VALUES ($1, $2, $3::time_granularity, $4, $5, $6, $7, $8)
                 ON CONFLICT (room_id, time_bucket, granularity)
                 DO UPDATE SET
                     peak_online_count = EXCLUDED.peak_online_count,
@@ -486,18 +661,11 @@ impl StatsAggregationService {
             .bind(stat.total_connections)
             .bind(stat.unique_users)
             .bind(stat.avg_session_duration)
-            .execute(&mut *tx)
+            .execute(&mut *conn)
             .await
             .map_err(map_sqlx_err)?;
         }
 
-        tx.commit().await.map_err(map_sqlx_err)?;
-
-        tracing::info!(
-            stats_count = stats.len(),
-            "Successfully saved aggregated statistics"
-        );
-
         Ok(())
     }
 
@@ -624,6 +792,121 @@ impl StatsAggregationService {
         })
     }
 
+    /// 从原始事件计算用户在线时长并写入 user_online_time
+    ///
+    /// 窗口 [start_time, end_time) 应与时间桶对齐：重算时窗口内的旧结果会被整体替换。
+    pub async fn aggregate_user_online_time(
+        &self,
+        granularity: TimeGranularity,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<UserOnlineTime>, ApplicationError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user_id, session_id, event_type = 'Connected' AS connected, timestamp
+            FROM presence_events
+            WHERE timestamp >= $1 AND timestamp < $2
+              AND event_type IN ('Connected', 'Disconnected')
+            ORDER BY timestamp, event_type
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        let events: Vec<SessionEvent> = rows
+            .iter()
+            .map(|row| SessionEvent {
+                user_id: row.get("user_id"),
+                session_id: row.get("session_id"),
+                connected: row.get("connected"),
+                timestamp: row.get("timestamp"),
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for (user_id, spans) in online_intervals(&events, end_time) {
+            for (time_bucket, online_seconds) in split_into_buckets(granularity, &spans) {
+                results.push(UserOnlineTime {
+                    user_id: UserId::from(user_id),
+                    time_bucket,
+                    granularity,
+                    online_seconds,
+                });
+            }
+        }
+        results.sort_by_key(|item| (Uuid::from(item.user_id), item.time_bucket));
+
+        let granularity_str = granularity.to_string();
+        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+        sqlx::query(
+            "DELETE FROM user_online_time
+             WHERE granularity = $1::time_granularity AND time_bucket >= $2 AND time_bucket < $3",
+        )
+        .bind(&granularity_str)
+        .bind(start_time)
+        .bind(end_time)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        for item in &results {
+            sqlx::query(
+                r#"
+                INSERT INTO user_online_time (user_id, time_bucket, granularity, online_seconds)
+                VALUES ($1, $2, $3::time_granularity, $4)
+                ON CONFLICT (user_id, granularity, time_bucket)
+                DO UPDATE SET online_seconds = EXCLUDED.online_seconds
+                "#,
+            )
+            .bind(Uuid::from(item.user_id))
+            .bind(item.time_bucket)
+            .bind(&granularity_str)
+            .bind(item.online_seconds)
+            .execute(&mut *tx)
+            .await
+            .map_err(map_sqlx_err)?;
+        }
+
+        tx.commit().await.map_err(map_sqlx_err)?;
+
+        tracing::info!(
+            granularity = ?granularity,
+            rows = results.len(),
+            "User online time aggregated"
+        );
+
+        Ok(results)
+    }
+
+    /// 用户在时间范围内的在线总秒数，按小时粒度的聚合结果累加
+    pub async fn user_online_seconds(
+        &self,
+        user_id: UserId,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<i64, ApplicationError> {
+        let total: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT SUM(online_seconds)::BIGINT
+            FROM user_online_time
+            WHERE user_id = $1 AND granularity = 'Hour'
+              AND time_bucket >= $2 AND time_bucket < $3
+            "#,
+        )
+        .bind(Uuid::from(user_id))
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+        Ok(total.unwrap_or(0))
+    }
+
     /// 创建分区表（按月分区）
     /// 用于确保新月份的分区存在
     pub async fn create_partition_if_not_exists(
@@ -884,9 +1167,8 @@ impl StatsAggregationService {
 
     /// 执行增量聚合流水线（推荐使用）
     ///
-    /// 1. 计算增量统计（只处理新事件）
-    /// 2. 保存到数据库
-    /// 3. 清理过期数据
+    /// 1. 计算增量统计（只重算水位线之后写入的事件涉及的桶），与新水位线一起保存
+    /// 2. 清理过期数据
     pub async fn run_incremental_aggregation_pipeline(
         &self,
         granularity: TimeGranularity,
@@ -896,14 +1178,11 @@ impl StatsAggregationService {
             "Starting incremental aggregation pipeline"
         );
 
-        // 1. 计算增量聚合统计
-        let stats = self.incremental_aggregate_stats(granularity).await?;
-        let stats_count = stats.len();
-
-        // 2. 保存统计数据
-        self.save_aggregated_stats(&stats).await?;
+        // 1. 计算并保存增量聚合统计
+        let aggregation = self.incremental_aggregate_stats(granularity).await?;
+        let stats_count = aggregation.stats.len();
 
-        // 3. 清理过期数据（定期执行，不用每次都执行）
+        // 2. 清理过期数据（定期执行，不用每次都执行）
         let _deleted_count = self.cleanup_expired_data().await?;
 
         tracing::info!(
@@ -917,6 +1196,8 @@ impl StatsAggregationService {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[tokio::test]
     #[ignore] // 需要数据库连接
     async fn test_aggregate_stats() {
@@ -928,4 +1209,65 @@ mod tests {
     async fn test_query_stats() {
         // 测试统计查询
     }
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        "2024-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minute)
+    }
+
+    fn event(user_id: Uuid, session_id: Uuid, connected: bool, minute: i64) -> SessionEvent {
+        SessionEvent {
+            user_id,
+            session_id,
+            connected,
+            timestamp: at(minute),
+        }
+    }
+
+    #[test]
+    fn test_unmatched_connected_counts_until_window_end() {
+        let user_id = Uuid::new_v4();
+        let events = [event(user_id, Uuid::new_v4(), true, 100)];
+
+        let intervals = online_intervals(&events, at(120));
+        assert_eq!(intervals[&user_id], [(at(100), at(120))]);
+    }
+
+    #[test]
+    fn test_unmatched_disconnected_is_ignored() {
+        let user_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        let events = [
+            event(user_id, Uuid::new_v4(), false, 5),
+            event(user_id, session_id, true, 10),
+            event(user_id, session_id, false, 40),
+        ];
+
+        let intervals = online_intervals(&events, at(120));
+        assert_eq!(intervals[&user_id], [(at(10), at(40))]);
+    }
+
+    #[test]
+    fn test_overlapping_sessions_counted_once() {
+        let user_id = Uuid::new_v4();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let events = [
+            event(user_id, first, true, 0),
+            event(user_id, second, true, 20),
+            event(user_id, first, false, 30),
+            event(user_id, second, false, 50),
+        ];
+
+        let intervals = online_intervals(&events, at(120));
+        assert_eq!(intervals[&user_id], [(at(0), at(50))]);
+    }
+
+    #[test]
+    fn test_split_into_hour_buckets() {
+        let buckets = split_into_buckets(TimeGranularity::Hour, &[(at(30), at(100))]);
+
+        assert_eq!(
+            buckets.into_iter().collect::<Vec<_>>(),
+            [(at(0), 30 * 60), (at(60), 40 * 60)]
+        );
+    }
 }
@@ -0,0 +1,103 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use application::{ByteStream, ObjectStorage, StorageError};
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// 本地文件系统上的对象存储，键就是相对于根目录的路径
+///
+/// 只适用于单实例部署或挂载了共享目录的多实例部署；不能生成直接下载地址，下载由 API 转发
+#[derive(Debug, Clone)]
+pub struct LocalObjectStorage {
+    root: PathBuf,
+}
+
+impl LocalObjectStorage {
+    /// 根目录不存在时创建
+    pub async fn new(root: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let root = root.into();
+        tokio::fs::create_dir_all(&root).await?;
+        Ok(Self { root })
+    }
+
+    /// 键只能由普通路径段组成，不能跳出根目录
+    fn path_of(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let is_plain = !key.is_empty()
+            && !key.contains('\\')
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !is_plain {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for LocalObjectStorage {
+    async fn put(&self, key: &str, mut body: ByteStream) -> Result<u64, StorageError> {
+        let path = self.path_of(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // 先写临时文件，写完再改名，读的人不会看到写了一半的文件
+        let temp_path = path.with_extension(format!("{}.part", Uuid::new_v4().simple()));
+        let write = async {
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            let mut written = 0u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                written += chunk.len() as u64;
+            }
+            file.sync_all().await?;
+            tokio::fs::rename(&temp_path, &path).await?;
+            Ok::<_, std::io::Error>(written)
+        };
+
+        match write.await {
+            Ok(written) => Ok(written),
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                Err(err.into())
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<ByteStream, StorageError> {
+        let path = self.path_of(key)?;
+        let file = tokio::fs::File::open(&path).await.map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                StorageError::NotFound(key.to_string())
+            } else {
+                err.into()
+            }
+        })?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_of(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn presign(
+        &self,
+        key: &str,
+        _expires_in: Duration,
+    ) -> Result<Option<String>, StorageError> {
+        self.path_of(key)?;
+        Ok(None)
+    }
+}
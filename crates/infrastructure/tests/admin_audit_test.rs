@@ -0,0 +1,271 @@
+//! 管理操作审计测试
+//!
+//! 验证：踢人、调整角色、删除房间、授予/撤销系统管理员都和操作本身一起写入审计日志；
+//! 审计日志可按操作人、操作类型和时间检索，只有系统管理员能查看
+mod support;
+
+use application::{
+    repository::AuditLogQuery,
+    services::{
+        ChatService, CreateRoomRequest, DeleteRoomRequest, JoinRoomRequest, RemoveMemberRequest,
+        UpdateMemberRoleRequest,
+    },
+    ApplicationError, Clock, SystemClock,
+};
+use domain::{AuditLogEntry, ChatRoomVisibility, DomainError, RoomId, RoomRole, UserId};
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{chat_service, create_superuser, create_user, setup_test_db};
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("audit-{}", &member_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+/// 某个操作人的某类审计记录
+fn query(actor_id: Uuid, action: &str) -> AuditLogQuery {
+    AuditLogQuery {
+        actor_id: Some(UserId::from(actor_id)),
+        action: Some(action.to_string()),
+        from: None,
+        to: None,
+        page: 1,
+        page_size: 20,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_kicking_member_writes_audit_row() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let admin_id = create_superuser(&pool).await;
+    let room_id = create_room(&service, owner_id, member_id).await;
+
+    service
+        .remove_member(RemoveMemberRequest {
+            room_id,
+            operator_id: owner_id,
+            target_user_id: member_id,
+        })
+        .await
+        .unwrap();
+
+    let (action, target_user_id, target_room_id, details): (
+        String,
+        Option<Uuid>,
+        Option<Uuid>,
+        serde_json::Value,
+    ) = sqlx::query_as(
+        "SELECT action, target_user_id, target_room_id, details
+         FROM admin_audit_log WHERE actor_id = $1",
+    )
+    .bind(owner_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(action, AuditLogEntry::MEMBER_REMOVED);
+    assert_eq!(target_user_id, Some(member_id));
+    assert_eq!(target_room_id, Some(room_id));
+    assert_eq!(details, json!({ "role": "Member" }));
+
+    // 系统管理员可以按操作人和操作类型检索到这条记录
+    let page = service
+        .search_audit_log(admin_id, query(owner_id, AuditLogEntry::MEMBER_REMOVED))
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 1);
+    assert_eq!(page.items[0].target_room_id, Some(RoomId::from(room_id)));
+
+    // 踢人失败（目标已不在房间）时不留审计记录
+    let err = service
+        .remove_member(RemoveMemberRequest {
+            room_id,
+            operator_id: owner_id,
+            target_user_id: member_id,
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::UserNotInRoom)
+    ));
+    let page = service
+        .search_audit_log(admin_id, query(owner_id, AuditLogEntry::MEMBER_REMOVED))
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_role_change_and_room_deletion_are_audited() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let admin_id = create_superuser(&pool).await;
+    let room_id = create_room(&service, owner_id, member_id).await;
+
+    service
+        .update_member_role(UpdateMemberRoleRequest {
+            room_id,
+            operator_id: owner_id,
+            target_user_id: member_id,
+            role: RoomRole::Admin,
+        })
+        .await
+        .unwrap();
+    let page = service
+        .search_audit_log(
+            admin_id,
+            query(owner_id, AuditLogEntry::MEMBER_ROLE_CHANGED),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 1);
+    assert_eq!(
+        page.items[0].details,
+        json!({ "old_role": "Member", "new_role": "Admin" })
+    );
+
+    service
+        .delete_room(DeleteRoomRequest {
+            room_id,
+            operator_id: owner_id,
+        })
+        .await
+        .unwrap();
+    let page = service
+        .search_audit_log(admin_id, query(owner_id, AuditLogEntry::ROOM_DELETED))
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 1);
+    assert_eq!(page.items[0].target_room_id, Some(RoomId::from(room_id)));
+    assert_eq!(
+        page.items[0].details["name"],
+        format!("audit-{}", &member_id.to_string()[0..8])
+    );
+
+    // 不带条件时按时间倒序，最近的删除房间在前
+    let page = service
+        .search_audit_log(
+            admin_id,
+            AuditLogQuery {
+                action: None,
+                ..query(owner_id, "")
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 2);
+    assert_eq!(page.items[0].action, AuditLogEntry::ROOM_DELETED);
+    assert_eq!(page.items[1].action, AuditLogEntry::MEMBER_ROLE_CHANGED);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_superuser_grant_and_revoke_are_audited() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let admin_id = create_superuser(&pool).await;
+    let user_id = create_user(&pool).await;
+    let started_at = SystemClock.now();
+
+    // 普通用户既不能授权，也不能查看审计日志
+    let err = service
+        .set_superuser(user_id, admin_id, false)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InsufficientPermissions)
+    ));
+    let err = service
+        .search_audit_log(user_id, query(admin_id, AuditLogEntry::SUPERUSER_GRANTED))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InsufficientPermissions)
+    ));
+
+    let user = service
+        .set_superuser(admin_id, user_id, true)
+        .await
+        .unwrap();
+    assert!(user.is_system_admin());
+    // 重复授权不再记录
+    service
+        .set_superuser(admin_id, user_id, true)
+        .await
+        .unwrap();
+    let user = service
+        .set_superuser(admin_id, user_id, false)
+        .await
+        .unwrap();
+    assert!(!user.is_system_admin());
+
+    let page = service
+        .search_audit_log(
+            admin_id,
+            AuditLogQuery {
+                action: None,
+                from: Some(started_at),
+                ..query(admin_id, "")
+            },
+        )
+        .await
+        .unwrap();
+    let actions: Vec<_> = page
+        .items
+        .iter()
+        .map(|entry| entry.action.as_str())
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            AuditLogEntry::SUPERUSER_REVOKED,
+            AuditLogEntry::SUPERUSER_GRANTED
+        ]
+    );
+    assert!(page
+        .items
+        .iter()
+        .all(|entry| entry.target_user_id == Some(UserId::from(user_id))));
+
+    // 时间范围之外没有记录
+    let page = service
+        .search_audit_log(
+            admin_id,
+            AuditLogQuery {
+                action: None,
+                to: Some(started_at),
+                ..query(admin_id, "")
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 0);
+}
@@ -72,9 +72,13 @@ fn create_test_message(room_id: RoomId, sender_id: UserId, content: &str) -> Mes
         content: MessageContent::new(content.to_string()).unwrap(),
         message_type: MessageType::Text,
         reply_to: None,
+        thread_root_id: None,
+        seq: 0,
         created_at: OffsetDateTime::now_utc(),
         last_revision: None,
+        version: 1,
         is_deleted: false,
+        encrypted: false,
     }
 }
 
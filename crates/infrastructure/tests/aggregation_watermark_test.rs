@@ -0,0 +1,219 @@
+//! 增量聚合水位线测试
+//!
+//! 验证：没有水位线时从最早写入的事件开始聚合；聚合结果和水位线一起保存，
+//! 没有新事件时再跑一次不处理任何事件；水位线按写入时间推进，
+//! 事件时间早于水位线的晚到事件仍会触发所在桶重算
+mod support;
+
+use chrono::{DateTime, Duration, Utc};
+use domain::RoomId;
+use infrastructure::{StatsAggregationService, TimeGranularity};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::setup_test_db;
+
+/// 水位线按粒度全局只有一行：本文件的测试逐个运行，各自先把水位线设到已知位置，
+/// 不受之前运行或其他测试推进的水位线影响
+static WATERMARK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// 把小时粒度的水位线设到指定写入时间
+async fn pin_watermark(pool: &PgPool, watermark: DateTime<Utc>) {
+    sqlx::query(
+        "INSERT INTO aggregation_watermarks (granularity, watermark)
+         VALUES ('Hour', $1)
+         ON CONFLICT (granularity) DO UPDATE SET watermark = EXCLUDED.watermark",
+    )
+    .bind(watermark)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// 创建用户和房间，返回 (用户ID, 房间ID)
+async fn create_user_and_room(pool: &PgPool) -> (Uuid, Uuid) {
+    let user_id = Uuid::new_v4();
+    let suffix = &user_id.to_string()[0..8];
+    sqlx::query(
+        "INSERT INTO users (id, username, email, password_hash, status)
+         VALUES ($1, $2, $3, 'hashed_password', 'active'::user_status)",
+    )
+    .bind(user_id)
+    .bind(format!("watermark_{suffix}"))
+    .bind(format!("watermark_{suffix}@example.com"))
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("watermark_room_{suffix}"))
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+
+    (user_id, room_id)
+}
+
+async fn insert_event(
+    pool: &PgPool,
+    user_id: Uuid,
+    room_id: Uuid,
+    session_id: Uuid,
+    event_type: &str,
+    timestamp: DateTime<Utc>,
+    ingested_at: DateTime<Utc>,
+) {
+    sqlx::query(
+        "INSERT INTO presence_events (user_id, room_id, event_type, timestamp, session_id, ingested_at)
+         VALUES ($1, $2, $3::presence_event_type, $4, $5, $6)",
+    )
+    .bind(user_id)
+    .bind(room_id)
+    .bind(event_type)
+    .bind(timestamp)
+    .bind(session_id)
+    .bind(ingested_at)
+    .execute(pool)
+    .await
+    .expect("Failed to insert presence event");
+}
+
+/// 在两小时前的桶里写入一个完整会话，写入时间早于聚合留出的余量；返回 (桶起点, 房间ID)
+async fn seed_session(pool: &PgPool, service: &StatsAggregationService) -> (DateTime<Utc>, Uuid) {
+    let bucket = TimeGranularity::Hour.bucket_start(Utc::now() - Duration::hours(2));
+    service
+        .create_partition_if_not_exists(bucket)
+        .await
+        .unwrap();
+
+    let (user_id, room_id) = create_user_and_room(pool).await;
+    let session = Uuid::new_v4();
+    let ingested_at = Utc::now() - Duration::minutes(5);
+    for (event_type, minute) in [("Connected", 5), ("Heartbeat", 6), ("Disconnected", 20)] {
+        insert_event(
+            pool,
+            user_id,
+            room_id,
+            session,
+            event_type,
+            bucket + Duration::minutes(minute),
+            ingested_at,
+        )
+        .await;
+    }
+    (bucket, room_id)
+}
+
+async fn saved_connections(pool: &PgPool, room_id: Uuid, bucket: DateTime<Utc>) -> i64 {
+    sqlx::query_scalar(
+        "SELECT total_connections FROM stats_aggregated
+         WHERE room_id = $1 AND time_bucket = $2 AND granularity = 'Hour'",
+    )
+    .bind(room_id)
+    .bind(bucket)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn second_run_without_new_events_processes_nothing() {
+    let _guard = WATERMARK_LOCK.lock().await;
+    let pool = setup_test_db().await;
+    let service = StatsAggregationService::new(pool.clone());
+    let (bucket, room_id) = seed_session(&pool, &service).await;
+
+    // 没有水位线时从最早写入的事件开始
+    sqlx::query("DELETE FROM aggregation_watermarks WHERE granularity = 'Hour'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let watermark = service.watermark(TimeGranularity::Hour).await.unwrap();
+    assert!(watermark.is_some_and(|watermark| watermark <= Utc::now() - Duration::minutes(5)));
+
+    // 第一次聚合覆盖新事件，水位线推进到留出余量之前的写入时间
+    let first = service
+        .incremental_aggregate_stats(TimeGranularity::Hour)
+        .await
+        .unwrap();
+    assert!(first.events_processed >= 3, "{first:?}");
+    let advanced = first.watermark.expect("watermark should advance");
+    assert!(advanced > Utc::now() - Duration::minutes(5) && advanced < Utc::now());
+    assert_eq!(
+        service.watermark(TimeGranularity::Hour).await.unwrap(),
+        Some(advanced)
+    );
+    let mine = first
+        .stats
+        .iter()
+        .find(|stat| stat.room_id == RoomId::from(room_id))
+        .expect("room stats should be aggregated");
+    assert_eq!(mine.time_bucket, bucket);
+    assert_eq!(mine.total_connections, 1);
+    assert_eq!(mine.unique_users, 1);
+
+    // 结果已随水位线保存
+    assert_eq!(saved_connections(&pool, room_id, bucket).await, 1);
+
+    // 第二次没有新事件：不处理任何事件
+    let second = service
+        .incremental_aggregate_stats(TimeGranularity::Hour)
+        .await
+        .unwrap();
+    assert_eq!(second.events_processed, 0);
+    assert!(second.stats.is_empty());
+    assert!(second.watermark >= Some(advanced));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn late_event_behind_watermark_reaggregates_its_bucket() {
+    let _guard = WATERMARK_LOCK.lock().await;
+    let pool = setup_test_db().await;
+    let service = StatsAggregationService::new(pool.clone());
+    pin_watermark(&pool, Utc::now() - Duration::minutes(10)).await;
+    let (bucket, room_id) = seed_session(&pool, &service).await;
+
+    let first = service
+        .incremental_aggregate_stats(TimeGranularity::Hour)
+        .await
+        .unwrap();
+    let watermark = first.watermark.expect("watermark should advance");
+    assert_eq!(saved_connections(&pool, room_id, bucket).await, 1);
+
+    // 事件时间在两小时前的桶里，远早于水位线，但在水位线之后才写入（积压、暂停恢复、死信重放）
+    let (late_user, _) = create_user_and_room(&pool).await;
+    insert_event(
+        &pool,
+        late_user,
+        room_id,
+        Uuid::new_v4(),
+        "Connected",
+        bucket + Duration::minutes(30),
+        watermark,
+    )
+    .await;
+
+    let second = service
+        .incremental_aggregate_stats(TimeGranularity::Hour)
+        .await
+        .unwrap();
+    assert!(second.events_processed >= 1, "{second:?}");
+    let mine = second
+        .stats
+        .iter()
+        .find(|stat| stat.room_id == RoomId::from(room_id))
+        .expect("late event's bucket should be re-aggregated");
+    assert_eq!(mine.time_bucket, bucket);
+    // 重算按桶内全部事件，原来的会话仍然计入
+    assert_eq!(mine.total_connections, 2);
+    assert_eq!(mine.unique_users, 2);
+    assert_eq!(saved_connections(&pool, room_id, bucket).await, 2);
+}
@@ -0,0 +1,203 @@
+//! Redis 广播断线测试
+//!
+//! 验证：Redis 不可用时发布按退避重试，用尽后发送失败（消息已落库，可从存储补齐）；
+//! 订阅连接被断开后自动重连并重新订阅同一房间，重连时产出 Gap 标记
+mod support;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::{
+    broadcaster::BroadcastError,
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        ReconnectBackfill, SendMessageRequest,
+    },
+    ApplicationError, MessageBroadcast, MessageBroadcaster, StreamEvent, WebSocketMessage,
+};
+use domain::{ChatRoomVisibility, MessageId, MessageType, RoomId};
+use infrastructure::{RedisMessageBroadcaster, RetryPolicy};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+/// 没有进程监听的端口，连接立即被拒绝
+const UNREACHABLE_REDIS_URL: &str = "redis://127.0.0.1:1";
+
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy {
+        publish_retries: 2,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    }
+}
+
+fn unreachable_broadcaster() -> RedisMessageBroadcaster {
+    RedisMessageBroadcaster::new(redis::Client::open(UNREACHABLE_REDIS_URL).unwrap())
+        .with_retry_policy(fast_retry())
+}
+
+fn chat_service(pool: &PgPool, max_reconnect_backfill: u32) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster: Arc::new(unreachable_broadcaster()),
+        message_config: config::MessageConfig {
+            max_reconnect_backfill,
+            ..config::MessageConfig::default()
+        },
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("bcast-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+async fn send(
+    service: &ChatService,
+    room_id: Uuid,
+    sender_id: Uuid,
+) -> Result<domain::Message, ApplicationError> {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: "hello".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+}
+
+#[tokio::test]
+async fn publish_fails_as_unavailable_after_bounded_retries() {
+    let broadcaster = unreachable_broadcaster();
+    let room_id = RoomId::from(Uuid::new_v4());
+
+    let result = broadcaster
+        .broadcast(MessageBroadcast {
+            room_id,
+            message: WebSocketMessage::MessageDeleted {
+                message_id: MessageId::from(Uuid::new_v4()),
+            },
+        })
+        .await;
+
+    assert!(
+        matches!(result, Err(BroadcastError::Unavailable(_))),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn send_during_outage_fails_and_messages_are_recovered_from_storage() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, 2);
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let after_seq = service.latest_seq(room_id).await.unwrap();
+
+    // 广播失败以专门的错误返回，而不是悄悄丢失
+    let err = send(&service, room_id, owner).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ApplicationError::Broadcast(BroadcastError::Unavailable(_))
+        ),
+        "{err:?}"
+    );
+
+    // 消息已落库，订阅方重连后从存储补齐
+    let backfill = service
+        .backfill_after_seq(room_id, member, after_seq)
+        .await
+        .unwrap();
+    let ReconnectBackfill::Messages(messages) = backfill else {
+        panic!("expected backfilled messages, got {backfill:?}");
+    };
+    assert_eq!(messages.len(), 1);
+    assert_eq!(Uuid::from(messages[0].sender_id), owner);
+    assert_eq!(service.latest_seq(room_id).await.unwrap(), messages[0].seq);
+
+    // 缺口超过补发上限时要求重新同步
+    let _ = send(&service, room_id, owner).await;
+    let _ = send(&service, room_id, owner).await;
+    let backfill = service
+        .backfill_after_seq(room_id, member, after_seq)
+        .await
+        .unwrap();
+    assert!(
+        matches!(backfill, ReconnectBackfill::ResyncRequired { .. }),
+        "{backfill:?}"
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires local redis"]
+async fn stream_resubscribes_after_connection_is_killed() {
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let client = redis::Client::open(redis_url).unwrap();
+    let broadcaster = RedisMessageBroadcaster::new(client.clone()).with_retry_policy(fast_retry());
+    let room_id = RoomId::from(Uuid::new_v4());
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    // 模拟 Redis 重启：断开所有订阅连接
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    let _: i64 = redis::cmd("CLIENT")
+        .arg("KILL")
+        .arg("TYPE")
+        .arg("pubsub")
+        .query_async(&mut conn)
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(Duration::from_secs(5), stream.recv_event())
+        .await
+        .expect("stream should reconnect")
+        .expect("stream should stay open");
+    assert!(matches!(event, StreamEvent::Gap), "{event:?}");
+
+    // 重新订阅的是同一个房间频道
+    let message_id = MessageId::from(Uuid::new_v4());
+    broadcaster
+        .broadcast(MessageBroadcast {
+            room_id,
+            message: WebSocketMessage::MessageDeleted { message_id },
+        })
+        .await
+        .unwrap();
+    let received = tokio::time::timeout(Duration::from_secs(5), stream.recv())
+        .await
+        .expect("broadcast after reconnect should arrive")
+        .expect("stream should stay open");
+    assert!(matches!(
+        received.message,
+        WebSocketMessage::MessageDeleted { message_id: id } if id == message_id
+    ));
+
+    broadcaster.shutdown().await;
+    assert!(stream.recv_event().await.is_none());
+}
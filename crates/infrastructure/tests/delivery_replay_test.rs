@@ -0,0 +1,133 @@
+//! 未送达消息补发测试
+//!
+//! 验证：补发查询只取最近的若干条并按发送时间排列，ack 不属于自己的消息不报错
+mod support;
+
+use application::repository::{MessageDeliveryRepository, MessageRepository};
+use chrono::Utc;
+use domain::{Message, MessageContent, MessageDelivery, MessageId, MessageType, RoomId, UserId};
+use infrastructure::{PgMessageDeliveryRepository, PgMessageRepository};
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use support::{create_user, setup_test_db};
+
+async fn create_room(pool: &PgPool, owner: UserId) -> RoomId {
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("replay_room_{}", &room_id.to_string()[0..8]))
+    .bind(Uuid::from(owner))
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+    RoomId::from(room_id)
+}
+
+async fn save_message(repo: &PgMessageRepository, room_id: RoomId, sender_id: UserId) -> MessageId {
+    let message = Message {
+        id: MessageId::from(Uuid::new_v4()),
+        room_id,
+        sender_id,
+        content: MessageContent::new("replay me".to_string()).unwrap(),
+        message_type: MessageType::Text,
+        reply_to: None,
+        thread_root_id: None,
+        seq: 0,
+        created_at: OffsetDateTime::now_utc(),
+        last_revision: None,
+        version: 1,
+        is_deleted: false,
+        encrypted: false,
+    };
+    repo.save_message(message).await.unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_replay_is_bounded_and_ordered() {
+    let pool = setup_test_db().await;
+    let messages = PgMessageRepository::new(pool.clone());
+    let deliveries = PgMessageDeliveryRepository::new(pool.clone());
+
+    let sender = UserId::from(create_user(&pool).await);
+    let recipient = UserId::from(create_user(&pool).await);
+    let room_id = create_room(&pool, sender).await;
+
+    let base = OffsetDateTime::now_utc() - Duration::minutes(10);
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let id = save_message(&messages, room_id, sender).await;
+        deliveries
+            .record_sent(MessageDelivery::new_sent(
+                id,
+                recipient,
+                base + Duration::seconds(i),
+            ))
+            .await
+            .unwrap();
+        ids.push(id);
+    }
+
+    // 已送达的不再补发
+    deliveries
+        .mark_delivered(ids[4], recipient, Utc::now())
+        .await
+        .unwrap();
+
+    // 只取最近 3 条未送达，按发送时间先后排列
+    let undelivered = deliveries
+        .find_undelivered_for_user(recipient, 3)
+        .await
+        .unwrap();
+    let undelivered: Vec<MessageId> = undelivered.iter().map(|d| d.message_id).collect();
+    assert_eq!(undelivered, ids[1..4]);
+
+    // 批量取回消息内容，按序列号排列
+    let loaded = messages.find_by_ids(&undelivered).await.unwrap();
+    let loaded: Vec<MessageId> = loaded.iter().map(|m| m.id).collect();
+    assert_eq!(loaded, undelivered);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_ack_for_foreign_message_is_ignored() {
+    let pool = setup_test_db().await;
+    let messages = PgMessageRepository::new(pool.clone());
+    let deliveries = PgMessageDeliveryRepository::new(pool.clone());
+
+    let sender = UserId::from(create_user(&pool).await);
+    let recipient = UserId::from(create_user(&pool).await);
+    let stranger = UserId::from(create_user(&pool).await);
+    let room_id = create_room(&pool, sender).await;
+
+    let id = save_message(&messages, room_id, sender).await;
+    deliveries
+        .record_sent(MessageDelivery::new_sent(
+            id,
+            recipient,
+            OffsetDateTime::now_utc(),
+        ))
+        .await
+        .unwrap();
+
+    // 不是发给 stranger 的消息，ack 不报错也不影响收件人的状态
+    deliveries
+        .mark_delivered(id, stranger, Utc::now())
+        .await
+        .unwrap();
+    deliveries
+        .mark_delivered(MessageId::from(Uuid::new_v4()), stranger, Utc::now())
+        .await
+        .unwrap();
+
+    let pending = deliveries
+        .find_undelivered_for_user(recipient, 500)
+        .await
+        .unwrap();
+    assert!(pending.iter().any(|d| d.message_id == id));
+}
@@ -0,0 +1,336 @@
+//! 文件上传测试
+//!
+//! 验证：上传受内容类型和大小限制；图片/文件消息只能引用自己上传且未被引用的文件；
+//! 房间成员可以下载消息引用的文件；一直没有被引用的上传会被清理
+mod support;
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use application::{
+    services::{
+        ChatService, CreateRoomRequest, JoinRoomRequest, SendMessageRequest, UploadFileRequest,
+        UploadService, UploadServiceDependencies,
+    },
+    ApplicationError, ByteStream, SystemClock,
+};
+use config::UploadConfig;
+use domain::{ChatRoomVisibility, DomainError, FileUpload, MessageType};
+use infrastructure::{LocalObjectStorage, PgStorage};
+use sqlx::PgPool;
+use tokio_stream::StreamExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+/// 上传服务，文件存放在临时目录
+async fn upload_service(pool: &PgPool, max_size_bytes: u64) -> (UploadService, PathBuf) {
+    let root = std::env::temp_dir().join(format!("chatroom-uploads-{}", Uuid::new_v4()));
+    let storage = LocalObjectStorage::new(&root).await.unwrap();
+    let service = UploadService::new(UploadServiceDependencies {
+        file_upload_repository: PgStorage::new(pool.clone()).file_upload_repository,
+        storage: Arc::new(storage),
+        clock: Arc::new(SystemClock),
+        config: UploadConfig {
+            max_size_bytes,
+            ..UploadConfig::default()
+        },
+    });
+    (service, root)
+}
+
+fn body(data: &[u8]) -> ByteStream {
+    Box::pin(ReaderStream::with_capacity(Cursor::new(data.to_vec()), 4))
+}
+
+async fn read_all(mut stream: ByteStream) -> Vec<u8> {
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    data
+}
+
+async fn upload(
+    service: &UploadService,
+    uploader_id: Uuid,
+    content_type: &str,
+    data: &[u8],
+) -> Result<FileUpload, ApplicationError> {
+    service
+        .upload(
+            UploadFileRequest {
+                uploader_id,
+                file_name: "../photo.png".to_string(),
+                content_type: content_type.to_string(),
+            },
+            body(data),
+        )
+        .await
+}
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("upload-{}", &member_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+fn message(
+    room_id: Uuid,
+    sender_id: Uuid,
+    message_type: MessageType,
+    content: String,
+) -> SendMessageRequest {
+    SendMessageRequest {
+        room_id,
+        sender_id,
+        content,
+        message_type,
+        reply_to: None,
+        idempotency_key: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn upload_enforces_content_type_and_size_limit() {
+    let pool = setup_test_db().await;
+    let user_id = create_user(&pool).await;
+    let (service, root) = upload_service(&pool, 16).await;
+
+    let err = upload(&service, user_id, "application/x-msdownload", b"MZ")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::UnsupportedContentType { .. })
+    ));
+
+    let err = upload(&service, user_id, "image/png", &[0u8; 17])
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileTooLarge { limit: 16 })
+    ));
+    // 超限的上传不留下任何文件
+    assert!(std::fs::read_dir(root.join(user_id.to_string()))
+        .map(|entries| entries.count() == 0)
+        .unwrap_or(true));
+
+    let uploaded = upload(&service, user_id, "Image/PNG; charset=binary", &[7u8; 16])
+        .await
+        .unwrap();
+    assert_eq!(uploaded.content_type, "image/png");
+    assert_eq!(uploaded.file_name, "photo.png");
+    assert_eq!(uploaded.size_bytes, 16);
+
+    let (_, stream) = service.download(user_id, uploaded.id).await.unwrap();
+    assert_eq!(read_all(stream).await, vec![7u8; 16]);
+    assert_eq!(
+        service.download_url(&uploaded).await.unwrap(),
+        format!("/api/v1/uploads/{}", uploaded.id)
+    );
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn image_message_shares_file_with_room_members() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let outsider_id = create_user(&pool).await;
+    let chat = chat_service(&pool);
+    let room_id = create_room(&chat, owner_id, member_id).await;
+    let (service, root) = upload_service(&pool, 1024).await;
+
+    let image = upload(&service, owner_id, "image/png", b"png-bytes")
+        .await
+        .unwrap();
+    let document = upload(&service, owner_id, "application/pdf", b"pdf-bytes")
+        .await
+        .unwrap();
+
+    // 发送前只有上传者能下载
+    let err = service
+        .download(member_id, image.id)
+        .await
+        .err()
+        .expect("download should fail");
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileNotFound)
+    ));
+
+    // 内容必须是文件ID，图片消息只能引用图片
+    let err = chat
+        .send_message(message(
+            room_id,
+            owner_id,
+            MessageType::Image,
+            "not-an-id".to_string(),
+        ))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InvalidArgument {
+            field: "content",
+            ..
+        })
+    ));
+    let err = chat
+        .send_message(message(
+            room_id,
+            owner_id,
+            MessageType::Image,
+            document.id.to_string(),
+        ))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InvalidArgument {
+            field: "content",
+            ..
+        })
+    ));
+
+    // 不能引用别人上传的文件
+    let err = chat
+        .send_message(message(
+            room_id,
+            member_id,
+            MessageType::Image,
+            image.id.to_string(),
+        ))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileNotFound)
+    ));
+
+    chat.send_message(message(
+        room_id,
+        owner_id,
+        MessageType::Image,
+        image.id.to_string(),
+    ))
+    .await
+    .unwrap();
+    chat.send_message(message(
+        room_id,
+        owner_id,
+        MessageType::File,
+        document.id.to_string(),
+    ))
+    .await
+    .unwrap();
+
+    // 同一个文件不能被第二条消息引用
+    let err = chat
+        .send_message(message(
+            room_id,
+            owner_id,
+            MessageType::Image,
+            image.id.to_string(),
+        ))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileNotFound)
+    ));
+
+    let (downloaded, stream) = service.download(member_id, image.id).await.unwrap();
+    assert!(downloaded.message_id.is_some());
+    assert_eq!(read_all(stream).await, b"png-bytes");
+
+    let err = service
+        .download(outsider_id, image.id)
+        .await
+        .err()
+        .expect("download should fail");
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileNotFound)
+    ));
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn cleanup_removes_only_stale_unattached_uploads() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let chat = chat_service(&pool);
+    let room_id = create_room(&chat, owner_id, member_id).await;
+    let (service, root) = upload_service(&pool, 1024).await;
+
+    let stale = upload(&service, owner_id, "image/png", b"stale")
+        .await
+        .unwrap();
+    let fresh = upload(&service, owner_id, "image/png", b"fresh")
+        .await
+        .unwrap();
+    let attached = upload(&service, owner_id, "image/png", b"attached")
+        .await
+        .unwrap();
+    chat.send_message(message(
+        room_id,
+        owner_id,
+        MessageType::Image,
+        attached.id.to_string(),
+    ))
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "UPDATE file_uploads SET created_at = NOW() - INTERVAL '2 days' WHERE id = ANY($1)",
+    )
+    .bind(vec![stale.id, attached.id])
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert!(service.cleanup_orphans().await.unwrap() >= 1);
+
+    let err = service
+        .download(owner_id, stale.id)
+        .await
+        .err()
+        .expect("download should fail");
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::FileNotFound)
+    ));
+    assert!(!root.join(&stale.storage_key).exists());
+    assert!(service.download(owner_id, fresh.id).await.is_ok());
+    assert!(service.download(member_id, attached.id).await.is_ok());
+
+    let _ = std::fs::remove_dir_all(root);
+}
@@ -0,0 +1,114 @@
+//! 进程内广播落后补齐测试
+//!
+//! 验证：订阅者暂停消费期间房间缓冲被覆盖后，按 WebSocket 连接的做法收到 Gap 时从存储补齐，
+//! 最终按发送顺序拿到全部消息，没有遗漏也没有重复
+mod support;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::{
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        ReconnectBackfill, SendMessageRequest,
+    },
+    LocalMessageBroadcaster, MessageBroadcaster, StreamEvent, WebSocketMessage,
+};
+use domain::{ChatRoomVisibility, MessageId, MessageType};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+fn chat_service(pool: &PgPool, broadcaster: Arc<dyn MessageBroadcaster>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster,
+        ..chat_service_deps(pool)
+    })
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn paused_subscriber_recovers_every_message_in_order() {
+    let pool = setup_test_db().await;
+    let broadcaster = Arc::new(LocalMessageBroadcaster::new(4));
+    let service = chat_service(&pool, broadcaster.clone());
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("lag-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id: owner,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member,
+            password: None,
+        })
+        .await
+        .unwrap();
+
+    // 连接建立：订阅后记下房间当前最新的序列号
+    let mut stream = broadcaster.subscribe(room.id).await.unwrap();
+    let mut last_seq = service.latest_seq(room_id).await.unwrap();
+
+    // 订阅者暂停消费期间发出远超缓冲容量的消息
+    let mut sent = Vec::new();
+    for i in 0..20 {
+        let message = service
+            .send_message(SendMessageRequest {
+                room_id,
+                sender_id: owner,
+                content: format!("message {i}"),
+                message_type: MessageType::Text,
+                reply_to: None,
+                idempotency_key: None,
+            })
+            .await
+            .unwrap();
+        sent.push(message.id);
+    }
+
+    // 恢复消费：Gap 时从存储补齐，实时流里已经补过的消息按序列号跳过
+    let mut delivered: Vec<MessageId> = Vec::new();
+    let mut saw_gap = false;
+    while delivered.len() < sent.len() {
+        let event = tokio::time::timeout(Duration::from_secs(5), stream.recv_event())
+            .await
+            .expect("消息流不应卡住")
+            .expect("消息流不应结束");
+        let messages = match event {
+            StreamEvent::Gap => {
+                saw_gap = true;
+                match service
+                    .backfill_after_seq(room_id, member, last_seq)
+                    .await
+                    .unwrap()
+                {
+                    ReconnectBackfill::Messages(messages) => messages,
+                    other => panic!("unexpected backfill: {:?}", other),
+                }
+            }
+            StreamEvent::Message(broadcast) => match broadcast.message {
+                WebSocketMessage::ChatMessage(message) => vec![message],
+                _ => continue,
+            },
+        };
+        for message in messages {
+            if message.seq > last_seq {
+                last_seq = message.seq;
+                delivered.push(message.id);
+            }
+        }
+    }
+
+    assert!(saw_gap, "缓冲容量为 4，发送 20 条应该触发落后");
+    assert_eq!(delivered, sent);
+}
@@ -0,0 +1,255 @@
+//! 提及通知测试
+//!
+//! 验证：@ 房间成员会生成通知并推送提及提醒；@ 非成员被忽略；
+//! `@everyone` 只有房间管理员在成员数不超过上限时才生效
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    repository::NotificationRepository,
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        SendMessageRequest,
+    },
+    WebSocketMessage,
+};
+use domain::{ChatRoomVisibility, MessageType, Notification, UserId};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{
+    chat_service_deps, create_named_user, create_user, setup_test_db, RecordingBroadcaster,
+};
+
+/// 已推送的提及提醒，其他广播忽略
+fn mentions(broadcaster: &RecordingBroadcaster) -> Vec<Vec<UserId>> {
+    broadcaster
+        .messages()
+        .into_iter()
+        .filter_map(|message| match message {
+            WebSocketMessage::Mention { user_ids, .. } => Some(user_ids),
+            _ => None,
+        })
+        .collect()
+}
+
+fn chat_service(
+    pool: &PgPool,
+    broadcaster: Arc<RecordingBroadcaster>,
+    everyone_mention_max_members: usize,
+) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster,
+        message_config: config::MessageConfig {
+            everyone_mention_max_members,
+            ..config::MessageConfig::default()
+        },
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建公开房间，members 依次加入
+async fn create_room(service: &ChatService, owner_id: Uuid, members: &[Uuid]) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("mention-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    for member_id in members {
+        service
+            .join_room(JoinRoomRequest {
+                room_id,
+                user_id: *member_id,
+                password: None,
+            })
+            .await
+            .unwrap();
+    }
+    room_id
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid, content: String) {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content,
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap();
+}
+
+async fn notifications(pool: &PgPool, user_id: Uuid) -> Vec<Notification> {
+    PgStorage::new(pool.clone())
+        .notification_repository
+        .find_for_user(UserId::from(user_id), true, 1, 50)
+        .await
+        .unwrap()
+        .items
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn mentioning_member_creates_notification_and_pushes_alert() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let (member_id, member_name) = create_named_user(&pool).await;
+    let (outsider_id, outsider_name) = create_named_user(&pool).await;
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let service = chat_service(&pool, broadcaster.clone(), 100);
+    let room_id = create_room(&service, owner_id, &[member_id]).await;
+
+    send(
+        &service,
+        room_id,
+        owner_id,
+        format!("hi @{member_name} and @{outsider_name}"),
+    )
+    .await;
+
+    let member_notifications = notifications(&pool, member_id).await;
+    assert_eq!(member_notifications.len(), 1);
+    let notification = &member_notifications[0];
+    assert_eq!(notification.kind, Notification::MENTION);
+    assert_eq!(notification.actor_id, Some(UserId::from(owner_id)));
+    assert_eq!(notification.room_id.map(Uuid::from), Some(room_id));
+    assert!(!notification.is_read());
+
+    // 不是房间成员的人不会收到通知
+    assert!(notifications(&pool, outsider_id).await.is_empty());
+    assert_eq!(mentions(&broadcaster), vec![vec![UserId::from(member_id)]]);
+
+    // 没有提及任何人的消息不推送提醒
+    send(&service, room_id, owner_id, "no mentions".to_string()).await;
+    assert_eq!(mentions(&broadcaster).len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn everyone_mention_requires_room_admin() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let first_id = create_user(&pool).await;
+    let second_id = create_user(&pool).await;
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let service = chat_service(&pool, broadcaster.clone(), 100);
+    let room_id = create_room(&service, owner_id, &[first_id, second_id]).await;
+
+    // 普通成员的 @everyone 当作普通文本
+    send(&service, room_id, first_id, "@everyone look".to_string()).await;
+    assert!(notifications(&pool, second_id).await.is_empty());
+    assert!(notifications(&pool, owner_id).await.is_empty());
+    assert!(mentions(&broadcaster).is_empty());
+
+    // 房主的 @everyone 通知除自己以外的所有成员
+    send(&service, room_id, owner_id, "@everyone meeting".to_string()).await;
+    assert_eq!(notifications(&pool, first_id).await.len(), 1);
+    assert_eq!(notifications(&pool, second_id).await.len(), 1);
+    assert!(notifications(&pool, owner_id).await.is_empty());
+
+    let mentions = mentions(&broadcaster);
+    assert_eq!(mentions.len(), 1);
+    let mut notified = mentions[0].clone();
+    notified.sort_by_key(|user_id| Uuid::from(*user_id));
+    let mut expected = vec![UserId::from(first_id), UserId::from(second_id)];
+    expected.sort_by_key(|user_id| Uuid::from(*user_id));
+    assert_eq!(notified, expected);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn everyone_mention_ignored_in_large_rooms() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let (first_id, first_name) = create_named_user(&pool).await;
+    let second_id = create_user(&pool).await;
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    // 房间有 3 个成员，超过上限 2
+    let service = chat_service(&pool, broadcaster.clone(), 2);
+    let room_id = create_room(&service, owner_id, &[first_id, second_id]).await;
+
+    // 同一条消息里点名的成员仍然会收到通知
+    send(
+        &service,
+        room_id,
+        owner_id,
+        format!("@everyone and @{first_name}"),
+    )
+    .await;
+    assert_eq!(notifications(&pool, first_id).await.len(), 1);
+    assert!(notifications(&pool, second_id).await.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn mentions_feed_lists_messages_mentioning_member() {
+    let pool = setup_test_db().await;
+    let (owner_id, owner_name) = create_named_user(&pool).await;
+    let (first_id, first_name) = create_named_user(&pool).await;
+    let (second_id, second_name) = create_named_user(&pool).await;
+    let (outsider_id, outsider_name) = create_named_user(&pool).await;
+    let service = chat_service(&pool, Arc::new(RecordingBroadcaster::default()), 100);
+    let room_id = create_room(&service, owner_id, &[first_id, second_id]).await;
+
+    // 一条消息提及多人，不存在的用户名和非成员被忽略
+    send(
+        &service,
+        room_id,
+        owner_id,
+        format!("@{first_name} @{second_name} @{outsider_name} @nobody_{first_name}"),
+    )
+    .await;
+    // 提及自己不算
+    send(
+        &service,
+        room_id,
+        owner_id,
+        format!("@{owner_name} @{first_name}"),
+    )
+    .await;
+
+    let first_feed = service.list_mentions(first_id, 1, 1).await.unwrap();
+    assert_eq!(first_feed.total_count, 2);
+    assert_eq!(first_feed.items.len(), 1);
+    let second_page = service.list_mentions(first_id, 2, 1).await.unwrap();
+    assert_eq!(second_page.items.len(), 1);
+    assert_ne!(
+        first_feed.items[0].message.id,
+        second_page.items[0].message.id
+    );
+
+    assert_eq!(
+        service
+            .list_mentions(second_id, 1, 20)
+            .await
+            .unwrap()
+            .total_count,
+        1
+    );
+    assert_eq!(
+        service
+            .list_mentions(outsider_id, 1, 20)
+            .await
+            .unwrap()
+            .total_count,
+        0
+    );
+    assert_eq!(
+        service
+            .list_mentions(owner_id, 1, 20)
+            .await
+            .unwrap()
+            .total_count,
+        0
+    );
+}
@@ -0,0 +1,159 @@
+//! 消息批量写入测试
+//!
+//! 验证：一次批量写入 1000 条消息，返回的 ID 与传入顺序一致且都能查到，房间内序列号按传入顺序递增；
+//! 攒批包装层下并发发送全部写入，同一发送方的顺序不变；批内有坏消息时其他消息照常写入
+mod support;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use application::repository::MessageRepository;
+use domain::{Message, MessageContent, MessageId, MessageType, RoomId, UserId};
+use infrastructure::{BatchingMessageRepository, PgMessageRepository};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use support::{create_user, setup_test_db};
+
+async fn create_room(pool: &PgPool, owner: UserId) -> RoomId {
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("batch_room_{}", &room_id.to_string()[0..8]))
+    .bind(Uuid::from(owner))
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+    RoomId::from(room_id)
+}
+
+fn new_message(room_id: RoomId, sender_id: UserId, content: String) -> Message {
+    Message {
+        id: MessageId::from(Uuid::new_v4()),
+        room_id,
+        sender_id,
+        content: MessageContent::new(content).unwrap(),
+        message_type: MessageType::Text,
+        reply_to: None,
+        thread_root_id: None,
+        seq: 0,
+        created_at: OffsetDateTime::now_utc(),
+        last_revision: None,
+        version: 1,
+        is_deleted: false,
+        encrypted: false,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_create_batch_inserts_1000_messages_in_order() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+    let sender = UserId::from(create_user(&pool).await);
+    let rooms = [
+        create_room(&pool, sender).await,
+        create_room(&pool, sender).await,
+    ];
+
+    let messages: Vec<Message> = (0..1000)
+        .map(|i| new_message(rooms[i % 2], sender, format!("batch {i}")))
+        .collect();
+    let expected: Vec<MessageId> = messages.iter().map(|message| message.id).collect();
+
+    let started = Instant::now();
+    let ids = repo.create_batch(messages).await.unwrap();
+    println!("inserted 1000 messages in {:?}", started.elapsed());
+    assert_eq!(ids, expected);
+
+    let stored = repo.find_by_ids(&ids).await.unwrap();
+    assert_eq!(stored.len(), 1000);
+
+    // 每个房间内序列号按传入顺序递增
+    for room_id in rooms {
+        let mut in_room: Vec<&Message> = stored
+            .iter()
+            .filter(|message| message.room_id == room_id)
+            .collect();
+        assert_eq!(in_room.len(), 500);
+        in_room.sort_by_key(|message| message.seq);
+        let positions: Vec<usize> = in_room
+            .iter()
+            .map(|message| ids.iter().position(|id| *id == message.id).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    assert!(repo.create_batch(Vec::new()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_batching_repository_preserves_order_under_concurrency() {
+    let pool = setup_test_db().await;
+    let repo: Arc<dyn MessageRepository> = Arc::new(BatchingMessageRepository::new(
+        Arc::new(PgMessageRepository::new(pool.clone())),
+        Duration::from_millis(20),
+        50,
+    ));
+    let room_id = create_room(&pool, UserId::from(create_user(&pool).await)).await;
+
+    // 每个发送方依次发送，各发送方之间并发
+    let mut senders = Vec::new();
+    for _ in 0..10 {
+        senders.push(UserId::from(create_user(&pool).await));
+    }
+    let tasks: Vec<_> = senders
+        .iter()
+        .map(|&sender| {
+            let repo = repo.clone();
+            tokio::spawn(async move {
+                let mut ids = Vec::new();
+                for i in 0..20 {
+                    let message = new_message(room_id, sender, format!("concurrent {i}"));
+                    ids.push(repo.create(message).await.unwrap());
+                }
+                ids
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        let ids = task.await.unwrap();
+        let mut stored = repo.find_by_ids(&ids).await.unwrap();
+        assert_eq!(stored.len(), 20);
+        stored.sort_by_key(|message| message.seq);
+        let by_seq: Vec<MessageId> = stored.iter().map(|message| message.id).collect();
+        assert_eq!(by_seq, ids);
+    }
+    assert_eq!(repo.count_after(room_id, None).await.unwrap(), 200);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_batching_repository_isolates_failed_message() {
+    let pool = setup_test_db().await;
+    let repo: Arc<dyn MessageRepository> = Arc::new(BatchingMessageRepository::new(
+        Arc::new(PgMessageRepository::new(pool.clone())),
+        Duration::from_millis(50),
+        10,
+    ));
+    let sender = UserId::from(create_user(&pool).await);
+    let room_id = create_room(&pool, sender).await;
+    let missing_room = RoomId::from(Uuid::new_v4());
+
+    // 三条消息落在同一批，中间一条的房间不存在
+    let (first, broken, last) = tokio::join!(
+        repo.create(new_message(room_id, sender, "first".to_string())),
+        repo.create(new_message(missing_room, sender, "broken".to_string())),
+        repo.create(new_message(room_id, sender, "last".to_string())),
+    );
+
+    assert!(broken.is_err());
+    let ids = [first.unwrap(), last.unwrap()];
+    assert_eq!(repo.find_by_ids(&ids).await.unwrap().len(), 2);
+}
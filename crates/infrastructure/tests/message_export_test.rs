@@ -0,0 +1,287 @@
+//! 聊天记录导出测试
+//!
+//! 验证：几千条消息逐行导出的行数正确；CSV 中含逗号、换行、引号的内容被正确转义；
+//! 已删除的消息带标记导出；时间范围过滤；只有房间 owner 和系统管理员可以导出
+mod support;
+
+use application::{
+    encode_export,
+    services::{ChatService, CreateRoomRequest, JoinRoomRequest, SendMessageRequest},
+    ApplicationError, ExportFormat,
+};
+use domain::{ChatRoomVisibility, DomainError, Message, MessageType};
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+use support::{chat_service, create_named_user, create_superuser, create_user, setup_test_db};
+
+/// 批量生成的消息条数
+const GENERATED_MESSAGES: i64 = 3000;
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("export-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+/// 一次插入大量消息，创建时间从一天前开始每条相隔一秒
+async fn generate_messages(pool: &PgPool, room_id: Uuid, sender_id: Uuid, start: OffsetDateTime) {
+    sqlx::query(
+        "INSERT INTO messages (room_id, user_id, content, message_type, created_at)
+         SELECT $1, $2, 'generated ' || n, 'text'::message_type, $3 + n * INTERVAL '1 second'
+         FROM generate_series(0, $4 - 1) AS n",
+    )
+    .bind(room_id)
+    .bind(sender_id)
+    .bind(start)
+    .bind(GENERATED_MESSAGES)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid, content: &str) -> Message {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: content.to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap()
+}
+
+async fn export(
+    service: &ChatService,
+    room_id: Uuid,
+    operator_id: Uuid,
+    format: ExportFormat,
+    from: Option<OffsetDateTime>,
+    to: Option<OffsetDateTime>,
+) -> Result<String, ApplicationError> {
+    let rows = service
+        .export_messages(room_id, operator_id, from, to)
+        .await?;
+    let mut body = encode_export(rows, format);
+    let mut output = Vec::new();
+    while let Some(chunk) = body.next().await {
+        output.extend_from_slice(&chunk.unwrap());
+    }
+    Ok(String::from_utf8(output).unwrap())
+}
+
+/// 按 RFC 4180 解析 CSV：引号内的逗号和换行属于字段，连续两个引号表示一个引号
+fn parse_csv(input: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    assert!(!in_quotes, "unterminated quoted field");
+    assert!(
+        field.is_empty() && record.is_empty(),
+        "missing final newline"
+    );
+    records
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn export_streams_every_row_with_csv_escaping() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let (member, member_name) = create_named_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+
+    generate_messages(
+        &pool,
+        room_id,
+        member,
+        OffsetDateTime::now_utc() - Duration::days(1),
+    )
+    .await;
+    let tricky = [
+        "hello, world",
+        "first line\nsecond line",
+        "she said \"hi\", then left",
+        "windows\r\nline ending",
+    ];
+    let mut tricky_ids = Vec::new();
+    for content in tricky {
+        tricky_ids.push(send(&service, room_id, member, content).await.id);
+    }
+    sqlx::query("UPDATE messages SET is_deleted = TRUE WHERE id = $1")
+        .bind(Uuid::from(tricky_ids[0]))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let total = GENERATED_MESSAGES as usize + tricky.len();
+
+    let csv = export(&service, room_id, owner, ExportFormat::Csv, None, None)
+        .await
+        .unwrap();
+    let records = parse_csv(&csv);
+    assert_eq!(
+        records[0],
+        vec![
+            "id",
+            "sender_username",
+            "content",
+            "message_type",
+            "created_at",
+            "is_deleted"
+        ]
+    );
+    let rows = &records[1..];
+    assert_eq!(rows.len(), total);
+    assert!(rows.iter().all(|row| row.len() == 6));
+    assert!(rows.iter().all(|row| row[1] == member_name));
+
+    // 按序列号升序，手动发送的消息排在最后，内容原样还原
+    assert_eq!(rows[0][2], "generated 0");
+    let tail = &rows[GENERATED_MESSAGES as usize..];
+    for ((row, content), id) in tail.iter().zip(tricky).zip(&tricky_ids) {
+        assert_eq!(row[0], id.to_string());
+        assert_eq!(row[2], content);
+        assert_eq!(row[3], "Text");
+    }
+    assert_eq!(tail[0][5], "true");
+    assert!(tail[1..].iter().all(|row| row[5] == "false"));
+
+    let ndjson = export(&service, room_id, owner, ExportFormat::Ndjson, None, None)
+        .await
+        .unwrap();
+    let lines: Vec<serde_json::Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), total);
+    assert_eq!(lines[total - 3]["content"], tricky[1]);
+    assert_eq!(lines[total - 4]["is_deleted"], true);
+    assert_eq!(lines[0]["sender_username"], member_name.as_str());
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn export_filters_by_time_range() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let start = OffsetDateTime::now_utc() - Duration::days(1);
+    generate_messages(&pool, room_id, member, start).await;
+
+    // from 含、to 不含：第 100 到第 199 条
+    let from = start + Duration::seconds(100);
+    let to = start + Duration::seconds(200);
+    let ndjson = export(
+        &service,
+        room_id,
+        owner,
+        ExportFormat::Ndjson,
+        Some(from),
+        Some(to),
+    )
+    .await
+    .unwrap();
+    let contents: Vec<String> = ndjson
+        .lines()
+        .map(|line| {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            value["content"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(contents.len(), 100);
+    assert_eq!(contents.first().unwrap(), "generated 100");
+    assert_eq!(contents.last().unwrap(), "generated 199");
+
+    let err = export(
+        &service,
+        room_id,
+        owner,
+        ExportFormat::Csv,
+        Some(to),
+        Some(from),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InvalidArgument { field: "to", .. })
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn only_owner_and_superuser_can_export() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let outsider = create_user(&pool).await;
+    let superuser = create_superuser(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    send(&service, room_id, member, "hello").await;
+
+    let err = export(&service, room_id, member, ExportFormat::Csv, None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::OperationNotAllowed)
+    ));
+    let err = export(&service, room_id, outsider, ExportFormat::Csv, None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::UserNotInRoom)
+    ));
+
+    // 系统管理员不是房间成员也可以导出
+    let csv = export(&service, room_id, superuser, ExportFormat::Csv, None, None)
+        .await
+        .unwrap();
+    assert_eq!(parse_csv(&csv).len(), 2);
+}
@@ -0,0 +1,282 @@
+//! 发送消息幂等键测试
+//!
+//! 验证：同一用户带同一个幂等键重试只产生一条消息；不同用户的同名键互不影响；
+//! 键过期后再用会发出新消息；格式错误的键被拒绝；过期的键会被清理；
+//! 慢速模式下重试仍拿到原消息且不消耗配额；同一个键换房间重发被拒绝
+mod support;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use application::{
+    repository::IdempotencyKeyRepository,
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        SendMessageRequest,
+    },
+    ApplicationError, RateLimitError, SendRateLimiter,
+};
+use async_trait::async_trait;
+use domain::{ChatRoomVisibility, DomainError, Message, MessageType, RoomId, UserId};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db, TestClock};
+
+/// 幂等键的有效期（秒）
+const TTL_SECS: u64 = 60;
+
+/// 慢速模式：每个用户在每个房间只能发一条，之后一直被拒绝
+#[derive(Default)]
+struct SlowModeLimiter {
+    sent: Mutex<HashSet<(RoomId, UserId)>>,
+}
+
+#[async_trait]
+impl SendRateLimiter for SlowModeLimiter {
+    async fn acquire(&self, _room_id: RoomId, _user_id: UserId) -> Result<(), RateLimitError> {
+        Ok(())
+    }
+
+    async fn check_room(
+        &self,
+        room_id: RoomId,
+        user_id: UserId,
+        _is_room_admin: bool,
+    ) -> Result<(), RateLimitError> {
+        if self.sent.lock().unwrap().insert((room_id, user_id)) {
+            Ok(())
+        } else {
+            Err(RateLimitError::RoomRateLimitExceeded {
+                max: 1,
+                window_secs: 3600,
+                retry_after_secs: 3600,
+            })
+        }
+    }
+}
+
+fn chat_service(pool: &PgPool, clock: Arc<TestClock>) -> ChatService {
+    chat_service_with(pool, clock, None)
+}
+
+fn chat_service_with(
+    pool: &PgPool,
+    clock: Arc<TestClock>,
+    send_rate_limiter: Option<Arc<dyn SendRateLimiter>>,
+) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        clock,
+        message_config: config::MessageConfig {
+            idempotency_key_ttl_secs: TTL_SECS,
+            ..config::MessageConfig::default()
+        },
+        send_rate_limiter,
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("idem-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+async fn send(
+    service: &ChatService,
+    room_id: Uuid,
+    sender_id: Uuid,
+    idempotency_key: &str,
+) -> Result<Message, ApplicationError> {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: "hello".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: Some(idempotency_key.to_string()),
+        })
+        .await
+}
+
+async fn message_count(pool: &PgPool, room_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE room_id = $1")
+        .bind(room_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn retry_with_same_key_returns_original_message() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let key = Uuid::new_v4().to_string();
+
+    let first = send(&service, room_id, member, &key).await.unwrap();
+    let retried = send(&service, room_id, member, &key).await.unwrap();
+    assert_eq!(first, retried);
+    assert_eq!(message_count(&pool, room_id).await, 1);
+
+    // 不同用户用同一个键互不影响
+    let other = send(&service, room_id, owner, &key).await.unwrap();
+    assert_ne!(other.id, first.id);
+    assert_eq!(message_count(&pool, room_id).await, 2);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn retry_under_slow_mode_returns_original_message() {
+    let pool = setup_test_db().await;
+    let service = chat_service_with(
+        &pool,
+        Arc::new(TestClock::new()),
+        Some(Arc::new(SlowModeLimiter::default())),
+    );
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let key = Uuid::new_v4().to_string();
+
+    let first = send(&service, room_id, member, &key).await.unwrap();
+    for _ in 0..3 {
+        let retried = send(&service, room_id, member, &key).await.unwrap();
+        assert_eq!(retried, first);
+    }
+    assert_eq!(message_count(&pool, room_id).await, 1);
+
+    // 新的键才是新消息，受慢速模式限制
+    assert!(matches!(
+        send(&service, room_id, member, &Uuid::new_v4().to_string()).await,
+        Err(ApplicationError::RateLimited(
+            RateLimitError::RoomRateLimitExceeded { .. }
+        ))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn key_reused_in_another_room_is_rejected() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let first_room = create_room(&service, owner, member).await;
+    let second_room = create_room(&service, owner, member).await;
+    let key = Uuid::new_v4().to_string();
+
+    send(&service, first_room, member, &key).await.unwrap();
+    assert!(matches!(
+        send(&service, second_room, member, &key).await,
+        Err(ApplicationError::Domain(DomainError::IdempotencyKeyReused))
+    ));
+    assert_eq!(message_count(&pool, second_room).await, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn expired_key_sends_new_message() {
+    let pool = setup_test_db().await;
+    let clock = Arc::new(TestClock::new());
+    let service = chat_service(&pool, clock.clone());
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let key = Uuid::new_v4().to_string();
+
+    let first = send(&service, room_id, member, &key).await.unwrap();
+    clock.advance(Duration::seconds(TTL_SECS as i64 + 1));
+
+    let second = send(&service, room_id, member, &key).await.unwrap();
+    assert_ne!(second.id, first.id);
+    assert_eq!(message_count(&pool, room_id).await, 2);
+
+    // 过期后重新占用的键指向新消息
+    let retried = send(&service, room_id, member, &key).await.unwrap();
+    assert_eq!(retried.id, second.id);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn invalid_key_is_rejected() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+
+    for key in ["", "has space", &"k".repeat(256)] {
+        let err = send(&service, room_id, member, key).await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ApplicationError::Domain(DomainError::InvalidArgument {
+                    field: "idempotency_key",
+                    ..
+                })
+            ),
+            "unexpected error for {key:?}: {err:?}"
+        );
+    }
+    assert_eq!(message_count(&pool, room_id).await, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn delete_expired_removes_only_expired_keys() {
+    let pool = setup_test_db().await;
+    let repository = PgStorage::new(pool.clone()).idempotency_key_repository;
+    let user_id = UserId::from(create_user(&pool).await);
+    let now = OffsetDateTime::now_utc();
+
+    let expired_message = Uuid::new_v4().into();
+    let live_message = Uuid::new_v4().into();
+    repository
+        .claim(
+            user_id,
+            "expired",
+            expired_message,
+            now - Duration::hours(2),
+            now - Duration::hours(1),
+        )
+        .await
+        .unwrap();
+    repository
+        .claim(user_id, "live", live_message, now, now + Duration::hours(1))
+        .await
+        .unwrap();
+
+    assert!(repository.delete_expired(now).await.unwrap() >= 1);
+    assert_eq!(
+        repository.find(user_id, "expired", now).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        repository.find(user_id, "live", now).await.unwrap(),
+        Some(live_message)
+    );
+}
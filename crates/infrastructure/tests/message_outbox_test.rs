@@ -0,0 +1,311 @@
+//! 消息广播发件箱测试
+//!
+//! 验证：消息和发件箱记录一起提交，写库后进程退出（分发任务没跑）的消息在分发任务启动后照样广播；
+//! 新记录通过 LISTEN/NOTIFY 立即唤醒分发任务；发布失败按次数重试后进入死信，重新入队后发布成功
+mod support;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::{
+    broadcaster::BroadcastError,
+    repository::{MessageRepository, OutboxRepository, PaginationParams},
+    services::{ChatService, ChatServiceDependencies, CreateRoomRequest, SendMessageRequest},
+    LocalMessageBroadcaster, MessageBroadcast, MessageBroadcaster, MessageStream, WebSocketMessage,
+};
+use async_trait::async_trait;
+use config::OutboxConfig;
+use domain::{
+    ChatRoomVisibility, Message, MessageContent, MessageId, MessageType, OutboxStatus, RoomId,
+    UserId,
+};
+use infrastructure::{OutboxDispatcher, PgMessageRepository, PgOutboxRepository};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+fn outbox_config() -> OutboxConfig {
+    OutboxConfig {
+        enabled: true,
+        max_attempts: 2,
+        retry_backoff_ms: 0,
+        ..OutboxConfig::default()
+    }
+}
+
+fn chat_service(pool: &PgPool, broadcaster: Arc<dyn MessageBroadcaster>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster,
+        message_config: config::MessageConfig {
+            outbox: outbox_config(),
+            ..config::MessageConfig::default()
+        },
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 发件箱表在所有测试之间共享，分发任务会领取别的测试留下的记录：
+/// 本文件的测试逐个运行，各自写入记录前先把遗留的到期记录分发掉，保证领取到的批次里有自己的记录
+static OUTBOX_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+async fn drain_due(dispatcher: &OutboxDispatcher) {
+    while dispatcher.dispatch_due().await.unwrap() > 0 {}
+}
+
+fn dispatcher(pool: &PgPool, broadcaster: Arc<dyn MessageBroadcaster>) -> OutboxDispatcher {
+    OutboxDispatcher::new(
+        Arc::new(PgOutboxRepository::new(pool.clone())),
+        Arc::new(PgMessageRepository::new(pool.clone())),
+        broadcaster,
+        outbox_config(),
+    )
+}
+
+async fn create_room(service: &ChatService, owner_id: Uuid) -> RoomId {
+    service
+        .create_room(CreateRoomRequest {
+            name: format!("outbox-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap()
+        .id
+}
+
+async fn send(service: &ChatService, room_id: RoomId, sender_id: Uuid) -> Message {
+    service
+        .send_message(SendMessageRequest {
+            room_id: Uuid::from(room_id),
+            sender_id,
+            content: "through the outbox".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap()
+}
+
+async fn outbox_state(pool: &PgPool, message_id: MessageId) -> (OutboxStatus, i32) {
+    sqlx::query_as("SELECT status, attempts FROM message_outbox WHERE message_id = $1")
+        .bind(Uuid::from(message_id))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// 等待房间流里的下一条聊天消息
+async fn next_chat_message(stream: &mut MessageStream, within: Duration) -> Option<MessageId> {
+    tokio::time::timeout(within, async {
+        loop {
+            let broadcast = stream.recv().await?;
+            if let WebSocketMessage::ChatMessage(message) = broadcast.message {
+                return Some(message.id);
+            }
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// 对指定房间的发布失败，其他房间照常发布；测试库里其他测试留下的记录不受影响
+struct FlakyBroadcaster {
+    inner: LocalMessageBroadcaster,
+    failing_room: RoomId,
+    failing: AtomicBool,
+}
+
+#[async_trait]
+impl MessageBroadcaster for FlakyBroadcaster {
+    async fn broadcast(&self, payload: MessageBroadcast) -> Result<(), BroadcastError> {
+        if payload.room_id == self.failing_room && self.failing.load(Ordering::SeqCst) {
+            return Err(BroadcastError::unavailable("broker down"));
+        }
+        self.inner.broadcast(payload).await
+    }
+
+    async fn subscribe(&self, room_id: RoomId) -> Result<MessageStream, BroadcastError> {
+        self.inner.subscribe(room_id).await
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn committed_message_is_broadcast_once_dispatcher_runs() {
+    let _guard = OUTBOX_LOCK.lock().await;
+    let pool = setup_test_db().await;
+    let broadcaster = Arc::new(LocalMessageBroadcaster::new(16));
+    let service = chat_service(&pool, broadcaster.clone());
+    let dispatcher = dispatcher(&pool, broadcaster.clone());
+    drain_due(&dispatcher).await;
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    // 分发任务没跑，相当于消息写库后进程立即退出：消息已落库但没有广播
+    let message = send(&service, room_id, owner).await;
+    assert_eq!(
+        outbox_state(&pool, message.id).await,
+        (OutboxStatus::Pending, 0)
+    );
+    assert_eq!(
+        next_chat_message(&mut stream, Duration::from_millis(100)).await,
+        None
+    );
+
+    // 重启后的分发任务把它发出去
+    dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(
+        next_chat_message(&mut stream, Duration::from_secs(1)).await,
+        Some(message.id)
+    );
+    assert_eq!(
+        outbox_state(&pool, message.id).await,
+        (OutboxStatus::Dispatched, 1)
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn notification_wakes_dispatcher_before_next_poll() {
+    let _guard = OUTBOX_LOCK.lock().await;
+    let pool = setup_test_db().await;
+    let broadcaster = Arc::new(LocalMessageBroadcaster::new(16));
+    let service = chat_service(&pool, broadcaster.clone());
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+
+    // 轮询间隔远大于等待时间，只有 NOTIFY 能及时唤醒
+    let task = OutboxDispatcher::new(
+        Arc::new(PgOutboxRepository::new(pool.clone())),
+        Arc::new(PgMessageRepository::new(pool.clone())),
+        broadcaster.clone(),
+        OutboxConfig {
+            poll_interval_ms: 60_000,
+            ..outbox_config()
+        },
+    )
+    .spawn(pool.clone());
+    // 等分发任务开始监听
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let message = send(&service, room_id, owner).await;
+    assert_eq!(
+        next_chat_message(&mut stream, Duration::from_secs(5)).await,
+        Some(message.id)
+    );
+    task.abort();
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn failed_publish_dead_letters_and_requeue_redelivers() {
+    let _guard = OUTBOX_LOCK.lock().await;
+    let pool = setup_test_db().await;
+    let owner = UserId::from(create_user(&pool).await);
+    let room_id = RoomId::from(Uuid::new_v4());
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private) VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(Uuid::from(room_id))
+    .bind(format!(
+        "outbox_room_{}",
+        &Uuid::from(room_id).to_string()[0..8]
+    ))
+    .bind(Uuid::from(owner))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let broadcaster = Arc::new(FlakyBroadcaster {
+        inner: LocalMessageBroadcaster::new(16),
+        failing_room: room_id,
+        failing: AtomicBool::new(true),
+    });
+    let mut stream = broadcaster.subscribe(room_id).await.unwrap();
+    let dispatcher = dispatcher(&pool, broadcaster.clone());
+    let outbox = PgOutboxRepository::new(pool.clone());
+    drain_due(&dispatcher).await;
+
+    let message_id = PgMessageRepository::new(pool.clone())
+        .create_with_outbox(Message {
+            id: MessageId::from(Uuid::new_v4()),
+            room_id,
+            sender_id: owner,
+            content: MessageContent::new("will fail").unwrap(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            thread_root_id: None,
+            seq: 0,
+            created_at: OffsetDateTime::now_utc(),
+            last_revision: None,
+            version: 1,
+            is_deleted: false,
+            encrypted: false,
+        })
+        .await
+        .unwrap();
+
+    // 第一次失败后等待重试，第二次失败后用尽次数进入死信
+    dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(
+        outbox_state(&pool, message_id).await,
+        (OutboxStatus::Pending, 1)
+    );
+    dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(
+        outbox_state(&pool, message_id).await,
+        (OutboxStatus::DeadLetter, 2)
+    );
+
+    // 死信不再被领取
+    dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(
+        outbox_state(&pool, message_id).await,
+        (OutboxStatus::DeadLetter, 2)
+    );
+
+    let dead = outbox
+        .list_dead_letters(PaginationParams::new(1000))
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|entry| entry.message_id == message_id)
+        .expect("死信列表里应有这条记录");
+    assert_eq!(
+        dead.last_error.as_deref(),
+        Some("broadcast unavailable: broker down")
+    );
+
+    // 恢复后重新入队，再分发一次即可送达
+    broadcaster.failing.store(false, Ordering::SeqCst);
+    let requeued = outbox
+        .requeue(dead.id, OffsetDateTime::now_utc())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(requeued.status, OutboxStatus::Pending);
+    assert_eq!(requeued.attempts, 0);
+    assert!(outbox
+        .requeue(dead.id, OffsetDateTime::now_utc())
+        .await
+        .unwrap()
+        .is_none());
+
+    dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(
+        next_chat_message(&mut stream, Duration::from_secs(1)).await,
+        Some(message_id)
+    );
+    assert_eq!(
+        outbox_state(&pool, message_id).await,
+        (OutboxStatus::Dispatched, 1)
+    );
+}
@@ -1,7 +1,10 @@
-use application::repository::MessageRepository;
+use application::repository::{MessageRepository, PaginationParams, RoomMemberRepository};
 use chrono::Utc;
-use domain::{Message, MessageContent, MessageId, MessageType, RoomId, UserId};
-use infrastructure::{create_pg_pool, PgMessageRepository};
+use domain::{
+    Message, MessageContent, MessageId, MessageType, RepositoryError, RoomId, RoomMember, RoomRole,
+    UserId,
+};
+use infrastructure::{create_pg_pool, PgMessageRepository, PgRoomMemberRepository};
 use sqlx::PgPool;
 use time::OffsetDateTime;
 use uuid::Uuid;
@@ -72,9 +75,13 @@ fn create_test_message(room_id: RoomId, sender_id: UserId, content: &str) -> Mes
         content: MessageContent::new(content.to_string()).unwrap(),
         message_type: MessageType::Text,
         reply_to: None,
+        thread_root_id: None,
+        seq: 0,
         created_at: OffsetDateTime::now_utc(),
         last_revision: None,
+        version: 1,
         is_deleted: false,
+        encrypted: false,
     }
 }
 
@@ -169,6 +176,290 @@ async fn test_get_messages_since() {
     assert_eq!(new_messages[1].content.as_str(), "New message 2");
 }
 
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_ordering_survives_clock_skew() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, sender_id) = create_test_data(&pool).await;
+    let room_id = RoomId::from(room_id);
+    let sender_id = UserId::from(sender_id);
+
+    // 模拟两个实例交替写入：实例B的时钟比实例A慢10分钟
+    let now = OffsetDateTime::now_utc();
+    let skews = [
+        time::Duration::ZERO,
+        time::Duration::minutes(-10),
+        time::Duration::seconds(1),
+        time::Duration::minutes(-9),
+    ];
+    for (i, skew) in skews.iter().enumerate() {
+        let mut message = create_test_message(room_id, sender_id, &format!("Skewed {}", i + 1));
+        message.created_at = now + *skew;
+        repo.save_message(message).await.unwrap();
+    }
+
+    // 最近消息按写入顺序倒序，与created_at无关
+    let recent = repo.get_recent_messages(room_id, 10, None).await.unwrap();
+    let contents: Vec<&str> = recent.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Skewed 4", "Skewed 3", "Skewed 2", "Skewed 1"]);
+    assert!(recent.windows(2).all(|pair| pair[0].seq > pair[1].seq));
+
+    // before游标分页同样基于序列号
+    let older = repo
+        .get_recent_messages(room_id, 10, Some(recent[1].id))
+        .await
+        .unwrap();
+    let contents: Vec<&str> = older.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Skewed 2", "Skewed 1"]);
+
+    // 序列号补偿按写入顺序返回
+    let after = repo
+        .find_after_sequence(room_id, recent[3].seq, PaginationParams::new(10))
+        .await
+        .unwrap();
+    let contents: Vec<&str> = after.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Skewed 2", "Skewed 3", "Skewed 4"]);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_unread_count_after_read_receipt() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+    let member_repo = PgRoomMemberRepository::new(pool.clone());
+
+    let (room_id, user_id) = create_test_data(&pool).await;
+    let room_id = RoomId::from(room_id);
+    let user_id = UserId::from(user_id);
+    member_repo
+        .upsert(RoomMember::new(
+            room_id,
+            user_id,
+            RoomRole::Owner,
+            OffsetDateTime::now_utc(),
+        ))
+        .await
+        .unwrap();
+
+    let mut ids = Vec::new();
+    for i in 1..=3 {
+        let message = create_test_message(room_id, user_id, &format!("Message {}", i));
+        ids.push(repo.create(message).await.unwrap());
+    }
+    assert_eq!(
+        repo.count_unread_for_member(room_id, user_id)
+            .await
+            .unwrap(),
+        3
+    );
+
+    // 标记第二条为已读，只剩第三条未读
+    member_repo
+        .update_last_read(room_id, user_id, ids[1])
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.count_unread_for_member(room_id, user_id)
+            .await
+            .unwrap(),
+        1
+    );
+
+    assert_eq!(repo.count_after(room_id, Some(ids[1])).await.unwrap(), 1);
+    assert_eq!(repo.count_after(room_id, None).await.unwrap(), 3);
+
+    // 旧回执不能让已读位置后退
+    member_repo
+        .update_last_read(room_id, user_id, ids[0])
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.count_unread_for_member(room_id, user_id)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_search_in_room_ranks_relevant_messages() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, user_id) = create_test_data(&pool).await;
+    let room_id = RoomId::from(room_id);
+    let user_id = UserId::from(user_id);
+
+    for content in [
+        "rust async runtime",
+        "python tips and tricks",
+        "rust ownership rust borrowing rust lifetimes",
+        "weekend plans",
+    ] {
+        repo.create(create_test_message(room_id, user_id, content))
+            .await
+            .unwrap();
+    }
+    let deleted = repo
+        .create(create_test_message(room_id, user_id, "rust deleted"))
+        .await
+        .unwrap();
+    repo.soft_delete(deleted).await.unwrap();
+
+    // 命中次数多的排在前面，已删除和不相关的消息不返回
+    let results = repo
+        .search_in_room(room_id, "rust", PaginationParams::new(10))
+        .await
+        .unwrap();
+    let contents: Vec<&str> = results.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(
+        contents,
+        [
+            "rust ownership rust borrowing rust lifetimes",
+            "rust async runtime"
+        ]
+    );
+
+    let results = repo
+        .search_in_room(room_id, "nothing-matches", PaginationParams::new(10))
+        .await
+        .unwrap();
+    assert!(results.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_thread_keeps_replies_after_root_deleted() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, user_id) = create_test_data(&pool).await;
+    let room_id = RoomId::from(room_id);
+    let user_id = UserId::from(user_id);
+
+    // 两级回复链：root <- reply1 <- reply2，都归属 root 的线程
+    let root = create_test_message(room_id, user_id, "root");
+    repo.create(root.clone()).await.unwrap();
+    let mut reply1 = create_test_message(room_id, user_id, "reply 1");
+    reply1.reply_in_thread(&root);
+    repo.create(reply1.clone()).await.unwrap();
+    let mut reply2 = create_test_message(room_id, user_id, "reply 2");
+    reply2.reply_in_thread(&reply1);
+    repo.create(reply2.clone()).await.unwrap();
+    assert_eq!(reply2.thread_root_id, Some(root.id));
+
+    let thread = repo
+        .find_thread(root.id, PaginationParams::new(10))
+        .await
+        .unwrap();
+    let contents: Vec<&str> = thread
+        .iter()
+        .map(|reply| reply.message.content.as_str())
+        .collect();
+    assert_eq!(contents, ["reply 1", "reply 2"]);
+    assert!(thread.iter().all(|reply| !reply.parent_deleted));
+    assert_eq!(repo.thread_reply_count(root.id).await.unwrap(), 2);
+
+    // 删除根消息：回复仍在线程里，直接回复根的那条带上标记
+    repo.soft_delete(root.id).await.unwrap();
+    let thread = repo
+        .find_thread(root.id, PaginationParams::new(10))
+        .await
+        .unwrap();
+    assert_eq!(thread.len(), 2);
+    assert!(thread[0].parent_deleted);
+    assert!(!thread[1].parent_deleted);
+    assert_eq!(repo.thread_reply_count(root.id).await.unwrap(), 2);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_concurrent_edits_with_stale_version_conflict() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, sender_id) = create_test_data(&pool).await;
+    let message = create_test_message(RoomId::from(room_id), UserId::from(sender_id), "draft");
+    repo.create(message.clone()).await.unwrap();
+
+    // 两个管理员读到同一个版本后分别编辑
+    let stored = repo.find_by_id(message.id).await.unwrap().unwrap();
+    assert_eq!(stored.version, 1);
+    let mut first = stored.clone();
+    first
+        .edit(
+            MessageContent::new("first edit".to_string()).unwrap(),
+            OffsetDateTime::now_utc(),
+        )
+        .unwrap();
+    let mut second = stored.clone();
+    second
+        .edit(
+            MessageContent::new("second edit".to_string()).unwrap(),
+            OffsetDateTime::now_utc(),
+        )
+        .unwrap();
+
+    repo.update(first, stored.version).await.unwrap();
+    let result = repo.update(second, stored.version).await;
+    assert!(matches!(result, Err(RepositoryError::Conflict)));
+
+    // 先到的编辑生效，编辑历史保持不变
+    let current = repo.find_by_id(message.id).await.unwrap().unwrap();
+    assert_eq!(current.version, 2);
+    assert_eq!(current.content.as_str(), "first edit");
+    assert_eq!(
+        current
+            .last_revision
+            .as_ref()
+            .map(|revision| revision.content.as_str()),
+        Some("draft")
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_edit_history_keeps_every_revision() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, sender_id) = create_test_data(&pool).await;
+    let message = create_test_message(RoomId::from(room_id), UserId::from(sender_id), "v1");
+    repo.create(message.clone()).await.unwrap();
+
+    // 连续编辑三次，每次都基于最新版本
+    for content in ["v2", "v3", "v4"] {
+        let mut current = repo.find_by_id(message.id).await.unwrap().unwrap();
+        let expected_version = current.version;
+        current
+            .edit(
+                MessageContent::new(content.to_string()).unwrap(),
+                OffsetDateTime::now_utc(),
+            )
+            .unwrap();
+        repo.update(current, expected_version).await.unwrap();
+    }
+
+    // 三个旧版本按编辑顺序全部保留，当前内容和最近一次修订不变
+    let revisions = repo.revisions(message.id).await.unwrap();
+    let contents: Vec<&str> = revisions
+        .iter()
+        .map(|revision| revision.content.as_str())
+        .collect();
+    assert_eq!(contents, ["v1", "v2", "v3"]);
+    assert!(revisions
+        .windows(2)
+        .all(|pair| pair[0].updated_at <= pair[1].updated_at));
+
+    let current = repo.find_by_id(message.id).await.unwrap().unwrap();
+    assert_eq!(current.content.as_str(), "v4");
+    assert_eq!(current.version, 4);
+    assert_eq!(current.last_revision.as_ref(), revisions.last());
+}
+
 #[tokio::test]
 #[ignore = "requires database"]
 async fn test_performance_single_message_under_10ms() {
@@ -192,3 +483,53 @@ async fn test_performance_single_message_under_10ms() {
         duration.as_millis()
     );
 }
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_find_page_by_room_cursor_pages() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, sender_id) = create_test_data(&pool).await;
+    let room_id = RoomId::from(room_id);
+    let sender_id = UserId::from(sender_id);
+
+    for i in 1..=5 {
+        let message = create_test_message(room_id, sender_id, &format!("Page {}", i));
+        repo.save_message(message).await.unwrap();
+    }
+
+    // 满页：还有更早的消息，游标指向本页最旧一条
+    let first = repo.find_page_by_room(room_id, 3, None).await.unwrap();
+    let contents: Vec<&str> = first.items.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Page 5", "Page 4", "Page 3"]);
+    assert!(first.has_more);
+    assert_eq!(first.next_cursor, Some(first.items[2].id));
+
+    // 最后一页不满：没有更多，也没有游标
+    let last = repo
+        .find_page_by_room(room_id, 3, first.next_cursor)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = last.items.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["Page 2", "Page 1"]);
+    assert!(!last.has_more);
+    assert_eq!(last.next_cursor, None);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_find_page_by_room_empty_room() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+
+    let (room_id, _) = create_test_data(&pool).await;
+    let page = repo
+        .find_page_by_room(RoomId::from(room_id), 3, None)
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+    assert!(!page.has_more);
+    assert_eq!(page.next_cursor, None);
+}
@@ -0,0 +1,134 @@
+mod support;
+
+use application::repository::MessageRepository;
+use domain::{Message, MessageContent, MessageId, MessageType, RoomId, UserId};
+use infrastructure::PgMessageRepository;
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use support::setup_test_db;
+
+/// 创建用户和房间，返回 (房间ID, 用户)
+async fn create_room(pool: &PgPool) -> (RoomId, UserId) {
+    let user_id = Uuid::new_v4();
+    let suffix = &user_id.to_string()[0..8];
+    sqlx::query(
+        "INSERT INTO users (id, username, email, password_hash, status)
+         VALUES ($1, $2, $3, 'hashed_password', 'active'::user_status)",
+    )
+    .bind(user_id)
+    .bind(format!("retention_{suffix}"))
+    .bind(format!("retention_{suffix}@example.com"))
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("retention_room_{suffix}"))
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+
+    (RoomId::from(room_id), UserId::from(user_id))
+}
+
+async fn create_message(
+    repo: &PgMessageRepository,
+    room_id: RoomId,
+    sender: UserId,
+    created_at: OffsetDateTime,
+    reply_to: Option<MessageId>,
+) -> MessageId {
+    repo.create(Message {
+        id: MessageId::from(Uuid::new_v4()),
+        room_id,
+        sender_id: sender,
+        content: MessageContent::new("retention").unwrap(),
+        message_type: MessageType::Text,
+        reply_to,
+        thread_root_id: None,
+        seq: 0,
+        created_at,
+        last_revision: None,
+        version: 1,
+        is_deleted: false,
+        encrypted: false,
+    })
+    .await
+    .expect("Failed to create test message")
+}
+
+async fn count_for_message(pool: &PgPool, table: &str, message_id: MessageId) -> i64 {
+    sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM {table} WHERE message_id = $1"
+    ))
+    .bind(Uuid::from(message_id))
+    .fetch_one(pool)
+    .await
+    .expect("Failed to count rows")
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_purge_removes_only_expired_messages() {
+    let pool = setup_test_db().await;
+    let repo = PgMessageRepository::new(pool.clone());
+    let (room_id, sender) = create_room(&pool).await;
+    let now = OffsetDateTime::now_utc();
+
+    let old = create_message(&repo, room_id, sender, now - Duration::days(40), None).await;
+    let old_deleted = create_message(&repo, room_id, sender, now - Duration::days(35), None).await;
+    repo.soft_delete(old_deleted).await.unwrap();
+    let recent = create_message(&repo, room_id, sender, now - Duration::days(1), Some(old)).await;
+
+    // 过期消息上的投递记录和回应随消息一起删除
+    sqlx::query("INSERT INTO message_deliveries (message_id, user_id) VALUES ($1, $2)")
+        .bind(Uuid::from(old))
+        .bind(Uuid::from(sender))
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO message_reactions (message_id, user_id, emoji) VALUES ($1, $2, '👍')")
+        .bind(Uuid::from(old))
+        .bind(Uuid::from(sender))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let purged = repo
+        .purge_older_than(now - Duration::days(30))
+        .await
+        .unwrap();
+    assert!(purged >= 2);
+
+    assert!(repo.find_by_id(old).await.unwrap().is_none());
+    assert!(repo.find_by_id(old_deleted).await.unwrap().is_none());
+    assert_eq!(count_for_message(&pool, "message_deliveries", old).await, 0);
+    assert_eq!(count_for_message(&pool, "message_reactions", old).await, 0);
+
+    // 未过期的回复保留，只是引用被置空
+    let kept = repo
+        .find_by_id(recent)
+        .await
+        .unwrap()
+        .expect("recent message should be kept");
+    assert_eq!(kept.reply_to, None);
+
+    // 再次清理没有可删的
+    let purged_again = repo
+        .purge_older_than(now - Duration::days(30))
+        .await
+        .unwrap();
+    assert!(repo.find_by_id(recent).await.unwrap().is_some());
+    assert_eq!(
+        purged_again, 0,
+        "expired messages should all be removed in the first run"
+    );
+}
@@ -0,0 +1,178 @@
+//! 通知中心测试
+//!
+//! 验证：邀请和角色调整会给当事人发通知；通知可以单条或全部标记已读、删除；
+//! 操作别人的通知和不存在的一样返回 NotificationNotFound；未读数随之变化
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    services::{
+        ChatService, CreateRoomRequest, InviteMemberRequest, JoinRoomRequest, NotificationService,
+        NotificationServiceDependencies, UpdateMemberRoleRequest,
+    },
+    ApplicationError, SystemClock,
+};
+use domain::{ChatRoomVisibility, DomainError, Notification, RoomRole, UserId};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+fn notification_service(pool: &PgPool) -> NotificationService {
+    NotificationService::new(NotificationServiceDependencies {
+        notification_repository: PgStorage::new(pool.clone()).notification_repository,
+        clock: Arc::new(SystemClock),
+    })
+}
+
+async fn create_room(service: &ChatService, owner_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("notify-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+async fn invite(service: &ChatService, room_id: Uuid, inviter_id: Uuid, invitee_id: Uuid) {
+    service
+        .invite_member(InviteMemberRequest {
+            room_id,
+            inviter_id,
+            invitee_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+}
+
+fn assert_not_found(err: ApplicationError) {
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::NotificationNotFound)
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn invite_and_role_change_notify_the_member() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let joiner_id = create_user(&pool).await;
+    let chat = chat_service(&pool);
+    let notifications = notification_service(&pool);
+    let room_id = create_room(&chat, owner_id).await;
+
+    invite(&chat, room_id, owner_id, member_id).await;
+    // 自己加入房间不产生通知
+    chat.join_room(JoinRoomRequest {
+        room_id,
+        user_id: joiner_id,
+        password: None,
+    })
+    .await
+    .unwrap();
+    assert_eq!(notifications.unread_count(joiner_id).await.unwrap(), 0);
+
+    chat.update_member_role(UpdateMemberRoleRequest {
+        room_id,
+        operator_id: owner_id,
+        target_user_id: member_id,
+        role: RoomRole::Admin,
+    })
+    .await
+    .unwrap();
+
+    let listed = notifications.list(member_id, true, 1, 20).await.unwrap();
+    assert_eq!(listed.total_count, 2);
+    let kinds: Vec<&str> = listed.items.iter().map(|n| n.kind.as_str()).collect();
+    assert!(kinds.contains(&Notification::ROOM_INVITE));
+    assert!(kinds.contains(&Notification::ROLE_CHANGED));
+    let role_changed = listed
+        .items
+        .iter()
+        .find(|n| n.kind == Notification::ROLE_CHANGED)
+        .unwrap();
+    assert_eq!(role_changed.actor_id, Some(UserId::from(owner_id)));
+    assert_eq!(role_changed.room_id.map(Uuid::from), Some(room_id));
+    assert_eq!(role_changed.data["role"], "Admin");
+    assert_eq!(notifications.unread_count(member_id).await.unwrap(), 2);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn notifications_are_marked_read_and_deleted_by_their_owner_only() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let chat = chat_service(&pool);
+    let notifications = notification_service(&pool);
+
+    for _ in 0..3 {
+        let room_id = create_room(&chat, owner_id).await;
+        invite(&chat, room_id, owner_id, member_id).await;
+    }
+    let unread = notifications
+        .list(member_id, true, 1, 20)
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(unread.len(), 3);
+
+    // 别人的通知和不存在的一样
+    assert_not_found(
+        notifications
+            .mark_read(owner_id, unread[0].id)
+            .await
+            .unwrap_err(),
+    );
+    assert_not_found(
+        notifications
+            .delete(owner_id, unread[0].id)
+            .await
+            .unwrap_err(),
+    );
+    assert_not_found(
+        notifications
+            .mark_read(member_id, Uuid::new_v4())
+            .await
+            .unwrap_err(),
+    );
+    assert_eq!(notifications.unread_count(member_id).await.unwrap(), 3);
+
+    let read = notifications
+        .mark_read(member_id, unread[0].id)
+        .await
+        .unwrap();
+    assert!(read.is_read());
+    // 重复标记保持第一次的已读时间
+    let again = notifications
+        .mark_read(member_id, unread[0].id)
+        .await
+        .unwrap();
+    assert_eq!(again.read_at, read.read_at);
+    assert_eq!(notifications.unread_count(member_id).await.unwrap(), 2);
+
+    notifications.delete(member_id, unread[1].id).await.unwrap();
+    assert_not_found(
+        notifications
+            .delete(member_id, unread[1].id)
+            .await
+            .unwrap_err(),
+    );
+    assert_eq!(notifications.unread_count(member_id).await.unwrap(), 1);
+
+    assert_eq!(notifications.mark_all_read(member_id).await.unwrap(), 1);
+    assert_eq!(notifications.unread_count(member_id).await.unwrap(), 0);
+
+    let all = notifications.list(member_id, false, 1, 20).await.unwrap();
+    assert_eq!(all.total_count, 2);
+    assert!(all.items.iter().all(Notification::is_read));
+}
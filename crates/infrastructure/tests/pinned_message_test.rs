@@ -0,0 +1,257 @@
+//! 置顶消息测试
+//!
+//! 验证：房间管理员可以置顶和取消置顶，普通成员不行；每个房间的置顶数有上限；
+//! 其他房间的消息不能置顶；置顶和取消置顶都会广播
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        PinMessageRequest, SendMessageRequest,
+    },
+    ApplicationError, MessageRepository, WebSocketMessage,
+};
+use domain::{
+    ChatRoomVisibility, DomainError, MessageId, MessageType, RepositoryError, RoomId, UserId,
+};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db, RecordingBroadcaster};
+
+/// 置顶相关的广播，其他广播忽略
+fn pin_events(broadcaster: &RecordingBroadcaster) -> Vec<WebSocketMessage> {
+    broadcaster
+        .messages()
+        .into_iter()
+        .filter(|message| {
+            matches!(
+                message,
+                WebSocketMessage::MessagePinned { .. } | WebSocketMessage::MessageUnpinned { .. }
+            )
+        })
+        .collect()
+}
+
+fn chat_service(
+    pool: &PgPool,
+    broadcaster: Arc<RecordingBroadcaster>,
+    max_pinned_per_room: usize,
+) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster,
+        message_config: config::MessageConfig {
+            max_pinned_per_room,
+            ..config::MessageConfig::default()
+        },
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("pin-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid) -> Uuid {
+    let message = service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: "announcement".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(message.id)
+}
+
+fn pin(room_id: Uuid, message_id: Uuid, operator_id: Uuid) -> PinMessageRequest {
+    PinMessageRequest {
+        room_id,
+        message_id,
+        operator_id,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn admins_pin_and_unpin_messages() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let service = chat_service(&pool, broadcaster.clone(), 10);
+    let room_id = create_room(&service, owner_id, member_id).await;
+    let message_id = send(&service, room_id, member_id).await;
+
+    // 普通成员不能置顶
+    let err = service
+        .pin_message(pin(room_id, message_id, member_id))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::InsufficientPermissions)
+    ));
+
+    let pinned = service
+        .pin_message(pin(room_id, message_id, owner_id))
+        .await
+        .unwrap();
+    assert_eq!(Uuid::from(pinned.message.id), message_id);
+    assert_eq!(pinned.pinned_by, Some(UserId::from(owner_id)));
+
+    // 重复置顶是幂等的，不再广播
+    service
+        .pin_message(pin(room_id, message_id, owner_id))
+        .await
+        .unwrap();
+
+    // 所有成员都能看到置顶
+    let listed = service
+        .list_pinned_messages(room_id, member_id)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(Uuid::from(listed[0].message.id), message_id);
+
+    service
+        .unpin_message(pin(room_id, message_id, owner_id))
+        .await
+        .unwrap();
+    assert!(service
+        .list_pinned_messages(room_id, member_id)
+        .await
+        .unwrap()
+        .is_empty());
+    // 没有置顶的消息取消置顶是无操作
+    service
+        .unpin_message(pin(room_id, message_id, owner_id))
+        .await
+        .unwrap();
+
+    let events = pin_events(&broadcaster);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &events[0],
+        WebSocketMessage::MessagePinned { message_id: pinned_id, pinned_by, .. }
+            if Uuid::from(*pinned_id) == message_id && *pinned_by == UserId::from(owner_id)
+    ));
+    assert!(matches!(
+        &events[1],
+        WebSocketMessage::MessageUnpinned { message_id: unpinned_id, .. }
+            if Uuid::from(*unpinned_id) == message_id
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn pinning_is_capped_per_room() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let service = chat_service(&pool, Arc::new(RecordingBroadcaster::default()), 2);
+    let room_id = create_room(&service, owner_id, member_id).await;
+
+    let mut message_ids = Vec::new();
+    for _ in 0..3 {
+        message_ids.push(send(&service, room_id, owner_id).await);
+    }
+    for message_id in &message_ids[..2] {
+        service
+            .pin_message(pin(room_id, *message_id, owner_id))
+            .await
+            .unwrap();
+    }
+
+    let err = service
+        .pin_message(pin(room_id, message_ids[2], owner_id))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::PinLimitExceeded { limit: 2 })
+    ));
+
+    // 取消一个后又可以置顶
+    service
+        .unpin_message(pin(room_id, message_ids[0], owner_id))
+        .await
+        .unwrap();
+    service
+        .pin_message(pin(room_id, message_ids[2], owner_id))
+        .await
+        .unwrap();
+
+    let listed = service
+        .list_pinned_messages(room_id, owner_id)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2);
+    // 最近置顶的在前
+    assert_eq!(Uuid::from(listed[0].message.id), message_ids[2]);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn pinning_message_from_another_room_is_rejected() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let service = chat_service(&pool, Arc::new(RecordingBroadcaster::default()), 10);
+    let room_id = create_room(&service, owner_id, member_id).await;
+    let other_room_id = create_room(&service, owner_id, member_id).await;
+    let other_message_id = send(&service, other_room_id, owner_id).await;
+
+    let err = service
+        .pin_message(pin(room_id, other_message_id, owner_id))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::MessageNotFound)
+    ));
+    assert!(service
+        .list_pinned_messages(room_id, owner_id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 存储层同样拒绝跨房间置顶
+    let storage = PgStorage::new(pool.clone());
+    let err = storage
+        .message_repository
+        .pin(
+            RoomId::from(room_id),
+            MessageId::from(other_message_id),
+            UserId::from(owner_id),
+            time::OffsetDateTime::now_utc(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RepositoryError::NotFound));
+}
@@ -4,13 +4,13 @@ use application::{
     clock::SystemClock,
     repository::UserRepository,
     services::{
-        ChatService, ChatServiceDependencies, CreateRoomRequest, RegisterUserRequest, UserService,
-        UserServiceDependencies,
+        ChatService, ChatServiceDependencies, CreateRoomRequest, InviteMemberRequest,
+        RegisterUserRequest, UpdateMemberRoleRequest, UserService, UserServiceDependencies,
     },
     Clock, MessageBroadcaster,
 };
 use async_trait::async_trait;
-use domain::{ChatRoomVisibility, RoomRole, UserId};
+use domain::{ChatRoomVisibility, DomainError, RoomRole, UserId};
 use infrastructure::{repository::PgStorage, BcryptPasswordHasher};
 use sqlx::PgPool;
 use testcontainers::{runners::AsyncRunner, ContainerAsync};
@@ -76,6 +76,10 @@ async fn rbac_permission_system_works() {
         password_hasher: password_hasher.clone(),
         clock: clock.clone(),
         presence_manager: Arc::new(application::presence::memory::MemoryPresenceManager::new()),
+        email_verification_repository: storage.email_verification_repository.clone(),
+        password_reset_repository: storage.password_reset_repository.clone(),
+        email_sender: Arc::new(infrastructure::LoggingEmailSender),
+        registration_config: config::RegistrationConfig::default(),
     });
 
     // 创建聊天服务
@@ -83,10 +87,23 @@ async fn rbac_permission_system_works() {
         room_repository: storage.room_repository.clone(),
         member_repository: storage.member_repository.clone(),
         message_repository: storage.message_repository.clone(),
+        reaction_repository: storage.reaction_repository.clone(),
+        user_block_repository: storage.user_block_repository.clone(),
+        mention_repository: storage.mention_repository.clone(),
+        delivery_repository: storage.delivery_repository.clone(),
         user_repository: storage.user_repository.clone(),
+        audit_log_repository: storage.audit_log_repository.clone(),
+        file_upload_repository: storage.file_upload_repository.clone(),
+        notification_repository: storage.notification_repository.clone(),
+        idempotency_key_repository: storage.idempotency_key_repository.clone(),
+        invitation_repository: storage.invitation_repository.clone(),
+        invite_repository: storage.invite_repository.clone(),
         password_hasher: password_hasher.clone(),
         clock: clock.clone(),
         broadcaster: Arc::new(MockBroadcaster),
+        message_config: config::MessageConfig::default(),
+        room_encryption: None,
+        send_rate_limiter: None,
     });
 
     // 1. 创建测试用户
@@ -216,7 +233,108 @@ async fn rbac_permission_system_works() {
 
     println!("✅ 权限检查逻辑验证通过");
 
-    // 6. 测试数据持久化
+    // 6. 测试成员角色调整
+    println!("🔧 测试成员角色调整...");
+
+    let member_user = user_service
+        .register(RegisterUserRequest {
+            username: "member_user".to_string(),
+            email: "member@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .expect("创建成员用户");
+
+    chat_service
+        .invite_member(InviteMemberRequest {
+            room_id: room.id.into(),
+            inviter_id: regular_user.id.into(),
+            invitee_id: member_user.id.into(),
+            password: None,
+        })
+        .await
+        .expect("邀请成员");
+
+    async fn change_role(
+        chat_service: &ChatService,
+        room_id: Uuid,
+        operator: UserId,
+        target: UserId,
+        role: RoomRole,
+    ) -> Result<domain::RoomMember, application::ApplicationError> {
+        chat_service
+            .update_member_role(UpdateMemberRoleRequest {
+                room_id,
+                operator_id: operator.into(),
+                target_user_id: target.into(),
+                role,
+            })
+            .await
+    }
+    let room_uuid: Uuid = room.id.into();
+
+    // 所有者提升成员为管理员
+    let promoted = change_role(
+        &chat_service,
+        room_uuid,
+        regular_user.id,
+        member_user.id,
+        RoomRole::Admin,
+    )
+    .await
+    .expect("所有者提升管理员");
+    assert_eq!(promoted.role, RoomRole::Admin);
+
+    // 管理员不能改自己的角色，也不能改所有者的角色
+    let result = change_role(
+        &chat_service,
+        room_uuid,
+        member_user.id,
+        member_user.id,
+        RoomRole::Member,
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(application::ApplicationError::Domain(
+            DomainError::RoleChangeNotAllowed { .. }
+        ))
+    ));
+    let result = change_role(
+        &chat_service,
+        room_uuid,
+        member_user.id,
+        regular_user.id,
+        RoomRole::Member,
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(application::ApplicationError::Domain(
+            DomainError::RoleChangeNotAllowed { .. }
+        ))
+    ));
+
+    // 所有者撤销管理员
+    let demoted = change_role(
+        &chat_service,
+        room_uuid,
+        regular_user.id,
+        member_user.id,
+        RoomRole::Member,
+    )
+    .await
+    .expect("所有者撤销管理员");
+    assert_eq!(demoted.role, RoomRole::Member);
+    let role = chat_service
+        .get_user_role_in_room(room.id, member_user.id)
+        .await
+        .expect("获取成员角色");
+    assert_eq!(role, Some(RoomRole::Member));
+
+    println!("✅ 成员角色调整验证通过");
+
+    // 7. 测试数据持久化
     println!("🔧 测试数据持久化...");
 
     // 重新从数据库加载用户，验证 is_superuser 字段正确保存
@@ -0,0 +1,127 @@
+mod support;
+
+use application::repository::{MessageRepository, ReactionRepository};
+use domain::{Message, MessageContent, MessageId, MessageType, ReactionEmoji, RoomId, UserId};
+use infrastructure::{PgMessageRepository, PgReactionRepository};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use support::{create_user, setup_test_db};
+
+/// 创建房间并发送一条消息，返回 (消息ID, 发送者)
+async fn create_message(pool: &PgPool) -> (MessageId, UserId) {
+    let sender = UserId::from(create_user(pool).await);
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("reaction_room_{}", &room_id.to_string()[0..8]))
+    .bind(Uuid::from(sender))
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+
+    let message = Message {
+        id: MessageId::from(Uuid::new_v4()),
+        room_id: RoomId::from(room_id),
+        sender_id: sender,
+        content: MessageContent::new("react to me").unwrap(),
+        message_type: MessageType::Text,
+        reply_to: None,
+        thread_root_id: None,
+        seq: 0,
+        created_at: OffsetDateTime::now_utc(),
+        last_revision: None,
+        version: 1,
+        is_deleted: false,
+        encrypted: false,
+    };
+    let message_id = PgMessageRepository::new(pool.clone())
+        .create(message)
+        .await
+        .expect("Failed to create test message");
+    (message_id, sender)
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_add_reaction_aggregates_counts() {
+    let pool = setup_test_db().await;
+    let repo = PgReactionRepository::new(pool.clone());
+    let (message_id, sender) = create_message(&pool).await;
+    let other = UserId::from(create_user(&pool).await);
+    let thumbs_up = ReactionEmoji::new("👍").unwrap();
+    let party = ReactionEmoji::new("🎉").unwrap();
+
+    assert!(repo
+        .add_reaction(message_id, sender, &thumbs_up)
+        .await
+        .unwrap());
+    assert!(repo
+        .add_reaction(message_id, other, &thumbs_up)
+        .await
+        .unwrap());
+    assert!(repo.add_reaction(message_id, other, &party).await.unwrap());
+
+    let reactions = repo.reactions_for_messages(&[message_id]).await.unwrap();
+    let summary = &reactions[&message_id];
+    assert_eq!(summary.len(), 2);
+    assert_eq!(summary[0].emoji, "👍");
+    assert_eq!(summary[0].count, 2);
+    assert_eq!(summary[0].user_ids, vec![sender, other]);
+    assert_eq!(summary[1].emoji, "🎉");
+    assert_eq!(summary[1].count, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_duplicate_reaction_is_idempotent() {
+    let pool = setup_test_db().await;
+    let repo = PgReactionRepository::new(pool.clone());
+    let (message_id, sender) = create_message(&pool).await;
+    let heart = ReactionEmoji::new("❤️").unwrap();
+
+    assert!(repo.add_reaction(message_id, sender, &heart).await.unwrap());
+    assert!(!repo.add_reaction(message_id, sender, &heart).await.unwrap());
+
+    let reactions = repo.reactions_for_messages(&[message_id]).await.unwrap();
+    assert_eq!(reactions[&message_id][0].count, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_remove_reaction() {
+    let pool = setup_test_db().await;
+    let repo = PgReactionRepository::new(pool.clone());
+    let (message_id, sender) = create_message(&pool).await;
+    let other = UserId::from(create_user(&pool).await);
+    let thumbs_up = ReactionEmoji::new("👍").unwrap();
+
+    repo.add_reaction(message_id, sender, &thumbs_up)
+        .await
+        .unwrap();
+
+    // 移除别人的回应：无操作
+    assert!(!repo
+        .remove_reaction(message_id, other, &thumbs_up)
+        .await
+        .unwrap());
+    assert_eq!(
+        repo.reactions_for_messages(&[message_id]).await.unwrap()[&message_id][0].count,
+        1
+    );
+
+    // 移除自己的回应：消息不再出现在聚合结果中
+    assert!(repo
+        .remove_reaction(message_id, sender, &thumbs_up)
+        .await
+        .unwrap());
+    assert!(repo
+        .reactions_for_messages(&[message_id])
+        .await
+        .unwrap()
+        .is_empty());
+}
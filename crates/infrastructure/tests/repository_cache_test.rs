@@ -0,0 +1,147 @@
+//! 房间和用户查询缓存测试
+//!
+//! 验证：第二次查询由缓存返回（绕过缓存直接改库后仍读到旧值），经过缓存层的修改立即可见，
+//! 删除后不再返回，条目到期后重新查库；缓存往返不丢失密码哈希和令牌版本
+mod support;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use application::repository::{ChatRoomRepository, UserRepository};
+use domain::{ChatRoom, PasswordHash, RoomId, UserId};
+use infrastructure::{
+    CachedChatRoomRepository, CachedUserRepository, EntityCache, MemoryEntityCache,
+    PgChatRoomRepository, PgUserRepository,
+};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use support::setup_test_db;
+
+async fn create_user(pool: &PgPool) -> Uuid {
+    let user_id = Uuid::new_v4();
+    let suffix = &user_id.to_string()[0..8];
+    sqlx::query(
+        "INSERT INTO users (id, username, email, password_hash, status, token_version)
+         VALUES ($1, $2, $3, 'hashed_password', 'active'::user_status, 3)",
+    )
+    .bind(user_id)
+    .bind(format!("cache_{suffix}"))
+    .bind(format!("cache_{suffix}@example.com"))
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+    user_id
+}
+
+fn memory_cache() -> Arc<dyn EntityCache> {
+    Arc::new(MemoryEntityCache::new(100))
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_user_cache_serves_second_read_and_updates_write_through() {
+    let pool = setup_test_db().await;
+    let user_id = UserId::from(create_user(&pool).await);
+    let repository = CachedUserRepository::new(
+        Arc::new(PgUserRepository::new(pool.clone())),
+        memory_cache(),
+        Duration::from_secs(60),
+    );
+
+    let user = repository.find_by_id(user_id).await.unwrap().unwrap();
+    assert_eq!(user.display_name, None);
+
+    // 绕过缓存直接改库：第二次查询由缓存返回，看不到这次修改
+    sqlx::query("UPDATE users SET display_name = 'direct' WHERE id = $1")
+        .bind(Uuid::from(user_id))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cached = repository.find_by_id(user_id).await.unwrap().unwrap();
+    assert_eq!(cached, user);
+    assert_eq!(cached.password.as_str(), "hashed_password");
+    assert_eq!(cached.token_version, 3);
+
+    // 经过缓存层修改，之后立即读到新值
+    let mut changed = cached;
+    changed.display_name = Some("through cache".to_string());
+    changed.updated_at = OffsetDateTime::now_utc();
+    let updated = repository.update(changed).await.unwrap();
+    let reread = repository.find_by_id(user_id).await.unwrap().unwrap();
+    assert_eq!(reread, updated);
+    assert_eq!(reread.display_name.as_deref(), Some("through cache"));
+    assert_eq!(reread.token_version, 3);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_user_cache_entry_expires() {
+    let pool = setup_test_db().await;
+    let user_id = UserId::from(create_user(&pool).await);
+    let repository = CachedUserRepository::new(
+        Arc::new(PgUserRepository::new(pool.clone())),
+        memory_cache(),
+        Duration::from_millis(200),
+    );
+
+    repository.find_by_id(user_id).await.unwrap().unwrap();
+    sqlx::query("UPDATE users SET display_name = 'direct' WHERE id = $1")
+        .bind(Uuid::from(user_id))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let user = repository.find_by_id(user_id).await.unwrap().unwrap();
+    assert_eq!(user.display_name.as_deref(), Some("direct"));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_room_cache_invalidated_on_update_and_delete() {
+    let pool = setup_test_db().await;
+    let owner_id = UserId::from(create_user(&pool).await);
+    let repository = CachedChatRoomRepository::new(
+        Arc::new(PgChatRoomRepository::new(pool.clone())),
+        memory_cache(),
+        Duration::from_secs(60),
+    );
+
+    let room_id = RoomId::from(Uuid::new_v4());
+    let room = ChatRoom::new_private(
+        room_id,
+        format!("cache-{}", &Uuid::from(room_id).to_string()[0..8]),
+        owner_id,
+        PasswordHash::new("hashed_room_password".to_string()).unwrap(),
+        OffsetDateTime::now_utc(),
+    )
+    .unwrap();
+    repository.create(room).await.unwrap();
+
+    let room = repository.find_by_id(room_id).await.unwrap().unwrap();
+    sqlx::query("UPDATE chat_rooms SET name = name || '-direct' WHERE id = $1")
+        .bind(Uuid::from(room_id))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cached = repository.find_by_id(room_id).await.unwrap().unwrap();
+    assert_eq!(cached, room);
+    assert_eq!(
+        cached.password.as_ref().map(PasswordHash::as_str),
+        Some("hashed_room_password")
+    );
+
+    let mut changed = cached;
+    changed.name = format!("{}-renamed", room.name);
+    changed.is_closed = true;
+    let updated = repository.update(changed).await.unwrap();
+    let reread = repository.find_by_id(room_id).await.unwrap().unwrap();
+    assert_eq!(reread, updated);
+    assert!(reread.name.ends_with("-renamed"));
+    assert!(reread.is_closed);
+
+    repository.delete(room_id).await.unwrap();
+    assert!(repository.find_by_id(room_id).await.unwrap().is_none());
+}
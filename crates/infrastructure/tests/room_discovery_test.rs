@@ -0,0 +1,112 @@
+mod support;
+
+use application::repository::{ChatRoomRepository, PublicRoomQuery, RoomSortOrder};
+use infrastructure::PgChatRoomRepository;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{create_user, setup_test_db};
+
+/// 创建房间并加入 `members` 个成员
+async fn create_room(pool: &PgPool, name: &str, is_private: bool, is_closed: bool, members: usize) {
+    let owner = create_user(pool).await;
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private, password_hash, is_closed)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(room_id)
+    .bind(name)
+    .bind(owner)
+    .bind(is_private)
+    .bind(is_private.then_some("hashed_password"))
+    .bind(is_closed)
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+
+    for _ in 0..members {
+        let user_id = create_user(pool).await;
+        sqlx::query("INSERT INTO room_members (room_id, user_id, role) VALUES ($1, $2, 'member')")
+            .bind(room_id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .expect("Failed to add room member");
+    }
+}
+
+fn query(search: &str, sort: RoomSortOrder, include_closed: bool, page: u32) -> PublicRoomQuery {
+    PublicRoomQuery {
+        search: Some(search.to_string()),
+        sort,
+        include_closed,
+        page,
+        page_size: 2,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_find_public_excludes_private_and_closed_rooms() {
+    let pool = setup_test_db().await;
+    let repo = PgChatRoomRepository::new(pool.clone());
+    let tag = Uuid::new_v4().simple().to_string();
+
+    create_room(&pool, &format!("{tag}-quiet"), false, false, 1).await;
+    create_room(&pool, &format!("{tag}-busy"), false, false, 3).await;
+    create_room(&pool, &format!("{tag}-medium"), false, false, 2).await;
+    create_room(&pool, &format!("{tag}-secret"), true, false, 5).await;
+    create_room(&pool, &format!("{tag}-archived"), false, true, 4).await;
+
+    // 按成员数排序，分页返回总数
+    let first = repo
+        .find_public(&query(&tag, RoomSortOrder::MemberCount, false, 1))
+        .await
+        .unwrap();
+    assert_eq!(first.total_count, 3);
+    let names: Vec<&str> = first.items.iter().map(|r| r.room.name.as_str()).collect();
+    assert_eq!(names, [format!("{tag}-busy"), format!("{tag}-medium")]);
+    assert_eq!(first.items[0].member_count, 3);
+
+    let second = repo
+        .find_public(&query(&tag, RoomSortOrder::MemberCount, false, 2))
+        .await
+        .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.items[0].room.name, format!("{tag}-quiet"));
+
+    // 包含已关闭房间，私有房间依然不出现
+    let all = repo
+        .find_public(&PublicRoomQuery {
+            page_size: 10,
+            ..query(&tag, RoomSortOrder::CreatedAt, true, 1)
+        })
+        .await
+        .unwrap();
+    assert_eq!(all.total_count, 4);
+    assert!(all.items.iter().any(|r| r.room.is_closed));
+    assert!(all.items.iter().all(|r| !r.room.name.ends_with("-secret")));
+
+    // 子串匹配不区分大小写，LIKE 通配符按字面匹配
+    let found = repo
+        .find_public(&query(
+            &format!("{}-BUSY", tag.to_uppercase()),
+            RoomSortOrder::CreatedAt,
+            false,
+            1,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(found.total_count, 1);
+    let wildcard = repo
+        .find_public(&query(
+            &format!("{tag}%"),
+            RoomSortOrder::CreatedAt,
+            false,
+            1,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(wildcard.total_count, 0);
+}
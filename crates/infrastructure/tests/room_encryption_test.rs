@@ -0,0 +1,297 @@
+//! 房间消息静态加密测试
+//!
+//! 验证：房间开启加密后消息内容以密文入库，通过服务读取时透明解密（包括编辑历史），
+//! 开启前的旧消息保持明文可读（即使内容恰好带着密文前缀），解不开的消息以占位内容返回而不影响整页，
+//! 加密房间不提供全文检索
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    repository::{MentionRepository, MessageRepository},
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, EditMessageRequest,
+        SendMessageRequest,
+    },
+    ApplicationError, EncryptedMentionRepository, EncryptedMessageRepository, MessageCipher,
+    RoomEncryption,
+};
+use domain::{ChatRoomVisibility, DomainError, MessageType};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+const MASTER_KEY: &str = "8f2c1d0e4b6a79583c2e1f0d9b8a7c6e5d4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b";
+
+fn chat_service(pool: &PgPool, encrypted: bool) -> ChatService {
+    let storage = PgStorage::new(pool.clone());
+    let mut message_repository: Arc<dyn MessageRepository> = storage.message_repository.clone();
+    let mut mention_repository: Arc<dyn MentionRepository> = storage.mention_repository.clone();
+    let room_encryption = encrypted.then(|| {
+        Arc::new(RoomEncryption::new(
+            MessageCipher::from_hex(MASTER_KEY).unwrap(),
+            storage.room_repository.clone(),
+        ))
+    });
+    if let Some(encryption) = &room_encryption {
+        message_repository = Arc::new(EncryptedMessageRepository::new(
+            message_repository,
+            encryption.clone(),
+        ));
+        mention_repository = Arc::new(EncryptedMentionRepository::new(
+            mention_repository,
+            encryption.clone(),
+        ));
+    }
+    ChatService::new(ChatServiceDependencies {
+        message_repository,
+        mention_repository,
+        room_encryption,
+        ..chat_service_deps(pool)
+    })
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid, content: &str) -> Uuid {
+    let message = service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: content.to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(message.id)
+}
+
+async fn stored_content(pool: &PgPool, message_id: Uuid) -> String {
+    sqlx::query_scalar("SELECT content FROM messages WHERE id = $1")
+        .bind(message_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_encrypted_room_stores_ciphertext_and_reads_plaintext() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, true);
+    let owner_id = create_user(&pool).await;
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("encrypted-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    assert!(!room.encrypted);
+    let room_id = Uuid::from(room.id);
+
+    // 开启加密之前的消息保持明文
+    let legacy_id = send(&service, room_id, owner_id, "sent before encryption").await;
+
+    let room = service
+        .enable_room_encryption(room_id, owner_id)
+        .await
+        .unwrap();
+    assert!(room.encrypted);
+    // 重复开启不换密钥
+    let wrapped_key: Vec<u8> =
+        sqlx::query_scalar("SELECT encrypted_data_key FROM chat_rooms WHERE id = $1")
+            .bind(room_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    service
+        .enable_room_encryption(room_id, owner_id)
+        .await
+        .unwrap();
+    let same_key: Vec<u8> =
+        sqlx::query_scalar("SELECT encrypted_data_key FROM chat_rooms WHERE id = $1")
+            .bind(room_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(wrapped_key, same_key);
+
+    let secret_id = send(&service, room_id, owner_id, "the vault code is 4711").await;
+
+    // 库里存的是密文
+    let stored = stored_content(&pool, secret_id).await;
+    assert!(stored.starts_with(application::encryption::CIPHERTEXT_PREFIX));
+    assert!(!stored.contains("4711"));
+    assert_eq!(
+        stored_content(&pool, legacy_id).await,
+        "sent before encryption"
+    );
+
+    // 通过服务读取得到明文
+    let history = service
+        .get_history(room_id, owner_id, 50, None)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = history
+        .items
+        .iter()
+        .map(|message| message.content.as_str())
+        .collect();
+    assert_eq!(
+        contents,
+        vec!["the vault code is 4711", "sent before encryption"]
+    );
+
+    // 编辑后新旧内容都加密存储，编辑历史解密返回
+    let edited = service
+        .edit_message(EditMessageRequest {
+            room_id,
+            message_id: secret_id,
+            operator_id: owner_id,
+            content: "the vault code is 1174".to_string(),
+            expected_version: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(edited.content.as_str(), "the vault code is 1174");
+    assert!(!stored_content(&pool, secret_id).await.contains("1174"));
+    let stored_revisions: Vec<String> =
+        sqlx::query_scalar("SELECT content FROM message_revisions WHERE message_id = $1")
+            .bind(secret_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert!(stored_revisions
+        .iter()
+        .all(|content| !content.contains("4711")));
+    let revisions = service
+        .message_revisions(secret_id, owner_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        revisions.last().unwrap().content.as_str(),
+        "the vault code is 4711"
+    );
+
+    // 加密房间不提供检索
+    let search = service
+        .search_messages(room_id, owner_id, "vault", 20)
+        .await;
+    assert!(matches!(
+        search,
+        Err(ApplicationError::Domain(
+            DomainError::EncryptedRoomNotSearchable
+        ))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_encrypted_flag_decides_decryption_and_bad_rows_degrade() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, true);
+    let owner_id = create_user(&pool).await;
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("encrypted-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+
+    // 开启加密前发的明文恰好带着密文前缀，仍按明文返回
+    let lookalike = format!("{}00ff", application::encryption::CIPHERTEXT_PREFIX);
+    let lookalike_id = send(&service, room_id, owner_id, &lookalike).await;
+
+    service
+        .enable_room_encryption(room_id, owner_id)
+        .await
+        .unwrap();
+    let secret_id = send(&service, room_id, owner_id, "the vault code is 4711").await;
+    let corrupted_id = send(&service, room_id, owner_id, "soon unreadable").await;
+
+    let encrypted_flag = |message_id: Uuid| {
+        sqlx::query_scalar::<_, bool>("SELECT encrypted FROM messages WHERE id = $1")
+            .bind(message_id)
+            .fetch_one(&pool)
+    };
+    assert!(!encrypted_flag(lookalike_id).await.unwrap());
+    assert!(encrypted_flag(secret_id).await.unwrap());
+
+    // 密文损坏的消息以占位内容返回，同一页的其他消息照常解密
+    sqlx::query("UPDATE messages SET content = $2 WHERE id = $1")
+        .bind(corrupted_id)
+        .bind(format!(
+            "{}0011",
+            application::encryption::CIPHERTEXT_PREFIX
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let history = service
+        .get_history(room_id, owner_id, 50, None)
+        .await
+        .unwrap();
+    let contents: Vec<&str> = history
+        .items
+        .iter()
+        .map(|message| message.content.as_str())
+        .collect();
+    assert_eq!(
+        contents,
+        vec![
+            application::encryption::UNDECRYPTABLE_CONTENT,
+            "the vault code is 4711",
+            lookalike.as_str(),
+        ]
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_enable_encryption_requires_master_key_and_room_admin() {
+    let pool = setup_test_db().await;
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let service = chat_service(&pool, true);
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("encrypted-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    sqlx::query("INSERT INTO room_members (room_id, user_id, role) VALUES ($1, $2, 'member')")
+        .bind(room_id)
+        .bind(member_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let denied = service.enable_room_encryption(room_id, member_id).await;
+    assert!(matches!(
+        denied,
+        Err(ApplicationError::Domain(DomainError::OperationNotAllowed))
+    ));
+
+    let unconfigured = chat_service(&pool, false)
+        .enable_room_encryption(room_id, owner_id)
+        .await;
+    assert!(matches!(
+        unconfigured,
+        Err(ApplicationError::Domain(
+            DomainError::EncryptionNotConfigured
+        ))
+    ));
+}
@@ -0,0 +1,242 @@
+//! 入房邀请测试
+//!
+//! 验证：邀请不会直接加人，被邀请人接受后才加入；重复的待处理邀请被拒绝；
+//! 拒绝和撤回之后不能再接受；过期的邀请不能接受，可以重新邀请；只有管理员能邀请
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    services::{ChatService, ChatServiceDependencies, CreateInvitationRequest, CreateRoomRequest},
+    ApplicationError,
+};
+use domain::{ChatRoomVisibility, DomainError, InvitationStatus, Notification, RoomRole};
+use sqlx::PgPool;
+use time::Duration;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db, TestClock};
+
+/// 邀请的有效期（秒）
+const TTL_SECS: u64 = 60;
+
+fn chat_service(pool: &PgPool, clock: Arc<TestClock>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        clock,
+        message_config: config::MessageConfig {
+            invitation_ttl_secs: TTL_SECS,
+            ..config::MessageConfig::default()
+        },
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建的公开房间
+async fn create_room(service: &ChatService, owner_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("inv-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+fn invite(room_id: Uuid, inviter_id: Uuid, invitee_id: Uuid) -> CreateInvitationRequest {
+    CreateInvitationRequest {
+        room_id,
+        inviter_id,
+        invitee_id,
+    }
+}
+
+fn domain_error(err: ApplicationError) -> DomainError {
+    match err {
+        ApplicationError::Domain(err) => err,
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+async fn is_member(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> bool {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM room_members WHERE room_id = $1 AND user_id = $2)",
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn accepted_invitation_joins_room() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let invitee = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let invitation = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap();
+    assert_eq!(invitation.status, InvitationStatus::Pending);
+    assert!(!is_member(&pool, room_id, invitee).await);
+
+    // 被邀请人收到通知，邀请出现在待处理列表里
+    let kinds: Vec<String> = sqlx::query_scalar(
+        "SELECT kind FROM notifications WHERE user_id = $1 AND data->>'invitation_id' = $2",
+    )
+    .bind(invitee)
+    .bind(invitation.id.to_string())
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(kinds, vec![Notification::ROOM_INVITATION.to_string()]);
+    let pending = service.list_invitations(invitee).await.unwrap();
+    assert_eq!(
+        pending.iter().map(|i| i.id).collect::<Vec<_>>(),
+        vec![invitation.id]
+    );
+
+    // 同一用户同一房间不能有两条待处理邀请
+    let err = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationAlreadyPending);
+
+    // 只有被邀请人能接受
+    let err = service
+        .accept_invitation(invitation.id, owner)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationNotFound);
+
+    let member = service
+        .accept_invitation(invitation.id, invitee)
+        .await
+        .unwrap();
+    assert_eq!(member.role, RoomRole::Member);
+    assert!(is_member(&pool, room_id, invitee).await);
+    assert!(service.list_invitations(invitee).await.unwrap().is_empty());
+
+    let err = service
+        .accept_invitation(invitation.id, invitee)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationNotPending);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn declined_and_revoked_invitations_cannot_be_accepted() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let invitee = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let declined = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap();
+    let declined = service
+        .decline_invitation(declined.id, invitee)
+        .await
+        .unwrap();
+    assert_eq!(declined.status, InvitationStatus::Declined);
+    assert!(declined.responded_at.is_some());
+    let err = service
+        .accept_invitation(declined.id, invitee)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationNotPending);
+
+    // 拒绝之后可以重新邀请；被邀请人不能撤回，邀请人可以
+    let revoked = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap();
+    let err = service
+        .revoke_invitation(revoked.id, invitee)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::UserNotInRoom);
+    let revoked = service.revoke_invitation(revoked.id, owner).await.unwrap();
+    assert_eq!(revoked.status, InvitationStatus::Revoked);
+    let err = service
+        .accept_invitation(revoked.id, invitee)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationNotPending);
+    assert!(!is_member(&pool, room_id, invitee).await);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn expired_invitation_cannot_be_accepted_and_can_be_reissued() {
+    let pool = setup_test_db().await;
+    let clock = Arc::new(TestClock::new());
+    let service = chat_service(&pool, clock.clone());
+    let owner = create_user(&pool).await;
+    let invitee = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let expired = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap();
+    clock.advance(Duration::seconds(TTL_SECS as i64 + 1));
+
+    assert!(service.list_invitations(invitee).await.unwrap().is_empty());
+    let err = service
+        .accept_invitation(expired.id, invitee)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InvitationExpired);
+
+    let reissued = service
+        .invite(invite(room_id, owner, invitee))
+        .await
+        .unwrap();
+    assert_ne!(reissued.id, expired.id);
+    service
+        .accept_invitation(reissued.id, invitee)
+        .await
+        .unwrap();
+    assert!(is_member(&pool, room_id, invitee).await);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn only_admins_can_invite() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let outsider = create_user(&pool).await;
+    let invitee = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let err = service
+        .invite(invite(room_id, outsider, invitee))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::UserNotInRoom);
+
+    let err = service
+        .invite(invite(room_id, owner, owner))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        domain_error(err),
+        DomainError::InvalidArgument {
+            field: "invitee_id",
+            ..
+        }
+    ));
+}
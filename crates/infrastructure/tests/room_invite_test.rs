@@ -0,0 +1,236 @@
+//! 入房邀请链接测试
+//!
+//! 验证：凭链接加入房间，私密房间不需要密码；过期、次数用完、令牌不存在分别报错；
+//! 次数上限的边界；已是成员时不占用次数；只有房间管理员能创建链接
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    services::{ChatService, ChatServiceDependencies, CreateInviteRequest, CreateRoomRequest},
+    ApplicationError,
+};
+use domain::{ChatRoomVisibility, DomainError, RoomRole};
+use sqlx::PgPool;
+use time::Duration;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db, TestClock};
+
+fn chat_service(pool: &PgPool, clock: Arc<TestClock>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        clock,
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建的公开房间
+async fn create_room(service: &ChatService, owner_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("link-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+fn create_invite(
+    room_id: Uuid,
+    creator_id: Uuid,
+    max_uses: Option<u32>,
+    expires_in_secs: Option<u64>,
+) -> CreateInviteRequest {
+    CreateInviteRequest {
+        room_id,
+        creator_id,
+        max_uses,
+        expires_in_secs,
+    }
+}
+
+fn domain_error(err: ApplicationError) -> DomainError {
+    match err {
+        ApplicationError::Domain(err) => err,
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+async fn used_count(pool: &PgPool, token: &str) -> i32 {
+    sqlx::query_scalar("SELECT used_count FROM invites WHERE token = $1")
+        .bind(token)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn redeem_joins_room_without_password() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let guest = create_user(&pool).await;
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("link-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id: owner,
+            visibility: ChatRoomVisibility::Private,
+            password: Some("secret".to_string()),
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+
+    let invite = service
+        .create_invite(create_invite(room_id, owner, None, None))
+        .await
+        .unwrap();
+    assert_eq!(invite.token.len(), 32);
+    assert_eq!(invite.used_count, 0);
+
+    let member = service.redeem_invite(&invite.token, guest).await.unwrap();
+    assert_eq!(Uuid::from(member.room_id), room_id);
+    assert_eq!(member.role, RoomRole::Member);
+    assert_eq!(used_count(&pool, &invite.token).await, 1);
+
+    // 已经是成员时报错，不占用次数
+    let err = service
+        .redeem_invite(&invite.token, guest)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::UserAlreadyInRoom);
+    assert_eq!(used_count(&pool, &invite.token).await, 1);
+
+    let err = service
+        .redeem_invite("no-such-token", guest)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InviteNotFound);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn usage_limit_boundary() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let invite = service
+        .create_invite(create_invite(room_id, owner, Some(2), None))
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let user = create_user(&pool).await;
+        service.redeem_invite(&invite.token, user).await.unwrap();
+    }
+    assert_eq!(used_count(&pool, &invite.token).await, 2);
+
+    // 第三次超过上限
+    let late = create_user(&pool).await;
+    let err = service
+        .redeem_invite(&invite.token, late)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InviteExhausted);
+    assert_eq!(used_count(&pool, &invite.token).await, 2);
+    assert!(service
+        .get_user_role_in_room(room_id.into(), late.into())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn concurrent_redeems_never_exceed_limit() {
+    let pool = setup_test_db().await;
+    let service = Arc::new(chat_service(&pool, Arc::new(TestClock::new())));
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    let invite = service
+        .create_invite(create_invite(room_id, owner, Some(3), None))
+        .await
+        .unwrap();
+
+    let mut handles = Vec::new();
+    for _ in 0..8 {
+        let user = create_user(&pool).await;
+        let service = service.clone();
+        let token = invite.token.clone();
+        handles.push(tokio::spawn(async move {
+            service.redeem_invite(&token, user).await
+        }));
+    }
+    let mut joined = 0;
+    for handle in handles {
+        match handle.await.unwrap() {
+            Ok(_) => joined += 1,
+            Err(err) => assert_eq!(domain_error(err), DomainError::InviteExhausted),
+        }
+    }
+    assert_eq!(joined, 3);
+    assert_eq!(used_count(&pool, &invite.token).await, 3);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn expired_invite_is_rejected() {
+    let pool = setup_test_db().await;
+    let clock = Arc::new(TestClock::new());
+    let service = chat_service(&pool, clock.clone());
+    let owner = create_user(&pool).await;
+    let guest = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let invite = service
+        .create_invite(create_invite(room_id, owner, Some(5), Some(60)))
+        .await
+        .unwrap();
+    clock.advance(Duration::seconds(60));
+
+    let err = service
+        .redeem_invite(&invite.token, guest)
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::InviteExpired);
+    assert_eq!(used_count(&pool, &invite.token).await, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn only_room_admins_can_create_invites() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(TestClock::new()));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    let invite = service
+        .create_invite(create_invite(room_id, owner, None, None))
+        .await
+        .unwrap();
+    service.redeem_invite(&invite.token, member).await.unwrap();
+
+    let err = service
+        .create_invite(create_invite(room_id, member, None, None))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::OperationNotAllowed);
+
+    let err = service
+        .create_invite(create_invite(room_id, owner, Some(0), None))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        domain_error(err),
+        DomainError::InvalidArgument {
+            field: "max_uses",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,187 @@
+//! 我的房间列表与批量已读测试
+//!
+//! 验证：列表中用户置顶的房间在前，其余按最近活动倒序；置顶只影响用户自己；
+//! 批量已读可以指定房间或全部房间，包含非成员房间时整体拒绝
+mod support;
+
+use application::{
+    services::{
+        ChatService, CreateRoomRequest, JoinRoomRequest, MarkRoomsReadRequest, SendMessageRequest,
+    },
+    ApplicationError,
+};
+use domain::{ChatRoomVisibility, DomainError, MessageType, RoomId};
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid, name: &str) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("{name}-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid) {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: "hello".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap();
+}
+
+async fn room_order(service: &ChatService, user_id: Uuid) -> Vec<(Uuid, bool, i64)> {
+    service
+        .list_my_rooms(user_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| {
+            (
+                Uuid::from(entry.room.id),
+                entry.pinned_at.is_some(),
+                entry.unread_count,
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_room_list_orders_user_pins_then_last_activity() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner_id = create_user(&pool).await;
+    let user_id = create_user(&pool).await;
+
+    let quiet = create_room(&service, owner_id, user_id, "quiet").await;
+    let older = create_room(&service, owner_id, user_id, "older").await;
+    let newer = create_room(&service, owner_id, user_id, "newer").await;
+    send(&service, older, owner_id).await;
+    send(&service, newer, owner_id).await;
+    send(&service, newer, owner_id).await;
+    // 没有消息的房间按加入时间排序，最早加入，排在最后
+    sqlx::query("UPDATE room_members SET joined_at = NOW() - INTERVAL '1 day' WHERE room_id = $1")
+        .bind(quiet)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        room_order(&service, user_id).await,
+        vec![(newer, false, 2), (older, false, 1), (quiet, false, 0)]
+    );
+
+    // 置顶的房间排在最前，置顶之间同样按最近活动排序
+    service.pin_room(quiet, user_id).await.unwrap();
+    service.pin_room(older, user_id).await.unwrap();
+    service.pin_room(older, user_id).await.unwrap();
+    assert_eq!(
+        room_order(&service, user_id).await,
+        vec![(older, true, 1), (quiet, true, 0), (newer, false, 2)]
+    );
+
+    // 置顶只影响自己的列表
+    let owner_rooms = room_order(&service, owner_id).await;
+    assert!(owner_rooms.iter().all(|(_, pinned, _)| !pinned));
+
+    service.unpin_room(older, user_id).await.unwrap();
+    service.unpin_room(older, user_id).await.unwrap();
+    assert_eq!(
+        room_order(&service, user_id).await,
+        vec![(quiet, true, 0), (newer, false, 2), (older, false, 1)]
+    );
+
+    // 不在房间里不能置顶
+    let outsider_id = create_user(&pool).await;
+    let denied = service.pin_room(newer, outsider_id).await;
+    assert!(matches!(
+        denied,
+        Err(ApplicationError::Domain(DomainError::UserNotInRoom))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_bulk_mark_rooms_read() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner_id = create_user(&pool).await;
+    let user_id = create_user(&pool).await;
+    let outsider_id = create_user(&pool).await;
+
+    let first = create_room(&service, owner_id, user_id, "first").await;
+    let second = create_room(&service, owner_id, user_id, "second").await;
+    let third = create_room(&service, owner_id, user_id, "third").await;
+    let foreign = create_room(&service, outsider_id, owner_id, "foreign").await;
+    for room_id in [first, second, third, second] {
+        send(&service, room_id, owner_id).await;
+    }
+
+    // 包含非成员房间时整体拒绝，已读位置都不动
+    let denied = service
+        .mark_rooms_read(MarkRoomsReadRequest {
+            user_id,
+            room_ids: Some(vec![first, foreign]),
+        })
+        .await;
+    assert!(matches!(
+        denied,
+        Err(ApplicationError::Domain(DomainError::UserNotInRoom))
+    ));
+    assert_eq!(service.unread_count(first, user_id).await.unwrap(), 1);
+
+    // 指定房间
+    let marked = service
+        .mark_rooms_read(MarkRoomsReadRequest {
+            user_id,
+            room_ids: Some(vec![first, second]),
+        })
+        .await
+        .unwrap();
+    let mut marked: Vec<Uuid> = marked.into_iter().map(Uuid::from).collect();
+    marked.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(marked, expected);
+    assert_eq!(service.unread_count(first, user_id).await.unwrap(), 0);
+    assert_eq!(service.unread_count(second, user_id).await.unwrap(), 0);
+    assert_eq!(service.unread_count(third, user_id).await.unwrap(), 1);
+
+    // 全部房间；已经读到最新的房间不算在内
+    let marked = service
+        .mark_rooms_read(MarkRoomsReadRequest {
+            user_id,
+            room_ids: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(marked, vec![RoomId::from(third)]);
+    let counts = service.unread_counts(user_id).await.unwrap();
+    assert!(counts.values().all(|unread| *unread == 0));
+
+    // 其他成员的已读位置不受影响
+    assert_eq!(service.unread_count(first, owner_id).await.unwrap(), 1);
+}
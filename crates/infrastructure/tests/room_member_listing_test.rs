@@ -0,0 +1,193 @@
+//! 房间成员列表测试
+//!
+//! 验证：按角色（Owner、Admin、Member）再按加入时间排序、分页边界和总数，
+//! 只有房间成员能查看
+mod support;
+
+use application::{
+    services::{ChatService, CreateRoomRequest},
+    ApplicationError,
+};
+use domain::{ChatRoomVisibility, DomainError, RoomRole, UserId};
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+async fn create_room(service: &ChatService, owner_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("list-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+/// 以指定角色和加入时间直接写入成员，排序结果不依赖插入顺序
+async fn add_member(
+    pool: &PgPool,
+    room_id: Uuid,
+    user_id: Uuid,
+    role: &str,
+    joined_at: OffsetDateTime,
+) {
+    sqlx::query(
+        "INSERT INTO room_members (room_id, user_id, role, joined_at)
+         VALUES ($1, $2, $3::room_role, $4)",
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(role)
+    .bind(joined_at)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn members_are_sorted_by_role_then_join_date() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    // 插入顺序故意和期望顺序不同
+    let base = OffsetDateTime::now_utc() - Duration::days(1);
+    let late_member = create_user(&pool).await;
+    let early_member = create_user(&pool).await;
+    let late_admin = create_user(&pool).await;
+    let early_admin = create_user(&pool).await;
+    add_member(
+        &pool,
+        room_id,
+        late_member,
+        "member",
+        base + Duration::hours(3),
+    )
+    .await;
+    add_member(&pool, room_id, early_member, "member", base).await;
+    add_member(
+        &pool,
+        room_id,
+        late_admin,
+        "admin",
+        base + Duration::hours(2),
+    )
+    .await;
+    add_member(
+        &pool,
+        room_id,
+        early_admin,
+        "admin",
+        base + Duration::hours(1),
+    )
+    .await;
+
+    let result = service
+        .list_members(room_id, late_member, 1, 50)
+        .await
+        .unwrap();
+    assert_eq!(result.total_count, 5);
+    assert_eq!((result.page, result.page_size), (1, 50));
+
+    let order: Vec<(Uuid, RoomRole)> = result
+        .items
+        .iter()
+        .map(|member| (Uuid::from(member.user_id), member.role.clone()))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            (owner, RoomRole::Owner),
+            (early_admin, RoomRole::Admin),
+            (late_admin, RoomRole::Admin),
+            (early_member, RoomRole::Member),
+            (late_member, RoomRole::Member),
+        ]
+    );
+
+    // 用户名来自 users 表
+    let username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = $1")
+        .bind(early_admin)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(result.items[1].username, username);
+    assert_eq!(result.items[1].user_id, UserId::from(early_admin));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn pagination_boundaries() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let base = OffsetDateTime::now_utc() - Duration::days(1);
+    let mut expected = vec![owner];
+    for i in 0..4 {
+        let member = create_user(&pool).await;
+        add_member(
+            &pool,
+            room_id,
+            member,
+            "member",
+            base + Duration::minutes(i),
+        )
+        .await;
+        expected.push(member);
+    }
+
+    // 5 个成员每页 2 个：2 + 2 + 1，超出范围的页为空但总数不变
+    let mut seen = Vec::new();
+    for (page, expected_len) in [(1, 2), (2, 2), (3, 1), (4, 0)] {
+        let result = service.list_members(room_id, owner, page, 2).await.unwrap();
+        assert_eq!(result.total_count, 5);
+        assert_eq!(result.page, page);
+        assert_eq!(result.items.len(), expected_len, "page {page}");
+        seen.extend(result.items.iter().map(|member| Uuid::from(member.user_id)));
+    }
+    assert_eq!(seen, expected);
+
+    // 页码从 1 开始，0 按第一页处理
+    let first = service.list_members(room_id, owner, 0, 2).await.unwrap();
+    assert_eq!(first.page, 1);
+    assert_eq!(first.items.len(), 2);
+    assert_eq!(Uuid::from(first.items[0].user_id), owner);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn only_members_can_list_members() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let outsider = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let err = service
+        .list_members(room_id, outsider, 1, 20)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::UserNotInRoom)
+    ));
+
+    // 不存在的房间同样报不是成员，不暴露房间是否存在
+    let err = service
+        .list_members(Uuid::new_v4(), owner, 1, 20)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ApplicationError::Domain(DomainError::UserNotInRoom)
+    ));
+}
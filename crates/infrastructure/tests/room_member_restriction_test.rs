@@ -0,0 +1,387 @@
+//! 房间内禁言与封禁测试
+//!
+//! 验证：被禁言的成员发送消息会被拒绝并返回剩余时长，解除后恢复；
+//! 被封禁的用户被移出房间且不能重新加入或被邀请，解除封禁后恢复；
+//! 踢出的用户可以立即重新加入；封禁名单记录封禁人和原因；房主不能被限制
+mod support;
+
+use application::{
+    repository::RoomMemberRepository,
+    services::{
+        BanMemberRequest, ChatService, CreateInviteRequest, CreateRoomRequest, InviteMemberRequest,
+        JoinRoomRequest, MemberRestrictionRequest, MuteMemberRequest, RemoveMemberRequest,
+        SendMessageRequest,
+    },
+    ApplicationError, Clock, SystemClock,
+};
+use domain::{ChatRoomVisibility, DomainError, MessageType, RoomId, RoomRole, UserId};
+use infrastructure::PgStorage;
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+struct Fixture {
+    service: ChatService,
+    storage: PgStorage,
+    room_id: Uuid,
+    owner_id: Uuid,
+    admin_id: Uuid,
+    member_id: Uuid,
+}
+
+async fn setup() -> Fixture {
+    let pool = setup_test_db().await;
+    let storage = PgStorage::new(pool.clone());
+    let service = chat_service(&pool);
+
+    let owner_id = create_user(&pool).await;
+    let admin_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("restricted-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+
+    for (user_id, role) in [(admin_id, RoomRole::Admin), (member_id, RoomRole::Member)] {
+        storage
+            .member_repository
+            .upsert(domain::RoomMember::new(
+                RoomId::from(room_id),
+                UserId::from(user_id),
+                role,
+                SystemClock.now(),
+            ))
+            .await
+            .unwrap();
+    }
+
+    Fixture {
+        service,
+        storage,
+        room_id,
+        owner_id,
+        admin_id,
+        member_id,
+    }
+}
+
+fn restriction(
+    fixture: &Fixture,
+    operator_id: Uuid,
+    target_user_id: Uuid,
+) -> MemberRestrictionRequest {
+    MemberRestrictionRequest {
+        room_id: fixture.room_id,
+        operator_id,
+        target_user_id,
+    }
+}
+
+fn ban(fixture: &Fixture, operator_id: Uuid, target_user_id: Uuid) -> BanMemberRequest {
+    BanMemberRequest {
+        room_id: fixture.room_id,
+        operator_id,
+        target_user_id,
+        reason: None,
+    }
+}
+
+async fn send(fixture: &Fixture, sender_id: Uuid) -> Result<(), ApplicationError> {
+    fixture
+        .service
+        .send_message(SendMessageRequest {
+            room_id: fixture.room_id,
+            sender_id,
+            content: "hello".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_muted_member_cannot_send_until_unmuted() {
+    let fixture = setup().await;
+
+    let muted = fixture
+        .service
+        .mute_member(MuteMemberRequest {
+            room_id: fixture.room_id,
+            operator_id: fixture.admin_id,
+            target_user_id: fixture.member_id,
+            duration_secs: 600,
+        })
+        .await
+        .unwrap();
+    assert!(muted.muted_until.is_some());
+    assert!(!muted.banned);
+
+    match send(&fixture, fixture.member_id).await {
+        Err(ApplicationError::Domain(DomainError::UserMuted { remaining_secs })) => {
+            assert!(remaining_secs > 590 && remaining_secs <= 600);
+        }
+        other => panic!("expected UserMuted, got {other:?}"),
+    }
+    // 其他成员不受影响
+    send(&fixture, fixture.admin_id).await.unwrap();
+
+    fixture
+        .service
+        .unmute_member(restriction(&fixture, fixture.admin_id, fixture.member_id))
+        .await
+        .unwrap();
+    send(&fixture, fixture.member_id).await.unwrap();
+
+    // 禁言到期后自动恢复
+    fixture
+        .storage
+        .member_repository
+        .set_muted_until(
+            RoomId::from(fixture.room_id),
+            UserId::from(fixture.member_id),
+            Some(SystemClock.now() - time::Duration::seconds(1)),
+        )
+        .await
+        .unwrap();
+    send(&fixture, fixture.member_id).await.unwrap();
+
+    // 禁言时长必须为正
+    let invalid = fixture
+        .service
+        .mute_member(MuteMemberRequest {
+            room_id: fixture.room_id,
+            operator_id: fixture.admin_id,
+            target_user_id: fixture.member_id,
+            duration_secs: 0,
+        })
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(ApplicationError::Domain(
+            DomainError::InvalidArgument { .. }
+        ))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_banned_user_is_removed_and_cannot_rejoin() {
+    let fixture = setup().await;
+
+    fixture
+        .service
+        .ban_member(ban(&fixture, fixture.owner_id, fixture.member_id))
+        .await
+        .unwrap();
+    let membership = fixture
+        .storage
+        .member_repository
+        .find(
+            RoomId::from(fixture.room_id),
+            UserId::from(fixture.member_id),
+        )
+        .await
+        .unwrap();
+    assert!(membership.is_none());
+
+    let rejoin = fixture
+        .service
+        .join_room(JoinRoomRequest {
+            room_id: fixture.room_id,
+            user_id: fixture.member_id,
+            password: None,
+        })
+        .await;
+    assert!(matches!(
+        rejoin,
+        Err(ApplicationError::Domain(DomainError::UserBannedFromRoom))
+    ));
+    let invite = fixture
+        .service
+        .invite_member(InviteMemberRequest {
+            room_id: fixture.room_id,
+            inviter_id: fixture.admin_id,
+            invitee_id: fixture.member_id,
+            password: None,
+        })
+        .await;
+    assert!(matches!(
+        invite,
+        Err(ApplicationError::Domain(DomainError::UserBannedFromRoom))
+    ));
+
+    fixture
+        .service
+        .unban_member(restriction(&fixture, fixture.owner_id, fixture.member_id))
+        .await
+        .unwrap();
+    fixture
+        .service
+        .join_room(JoinRoomRequest {
+            room_id: fixture.room_id,
+            user_id: fixture.member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_restrictions_require_manager_and_spare_owner() {
+    let fixture = setup().await;
+
+    // 房主不能被禁言或封禁
+    let mute_owner = fixture
+        .service
+        .mute_member(MuteMemberRequest {
+            room_id: fixture.room_id,
+            operator_id: fixture.admin_id,
+            target_user_id: fixture.owner_id,
+            duration_secs: 60,
+        })
+        .await;
+    assert!(matches!(
+        mute_owner,
+        Err(ApplicationError::Domain(DomainError::OperationNotAllowed))
+    ));
+    let ban_owner = fixture
+        .service
+        .ban_member(ban(&fixture, fixture.admin_id, fixture.owner_id))
+        .await;
+    assert!(matches!(
+        ban_owner,
+        Err(ApplicationError::Domain(DomainError::OperationNotAllowed))
+    ));
+
+    // 普通成员没有管理权限
+    let by_member = fixture
+        .service
+        .ban_member(ban(&fixture, fixture.member_id, fixture.admin_id))
+        .await;
+    assert!(matches!(
+        by_member,
+        Err(ApplicationError::Domain(DomainError::OperationNotAllowed))
+    ));
+
+    // 不能限制自己
+    let admin_by_self = fixture
+        .service
+        .ban_member(ban(&fixture, fixture.admin_id, fixture.admin_id))
+        .await;
+    assert!(matches!(
+        admin_by_self,
+        Err(ApplicationError::Domain(DomainError::OperationNotAllowed))
+    ));
+
+    // 房主可以限制管理员
+    fixture
+        .service
+        .mute_member(MuteMemberRequest {
+            room_id: fixture.room_id,
+            operator_id: fixture.owner_id,
+            target_user_id: fixture.admin_id,
+            duration_secs: 60,
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_kicked_user_can_rejoin_but_banned_user_cannot() {
+    let fixture = setup().await;
+
+    // 踢出只移出房间，可以立即重新加入
+    fixture
+        .service
+        .remove_member(RemoveMemberRequest {
+            room_id: fixture.room_id,
+            operator_id: fixture.owner_id,
+            target_user_id: fixture.member_id,
+        })
+        .await
+        .unwrap();
+    fixture
+        .service
+        .join_room(JoinRoomRequest {
+            room_id: fixture.room_id,
+            user_id: fixture.member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+
+    let record = fixture
+        .service
+        .ban_member(BanMemberRequest {
+            reason: Some("  spam  ".to_string()),
+            ..ban(&fixture, fixture.admin_id, fixture.member_id)
+        })
+        .await
+        .unwrap();
+    assert_eq!(record.banned_by, Some(UserId::from(fixture.admin_id)));
+    assert_eq!(record.reason.as_deref(), Some("spam"));
+
+    let bans = fixture
+        .service
+        .list_bans(fixture.room_id, fixture.owner_id)
+        .await
+        .unwrap();
+    assert_eq!(bans, vec![record]);
+    let by_member = fixture
+        .service
+        .list_bans(fixture.room_id, fixture.member_id)
+        .await;
+    assert!(matches!(
+        by_member,
+        Err(ApplicationError::Domain(DomainError::UserNotInRoom))
+    ));
+
+    // 邀请链接也不能绕过封禁
+    let invite = fixture
+        .service
+        .create_invite(CreateInviteRequest {
+            room_id: fixture.room_id,
+            creator_id: fixture.owner_id,
+            max_uses: None,
+            expires_in_secs: None,
+        })
+        .await
+        .unwrap();
+    let redeemed = fixture
+        .service
+        .redeem_invite(&invite.token, fixture.member_id)
+        .await;
+    assert!(matches!(
+        redeemed,
+        Err(ApplicationError::Domain(DomainError::UserBannedFromRoom))
+    ));
+
+    // 解除封禁后恢复资格
+    fixture
+        .service
+        .unban_member(restriction(&fixture, fixture.owner_id, fixture.member_id))
+        .await
+        .unwrap();
+    assert!(fixture
+        .service
+        .list_bans(fixture.room_id, fixture.owner_id)
+        .await
+        .unwrap()
+        .is_empty());
+    fixture
+        .service
+        .redeem_invite(&invite.token, fixture.member_id)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,199 @@
+//! 房间所有权转让测试
+//!
+//! 验证：转让后房间 owner_id 和双方角色一起更新并留下审计记录，原 Owner 可以离开；
+//! Owner 不能直接离开；目标不是成员、调用者不是 Owner、转让给自己都会被拒绝且不改任何数据
+mod support;
+
+use application::{
+    repository::AuditLogRepository,
+    services::{
+        ChatService, CreateRoomRequest, JoinRoomRequest, LeaveRoomRequest, TransferOwnershipRequest,
+    },
+    ApplicationError,
+};
+use domain::{AuditLogEntry, ChatRoomVisibility, DomainError, RoomId, RoomRole, UserId};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service, create_user, setup_test_db};
+
+async fn create_room(service: &ChatService, owner_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("transfer-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+async fn join(service: &ChatService, room_id: Uuid, user_id: Uuid) {
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+}
+
+async fn role_of(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Option<RoomRole> {
+    sqlx::query_scalar("SELECT role FROM room_members WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+}
+
+async fn owner_of(pool: &PgPool, room_id: Uuid) -> Uuid {
+    sqlx::query_scalar("SELECT owner_id FROM chat_rooms WHERE id = $1")
+        .bind(room_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn transfer(room_id: Uuid, operator_id: Uuid, new_owner_id: Uuid) -> TransferOwnershipRequest {
+    TransferOwnershipRequest {
+        room_id,
+        operator_id,
+        new_owner_id,
+    }
+}
+
+fn domain_error(err: ApplicationError) -> DomainError {
+    match err {
+        ApplicationError::Domain(err) => err,
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn transfer_updates_room_and_roles_then_former_owner_can_leave() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let heir = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    join(&service, room_id, heir).await;
+
+    // 转让前 Owner 不能离开
+    let err = service
+        .leave_room(LeaveRoomRequest {
+            room_id,
+            user_id: owner,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::OwnerCannotLeave);
+
+    let room = service
+        .transfer_ownership(transfer(room_id, owner, heir))
+        .await
+        .unwrap();
+    assert_eq!(room.owner_id, UserId::from(heir));
+    assert_eq!(owner_of(&pool, room_id).await, heir);
+    assert_eq!(role_of(&pool, room_id, heir).await, Some(RoomRole::Owner));
+    assert_eq!(role_of(&pool, room_id, owner).await, Some(RoomRole::Admin));
+
+    let storage = PgStorage::new(pool.clone());
+    let entries = storage
+        .audit_log_repository
+        .find_by_target_room(
+            RoomId::from(room_id),
+            AuditLogEntry::ROOM_OWNERSHIP_TRANSFERRED,
+            1,
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(entries.total_count, 1);
+    assert_eq!(entries.items[0].actor_id, UserId::from(owner));
+
+    // 原 Owner 已经降为 Admin，不能再转让
+    let err = service
+        .transfer_ownership(transfer(room_id, owner, heir))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::OperationNotAllowed);
+
+    service
+        .leave_room(LeaveRoomRequest {
+            room_id,
+            user_id: owner,
+        })
+        .await
+        .unwrap();
+    assert_eq!(role_of(&pool, room_id, owner).await, None);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn transfer_to_non_member_is_rejected() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let outsider = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+
+    let err = service
+        .transfer_ownership(transfer(room_id, owner, outsider))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::MemberNotFound);
+
+    let err = service
+        .transfer_ownership(transfer(room_id, owner, owner))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        domain_error(err),
+        DomainError::RoleChangeNotAllowed { .. }
+    ));
+
+    assert_eq!(owner_of(&pool, room_id).await, owner);
+    assert_eq!(role_of(&pool, room_id, owner).await, Some(RoomRole::Owner));
+    assert_eq!(role_of(&pool, room_id, outsider).await, None);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn non_owner_cannot_transfer() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool);
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let other = create_user(&pool).await;
+    let outsider = create_user(&pool).await;
+    let room_id = create_room(&service, owner).await;
+    join(&service, room_id, member).await;
+    join(&service, room_id, other).await;
+
+    // 普通成员不能把房间转给别人
+    let err = service
+        .transfer_ownership(transfer(room_id, member, other))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::OperationNotAllowed);
+
+    // 房间外的人
+    let err = service
+        .transfer_ownership(transfer(room_id, outsider, member))
+        .await
+        .unwrap_err();
+    assert_eq!(domain_error(err), DomainError::UserNotInRoom);
+
+    assert_eq!(owner_of(&pool, room_id).await, owner);
+    assert_eq!(
+        role_of(&pool, room_id, member).await,
+        Some(RoomRole::Member)
+    );
+    assert_eq!(role_of(&pool, room_id, other).await, Some(RoomRole::Member));
+}
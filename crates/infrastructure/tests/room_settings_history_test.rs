@@ -0,0 +1,141 @@
+//! 房间设置修改历史测试
+//!
+//! 验证：每次修改房间设置都记入审计日志，历史按时间先后返回改前改后的值，
+//! 密码修改脱敏，没有实际变化的修改不记录，只有房间管理员和系统管理员能查看
+mod support;
+
+use application::{
+    services::{CreateRoomRequest, UpdateRoomRequest},
+    ApplicationError,
+};
+use domain::{ChatRoomVisibility, DomainError, ReactionPolicy, RoomSettingDiff, UserId};
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{chat_service, create_superuser, create_user, setup_test_db};
+
+fn update(room_id: Uuid, operator_id: Uuid) -> UpdateRoomRequest {
+    UpdateRoomRequest {
+        room_id,
+        operator_id,
+        name: None,
+        visibility: None,
+        password: None,
+        reaction_policy: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_room_settings_history_in_order_with_redaction() {
+    let pool = setup_test_db().await;
+    let chat_service = chat_service(&pool);
+
+    let owner_id = create_user(&pool).await;
+    let superuser_id = create_superuser(&pool).await;
+    let outsider_id = create_user(&pool).await;
+    let original_name = format!("settings-{}", &owner_id.to_string()[0..8]);
+    let room = chat_service
+        .create_room(CreateRoomRequest {
+            name: original_name.clone(),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    let renamed = format!("{original_name}-renamed");
+
+    // 1. 改名
+    chat_service
+        .update_room(UpdateRoomRequest {
+            name: Some(renamed.clone()),
+            ..update(room_id, owner_id)
+        })
+        .await
+        .unwrap();
+
+    // 2. 改为私有并设置密码
+    chat_service
+        .update_room(UpdateRoomRequest {
+            visibility: Some(ChatRoomVisibility::Private),
+            password: Some("room-secret".to_string()),
+            ..update(room_id, owner_id)
+        })
+        .await
+        .unwrap();
+
+    // 没有实际变化的修改不记录
+    chat_service
+        .update_room(UpdateRoomRequest {
+            name: Some(renamed.clone()),
+            ..update(room_id, owner_id)
+        })
+        .await
+        .unwrap();
+
+    // 3. 修改回应策略
+    chat_service
+        .update_room(UpdateRoomRequest {
+            reaction_policy: Some(ReactionPolicy::EmojiOnly),
+            ..update(room_id, owner_id)
+        })
+        .await
+        .unwrap();
+
+    // 审计记录在后台写入
+    chat_service.audit_logger().flush().await;
+    let history = chat_service
+        .room_settings_history(room_id, owner_id, 1, 20)
+        .await
+        .unwrap();
+    assert_eq!(history.total_count, 3);
+    assert_eq!(history.items.len(), 3);
+
+    let first = &history.items[0];
+    assert_eq!(first.changed_by, UserId::from(owner_id));
+    assert_eq!(
+        first.changes,
+        vec![RoomSettingDiff::new("name", &original_name, &renamed)]
+    );
+
+    let second = &history.items[1];
+    assert_eq!(second.changes.len(), 2);
+    assert_eq!(second.changes[0].field, "visibility");
+    assert_eq!(second.changes[0].old, json!("Public"));
+    assert_eq!(second.changes[0].new, json!("Private"));
+    assert_eq!(second.changes[1], RoomSettingDiff::redacted("password"));
+    assert!(!serde_json::to_string(&history.items)
+        .unwrap()
+        .contains("room-secret"));
+
+    let third = &history.items[2];
+    assert_eq!(third.changed_by, UserId::from(owner_id));
+    assert_eq!(
+        third.changes,
+        vec![RoomSettingDiff::new(
+            "reaction_policy",
+            ReactionPolicy::Any,
+            ReactionPolicy::EmojiOnly
+        )]
+    );
+    assert!(first.changed_at <= second.changed_at && second.changed_at <= third.changed_at);
+
+    // 系统管理员不是房间成员也能查看，支持分页
+    let page = chat_service
+        .room_settings_history(room_id, superuser_id, 2, 2)
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 3);
+    assert_eq!(page.items, vec![third.clone()]);
+
+    // 非管理员不能查看
+    let denied = chat_service
+        .room_settings_history(room_id, outsider_id, 1, 20)
+        .await;
+    assert!(matches!(
+        denied,
+        Err(ApplicationError::Domain(DomainError::UserNotInRoom))
+    ));
+}
@@ -0,0 +1,210 @@
+//! 发送速率限制测试
+//!
+//! 验证：同一用户在同一房间连发超过突发容量后被拒绝，被拒绝的消息不落库，
+//! 令牌补充后恢复发送；配额按房间独立计算；房间合计速率在 ChatService 里执行，
+//! 房间 owner/admin 按配置豁免
+mod support;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use application::{
+    rate_limiter::memory::MemorySendRateLimiter,
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        SendMessageRequest,
+    },
+    ApplicationError, RateLimitError, SendRateLimiter, TokenBucketLimit,
+};
+use async_trait::async_trait;
+use domain::{ChatRoomVisibility, MessageType, RoomId, UserId};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+/// 只限制房间合计条数的限流器，owner/admin 豁免
+struct RoomTotalLimiter {
+    max_per_room: u32,
+    sent: Mutex<HashMap<RoomId, u32>>,
+}
+
+#[async_trait]
+impl SendRateLimiter for RoomTotalLimiter {
+    async fn acquire(&self, _room_id: RoomId, _user_id: UserId) -> Result<(), RateLimitError> {
+        Ok(())
+    }
+
+    async fn check_room(
+        &self,
+        room_id: RoomId,
+        _user_id: UserId,
+        is_room_admin: bool,
+    ) -> Result<(), RateLimitError> {
+        if is_room_admin {
+            return Ok(());
+        }
+        let mut sent = self.sent.lock().unwrap();
+        let count = sent.entry(room_id).or_default();
+        if *count >= self.max_per_room {
+            return Err(RateLimitError::RoomRateLimited {
+                max_per_sec: self.max_per_room,
+                retry_after_secs: 1,
+            });
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+fn chat_service(pool: &PgPool, limit: TokenBucketLimit) -> ChatService {
+    chat_service_with(pool, Arc::new(MemorySendRateLimiter::new(limit)))
+}
+
+fn chat_service_with(pool: &PgPool, send_rate_limiter: Arc<dyn SendRateLimiter>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        send_rate_limiter: Some(send_rate_limiter),
+        ..chat_service_deps(pool)
+    })
+}
+
+async fn create_room(service: &ChatService, owner_id: Uuid, name: &str) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("{name}-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    Uuid::from(room.id)
+}
+
+async fn send(
+    service: &ChatService,
+    room_id: Uuid,
+    sender_id: Uuid,
+) -> Result<domain::Message, ApplicationError> {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: "flood".to_string(),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+}
+
+async fn message_count(pool: &PgPool, room_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE room_id = $1")
+        .bind(room_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn flood_is_rejected_until_tokens_refill() {
+    let pool = setup_test_db().await;
+    // 每秒补充一个令牌，最多连发 3 条
+    let service = chat_service(
+        &pool,
+        TokenBucketLimit {
+            per_minute: 60,
+            burst: 3,
+        },
+    );
+    let owner_id = create_user(&pool).await;
+    let room_id = create_room(&service, owner_id, "flood").await;
+
+    for _ in 0..3 {
+        send(&service, room_id, owner_id).await.unwrap();
+    }
+    match send(&service, room_id, owner_id).await {
+        Err(ApplicationError::RateLimited(RateLimitError::MessageRateLimited {
+            per_minute,
+            burst,
+            retry_after_secs,
+        })) => {
+            assert_eq!((per_minute, burst), (60, 3));
+            assert_eq!(retry_after_secs, 1);
+        }
+        other => panic!("expected MessageRateLimited, got {:?}", other),
+    }
+    assert_eq!(message_count(&pool, room_id).await, 3);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    send(&service, room_id, owner_id).await.unwrap();
+    assert_eq!(message_count(&pool, room_id).await, 4);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn quota_is_per_room() {
+    let pool = setup_test_db().await;
+    let service = chat_service(
+        &pool,
+        TokenBucketLimit {
+            per_minute: 1,
+            burst: 1,
+        },
+    );
+    let owner_id = create_user(&pool).await;
+    let busy_room = create_room(&service, owner_id, "busy").await;
+    let quiet_room = create_room(&service, owner_id, "quiet").await;
+
+    send(&service, busy_room, owner_id).await.unwrap();
+    assert!(matches!(
+        send(&service, busy_room, owner_id).await,
+        Err(ApplicationError::RateLimited(_))
+    ));
+    send(&service, quiet_room, owner_id).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn room_total_is_enforced_by_service_and_exempts_admins() {
+    let pool = setup_test_db().await;
+    let service = chat_service_with(
+        &pool,
+        Arc::new(RoomTotalLimiter {
+            max_per_room: 2,
+            sent: Mutex::new(HashMap::new()),
+        }),
+    );
+    let owner_id = create_user(&pool).await;
+    let member_id = create_user(&pool).await;
+    let flooded_room = create_room(&service, owner_id, "flooded").await;
+    let quiet_room = create_room(&service, owner_id, "calm").await;
+    for room_id in [flooded_room, quiet_room] {
+        service
+            .join_room(JoinRoomRequest {
+                room_id,
+                user_id: member_id,
+                password: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    for _ in 0..2 {
+        send(&service, flooded_room, member_id).await.unwrap();
+    }
+    assert!(matches!(
+        send(&service, flooded_room, member_id).await,
+        Err(ApplicationError::RateLimited(
+            RateLimitError::RoomRateLimited { max_per_sec: 2, .. }
+        ))
+    ));
+    assert_eq!(message_count(&pool, flooded_room).await, 2);
+
+    // owner 不受合计速率约束，其他房间各算各的
+    send(&service, flooded_room, owner_id).await.unwrap();
+    send(&service, quiet_room, member_id).await.unwrap();
+    assert_eq!(message_count(&pool, flooded_room).await, 3);
+}
@@ -0,0 +1,208 @@
+//! 序列号缺口与重放测试
+//!
+//! 验证：客户端按序列号发现缺口后，从缺口处重放得到按序排列的完整消息，和实时流拼起来不缺不重；
+//! 重放超过上限时分页，next_seq 接续下一页；Redis 计数丢失后从库中最大序列号续上
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        SendMessageRequest,
+    },
+    LocalMessageBroadcaster, MessageBroadcaster, MessageSequencer, SequenceCheck, SequenceTracker,
+};
+use domain::{ChatRoomVisibility, MessageId, MessageType, RoomId};
+use infrastructure::PgStorage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+fn chat_service(pool: &PgPool, broadcaster: Arc<dyn MessageBroadcaster>) -> ChatService {
+    ChatService::new(ChatServiceDependencies {
+        broadcaster,
+        ..chat_service_deps(pool)
+    })
+}
+
+/// 由 owner 创建公开房间，member 加入
+async fn create_room(service: &ChatService, owner_id: Uuid, member_id: Uuid) -> Uuid {
+    let room = service
+        .create_room(CreateRoomRequest {
+            name: format!("resync-{}", &Uuid::new_v4().to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+    service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id: member_id,
+            password: None,
+        })
+        .await
+        .unwrap();
+    room_id
+}
+
+async fn send(service: &ChatService, room_id: Uuid, sender_id: Uuid, i: usize) -> domain::Message {
+    service
+        .send_message(SendMessageRequest {
+            room_id,
+            sender_id,
+            content: format!("message {i}"),
+            message_type: MessageType::Text,
+            reply_to: None,
+            idempotency_key: None,
+        })
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn gap_is_detected_and_resync_replays_missing_messages_in_order() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(LocalMessageBroadcaster::new(16)));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let room = RoomId::from(room_id);
+
+    let mut sent = Vec::new();
+    for i in 0..6 {
+        sent.push(send(&service, room_id, owner, i).await);
+    }
+
+    // 客户端收到第 1、2 条后，实时流里跳到了第 5 条
+    let mut tracker = SequenceTracker::new();
+    tracker.starting_at(room, sent[0].seq as u64);
+    let mut delivered: Vec<MessageId> = Vec::new();
+    for message in &sent[..2] {
+        assert_eq!(
+            tracker.observe(room, message.seq as u64),
+            SequenceCheck::InOrder
+        );
+        delivered.push(message.id);
+    }
+    let check = tracker.observe(room, sent[4].seq as u64);
+    assert_eq!(
+        check,
+        SequenceCheck::Gap {
+            from_seq: sent[2].seq as u64
+        }
+    );
+
+    // 从缺口处重放：按序列号拿回第 3 条及之后的全部消息
+    let SequenceCheck::Gap { from_seq } = check else {
+        unreachable!()
+    };
+    let replay = service
+        .replay_from_seq(room_id, member, from_seq as i64, 100)
+        .await
+        .unwrap();
+    assert!(!replay.has_more);
+    assert_eq!(replay.next_seq, sent[5].seq + 1);
+    let seqs: Vec<i64> = replay.messages.iter().map(|message| message.seq).collect();
+    assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+
+    for message in &replay.messages {
+        if tracker.observe(room, message.seq as u64) == SequenceCheck::InOrder {
+            delivered.push(message.id);
+        }
+    }
+    tracker.skip_to(room, replay.next_seq as u64);
+
+    // 之后实时流里迟到的第 5 条是重复，丢弃
+    assert_eq!(
+        tracker.observe(room, sent[4].seq as u64),
+        SequenceCheck::Duplicate
+    );
+    let expected: Vec<MessageId> = sent.iter().map(|message| message.id).collect();
+    assert_eq!(delivered, expected);
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn resync_replay_pages_with_next_seq() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(LocalMessageBroadcaster::new(16)));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+
+    let mut sent = Vec::new();
+    for i in 0..5 {
+        sent.push(send(&service, room_id, owner, i).await.id);
+    }
+
+    // 每页两条，按 next_seq 接续直到取完
+    let mut from_seq = 0;
+    let mut replayed = Vec::new();
+    let mut pages = 0;
+    loop {
+        let replay = service
+            .replay_from_seq(room_id, member, from_seq, 2)
+            .await
+            .unwrap();
+        pages += 1;
+        replayed.extend(replay.messages.iter().map(|message| message.id));
+        from_seq = replay.next_seq;
+        if !replay.has_more {
+            break;
+        }
+    }
+    assert_eq!(pages, 3);
+    assert_eq!(replayed, sent);
+    assert_eq!(from_seq, service.latest_seq(room_id).await.unwrap() + 1);
+
+    // 已经追上时返回空结果，next_seq 不变
+    let replay = service
+        .replay_from_seq(room_id, member, from_seq, 2)
+        .await
+        .unwrap();
+    assert!(replay.messages.is_empty());
+    assert_eq!(replay.next_seq, from_seq);
+
+    // 非成员不能重放
+    let outsider = create_user(&pool).await;
+    assert!(service
+        .replay_from_seq(room_id, outsider, 1, 2)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires local redis"]
+async fn sequencer_resumes_from_database_max_after_restart() {
+    let pool = setup_test_db().await;
+    let service = chat_service(&pool, Arc::new(LocalMessageBroadcaster::new(16)));
+    let owner = create_user(&pool).await;
+    let member = create_user(&pool).await;
+    let room_id = create_room(&service, owner, member).await;
+    let mut last = None;
+    for i in 0..3 {
+        last = Some(send(&service, room_id, owner, i).await);
+    }
+    let latest_seq = last.unwrap().seq as u64;
+
+    // Redis 中还没有这个房间的计数，相当于重启后计数丢失
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let sequencer = MessageSequencer::new(Arc::new(redis::Client::open(redis_url).unwrap()))
+        .with_storage(PgStorage::new(pool.clone()).message_repository.clone());
+    let room = RoomId::from(room_id);
+
+    assert_eq!(sequencer.expected_next(room).await.unwrap(), latest_seq + 1);
+    let assigned = sequencer
+        .assign_sequence(room, MessageId::from(Uuid::new_v4()))
+        .await
+        .unwrap();
+    assert_eq!(assigned.sequence_id, latest_seq + 1);
+    assert_eq!(sequencer.expected_next(room).await.unwrap(), latest_seq + 2);
+}
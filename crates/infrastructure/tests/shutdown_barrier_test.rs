@@ -0,0 +1,114 @@
+//! 停机屏障测试
+//!
+//! 验证：停机时进行中的发送先完成写库和广播，之后的新发送被拒绝
+mod support;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use application::{
+    broadcaster::BroadcastError,
+    repository::MessageRepository,
+    services::{ChatService, ChatServiceDependencies, CreateRoomRequest, SendMessageRequest},
+    ApplicationError, MessageBroadcast, MessageBroadcaster,
+};
+use async_trait::async_trait;
+use domain::{ChatRoomVisibility, MessageType};
+use infrastructure::PgStorage;
+use uuid::Uuid;
+
+use support::{chat_service_deps, create_user, setup_test_db};
+
+/// 广播前先等待一段时间，模拟停机时还没广播完的发送
+struct SlowBroadcaster {
+    delay: Duration,
+    delivered: Mutex<Vec<MessageBroadcast>>,
+}
+
+#[async_trait]
+impl MessageBroadcaster for SlowBroadcaster {
+    async fn broadcast(&self, broadcast: MessageBroadcast) -> Result<(), BroadcastError> {
+        tokio::time::sleep(self.delay).await;
+        self.delivered.lock().unwrap().push(broadcast);
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        _room_id: domain::RoomId,
+    ) -> Result<application::MessageStream, BroadcastError> {
+        Err(BroadcastError::failed("Not implemented for test"))
+    }
+}
+
+fn message_request(room_id: Uuid, sender_id: Uuid, content: &str) -> SendMessageRequest {
+    SendMessageRequest {
+        room_id,
+        sender_id,
+        content: content.to_string(),
+        message_type: MessageType::Text,
+        reply_to: None,
+        idempotency_key: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_shutdown_waits_for_in_flight_send() {
+    let pool = setup_test_db().await;
+    let storage = PgStorage::new(pool.clone());
+    let broadcaster = Arc::new(SlowBroadcaster {
+        delay: Duration::from_millis(300),
+        delivered: Mutex::new(Vec::new()),
+    });
+    let chat_service = Arc::new(ChatService::new(ChatServiceDependencies {
+        broadcaster: broadcaster.clone(),
+        ..chat_service_deps(&pool)
+    }));
+
+    let owner_id = create_user(&pool).await;
+    let room = chat_service
+        .create_room(CreateRoomRequest {
+            name: format!("shutdown-{}", &owner_id.to_string()[0..8]),
+            owner_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+    let room_id = Uuid::from(room.id);
+
+    // 发送进行到一半（卡在广播）时开始停机
+    let sending = tokio::spawn({
+        let chat_service = chat_service.clone();
+        async move {
+            chat_service
+                .send_message(message_request(room_id, owner_id, "in flight"))
+                .await
+        }
+    });
+    let barrier = chat_service.shutdown_barrier();
+    while barrier.in_flight() == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    assert!(barrier.shutdown(Duration::from_secs(5)).await);
+
+    // 屏障放行时消息已经写库并广播
+    assert_eq!(broadcaster.delivered.lock().unwrap().len(), 1);
+    let sent = sending.await.unwrap().unwrap();
+    let stored = storage
+        .message_repository
+        .find_by_id(sent.id)
+        .await
+        .unwrap()
+        .expect("message should be persisted");
+    assert_eq!(stored.content.as_str(), "in flight");
+
+    // 停机后的新发送被拒绝
+    let rejected = chat_service
+        .send_message(message_request(room_id, owner_id, "too late"))
+        .await;
+    assert!(matches!(rejected, Err(ApplicationError::ShuttingDown)));
+    assert_eq!(barrier.in_flight(), 0);
+}
@@ -99,10 +99,23 @@ async fn test_atomic_create_room() {
         room_repository: storage.room_repository.clone(),
         member_repository: storage.member_repository.clone(),
         message_repository: storage.message_repository.clone(),
+        reaction_repository: storage.reaction_repository.clone(),
+        user_block_repository: storage.user_block_repository.clone(),
+        mention_repository: storage.mention_repository.clone(),
+        delivery_repository: storage.delivery_repository.clone(),
         user_repository: storage.user_repository.clone(),
+        audit_log_repository: storage.audit_log_repository.clone(),
+        file_upload_repository: storage.file_upload_repository.clone(),
+        notification_repository: storage.notification_repository.clone(),
+        idempotency_key_repository: storage.idempotency_key_repository.clone(),
+        invitation_repository: storage.invitation_repository.clone(),
+        invite_repository: storage.invite_repository.clone(),
         password_hasher: Arc::new(TestPasswordHasher),
         clock: Arc::new(TestClock::new()),
         broadcaster: Arc::new(TestBroadcaster),
+        message_config: config::MessageConfig::default(),
+        room_encryption: None,
+        send_rate_limiter: None,
     });
 
     let owner_id = Uuid::new_v4();
@@ -118,6 +131,10 @@ async fn test_atomic_create_room() {
         created_at: OffsetDateTime::now_utc(),
         updated_at: OffsetDateTime::now_utc(),
         org_id: None,
+        display_name: None,
+        avatar_url: None,
+        bio: None,
+        token_version: 0,
     };
 
     storage.user_repository.create(user).await.unwrap();
@@ -0,0 +1,129 @@
+//! 用户在线时长聚合测试
+//!
+//! 验证：同一会话的 Connected/Disconnected 配对计时；窗口内没有断开的会话算到窗口结束；
+//! 没有对应连接的 Disconnected 被忽略
+mod support;
+
+use chrono::{DateTime, Duration, Utc};
+use domain::UserId;
+use infrastructure::{StatsAggregationService, TimeGranularity};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use support::setup_test_db;
+
+/// 创建用户和房间，返回 (用户ID, 房间ID)
+async fn create_user_and_room(pool: &PgPool) -> (Uuid, Uuid) {
+    let user_id = Uuid::new_v4();
+    let suffix = &user_id.to_string()[0..8];
+    sqlx::query(
+        "INSERT INTO users (id, username, email, password_hash, status)
+         VALUES ($1, $2, $3, 'hashed_password', 'active'::user_status)",
+    )
+    .bind(user_id)
+    .bind(format!("online_{suffix}"))
+    .bind(format!("online_{suffix}@example.com"))
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+
+    let room_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO chat_rooms (id, name, owner_id, is_private)
+         VALUES ($1, $2, $3, FALSE)",
+    )
+    .bind(room_id)
+    .bind(format!("online_room_{suffix}"))
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .expect("Failed to create test room");
+
+    (user_id, room_id)
+}
+
+async fn insert_event(
+    pool: &PgPool,
+    user_id: Uuid,
+    room_id: Uuid,
+    session_id: Uuid,
+    event_type: &str,
+    timestamp: DateTime<Utc>,
+) {
+    sqlx::query(
+        "INSERT INTO presence_events (user_id, room_id, event_type, timestamp, session_id)
+         VALUES ($1, $2, $3::presence_event_type, $4, $5)",
+    )
+    .bind(user_id)
+    .bind(room_id)
+    .bind(event_type)
+    .bind(timestamp)
+    .bind(session_id)
+    .execute(pool)
+    .await
+    .expect("Failed to insert presence event");
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_user_online_time_pairs_sessions() {
+    let pool = setup_test_db().await;
+    let service = StatsAggregationService::new(pool.clone());
+
+    let window_start = TimeGranularity::Hour.bucket_start(Utc::now() - Duration::hours(2));
+    let window_end = window_start + Duration::hours(1);
+    service
+        .create_partition_if_not_exists(window_start)
+        .await
+        .unwrap();
+    service
+        .create_partition_if_not_exists(window_end)
+        .await
+        .unwrap();
+
+    let (user_id, room_id) = create_user_and_room(&pool).await;
+    let minute = |m: i64| window_start + Duration::minutes(m);
+
+    // 正常配对的会话：15 分钟
+    let paired = Uuid::new_v4();
+    insert_event(&pool, user_id, room_id, paired, "Connected", minute(10)).await;
+    insert_event(&pool, user_id, room_id, paired, "Disconnected", minute(25)).await;
+
+    // 窗口内没有断开（断开在窗口之后）：算到窗口结束，10 分钟
+    let open = Uuid::new_v4();
+    insert_event(&pool, user_id, room_id, open, "Connected", minute(50)).await;
+    insert_event(&pool, user_id, room_id, open, "Disconnected", minute(70)).await;
+
+    // 没有对应 Connected 的 Disconnected：忽略
+    let orphan = Uuid::new_v4();
+    insert_event(&pool, user_id, room_id, orphan, "Disconnected", minute(5)).await;
+
+    let results = service
+        .aggregate_user_online_time(TimeGranularity::Hour, window_start, window_end)
+        .await
+        .unwrap();
+    let mine: Vec<_> = results
+        .iter()
+        .filter(|item| item.user_id == UserId::from(user_id))
+        .collect();
+    assert_eq!(mine.len(), 1);
+    assert_eq!(mine[0].time_bucket, window_start);
+    assert_eq!(mine[0].online_seconds, 25 * 60);
+
+    let total = service
+        .user_online_seconds(UserId::from(user_id), window_start, window_end)
+        .await
+        .unwrap();
+    assert_eq!(total, 25 * 60);
+
+    // 重算同一窗口结果不变
+    service
+        .aggregate_user_online_time(TimeGranularity::Hour, window_start, window_end)
+        .await
+        .unwrap();
+    let total = service
+        .user_online_seconds(UserId::from(user_id), window_start, window_end)
+        .await
+        .unwrap();
+    assert_eq!(total, 25 * 60);
+}
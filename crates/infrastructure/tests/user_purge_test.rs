@@ -0,0 +1,193 @@
+//! 清除用户内容测试
+//!
+//! 验证：系统管理员清除用户内容后，该用户在所有房间的消息被软删除、回应被移除，
+//! ban 时移出加入的房间并停用账号，操作记入审计日志
+mod support;
+
+use std::sync::Arc;
+
+use application::{
+    repository::{AuditLogRepository, MessageRepository, RoomMemberRepository, UserRepository},
+    services::{
+        ChatService, ChatServiceDependencies, CreateRoomRequest, JoinRoomRequest,
+        PurgeUserContentRequest, ReactionRequest, SendMessageRequest,
+    },
+    ApplicationError, WebSocketMessage,
+};
+use domain::{AuditLogEntry, ChatRoomVisibility, DomainError, MessageType, UserId, UserStatus};
+use infrastructure::PgStorage;
+use uuid::Uuid;
+
+use support::{
+    chat_service_deps, create_superuser, create_user, setup_test_db, RecordingBroadcaster,
+};
+
+fn deleted_count(broadcaster: &RecordingBroadcaster) -> usize {
+    broadcaster
+        .messages()
+        .iter()
+        .filter(|message| matches!(message, WebSocketMessage::MessageDeleted { .. }))
+        .count()
+}
+
+fn message_request(room_id: Uuid, sender_id: Uuid, content: &str) -> SendMessageRequest {
+    SendMessageRequest {
+        room_id,
+        sender_id,
+        content: content.to_string(),
+        message_type: MessageType::Text,
+        reply_to: None,
+        idempotency_key: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_purge_user_content_across_rooms() {
+    let pool = setup_test_db().await;
+    let storage = PgStorage::new(pool.clone());
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let chat_service = ChatService::new(ChatServiceDependencies {
+        broadcaster: broadcaster.clone(),
+        ..chat_service_deps(&pool)
+    });
+
+    let admin_id = create_superuser(&pool).await;
+    let owner_id = create_user(&pool).await;
+    let target_id = create_user(&pool).await;
+
+    // 1. 目标用户加入别人的两个房间并发言，另外自己创建一个房间
+    let mut joined_rooms = Vec::new();
+    for i in 0..2 {
+        let room = chat_service
+            .create_room(CreateRoomRequest {
+                name: format!("purge-{}-{i}", &owner_id.to_string()[0..8]),
+                owner_id,
+                visibility: ChatRoomVisibility::Public,
+                password: None,
+            })
+            .await
+            .unwrap();
+        joined_rooms.push(Uuid::from(room.id));
+    }
+    let own_room = chat_service
+        .create_room(CreateRoomRequest {
+            name: format!("purge-own-{}", &target_id.to_string()[0..8]),
+            owner_id: target_id,
+            visibility: ChatRoomVisibility::Public,
+            password: None,
+        })
+        .await
+        .unwrap();
+
+    let mut target_messages = Vec::new();
+    for room_id in &joined_rooms {
+        chat_service
+            .join_room(JoinRoomRequest {
+                room_id: *room_id,
+                user_id: target_id,
+                password: None,
+            })
+            .await
+            .unwrap();
+        for n in 0..3 {
+            let message = chat_service
+                .send_message(message_request(*room_id, target_id, &format!("spam {n}")))
+                .await
+                .unwrap();
+            target_messages.push(message.id);
+        }
+    }
+
+    // 目标用户给房主的消息点了回应
+    let owner_message = chat_service
+        .send_message(message_request(joined_rooms[0], owner_id, "hello"))
+        .await
+        .unwrap();
+    chat_service
+        .add_reaction(ReactionRequest {
+            message_id: Uuid::from(owner_message.id),
+            user_id: target_id,
+            emoji: "👍".to_string(),
+        })
+        .await
+        .unwrap();
+
+    // 2. 普通用户不能清除
+    let denied = chat_service
+        .purge_user_content(PurgeUserContentRequest {
+            operator_id: owner_id,
+            user_id: target_id,
+            ban: true,
+        })
+        .await;
+    assert!(matches!(
+        denied,
+        Err(ApplicationError::Domain(
+            DomainError::InsufficientPermissions
+        ))
+    ));
+
+    // 3. 系统管理员清除并封禁
+    let report = chat_service
+        .purge_user_content(PurgeUserContentRequest {
+            operator_id: admin_id,
+            user_id: target_id,
+            ban: true,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(report.messages_deleted, 6);
+    assert_eq!(report.reactions_removed, 1);
+    assert_eq!(report.rooms_affected, 2);
+    assert_eq!(report.rooms_left, 2);
+    assert!(report.banned);
+    assert_eq!(deleted_count(&broadcaster), 6);
+
+    for message_id in target_messages {
+        let stored = storage
+            .message_repository
+            .find_by_id(message_id)
+            .await
+            .unwrap()
+            .expect("message should still exist");
+        assert!(stored.is_deleted);
+    }
+    let owner_message = storage
+        .message_repository
+        .find_by_id(owner_message.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!owner_message.is_deleted);
+
+    // 只保留自己创建的房间
+    let memberships = storage
+        .member_repository
+        .find_by_user(UserId::from(target_id))
+        .await
+        .unwrap();
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].room_id, own_room.id);
+
+    let target = storage
+        .user_repository
+        .find_by_id(UserId::from(target_id))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(target.status, UserStatus::Suspended);
+
+    // 4. 审计日志记录了操作人和结果（后台写入）
+    chat_service.audit_logger().flush().await;
+    let entries = storage
+        .audit_log_repository
+        .find_by_target_user(UserId::from(target_id), 10)
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].actor_id, UserId::from(admin_id));
+    assert_eq!(entries[0].action, AuditLogEntry::USER_CONTENT_PURGED);
+    assert_eq!(entries[0].details["messages_deleted"], 6);
+}
@@ -3,25 +3,41 @@
 //! 启动 Axum Web API 服务。
 
 use application::repository::{
-    ChatRoomRepository, MessageRepository, RoomMemberRepository, UserRepository,
+    AuditLogRepository, ChatRoomRepository, EmailVerificationRepository, FileUploadRepository,
+    IdempotencyKeyRepository, MentionRepository, MessageDeliveryRepository, MessageRepository,
+    NotificationRepository, PasswordResetRepository, ReactionRepository, RoomInvitationRepository,
+    RoomInviteRepository, RoomMemberRepository, UserBlockRepository, UserRepository,
 };
 use application::{
     services::{
-        BulkUserService, ChatService, ChatServiceDependencies, StatsService, UserService,
+        spawn_orphan_upload_cleanup, BulkUserService, ChatService, ChatServiceDependencies,
+        StatsService, UploadService, UploadServiceDependencies, UserService,
         UserServiceDependencies,
     },
-    Clock, MessageBroadcaster, PasswordHasher, SystemClock,
+    wait_for_dependency, Clock, ConfigReloader, EmailSender, EncryptedMentionRepository,
+    EncryptedMessageRepository, LocalMessageBroadcaster, MessageBroadcaster, MessageCipher,
+    MessageRateLimiter, PasswordHasher, Readiness, RoomEncryption, SendRateLimiter, SystemClock,
 };
 use config::AppConfig;
 use infrastructure::{
-    create_pg_pool, BcryptPasswordHasher, PgChatRoomRepository, PgMessageRepository,
-    PgOrganizationRepository, PgRoomMemberRepository, PgStorage, PgUserRepository,
-    RedisMessageBroadcaster, StatsAggregationService,
+    create_pg_pool, entity_cache, BatchingMessageRepository, BcryptPasswordHasher,
+    CachedChatRoomRepository, CachedUserRepository, LocalObjectStorage, LoggingEmailSender,
+    OutboxDispatcher, PgAuditLogRepository, PgChatRoomRepository, PgEmailVerificationRepository,
+    PgFileUploadRepository, PgIdempotencyKeyRepository, PgMentionRepository,
+    PgMessageDeliveryRepository, PgMessageRepository, PgNotificationRepository,
+    PgOrganizationRepository, PgOutboxRepository, PgPasswordResetRepository, PgReactionRepository,
+    PgRoomInvitationRepository, PgRoomInviteRepository, PgRoomMemberRepository, PgStorage,
+    PgUserBlockRepository, PgUserRepository, RedisMessageBroadcaster, RetryPolicy,
+    StatsAggregationService,
 };
 use redis::Client as RedisClient;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::EnvFilter;
-use web_api::{router, AppState, JwtService};
+use web_api::{
+    cors_layer, router, serve_with_graceful_shutdown, AppState, HealthProbe, HttpRateLimiter,
+    JwtService, WsConnectionLimits, WsDeflateSubprotocol, WsFlowControl, WsHeartbeat,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -50,8 +66,11 @@ async fn main() -> anyhow::Result<()> {
         }
     );
 
-    // 直接创建 PostgreSQL 连接池
-    let pg_pool = create_pg_pool(&config.database.url, config.database.max_connections).await?;
+    // 关键依赖就绪之前不监听端口，重试用尽直接启动失败
+    let pg_pool = wait_for_dependency("postgres", &config.startup, || {
+        create_pg_pool(&config.database.url, config.database.max_connections)
+    })
+    .await?;
 
     // 运行迁移
     sqlx::migrate!("../../migrations").run(&pg_pool).await?;
@@ -62,21 +81,127 @@ async fn main() -> anyhow::Result<()> {
         Arc::new(PgChatRoomRepository::new(pg_pool.clone()));
     let member_repository: Arc<dyn RoomMemberRepository> =
         Arc::new(PgRoomMemberRepository::new(pg_pool.clone()));
-    let message_repository: Arc<dyn MessageRepository> =
-        Arc::new(PgMessageRepository::new(pg_pool.clone()));
+    // 开启攒批时新消息攒够一批或等到间隔后一次写库
+    let message_repository: Arc<dyn MessageRepository> = BatchingMessageRepository::from_config(
+        Arc::new(PgMessageRepository::new(pg_pool.clone())),
+        &config.message,
+    );
+    let reaction_repository: Arc<dyn ReactionRepository> =
+        Arc::new(PgReactionRepository::new(pg_pool.clone()));
+    let user_block_repository: Arc<dyn UserBlockRepository> =
+        Arc::new(PgUserBlockRepository::new(pg_pool.clone()));
+    let mention_repository: Arc<dyn MentionRepository> =
+        Arc::new(PgMentionRepository::new(pg_pool.clone()));
+    let delivery_repository: Arc<dyn MessageDeliveryRepository> =
+        Arc::new(PgMessageDeliveryRepository::new(pg_pool.clone()));
+    let email_verification_repository: Arc<dyn EmailVerificationRepository> =
+        Arc::new(PgEmailVerificationRepository::new(pg_pool.clone()));
+    let password_reset_repository: Arc<dyn PasswordResetRepository> =
+        Arc::new(PgPasswordResetRepository::new(pg_pool.clone()));
+    let audit_log_repository: Arc<dyn AuditLogRepository> =
+        Arc::new(PgAuditLogRepository::new(pg_pool.clone()));
+    let file_upload_repository: Arc<dyn FileUploadRepository> =
+        Arc::new(PgFileUploadRepository::new(pg_pool.clone()));
+    let notification_repository: Arc<dyn NotificationRepository> =
+        Arc::new(PgNotificationRepository::new(pg_pool.clone()));
+    let idempotency_key_repository: Arc<dyn IdempotencyKeyRepository> =
+        Arc::new(PgIdempotencyKeyRepository::new(pg_pool.clone()));
+    let invitation_repository: Arc<dyn RoomInvitationRepository> =
+        Arc::new(PgRoomInvitationRepository::new(pg_pool.clone()));
+    let invite_repository: Arc<dyn RoomInviteRepository> =
+        Arc::new(PgRoomInviteRepository::new(pg_pool.clone()));
+
+    // 房间和用户按 ID 查询走缓存，修改经过同一层包装，写库后同步更新缓存
+    let (user_repository, room_repository) = if config.cache.enabled {
+        let redis = match &config.broadcast.redis_url {
+            Some(redis_url) => Some(Arc::new(RedisClient::open(redis_url.clone())?)),
+            None => None,
+        };
+        let cache = entity_cache(&config.cache, redis);
+        let ttl = Duration::from_secs(config.cache.ttl_secs);
+        (
+            Arc::new(CachedUserRepository::new(
+                user_repository,
+                cache.clone(),
+                ttl,
+            )) as Arc<dyn UserRepository>,
+            Arc::new(CachedChatRoomRepository::new(room_repository, cache, ttl))
+                as Arc<dyn ChatRoomRepository>,
+        )
+    } else {
+        (user_repository, room_repository)
+    };
+
+    // 创建 JWT 服务（校验令牌时核对用户的令牌版本）；服务层修改用户经过它包装的仓库，
+    // 改了令牌版本或状态立即清掉令牌校验缓存
+    let jwt_service = Arc::new(JwtService::new(config.jwt.clone(), user_repository.clone()));
+    let user_repository = jwt_service.invalidating_user_repository(user_repository);
+
+    // 配置了主密钥时，消息和提及的读写经过房间加密层，加密房间的内容以密文入库
+    let room_encryption = MessageCipher::from_config(&config.encryption)?
+        .map(|cipher| Arc::new(RoomEncryption::new(cipher, room_repository.clone())));
+    let (message_repository, mention_repository) = match &room_encryption {
+        Some(encryption) => (
+            Arc::new(EncryptedMessageRepository::new(
+                message_repository,
+                encryption.clone(),
+            )) as Arc<dyn MessageRepository>,
+            Arc::new(EncryptedMentionRepository::new(
+                mention_repository,
+                encryption.clone(),
+            )) as Arc<dyn MentionRepository>,
+        ),
+        None => (message_repository, mention_repository),
+    };
 
     // 创建其他服务
     let password_hasher: Arc<dyn PasswordHasher> = Arc::new(BcryptPasswordHasher::default());
     let clock: Arc<dyn Clock> = Arc::new(SystemClock::default());
-    let redis_url = config
-        .broadcast
-        .redis_url
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("Redis URL is required for broadcaster"))?;
-    let client = RedisClient::open(redis_url.clone())?;
-    let broadcaster: Arc<dyn MessageBroadcaster> = Arc::new(RedisMessageBroadcaster::new(client));
-
-    // 创建统计相关服务
+    // 还没有接入邮件服务，验证链接只打印到日志
+    let email_sender: Arc<dyn EmailSender> = Arc::new(LoggingEmailSender);
+    // 就绪探针持续检查启动时等待过的关键依赖
+    let mut health_probe = HealthProbe::new(
+        pg_pool.clone(),
+        Duration::from_millis(config.server.readiness_check_timeout_ms),
+    );
+    // 多实例部署（配置了 broadcast.redis_url）通过 Redis 广播，单实例部署在进程内广播
+    let broadcaster: Arc<dyn MessageBroadcaster> = match &config.broadcast.redis_url {
+        Some(redis_url) => {
+            let client = RedisClient::open(redis_url.clone())?;
+            wait_for_dependency("redis", &config.startup, || ping_redis(&client)).await?;
+            health_probe = health_probe.with_redis("redis", client.clone());
+            Arc::new(
+                RedisMessageBroadcaster::new(client)
+                    .with_retry_policy(RetryPolicy::from_config(&config.broadcast)),
+            )
+        }
+        None => {
+            tracing::info!("未配置 broadcast.redis_url，消息只在本进程内广播");
+            Arc::new(LocalMessageBroadcaster::from_config(&config.broadcast))
+        }
+    };
+
+    // 创建消息限流器（默认策略来自配置，房间策略存放在 Redis）
+    let rate_limit_client = RedisClient::open(config.redis.url.clone())?;
+    if config.broadcast.redis_url.as_ref() != Some(&config.redis.url) {
+        wait_for_dependency("rate limit redis", &config.startup, || {
+            ping_redis(&rate_limit_client)
+        })
+        .await?;
+        health_probe = health_probe.with_redis("rate limit redis", rate_limit_client.clone());
+    }
+    let rate_limiter = Arc::new(MessageRateLimiter::from_config(
+        Arc::new(rate_limit_client),
+        &config.rate_limit,
+    ));
+    // 发送配额的令牌桶也放在 Redis，多实例共享
+    let send_rate_limiter: Arc<dyn SendRateLimiter> = rate_limiter.clone();
+
+    // 限流默认策略支持运行时重载（SIGHUP 或管理接口），其他配置改了要重启
+    let config_reloader = Arc::new(ConfigReloader::new(config.clone(), rate_limiter.clone()));
+    spawn_reload_on_hangup(config_reloader.clone());
+
+    // 创建统计相关服务（非关键依赖，查询时才用到，不阻塞启动）
     let stats_aggregation_service = Arc::new(StatsAggregationService::new(pg_pool.clone()));
     let stats_service = Arc::new(StatsService::new(Arc::new(pg_pool.clone())));
 
@@ -96,28 +221,108 @@ async fn main() -> anyhow::Result<()> {
                 &config,
             ))
         } else {
-            Arc::new(application::presence::memory::MemoryPresenceManager::new())
+            Arc::new(
+                application::presence::memory::MemoryPresenceManager::with_heartbeat_timeout(
+                    Duration::from_secs(config.presence.heartbeat_timeout_secs),
+                ),
+            )
         };
 
+    // 后台清扫心跳超时的会话（WebSocket 非正常断开时用户不会一直显示在线）
+    application::spawn_heartbeat_sweeper(
+        presence_manager.clone(),
+        broadcaster.clone(),
+        Duration::from_secs(config.presence.sweep_interval_secs),
+    );
+
+    // 定期清理已送达的投递记录，未送达的留着给离线用户补发
+    application::spawn_delivery_cleanup(
+        delivery_repository.clone(),
+        Duration::from_secs(config.message.delivery_cleanup_interval_secs),
+        Duration::from_secs(config.message.delivery_retention_secs),
+    );
+
+    // 过期的幂等键不再生效，定期删掉（最长一小时清理一次）
+    application::spawn_idempotency_key_cleanup(
+        idempotency_key_repository.clone(),
+        Duration::from_secs(config.message.idempotency_key_ttl_secs.min(3600)),
+    );
+
     let user_service = UserService::new(UserServiceDependencies {
         user_repository: user_repository.clone(),
         password_hasher: password_hasher.clone(),
         clock: clock.clone(),
         presence_manager: presence_manager.clone(),
+        email_verification_repository,
+        password_reset_repository,
+        email_sender,
+        registration_config: config.registration.clone(),
+    });
+
+    // 开启发件箱时新消息由后台任务广播，进程在写库后退出的消息重启后照样发出
+    let outbox_dispatcher = config.message.outbox.enabled.then(|| {
+        OutboxDispatcher::new(
+            Arc::new(PgOutboxRepository::new(pg_pool.clone())),
+            message_repository.clone(),
+            broadcaster.clone(),
+            config.message.outbox.clone(),
+        )
+        .spawn(pg_pool.clone())
     });
 
     let chat_service = ChatService::new(ChatServiceDependencies {
         room_repository,
         member_repository,
         message_repository,
+        reaction_repository,
+        user_block_repository,
+        mention_repository,
+        delivery_repository: delivery_repository.clone(),
         user_repository: user_repository.clone(),
+        audit_log_repository,
+        file_upload_repository: file_upload_repository.clone(),
+        notification_repository,
+        idempotency_key_repository,
+        invitation_repository,
+        invite_repository,
         password_hasher,
-        clock,
+        clock: clock.clone(),
         broadcaster: broadcaster.clone(),
+        message_config: config.message.clone(),
+        room_encryption,
+        send_rate_limiter: Some(send_rate_limiter),
     });
 
-    // 创建 JWT 服务
-    let jwt_service = Arc::new(JwtService::new(config.jwt));
+    let send_barrier = chat_service.shutdown_barrier();
+    let audit_logger = chat_service.audit_logger().clone();
+
+    // 上传的文件存放在本地目录，一直没有被消息引用的定期清理
+    let object_storage = LocalObjectStorage::new(&config.upload.storage_dir)
+        .await
+        .map_err(|e| anyhow::anyhow!("上传目录不可用: {}", e))?;
+    let upload_service = Arc::new(UploadService::new(UploadServiceDependencies {
+        file_upload_repository,
+        storage: Arc::new(object_storage),
+        clock,
+        config: config.upload.clone(),
+    }));
+    spawn_orphan_upload_cleanup(
+        upload_service.clone(),
+        Duration::from_secs(config.upload.orphan_cleanup_interval_secs),
+    );
+
+    let ws_heartbeat = WsHeartbeat::from_app_config(&config);
+    let ws_flow_control = WsFlowControl::from_app_config(&config);
+    let ws_deflate_subprotocol = WsDeflateSubprotocol::from_app_config(&config);
+    let ws_connection_limits = WsConnectionLimits::from_app_config(&config);
+
+    // 接口限流：多实例部署（配置了 broadcast.redis_url）时计数放在 Redis
+    let http_rate_limiter = HttpRateLimiter::from_app_config(&config)?;
+
+    let readiness = Readiness::new();
+
+    // 停机时还要通知广播器结束订阅
+    let broadcaster_handle = broadcaster.clone();
 
     // 创建应用状态
     let state = AppState::new(
@@ -131,13 +336,36 @@ async fn main() -> anyhow::Result<()> {
         org_repository,
         bulk_user_service,
         storage,
-    );
+        rate_limiter,
+    )
+    .with_ws_heartbeat(ws_heartbeat)
+    .with_ws_flow_control(ws_flow_control)
+    .with_ws_deflate_subprotocol(ws_deflate_subprotocol)
+    .with_ws_connection_limits(ws_connection_limits)
+    .with_config_reloader(config_reloader)
+    .with_upload_service(upload_service)
+    .with_readiness(readiness.clone())
+    .with_health_probe(health_probe)
+    .with_http_rate_limiter(http_rate_limiter)
+    .with_cors(cors_layer(&config.cors))
+    .with_stats_api_token(config.stats.api_token.clone());
+
+    // 指标 recorder 在开始处理请求之前安装
+    let state = if config.server.metrics_enabled {
+        let metrics = web_api::metrics::install_recorder()?;
+        web_api::metrics::spawn_upkeep(metrics.clone(), Duration::from_secs(5));
+        state.with_metrics(metrics)
+    } else {
+        state
+    };
 
     // 启动 Web 服务器
+    let ws_connections = state.ws_connections.clone();
     let app = router(state);
     let listener =
         tokio::net::TcpListener::bind(format!("{}:{}", config.server.host, config.server.port))
             .await?;
+    readiness.mark_ready();
 
     tracing::info!(
         "🚀 聊天室服务器启动在 http://{}:{} (配置模式: {})",
@@ -149,7 +377,103 @@ async fn main() -> anyhow::Result<()> {
             "生产环境"
         }
     );
-    axum::serve(listener, app).await?;
 
+    // 收到停机信号后停止接受新连接，通知 WebSocket 客户端重连，等进行中的请求完成
+    let shutdown = {
+        let readiness = readiness.clone();
+        async move {
+            shutdown_signal().await;
+            readiness.mark_not_ready();
+            tracing::info!("开始停机，等待进行中的请求和 WebSocket 连接结束");
+        }
+    };
+    let drain_timeout = Duration::from_secs(config.server.drain_timeout_secs);
+    if serve_with_graceful_shutdown(listener, app, ws_connections, shutdown, drain_timeout).await? {
+        tracing::info!("进行中的请求和 WebSocket 连接已全部结束");
+    }
+
+    // 连接排空后拒绝新的发送，等后台进行中的发送写库并广播完
+    let timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    if send_barrier.shutdown(timeout).await {
+        tracing::info!("进行中的消息发送已全部完成");
+    } else {
+        tracing::warn!(
+            remaining = send_barrier.in_flight(),
+            "等待消息发送超时，强制停机"
+        );
+    }
+    if tokio::time::timeout(timeout, audit_logger.flush())
+        .await
+        .is_err()
+    {
+        tracing::warn!("等待审计记录写入超时，强制停机");
+    }
+    // 未发布的发件箱记录留在库里，下次启动（或其他实例）继续发布
+    if let Some(dispatcher) = outbox_dispatcher {
+        dispatcher.abort();
+    }
+    broadcaster_handle.shutdown().await;
+
+    Ok(())
+}
+
+/// 探测 Redis 是否可用
+async fn ping_redis(client: &RedisClient) -> redis::RedisResult<()> {
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    let _: () = redis::cmd("PING").query_async(&mut conn).await?;
     Ok(())
 }
+
+/// 收到 SIGHUP 时重新加载配置；新配置不合法时保留原配置
+#[cfg(unix)]
+fn spawn_reload_on_hangup(reloader: Arc<ConfigReloader>) {
+    tokio::spawn(async move {
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(signal) => signal,
+            Err(err) => {
+                tracing::error!(error = %err, "监听 SIGHUP 失败，配置重载只能通过管理接口触发");
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            tracing::info!("接收到 SIGHUP 信号，重新加载配置");
+            if let Err(err) = reloader.reload().await {
+                tracing::error!(error = %err, "配置重载失败，继续使用原配置");
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_reload_on_hangup(_reloader: Arc<ConfigReloader>) {}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "监听 Ctrl+C 失败");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig_term) => {
+                sig_term.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "监听终止信号失败");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("接收到 Ctrl+C 信号"),
+        _ = terminate => tracing::info!("接收到终止信号"),
+    }
+}
@@ -1,13 +1,15 @@
 use anyhow::Result;
+use application::repository::MessageRepository;
+use application::StatsPipelineControl;
 use chrono::{Duration, Utc};
 use config::AppConfig;
-use infrastructure::repository::create_pg_pool;
+use infrastructure::repository::{create_pg_pool, PgMessageRepository};
 use infrastructure::stats_aggregation::{StatsAggregationService, TimeGranularity};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::signal;
 use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// 统计聚合服务
 ///
@@ -15,19 +17,46 @@ use tracing::{error, info};
 /// 并将结果保存到 stats_aggregated 表供报表查询使用。
 pub struct StatsAggregator {
     aggregation_service: StatsAggregationService,
+    message_repository: Arc<dyn MessageRepository>,
+    /// 运行时暂停控制；未配置 Redis 时为 None，管道始终运行
+    pipeline_control: Option<Arc<StatsPipelineControl>>,
 }
 
 impl StatsAggregator {
     /// 创建新的统计聚合服务
-    pub fn new(pool: PgPool) -> Self {
-        let aggregation_service = StatsAggregationService::new(pool);
+    pub fn new(pool: PgPool, pipeline_control: Option<Arc<StatsPipelineControl>>) -> Self {
+        let aggregation_service = StatsAggregationService::new(pool.clone());
         Self {
             aggregation_service,
+            message_repository: Arc::new(PgMessageRepository::new(pool)),
+            pipeline_control,
         }
     }
 
+    /// 管道暂停时跳过本次任务：不计算、不推进水位线，恢复后下次调度补上
+    async fn should_skip(&self, job: &str) -> bool {
+        let Some(control) = &self.pipeline_control else {
+            return false;
+        };
+        if !control.is_paused().await {
+            return false;
+        }
+        control.record_skipped_cycle();
+        let metrics = control.metrics();
+        info!(
+            job,
+            skipped_cycles = metrics.skipped_cycles,
+            "统计管道已暂停，跳过本次任务"
+        );
+        true
+    }
+
     /// 执行小时级增量统计聚合（优化版本）
     pub async fn aggregate_hourly_stats(&self) -> Result<()> {
+        if self.should_skip("hourly").await {
+            return Ok(());
+        }
+
         info!("开始执行小时级增量统计聚合");
 
         let count = self
@@ -36,11 +65,28 @@ impl StatsAggregator {
             .await?;
 
         info!("小时级增量统计聚合完成，处理了 {} 条记录", count);
+
+        // 用户在线时长按上一个完整小时计算
+        let current_hour = TimeGranularity::Hour.bucket_start(Utc::now());
+        let online_time = self
+            .aggregation_service
+            .aggregate_user_online_time(
+                TimeGranularity::Hour,
+                current_hour - Duration::hours(1),
+                current_hour,
+            )
+            .await?;
+
+        info!("用户在线时长聚合完成，写入 {} 条记录", online_time.len());
         Ok(())
     }
 
     /// 执行日级增量统计聚合（优化版本）
     pub async fn aggregate_daily_stats(&self) -> Result<()> {
+        if self.should_skip("daily").await {
+            return Ok(());
+        }
+
         info!("开始执行日级增量统计聚合");
 
         let count = self
@@ -54,6 +100,10 @@ impl StatsAggregator {
 
     /// 执行周级增量统计聚合（优化版本）
     pub async fn aggregate_weekly_stats(&self) -> Result<()> {
+        if self.should_skip("weekly").await {
+            return Ok(());
+        }
+
         info!("开始执行周级增量统计聚合");
 
         let count = self
@@ -67,6 +117,10 @@ impl StatsAggregator {
 
     /// 执行月级增量统计聚合（优化版本）
     pub async fn aggregate_monthly_stats(&self) -> Result<()> {
+        if self.should_skip("monthly").await {
+            return Ok(());
+        }
+
         info!("开始执行月级增量统计聚合");
 
         let count = self
@@ -80,6 +134,10 @@ impl StatsAggregator {
 
     /// 执行数据清理和分区管理
     pub async fn cleanup_expired_data(&self) -> Result<()> {
+        if self.should_skip("cleanup").await {
+            return Ok(());
+        }
+
         info!("开始数据清理和分区管理");
 
         // 1. 创建下个月的分区（预创建）
@@ -122,6 +180,24 @@ impl StatsAggregator {
         Ok(())
     }
 
+    /// 物理删除超过保留天数的消息；`retention_days` 为 0 时永久保留
+    ///
+    /// 与统计管道无关，管道暂停时照常执行
+    pub async fn purge_expired_messages(&self, retention_days: u32) -> Result<u64> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+
+        let cutoff =
+            time::OffsetDateTime::now_utc() - time::Duration::days(i64::from(retention_days));
+        info!(retention_days, %cutoff, "开始清理过期消息");
+
+        let purged = self.message_repository.purge_older_than(cutoff).await?;
+
+        info!("过期消息清理完成，删除了 {} 条消息", purged);
+        Ok(purged)
+    }
+
     /// 运行统计聚合服务主循环
     pub async fn run(self: Arc<Self>) -> Result<()> {
         info!("启动统计聚合服务");
@@ -222,12 +298,40 @@ impl StatsAggregator {
             )?)
             .await?;
 
+        // 过期消息清理 - 保留天数为 0 时不注册任务
+        let retention_days = config.retention.message_days;
+        if retention_days > 0 {
+            let purge_aggregator = aggregator.clone();
+            let purge_schedule = config.retention.message_purge_schedule.clone();
+            scheduler
+                .add(Job::new_async(
+                    purge_schedule.as_str(),
+                    move |_uuid, _l| {
+                        let agg = purge_aggregator.clone();
+                        Box::pin(async move {
+                            if let Err(e) = agg.purge_expired_messages(retention_days).await {
+                                error!("过期消息清理失败: {}", e);
+                            }
+                        })
+                    },
+                )?)
+                .await?;
+        }
+
         info!("定时任务已设置完成");
         info!("小时级统计: {}", config.stats.schedule.hourly_aggregation);
         info!("日级统计: {}", config.stats.schedule.daily_aggregation);
         info!("周级统计: {}", config.stats.schedule.weekly_aggregation);
         info!("月级统计: {}", config.stats.schedule.monthly_aggregation);
         info!("数据清理: {}", config.stats.schedule.data_cleanup);
+        if retention_days > 0 {
+            info!(
+                "过期消息清理: {}（保留 {} 天）",
+                config.retention.message_purge_schedule, retention_days
+            );
+        } else {
+            info!("过期消息清理: 未启用（永久保留）");
+        }
 
         // 启动调度器
         scheduler.start().await?;
@@ -270,8 +374,20 @@ async fn main() -> Result<()> {
     // 创建数据库连接池
     let db_pool = create_pg_pool(&config.database.url, config.database.max_connections).await?;
 
+    // 暂停/恢复控制（依赖 Redis 标志位）
+    let pipeline_control = match config.broadcast.redis_url.as_ref() {
+        Some(redis_url) => {
+            let redis_client = Arc::new(redis::Client::open(redis_url.clone())?);
+            Some(Arc::new(StatsPipelineControl::new(redis_client)))
+        }
+        None => {
+            warn!("Redis URL 未配置，统计管道不支持运行时暂停");
+            None
+        }
+    };
+
     // 创建统计聚合服务
-    let aggregator = StatsAggregator::new(db_pool);
+    let aggregator = StatsAggregator::new(db_pool, pipeline_control);
 
     // 运行主循环
     Arc::new(aggregator).run().await?;
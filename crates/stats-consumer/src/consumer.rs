@@ -0,0 +1,684 @@
+//! Stats Consumer 主循环
+//!
+//! 从 Redis Stream 读取用户状态事件，批量写入 PostgreSQL
+//!
+//! 消息只在所在批次写库提交（或转入死信流）之后才确认。写库前进程退出的消息留在
+//! 消费者组的待确认列表里，重启后先重读这些消息，同样写库成功后才确认；
+//! 事件主键去重，重复写入不会产生重复记录。
+//!
+//! 多个实例共用一个消费者组水平扩展；实例崩溃后留在它名下的消息由存活实例定期用
+//! XAUTOCLAIM 认领，转到自己名下后按同样的流程处理
+
+use application::{PresenceEventType, StatsPipelineControl, UserPresenceEvent};
+use chrono::{DateTime, Utc};
+use domain::{RoomId, UserId};
+use redis::streams::{
+    StreamAutoClaimReply, StreamPendingCountReply, StreamRangeReply, StreamReadReply,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::event_storage::EventStorage;
+use crate::pg_event_storage::PgEventStorage;
+
+/// Stats Consumer 配置
+#[derive(Debug, Clone)]
+pub struct ConsumerConfig {
+    pub stream_name: String,
+    pub consumer_group: String,
+    pub consumer_name: String,
+    pub batch_size: i64,
+    pub poll_interval: Duration,
+    /// 写库遇到坏事件时隔离并转入死信流，而不是整批失败
+    pub isolate_failures: bool,
+    pub dead_letter_stream: String,
+    /// 整批写库失败后最多重试的次数，用尽后转入死信流
+    pub max_retries: u32,
+    /// 空闲超过该时长的未确认消息由本实例认领，`None` 表示不认领
+    pub claim_idle: Option<Duration>,
+    /// 认领空闲消息的间隔
+    pub claim_interval: Duration,
+}
+
+impl ConsumerConfig {
+    pub fn from_app_config(app_config: &config::AppConfig) -> Self {
+        Self {
+            stream_name: app_config.presence.stream_name.clone(),
+            consumer_group: app_config.stats.consumer.consumer_group.clone(),
+            consumer_name: match app_config.stats.consumer.consumer_name.as_str() {
+                "" => instance_consumer_name(),
+                name => name.to_string(),
+            },
+            batch_size: app_config.stats.consumer.batch_size,
+            poll_interval: Duration::from_secs(app_config.stats.consumer.poll_interval_secs),
+            isolate_failures: app_config.stats.consumer.isolate_failures,
+            dead_letter_stream: app_config.stats.consumer.dead_letter_stream.clone(),
+            max_retries: app_config.stats.consumer.max_retries,
+            claim_idle: (app_config.stats.consumer.claim_idle_secs > 0)
+                .then(|| Duration::from_secs(app_config.stats.consumer.claim_idle_secs)),
+            claim_interval: Duration::from_secs(app_config.stats.consumer.claim_interval_secs),
+        }
+    }
+}
+
+/// 本实例的消费者名称：`主机名-进程号`
+///
+/// 容器里优先用 `HOSTNAME`（即 Pod 名），取不到时读 `/etc/hostname`
+pub fn instance_consumer_name() -> String {
+    let hostname = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "stats-consumer".to_string());
+    format!("{hostname}-{}", std::process::id())
+}
+
+/// 死信流中的一条记录
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeadLetterEntry {
+    /// 死信流里的消息 ID
+    pub id: String,
+    /// 原事件流里的消息 ID
+    pub source_id: Option<String>,
+    pub reason: Option<String>,
+    /// 写库失败时是序列化后的事件，解析失败时是原始字段（JSON 对象）
+    pub payload: Option<String>,
+}
+
+/// 重新处理死信流的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeadLetterReprocessReport {
+    /// 写库成功并从死信流删除的事件数
+    pub reprocessed: usize,
+    /// 仍然无法解析或写库、留在死信流里的事件数
+    pub remaining: usize,
+}
+
+/// Stats Consumer 主服务
+pub struct StatsConsumer {
+    redis_client: Arc<redis::Client>,
+    event_storage: PgEventStorage,
+    config: ConsumerConfig,
+    pipeline_control: Arc<StatsPipelineControl>,
+}
+
+impl StatsConsumer {
+    /// 创建 Stats Consumer
+    pub fn new(
+        redis_client: Arc<redis::Client>,
+        event_storage: PgEventStorage,
+        config: ConsumerConfig,
+        pipeline_control: Arc<StatsPipelineControl>,
+    ) -> Self {
+        Self {
+            redis_client,
+            event_storage,
+            config,
+            pipeline_control,
+        }
+    }
+
+    /// 生效的消费者配置
+    pub fn config(&self) -> &ConsumerConfig {
+        &self.config
+    }
+
+    /// 启动消费者主循环
+    pub async fn run(&self) -> anyhow::Result<()> {
+        info!(
+            stream_name = %self.config.stream_name,
+            consumer_group = %self.config.consumer_group,
+            consumer_name = %self.config.consumer_name,
+            "Stats Consumer 开始运行"
+        );
+
+        // 尝试创建消费者组（如果不存在）
+        self.ensure_consumer_group().await?;
+
+        let mut last_claim: Option<tokio::time::Instant> = None;
+        loop {
+            // 启动时和每个认领间隔认领一次崩溃实例留下的消息，随后的批次先处理它们
+            if self.config.claim_idle.is_some()
+                && last_claim.is_none_or(|at| at.elapsed() >= self.config.claim_interval)
+            {
+                match self.reclaim_stale().await {
+                    Ok(0) => {}
+                    Ok(claimed) => info!(count = claimed, "已认领空闲的未确认消息"),
+                    Err(e) => warn!(error = %e, "认领空闲消息失败"),
+                }
+                last_claim = Some(tokio::time::Instant::now());
+            }
+
+            match self.process_batch().await {
+                Ok(processed_count) => {
+                    if processed_count > 0 {
+                        info!(count = processed_count, "已处理事件批次");
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, "处理批次时发生错误");
+                    // 等待一段时间后重试
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+
+            // 轮询间隔
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// 确保消费者组存在
+    pub async fn ensure_consumer_group(&self) -> anyhow::Result<()> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        // 尝试创建消费者组，忽略 BUSYGROUP 错误（组已存在）
+        let result: Result<String, redis::RedisError> = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(&self.config.stream_name)
+            .arg(&self.config.consumer_group)
+            .arg("0") // 从流的开始位置
+            .arg("MKSTREAM") // 如果流不存在则创建
+            .query_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(_) => {
+                info!(
+                    stream_name = %self.config.stream_name,
+                    group = %self.config.consumer_group,
+                    "消费者组已创建"
+                );
+            }
+            Err(e) if e.to_string().contains("BUSYGROUP") => {
+                info!(
+                    stream_name = %self.config.stream_name,
+                    group = %self.config.consumer_group,
+                    "消费者组已存在"
+                );
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!("创建消费者组失败: {}", e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 把消费者组里空闲超过 `claim_idle` 的未确认消息认领到本实例名下，返回认领条数
+    ///
+    /// 只转移归属，不在这里处理：下一次 `process_batch` 先重读本实例名下的未确认消息，
+    /// 写库提交后才确认。认领会累加投递次数，反复失败的消息照样按 `max_retries` 转入死信流
+    pub async fn reclaim_stale(&self) -> anyhow::Result<usize> {
+        let Some(min_idle) = self.config.claim_idle else {
+            return Ok(0);
+        };
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        let mut claimed = 0;
+        let mut cursor = "0-0".to_string();
+        loop {
+            let reply: StreamAutoClaimReply = redis::cmd("XAUTOCLAIM")
+                .arg(&self.config.stream_name)
+                .arg(&self.config.consumer_group)
+                .arg(&self.config.consumer_name)
+                .arg(min_idle.as_millis() as u64)
+                .arg(&cursor)
+                .arg("COUNT")
+                .arg(self.config.batch_size)
+                .query_async(&mut conn)
+                .await?;
+            claimed += reply.claimed.len();
+
+            // 游标回到 0-0 表示整个待确认列表扫描完毕
+            if reply.next_stream_id == "0-0" {
+                break;
+            }
+            cursor = reply.next_stream_id;
+        }
+
+        Ok(claimed)
+    }
+
+    /// 处理一个批次的事件
+    ///
+    /// 管道暂停时直接跳过：不读取、不确认，消息留在 Stream 中，恢复后继续消费。
+    /// 上次写库失败未确认的消息优先重读，重试次数用尽后转入死信流
+    pub async fn process_batch(&self) -> anyhow::Result<usize> {
+        if self.pipeline_control.is_paused().await {
+            self.pipeline_control.record_skipped_cycle();
+            return Ok(0);
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        // 先读本消费者已领取但未确认的消息，没有时再阻塞等待新消息
+        let mut redelivered = true;
+        let mut stream_reply = self.read_group(&mut conn, "0", None).await?;
+        if stream_reply.keys.iter().all(|key| key.ids.is_empty()) {
+            redelivered = false;
+            stream_reply = self.read_group(&mut conn, ">", Some(1000)).await?;
+        }
+
+        let mut events = Vec::new();
+        let mut message_ids = Vec::new();
+
+        // 解析消息；解析失败重试也不会成功，直接转入死信流并确认
+        for stream_key in &stream_reply.keys {
+            for stream_id in &stream_key.ids {
+                match self.parse_event(&stream_id.map) {
+                    Ok(event) => {
+                        events.push(event);
+                        message_ids.push(stream_id.id.clone());
+                    }
+                    Err(reason) => {
+                        self.dead_letter_raw(&mut conn, &stream_id.id, &stream_id.map, &reason)
+                            .await?;
+                        self.ack(&mut conn, std::slice::from_ref(&stream_id.id))
+                            .await?;
+                    }
+                }
+            }
+        }
+
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        // 批量写入数据库；开启隔离时坏事件进死信流，不再卡住整个批次反复重试
+        let persisted = if self.config.isolate_failures {
+            match self.event_storage.insert_events_isolating(&events).await {
+                Ok(rejected) => {
+                    for rejected_event in &rejected {
+                        let index = rejected_event.index;
+                        self.dead_letter(
+                            &mut conn,
+                            &message_ids[index],
+                            &events[index],
+                            &rejected_event.reason,
+                        )
+                        .await?;
+                    }
+                    events.len() - rejected.len()
+                }
+                Err(err) => {
+                    return self
+                        .retry_or_dead_letter(&mut conn, &events, &message_ids, redelivered, err)
+                        .await;
+                }
+            }
+        } else {
+            match self.event_storage.insert_events(&events).await {
+                Ok(()) => events.len(),
+                Err(err) => {
+                    return self
+                        .retry_or_dead_letter(&mut conn, &events, &message_ids, redelivered, err)
+                        .await;
+                }
+            }
+        };
+
+        // 写库已提交，一次确认整批消息（死信事件已转存，同样确认）
+        self.ack(&mut conn, &message_ids).await?;
+
+        Ok(persisted)
+    }
+
+    /// 从消费者组读取消息：`">"` 读新消息，`"0"` 重读本消费者未确认的消息
+    async fn read_group(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        start_id: &str,
+        block_ms: Option<u64>,
+    ) -> anyhow::Result<StreamReadReply> {
+        let mut cmd = redis::cmd("XREADGROUP");
+        cmd.arg("GROUP")
+            .arg(&self.config.consumer_group)
+            .arg(&self.config.consumer_name)
+            .arg("COUNT")
+            .arg(self.config.batch_size);
+        if let Some(block_ms) = block_ms {
+            cmd.arg("BLOCK").arg(block_ms);
+        }
+        cmd.arg("STREAMS")
+            .arg(&self.config.stream_name)
+            .arg(start_id);
+        Ok(cmd.query_async(conn).await?)
+    }
+
+    async fn ack(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        message_ids: &[String],
+    ) -> anyhow::Result<()> {
+        if message_ids.is_empty() {
+            return Ok(());
+        }
+        let _: i64 = redis::cmd("XACK")
+            .arg(&self.config.stream_name)
+            .arg(&self.config.consumer_group)
+            .arg(message_ids)
+            .query_async(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 整批写库失败：消息不确认，下一轮重读；已经投递超过 `max_retries + 1` 次的转入死信流并确认
+    ///
+    /// 仍然返回写库错误，主循环记录后稍等再重试
+    async fn retry_or_dead_letter(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        events: &[UserPresenceEvent],
+        message_ids: &[String],
+        redelivered: bool,
+        err: application::ApplicationError,
+    ) -> anyhow::Result<usize> {
+        // 新读到的消息只投递过一次，不用查
+        let deliveries = if redelivered {
+            self.delivery_counts(conn).await?
+        } else {
+            HashMap::new()
+        };
+
+        let reason = err.to_string();
+        for (event, message_id) in events.iter().zip(message_ids) {
+            let delivered = deliveries.get(message_id).copied().unwrap_or(1);
+            if delivered > self.config.max_retries as usize {
+                self.dead_letter(conn, message_id, event, &reason).await?;
+                self.ack(conn, std::slice::from_ref(message_id)).await?;
+            }
+        }
+
+        Err(err.into())
+    }
+
+    /// 本消费者未确认消息的投递次数
+    async fn delivery_counts(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+    ) -> anyhow::Result<HashMap<String, usize>> {
+        let pending: StreamPendingCountReply = redis::cmd("XPENDING")
+            .arg(&self.config.stream_name)
+            .arg(&self.config.consumer_group)
+            .arg("-")
+            .arg("+")
+            .arg(self.config.batch_size)
+            .arg(&self.config.consumer_name)
+            .query_async(conn)
+            .await?;
+
+        Ok(pending
+            .ids
+            .into_iter()
+            .map(|pending| (pending.id, pending.times_delivered))
+            .collect())
+    }
+
+    /// 把写库失败的事件转存到死信流，保留原消息 ID 和失败原因便于排查
+    async fn dead_letter(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        message_id: &str,
+        event: &UserPresenceEvent,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let payload = serde_json::to_string(event)?;
+        let _: String = redis::cmd("XADD")
+            .arg(&self.config.dead_letter_stream)
+            .arg("*")
+            .arg("source_stream")
+            .arg(&self.config.stream_name)
+            .arg("source_id")
+            .arg(message_id)
+            .arg("event")
+            .arg(payload)
+            .arg("reason")
+            .arg(reason)
+            .query_async(conn)
+            .await?;
+
+        metrics::counter!(
+            application::metrics::STATS_EVENTS_DEAD_LETTERED_TOTAL,
+            "cause" => "storage"
+        )
+        .increment(1);
+        warn!(
+            message_id = %message_id,
+            event_id = %event.event_id,
+            reason = %reason,
+            "事件写库失败，已转入死信流"
+        );
+        Ok(())
+    }
+
+    /// 把无法解析的消息原样转存到死信流，原始字段以 JSON 对象保存在 `raw`
+    async fn dead_letter_raw(
+        &self,
+        conn: &mut redis::aio::MultiplexedConnection,
+        message_id: &str,
+        fields: &HashMap<String, redis::Value>,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let raw: HashMap<&str, String> = fields
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    redis::Value::BulkString(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                    other => format!("{other:?}"),
+                };
+                (key.as_str(), value)
+            })
+            .collect();
+        let _: String = redis::cmd("XADD")
+            .arg(&self.config.dead_letter_stream)
+            .arg("*")
+            .arg("source_stream")
+            .arg(&self.config.stream_name)
+            .arg("source_id")
+            .arg(message_id)
+            .arg("raw")
+            .arg(serde_json::to_string(&raw)?)
+            .arg("reason")
+            .arg(reason)
+            .query_async(conn)
+            .await?;
+
+        metrics::counter!(
+            application::metrics::STATS_EVENTS_DEAD_LETTERED_TOTAL,
+            "cause" => "parse"
+        )
+        .increment(1);
+        warn!(
+            message_id = %message_id,
+            reason = %reason,
+            "无法解析事件，已转入死信流"
+        );
+        Ok(())
+    }
+
+    /// 列出死信流里最早的 `limit` 条记录，供排查后决定是否重新处理
+    pub async fn dead_letters(&self, limit: usize) -> anyhow::Result<Vec<DeadLetterEntry>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let entries: StreamRangeReply = redis::cmd("XRANGE")
+            .arg(&self.config.dead_letter_stream)
+            .arg("-")
+            .arg("+")
+            .arg("COUNT")
+            .arg(limit)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(entries
+            .ids
+            .iter()
+            .map(|entry| DeadLetterEntry {
+                id: entry.id.clone(),
+                source_id: self.get_string_field(&entry.map, "source_id"),
+                reason: self.get_string_field(&entry.map, "reason"),
+                payload: self
+                    .get_string_field(&entry.map, "event")
+                    .or_else(|| self.get_string_field(&entry.map, "raw")),
+            })
+            .collect())
+    }
+
+    /// 重新处理死信流里最早的 `limit` 条事件（修复数据或解析逻辑之后调用）
+    ///
+    /// 写库成功的从死信流删除；仍然无法解析或被数据库拒绝的留在原处。连接类错误直接返回
+    pub async fn reprocess_dlq(&self, limit: usize) -> anyhow::Result<DeadLetterReprocessReport> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let entries: StreamRangeReply = redis::cmd("XRANGE")
+            .arg(&self.config.dead_letter_stream)
+            .arg("-")
+            .arg("+")
+            .arg("COUNT")
+            .arg(limit)
+            .query_async(&mut conn)
+            .await?;
+
+        let mut report = DeadLetterReprocessReport::default();
+        let mut events = Vec::new();
+        let mut entry_ids = Vec::new();
+        for entry in &entries.ids {
+            match self.parse_dead_letter(&entry.map) {
+                Ok(event) => {
+                    events.push(event);
+                    entry_ids.push(entry.id.clone());
+                }
+                Err(reason) => {
+                    warn!(entry_id = %entry.id, reason = %reason, "死信事件仍然无法解析");
+                    report.remaining += 1;
+                }
+            }
+        }
+        if events.is_empty() {
+            return Ok(report);
+        }
+
+        let rejected = self.event_storage.insert_events_isolating(&events).await?;
+        for rejected_event in &rejected {
+            warn!(
+                entry_id = %entry_ids[rejected_event.index],
+                reason = %rejected_event.reason,
+                "死信事件仍然无法写库"
+            );
+        }
+        report.remaining += rejected.len();
+
+        let rejected_indexes: HashSet<usize> =
+            rejected.iter().map(|rejected| rejected.index).collect();
+        for (index, entry_id) in entry_ids.iter().enumerate() {
+            if rejected_indexes.contains(&index) {
+                continue;
+            }
+            let _: i64 = redis::cmd("XDEL")
+                .arg(&self.config.dead_letter_stream)
+                .arg(entry_id)
+                .query_async(&mut conn)
+                .await?;
+            report.reprocessed += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// 死信记录里保存的事件：写库失败的是序列化后的事件，解析失败的是原始字段
+    fn parse_dead_letter(
+        &self,
+        fields: &HashMap<String, redis::Value>,
+    ) -> Result<UserPresenceEvent, String> {
+        if let Some(event) = self.get_string_field(fields, "event") {
+            return serde_json::from_str(&event).map_err(|e| format!("invalid event: {e}"));
+        }
+        let raw = self
+            .get_string_field(fields, "raw")
+            .ok_or_else(|| "missing event and raw fields".to_string())?;
+        let raw: HashMap<String, String> =
+            serde_json::from_str(&raw).map_err(|e| format!("invalid raw fields: {e}"))?;
+        let fields = raw
+            .into_iter()
+            .map(|(key, value)| (key, redis::Value::BulkString(value.into_bytes())))
+            .collect();
+        self.parse_event(&fields)
+    }
+
+    /// 解析 Redis Stream 消息为用户状态事件，失败时返回原因
+    fn parse_event(
+        &self,
+        fields: &HashMap<String, redis::Value>,
+    ) -> Result<UserPresenceEvent, String> {
+        let event_id = self.require_uuid_field(fields, "event_id")?;
+        let user_id = UserId::from(self.require_uuid_field(fields, "user_id")?);
+        let room_id = RoomId::from(self.require_uuid_field(fields, "room_id")?);
+        let session_id = self.require_uuid_field(fields, "session_id")?;
+
+        let event_type_str = self.require_string_field(fields, "event_type")?;
+        let event_type = match event_type_str.as_str() {
+            "Connected" => PresenceEventType::Connected,
+            "Disconnected" => PresenceEventType::Disconnected,
+            "Heartbeat" => PresenceEventType::Heartbeat,
+            _ => return Err(format!("unknown event_type: {event_type_str}")),
+        };
+
+        let timestamp_str = self.require_string_field(fields, "timestamp")?;
+        let timestamp = timestamp_str
+            .parse::<DateTime<Utc>>()
+            .map_err(|e| format!("invalid timestamp {timestamp_str:?}: {e}"))?;
+
+        let user_ip = self
+            .get_string_field(fields, "user_ip")
+            .filter(|s| !s.is_empty());
+        let user_agent = self
+            .get_string_field(fields, "user_agent")
+            .filter(|s| !s.is_empty());
+
+        Ok(UserPresenceEvent {
+            event_id,
+            user_id,
+            room_id,
+            event_type,
+            timestamp,
+            session_id,
+            user_ip,
+            user_agent,
+        })
+    }
+
+    /// 从字段中获取 UUID
+    fn require_uuid_field(
+        &self,
+        fields: &HashMap<String, redis::Value>,
+        key: &str,
+    ) -> Result<Uuid, String> {
+        let value = self.require_string_field(fields, key)?;
+        value
+            .parse::<Uuid>()
+            .map_err(|_| format!("invalid {key}: {value:?}"))
+    }
+
+    /// 从字段中获取必填的字符串
+    fn require_string_field(
+        &self,
+        fields: &HashMap<String, redis::Value>,
+        key: &str,
+    ) -> Result<String, String> {
+        self.get_string_field(fields, key)
+            .ok_or_else(|| format!("missing field {key}"))
+    }
+
+    /// 从字段中获取字符串
+    fn get_string_field(
+        &self,
+        fields: &HashMap<String, redis::Value>,
+        key: &str,
+    ) -> Option<String> {
+        match fields.get(key) {
+            Some(redis::Value::BulkString(bytes)) => String::from_utf8(bytes.clone()).ok(),
+            _ => None,
+        }
+    }
+}
@@ -2,12 +2,30 @@ use application::{ApplicationError, UserPresenceEvent};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 
+/// 批量写入时被拒绝的事件
+#[derive(Debug, Clone)]
+pub struct RejectedEvent {
+    /// 事件在输入批次中的下标
+    pub index: usize,
+    /// 数据库返回的失败原因
+    pub reason: String,
+}
+
 /// 事件存储trait - 用于将用户状态事件持久化到数据库
 #[async_trait]
 pub trait EventStorage: Send + Sync {
     /// 批量插入用户状态事件到数据库
     async fn insert_events(&self, events: &[UserPresenceEvent]) -> Result<(), ApplicationError>;
 
+    /// 批量插入，单个坏事件不拖垮整批
+    ///
+    /// 数据层面的错误（外键、约束等）只拒绝出错的事件，其余照常写入；
+    /// 连接类错误仍然整批失败。返回被拒绝的事件，按下标排序
+    async fn insert_events_isolating(
+        &self,
+        events: &[UserPresenceEvent],
+    ) -> Result<Vec<RejectedEvent>, ApplicationError>;
+
     /// 获取事件总数（用于监控）
     #[allow(dead_code)]
     async fn get_event_count(&self) -> Result<i64, ApplicationError>;
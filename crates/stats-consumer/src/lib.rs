@@ -2,8 +2,13 @@
 //!
 //! 提供从 Redis Stream 读取事件并写入 PostgreSQL 的功能
 
+pub mod consumer;
 pub mod event_storage;
 pub mod pg_event_storage;
 
-pub use event_storage::EventStorage;
+pub use consumer::{
+    instance_consumer_name, ConsumerConfig, DeadLetterEntry, DeadLetterReprocessReport,
+    StatsConsumer,
+};
+pub use event_storage::{EventStorage, RejectedEvent};
 pub use pg_event_storage::{create_event_storage, PgEventStorage};
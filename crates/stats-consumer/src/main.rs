@@ -1,263 +1,47 @@
 //! Stats Consumer 服务
 //!
 //! 从 Redis Stream 读取用户状态事件，批量写入 PostgreSQL
+//!
+//! 死信流维护：`stats-consumer dlq list [条数]` 每行打印一条死信记录（JSON），
+//! `stats-consumer dlq replay [条数]` 在修复数据或解析逻辑后把死信事件重新写库
 
-use application::{PresenceEventType, UserPresenceEvent};
-use chrono::{DateTime, Utc};
+use application::StatsPipelineControl;
 use config::AppConfig;
-use domain::{RoomId, UserId};
-use redis::streams::StreamReadReply;
+use stats_consumer::{create_event_storage, ConsumerConfig, StatsConsumer};
 use std::sync::Arc;
-use std::time::Duration;
-use tracing::{error, info, warn};
-use uuid::Uuid;
-
-mod event_storage;
-mod pg_event_storage;
+use tracing::info;
 
-use event_storage::EventStorage;
-use pg_event_storage::{create_event_storage, PgEventStorage};
-
-/// Stats Consumer 配置
-#[derive(Debug, Clone)]
-struct ConsumerConfig {
-    stream_name: String,
-    consumer_group: String,
-    consumer_name: String,
-    batch_size: i64,
-    poll_interval: Duration,
-}
+const USAGE: &str = "用法: stats-consumer [dlq list|replay [条数]]";
 
-impl ConsumerConfig {
-    fn from_app_config(app_config: &config::AppConfig) -> Self {
-        Self {
-            stream_name: app_config.presence.stream_name.clone(),
-            consumer_group: app_config.stats.consumer.consumer_group.clone(),
-            consumer_name: app_config.stats.consumer.consumer_name.clone(),
-            batch_size: app_config.stats.consumer.batch_size,
-            poll_interval: Duration::from_secs(app_config.stats.consumer.poll_interval_secs),
-        }
-    }
-}
+/// 死信子命令默认处理的条数
+const DEFAULT_DLQ_LIMIT: usize = 100;
 
-/// Stats Consumer 主服务
-pub struct StatsConsumer {
-    redis_client: Arc<redis::Client>,
-    event_storage: PgEventStorage,
-    config: ConsumerConfig,
+/// 命令行指定的死信流维护操作
+enum DlqCommand {
+    List(usize),
+    Replay(usize),
 }
 
-impl StatsConsumer {
-    /// 创建 Stats Consumer
-    fn new(
-        redis_client: Arc<redis::Client>,
-        event_storage: PgEventStorage,
-        config: ConsumerConfig,
-    ) -> Self {
-        Self {
-            redis_client,
-            event_storage,
-            config,
-        }
-    }
-
-    /// 启动消费者主循环
-    pub async fn run(&self) -> anyhow::Result<()> {
-        info!(
-            stream_name = %self.config.stream_name,
-            consumer_group = %self.config.consumer_group,
-            consumer_name = %self.config.consumer_name,
-            "Stats Consumer 开始运行"
-        );
-
-        // 尝试创建消费者组（如果不存在）
-        self.ensure_consumer_group().await?;
-
-        loop {
-            match self.process_batch().await {
-                Ok(processed_count) => {
-                    if processed_count > 0 {
-                        info!(count = processed_count, "已处理事件批次");
-                    }
-                }
-                Err(e) => {
-                    error!(error = %e, "处理批次时发生错误");
-                    // 等待一段时间后重试
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                }
-            }
-
-            // 轮询间隔
-            tokio::time::sleep(self.config.poll_interval).await;
-        }
-    }
-
-    /// 确保消费者组存在
-    async fn ensure_consumer_group(&self) -> anyhow::Result<()> {
-        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
-
-        // 尝试创建消费者组，忽略 BUSYGROUP 错误（组已存在）
-        let result: Result<String, redis::RedisError> = redis::cmd("XGROUP")
-            .arg("CREATE")
-            .arg(&self.config.stream_name)
-            .arg(&self.config.consumer_group)
-            .arg("0") // 从流的开始位置
-            .arg("MKSTREAM") // 如果流不存在则创建
-            .query_async(&mut conn)
-            .await;
-
-        match result {
-            Ok(_) => {
-                info!(
-                    stream_name = %self.config.stream_name,
-                    group = %self.config.consumer_group,
-                    "消费者组已创建"
-                );
-            }
-            Err(e) if e.to_string().contains("BUSYGROUP") => {
-                info!(
-                    stream_name = %self.config.stream_name,
-                    group = %self.config.consumer_group,
-                    "消费者组已存在"
-                );
-            }
-            Err(e) => {
-                return Err(anyhow::anyhow!("创建消费者组失败: {}", e));
-            }
-        }
-
-        Ok(())
-    }
-
-    /// 处理一个批次的事件
-    async fn process_batch(&self) -> anyhow::Result<usize> {
-        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
-
-        // 使用 XREADGROUP 读取事件
-        let stream_reply: StreamReadReply = redis::cmd("XREADGROUP")
-            .arg("GROUP")
-            .arg(&self.config.consumer_group)
-            .arg(&self.config.consumer_name)
-            .arg("COUNT")
-            .arg(self.config.batch_size)
-            .arg("BLOCK")
-            .arg(1000) // 阻塞 1 秒
-            .arg("STREAMS")
-            .arg(&self.config.stream_name)
-            .arg(">") // 只读取新消息
-            .query_async(&mut conn)
-            .await?;
-
-        if stream_reply.keys.is_empty() {
-            return Ok(0); // 没有新消息
-        }
-
-        let mut events = Vec::new();
-        let mut message_ids = Vec::new();
-
-        // 解析消息
-        for stream_key in &stream_reply.keys {
-            for stream_id in &stream_key.ids {
-                if let Some(event) = self.parse_event(&stream_id.map) {
-                    events.push(event);
-                    message_ids.push(stream_id.id.clone());
-                } else {
-                    warn!(
-                        message_id = %stream_id.id,
-                        "无法解析事件，跳过此消息"
-                    );
-                }
-            }
-        }
-
-        if events.is_empty() {
-            return Ok(0);
-        }
-
-        // 批量写入数据库
-        self.event_storage.insert_events(&events).await?;
-
-        // 确认消息已处理
-        for message_id in message_ids {
-            let _: i64 = redis::cmd("XACK")
-                .arg(&self.config.stream_name)
-                .arg(&self.config.consumer_group)
-                .arg(&message_id)
-                .query_async(&mut conn)
-                .await?;
-        }
-
-        Ok(events.len())
-    }
-
-    /// 解析 Redis Stream 消息为用户状态事件
-    fn parse_event(
-        &self,
-        fields: &std::collections::HashMap<String, redis::Value>,
-    ) -> Option<UserPresenceEvent> {
-        let event_id = self.get_uuid_field(fields, "event_id")?;
-        let user_id = UserId::from(self.get_uuid_field(fields, "user_id")?);
-        let room_id = RoomId::from(self.get_uuid_field(fields, "room_id")?);
-        let session_id = self.get_uuid_field(fields, "session_id")?;
-
-        let event_type_str = self.get_string_field(fields, "event_type")?;
-        let event_type = match event_type_str.as_str() {
-            "Connected" => PresenceEventType::Connected,
-            "Disconnected" => PresenceEventType::Disconnected,
-            "Heartbeat" => PresenceEventType::Heartbeat,
-            _ => {
-                warn!(event_type = %event_type_str, "未知的事件类型");
-                return None;
-            }
-        };
-
-        let timestamp_str = self.get_string_field(fields, "timestamp")?;
-        let timestamp = timestamp_str.parse::<DateTime<Utc>>().ok()?;
-
-        let user_ip = self
-            .get_string_field(fields, "user_ip")
-            .filter(|s| !s.is_empty());
-        let user_agent = self
-            .get_string_field(fields, "user_agent")
-            .filter(|s| !s.is_empty());
-
-        Some(UserPresenceEvent {
-            event_id,
-            user_id,
-            room_id,
-            event_type,
-            timestamp,
-            session_id,
-            user_ip,
-            user_agent,
-        })
-    }
-
-    /// 从字段中获取 UUID
-    fn get_uuid_field(
-        &self,
-        fields: &std::collections::HashMap<String, redis::Value>,
-        key: &str,
-    ) -> Option<Uuid> {
-        self.get_string_field(fields, key)
-            .and_then(|s| s.parse::<Uuid>().ok())
-    }
-
-    /// 从字段中获取字符串
-    fn get_string_field(
-        &self,
-        fields: &std::collections::HashMap<String, redis::Value>,
-        key: &str,
-    ) -> Option<String> {
-        match fields.get(key) {
-            Some(redis::Value::BulkString(bytes)) => String::from_utf8(bytes.clone()).ok(),
-            _ => None,
-        }
+fn parse_args(args: &[String]) -> anyhow::Result<Option<DlqCommand>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (action, limit) = match args.as_slice() {
+        [] => return Ok(None),
+        ["dlq", action] => (*action, DEFAULT_DLQ_LIMIT),
+        ["dlq", action, limit] => (*action, limit.parse()?),
+        _ => anyhow::bail!(USAGE),
+    };
+    match action {
+        "list" => Ok(Some(DlqCommand::List(limit))),
+        "replay" => Ok(Some(DlqCommand::Replay(limit))),
+        _ => anyhow::bail!(USAGE),
     }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let dlq_command = parse_args(&args)?;
+
     // 初始化日志
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
@@ -299,7 +83,31 @@ async fn main() -> anyhow::Result<()> {
     let consumer_config = ConsumerConfig::from_app_config(&app_config);
 
     // 创建并启动消费者
-    let consumer = StatsConsumer::new(redis_client, event_storage, consumer_config);
+    let pipeline_control = Arc::new(StatsPipelineControl::new(redis_client.clone()));
+    let consumer = StatsConsumer::new(
+        redis_client,
+        event_storage,
+        consumer_config,
+        pipeline_control,
+    );
+
+    match dlq_command {
+        Some(DlqCommand::List(limit)) => {
+            for entry in consumer.dead_letters(limit).await? {
+                println!("{}", serde_json::to_string(&entry)?);
+            }
+            return Ok(());
+        }
+        Some(DlqCommand::Replay(limit)) => {
+            let report = consumer.reprocess_dlq(limit).await?;
+            println!(
+                "reprocessed: {}, remaining: {}",
+                report.reprocessed, report.remaining
+            );
+            return Ok(());
+        }
+        None => {}
+    }
 
     info!("Stats Consumer 启动完成，开始处理事件...");
     consumer.run().await?;
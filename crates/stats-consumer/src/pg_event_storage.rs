@@ -2,7 +2,7 @@ use application::{ApplicationError, UserPresenceEvent};
 use async_trait::async_trait;
 use sqlx::{types::chrono, PgPool, Row};
 
-use crate::event_storage::EventStorage;
+use crate::event_storage::{EventStorage, RejectedEvent};
 
 /// PostgreSQL事件存储实现
 /// 实现EventStorage trait，负责将用户状态事件批量插入数据库
@@ -30,6 +30,55 @@ impl PgEventStorage {
     }
 }
 
+impl PgEventStorage {
+    /// 单条 INSERT ... SELECT FROM UNNEST；主键冲突视为重复投递，跳过
+    ///
+    /// 每列绑定成一个数组，参数个数固定为 8，不受批次大小和 65535 个绑定参数的上限限制
+    async fn try_insert(&self, events: &[UserPresenceEvent]) -> Result<(), sqlx::Error> {
+        let mut event_ids = Vec::with_capacity(events.len());
+        let mut user_ids = Vec::with_capacity(events.len());
+        let mut room_ids = Vec::with_capacity(events.len());
+        let mut event_types = Vec::with_capacity(events.len());
+        let mut timestamps = Vec::with_capacity(events.len());
+        let mut session_ids = Vec::with_capacity(events.len());
+        let mut user_ips = Vec::with_capacity(events.len());
+        let mut user_agents = Vec::with_capacity(events.len());
+        for event in events {
+            event_ids.push(event.event_id);
+            user_ids.push(uuid::Uuid::from(event.user_id));
+            room_ids.push(uuid::Uuid::from(event.room_id));
+            event_types.push(event.event_type.to_string());
+            timestamps.push(event.timestamp);
+            session_ids.push(event.session_id);
+            user_ips.push(event.user_ip.clone());
+            user_agents.push(event.user_agent.clone());
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO presence_events
+                (event_id, user_id, room_id, event_type, timestamp, session_id, user_ip, user_agent)
+            SELECT * FROM UNNEST(
+                $1::uuid[], $2::uuid[], $3::uuid[], $4::text[]::presence_event_type[],
+                $5::timestamptz[], $6::uuid[], $7::text[]::inet[], $8::text[]
+            )
+            ON CONFLICT (event_id, timestamp) DO NOTHING
+            "#,
+        )
+        .bind(event_ids)
+        .bind(user_ids)
+        .bind(room_ids)
+        .bind(event_types)
+        .bind(timestamps)
+        .bind(session_ids)
+        .bind(user_ips)
+        .bind(user_agents)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
 fn map_sqlx_err(err: sqlx::Error) -> ApplicationError {
     ApplicationError::infrastructure_with_source("Database operation failed".to_string(), err)
 }
@@ -38,42 +87,64 @@ fn map_sqlx_err(err: sqlx::Error) -> ApplicationError {
 impl EventStorage for PgEventStorage {
     /// 批量插入用户状态事件到数据库
     ///
-    /// 使用事务确保数据一致性，批量插入提高性能
+    /// 整批一条语句，本身就是原子的；重复的事件（重投递）直接忽略
     async fn insert_events(&self, events: &[UserPresenceEvent]) -> Result<(), ApplicationError> {
         if events.is_empty() {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+        self.try_insert(events).await.map_err(map_sqlx_err)?;
 
-        // 构建批量插入SQL
-        let mut query_builder = sqlx::QueryBuilder::new(
-            "INSERT INTO presence_events (event_id, user_id, room_id, event_type, timestamp, session_id, user_ip, user_agent) "
+        tracing::info!(
+            batch_size = events.len(),
+            "Successfully inserted presence events batch"
         );
 
-        query_builder.push_values(events, |mut b, event| {
-            b.push_bind(event.event_id)
-                .push_bind(uuid::Uuid::from(event.user_id))
-                .push_bind(uuid::Uuid::from(event.room_id))
-                .push_bind(event.event_type.to_string()) // 转换为字符串
-                .push_bind(event.timestamp)
-                .push_bind(event.session_id)
-                .push_bind(&event.user_ip)
-                .push_bind(&event.user_agent);
-        });
-
-        let query = query_builder.build();
-
-        query.execute(&mut *tx).await.map_err(map_sqlx_err)?;
+        Ok(())
+    }
 
-        tx.commit().await.map_err(map_sqlx_err)?;
+    /// 整批失败时二分重试，把坏事件逐步隔离出来
+    ///
+    /// 一个坏事件大约多花 2·log2(n) 条语句，正常批次仍然只有一条
+    async fn insert_events_isolating(
+        &self,
+        events: &[UserPresenceEvent],
+    ) -> Result<Vec<RejectedEvent>, ApplicationError> {
+        let mut rejected = Vec::new();
+        // 待插入的区间（左闭右开）
+        let mut pending = vec![(0, events.len())];
+
+        while let Some((start, end)) = pending.pop() {
+            let range = start..end;
+            if range.is_empty() {
+                continue;
+            }
+            match self.try_insert(&events[range.clone()]).await {
+                Ok(()) => {}
+                Err(sqlx::Error::Database(err)) if range.len() == 1 => {
+                    rejected.push(RejectedEvent {
+                        index: range.start,
+                        reason: err.to_string(),
+                    });
+                }
+                Err(sqlx::Error::Database(_)) => {
+                    let mid = start + range.len() / 2;
+                    pending.push((mid, end));
+                    pending.push((start, mid));
+                }
+                // 连接断开之类的错误和具体事件无关，整批留给下次重试
+                Err(err) => return Err(map_sqlx_err(err)),
+            }
+        }
 
+        rejected.sort_by_key(|event| event.index);
         tracing::info!(
             batch_size = events.len(),
-            "Successfully inserted presence events batch"
+            rejected = rejected.len(),
+            "Inserted presence events batch with failure isolation"
         );
 
-        Ok(())
+        Ok(rejected)
     }
 
     /// 获取事件总数（用于监控）
@@ -0,0 +1,56 @@
+//! 消费者配置装配测试
+//!
+//! 验证：流名称、消费者组、批次等取自 AppConfig；未配置实例名称时按主机名和进程号生成
+
+use application::StatsPipelineControl;
+use config::AppConfig;
+use stats_consumer::{create_event_storage, ConsumerConfig, StatsConsumer};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn consumer_from(app_config: &AppConfig) -> StatsConsumer {
+    // 只装配不连接：Redis 客户端和延迟连接池都不会真正建立连接
+    let redis_client = Arc::new(redis::Client::open("redis://127.0.0.1:6379").unwrap());
+    let pg_pool = sqlx::PgPool::connect_lazy(&app_config.database.url).unwrap();
+    StatsConsumer::new(
+        redis_client.clone(),
+        create_event_storage(pg_pool),
+        ConsumerConfig::from_app_config(app_config),
+        Arc::new(StatsPipelineControl::new(redis_client)),
+    )
+}
+
+#[tokio::test]
+async fn consumer_is_wired_from_app_config() {
+    let mut app_config = AppConfig::test_config();
+    app_config.presence.stream_name = "presence_events_wired".to_string();
+    app_config.stats.consumer.consumer_group = "wired_group".to_string();
+    app_config.stats.consumer.consumer_name = "wired_consumer".to_string();
+    app_config.stats.consumer.batch_size = 250;
+    app_config.stats.consumer.poll_interval_secs = 7;
+    app_config.stats.consumer.dead_letter_stream = "wired_dlq".to_string();
+    app_config.stats.consumer.max_retries = 5;
+
+    let consumer = consumer_from(&app_config);
+    let config = consumer.config();
+    assert_eq!(config.stream_name, "presence_events_wired");
+    assert_eq!(config.consumer_group, "wired_group");
+    assert_eq!(config.consumer_name, "wired_consumer");
+    assert_eq!(config.batch_size, 250);
+    assert_eq!(config.poll_interval, Duration::from_secs(7));
+    assert_eq!(config.dead_letter_stream, "wired_dlq");
+    assert_eq!(config.max_retries, 5);
+}
+
+#[tokio::test]
+async fn empty_consumer_name_is_generated_per_instance() {
+    let mut app_config = AppConfig::test_config();
+    app_config.stats.consumer.consumer_name = String::new();
+
+    let consumer = consumer_from(&app_config);
+    let name = &consumer.config().consumer_name;
+    let suffix = format!("-{}", std::process::id());
+    assert!(name.ends_with(&suffix), "{name}");
+    assert!(name.len() > suffix.len(), "{name}");
+    assert_eq!(*name, stats_consumer::instance_consumer_name());
+}
@@ -130,7 +130,10 @@ async fn get_room_stats(
     State(state): State<AppState>,
     Query(params): Query<StatsQueryParams>,
 ) -> Result<Json<Vec<RoomStatsResponse>>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
     verify_admin_access(&state, user_id, None).await?;
 
     // 设置默认值
@@ -172,7 +175,10 @@ async fn get_room_stats_by_id(
     Path(room_id): Path<Uuid>,
     Query(params): Query<StatsQueryParams>,
 ) -> Result<Json<Vec<RoomStatsResponse>>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
     verify_admin_access(&state, user_id, Some(room_id)).await?;
 
     let granularity = if let Some(g) = params.granularity {
@@ -212,7 +218,10 @@ async fn get_stats_summary(
     State(state): State<AppState>,
     Query(params): Query<StatsQueryParams>,
 ) -> Result<Json<OnlineStatsSummary>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
     verify_admin_access(&state, user_id, None).await?;
 
     let end_time = params.end_time.unwrap_or_else(Utc::now);
@@ -236,7 +245,10 @@ async fn get_current_online_stats(
     headers: HeaderMap,
     State(state): State<AppState>,
 ) -> Result<Json<Vec<CurrentOnlineStatsResponse>>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
     verify_admin_access(&state, user_id, None).await?;
 
     // 简化实现：返回固定的演示数据
@@ -255,7 +267,10 @@ async fn get_event_metrics(
     headers: HeaderMap,
     State(state): State<AppState>,
 ) -> Result<Json<EventMetricsResponse>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
     verify_admin_access(&state, user_id, None).await?;
 
     // 注意：事件处理现在由独立的 stats-consumer 服务完成
@@ -1,20 +1,70 @@
 //! JWT 认证和授权模块
 //!
-//! 提供 JWT token 生成、验证
+//! 提供 JWT token 生成、验证，以及刷新令牌的签发与轮换
 
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use application::repository::{RefreshTokenRepository, UserRepository};
+use application::ApplicationError;
+use async_trait::async_trait;
 use axum::http::HeaderMap;
 use config::JwtConfig;
+use domain::{
+    AuditLogEntry, DomainError, RefreshToken, RepositoryError, User, UserEmail, UserId, UserStatus,
+};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::ApiError;
 
+/// 令牌类型：访问令牌不能当刷新令牌用，反之亦然
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    #[default]
+    Access,
+    Refresh,
+}
+
 /// JWT Claims 结构
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub user_id: Uuid,
     pub exp: i64, // 过期时间 (Unix timestamp)
+    #[serde(default)]
+    pub token_type: TokenType,
+    /// 刷新令牌唯一标识（访问令牌没有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
+    /// 刷新令牌家族标识（访问令牌没有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_id: Option<Uuid>,
+    /// 签发时用户的令牌版本，与用户当前版本不一致即失效
+    #[serde(default)]
+    pub ver: i32,
+}
+
+/// 访问令牌 + 刷新令牌
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// 访问令牌有效期（秒）
+    pub expires_in: i64,
+}
+
+/// 缓存条目超过这个数量时清理过期的条目
+const USER_CACHE_PRUNE_THRESHOLD: usize = 10_000;
+
+/// 校验令牌需要的用户状态，短时间缓存避免每个请求都查库
+#[derive(Debug, Clone, Copy)]
+struct CachedUser {
+    token_version: i32,
+    suspended: bool,
+    cached_at: Instant,
 }
 
 /// JWT Token 服务
@@ -23,43 +73,266 @@ pub struct JwtService {
     config: JwtConfig,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    /// 校验令牌时查询用户当前的令牌版本和状态
+    user_repository: Arc<dyn UserRepository>,
+    user_cache: Arc<Mutex<HashMap<Uuid, CachedUser>>>,
+    user_cache_ttl: Duration,
 }
 
 impl JwtService {
-    pub fn new(config: JwtConfig) -> Self {
+    pub fn new(config: JwtConfig, user_repository: Arc<dyn UserRepository>) -> Self {
         let encoding_key = EncodingKey::from_secret(config.secret.as_ref());
         let decoding_key = DecodingKey::from_secret(config.secret.as_ref());
 
+        let user_cache_ttl = Duration::from_secs(config.user_cache_ttl_secs);
+
         Self {
             config,
             encoding_key,
             decoding_key,
+            user_repository,
+            user_cache: Arc::new(Mutex::new(HashMap::new())),
+            user_cache_ttl,
         }
     }
 
-    /// 生成 JWT token
-    pub fn generate_token(&self, user_id: Uuid) -> Result<String, ApiError> {
-        let now = chrono::Utc::now();
-        let exp = now + chrono::Duration::hours(self.config.expiration_hours);
+    /// 清掉用户的缓存状态，停用、启用等修改在本实例上立即生效
+    pub fn invalidate_user(&self, user_id: Uuid) {
+        self.cache().remove(&user_id);
+    }
+
+    /// 包装用户仓库：经过它修改或删除用户后自动清掉缓存状态
+    ///
+    /// 修改密码、重置密码、停用启用等改动令牌版本或状态的操作都走用户仓库，
+    /// 服务层拿包装后的仓库，新增的调用方也不会漏掉清缓存
+    pub fn invalidating_user_repository(
+        &self,
+        inner: Arc<dyn UserRepository>,
+    ) -> Arc<dyn UserRepository> {
+        Arc::new(InvalidatingUserRepository {
+            inner,
+            jwt_service: self.clone(),
+        })
+    }
+
+    /// 生成 JWT token（访问令牌），`token_version` 为用户当前的令牌版本
+    pub fn generate_token(&self, user_id: Uuid, token_version: i32) -> Result<String, ApiError> {
+        let exp =
+            chrono::Utc::now() + chrono::Duration::minutes(self.config.access_token_minutes());
 
-        let claims = Claims {
+        self.encode_claims(&Claims {
             user_id,
             exp: exp.timestamp(),
+            token_type: TokenType::Access,
+            jti: None,
+            family_id: None,
+            ver: token_version,
+        })
+    }
+
+    /// 签发新的令牌对（一次新的登录，开启新的令牌家族），并持久化刷新令牌
+    pub async fn issue_token_pair(
+        &self,
+        repository: &dyn RefreshTokenRepository,
+        user_id: Uuid,
+        token_version: i32,
+    ) -> Result<TokenPair, ApiError> {
+        let (pair, record) = self.build_token_pair(user_id, token_version, Uuid::new_v4())?;
+        repository.create(record).await?;
+        Ok(pair)
+    }
+
+    /// 使用刷新令牌换取新的令牌对（轮换）
+    ///
+    /// 旧刷新令牌立即失效；已吊销的令牌被再次使用时吊销整个家族
+    pub async fn refresh(
+        &self,
+        repository: &dyn RefreshTokenRepository,
+        refresh_token: &str,
+    ) -> Result<TokenPair, ApiError> {
+        let claims = self.decode_token_of_type(refresh_token, TokenType::Refresh)?;
+        let token_version = self.ensure_current_version(&claims).await?;
+        let jti = claims
+            .jti
+            .ok_or_else(|| ApiError::unauthorized("Invalid refresh token"))?;
+
+        let stored = repository
+            .find_by_jti(jti)
+            .await?
+            .ok_or_else(|| ApiError::unauthorized("Unknown refresh token"))?;
+
+        if stored.is_revoked() {
+            self.revoke_family_on_reuse(repository, &stored).await?;
+            return Err(ApiError::unauthorized("Refresh token reuse detected"));
+        }
+        if stored.is_expired(time::OffsetDateTime::now_utc()) {
+            return Err(ApiError::unauthorized("Refresh token expired"));
+        }
+
+        let (pair, record) =
+            self.build_token_pair(claims.user_id, token_version, stored.family_id)?;
+        match repository.rotate(jti, record).await {
+            Ok(()) => Ok(pair),
+            // 并发轮换：另一个请求已经用过这个令牌，同样视为重放
+            Err(RepositoryError::Conflict) => {
+                self.revoke_family_on_reuse(repository, &stored).await?;
+                Err(ApiError::unauthorized("Refresh token reuse detected"))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 登出：吊销该刷新令牌所在的整个家族（即这一次登录），其他设备的登录不受影响
+    ///
+    /// 只校验签名和归属，不要求令牌版本一致，修改密码后仍然可以正常登出
+    pub async fn revoke_refresh_token(
+        &self,
+        repository: &dyn RefreshTokenRepository,
+        user_id: Uuid,
+        refresh_token: &str,
+    ) -> Result<(), ApiError> {
+        let claims = self.decode_token_of_type(refresh_token, TokenType::Refresh)?;
+        if claims.user_id != user_id {
+            return Err(ApiError::unauthorized(
+                "Refresh token belongs to another user",
+            ));
+        }
+        let family_id = claims
+            .family_id
+            .ok_or_else(|| ApiError::unauthorized("Invalid refresh token"))?;
+
+        let revoked = repository.revoke_family(family_id).await?;
+        tracing::info!(%user_id, %family_id, revoked, "登出，已吊销刷新令牌");
+        Ok(())
+    }
+
+    async fn revoke_family_on_reuse(
+        &self,
+        repository: &dyn RefreshTokenRepository,
+        token: &RefreshToken,
+    ) -> Result<(), ApiError> {
+        let revoked = repository.revoke_family(token.family_id).await?;
+        tracing::warn!(
+            user_id = %token.user_id,
+            family_id = %token.family_id,
+            revoked,
+            "刷新令牌被重复使用，已吊销整个令牌家族"
+        );
+        Ok(())
+    }
+
+    fn build_token_pair(
+        &self,
+        user_id: Uuid,
+        token_version: i32,
+        family_id: Uuid,
+    ) -> Result<(TokenPair, RefreshToken), ApiError> {
+        let now = time::OffsetDateTime::now_utc();
+        let expires_at = now + time::Duration::days(self.config.refresh_expiration_days);
+        let jti = Uuid::new_v4();
+
+        let refresh_token = self.encode_claims(&Claims {
+            user_id,
+            exp: expires_at.unix_timestamp(),
+            token_type: TokenType::Refresh,
+            jti: Some(jti),
+            family_id: Some(family_id),
+            ver: token_version,
+        })?;
+
+        let pair = TokenPair {
+            access_token: self.generate_token(user_id, token_version)?,
+            refresh_token,
+            expires_in: self.config.access_token_minutes() * 60,
         };
+        let record = RefreshToken::issue(jti, UserId::from(user_id), family_id, expires_at, now);
+        Ok((pair, record))
+    }
 
-        encode(&Header::default(), &claims, &self.encoding_key)
+    fn encode_claims(&self, claims: &Claims) -> Result<String, ApiError> {
+        encode(&Header::default(), claims, &self.encoding_key)
             .map_err(|err| ApiError::unauthorized(format!("Token generation failed: {}", err)))
     }
 
-    /// 验证并解析 JWT token
-    pub fn verify_token(&self, token: &str) -> Result<Claims, ApiError> {
-        decode::<Claims>(token, &self.decoding_key, &Validation::default())
+    /// 验证并解析 JWT token（只接受访问令牌）
+    ///
+    /// 除签名和有效期外还要求令牌版本与用户当前版本一致，修改密码后旧令牌立即失效；
+    /// 已停用的用户返回 403
+    pub async fn verify_token(&self, token: &str) -> Result<Claims, ApiError> {
+        let result = self.verify_access_token(token).await;
+        crate::metrics::record_auth(result.is_ok());
+        result
+    }
+
+    async fn verify_access_token(&self, token: &str) -> Result<Claims, ApiError> {
+        let claims = self.decode_token_of_type(token, TokenType::Access)?;
+        self.ensure_current_version(&claims).await?;
+        Ok(claims)
+    }
+
+    /// 令牌版本与用户当前版本一致时返回当前版本；用户不存在同样视为令牌无效
+    async fn ensure_current_version(&self, claims: &Claims) -> Result<i32, ApiError> {
+        let user = self.cached_user(claims.user_id).await?;
+
+        if user.suspended {
+            return Err(ApplicationError::from(DomainError::AccountSuspended).into());
+        }
+        if user.token_version != claims.ver {
+            return Err(ApiError::unauthorized("Token has been revoked"));
+        }
+        Ok(user.token_version)
+    }
+
+    /// 读取用户状态，缓存未过期时不查库
+    async fn cached_user(&self, user_id: Uuid) -> Result<CachedUser, ApiError> {
+        let now = Instant::now();
+        if let Some(cached) = self.cache().get(&user_id) {
+            if now.duration_since(cached.cached_at) < self.user_cache_ttl {
+                return Ok(*cached);
+            }
+        }
+
+        let user = self
+            .user_repository
+            .find_by_id(UserId::from(user_id))
+            .await?
+            .ok_or_else(|| ApiError::unauthorized("Invalid token"))?;
+        let cached = CachedUser {
+            token_version: user.token_version,
+            suspended: user.status == UserStatus::Suspended,
+            cached_at: now,
+        };
+
+        if !self.user_cache_ttl.is_zero() {
+            let mut cache = self.cache();
+            if cache.len() >= USER_CACHE_PRUNE_THRESHOLD {
+                let ttl = self.user_cache_ttl;
+                cache.retain(|_, entry| now.duration_since(entry.cached_at) < ttl);
+            }
+            cache.insert(user_id, cached);
+        }
+        Ok(cached)
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, CachedUser>> {
+        self.user_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn decode_token_of_type(&self, token: &str, expected: TokenType) -> Result<Claims, ApiError> {
+        let claims = decode::<Claims>(token, &self.decoding_key, &Validation::default())
             .map(|token_data| token_data.claims)
-            .map_err(|err| ApiError::unauthorized(format!("Invalid token: {}", err)))
+            .map_err(|err| ApiError::unauthorized(format!("Invalid token: {}", err)))?;
+
+        if claims.token_type != expected {
+            return Err(ApiError::unauthorized("Invalid token type"));
+        }
+        Ok(claims)
     }
 
     /// 从 headers 中提取和验证 token
-    pub fn extract_user_from_headers(&self, headers: &HeaderMap) -> Result<Uuid, ApiError> {
+    pub async fn extract_user_from_headers(&self, headers: &HeaderMap) -> Result<Uuid, ApiError> {
         let auth_header = headers
             .get(axum::http::header::AUTHORIZATION)
             .and_then(|header| header.to_str().ok())
@@ -69,14 +342,60 @@ impl JwtService {
             .strip_prefix("Bearer ")
             .ok_or_else(|| ApiError::unauthorized("Invalid authorization header format"))?;
 
-        let claims = self.verify_token(token)?;
+        let claims = self.verify_token(token).await?;
         Ok(claims.user_id)
     }
 }
 
+/// 修改用户后清掉 [`JwtService`] 缓存状态的用户仓库
+struct InvalidatingUserRepository {
+    inner: Arc<dyn UserRepository>,
+    jwt_service: JwtService,
+}
+
+#[async_trait]
+impl UserRepository for InvalidatingUserRepository {
+    async fn create(&self, user: User) -> Result<User, RepositoryError> {
+        self.inner.create(user).await
+    }
+
+    async fn update(&self, user: User) -> Result<User, RepositoryError> {
+        let updated = self.inner.update(user).await?;
+        self.jwt_service.invalidate_user(updated.id.into());
+        Ok(updated)
+    }
+
+    async fn update_audited(
+        &self,
+        user: User,
+        audit: AuditLogEntry,
+    ) -> Result<User, RepositoryError> {
+        let updated = self.inner.update_audited(user, audit).await?;
+        self.jwt_service.invalidate_user(updated.id.into());
+        Ok(updated)
+    }
+
+    async fn find_by_id(&self, id: UserId) -> Result<Option<User>, RepositoryError> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn find_by_email(&self, email: UserEmail) -> Result<Option<User>, RepositoryError> {
+        self.inner.find_by_email(email).await
+    }
+
+    async fn delete(&self, id: UserId) -> Result<(), RepositoryError> {
+        self.inner.delete(id).await?;
+        self.jwt_service.invalidate_user(id.into());
+        Ok(())
+    }
+}
+
 /// 登录响应结构
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub user: domain::User,
+    /// 访问令牌（保留原字段名以兼容旧客户端）
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
 }
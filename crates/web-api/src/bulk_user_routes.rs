@@ -71,7 +71,12 @@ async fn bulk_create_users(
     Json(payload): Json<BulkCreatePayload>,
 ) -> Result<(StatusCode, Json<BulkTaskResponse>), ApiError> {
     // 验证用户身份和权限
-    let user_id = UserId::new(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::new(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -127,7 +132,12 @@ async fn get_task_status(
     Path(task_id): Path<Uuid>,
 ) -> Result<Json<BulkTaskResponse>, ApiError> {
     // 验证用户身份
-    let user_id = UserId::new(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::new(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -156,7 +166,12 @@ async fn download_credentials(
     Path(task_id): Path<Uuid>,
 ) -> Result<Response, ApiError> {
     // 验证用户身份
-    let user_id = UserId::new(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::new(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -0,0 +1,44 @@
+//! 跨域资源共享（CORS）
+//!
+//! 浏览器前端和 API 部署在不同域名时，需要在预检和实际响应中带上 CORS 头。
+//! 允许的来源、方法和是否携带凭据都来自配置，启动时已经校验过格式。
+
+use axum::http::{header, HeaderValue, Method};
+use config::CorsConfig;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// 按配置构造 CORS 中间件；没有配置允许的来源时返回 None，不返回任何 CORS 头
+pub fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    // 预检请求本身用 OPTIONS，始终允许
+    let mut methods: Vec<Method> = config
+        .allowed_methods
+        .iter()
+        .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+        .collect();
+    if !methods.contains(&Method::OPTIONS) {
+        methods.push(Method::OPTIONS);
+    }
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .allow_credentials(config.allow_credentials),
+    )
+}
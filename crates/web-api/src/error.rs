@@ -1,6 +1,6 @@
-use application::ApplicationError;
+use application::{broadcaster::BroadcastError, ApplicationError, RateLimitError};
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -10,12 +10,20 @@ use serde::Serialize;
 pub struct ErrorBody {
     pub code: &'static str,
     pub message: String,
+    /// 房间密码错误时剩余的尝试次数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_attempts: Option<u32>,
+    /// 编辑冲突时资源的当前版本，客户端据此重新拉取
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_version: Option<i32>,
 }
 
 #[derive(Debug)]
 pub struct ApiError {
     status: StatusCode,
     body: ErrorBody,
+    /// 限流时通过 Retry-After 头告诉客户端需要等待的秒数
+    retry_after_secs: Option<u64>,
 }
 
 impl ApiError {
@@ -25,10 +33,36 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                remaining_attempts: None,
+                current_version: None,
             },
+            retry_after_secs: None,
         }
     }
 
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = Some(secs);
+        self
+    }
+
+    pub fn with_remaining_attempts(mut self, remaining: u32) -> Self {
+        self.body.remaining_attempts = Some(remaining);
+        self
+    }
+
+    pub fn with_current_version(mut self, version: i32) -> Self {
+        self.body.current_version = Some(version);
+        self
+    }
+
+    pub(crate) fn code(&self) -> &'static str {
+        self.body.code
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.body.message
+    }
+
     // 添加便利方法
     pub fn unauthorized(message: impl Into<String>) -> Self {
         Self::new(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", message)
@@ -69,6 +103,11 @@ impl From<ApplicationError> for ApiError {
             AppErr::Domain(DomainError::UserAlreadyExists) => {
                 ApiError::new(StatusCode::CONFLICT, "USER_EXISTS", "user already exists")
             }
+            AppErr::Domain(DomainError::EmailDomainNotAllowed) => ApiError::new(
+                StatusCode::FORBIDDEN,
+                "EMAIL_DOMAIN_NOT_ALLOWED",
+                "email domain not allowed",
+            ),
             AppErr::Domain(DomainError::UserNotFound) => {
                 ApiError::new(StatusCode::NOT_FOUND, "USER_NOT_FOUND", "user not found")
             }
@@ -85,6 +124,40 @@ impl From<ApplicationError> for ApiError {
                 "MESSAGE_NOT_FOUND",
                 "message not found",
             ),
+            AppErr::Domain(DomainError::MessageDeleted) => ApiError::new(
+                StatusCode::CONFLICT,
+                "MESSAGE_DELETED",
+                "message already deleted",
+            ),
+            AppErr::Domain(DomainError::MessageNotDeleted) => ApiError::new(
+                StatusCode::CONFLICT,
+                "MESSAGE_NOT_DELETED",
+                "message is not deleted",
+            ),
+            AppErr::Domain(DomainError::MessageVersionConflict { current_version }) => {
+                ApiError::new(
+                    StatusCode::CONFLICT,
+                    "MESSAGE_VERSION_CONFLICT",
+                    "message was modified by someone else",
+                )
+                .with_current_version(current_version)
+            }
+            AppErr::Domain(err @ DomainError::MessageTooLong { .. }) => {
+                ApiError::new(StatusCode::BAD_REQUEST, "MESSAGE_TOO_LONG", err.to_string())
+            }
+            AppErr::Domain(DomainError::ReactionNotAllowed) => ApiError::new(
+                StatusCode::FORBIDDEN,
+                "REACTION_NOT_ALLOWED",
+                "room only allows emoji reactions",
+            ),
+            AppErr::Domain(err @ DomainError::ReactionLimitExceeded { .. }) => ApiError::new(
+                StatusCode::CONFLICT,
+                "REACTION_LIMIT_EXCEEDED",
+                err.to_string(),
+            ),
+            AppErr::Domain(err @ DomainError::PinLimitExceeded { .. }) => {
+                ApiError::new(StatusCode::CONFLICT, "PIN_LIMIT_EXCEEDED", err.to_string())
+            }
             AppErr::Domain(DomainError::UserAlreadyInRoom) => ApiError::new(
                 StatusCode::CONFLICT,
                 "MEMBERSHIP_EXISTS",
@@ -93,11 +166,21 @@ impl From<ApplicationError> for ApiError {
             AppErr::Domain(DomainError::UserNotInRoom) => {
                 ApiError::new(StatusCode::FORBIDDEN, "NOT_ROOM_MEMBER", "user not in room")
             }
+            AppErr::Domain(DomainError::MemberNotFound) => ApiError::new(
+                StatusCode::NOT_FOUND,
+                "MEMBER_NOT_FOUND",
+                "member not found",
+            ),
             AppErr::Domain(DomainError::RoomIsPrivate) => ApiError::new(
                 StatusCode::FORBIDDEN,
                 "ROOM_PRIVATE",
                 "room requires password",
             ),
+            AppErr::Domain(DomainError::InvalidRoomPassword) => ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "INVALID_ROOM_PASSWORD",
+                "invalid room password",
+            ),
             AppErr::Domain(DomainError::RoomClosed) => {
                 ApiError::new(StatusCode::FORBIDDEN, "ROOM_CLOSED", "room is closed")
             }
@@ -106,6 +189,122 @@ impl From<ApplicationError> for ApiError {
                 "OPERATION_NOT_ALLOWED",
                 "operation not allowed",
             ),
+            AppErr::Domain(DomainError::OwnerCannotLeave) => ApiError::new(
+                StatusCode::CONFLICT,
+                "OWNER_CANNOT_LEAVE",
+                "room owner must transfer ownership before leaving",
+            ),
+            AppErr::Domain(err @ DomainError::RoleChangeNotAllowed { .. }) => ApiError::new(
+                StatusCode::CONFLICT,
+                "ROLE_CHANGE_NOT_ALLOWED",
+                err.to_string(),
+            ),
+            AppErr::Domain(DomainError::EmailNotVerified) => ApiError::new(
+                StatusCode::FORBIDDEN,
+                "EMAIL_NOT_VERIFIED",
+                "email not verified",
+            ),
+            AppErr::Domain(DomainError::InvalidVerificationToken) => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_VERIFICATION_TOKEN",
+                "invalid or expired verification token",
+            ),
+            AppErr::Domain(DomainError::VerificationResendTooSoon { retry_after_secs }) => {
+                ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "VERIFICATION_RESEND_TOO_SOON",
+                    "verification email sent recently",
+                )
+                .with_retry_after(retry_after_secs)
+            }
+            AppErr::Domain(DomainError::InvalidPasswordResetToken) => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_RESET_TOKEN",
+                "invalid or expired password reset token",
+            ),
+            AppErr::Domain(DomainError::AccountSuspended) => ApiError::new(
+                StatusCode::FORBIDDEN,
+                "ACCOUNT_SUSPENDED",
+                "account suspended",
+            ),
+            AppErr::Domain(err @ DomainError::UserMuted { remaining_secs }) => {
+                ApiError::new(StatusCode::FORBIDDEN, "USER_MUTED", err.to_string())
+                    .with_retry_after(remaining_secs)
+            }
+            AppErr::Domain(DomainError::UserBannedFromRoom) => ApiError::new(
+                StatusCode::FORBIDDEN,
+                "BANNED_FROM_ROOM",
+                "you are banned from this room",
+            ),
+            AppErr::Domain(DomainError::EncryptionNotConfigured) => ApiError::new(
+                StatusCode::CONFLICT,
+                "ENCRYPTION_NOT_CONFIGURED",
+                "message encryption is not configured on this server",
+            ),
+            AppErr::Domain(DomainError::EncryptedRoomNotSearchable) => ApiError::new(
+                StatusCode::CONFLICT,
+                "ENCRYPTED_ROOM_NOT_SEARCHABLE",
+                "search is not available in encrypted rooms",
+            ),
+            AppErr::Domain(DomainError::FileNotFound) => {
+                ApiError::new(StatusCode::NOT_FOUND, "FILE_NOT_FOUND", "file not found")
+            }
+            AppErr::Domain(DomainError::IdempotencyKeyInUse) => ApiError::new(
+                StatusCode::CONFLICT,
+                "IDEMPOTENCY_KEY_IN_USE",
+                "a request with this idempotency key is still in progress",
+            ),
+            AppErr::Domain(DomainError::IdempotencyKeyReused) => ApiError::new(
+                StatusCode::CONFLICT,
+                "IDEMPOTENCY_KEY_REUSED",
+                "this idempotency key was already used for a message in another room",
+            ),
+            AppErr::Domain(DomainError::NotificationNotFound) => ApiError::new(
+                StatusCode::NOT_FOUND,
+                "NOTIFICATION_NOT_FOUND",
+                "notification not found",
+            ),
+            AppErr::Domain(DomainError::InvitationNotFound) => ApiError::new(
+                StatusCode::NOT_FOUND,
+                "INVITATION_NOT_FOUND",
+                "invitation not found",
+            ),
+            AppErr::Domain(DomainError::InvitationAlreadyPending) => ApiError::new(
+                StatusCode::CONFLICT,
+                "INVITATION_ALREADY_PENDING",
+                "user already has a pending invitation to this room",
+            ),
+            AppErr::Domain(DomainError::InvitationNotPending) => ApiError::new(
+                StatusCode::CONFLICT,
+                "INVITATION_NOT_PENDING",
+                "invitation is no longer pending",
+            ),
+            AppErr::Domain(DomainError::InvitationExpired) => {
+                ApiError::new(StatusCode::GONE, "INVITATION_EXPIRED", "invitation expired")
+            }
+            AppErr::Domain(DomainError::InviteNotFound) => ApiError::new(
+                StatusCode::NOT_FOUND,
+                "INVITE_NOT_FOUND",
+                "invite link not found",
+            ),
+            AppErr::Domain(DomainError::InviteExpired) => {
+                ApiError::new(StatusCode::GONE, "INVITE_EXPIRED", "invite link expired")
+            }
+            AppErr::Domain(DomainError::InviteExhausted) => ApiError::new(
+                StatusCode::GONE,
+                "INVITE_EXHAUSTED",
+                "invite link has reached its usage limit",
+            ),
+            AppErr::Domain(err @ DomainError::FileTooLarge { .. }) => ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "FILE_TOO_LARGE",
+                err.to_string(),
+            ),
+            AppErr::Domain(err @ DomainError::UnsupportedContentType { .. }) => ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UNSUPPORTED_CONTENT_TYPE",
+                err.to_string(),
+            ),
             AppErr::Repository(repo_err) => match repo_err {
                 domain::RepositoryError::NotFound => ApiError::new(
                     StatusCode::NOT_FOUND,
@@ -126,6 +325,11 @@ impl From<ApplicationError> for ApiError {
                 "PASSWORD_ERROR",
                 format!("password error: {}", err),
             ),
+            AppErr::Broadcast(err @ BroadcastError::Unavailable(_)) => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "BROADCAST_UNAVAILABLE",
+                format!("broadcast unavailable: {}", err),
+            ),
             AppErr::Broadcast(err) => ApiError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "BROADCAST_ERROR",
@@ -141,6 +345,12 @@ impl From<ApplicationError> for ApiError {
                 "AUTHORIZATION_FAILED",
                 "authorization failed",
             ),
+            AppErr::RateLimited(err) => err.into(),
+            AppErr::ShuttingDown => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SHUTTING_DOWN",
+                "service is shutting down",
+            ),
             AppErr::Infrastructure { message, .. } => ApiError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "INFRASTRUCTURE_ERROR",
@@ -152,7 +362,77 @@ impl From<ApplicationError> for ApiError {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        (self.status, Json(self.body)).into_response()
+        match self.retry_after_secs {
+            Some(secs) => (
+                self.status,
+                [(header::RETRY_AFTER, secs.to_string())],
+                Json(self.body),
+            )
+                .into_response(),
+            None => (self.status, Json(self.body)).into_response(),
+        }
+    }
+}
+
+impl From<RateLimitError> for ApiError {
+    fn from(error: RateLimitError) -> Self {
+        match error {
+            RateLimitError::MessageRateLimited {
+                retry_after_secs, ..
+            }
+            | RateLimitError::RoomRateLimitExceeded {
+                retry_after_secs, ..
+            } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED",
+                error.to_string(),
+            )
+            .with_retry_after(retry_after_secs),
+            RateLimitError::RoomRateLimited {
+                retry_after_secs, ..
+            } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "ROOM_RATE_LIMITED",
+                error.to_string(),
+            )
+            .with_retry_after(retry_after_secs),
+            RateLimitError::TooManyJoinAttempts {
+                retry_after_secs, ..
+            } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOO_MANY_JOIN_ATTEMPTS",
+                error.to_string(),
+            )
+            .with_retry_after(retry_after_secs)
+            .with_remaining_attempts(0),
+            RateLimitError::TooManyPasswordResetRequests {
+                retry_after_secs, ..
+            } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOO_MANY_RESET_REQUESTS",
+                error.to_string(),
+            )
+            .with_retry_after(retry_after_secs),
+            RateLimitError::TooManyRequests {
+                retry_after_secs, ..
+            } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOO_MANY_REQUESTS",
+                error.to_string(),
+            )
+            .with_retry_after(retry_after_secs),
+            RateLimitError::RateLimitExceeded { .. }
+            | RateLimitError::TooManyConnections { .. } => ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED",
+                error.to_string(),
+            ),
+            RateLimitError::UserBanned { .. } | RateLimitError::Redis(_) => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "RATE_LIMITER_UNAVAILABLE",
+                error.to_string(),
+            ),
+        }
     }
 }
 
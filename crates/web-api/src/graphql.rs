@@ -0,0 +1,296 @@
+//! GraphQL 查询入口（`graphql` feature）
+//!
+//! 只是 REST 之外的另一种查询方式：所有解析器都委托给 `AppState` 里现有的服务，
+//! 校验、权限和限流与 REST 处理器完全一致。认证复用 JWT 提取逻辑，
+//! 请求上下文携带 [`AuthUser`]。
+
+use async_graphql::{
+    Context, EmptySubscription, Enum, ErrorExtensions, Object, Schema, SimpleObject,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{extract::State, http::HeaderMap, routing::post, Extension, Router};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use application::services::{CreateRoomRequest, SendMessageRequest};
+use domain::{ChatRoom, ChatRoomVisibility, Message, MessageType, RoomId, User, UserId};
+
+use crate::{error::ApiError, state::AppState};
+
+pub type ChatSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// 单次查询的消息条数上限，与 REST 历史接口一致
+const MAX_MESSAGES_PER_QUERY: u32 = 100;
+
+/// 已认证的调用者
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser(pub UserId);
+
+pub fn build_schema() -> ChatSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+}
+
+pub(crate) fn graphql_routes() -> Router<AppState> {
+    Router::new()
+        .route("/graphql", post(graphql_handler))
+        .layer(Extension(build_schema()))
+}
+
+async fn graphql_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Extension(schema): Extension<ChatSchema>,
+    request: GraphQLRequest,
+) -> Result<GraphQLResponse, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let request = request
+        .into_inner()
+        .data(state)
+        .data(AuthUser(UserId::from(user_id)));
+
+    Ok(schema.execute(request).await.into())
+}
+
+/// 错误码与 REST 响应保持一致，放在 `extensions.code`
+fn gql_error(error: impl Into<ApiError>) -> async_graphql::Error {
+    let error = error.into();
+    let code = error.code();
+    async_graphql::Error::new(error.message()).extend_with(|_, extensions| {
+        extensions.set("code", code);
+    })
+}
+
+fn context<'a>(ctx: &Context<'a>) -> async_graphql::Result<(&'a AppState, UserId)> {
+    let state = ctx.data::<AppState>()?;
+    let AuthUser(user_id) = *ctx.data::<AuthUser>()?;
+    Ok((state, user_id))
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomVisibility {
+    Public,
+    Private,
+}
+
+impl From<ChatRoomVisibility> for RoomVisibility {
+    fn from(value: ChatRoomVisibility) -> Self {
+        match value {
+            ChatRoomVisibility::Public => RoomVisibility::Public,
+            ChatRoomVisibility::Private => RoomVisibility::Private,
+        }
+    }
+}
+
+impl From<RoomVisibility> for ChatRoomVisibility {
+    fn from(value: RoomVisibility) -> Self {
+        match value {
+            RoomVisibility::Public => ChatRoomVisibility::Public,
+            RoomVisibility::Private => ChatRoomVisibility::Private,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Text,
+    Image,
+    File,
+}
+
+impl From<MessageType> for MessageKind {
+    fn from(value: MessageType) -> Self {
+        match value {
+            MessageType::Text => MessageKind::Text,
+            MessageType::Image => MessageKind::Image,
+            MessageType::File => MessageKind::File,
+        }
+    }
+}
+
+impl From<MessageKind> for MessageType {
+    fn from(value: MessageKind) -> Self {
+        match value {
+            MessageKind::Text => MessageType::Text,
+            MessageKind::Image => MessageType::Image,
+            MessageKind::File => MessageType::File,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct RoomObject {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub visibility: RoomVisibility,
+    pub is_closed: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl From<ChatRoom> for RoomObject {
+    fn from(room: ChatRoom) -> Self {
+        Self {
+            id: Uuid::from(room.id),
+            name: room.name,
+            owner_id: Uuid::from(room.owner_id),
+            visibility: room.visibility.into(),
+            is_closed: room.is_closed,
+            created_at: room.created_at,
+            updated_at: room.updated_at,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MessageObject {
+    pub id: Uuid,
+    pub room_id: Uuid,
+    pub sender_id: Uuid,
+    pub content: String,
+    pub message_type: MessageKind,
+    pub reply_to: Option<Uuid>,
+    pub thread_root_id: Option<Uuid>,
+    pub seq: i64,
+    pub created_at: OffsetDateTime,
+    pub edited_at: Option<OffsetDateTime>,
+}
+
+impl From<Message> for MessageObject {
+    fn from(message: Message) -> Self {
+        Self {
+            id: Uuid::from(message.id),
+            room_id: Uuid::from(message.room_id),
+            sender_id: Uuid::from(message.sender_id),
+            content: message.content.as_str().to_string(),
+            message_type: message.message_type.into(),
+            reply_to: message.reply_to.map(Uuid::from),
+            thread_root_id: message.thread_root_id.map(Uuid::from),
+            seq: message.seq,
+            created_at: message.created_at,
+            edited_at: message.last_revision.map(|revision| revision.updated_at),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct UserObject {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub is_superuser: bool,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<User> for UserObject {
+    fn from(user: User) -> Self {
+        Self {
+            id: Uuid::from(user.id),
+            username: user.username.as_str().to_string(),
+            email: user.email.as_str().to_string(),
+            is_superuser: user.is_superuser,
+            created_at: user.created_at,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// 房间信息；私有房间只对成员可见
+    async fn room(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<RoomObject> {
+        let (state, user_id) = context(ctx)?;
+        let room = state
+            .chat_service
+            .get_room(id, Uuid::from(user_id))
+            .await
+            .map_err(gql_error)?;
+        Ok(room.into())
+    }
+
+    /// 房间历史消息，按序列号倒序；`before` 为游标消息 ID
+    async fn room_messages(
+        &self,
+        ctx: &Context<'_>,
+        room_id: Uuid,
+        before: Option<Uuid>,
+        limit: Option<u32>,
+    ) -> async_graphql::Result<Vec<MessageObject>> {
+        let (state, user_id) = context(ctx)?;
+        let limit = limit.unwrap_or(50).min(MAX_MESSAGES_PER_QUERY);
+        let page = state
+            .chat_service
+            .get_history(room_id, Uuid::from(user_id), limit, before)
+            .await
+            .map_err(gql_error)?;
+        Ok(page.items.into_iter().map(MessageObject::from).collect())
+    }
+
+    /// 当前登录用户
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<UserObject> {
+        let (state, user_id) = context(ctx)?;
+        let user = state
+            .user_service
+            .find_user_by_id(user_id)
+            .await
+            .map_err(gql_error)?
+            .ok_or_else(|| gql_error(ApiError::not_found("user not found")))?;
+        Ok(user.into())
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// 发送消息，限流与 REST 接口相同
+    async fn send_message(
+        &self,
+        ctx: &Context<'_>,
+        room_id: Uuid,
+        content: String,
+        #[graphql(default_with = "MessageKind::Text")] message_type: MessageKind,
+        reply_to: Option<Uuid>,
+    ) -> async_graphql::Result<MessageObject> {
+        let (state, user_id) = context(ctx)?;
+        let message = state
+            .chat_service
+            .send_message(SendMessageRequest {
+                room_id,
+                sender_id: Uuid::from(user_id),
+                content,
+                message_type: message_type.into(),
+                reply_to,
+                idempotency_key: None,
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(message.into())
+    }
+
+    async fn create_room(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        visibility: RoomVisibility,
+        password: Option<String>,
+    ) -> async_graphql::Result<RoomObject> {
+        let (state, user_id) = context(ctx)?;
+        let room = state
+            .chat_service
+            .create_room(CreateRoomRequest {
+                name,
+                owner_id: Uuid::from(user_id),
+                visibility: visibility.into(),
+                password,
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(room.into())
+    }
+}
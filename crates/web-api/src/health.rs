@@ -0,0 +1,88 @@
+//! 就绪探针的依赖检查
+//!
+//! 每次探测都实际访问数据库和 Redis，单个依赖超时即视为不可用，
+//! 避免卡住的依赖把探针本身拖到超时。
+
+use std::time::Duration;
+
+use redis::Client as RedisClient;
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// 检查失败的依赖
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyFailure {
+    pub dependency: &'static str,
+    pub error: String,
+}
+
+/// 就绪探针需要检查的依赖
+#[derive(Clone)]
+pub struct HealthProbe {
+    pg_pool: PgPool,
+    redis: Vec<(&'static str, RedisClient)>,
+    timeout: Duration,
+}
+
+impl HealthProbe {
+    pub fn new(pg_pool: PgPool, timeout: Duration) -> Self {
+        Self {
+            pg_pool,
+            redis: Vec::new(),
+            timeout,
+        }
+    }
+
+    /// 增加一个需要检查的 Redis 实例，未配置 Redis 时不调用即可
+    pub fn with_redis(mut self, dependency: &'static str, client: RedisClient) -> Self {
+        self.redis.push((dependency, client));
+        self
+    }
+
+    /// 并发检查所有依赖，返回失败的依赖；全部可用时为空
+    pub async fn check(&self) -> Vec<DependencyFailure> {
+        let postgres = self.run("postgres", async {
+            sqlx::query("SELECT 1")
+                .execute(&self.pg_pool)
+                .await
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        });
+        let redis = futures_util::future::join_all(
+            self.redis
+                .iter()
+                .map(|(dependency, client)| self.run(dependency, ping_redis(client))),
+        );
+
+        let (postgres, redis) = tokio::join!(postgres, redis);
+        postgres
+            .into_iter()
+            .chain(redis.into_iter().flatten())
+            .collect()
+    }
+
+    async fn run(
+        &self,
+        dependency: &'static str,
+        check: impl std::future::Future<Output = Result<(), String>>,
+    ) -> Option<DependencyFailure> {
+        let error = match tokio::time::timeout(self.timeout, check).await {
+            Ok(Ok(())) => return None,
+            Ok(Err(err)) => err,
+            Err(_) => format!("timed out after {:?}", self.timeout),
+        };
+        tracing::warn!(dependency, %error, "就绪检查失败");
+        Some(DependencyFailure { dependency, error })
+    }
+}
+
+async fn ping_redis(client: &RedisClient) -> Result<(), String> {
+    let mut conn = client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|err| err.to_string())?;
+    redis::cmd("PING")
+        .query_async::<()>(&mut conn)
+        .await
+        .map_err(|err| err.to_string())
+}
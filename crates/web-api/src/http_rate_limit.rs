@@ -0,0 +1,223 @@
+//! 按客户端 IP 的接口限流中间件
+//!
+//! 登录、注册、找回密码这类接口是暴力破解的首要目标，每个接口按 IP 独立计数。
+//! 采用滑动窗口：任意 `window_secs` 长的时间段内请求数都不超过上限，
+//! 不会像固定窗口那样在窗口交界处放过两倍的请求。
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use application::RateLimitError;
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use config::{AppConfig, EndpointRateLimit, HttpRateLimitConfig};
+use uuid::Uuid;
+
+use crate::{error::ApiError, state::AppState};
+
+/// 滑动窗口计数的存储
+#[async_trait]
+pub trait SlidingWindowStore: Send + Sync {
+    /// 记录一次请求；窗口内已达上限时不计入，返回还需等待的毫秒数
+    async fn hit(
+        &self,
+        key: &str,
+        max_requests: u32,
+        window: Duration,
+    ) -> Result<Option<u64>, RateLimitError>;
+}
+
+/// 超过这么多个键时清掉窗口内已经没有请求的键
+const MAX_IDLE_KEYS: usize = 10_000;
+
+/// 进程内的滑动窗口，只适用于单实例部署
+#[derive(Default)]
+pub struct MemorySlidingWindow {
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+#[async_trait]
+impl SlidingWindowStore for MemorySlidingWindow {
+    async fn hit(
+        &self,
+        key: &str,
+        max_requests: u32,
+        window: Duration,
+    ) -> Result<Option<u64>, RateLimitError> {
+        let now = Instant::now();
+        let expired = |hit: &Instant| now.duration_since(*hit) >= window;
+
+        let mut hits = self
+            .hits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if hits.len() > MAX_IDLE_KEYS {
+            hits.retain(|_, log| log.back().is_some_and(|hit| !expired(hit)));
+        }
+
+        let log = hits.entry(key.to_string()).or_default();
+        while log.front().is_some_and(expired) {
+            log.pop_front();
+        }
+        if log.len() >= max_requests as usize {
+            let oldest = log.front().copied().unwrap_or(now);
+            let wait = window.saturating_sub(now.duration_since(oldest));
+            return Ok(Some(wait.as_millis() as u64));
+        }
+        log.push_back(now);
+        Ok(None)
+    }
+}
+
+/// Redis 有序集合实现的滑动窗口，多实例共享计数
+pub struct RedisSlidingWindow {
+    client: redis::Client,
+}
+
+impl RedisSlidingWindow {
+    pub fn new(client: redis::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SlidingWindowStore for RedisSlidingWindow {
+    async fn hit(
+        &self,
+        key: &str,
+        max_requests: u32,
+        window: Duration,
+    ) -> Result<Option<u64>, RateLimitError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        // 每次请求一个成员，分数为请求时间；时间取 Redis 服务器时间，避免各实例时钟偏差
+        let script = redis::Script::new(
+            r#"
+            local key = KEYS[1]
+            local limit = tonumber(ARGV[1])
+            local window_ms = tonumber(ARGV[2])
+
+            local time = redis.call('TIME')
+            local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+            redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window_ms)
+            if redis.call('ZCARD', key) >= limit then
+                local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+                return math.max(1, tonumber(oldest[2]) + window_ms - now)
+            end
+
+            redis.call('ZADD', key, now, ARGV[3])
+            redis.call('PEXPIRE', key, window_ms)
+            return 0
+            "#,
+        );
+
+        let wait_ms: i64 = script
+            .key(format!("rate_limit:http:{}", key))
+            .arg(max_requests)
+            .arg(window.as_millis() as u64)
+            .arg(Uuid::new_v4().to_string())
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok((wait_ms > 0).then_some(wait_ms as u64))
+    }
+}
+
+/// 接口限流器：按请求路径找到限流配置，再按客户端 IP 计数
+pub struct HttpRateLimiter {
+    endpoints: HashMap<String, EndpointRateLimit>,
+    trust_forwarded_for: bool,
+    store: Arc<dyn SlidingWindowStore>,
+}
+
+impl HttpRateLimiter {
+    pub fn new(config: &HttpRateLimitConfig, store: Arc<dyn SlidingWindowStore>) -> Self {
+        Self {
+            endpoints: config
+                .endpoints
+                .iter()
+                .map(|endpoint| (endpoint.path.clone(), endpoint.clone()))
+                .collect(),
+            trust_forwarded_for: config.trust_forwarded_for,
+            store,
+        }
+    }
+
+    /// 不限流任何接口，也不信任 `X-Forwarded-For`
+    pub fn disabled() -> Self {
+        Self {
+            endpoints: HashMap::new(),
+            trust_forwarded_for: false,
+            store: Arc::new(MemorySlidingWindow::default()),
+        }
+    }
+
+    /// 配置了 `broadcast.redis_url`（多实例部署）时计数放在 Redis，否则放在内存
+    pub fn from_app_config(config: &AppConfig) -> Result<Self, redis::RedisError> {
+        let store: Arc<dyn SlidingWindowStore> = match &config.broadcast.redis_url {
+            Some(redis_url) => Arc::new(RedisSlidingWindow::new(redis::Client::open(
+                redis_url.as_str(),
+            )?)),
+            None => Arc::new(MemorySlidingWindow::default()),
+        };
+        Ok(Self::new(&config.rate_limit.http, store))
+    }
+
+    /// 客户端 IP：信任反向代理时取 `X-Forwarded-For` 的第一跳，否则取连接地址
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+        headers
+            .get("x-forwarded-for")
+            .filter(|_| self.trust_forwarded_for)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty())
+            .map(str::to_string)
+            .or_else(|| peer.map(|addr| addr.ip().to_string()))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    async fn check(&self, endpoint: &EndpointRateLimit, client_ip: &str) -> Result<(), ApiError> {
+        let key = format!("{}:{}", endpoint.path, client_ip);
+        let window = Duration::from_secs(endpoint.window_secs);
+        if let Some(wait_ms) = self.store.hit(&key, endpoint.max_requests, window).await? {
+            return Err(RateLimitError::TooManyRequests {
+                max: endpoint.max_requests,
+                window_secs: endpoint.window_secs,
+                retry_after_secs: wait_ms.div_ceil(1000).max(1),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// 只拦截配置了限流的路径，其他请求直接放行
+pub(crate) async fn limit_by_client_ip(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.http_rate_limiter;
+    if let Some(endpoint) = limiter.endpoints.get(request.uri().path()) {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0);
+        let client_ip = limiter.client_ip(request.headers(), peer);
+        if let Err(err) = limiter.check(endpoint, &client_ip).await {
+            tracing::info!(path = %endpoint.path, %client_ip, "接口请求过于频繁");
+            return err.into_response();
+        }
+    }
+
+    next.run(request).await
+}
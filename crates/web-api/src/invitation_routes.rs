@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use uuid::Uuid;
+
+use domain::{RoomInvitation, RoomMember};
+
+use crate::{error::ApiError, state::AppState};
+
+/// 入房邀请路由，挂载在 `/invitations` 下；发出邀请走 `POST /rooms/{room_id}/members`
+pub fn invitation_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_invitations))
+        .route("/{invitation_id}/accept", post(accept_invitation))
+        .route("/{invitation_id}/decline", post(decline_invitation))
+        .route("/{invitation_id}", delete(revoke_invitation))
+}
+
+/// 当前用户收到的待处理邀请
+async fn list_invitations(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RoomInvitation>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let invitations = state.chat_service.list_invitations(user_id).await?;
+
+    Ok(Json(invitations))
+}
+
+/// 接受邀请并加入房间
+async fn accept_invitation(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<RoomMember>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let member = state
+        .chat_service
+        .accept_invitation(invitation_id, user_id)
+        .await?;
+
+    Ok(Json(member))
+}
+
+async fn decline_invitation(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<RoomInvitation>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let invitation = state
+        .chat_service
+        .decline_invitation(invitation_id, user_id)
+        .await?;
+
+    Ok(Json(invitation))
+}
+
+/// 邀请人或房间管理员撤回邀请
+async fn revoke_invitation(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .revoke_invitation(invitation_id, user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+
+use domain::RoomMember;
+
+use crate::{error::ApiError, state::AppState};
+
+/// 邀请链接路由，挂载在 `/invites` 下；创建链接走 `POST /rooms/{room_id}/invites`
+pub fn invite_routes() -> Router<AppState> {
+    Router::new().route("/{token}/redeem", post(redeem_invite))
+}
+
+/// 凭邀请链接加入房间
+async fn redeem_invite(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<(StatusCode, Json<RoomMember>), ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let member = state.chat_service.redeem_invite(&token, user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(member)))
+}
@@ -5,18 +5,48 @@
 mod admin_routes;
 mod auth;
 mod bulk_user_routes;
+mod cors;
 mod error;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod health;
+mod http_rate_limit;
+mod invitation_routes;
+mod invite_routes;
+pub mod metrics;
+mod notification_routes;
 mod org_routes;
 mod routes;
+mod shutdown;
 mod state;
 mod stats_routes;
 mod ws_connection;
+mod ws_deflate_subprotocol;
+mod ws_outbound;
+mod ws_registry;
 
 pub use admin_routes::admin_routes;
-pub use auth::{JwtService, LoginResponse};
+pub use auth::{JwtService, LoginResponse, TokenPair};
 pub use bulk_user_routes::bulk_user_routes;
 pub use config::JwtConfig;
+pub use cors::cors_layer;
+#[cfg(feature = "graphql")]
+pub use graphql::{build_schema, ChatSchema};
+pub use health::{DependencyFailure, HealthProbe};
+pub use http_rate_limit::{
+    HttpRateLimiter, MemorySlidingWindow, RedisSlidingWindow, SlidingWindowStore,
+};
+pub use invitation_routes::invitation_routes;
+pub use invite_routes::invite_routes;
+pub use notification_routes::notification_routes;
 pub use org_routes::org_routes;
 pub use routes::router;
-pub use state::AppState;
+pub use shutdown::serve_with_graceful_shutdown;
+pub use state::{AppState, WsFlowControl, WsHeartbeat};
 pub use stats_routes::stats_routes;
+pub use ws_deflate_subprotocol::{WsDeflateSubprotocol, DEFLATE_SUBPROTOCOL};
+pub use ws_outbound::{OutboundQueue, PushOutcome, TOO_SLOW_REASON};
+pub use ws_registry::{
+    ConnectionRegistry, ConnectionRejected, ConnectionTicket, WsConnectionLimits, EVICTED_REASON,
+    SERVER_RESTARTING_REASON,
+};
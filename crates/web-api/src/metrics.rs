@@ -0,0 +1,120 @@
+//! Prometheus 指标
+//!
+//! 通过 `metrics` 门面记录，由 Prometheus recorder 汇总后在 `GET /metrics` 以文本格式导出。
+//! 全局 recorder 每个进程只能安装一次，重复调用 [`install_recorder`] 返回同一个句柄。
+
+use std::time::Duration;
+
+use application::metrics::{
+    BROADCAST_FANOUT_SECONDS, BROADCAST_LAGGED_TOTAL, BROADCAST_ROOM_CAPACITY, MESSAGES_SENT_TOTAL,
+};
+use axum::{extract::State, http::header, response::IntoResponse};
+use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::OnceCell;
+
+use crate::state::AppState;
+
+/// 当前打开的 WebSocket 连接数
+pub const WS_CONNECTIONS_ACTIVE: &str = "chat_ws_connections_active";
+
+/// 因为客户端接收太慢被丢弃的 WebSocket 帧数
+pub const WS_FRAMES_DROPPED_TOTAL: &str = "chat_ws_frames_dropped_total";
+
+/// 因为客户端接收太慢被断开的 WebSocket 连接数，按 `reason`（buffer_full / stalled）区分
+pub const WS_SLOW_CLIENT_DISCONNECTS_TOTAL: &str = "chat_ws_slow_client_disconnects_total";
+
+/// 访问令牌校验次数，按 `result`（success / failure）区分
+pub const AUTH_ATTEMPTS_TOTAL: &str = "chat_auth_attempts_total";
+
+/// 广播扇出耗时的分桶（秒），本地广播在毫秒以内，Redis 广播多一次网络往返
+const FANOUT_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0,
+];
+
+static HANDLE: OnceCell<PrometheusHandle> = OnceCell::new();
+
+/// 安装全局 Prometheus recorder 并返回渲染句柄
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    HANDLE
+        .get_or_try_init(|| {
+            let handle = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full(BROADCAST_FANOUT_SECONDS.to_string()),
+                    FANOUT_BUCKETS,
+                )?
+                .install_recorder()?;
+            describe_metrics();
+            Ok(handle)
+        })
+        .cloned()
+}
+
+/// 定期整理直方图数据；没有人抓取时防止样本无限堆积
+pub fn spawn_upkeep(handle: PrometheusHandle, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            handle.run_upkeep();
+        }
+    });
+}
+
+fn describe_metrics() {
+    describe_counter!(MESSAGES_SENT_TOTAL, "Messages persisted and broadcast");
+    describe_histogram!(
+        BROADCAST_FANOUT_SECONDS,
+        Unit::Seconds,
+        "Time spent broadcasting a message to the room"
+    );
+    describe_counter!(
+        BROADCAST_LAGGED_TOTAL,
+        "In-process broadcast subscribers that fell behind the room buffer"
+    );
+    describe_gauge!(
+        BROADCAST_ROOM_CAPACITY,
+        "In-process broadcast buffer size of rooms grown after lag"
+    );
+    describe_gauge!(WS_CONNECTIONS_ACTIVE, "Open WebSocket connections");
+    describe_counter!(
+        WS_FRAMES_DROPPED_TOTAL,
+        "WebSocket frames dropped because the client could not keep up"
+    );
+    describe_counter!(
+        WS_SLOW_CLIENT_DISCONNECTS_TOTAL,
+        "WebSocket connections closed because the client could not keep up"
+    );
+    describe_counter!(AUTH_ATTEMPTS_TOTAL, "Access token verifications by result");
+}
+
+/// 记录一次访问令牌校验结果
+pub(crate) fn record_auth(success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics::counter!(AUTH_ATTEMPTS_TOTAL, "result" => result).increment(1);
+}
+
+pub(crate) fn ws_connection_opened() {
+    metrics::gauge!(WS_CONNECTIONS_ACTIVE).increment(1.0);
+}
+
+pub(crate) fn ws_connection_closed() {
+    metrics::gauge!(WS_CONNECTIONS_ACTIVE).decrement(1.0);
+}
+
+pub(crate) fn ws_frame_dropped() {
+    metrics::counter!(WS_FRAMES_DROPPED_TOTAL).increment(1);
+}
+
+pub(crate) fn ws_slow_client_disconnected(reason: &'static str) {
+    metrics::counter!(WS_SLOW_CLIENT_DISCONNECTS_TOTAL, "reason" => reason).increment(1);
+}
+
+pub(crate) async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state
+        .metrics
+        .as_ref()
+        .map(PrometheusHandle::render)
+        .unwrap_or_default();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use application::repository::PaginatedResult;
+use domain::Notification;
+
+use crate::{error::ApiError, state::AppState};
+
+#[derive(Debug, Deserialize)]
+struct NotificationsQuery {
+    #[serde(default)]
+    unread_only: bool,
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct UnreadCountResponse {
+    unread: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct MarkAllReadResponse {
+    /// 本次新标记为已读的通知数
+    updated: u64,
+}
+
+/// 通知中心路由，挂载在 `/notifications` 下
+pub fn notification_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_notifications))
+        .route("/unread-count", get(unread_count))
+        .route("/read-all", post(mark_all_read))
+        .route("/{notification_id}/read", post(mark_read))
+        .route("/{notification_id}", delete(delete_notification))
+}
+
+/// 当前用户的通知，`unread_only=true` 只列未读
+async fn list_notifications(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<NotificationsQuery>,
+) -> Result<Json<PaginatedResult<Notification>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let notifications = state
+        .notification_service
+        .list(user_id, query.unread_only, page, page_size)
+        .await?;
+
+    Ok(Json(notifications))
+}
+
+/// 未读角标，供客户端轮询
+async fn unread_count(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<UnreadCountResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let unread = state.notification_service.unread_count(user_id).await?;
+
+    Ok(Json(UnreadCountResponse { unread }))
+}
+
+async fn mark_read(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<Json<Notification>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let notification = state
+        .notification_service
+        .mark_read(user_id, notification_id)
+        .await?;
+
+    Ok(Json(notification))
+}
+
+async fn mark_all_read(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<MarkAllReadResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let updated = state.notification_service.mark_all_read(user_id).await?;
+
+    Ok(Json(MarkAllReadResponse { updated }))
+}
+
+async fn delete_notification(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .notification_service
+        .delete(user_id, notification_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
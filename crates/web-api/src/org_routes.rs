@@ -90,7 +90,12 @@ async fn create_organization(
     Json(payload): Json<CreateOrganizationPayload>,
 ) -> Result<(StatusCode, Json<OrganizationResponse>), ApiError> {
     // 验证用户身份和权限
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -137,7 +142,12 @@ async fn list_organizations(
     Query(query): Query<ListOrganizationsQuery>,
 ) -> Result<Json<Vec<OrganizationResponse>>, ApiError> {
     // 验证用户身份
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -170,7 +180,12 @@ async fn get_organization(
     Path(org_id): Path<Uuid>,
 ) -> Result<Json<OrganizationResponse>, ApiError> {
     // 验证用户身份
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let _user = state
         .storage
         .user_repository
@@ -196,7 +211,12 @@ async fn update_organization(
     Json(payload): Json<UpdateOrganizationPayload>,
 ) -> Result<Json<OrganizationResponse>, ApiError> {
     // 验证用户身份和权限
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -241,7 +261,12 @@ async fn delete_organization(
     Path(org_id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
     // 验证用户身份和权限
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -296,7 +321,12 @@ async fn get_organization_tree(
     Path(org_id): Path<Uuid>,
 ) -> Result<Json<OrganizationTreeResponse>, ApiError> {
     // 验证用户身份
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let _user = state
         .storage
         .user_repository
@@ -331,7 +361,12 @@ async fn move_organization(
     Json(payload): Json<MoveOrganizationPayload>,
 ) -> Result<Json<OrganizationResponse>, ApiError> {
     // 验证用户身份和权限
-    let user_id = UserId::from(state.jwt_service.extract_user_from_headers(&headers)?);
+    let user_id = UserId::from(
+        state
+            .jwt_service
+            .extract_user_from_headers(&headers)
+            .await?,
+    );
     let user = state
         .storage
         .user_repository
@@ -1,21 +1,46 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
 use axum::{
-    extract::{ws::WebSocketUpgrade, Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::Response,
-    routing::{delete, get, post, put},
-    Json, Router,
+    body::Body,
+    extract::{ws::WebSocketUpgrade, ConnectInfo, DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Extension, Json, Router,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
+use application::repository::{
+    AuditLogQuery, OutboxRepository, PaginatedResult, PaginationParams, PublicRoomQuery,
+    RefreshTokenRepository, RoomSortOrder,
+};
 use application::services::{
-    AuthenticateUserRequest, CreateRoomRequest, DeleteRoomRequest, InviteMemberRequest,
-    LeaveRoomRequest, RegisterUserRequest, RemoveMemberRequest, SendMessageRequest,
-    UpdateRoomRequest,
+    AuthenticateUserRequest, BanMemberRequest, BulkDeleteMessagesRequest, CreateInvitationRequest,
+    CreateInviteRequest, CreateRoomRequest, DeleteMessageRequest, DeleteRoomRequest,
+    EditMessageRequest, InviteMemberRequest, JoinRoomRequest, LeaveRoomRequest, MarkReadRequest,
+    MarkRoomsReadRequest, MemberRestrictionRequest, MessageThread, MuteMemberRequest,
+    PinMessageRequest, PurgeUserContentReport, PurgeUserContentRequest, ReactionRequest,
+    RegisterUserRequest, RemoveMemberRequest, RestoreMessageRequest, RoomMemberDetails,
+    RoomSettingsChange, SendMessageRequest, TransferOwnershipRequest, UpdateMemberRoleRequest,
+    UpdateProfileRequest, UpdateRoomRequest, UploadFileRequest, UploadService,
+};
+use application::{
+    encode_export, ApplicationError, ByteStream, ExportFormat, MessageBroadcast, ReloadReport,
+};
+use domain::{
+    AuditLogEntry, ChatRoom, ChatRoomVisibility, DomainError, FileUpload, MemberRoom,
+    MentionedMessage, Message, MessageRevision, MessageType, OutboxEntry, PinnedMessage,
+    ReactionPolicy, ReactionSummary, RoomBan, RoomId, RoomInvite, RoomMember, RoomMemberListing,
+    RoomMemberRestriction, RoomRole, Timestamp, User, UserId, UserProfile,
 };
-use domain::{ChatRoom, ChatRoomVisibility, Message, MessageType, User};
 
-use crate::{error::ApiError, state::AppState, LoginResponse};
+use crate::{error::ApiError, state::AppState, DependencyFailure, LoginResponse, TokenPair};
 
 #[derive(Debug, Deserialize)]
 struct RegisterPayload {
@@ -30,6 +55,50 @@ struct LoginPayload {
     password: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct VerifyEmailQuery {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResendVerificationPayload {
+    email: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgotPasswordPayload {
+    email: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResetPasswordPayload {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RefreshTokenPayload {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangePasswordPayload {
+    current_password: String,
+    new_password: String,
+}
+
+/// 只接受资料字段，带上 username、email 等其他字段直接拒绝
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateProfilePayload {
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    avatar_url: Option<String>,
+    #[serde(default)]
+    bio: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct CreateRoomPayload {
     name: String,
@@ -39,7 +108,8 @@ struct CreateRoomPayload {
 
 #[derive(Debug, Deserialize)]
 struct JoinRoomPayload {
-    _password: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,16 +119,198 @@ struct SendMessagePayload {
     reply_to: Option<Uuid>,
 }
 
+#[derive(Debug, Deserialize)]
+struct EditMessagePayload {
+    content: String,
+    /// 编辑所基于的消息版本，用于检测并发编辑
+    #[serde(default)]
+    version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReactionPayload {
+    emoji: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MarkReadPayload {
+    message_id: Uuid,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MarkRoomsReadPayload {
+    /// 不传表示全部房间
+    room_ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Serialize)]
+struct MarkRoomsReadResponse {
+    /// 已读位置有前进的房间
+    room_ids: Vec<RoomId>,
+}
+
+#[derive(Debug, Serialize)]
+struct UnreadCountResponse {
+    unread: i64,
+}
+
 #[derive(Debug, Deserialize)]
 struct HistoryQuery {
     before: Option<Uuid>,
     limit: Option<u32>,
+    #[serde(default)]
+    format: HistoryFormat,
+}
+
+/// 历史消息响应格式；`legacy` 返回旧版的裸数组，兼容尚未升级的客户端
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum HistoryFormat {
+    #[default]
+    Page,
+    Legacy,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomDiscoveryQuery {
+    search: Option<String>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    #[serde(default)]
+    sort: RoomSortOrder,
+    #[serde(default)]
+    include_closed: bool,
+    /// 只列出自己创建的房间（不限可见性），不走公开发现
+    #[serde(default)]
+    owned: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreadQuery {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkDeletePayload {
+    message_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+struct BulkDeleteResponse {
+    deleted: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PurgeUserPayload {
+    /// 同时移出所有房间并停用账号
+    #[serde(default)]
+    ban: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct MentionsQuery {
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberListQuery {
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SettingsHistoryQuery {
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeadLetterQuery {
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    /// RFC 3339 时间，含
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    from: Option<Timestamp>,
+    /// RFC 3339 时间，不含
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    to: Option<Timestamp>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditLogParams {
+    actor: Option<Uuid>,
+    action: Option<String>,
+    /// RFC 3339 时间，含
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    from: Option<Timestamp>,
+    /// RFC 3339 时间，不含
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    to: Option<Timestamp>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct UploadResponse {
+    #[serde(flatten)]
+    upload: FileUpload,
+    /// 下载地址：存储支持时是有时效的直接地址，否则是 `/api/v1/uploads/{id}`
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlocksQuery {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlowModePayload {
+    /// 每个用户两条消息之间的最小间隔（秒），0 表示关闭慢速模式
+    interval_secs: u64,
 }
 
 #[derive(Debug, Deserialize)]
 struct InviteMemberPayload {
     invitee_id: Uuid, // 被邀请用户的ID
     password: Option<String>,
+    /// 直接把用户加进房间，不经过邀请确认（旧行为）
+    #[serde(default)]
+    auto_accept: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CreateInvitePayload {
+    /// 最多可使用的次数，不传表示不限
+    max_uses: Option<u32>,
+    /// 多少秒后过期，不传表示永不过期
+    expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BanMemberPayload {
+    user_id: Uuid,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MuteMemberPayload {
+    /// 禁言时长（秒）
+    duration_secs: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,18 +318,69 @@ struct RemoveMemberPayload {
     _user_id: Uuid, // 要踢出的用户ID
 }
 
+/// 可通过接口分配的角色；Owner 不在其中
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AssignableRole {
+    Admin,
+    Member,
+}
+
+impl From<AssignableRole> for RoomRole {
+    fn from(role: AssignableRole) -> Self {
+        match role {
+            AssignableRole::Admin => RoomRole::Admin,
+            AssignableRole::Member => RoomRole::Member,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateMemberRolePayload {
+    role: AssignableRole,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferOwnershipPayload {
+    new_owner_id: Uuid,
+}
+
 #[derive(Debug, Deserialize)]
 struct UpdateRoomPayload {
     name: Option<String>,
     visibility: Option<ChatRoomVisibility>,
     password: Option<String>,
+    reaction_policy: Option<ReactionPolicy>,
 }
 
 pub fn router(state: AppState) -> Router {
-    Router::new()
+    let api = api_routes();
+    #[cfg(feature = "graphql")]
+    let api = api.merge(crate::graphql::graphql_routes());
+
+    let mut app = Router::new()
         .route("/health", get(health))
-        .nest("/api/v1", api_routes())
-        .with_state(state)
+        .route("/ready", get(ready))
+        .route("/health/live", get(health))
+        .route("/health/ready", get(health_ready));
+    // 指标接口不鉴权，只在配置开启时挂载
+    if state.metrics.is_some() {
+        app = app.route("/metrics", get(crate::metrics::metrics_handler));
+    }
+
+    let cors = state.cors.clone();
+    let app = app
+        .nest("/api/v1", api)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::http_rate_limit::limit_by_client_ip,
+        ))
+        .with_state(state);
+    // 放在最外层，预检请求不经过限流和鉴权
+    match cors {
+        Some(cors) => app.layer(cors),
+        None => app,
+    }
 }
 
 fn api_routes() -> Router<AppState> {
@@ -85,25 +388,136 @@ fn api_routes() -> Router<AppState> {
         // 不需要认证的路由
         .route("/auth/register", post(register_user))
         .route("/auth/login", post(login_user))
+        .route("/auth/refresh", post(refresh_token))
         .route("/auth/logout", post(logout_user))
+        .route("/auth/verify", get(verify_email))
+        .route("/auth/resend-verification", post(resend_verification))
+        .route("/auth/forgot-password", post(forgot_password))
+        .route("/auth/reset-password", post(reset_password))
         // 需要认证的路由
-        .route("/rooms", post(create_room))
+        .route("/rooms", get(list_public_rooms).post(create_room))
         // 修改：邀请用户加入房间（替代join_room）
-        .route("/rooms/{room_id}/members", post(invite_member))
+        .route(
+            "/rooms/{room_id}/members",
+            get(list_members).post(invite_member),
+        )
+        .route("/rooms/{room_id}/invites", post(create_invite))
+        .route(
+            "/rooms/{room_id}/transfer-ownership",
+            post(transfer_ownership),
+        )
         // 新增：管理路由
-        .route("/rooms/{room_id}/members/{user_id}", delete(remove_member))
+        .route(
+            "/rooms/{room_id}/members/{user_id}",
+            get(get_member).delete(remove_member),
+        )
+        .route(
+            "/rooms/{room_id}/members/{user_id}/role",
+            put(update_member_role),
+        )
+        .route("/rooms/{room_id}/members/{user_id}/mute", post(mute_member))
+        .route(
+            "/rooms/{room_id}/members/{user_id}/unmute",
+            post(unmute_member),
+        )
+        .route("/rooms/{room_id}/bans", get(list_bans).post(ban_member))
+        .route("/rooms/{room_id}/bans/{user_id}", delete(unban_member))
+        // 旧路径，保留兼容
+        .route(
+            "/rooms/{room_id}/members/{user_id}/ban",
+            post(ban_member_by_path),
+        )
+        .route(
+            "/rooms/{room_id}/members/{user_id}/unban",
+            post(unban_member),
+        )
         .route("/rooms/{room_id}", put(update_room).delete(delete_room))
+        .route("/rooms/{room_id}/join", post(join_room))
         .route("/rooms/{room_id}/leave", post(leave_room))
+        .route("/rooms/{room_id}/slowmode", put(set_slow_mode))
+        .route("/rooms/{room_id}/encryption", post(enable_room_encryption))
         .route(
             "/rooms/{room_id}/messages",
             post(send_message).get(get_history),
         )
+        .route("/rooms/{room_id}/messages/search", get(search_messages))
+        .route("/rooms/{room_id}/export", get(export_room_messages))
+        .route(
+            "/rooms/{room_id}/messages/bulk-delete",
+            post(bulk_delete_messages),
+        )
+        .route(
+            "/rooms/{room_id}/messages/{message_id}",
+            patch(edit_message).delete(delete_message),
+        )
+        .route("/rooms/{room_id}/pins", get(list_pinned_messages))
+        .route(
+            "/rooms/{room_id}/messages/{message_id}/pin",
+            post(pin_message).delete(unpin_message),
+        )
+        .route("/messages/{message_id}/thread", get(get_thread))
+        .route("/messages/{message_id}/restore", post(restore_message))
+        .route(
+            "/messages/{message_id}/revisions",
+            get(get_message_revisions),
+        )
+        .route("/messages/{message_id}/reactions", post(add_reaction))
+        .route(
+            "/messages/{message_id}/reactions/{emoji}",
+            delete(remove_reaction),
+        )
+        .route("/rooms/unread-counts", get(get_unread_counts))
+        .route("/rooms/{room_id}/read", post(mark_read))
+        .route("/rooms/{room_id}/unread", get(get_unread_count))
         .route("/rooms/{room_id}/online", get(get_online_users)) // 新增：获取房间在线用户
+        .route("/rooms/{room_id}/presence", get(get_room_presence))
+        .route("/users/me", patch(update_my_profile))
+        .route("/users/me/password", post(change_password))
+        .route("/users/me/mentions", get(list_mentions))
+        .route("/users/{user_id}", get(get_user_profile))
+        .route("/me/mentions", get(list_mentions))
+        .route("/me/rooms", get(list_my_rooms))
+        .route("/me/rooms/read", post(mark_rooms_read))
+        .route("/me/rooms/{room_id}/pin", put(pin_room).delete(unpin_room))
+        .route("/me/blocks", get(list_blocks))
+        .route("/me/blocks/{user_id}", put(block_user).delete(unblock_user))
+        .route(
+            "/users/{user_id}/block",
+            post(block_user).delete(unblock_user),
+        )
         .route("/ws", get(websocket_upgrade))
+        .route("/admin/config/reload", post(reload_config))
+        .route("/admin/users/{user_id}/purge", post(purge_user_content))
+        .route("/admin/users/{user_id}/suspend", post(suspend_user))
+        .route("/admin/users/{user_id}/activate", post(activate_user))
+        .route(
+            "/admin/users/{user_id}/superuser",
+            put(grant_superuser).delete(revoke_superuser),
+        )
+        .route("/admin/audit", get(search_audit_log))
+        .route("/admin/audit-log", get(search_audit_log))
+        .route("/admin/outbox/dead-letters", get(list_outbox_dead_letters))
+        .route(
+            "/admin/outbox/{outbox_id}/requeue",
+            post(requeue_outbox_entry),
+        )
+        // 上传大小由上传服务边写边检查，不受默认请求体上限约束
+        .route(
+            "/uploads",
+            post(upload_file).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/uploads/{file_id}", get(download_file))
+        .route(
+            "/admin/rooms/{room_id}/settings-history",
+            get(get_room_settings_history),
+        )
         // 新增：组织管理路由
         .nest("/organizations", crate::org_routes())
         // 新增：批量用户管理路由
         .nest("/users", crate::bulk_user_routes())
+        .nest("/notifications", crate::notification_routes())
+        .nest("/invitations", crate::invitation_routes())
+        .nest("/invites", crate::invite_routes())
         // 新增：统计查询路由
         .nest("/stats", crate::stats_routes())
 }
@@ -112,6 +526,43 @@ async fn health() -> StatusCode {
     StatusCode::OK
 }
 
+// 就绪探针：关键依赖就绪前和停机过程中返回 503
+async fn ready(State(state): State<AppState>) -> StatusCode {
+    if state.readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReadinessReport {
+    ready: bool,
+    failed: Vec<DependencyFailure>,
+}
+
+// 依赖就绪探针：实际检查数据库和 Redis，任一不可用或正在停机时返回 503 并列出失败的依赖
+async fn health_ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessReport>) {
+    let mut failed = Vec::new();
+    if !state.readiness.is_ready() {
+        failed.push(DependencyFailure {
+            dependency: "server",
+            error: "not accepting traffic".to_string(),
+        });
+    }
+    if let Some(probe) = &state.health_probe {
+        failed.extend(probe.check().await);
+    }
+
+    let ready = failed.is_empty();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ReadinessReport { ready, failed }))
+}
+
 async fn register_user(
     State(state): State<AppState>,
     Json(payload): Json<RegisterPayload>,
@@ -128,6 +579,71 @@ async fn register_user(
     Ok((StatusCode::CREATED, Json(user)))
 }
 
+// 邮箱验证链接：令牌有效时激活账号
+async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Json<User>, ApiError> {
+    let user = state.user_service.verify_email(&query.token).await?;
+    Ok(Json(user))
+}
+
+// 重发验证邮件；邮箱不存在或已验证时同样返回 202
+async fn resend_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationPayload>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .user_service
+        .resend_verification(payload.email)
+        .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// 申请重置密码：按 IP 和邮箱分别限流；邮箱是否存在都返回 202
+async fn forgot_password(
+    headers: HeaderMap,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordPayload>,
+) -> Result<StatusCode, ApiError> {
+    let client_ip = state
+        .http_rate_limiter
+        .client_ip(&headers, connect_info.as_ref().map(|info| info.0 .0));
+    let email = payload.email.trim().to_ascii_lowercase();
+
+    state
+        .rate_limiter
+        .check_password_reset_request(&format!("ip:{client_ip}"))
+        .await?;
+    state
+        .rate_limiter
+        .check_password_reset_request(&format!("email:{email}"))
+        .await?;
+
+    state.user_service.request_password_reset(email).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// 用邮件里的令牌设置新密码，随后退出该用户的全部会话
+async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordPayload>,
+) -> Result<StatusCode, ApiError> {
+    let user = state
+        .user_service
+        .reset_password(&payload.token, &payload.new_password)
+        .await?;
+
+    state
+        .storage
+        .refresh_token_repository
+        .revoke_all_for_user(user.id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 async fn login_user(
     State(state): State<AppState>,
     Json(payload): Json<LoginPayload>,
@@ -140,18 +656,61 @@ async fn login_user(
         })
         .await?;
 
-    // 生成 JWT token
-    let token = state.jwt_service.generate_token(user.id.into())?;
+    // 签发访问令牌 + 刷新令牌
+    let pair = state
+        .jwt_service
+        .issue_token_pair(
+            state.storage.refresh_token_repository.as_ref(),
+            user.id.into(),
+            user.token_version,
+        )
+        .await?;
 
-    let response = LoginResponse { user, token };
+    let response = LoginResponse {
+        user,
+        token: pair.access_token,
+        refresh_token: pair.refresh_token,
+        expires_in: pair.expires_in,
+    };
     Ok(Json(response))
 }
 
+// 使用刷新令牌换取新的令牌对（旧刷新令牌随即失效）
+async fn refresh_token(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenPayload>,
+) -> Result<Json<TokenPair>, ApiError> {
+    let pair = state
+        .jwt_service
+        .refresh(
+            state.storage.refresh_token_repository.as_ref(),
+            &payload.refresh_token,
+        )
+        .await?;
+
+    Ok(Json(pair))
+}
+
+// 登出：清理在线状态；带上刷新令牌时一并吊销，之后无法再用它换取新令牌
 async fn logout_user(
     headers: HeaderMap,
     State(state): State<AppState>,
+    payload: Option<Json<RefreshTokenPayload>>,
 ) -> Result<StatusCode, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    if let Some(Json(payload)) = payload {
+        state
+            .jwt_service
+            .revoke_refresh_token(
+                state.storage.refresh_token_repository.as_ref(),
+                user_id,
+                &payload.refresh_token,
+            )
+            .await?;
+    }
     state.user_service.logout(user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -161,7 +720,10 @@ async fn create_room(
     State(state): State<AppState>,
     Json(payload): Json<CreateRoomPayload>,
 ) -> Result<(StatusCode, Json<ChatRoom>), ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     let room = state
         .chat_service
@@ -176,14 +738,106 @@ async fn create_room(
     Ok((StatusCode::CREATED, Json(room)))
 }
 
+// 公开房间发现（私有房间永远不返回）；`owned=true` 时列出自己创建的房间
+async fn list_public_rooms(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<RoomDiscoveryQuery>,
+) -> Result<Response, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    if query.owned {
+        let rooms = state
+            .chat_service
+            .list_owned_rooms(user_id, page, page_size)
+            .await?;
+        return Ok(Json(rooms).into_response());
+    }
+
+    let rooms = state
+        .chat_service
+        .discover_rooms(
+            user_id,
+            PublicRoomQuery {
+                search: query.search,
+                sort: query.sort,
+                include_closed: query.include_closed,
+                page,
+                page_size,
+            },
+        )
+        .await?;
+
+    Ok(Json(rooms).into_response())
+}
+
+// 凭密码自助加入房间（公开房间不需要密码）
+async fn join_room(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<JoinRoomPayload>,
+) -> Result<(StatusCode, Json<RoomMember>), ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let limited_user = UserId::from(user_id);
+
+    // 密码错误次数用完之前不再校验密码，防止暴力破解
+    state.rate_limiter.check_join_attempts(limited_user).await?;
+
+    let result = state
+        .chat_service
+        .join_room(JoinRoomRequest {
+            room_id,
+            user_id,
+            password: payload.password,
+        })
+        .await;
+
+    match result {
+        Ok(member) => {
+            state.rate_limiter.clear_join_attempts(limited_user).await?;
+            Ok((StatusCode::CREATED, Json(member)))
+        }
+        Err(err @ ApplicationError::Domain(DomainError::InvalidRoomPassword)) => {
+            let remaining = state.rate_limiter.record_failed_join(limited_user).await?;
+            Err(ApiError::from(err).with_remaining_attempts(remaining))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 // 邀请用户加入房间（替代join_room）
 async fn invite_member(
     headers: HeaderMap, // 从请求头获取JWT
     State(state): State<AppState>,
     Path(room_id): Path<Uuid>,
     Json(payload): Json<InviteMemberPayload>,
-) -> Result<StatusCode, ApiError> {
-    let inviter_id = state.jwt_service.extract_user_from_headers(&headers)?;
+) -> Result<Response, ApiError> {
+    let inviter_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    // 默认发出邀请，被邀请人接受后才加入；自助加入和 auto_accept 直接加入
+    if !payload.auto_accept && payload.invitee_id != inviter_id {
+        let invitation = state
+            .chat_service
+            .invite(CreateInvitationRequest {
+                room_id,
+                inviter_id,
+                invitee_id: payload.invitee_id,
+            })
+            .await?;
+        return Ok((StatusCode::CREATED, Json(invitation)).into_response());
+    }
 
     state
         .chat_service
@@ -195,7 +849,33 @@ async fn invite_member(
         })
         .await?;
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// 创建可分享的邀请链接（只有owner和admin可以）
+async fn create_invite(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    payload: Option<Json<CreateInvitePayload>>,
+) -> Result<(StatusCode, Json<RoomInvite>), ApiError> {
+    let creator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let Json(payload) = payload.unwrap_or_default();
+
+    let invite = state
+        .chat_service
+        .create_invite(CreateInviteRequest {
+            room_id,
+            creator_id,
+            max_uses: payload.max_uses,
+            expires_in_secs: payload.expires_in_secs,
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(invite)))
 }
 
 async fn leave_room(
@@ -203,7 +883,10 @@ async fn leave_room(
     State(state): State<AppState>,
     Path(room_id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     state
         .chat_service
@@ -216,13 +899,32 @@ async fn leave_room(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// 客户端重试发送消息时携带的幂等键请求头
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
 async fn send_message(
     headers: HeaderMap, // 从请求头获取JWT
     State(state): State<AppState>,
     Path(room_id): Path<Uuid>,
     Json(payload): Json<SendMessagePayload>,
 ) -> Result<Json<Message>, ApiError> {
-    let user_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    // 非 ASCII 的头无法作为幂等键，按格式错误处理
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|value| {
+            value.to_str().map(str::to_owned).map_err(|_| {
+                ApplicationError::from(DomainError::invalid_argument(
+                    "idempotency_key",
+                    "must contain only visible ASCII characters",
+                ))
+            })
+        })
+        .transpose()?;
 
     let message = state
         .chat_service
@@ -232,33 +934,994 @@ async fn send_message(
             content: payload.content,
             message_type: payload.message_type,
             reply_to: payload.reply_to,
+            idempotency_key,
         })
         .await?;
 
     Ok(Json(message))
 }
 
-async fn get_history(
-    headers: HeaderMap, // 需要身份验证才能查看历史
+// 编辑消息（发送者在时间窗口内，或房间owner/admin）
+async fn edit_message(
+    headers: HeaderMap,
     State(state): State<AppState>,
-    Path(room_id): Path<Uuid>,
-    Query(query): Query<HistoryQuery>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<EditMessagePayload>,
+) -> Result<Json<Message>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let message = state
+        .chat_service
+        .edit_message(EditMessageRequest {
+            room_id,
+            message_id,
+            operator_id,
+            content: payload.content,
+            expected_version: payload.version,
+        })
+        .await?;
+
+    Ok(Json(message))
+}
+
+async fn delete_message(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .delete_message(DeleteMessageRequest {
+            room_id,
+            message_id,
+            operator_id,
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 批量删除房间内的消息（owner/admin），用于清理刷屏
+async fn bulk_delete_messages(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<BulkDeletePayload>,
+) -> Result<Json<BulkDeleteResponse>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let deleted = state
+        .chat_service
+        .bulk_delete_messages(BulkDeleteMessagesRequest {
+            room_id,
+            message_ids: payload.message_ids,
+            operator_id,
+        })
+        .await?;
+
+    Ok(Json(BulkDeleteResponse { deleted }))
+}
+
+// 恢复误删的消息（owner/admin）
+async fn restore_message(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+) -> Result<Json<Message>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let message = state
+        .chat_service
+        .restore_message(RestoreMessageRequest {
+            message_id,
+            operator_id,
+        })
+        .await?;
+
+    Ok(Json(message))
+}
+
+// 置顶消息（房间 Owner/Admin）
+async fn pin_message(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PinnedMessage>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let pinned = state
+        .chat_service
+        .pin_message(PinMessageRequest {
+            room_id,
+            message_id,
+            operator_id,
+        })
+        .await?;
+
+    Ok(Json(pinned))
+}
+
+async fn unpin_message(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .unpin_message(PinMessageRequest {
+            room_id,
+            message_id,
+            operator_id,
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_pinned_messages(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Vec<PinnedMessage>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let pinned = state
+        .chat_service
+        .list_pinned_messages(room_id, user_id)
+        .await?;
+
+    Ok(Json(pinned))
+}
+
+// 消息的完整编辑历史，只有发送者和房间管理员可以查看
+async fn get_message_revisions(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+) -> Result<Json<Vec<MessageRevision>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let revisions = state
+        .chat_service
+        .message_revisions(message_id, user_id)
+        .await?;
+
+    Ok(Json(revisions))
+}
+
+async fn add_reaction(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    Json(payload): Json<ReactionPayload>,
+) -> Result<Json<Vec<ReactionSummary>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let reactions = state
+        .chat_service
+        .add_reaction(ReactionRequest {
+            message_id,
+            user_id,
+            emoji: payload.emoji,
+        })
+        .await?;
+
+    Ok(Json(reactions))
+}
+
+async fn remove_reaction(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((message_id, emoji)): Path<(Uuid, String)>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .remove_reaction(ReactionRequest {
+            message_id,
+            user_id,
+            emoji,
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn mark_read(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<MarkReadPayload>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .mark_read(MarkReadRequest {
+            room_id,
+            user_id,
+            message_id: payload.message_id,
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_unread_count(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<UnreadCountResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let unread = state.chat_service.unread_count(room_id, user_id).await?;
+
+    Ok(Json(UnreadCountResponse { unread }))
+}
+
+async fn get_unread_counts(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<HashMap<Uuid, i64>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let counts = state.chat_service.unread_counts(user_id).await?;
+
+    Ok(Json(
+        counts
+            .into_iter()
+            .map(|(room_id, unread)| (Uuid::from(room_id), unread))
+            .collect(),
+    ))
+}
+
+async fn get_history(
+    headers: HeaderMap, // 需要身份验证才能查看历史
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Response, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let limit = query.limit.unwrap_or(50).min(100);
+    let page = state
+        .chat_service
+        .get_history(room_id, user_id, limit, query.before)
+        .await?;
+
+    Ok(match query.format {
+        HistoryFormat::Page => Json(page).into_response(),
+        HistoryFormat::Legacy => Json(page.items).into_response(),
+    })
+}
+
+async fn search_messages(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<SearchQuery>,
 ) -> Result<Json<Vec<Message>>, ApiError> {
-    let _user_id = state.jwt_service.extract_user_from_headers(&headers)?; // 验证身份但不使用
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let limit = query.limit.unwrap_or(20).min(100);
+    let items = state
+        .chat_service
+        .search_messages(room_id, user_id, &query.q, limit)
+        .await?;
+
+    Ok(Json(items))
+}
+
+// 为房间开启消息静态加密（仅房间owner/admin），开启后不能关闭
+async fn enable_room_encryption(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<ChatRoom>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let room = state
+        .chat_service
+        .enable_room_encryption(room_id, operator_id)
+        .await?;
+
+    Ok(Json(room))
+}
+
+// 设置房间慢速模式（仅房间owner/admin）
+async fn set_slow_mode(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<SlowModePayload>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let room_id = RoomId::from(room_id);
+
+    state
+        .chat_service
+        .check_admin_access(UserId::from(user_id), Some(room_id))
+        .await?;
+
+    state
+        .rate_limiter
+        .set_slow_mode(room_id, Duration::from_secs(payload.interval_secs))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 重新加载可热更新的配置（仅系统管理员），新配置校验失败时保留原配置
+async fn reload_config(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<ReloadReport>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .check_admin_access(UserId::from(user_id), None)
+        .await?;
+
+    let reloader = state
+        .config_reloader
+        .as_ref()
+        .ok_or_else(|| ApiError::not_implemented("config reload is not enabled"))?;
+
+    let report = reloader.reload().await.map_err(|err| {
+        ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_CONFIG",
+            err.to_string(),
+        )
+    })?;
+
+    Ok(Json(report))
+}
+
+// 清除用户在所有房间的内容，只有系统管理员可以
+async fn purge_user_content(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+    payload: Option<Json<PurgeUserPayload>>,
+) -> Result<Json<PurgeUserContentReport>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    let report = state
+        .chat_service
+        .purge_user_content(PurgeUserContentRequest {
+            operator_id: user_id,
+            user_id: target_user_id,
+            ban: payload.ban,
+        })
+        .await?;
+    if report.banned {
+        end_user_sessions(&state, UserId::from(target_user_id)).await?;
+    }
+
+    Ok(Json(report))
+}
+
+// 停用账号，立即生效：吊销刷新令牌，断开该用户的所有 WebSocket 连接
+async fn suspend_user(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+) -> Result<Json<User>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let user = state
+        .chat_service
+        .suspend_user(user_id, target_user_id)
+        .await?;
+    end_user_sessions(&state, user.id).await?;
+
+    Ok(Json(user))
+}
+
+// 重新启用被停用的账号，用户需要重新登录
+async fn activate_user(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+) -> Result<Json<User>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let user = state
+        .chat_service
+        .activate_user(user_id, target_user_id)
+        .await?;
+
+    Ok(Json(user))
+}
+
+// 授予系统管理员权限，对方下次请求时按新权限鉴权
+async fn grant_superuser(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+) -> Result<Json<User>, ApiError> {
+    set_superuser(headers, state, target_user_id, true).await
+}
+
+// 撤销系统管理员权限
+async fn revoke_superuser(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+) -> Result<Json<User>, ApiError> {
+    set_superuser(headers, state, target_user_id, false).await
+}
+
+async fn set_superuser(
+    headers: HeaderMap,
+    state: AppState,
+    target_user_id: Uuid,
+    superuser: bool,
+) -> Result<Json<User>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let user = state
+        .chat_service
+        .set_superuser(user_id, target_user_id, superuser)
+        .await?;
+
+    Ok(Json(user))
+}
+
+// 管理操作审计日志，可按操作人、操作类型和时间范围过滤（只有系统管理员可以）
+async fn search_audit_log(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(params): Query<AuditLogParams>,
+) -> Result<Json<PaginatedResult<AuditLogEntry>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let query = AuditLogQuery {
+        actor_id: params.actor.map(UserId::from),
+        action: params.action.filter(|action| !action.is_empty()),
+        from: params.from,
+        to: params.to,
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(20).clamp(1, 100),
+    };
+    let entries = state.chat_service.search_audit_log(user_id, query).await?;
+
+    Ok(Json(entries))
+}
+
+// 列出广播重试用尽的发件箱记录（仅系统管理员），最早的在前
+async fn list_outbox_dead_letters(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Vec<OutboxEntry>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    state
+        .chat_service
+        .check_admin_access(UserId::from(user_id), None)
+        .await?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+    let entries = state
+        .storage
+        .outbox_repository
+        .list_dead_letters(PaginationParams::with_offset(
+            i64::from(page_size),
+            i64::from((page - 1) * page_size),
+        ))
+        .await
+        .map_err(ApplicationError::from)?;
+
+    Ok(Json(entries))
+}
+
+// 死信重新入队（仅系统管理员），分发任务随即重新发布
+async fn requeue_outbox_entry(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(outbox_id): Path<i64>,
+) -> Result<Json<OutboxEntry>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    state
+        .chat_service
+        .check_admin_access(UserId::from(user_id), None)
+        .await?;
+
+    let entry = state
+        .storage
+        .outbox_repository
+        .requeue(outbox_id, time::OffsetDateTime::now_utc())
+        .await
+        .map_err(ApplicationError::from)?
+        .ok_or_else(|| ApiError::not_found("dead-lettered outbox entry not found"))?;
+
+    Ok(Json(entry))
+}
+
+fn upload_service(state: &AppState) -> Result<&Arc<UploadService>, ApiError> {
+    state
+        .upload_service
+        .as_ref()
+        .ok_or_else(|| ApiError::not_implemented("file upload is not enabled"))
+}
+
+// 上传文件（multipart 的 `file` 字段），返回的 id 作为图片/文件消息的内容发送
+async fn upload_file(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadResponse>), ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let upload_service = upload_service(&state)?;
+
+    let mut field = loop {
+        let field = multipart
+            .next_field()
+            .await
+            .map_err(|err| ApiError::bad_request(err.body_text()))?
+            .ok_or_else(|| ApiError::bad_request("missing file field"))?;
+        if field.name() == Some("file") {
+            break field;
+        }
+    };
+    let request = UploadFileRequest {
+        uploader_id: user_id,
+        file_name: field.file_name().unwrap_or_default().to_string(),
+        content_type: field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string(),
+    };
+
+    // 字段借用着 multipart，不能直接交给存储；经通道转发，上传中止时接收端关闭，转发随之结束
+    let (sender, receiver) = tokio::sync::mpsc::channel(4);
+    let forward = async move {
+        loop {
+            let chunk = match field.chunk().await {
+                Ok(Some(chunk)) => Ok(chunk),
+                Ok(None) => break,
+                Err(err) => Err(std::io::Error::other(err.body_text())),
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    };
+    let body: ByteStream = Box::pin(ReceiverStream::new(receiver));
+    let (upload, ()) = tokio::join!(upload_service.upload(request, body), forward);
+    let upload = upload?;
+    let url = upload_service.download_url(&upload).await?;
+
+    Ok((StatusCode::CREATED, Json(UploadResponse { upload, url })))
+}
+
+// 下载文件，只有上传者和引用它的消息所在房间的成员可以下载
+async fn download_file(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let (upload, body) = upload_service(&state)?.download(user_id, file_id).await?;
+
+    // 图片内联显示，其他文件作为附件下载
+    let disposition = if upload.is_image() {
+        "inline"
+    } else {
+        "attachment"
+    };
+    let file_name = upload.file_name.replace(['"', '\\'], "_");
+    let headers = [
+        (header::CONTENT_TYPE, upload.content_type.clone()),
+        (header::CONTENT_LENGTH, upload.size_bytes.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("{}; filename=\"{}\"", disposition, file_name),
+        ),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((headers, Body::from_stream(body)).into_response())
+}
+
+/// 结束被停用用户的所有会话：吊销刷新令牌、
+/// 通知该用户在线的每个房间关闭他的连接（经广播到达所有实例）
+async fn end_user_sessions(state: &AppState, user_id: UserId) -> Result<(), ApiError> {
+    state
+        .storage
+        .refresh_token_repository
+        .revoke_all_for_user(user_id)
+        .await?;
+
+    let rooms = state.presence_manager.get_user_rooms(user_id).await?;
+    for room_id in rooms {
+        let broadcast = MessageBroadcast::session_revoked(room_id, user_id, "ACCOUNT_SUSPENDED");
+        if let Err(err) = state.broadcaster.broadcast(broadcast).await {
+            tracing::warn!(error = %err, %user_id, %room_id, "广播强制下线失败");
+        }
+    }
+    Ok(())
+}
+
+// 房间设置修改历史（谁、何时、改前改后），密码修改只显示为已脱敏
+async fn get_room_settings_history(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<SettingsHistoryQuery>,
+) -> Result<Json<PaginatedResult<RoomSettingsChange>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let history = state
+        .chat_service
+        .room_settings_history(room_id, user_id, page, page_size)
+        .await?;
+
+    Ok(Json(history))
+}
+
+// 导出房间聊天记录（房间owner或系统管理员），边查库边分块发送，不把整个房间读进内存
+async fn export_room_messages(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let rows = state
+        .chat_service
+        .export_messages(room_id, user_id, query.from, query.to)
+        .await?;
+    let body = encode_export(rows, query.format);
+
+    // 不带 Content-Length，按 chunked 方式发送
+    let headers = [
+        (
+            header::CONTENT_TYPE,
+            query.format.content_type().to_string(),
+        ),
+        (
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"room-{}.{}\"",
+                room_id,
+                query.format.file_extension()
+            ),
+        ),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((headers, Body::from_stream(body)).into_response())
+}
+
+// 提及我的消息收件箱，跨所有仍在的房间
+async fn list_mentions(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<MentionsQuery>,
+) -> Result<Json<PaginatedResult<MentionedMessage>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+    let mentions = state
+        .chat_service
+        .list_mentions(user_id, page, page_size)
+        .await?;
+
+    Ok(Json(mentions))
+}
+
+async fn get_user_profile(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserProfile>, ApiError> {
+    state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let profile = state.user_service.get_profile(user_id).await?;
+
+    Ok(Json(profile))
+}
+
+async fn update_my_profile(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(payload): Json<UpdateProfilePayload>,
+) -> Result<Json<User>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let user = state
+        .user_service
+        .update_profile(UpdateProfileRequest {
+            user_id,
+            display_name: payload.display_name,
+            avatar_url: payload.avatar_url,
+            bio: payload.bio,
+        })
+        .await?;
+
+    Ok(Json(user))
+}
+
+// 修改密码：所有已签发的令牌（包括其他设备上的会话）随即失效，
+// 为当前客户端签发新的令牌对
+async fn change_password(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(payload): Json<ChangePasswordPayload>,
+) -> Result<Json<TokenPair>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let user = state
+        .user_service
+        .change_password(user_id, &payload.current_password, &payload.new_password)
+        .await?;
+
+    let refresh_tokens = state.storage.refresh_token_repository.as_ref();
+    refresh_tokens.revoke_all_for_user(user.id).await?;
+
+    let pair = state
+        .jwt_service
+        .issue_token_pair(refresh_tokens, user_id, user.token_version)
+        .await?;
+
+    Ok(Json(pair))
+}
+
+// 我所在的房间：我置顶的在前，其余按最近活动倒序
+async fn list_my_rooms(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MemberRoom>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let rooms = state.chat_service.list_my_rooms(user_id).await?;
+
+    Ok(Json(rooms))
+}
+
+// 批量标记已读（清除所有角标），不传房间列表时标记全部房间
+async fn mark_rooms_read(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    payload: Option<Json<MarkRoomsReadPayload>>,
+) -> Result<Json<MarkRoomsReadResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    let room_ids = state
+        .chat_service
+        .mark_rooms_read(MarkRoomsReadRequest {
+            user_id,
+            room_ids: payload.room_ids,
+        })
+        .await?;
+
+    Ok(Json(MarkRoomsReadResponse { room_ids }))
+}
+
+async fn pin_room(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state.chat_service.pin_room(room_id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unpin_room(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state.chat_service.unpin_room(room_id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_blocks(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(query): Query<BlocksQuery>,
+) -> Result<Json<Vec<UserProfile>>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     let limit = query.limit.unwrap_or(50).min(100);
     let items = state
         .chat_service
-        .get_history(room_id, limit, query.before)
+        .list_blocked_users(user_id, limit, query.offset.unwrap_or(0))
         .await?;
 
     Ok(Json(items))
 }
 
+async fn block_user(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(blocked_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state.chat_service.block_user(user_id, blocked_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unblock_user(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(blocked_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state.chat_service.unblock_user(user_id, blocked_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_thread(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    Query(query): Query<ThreadQuery>,
+) -> Result<Json<MessageThread>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let limit = query.limit.unwrap_or(50).min(100);
+    let thread = state
+        .chat_service
+        .get_thread(message_id, user_id, limit, query.offset.unwrap_or(0))
+        .await?;
+
+    Ok(Json(thread))
+}
+
 #[derive(Debug, Deserialize)]
 struct WsQuery {
     room_id: Uuid,
     token: Option<String>, // 通过查询参数传递JWT token
+    /// 重连时客户端最后收到的消息ID，服务端据此补发断线期间的消息
+    last_message_id: Option<Uuid>,
 }
 
 async fn websocket_upgrade(
@@ -269,16 +1932,29 @@ async fn websocket_upgrade(
     // 从查询参数或Authorization header获取用户ID
     let user_id = if let Some(token) = query.token {
         // 从查询参数中的token验证用户
-        state.jwt_service.verify_token(&token)?.user_id
+        state.jwt_service.verify_token(&token).await?.user_id
     } else {
         return Err(ApiError::unauthorized(
             "Missing JWT token in query parameter",
         ));
     };
 
+    // 开启 chatroom.deflate 子协议时，客户端带上它即选中
+    let ws = if state.ws_deflate_subprotocol.enabled {
+        ws.protocols([crate::DEFLATE_SUBPROTOCOL])
+    } else {
+        ws
+    };
+
     Ok(ws.on_upgrade(move |socket| async move {
-        match crate::ws_connection::WebSocketConnection::new(socket, state, user_id, query.room_id)
-            .await
+        match crate::ws_connection::WebSocketConnection::new(
+            socket,
+            state,
+            user_id,
+            query.room_id,
+            query.last_message_id,
+        )
+        .await
         {
             Ok(connection) => connection.run().await,
             Err(err) => {
@@ -294,7 +1970,10 @@ async fn remove_member(
     State(state): State<AppState>,
     Path((room_id, user_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode, ApiError> {
-    let operator_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     state
         .chat_service
@@ -308,6 +1987,225 @@ async fn remove_member(
     Ok(StatusCode::NO_CONTENT)
 }
 
+// 禁言成员（只有owner和admin可以，不能禁言owner）
+async fn mute_member(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<MuteMemberPayload>,
+) -> Result<Json<RoomMemberRestriction>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let restriction = state
+        .chat_service
+        .mute_member(MuteMemberRequest {
+            room_id,
+            operator_id,
+            target_user_id: user_id,
+            duration_secs: payload.duration_secs,
+        })
+        .await?;
+
+    Ok(Json(restriction))
+}
+
+// 解除禁言
+async fn unmute_member(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<RoomMemberRestriction>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let restriction = state
+        .chat_service
+        .unmute_member(MemberRestrictionRequest {
+            room_id,
+            operator_id,
+            target_user_id: user_id,
+        })
+        .await?;
+
+    Ok(Json(restriction))
+}
+
+// 封禁名单（owner和admin可见）
+async fn list_bans(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<Vec<RoomBan>>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let bans = state.chat_service.list_bans(room_id, operator_id).await?;
+
+    Ok(Json(bans))
+}
+
+// 封禁用户：移出房间并禁止重新加入（踢出则可以立即重新加入）
+async fn ban_member(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<BanMemberPayload>,
+) -> Result<(StatusCode, Json<RoomBan>), ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let ban = state
+        .chat_service
+        .ban_member(BanMemberRequest {
+            room_id,
+            operator_id,
+            target_user_id: payload.user_id,
+            reason: payload.reason,
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(ban)))
+}
+
+async fn ban_member_by_path(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<RoomBan>), ApiError> {
+    ban_member(
+        headers,
+        State(state),
+        Path(room_id),
+        Json(BanMemberPayload {
+            user_id,
+            reason: None,
+        }),
+    )
+    .await
+}
+
+// 解除封禁
+async fn unban_member(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    state
+        .chat_service
+        .unban_member(MemberRestrictionRequest {
+            room_id,
+            operator_id,
+            target_user_id: user_id,
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 分页列出房间成员（房间成员可见）
+async fn list_members(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<MemberListQuery>,
+) -> Result<Json<PaginatedResult<RoomMemberListing>>, ApiError> {
+    let requester_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 100);
+
+    let members = state
+        .chat_service
+        .list_members(room_id, requester_id, page, page_size)
+        .await?;
+
+    Ok(Json(members))
+}
+
+// 查询单个成员详情（房间成员可见）
+async fn get_member(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<RoomMemberDetails>, ApiError> {
+    let requester_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let details = state
+        .chat_service
+        .get_member(room_id, requester_id, user_id)
+        .await?;
+
+    Ok(Json(details))
+}
+
+// 调整成员角色（只有owner可以）
+async fn update_member_role(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberRolePayload>,
+) -> Result<Json<RoomMember>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let member = state
+        .chat_service
+        .update_member_role(UpdateMemberRoleRequest {
+            room_id,
+            operator_id,
+            target_user_id: user_id,
+            role: payload.role.into(),
+        })
+        .await?;
+
+    Ok(Json(member))
+}
+
+// 转让房间所有权（只有owner可以）
+async fn transfer_ownership(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<TransferOwnershipPayload>,
+) -> Result<Json<ChatRoom>, ApiError> {
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+
+    let room = state
+        .chat_service
+        .transfer_ownership(TransferOwnershipRequest {
+            room_id,
+            operator_id,
+            new_owner_id: payload.new_owner_id,
+        })
+        .await?;
+
+    Ok(Json(room))
+}
+
 // 更新房间信息（只有owner和admin可以）
 async fn update_room(
     headers: HeaderMap,
@@ -315,7 +2213,10 @@ async fn update_room(
     Path(room_id): Path<Uuid>,
     Json(payload): Json<UpdateRoomPayload>,
 ) -> Result<Json<ChatRoom>, ApiError> {
-    let operator_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     let room = state
         .chat_service
@@ -325,6 +2226,7 @@ async fn update_room(
             name: payload.name,
             visibility: payload.visibility,
             password: payload.password,
+            reaction_policy: payload.reaction_policy,
         })
         .await?;
 
@@ -337,7 +2239,10 @@ async fn delete_room(
     State(state): State<AppState>,
     Path(room_id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
-    let operator_id = state.jwt_service.extract_user_from_headers(&headers)?;
+    let operator_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
 
     state
         .chat_service
@@ -350,13 +2255,52 @@ async fn delete_room(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Debug, Serialize)]
+struct RoomPresenceResponse {
+    room_id: Uuid,
+    online_count: u64,
+    user_ids: Vec<Uuid>,
+}
+
+/// 房间在线成员的初始状态，之后的变化通过 WebSocket `room_presence` 推送
+///
+/// 私有房间只对成员可见
+async fn get_room_presence(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<RoomPresenceResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    state.chat_service.get_room(room_id, user_id).await?;
+
+    let user_ids: Vec<Uuid> = state
+        .presence_manager
+        .get_online_users(RoomId::from(room_id))
+        .await?
+        .into_iter()
+        .map(Uuid::from)
+        .collect();
+
+    Ok(Json(RoomPresenceResponse {
+        room_id,
+        online_count: user_ids.len() as u64,
+        user_ids,
+    }))
+}
+
 // 获取房间在线用户列表
 async fn get_online_users(
     headers: HeaderMap, // 需要认证才能查看在线用户
     State(state): State<AppState>,
     Path(room_id): Path<Uuid>,
 ) -> Result<Json<Vec<Uuid>>, ApiError> {
-    let _user_id = state.jwt_service.extract_user_from_headers(&headers)?; // 验证身份但不使用
+    let _user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?; // 验证身份但不使用
 
     let room_id_domain = domain::RoomId::from(room_id);
 
@@ -0,0 +1,94 @@
+//! 优雅停机
+//!
+//! 收到停机信号后停止接受新连接，通知所有 WebSocket 客户端服务正在重启，
+//! 在时限内等待进行中的 HTTP 请求完成、WebSocket 连接清理完在线状态后再返回。
+
+use std::future::{Future, IntoFuture};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::ConnectionRegistry;
+
+/// 对外提供服务，直到 `signal` 完成后排空连接
+///
+/// 时限内全部排空返回 true；超时返回 false，剩下的请求和连接随进程退出被中断
+pub async fn serve_with_graceful_shutdown(
+    listener: TcpListener,
+    app: Router,
+    connections: Arc<ConnectionRegistry>,
+    signal: impl Future<Output = ()> + Send + 'static,
+    drain_timeout: Duration,
+) -> std::io::Result<bool> {
+    let (signaled_tx, mut signaled_rx) = oneshot::channel();
+    let shutdown = {
+        let connections = connections.clone();
+        async move {
+            signal.await;
+            // 先拒绝新的 WebSocket 并通知已有连接，它们和 HTTP 请求一起排空
+            connections.shutdown();
+            let _ = signaled_tx.send((Instant::now(), connections.total()));
+        }
+    };
+
+    // 带上连接地址，密码重置等接口按客户端 IP 限流
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .into_future();
+    tokio::pin!(server);
+
+    let mut deadline = None;
+    let http_drained = tokio::select! {
+        result = &mut server => {
+            result?;
+            true
+        }
+        _ = async {
+            match (&mut signaled_rx).await {
+                Ok((signaled_at, open)) => {
+                    deadline = Some((signaled_at + drain_timeout, open));
+                    tokio::time::sleep_until(signaled_at + drain_timeout).await;
+                }
+                // 服务在收到信号之前就结束了，由上面的分支返回
+                Err(_) => std::future::pending().await,
+            }
+        } => false,
+    };
+
+    // 没有等待 HTTP 请求时信号还留在通道里；两边都没有说明服务在收到信号之前就结束了
+    let Some((deadline, open)) = deadline.or_else(|| {
+        signaled_rx
+            .try_recv()
+            .ok()
+            .map(|(signaled_at, open)| (signaled_at + drain_timeout, open))
+    }) else {
+        return Ok(true);
+    };
+    if !http_drained {
+        tracing::warn!("等待进行中的 HTTP 请求超时，强制停机");
+    }
+
+    let ws_drained = connections
+        .drain(deadline.saturating_duration_since(Instant::now()))
+        .await;
+    let remaining = connections.total();
+    if ws_drained {
+        tracing::info!(drained = open, "WebSocket 连接已全部关闭");
+    } else {
+        tracing::warn!(
+            drained = open.saturating_sub(remaining),
+            remaining,
+            "等待 WebSocket 连接关闭超时，强制停机"
+        );
+    }
+
+    Ok(http_drained && ws_drained)
+}
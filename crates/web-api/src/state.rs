@@ -2,12 +2,22 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use application::{
-    services::{BulkUserService, StatsService},
-    ChatService, MessageBroadcaster, PresenceManager, UserService,
+    services::{
+        BulkUserService, NotificationService, NotificationServiceDependencies, StatsService,
+        UploadService,
+    },
+    ChatService, ConfigReloader, MessageBroadcaster, MessageRateLimiter, PresenceManager,
+    Readiness, RoomPresenceNotifier, SystemClock, UserService,
 };
+use config::WsSlowClientPolicy;
 use infrastructure::{PgOrganizationRepository, PgStorage, StatsAggregationService};
+use metrics_exporter_prometheus::PrometheusHandle;
+use tower_http::cors::CorsLayer;
 
-use crate::JwtService;
+use crate::{
+    ConnectionRegistry, HealthProbe, HttpRateLimiter, JwtService, WsConnectionLimits,
+    WsDeflateSubprotocol,
+};
 
 /// 事件收集器队列状态
 #[derive(Debug, Clone)]
@@ -19,10 +29,79 @@ pub struct QueueStatus {
     pub flush_interval: Duration,
 }
 
+/// WebSocket 服务端心跳参数
+#[derive(Debug, Clone, Copy)]
+pub struct WsHeartbeat {
+    /// 服务端发送 ping 的间隔
+    pub ping_interval: Duration,
+    /// 超过该时长没有收到客户端任何帧（包括 pong）即视为连接已断开
+    pub idle_timeout: Duration,
+}
+
+impl WsHeartbeat {
+    pub fn from_app_config(app_config: &config::AppConfig) -> Self {
+        Self {
+            ping_interval: Duration::from_secs(app_config.presence.ping_interval_secs),
+            idle_timeout: Duration::from_secs(app_config.presence.heartbeat_timeout_secs),
+        }
+    }
+}
+
+impl Default for WsHeartbeat {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+/// WebSocket 推送流控参数
+#[derive(Debug, Clone, Copy)]
+pub struct WsFlowControl {
+    /// 已推送未 ack 的消息达到该数量时暂停推送，0 表示不限制
+    pub max_unacked_frames: usize,
+    /// 每个连接等待写出的帧数上限
+    pub send_buffer_capacity: usize,
+    /// 发送缓冲写满时的处理方式
+    pub slow_client_policy: WsSlowClientPolicy,
+    /// 发送缓冲持续写满超过该时长时断开连接，`None` 表示不限制
+    pub slow_client_timeout: Option<Duration>,
+}
+
+impl WsFlowControl {
+    pub fn from_app_config(app_config: &config::AppConfig) -> Self {
+        Self {
+            max_unacked_frames: app_config.message.max_unacked_frames as usize,
+            send_buffer_capacity: app_config.ws.send_buffer_capacity,
+            slow_client_policy: app_config.ws.slow_client_policy,
+            slow_client_timeout: stall_timeout(app_config.ws.slow_client_timeout_secs),
+        }
+    }
+}
+
+impl Default for WsFlowControl {
+    fn default() -> Self {
+        let ws = config::WsConfig::default();
+        Self {
+            max_unacked_frames: 0,
+            send_buffer_capacity: ws.send_buffer_capacity,
+            slow_client_policy: ws.slow_client_policy,
+            slow_client_timeout: stall_timeout(ws.slow_client_timeout_secs),
+        }
+    }
+}
+
+fn stall_timeout(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub user_service: Arc<UserService>,
     pub chat_service: Arc<ChatService>,
+    /// 通知中心，直接使用存储层的通知表
+    pub notification_service: Arc<NotificationService>,
     pub broadcaster: Arc<dyn MessageBroadcaster>,
     pub jwt_service: Arc<JwtService>,
     pub presence_manager: Arc<dyn PresenceManager>,
@@ -31,10 +110,36 @@ pub struct AppState {
     pub org_repository: Arc<PgOrganizationRepository>,
     pub bulk_user_service: Arc<BulkUserService>,
     pub storage: Arc<PgStorage>,
+    pub rate_limiter: Arc<MessageRateLimiter>,
+    pub ws_heartbeat: WsHeartbeat,
+    pub ws_flow_control: WsFlowControl,
+    /// chatroom.deflate 子协议参数，客户端在握手时选用后才生效
+    pub ws_deflate_subprotocol: WsDeflateSubprotocol,
+    /// 本实例的 WebSocket 连接登记，限制实例和单个用户的连接数
+    pub ws_connections: Arc<ConnectionRegistry>,
+    /// 房间在线成员变化的去抖推送
+    pub presence_notifier: Arc<RoomPresenceNotifier>,
+    /// 运行时配置重载，未设置时重载接口不可用
+    pub config_reloader: Option<Arc<ConfigReloader>>,
+    /// 文件上传，未设置时上传和下载接口不可用
+    pub upload_service: Option<Arc<UploadService>>,
+    /// 就绪状态，由启动流程在关键依赖就绪后标记
+    pub readiness: Readiness,
+    /// 就绪探针检查的依赖，未设置时只看就绪状态
+    pub health_probe: Option<HealthProbe>,
+    /// Prometheus 指标渲染句柄，未设置时不挂载 /metrics
+    pub metrics: Option<PrometheusHandle>,
+    /// 按客户端 IP 的接口限流，默认不限流
+    pub http_rate_limiter: Arc<HttpRateLimiter>,
+    /// 跨域配置，未设置时不返回 CORS 头
+    pub cors: Option<CorsLayer>,
+    /// 实时在线概况接口的访问令牌，未设置时只有系统管理员可以访问
+    pub stats_api_token: Option<Arc<str>>,
     // 注意：事件处理现在由独立的 stats-consumer 服务完成
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_service: Arc<UserService>,
         chat_service: Arc<ChatService>,
@@ -46,10 +151,21 @@ impl AppState {
         org_repository: Arc<PgOrganizationRepository>,
         bulk_user_service: Arc<BulkUserService>,
         storage: Arc<PgStorage>,
+        rate_limiter: Arc<MessageRateLimiter>,
     ) -> Self {
+        let presence_notifier = Arc::new(RoomPresenceNotifier::new(
+            presence_manager.clone(),
+            broadcaster.clone(),
+        ));
+        let notification_service =
+            Arc::new(NotificationService::new(NotificationServiceDependencies {
+                notification_repository: storage.notification_repository.clone(),
+                clock: Arc::new(SystemClock),
+            }));
         Self {
             user_service,
             chat_service,
+            notification_service,
             broadcaster,
             jwt_service,
             presence_manager,
@@ -58,9 +174,98 @@ impl AppState {
             org_repository,
             bulk_user_service,
             storage,
+            rate_limiter,
+            ws_heartbeat: WsHeartbeat::default(),
+            ws_flow_control: WsFlowControl::default(),
+            ws_deflate_subprotocol: WsDeflateSubprotocol::default(),
+            ws_connections: Arc::new(ConnectionRegistry::default()),
+            presence_notifier,
+            config_reloader: None,
+            upload_service: None,
+            readiness: Readiness::default(),
+            health_probe: None,
+            metrics: None,
+            http_rate_limiter: Arc::new(HttpRateLimiter::disabled()),
+            cors: None,
+            stats_api_token: None,
         }
     }
 
+    /// 设置 WebSocket 服务端心跳参数
+    pub fn with_ws_heartbeat(mut self, ws_heartbeat: WsHeartbeat) -> Self {
+        self.ws_heartbeat = ws_heartbeat;
+        self
+    }
+
+    /// 设置 WebSocket 推送流控参数
+    pub fn with_ws_flow_control(mut self, ws_flow_control: WsFlowControl) -> Self {
+        self.ws_flow_control = ws_flow_control;
+        self
+    }
+
+    /// 设置 chatroom.deflate 子协议参数
+    pub fn with_ws_deflate_subprotocol(
+        mut self,
+        ws_deflate_subprotocol: WsDeflateSubprotocol,
+    ) -> Self {
+        self.ws_deflate_subprotocol = ws_deflate_subprotocol;
+        self
+    }
+
+    /// 设置 WebSocket 连接数上限
+    pub fn with_ws_connection_limits(mut self, limits: WsConnectionLimits) -> Self {
+        self.ws_connections = Arc::new(ConnectionRegistry::new(limits));
+        self
+    }
+
+    /// 设置运行时配置重载器
+    pub fn with_config_reloader(mut self, config_reloader: Arc<ConfigReloader>) -> Self {
+        self.config_reloader = Some(config_reloader);
+        self
+    }
+
+    /// 开放文件上传和下载接口
+    pub fn with_upload_service(mut self, upload_service: Arc<UploadService>) -> Self {
+        self.upload_service = Some(upload_service);
+        self
+    }
+
+    /// 共享启动流程持有的就绪状态
+    pub fn with_readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// 设置就绪探针需要检查的依赖
+    pub fn with_health_probe(mut self, health_probe: HealthProbe) -> Self {
+        self.health_probe = Some(health_probe);
+        self
+    }
+
+    /// 开放 Prometheus 指标接口
+    pub fn with_metrics(mut self, metrics: PrometheusHandle) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 设置按客户端 IP 的接口限流
+    pub fn with_http_rate_limiter(mut self, http_rate_limiter: HttpRateLimiter) -> Self {
+        self.http_rate_limiter = Arc::new(http_rate_limiter);
+        self
+    }
+
+    /// 设置跨域规则，传入 None 时不返回 CORS 头
+    pub fn with_cors(mut self, cors: Option<CorsLayer>) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// 设置实时在线概况接口的访问令牌
+    pub fn with_stats_api_token(mut self, token: Option<String>) -> Self {
+        self.stats_api_token = token.map(Arc::from);
+        self
+    }
+
     /// 获取事件收集器状态（兼容性接口）
     ///
     /// 现在事件处理由独立的 stats-consumer 服务完成，
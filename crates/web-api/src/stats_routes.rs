@@ -1,6 +1,7 @@
 use axum::{
     extract::{Path, Query, State},
     http::HeaderMap,
+    response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
@@ -8,7 +9,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use application::services::{Dimension, Granularity, RealtimeStats, StatsData, TimeRange};
+use application::services::{
+    ActivityBucket, Dimension, Granularity, RealtimeStats, StatsData, TimeRange,
+};
+use application::PresenceSnapshot;
 use domain::{OrgId, RoomId, UserId};
 
 use crate::{error::ApiError, state::AppState};
@@ -70,14 +74,65 @@ impl From<RealtimeStats> for RealtimeStatsResponse {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RealtimeQuery {
+    /// 返回在线人数最多的前几个房间，默认 10，最多 100
+    pub top: Option<usize>,
+    /// 旧版查询：带了维度时按维度返回在线人数，不返回全站概况
+    pub dimension: Option<String>,
+    pub dimension_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    /// "hour"（默认）、"day"、"week"
+    pub granularity: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    /// 默认当前时间
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomActivityResponse {
+    pub room_id: Uuid,
+    pub granularity: String,
+    pub buckets: Vec<ActivityBucket>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OnlineTimeQuery {
+    pub from: Option<DateTime<Utc>>,
+    /// 默认当前时间
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserOnlineTimeResponse {
+    pub user_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total_minutes: i64,
+}
+
+/// 默认返回最近 24 个桶
+const DEFAULT_ACTIVITY_BUCKETS: i32 = 24;
+
+/// 看板访问实时在线概况时携带令牌的请求头
+const STATS_TOKEN_HEADER: &str = "x-stats-token";
+
+const DEFAULT_TOP_ROOMS: usize = 10;
+const MAX_TOP_ROOMS: usize = 100;
+
 pub fn stats_routes() -> Router<AppState> {
     Router::new()
         .route("/query", get(query_stats))
-        .route("/realtime", get(get_realtime_stats))
+        .route("/realtime", get(get_realtime))
         .route(
             "/realtime/{dimension_type}/{dimension_id}",
             get(get_realtime_stats_by_dimension),
         )
+        .route("/rooms/{room_id}/activity", get(get_room_activity))
+        .route("/users/{user_id}/online-time", get(get_user_online_time))
 }
 
 /// 查询统计数据
@@ -86,7 +141,10 @@ async fn query_stats(
     State(state): State<AppState>,
     Query(query): Query<StatsQuery>,
 ) -> Result<Json<Vec<StatsDataResponse>>, ApiError> {
-    let _user_id = state.jwt_service.extract_user_from_headers(&headers)?; // 验证身份
+    let _user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?; // 验证身份
 
     // 解析维度类型
     let dimension = match query.dimension.as_str() {
@@ -133,23 +191,55 @@ async fn query_stats(
     Ok(Json(responses))
 }
 
-/// 获取实时统计数据
-async fn get_realtime_stats(
+/// 实时统计：不带维度时返回全站在线概况，带 `dimension` 时沿用旧版按维度查询
+async fn get_realtime(
     headers: HeaderMap,
     State(state): State<AppState>,
-    Query(params): Query<StatsQuery>,
+    Query(query): Query<RealtimeQuery>,
+) -> Result<Response, ApiError> {
+    match query.dimension {
+        Some(dimension) => get_realtime_stats(&headers, &state, &dimension, query.dimension_id)
+            .await
+            .map(IntoResponse::into_response),
+        None => get_presence_snapshot(&headers, &state, query.top)
+            .await
+            .map(IntoResponse::into_response),
+    }
+}
+
+/// 全站实时在线概况，供运营看板轮询
+///
+/// 系统管理员，或携带 `X-Stats-Token` 请求头的看板可以访问
+async fn get_presence_snapshot(
+    headers: &HeaderMap,
+    state: &AppState,
+    top: Option<usize>,
+) -> Result<Json<PresenceSnapshot>, ApiError> {
+    authorize_stats_dashboard(headers, state).await?;
+
+    let top = top.unwrap_or(DEFAULT_TOP_ROOMS).min(MAX_TOP_ROOMS);
+    let snapshot = state.presence_manager.snapshot(top).await?;
+    Ok(Json(snapshot))
+}
+
+/// 按维度获取实时统计数据（旧版查询参数形式）
+async fn get_realtime_stats(
+    headers: &HeaderMap,
+    state: &AppState,
+    dimension: &str,
+    dimension_id: Option<Uuid>,
 ) -> Result<Json<RealtimeStatsResponse>, ApiError> {
-    let _user_id = state.jwt_service.extract_user_from_headers(&headers)?; // 验证身份
+    let _user_id = state.jwt_service.extract_user_from_headers(headers).await?; // 验证身份
 
     // 解析维度类型
-    let dimension = match params.dimension.as_str() {
-        "room" => Dimension::Room(RoomId::from(params.dimension_id.ok_or_else(|| {
+    let dimension = match dimension {
+        "room" => Dimension::Room(RoomId::from(dimension_id.ok_or_else(|| {
             ApiError::bad_request("dimension_id is required for room dimension")
         })?)),
-        "org" => Dimension::Org(OrgId::from(params.dimension_id.ok_or_else(|| {
+        "org" => Dimension::Org(OrgId::from(dimension_id.ok_or_else(|| {
             ApiError::bad_request("dimension_id is required for org dimension")
         })?)),
-        "user" => Dimension::User(UserId::from(params.dimension_id.ok_or_else(|| {
+        "user" => Dimension::User(UserId::from(dimension_id.ok_or_else(|| {
             ApiError::bad_request("dimension_id is required for user dimension")
         })?)),
         _ => {
@@ -164,13 +254,43 @@ async fn get_realtime_stats(
     Ok(Json(stats.into()))
 }
 
+/// 带了统计令牌时只核对令牌，否则要求系统管理员身份
+async fn authorize_stats_dashboard(headers: &HeaderMap, state: &AppState) -> Result<(), ApiError> {
+    if let Some(provided) = headers.get(STATS_TOKEN_HEADER) {
+        return match state.stats_api_token.as_deref() {
+            Some(expected) if token_matches(provided.as_bytes(), expected.as_bytes()) => Ok(()),
+            _ => Err(ApiError::unauthorized("Invalid stats token")),
+        };
+    }
+
+    let user_id = state.jwt_service.extract_user_from_headers(headers).await?;
+    state
+        .chat_service
+        .check_admin_access(UserId::from(user_id), None)
+        .await?;
+    Ok(())
+}
+
+/// 逐字节比较完所有内容，耗时不随第一个不同字节的位置变化
+fn token_matches(provided: &[u8], expected: &[u8]) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 /// 根据维度ID获取实时统计数据
 async fn get_realtime_stats_by_dimension(
     headers: HeaderMap,
     State(state): State<AppState>,
     Path((dimension_type, dimension_id)): Path<(String, Uuid)>,
 ) -> Result<Json<RealtimeStatsResponse>, ApiError> {
-    let _user_id = state.jwt_service.extract_user_from_headers(&headers)?; // 验证身份
+    let _user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?; // 验证身份
 
     // 解析维度类型
     let dimension = match dimension_type.as_str() {
@@ -188,3 +308,84 @@ async fn get_realtime_stats_by_dimension(
     let stats = state.stats_service.get_realtime_stats(dimension).await?;
     Ok(Json(stats.into()))
 }
+
+/// 房间消息量曲线（用于 sparkline），空桶补 0
+///
+/// 私有房间只对成员开放，与房间详情的可见性一致
+async fn get_room_activity(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<RoomActivityResponse>, ApiError> {
+    let user_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    state.chat_service.get_room(room_id, user_id).await?;
+
+    let granularity_name = query.granularity.unwrap_or_else(|| "hour".to_string());
+    let (granularity, width) = match granularity_name.as_str() {
+        "hour" => (Granularity::Hour, chrono::Duration::hours(1)),
+        "day" => (Granularity::Day, chrono::Duration::days(1)),
+        "week" => (Granularity::Week, chrono::Duration::weeks(1)),
+        _ => {
+            return Err(ApiError::bad_request(
+                "Invalid granularity. Must be one of: hour, day, week",
+            ))
+        }
+    };
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - width * DEFAULT_ACTIVITY_BUCKETS);
+
+    let buckets = state
+        .stats_service
+        .get_room_activity(RoomId::from(room_id), TimeRange::new(from, to), granularity)
+        .await?;
+
+    Ok(Json(RoomActivityResponse {
+        room_id,
+        granularity: granularity_name,
+        buckets,
+    }))
+}
+
+/// 用户在线总时长（分钟），来自按小时聚合的 user_online_time
+///
+/// 只能查自己的，系统管理员可以查任何人
+async fn get_user_online_time(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<OnlineTimeQuery>,
+) -> Result<Json<UserOnlineTimeResponse>, ApiError> {
+    let caller_id = state
+        .jwt_service
+        .extract_user_from_headers(&headers)
+        .await?;
+    if caller_id != user_id {
+        state
+            .chat_service
+            .check_admin_access(UserId::from(caller_id), None)
+            .await?;
+    }
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(1));
+    if from >= to {
+        return Err(ApiError::bad_request("from must be earlier than to"));
+    }
+
+    let online_seconds = state
+        .stats_aggregation_service
+        .user_online_seconds(UserId::from(user_id), from, to)
+        .await?;
+
+    Ok(Json(UserOnlineTimeResponse {
+        user_id,
+        from,
+        to,
+        total_minutes: online_seconds / 60,
+    }))
+}
@@ -1,12 +1,41 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use crate::error::ApiError;
 use crate::state::AppState;
-use application::MessageBroadcast;
-use axum::extract::ws::{Message as WsMessage, WebSocket};
-use domain::{RoomId, UserId};
+use crate::ws_outbound::{OutboundQueue, PushOutcome};
+use crate::ws_registry::{
+    ConnectionRejected, ConnectionTicket, EVICTED_REASON, SERVER_RESTARTING_REASON,
+};
+use application::repository::MessageDeliveryRepository;
+use application::services::{
+    ReconnectBackfill, ResumeRoomRequest, RoomResumeState, SequenceReplay,
+};
+use application::{
+    ApplicationError, MessageBroadcast, PresenceChange, StreamEvent, WebSocketMessage,
+};
+use axum::extract::ws::{close_code, CloseFrame, Message as WsMessage, WebSocket};
+use axum::http::StatusCode;
+use domain::{MessageId, RoomId, UserId};
 use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// 单次 resume 最多补发的消息条数，超出时客户端按 last_seq 继续补齐
+const RESUME_BATCH_LIMIT: u32 = 200;
+
+/// 连接建立时最多补发的未送达消息条数，更早的积压交给历史接口
+const REPLAY_LIMIT: u32 = 500;
+
+/// 发送任务结束后，写任务写完剩余帧的最长等待时间
+const WRITER_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 连接内屏蔽名单的缓存时长，屏蔽变更在这段时间内对实时推送生效
+const BLOCK_LIST_TTL: Duration = Duration::from_secs(5);
+
 /// WebSocket 连接管理器
 ///
 /// 封装单个 WebSocket 连接的所有状态和逻辑，包括：
@@ -19,7 +48,15 @@ pub struct WebSocketConnection {
     state: AppState,
     user_id: UserId,
     room_id: RoomId,
+    /// 本连接的会话 ID，用于心跳跟踪
+    session_id: Uuid,
+    /// 重连时客户端最后收到的消息，用于补发断线期间的消息
+    last_message_id: Option<Uuid>,
     message_stream: Option<application::MessageStream>,
+    /// 房间已关闭：只接收消息，发送由 HTTP 接口拒绝
+    read_only: bool,
+    /// 连接名额，连接销毁时释放
+    ticket: ConnectionTicket,
 }
 
 impl WebSocketConnection {
@@ -30,14 +67,61 @@ impl WebSocketConnection {
     /// 2. 更新用户在线状态
     /// 3. 设置消息流订阅
     pub async fn new(
-        socket: WebSocket,
+        mut socket: WebSocket,
         state: AppState,
         user_id: Uuid,
         room_id: Uuid,
+        last_message_id: Option<Uuid>,
     ) -> Result<Self, ApiError> {
         let room_id_domain = domain::RoomId::from(room_id);
         let user_id_domain = domain::UserId::from(user_id);
 
+        // 先占连接名额：实例满了或用户连接数超限（配置为拒绝新连接）时直接关闭
+        let ticket = match state.ws_connections.register(user_id_domain) {
+            Ok(ticket) => ticket,
+            Err(rejected) => {
+                let (status, code) = match rejected {
+                    ConnectionRejected::ServerFull { .. } => {
+                        (StatusCode::SERVICE_UNAVAILABLE, close_code::AGAIN)
+                    }
+                    ConnectionRejected::UserLimit { .. } => {
+                        (StatusCode::TOO_MANY_REQUESTS, close_code::POLICY)
+                    }
+                    ConnectionRejected::ShuttingDown => {
+                        (StatusCode::SERVICE_UNAVAILABLE, close_code::RESTART)
+                    }
+                };
+                let err =
+                    ApiError::new(status, rejected.reason(), "too many websocket connections");
+                tracing::info!(user_id = %user_id, ?rejected, "WebSocket 连接数超过上限，拒绝连接");
+                Self::reject(
+                    &mut socket,
+                    &err,
+                    Some(CloseFrame {
+                        code,
+                        reason: rejected.reason().into(),
+                    }),
+                )
+                .await;
+                return Err(err);
+            }
+        };
+
+        // 房间在上次打开后可能已被删除或关闭：删除的房间直接拒绝，关闭的房间按配置只读订阅
+        let room = match state
+            .chat_service
+            .authorize_subscription(room_id, user_id)
+            .await
+        {
+            Ok(room) => room,
+            Err(err) => {
+                let err = ApiError::from(err);
+                tracing::info!(user_id = %user_id, room_id = %room_id, code = err.code(), "拒绝 WebSocket 订阅");
+                Self::reject(&mut socket, &err, None).await;
+                return Err(err);
+            }
+        };
+
         tracing::info!(user_id = %user_id, room_id = %room_id, "WebSocket 连接已建立");
 
         // 用户连接到房间 - 更新在线状态
@@ -49,6 +133,24 @@ impl WebSocketConnection {
                 tracing::error!(error = %err, "Failed to update user presence");
                 ApiError::internal_server_error("Failed to establish connection")
             })?;
+        // 在订阅之前广播，自己不会收到自己的上线通知（已包含在 roster 快照里）
+        Self::broadcast_presence_change(
+            &state,
+            room_id_domain,
+            user_id_domain,
+            PresenceChange::Connected,
+        )
+        .await;
+
+        // 登记会话心跳；非正常断开时由后台清扫任务按心跳超时标记离线
+        let session_id = Uuid::new_v4();
+        if let Err(err) = state
+            .presence_manager
+            .heartbeat(room_id_domain, user_id_domain, session_id)
+            .await
+        {
+            tracing::warn!(error = %err, %session_id, "Failed to register session heartbeat");
+        }
 
         // 创建消息流 - 直接订阅广播器
         let mut message_stream =
@@ -75,15 +177,63 @@ impl WebSocketConnection {
         }
         tracing::info!(cleared_count, "清空了旧消息");
 
+        // 与 Drop 中的递减配对，只统计建立成功的连接
+        crate::metrics::ws_connection_opened();
         Ok(Self {
             socket: Some(socket),
             state,
             user_id: user_id_domain,
             room_id: room_id_domain,
+            session_id,
+            last_message_id,
             message_stream: Some(message_stream),
+            read_only: room.is_closed,
+            ticket,
         })
     }
 
+    /// 发送错误帧后关闭连接，失败只记日志（客户端可能已经断开）
+    async fn reject(socket: &mut WebSocket, err: &ApiError, close: Option<CloseFrame>) {
+        match serde_json::to_string(&ServerReply::error(err.code(), err.message())) {
+            Ok(frame) => {
+                if socket.send(WsMessage::Text(frame.into())).await.is_err() {
+                    tracing::debug!("Failed to send rejection frame");
+                }
+            }
+            Err(err) => tracing::warn!(error = %err, "failed to serialize websocket reply"),
+        }
+        let _ = socket.send(WsMessage::Close(close)).await;
+    }
+
+    /// 广播成员上线/下线，失败只记日志
+    async fn broadcast_presence_change(
+        state: &AppState,
+        room_id: RoomId,
+        user_id: UserId,
+        event: PresenceChange,
+    ) {
+        let broadcast = MessageBroadcast::presence(room_id, user_id, event);
+        if let Err(err) = state.broadcaster.broadcast(broadcast).await {
+            tracing::warn!(error = %err, %user_id, %room_id, "Failed to broadcast presence change");
+        }
+    }
+
+    /// 连接建立时的在线成员快照；同一用户的多个会话只出现一次
+    async fn load_roster(state: &AppState, room_id: RoomId) -> Option<String> {
+        let mut online = match state.presence_manager.get_online_users(room_id).await {
+            Ok(users) => users,
+            Err(err) => {
+                tracing::warn!(error = %err, %room_id, "Failed to load room roster");
+                return None;
+            }
+        };
+        online.sort_unstable_by_key(|user_id| Uuid::from(*user_id));
+        online.dedup();
+        serde_json::to_string(&ServerReply::Roster { room_id, online })
+            .map_err(|err| tracing::warn!(error = %err, "failed to serialize websocket reply"))
+            .ok()
+    }
+
     /// 广播统计更新到房间
     pub async fn broadcast_stats_update(state: &AppState, room_id: RoomId) -> Result<(), ApiError> {
         match state.presence_manager.get_online_stats(room_id).await {
@@ -100,6 +250,136 @@ impl WebSocketConnection {
         Ok(())
     }
 
+    /// 重连补发：`last_message_id` 之后的消息，缺口过大时改发 resync_required
+    ///
+    /// 补发在订阅之后进行，实时流里序列号不超过 `last_seq` 的消息会被跳过，保证不重复
+    async fn load_backfill(
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+        last_message_id: Option<Uuid>,
+    ) -> Backfill {
+        let Some(last_message_id) = last_message_id else {
+            return Backfill::default();
+        };
+
+        let result = state
+            .chat_service
+            .backfill_after(Uuid::from(room_id), Uuid::from(user_id), last_message_id)
+            .await;
+        Self::backfill_frames(result, user_id, room_id)
+    }
+
+    /// 广播订阅中断或落后后补齐：序列号 `after_seq` 之后的消息，缺口过大时改发 resync_required
+    async fn load_gap(
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+        after_seq: i64,
+    ) -> Backfill {
+        let result = state
+            .chat_service
+            .backfill_after_seq(Uuid::from(room_id), Uuid::from(user_id), after_seq)
+            .await;
+        Self::backfill_frames(result, user_id, room_id)
+    }
+
+    /// 把补发结果序列化成待发送的帧
+    fn backfill_frames(
+        result: Result<ReconnectBackfill, ApplicationError>,
+        user_id: UserId,
+        room_id: RoomId,
+    ) -> Backfill {
+        let mut backfill = Backfill::default();
+        let reply = match result {
+            Ok(ReconnectBackfill::Messages(messages)) => {
+                for message in messages {
+                    backfill.last_seq = backfill.last_seq.max(message.seq);
+                    backfill.message_ids.insert(message.id);
+                    match serde_json::to_string(&WebSocketMessage::ChatMessage(message)) {
+                        Ok(frame) => backfill.frames.push(frame),
+                        Err(err) => {
+                            tracing::warn!(error = %err, "failed to serialize backfill message")
+                        }
+                    }
+                }
+                return backfill;
+            }
+            Ok(ReconnectBackfill::ResyncRequired { missed }) => {
+                tracing::info!(room_id = %room_id, user_id = %user_id, ?missed, "重连缺口过大，要求客户端重新同步");
+                ServerReply::ResyncRequired { room_id, missed }
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, room_id = %room_id, user_id = %user_id, "重连补发失败");
+                ServerReply::error("BACKFILL_FAILED", err.to_string())
+            }
+        };
+
+        match serde_json::to_string(&reply) {
+            Ok(frame) => backfill.frames.push(frame),
+            Err(err) => tracing::warn!(error = %err, "failed to serialize websocket reply"),
+        }
+        backfill
+    }
+
+    /// 加载需要补发给该用户的未送达消息，序列化成待发送的帧
+    ///
+    /// 只补发当前房间内未删除的消息，已经通过重连补发的消息和被屏蔽用户的消息跳过；
+    /// 查询失败只记日志，不影响实时消息
+    async fn load_replay(
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+        skip: &HashSet<MessageId>,
+        blocked: &HashSet<UserId>,
+    ) -> Vec<String> {
+        let deliveries = match state
+            .storage
+            .delivery_repository
+            .find_undelivered_for_user(user_id, REPLAY_LIMIT)
+            .await
+        {
+            Ok(deliveries) => deliveries,
+            Err(err) => {
+                tracing::warn!(error = %err, %user_id, "加载未送达消息失败");
+                return Vec::new();
+            }
+        };
+        if deliveries.is_empty() {
+            return Vec::new();
+        }
+
+        let ids: Vec<MessageId> = deliveries.iter().map(|d| d.message_id).collect();
+        let messages = match state.chat_service.messages_by_ids(&ids).await {
+            Ok(messages) => messages,
+            Err(err) => {
+                tracing::warn!(error = %err, %user_id, "加载补发消息内容失败");
+                return Vec::new();
+            }
+        };
+
+        messages
+            .into_iter()
+            .filter(|message| {
+                message.room_id == room_id
+                    && !message.is_deleted
+                    && !skip.contains(&message.id)
+                    && !blocked.contains(&message.sender_id)
+            })
+            .filter_map(|message| {
+                let frame = WebSocketMessage::ReplayedMessage {
+                    message,
+                    replayed: true,
+                };
+                serde_json::to_string(&frame)
+                    .map_err(|err| {
+                        tracing::warn!(error = %err, "failed to serialize replayed message");
+                    })
+                    .ok()
+            })
+            .collect()
+    }
+
     /// 运行 WebSocket 连接的主循环
     ///
     /// 这是连接的核心逻辑，处理：
@@ -108,14 +388,70 @@ impl WebSocketConnection {
     /// - 连接生命周期管理
     pub async fn run(mut self) {
         let socket = self.socket.take().expect("Socket should be available");
+        // 握手时选中了 chatroom.deflate 子协议才压缩出站消息
+        let deflate = socket
+            .protocol()
+            .is_some_and(|protocol| protocol == crate::DEFLATE_SUBPROTOCOL)
+            .then_some(self.state.ws_deflate_subprotocol);
+        let encode = move |frame: WsMessage| match &deflate {
+            Some(deflate) => deflate.encode(frame),
+            None => frame,
+        };
         let mut message_stream = self
             .message_stream
             .take()
             .expect("Message stream should be available");
 
         let (mut sender, mut incoming) = socket.split();
+        let mut evicted = self.ticket.take_eviction();
+        let mut closing = self.ticket.shutdown_signal();
+
+        // 先补发断线期间的消息和未送达消息，再开始转发实时广播
+        let mut block_filter = BlockFilter::load(&self.state, self.user_id).await;
+        let backfill = Self::load_backfill(
+            &self.state,
+            self.user_id,
+            self.room_id,
+            self.last_message_id,
+        )
+        .await;
+        let replay = Self::load_replay(
+            &self.state,
+            self.user_id,
+            self.room_id,
+            &backfill.message_ids,
+            &block_filter.blocked,
+        )
+        .await;
+        let mut backfilled_seq = backfill.last_seq;
+        // 实时流里见过的最大序列号，订阅中断后从这里补齐；连接时取房间当前最新的序列号
+        let mut last_seq = match self
+            .state
+            .chat_service
+            .latest_seq(Uuid::from(self.room_id))
+            .await
+        {
+            Ok(seq) => seq.max(backfilled_seq),
+            Err(err) => {
+                tracing::warn!(error = %err, room_id = %self.room_id, "Failed to load latest message seq");
+                backfilled_seq
+            }
+        };
+        let roster = Self::load_roster(&self.state, self.room_id).await;
+
+        // 已关闭房间先告知客户端只读，再补发消息
+        let read_only_notice = if self.read_only {
+            serde_json::to_string(&ServerReply::RoomReadOnly {
+                room_id: self.room_id,
+            })
+            .map_err(|err| tracing::warn!(error = %err, "failed to serialize websocket reply"))
+            .ok()
+        } else {
+            None
+        };
 
         // 广播用户连接的统计更新
+        self.state.presence_notifier.notify(self.room_id);
         tokio::spawn({
             let state = self.state.clone();
             let room_id = self.room_id;
@@ -129,32 +465,170 @@ impl WebSocketConnection {
         // 创建 mpsc channel 来解耦对 sender 的访问
         let (cmd_tx, mut cmd_rx) = mpsc::channel::<WsCommand>(32);
 
-        // 发送任务：统一处理所有对 WebSocket sender 的写操作
+        // 发送任务：转发广播和处理写命令，帧放进有界发送缓冲，由写任务写给客户端
         let send_task = {
-            let cmd_tx_for_broadcast = cmd_tx.clone();
+            let state = self.state.clone();
+            let user_id = self.user_id;
+            let room_id = self.room_id;
+            let ping_interval = self.state.ws_heartbeat.ping_interval;
+            let mut flow = FlowControl::new(self.state.ws_flow_control.max_unacked_frames);
+            let outbound = Arc::new(
+                OutboundQueue::new(
+                    self.state.ws_flow_control.send_buffer_capacity,
+                    self.state.ws_flow_control.slow_client_policy,
+                )
+                .with_stall_timeout(self.state.ws_flow_control.slow_client_timeout),
+            );
 
             tokio::spawn(async move {
+                // 补发的帧数由补发上限控制，直接写出，不占发送缓冲
+                for frame in roster
+                    .into_iter()
+                    .chain(read_only_notice)
+                    .chain(backfill.frames)
+                    .chain(replay)
+                {
+                    if sender
+                        .send(encode(WsMessage::Text(frame.into())))
+                        .await
+                        .is_err()
+                    {
+                        tracing::warn!("Failed to send replayed message");
+                        return;
+                    }
+                }
+
+                // 写任务：客户端接收慢时只有它被阻塞，转发照常进行
+                let mut writer = tokio::spawn({
+                    let outbound = outbound.clone();
+                    async move {
+                        while let Some(frame) = outbound.pop().await {
+                            let is_close = matches!(frame, WsMessage::Close(_));
+                            if sender.send(encode(frame)).await.is_err() {
+                                tracing::warn!("Failed to send websocket frame");
+                                break;
+                            }
+                            if is_close {
+                                break;
+                            }
+                        }
+                    }
+                });
+                let mut writer_finished = false;
+
+                // 服务端定期 ping，客户端的 pong 由接收任务计入心跳
+                let mut ping_ticker = tokio::time::interval_at(
+                    tokio::time::Instant::now() + ping_interval,
+                    ping_interval,
+                );
+
                 loop {
                     tokio::select! {
+                        // 写任务结束说明连接已经写不进去
+                        _ = &mut writer => {
+                            writer_finished = true;
+                            break;
+                        }
+                        // 同一用户的新连接超过上限时挤掉本连接，告知原因后关闭
+                        result = async { evicted.as_mut().expect("eviction receiver").await }, if evicted.is_some() => {
+                            evicted = None;
+                            if result.is_err() {
+                                continue;
+                            }
+                            outbound.close(Some(CloseFrame {
+                                code: close_code::POLICY,
+                                reason: EVICTED_REASON.into(),
+                            }));
+                            tracing::info!(%user_id, %room_id, "连接被同一用户的新连接挤掉，关闭连接");
+                            break;
+                        }
+                        // 服务停机：写完已缓冲的帧后告知客户端稍后重连
+                        _ = async { let _ = closing.wait_for(|closing| *closing).await; } => {
+                            outbound.close(Some(CloseFrame {
+                                code: close_code::RESTART,
+                                reason: SERVER_RESTARTING_REASON.into(),
+                            }));
+                            tracing::info!(%user_id, %room_id, "服务停机，关闭连接");
+                            break;
+                        }
+                        _ = ping_ticker.tick() => {
+                            outbound.push_control(WsMessage::Ping(Default::default()));
+                        }
                         // 处理来自 mpsc channel 的写命令
                         Some(cmd) = cmd_rx.recv() => {
                             match cmd {
                                 WsCommand::SendText(text) => {
-                                    if sender.send(WsMessage::Text(text.into())).await.is_err() {
-                                        tracing::warn!("Failed to send text message");
+                                    if Self::enqueue(&outbound, text, user_id, room_id).is_break() {
                                         break;
                                     }
                                 }
                                 WsCommand::SendPong(data) => {
-                                    if sender.send(WsMessage::Pong(data.into())).await.is_err() {
-                                        tracing::warn!("Failed to send pong message");
-                                        break;
-                                    }
+                                    outbound.push_control(WsMessage::Pong(data.into()));
                                 }
+                                WsCommand::Acked(message_id) => flow.ack(message_id),
+                                WsCommand::Resynced => flow.reset(),
                             }
                         }
                         // 处理来自消息流的广播消息
-                        Some(broadcast) = message_stream.recv() => {
+                        Some(event) = message_stream.recv_event() => {
+                            let broadcast = match event {
+                                StreamEvent::Message(broadcast) => broadcast,
+                                // 订阅中断或缓冲被覆盖期间的广播已经丢失，从存储补齐；落后的连接等客户端 resume
+                                StreamEvent::Gap => {
+                                    if flow.is_lagging() {
+                                        continue;
+                                    }
+                                    let gap = Self::load_gap(&state, user_id, room_id, last_seq).await;
+                                    tracing::info!(%user_id, %room_id, after_seq = last_seq, recovered = gap.message_ids.len(), "广播流出现缺口，从存储补齐消息");
+                                    last_seq = last_seq.max(gap.last_seq);
+                                    // 重新订阅后实时流里可能再收到这些消息，按序列号跳过
+                                    backfilled_seq = backfilled_seq.max(gap.last_seq);
+                                    if gap
+                                        .frames
+                                        .into_iter()
+                                        .any(|frame| Self::enqueue(&outbound, frame, user_id, room_id).is_break())
+                                    {
+                                        break;
+                                    }
+                                    continue;
+                                }
+                            };
+                            // 强制下线只发给被下线的用户本人，告知原因后关闭连接
+                            if let WebSocketMessage::SessionRevoked { user_id: revoked, reason } = &broadcast.message {
+                                if *revoked != user_id {
+                                    continue;
+                                }
+                                if let Ok(json) = serde_json::to_string(&broadcast.message) {
+                                    outbound.push(WsMessage::Text(json.into()));
+                                }
+                                outbound.close(Some(CloseFrame {
+                                    code: close_code::POLICY,
+                                    reason: reason.as_str().into(),
+                                }));
+                                tracing::info!(%user_id, %room_id, %reason, "会话被强制结束，关闭连接");
+                                break;
+                            }
+                            // 提及通知只推给被提及的人
+                            if let WebSocketMessage::Mention { user_ids, .. } = &broadcast.message {
+                                if !user_ids.contains(&user_id) {
+                                    continue;
+                                }
+                            }
+                            // 落后的连接不再接收新广播，等客户端 resume 后恢复
+                            if flow.is_lagging() {
+                                continue;
+                            }
+                            if let WebSocketMessage::ChatMessage(message) = &broadcast.message {
+                                last_seq = last_seq.max(message.seq);
+                                // 补发时已经发过的消息不再重复转发
+                                if message.seq <= backfilled_seq {
+                                    continue;
+                                }
+                                // 屏蔽者看不到被屏蔽用户的消息，其他人照常收到
+                                if block_filter.is_blocked(&state, user_id, message.sender_id).await {
+                                    continue;
+                                }
+                            }
                             let payload = match serde_json::to_string(&broadcast.message) {
                                 Ok(json) => json,
                                 Err(err) => {
@@ -162,26 +636,75 @@ impl WebSocketConnection {
                                     continue;
                                 }
                             };
-                            if cmd_tx_for_broadcast.send(WsCommand::SendText(payload)).await.is_err() {
-                                tracing::warn!("Failed to send broadcast message to command channel");
+                            let payload = match &broadcast.message {
+                                WebSocketMessage::ChatMessage(message) if !flow.track(message.id) => {
+                                    tracing::warn!(%user_id, %room_id, unacked = flow.unacked(), "客户端 ack 跟不上，暂停推送并提示重新同步");
+                                    match serde_json::to_string(&ServerReply::ResyncRequired { room_id, missed: None }) {
+                                        Ok(json) => json,
+                                        Err(err) => {
+                                            tracing::warn!(error = %err, "failed to serialize websocket reply");
+                                            continue;
+                                        }
+                                    }
+                                }
+                                _ => payload,
+                            };
+                            if Self::enqueue(&outbound, payload, user_id, room_id).is_break() {
                                 break;
                             }
                         }
                     }
                 }
+
+                // 写任务把缓冲里剩下的帧（包括关闭帧）写完后结束，写不出去时到时间强制结束
+                if !writer_finished {
+                    outbound.finish();
+                    let abort = writer.abort_handle();
+                    tokio::spawn(async move {
+                        if tokio::time::timeout(WRITER_DRAIN_TIMEOUT, writer)
+                            .await
+                            .is_err()
+                        {
+                            abort.abort();
+                        }
+                    });
+                }
                 tracing::info!("WebSocket发送任务结束");
             })
         };
 
         // 接收任务：处理来自WebSocket客户端的消息
-        let recv_task = tokio::spawn(async move {
-            while let Some(Ok(message)) = incoming.next().await {
-                if (Self::handle_incoming(message, &cmd_tx).await).is_err() {
-                    break;
+        let recv_task = {
+            let state = self.state.clone();
+            let user_id = self.user_id;
+            let room_id = self.room_id;
+            let session_id = self.session_id;
+            let idle_timeout = self.state.ws_heartbeat.idle_timeout;
+
+            tokio::spawn(async move {
+                // TCP 连接被直接掐断时读端可能永远等不到数据，
+                // 超过心跳超时没收到任何帧（包括 pong）就主动结束连接
+                loop {
+                    let message = match tokio::time::timeout(idle_timeout, incoming.next()).await {
+                        Ok(Some(Ok(message))) => message,
+                        Ok(_) => break,
+                        Err(_) => {
+                            tracing::info!(%session_id, %user_id, "超过心跳超时未收到客户端响应，断开连接");
+                            break;
+                        }
+                    };
+                    if (Self::handle_incoming(
+                        message, &cmd_tx, &state, user_id, room_id, session_id,
+                    )
+                    .await)
+                        .is_err()
+                    {
+                        break;
+                    }
                 }
-            }
-            tracing::info!("WebSocket接收任务结束");
-        });
+                tracing::info!("WebSocket接收任务结束");
+            })
+        };
 
         // 等待任意一个任务完成（连接断开）
         tokio::select! {
@@ -194,6 +717,14 @@ impl WebSocketConnection {
         }
 
         // 连接断开时清理在线状态
+        if let Err(err) = self
+            .state
+            .presence_manager
+            .end_session(self.session_id)
+            .await
+        {
+            tracing::warn!(error = %err, session_id = %self.session_id, "Failed to end session heartbeat");
+        }
         if let Err(err) = self
             .state
             .presence_manager
@@ -201,9 +732,18 @@ impl WebSocketConnection {
             .await
         {
             tracing::error!(error = %err, user_id = %self.user_id, room_id = %self.room_id, "Failed to cleanup user presence");
+        } else {
+            Self::broadcast_presence_change(
+                &self.state,
+                self.room_id,
+                self.user_id,
+                PresenceChange::Disconnected,
+            )
+            .await;
         }
 
         // 广播用户断开的统计更新
+        self.state.presence_notifier.notify(self.room_id);
         tokio::spawn({
             let state = self.state.clone();
             let room_id = self.room_id;
@@ -217,15 +757,40 @@ impl WebSocketConnection {
         tracing::info!(user_id = %self.user_id, room_id = %self.room_id, "WebSocket连接已断开，在线状态已清理");
     }
 
+    /// 把一帧文本放进发送缓冲；客户端太慢被断开时返回 Break
+    fn enqueue(
+        outbound: &OutboundQueue,
+        text: String,
+        user_id: UserId,
+        room_id: RoomId,
+    ) -> ControlFlow<()> {
+        match outbound.push(WsMessage::Text(text.into())) {
+            PushOutcome::Queued => ControlFlow::Continue(()),
+            PushOutcome::DroppedOldest => {
+                tracing::debug!(%user_id, %room_id, dropped = outbound.dropped(), "客户端接收太慢，丢弃最旧的帧");
+                ControlFlow::Continue(())
+            }
+            PushOutcome::Disconnected => {
+                tracing::warn!(%user_id, %room_id, dropped = outbound.dropped(), "客户端接收太慢，断开连接");
+                ControlFlow::Break(())
+            }
+            PushOutcome::Closed => ControlFlow::Break(()),
+        }
+    }
+
     /// 处理来自客户端的消息
     ///
     /// 包括：
     /// - 关闭消息处理
     /// - Ping/Pong 心跳机制
-    /// - 其他客户端消息
+    /// - 客户端命令（resume、ack）
     async fn handle_incoming(
         message: WsMessage,
         cmd_tx: &mpsc::Sender<WsCommand>,
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+        session_id: Uuid,
     ) -> Result<(), ()> {
         match message {
             WsMessage::Close(_) => {
@@ -234,6 +799,7 @@ impl WebSocketConnection {
             }
             WsMessage::Ping(data) => {
                 tracing::debug!("收到ping消息，发送pong回应");
+                Self::refresh_heartbeat(state, user_id, room_id, session_id).await;
                 if cmd_tx
                     .send(WsCommand::SendPong(data.to_vec()))
                     .await
@@ -245,14 +811,313 @@ impl WebSocketConnection {
             }
             WsMessage::Pong(_) => {
                 tracing::debug!("收到pong消息");
+                Self::refresh_heartbeat(state, user_id, room_id, session_id).await;
             }
-            WsMessage::Text(_) | WsMessage::Binary(_) => {
-                // 暂时不处理客户端发送的消息，后续可以添加心跳或其他功能
-                tracing::debug!("收到客户端消息");
+            WsMessage::Text(text) => {
+                let reply = match serde_json::from_str::<ClientCommand>(text.as_str()) {
+                    Ok(command) => {
+                        // ack 和 resume 同时更新发送任务里的流控状态
+                        if let Some(update) = command.flow_control_update() {
+                            if cmd_tx.send(update).await.is_err() {
+                                tracing::warn!("Failed to send flow control command");
+                                return Err(());
+                            }
+                        }
+                        Self::handle_command(command, state, user_id, room_id).await
+                    }
+                    Err(err) => {
+                        tracing::debug!(error = %err, "无法解析的客户端消息");
+                        Some(ServerReply::error("INVALID_COMMAND", err.to_string()))
+                    }
+                };
+                let Some(reply) = reply else {
+                    return Ok(());
+                };
+                let payload = match serde_json::to_string(&reply) {
+                    Ok(json) => json,
+                    Err(err) => {
+                        tracing::warn!(error = %err, "failed to serialize websocket reply");
+                        return Ok(());
+                    }
+                };
+                if cmd_tx.send(WsCommand::SendText(payload)).await.is_err() {
+                    tracing::warn!("Failed to send reply command");
+                    return Err(());
+                }
+            }
+            WsMessage::Binary(_) => {
+                tracing::debug!("收到二进制客户端消息，忽略");
             }
         }
         Ok(())
     }
+
+    /// 客户端 ping 或回应服务端 ping 时续期会话心跳
+    async fn refresh_heartbeat(
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+        session_id: Uuid,
+    ) {
+        if let Err(err) = state
+            .presence_manager
+            .heartbeat(room_id, user_id, session_id)
+            .await
+        {
+            tracing::warn!(error = %err, %session_id, "Failed to refresh session heartbeat");
+        }
+    }
+
+    /// 执行客户端命令，结果只回复给当前连接，不经过广播；None 表示无需回复
+    async fn handle_command(
+        command: ClientCommand,
+        state: &AppState,
+        user_id: UserId,
+        room_id: RoomId,
+    ) -> Option<ServerReply> {
+        match command {
+            ClientCommand::Ack { message_id } => {
+                // 只更新发给本用户的投递记录，不是发给他的消息自然匹配不到，静默忽略
+                if let Err(err) = state
+                    .storage
+                    .delivery_repository
+                    .mark_delivered(MessageId::from(message_id), user_id, chrono::Utc::now())
+                    .await
+                {
+                    tracing::warn!(error = %err, %message_id, %user_id, "标记消息送达失败");
+                }
+                None
+            }
+            ClientCommand::Resume {
+                room_id: requested_room,
+                last_seq,
+            } => {
+                // 一个连接只订阅一个房间，跨房间 resume 没有意义
+                if RoomId::from(requested_room) != room_id {
+                    return Some(ServerReply::error(
+                        "ROOM_MISMATCH",
+                        "resume room_id must match the connected room",
+                    ));
+                }
+
+                let resumed = match state
+                    .chat_service
+                    .resume_room(ResumeRoomRequest {
+                        room_id: requested_room,
+                        user_id: Uuid::from(user_id),
+                        last_seq,
+                        limit: RESUME_BATCH_LIMIT,
+                    })
+                    .await
+                {
+                    Ok(resumed) => resumed,
+                    Err(err) => {
+                        tracing::warn!(error = %err, %room_id, %user_id, "resume 失败");
+                        return Some(ServerReply::error("RESUME_FAILED", err.to_string()));
+                    }
+                };
+
+                let online_users = match state.presence_manager.get_online_users(room_id).await {
+                    Ok(users) => users,
+                    Err(err) => {
+                        tracing::warn!(error = %err, %room_id, "resume 时获取在线用户失败");
+                        Vec::new()
+                    }
+                };
+
+                Some(ServerReply::Resumed(ResumeBatch {
+                    state: resumed,
+                    online_users,
+                }))
+            }
+            ClientCommand::Resync { from_seq } => {
+                match state
+                    .chat_service
+                    .replay_from_seq(
+                        Uuid::from(room_id),
+                        Uuid::from(user_id),
+                        from_seq,
+                        RESUME_BATCH_LIMIT,
+                    )
+                    .await
+                {
+                    Ok(replay) => Some(ServerReply::Resynced { room_id, replay }),
+                    Err(err) => {
+                        tracing::warn!(error = %err, %room_id, %user_id, from_seq, "resync 失败");
+                        Some(ServerReply::error("RESYNC_FAILED", err.to_string()))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 客户端发来的 WebSocket 命令
+///
+/// 与服务端消息使用相同的 `{"type": ..., "payload": ...}` 结构
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+enum ClientCommand {
+    /// 断线重连：一次往返拿回错过的消息、未读数、在线成员和房间设置
+    Resume { room_id: Uuid, last_seq: i64 },
+    /// 客户端发现序列号缺口：重放当前房间序列号不小于 `from_seq` 的消息
+    Resync { from_seq: i64 },
+    /// 客户端确认收到消息，标记为已送达
+    Ack { message_id: Uuid },
+}
+
+impl ClientCommand {
+    fn flow_control_update(&self) -> Option<WsCommand> {
+        match self {
+            ClientCommand::Ack { message_id } => {
+                Some(WsCommand::Acked(MessageId::from(*message_id)))
+            }
+            ClientCommand::Resume { .. } | ClientCommand::Resync { .. } => {
+                Some(WsCommand::Resynced)
+            }
+        }
+    }
+}
+
+/// 只回复给请求方的服务端消息
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+enum ServerReply {
+    Resumed(ResumeBatch),
+    /// resync 的重放结果；`has_more` 为 true 时客户端从 `next_seq` 继续请求
+    Resynced {
+        room_id: RoomId,
+        #[serde(flatten)]
+        replay: SequenceReplay,
+    },
+    /// 连接建立时的房间在线成员快照，之后由 presence 广播增量更新
+    Roster {
+        room_id: RoomId,
+        online: Vec<UserId>,
+    },
+    /// 重连缺口超过补发上限，或未 ack 的消息过多被暂停推送，
+    /// 客户端应丢弃本地状态，通过 resume 或历史接口重新同步
+    ResyncRequired {
+        room_id: RoomId,
+        missed: Option<i64>,
+    },
+    /// 房间已关闭，连接只接收消息，客户端应禁用发送
+    RoomReadOnly {
+        room_id: RoomId,
+    },
+    Error {
+        code: &'static str,
+        message: String,
+    },
+}
+
+impl ServerReply {
+    fn error(code: &'static str, message: impl Into<String>) -> Self {
+        ServerReply::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// resume 的完整响应
+///
+/// resume 与实时广播之间可能有重叠，客户端按 seq 去重即可
+#[derive(Debug, Serialize)]
+struct ResumeBatch {
+    #[serde(flatten)]
+    state: RoomResumeState,
+    online_users: Vec<UserId>,
+}
+
+/// 重连补发的待发送帧，以及用于实时流去重的信息
+#[derive(Debug, Default)]
+struct Backfill {
+    frames: Vec<String>,
+    message_ids: HashSet<MessageId>,
+    /// 已补发消息的最大序列号，没有补发时为 0
+    last_seq: i64,
+}
+
+/// 连接级的屏蔽名单缓存，过期后在下一条消息到达时重新加载
+struct BlockFilter {
+    blocked: HashSet<UserId>,
+    loaded_at: Instant,
+}
+
+impl BlockFilter {
+    /// 加载失败时按空名单处理，只记日志，不影响连接
+    async fn load(state: &AppState, user_id: UserId) -> Self {
+        let blocked = match state.chat_service.blocked_user_ids(user_id).await {
+            Ok(blocked) => blocked,
+            Err(err) => {
+                tracing::warn!(error = %err, %user_id, "加载屏蔽名单失败");
+                HashSet::new()
+            }
+        };
+        Self {
+            blocked,
+            loaded_at: Instant::now(),
+        }
+    }
+
+    async fn is_blocked(&mut self, state: &AppState, user_id: UserId, sender_id: UserId) -> bool {
+        if self.loaded_at.elapsed() >= BLOCK_LIST_TTL {
+            *self = Self::load(state, user_id).await;
+        }
+        self.blocked.contains(&sender_id)
+    }
+}
+
+/// 连接级的推送流控：跟踪已推送未 ack 的消息
+///
+/// 超过上限时标记为落后，不再推送新广播（而不是让发送队列无限增长），
+/// 客户端 resume 后恢复。只统计实时推送的聊天消息，补发的消息不计入。
+struct FlowControl {
+    max_unacked: usize,
+    unacked: HashSet<MessageId>,
+    lagging: bool,
+}
+
+impl FlowControl {
+    fn new(max_unacked: usize) -> Self {
+        Self {
+            max_unacked,
+            unacked: HashSet::new(),
+            lagging: false,
+        }
+    }
+
+    fn is_lagging(&self) -> bool {
+        self.lagging
+    }
+
+    fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// 记录即将推送的消息；已达上限时转为落后状态并返回 false
+    fn track(&mut self, message_id: MessageId) -> bool {
+        if self.max_unacked == 0 {
+            return true;
+        }
+        if self.unacked.len() >= self.max_unacked {
+            self.lagging = true;
+            return false;
+        }
+        self.unacked.insert(message_id);
+        true
+    }
+
+    fn ack(&mut self, message_id: MessageId) {
+        self.unacked.remove(&message_id);
+    }
+
+    /// 客户端已重新同步，之前的未 ack 消息由 resume 覆盖
+    fn reset(&mut self) {
+        self.unacked.clear();
+        self.lagging = false;
+    }
 }
 
 /// WebSocket 写操作命令
@@ -262,10 +1127,15 @@ impl WebSocketConnection {
 enum WsCommand {
     SendText(String),
     SendPong(Vec<u8>),
+    /// 客户端 ack 了一条消息
+    Acked(MessageId),
+    /// 客户端发起 resume，流控状态复位
+    Resynced,
 }
 
 impl Drop for WebSocketConnection {
     fn drop(&mut self) {
+        crate::metrics::ws_connection_closed();
         tracing::info!(
             user_id = %self.user_id,
             room_id = %self.room_id,
@@ -0,0 +1,74 @@
+//! `chatroom.deflate` 子协议：自定义的出站消息压缩，不是 RFC 7692 的 permessage-deflate
+//!
+//! 当前使用的 tungstenite 不支持 permessage-deflate 扩展（收到 RSV1 帧会直接报错），
+//! 这里改用子协议协商：客户端握手时在 `Sec-WebSocket-Protocol` 里带上 [`DEFLATE_SUBPROTOCOL`]，
+//! 服务端开启该子协议时选中它，之后超过阈值的文本消息以原始 deflate（RFC 1951）压缩后的二进制帧发送，
+//! 客户端解压后得到原来的 JSON。小消息、ping/pong 和关闭帧保持原样，客户端发来的帧不压缩。
+//! 只有实现了这份自定义格式的客户端才能选用，线上格式见 `docs/features/WebSocket自定义压缩子协议.md`。
+
+use std::io::Write;
+
+use axum::extract::ws::Message as WsMessage;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+/// 自定义压缩子协议名
+pub const DEFLATE_SUBPROTOCOL: &str = "chatroom.deflate";
+
+/// chatroom.deflate 子协议参数
+#[derive(Debug, Clone, Copy)]
+pub struct WsDeflateSubprotocol {
+    /// 是否允许客户端选用该子协议
+    pub enabled: bool,
+    /// deflate 压缩级别，0-9
+    pub level: u32,
+    /// 小于该字节数的消息不压缩
+    pub min_size: usize,
+}
+
+impl WsDeflateSubprotocol {
+    pub fn from_app_config(app_config: &config::AppConfig) -> Self {
+        Self::from(&app_config.ws.deflate_subprotocol)
+    }
+
+    /// 压缩一帧待发送的消息；只处理达到阈值的文本帧，压缩失败时原样发送
+    pub fn encode(&self, frame: WsMessage) -> WsMessage {
+        match frame {
+            WsMessage::Text(text) if text.len() >= self.min_size => {
+                match self.deflate(text.as_bytes()) {
+                    Ok(compressed) => WsMessage::Binary(compressed.into()),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "failed to compress websocket frame");
+                        WsMessage::Text(text)
+                    }
+                }
+            }
+            other => other,
+        }
+    }
+
+    fn deflate(&self, payload: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut encoder = DeflateEncoder::new(
+            Vec::with_capacity(payload.len() / 2),
+            Compression::new(self.level.min(9)),
+        );
+        encoder.write_all(payload)?;
+        encoder.finish()
+    }
+}
+
+impl From<&config::WsDeflateSubprotocolConfig> for WsDeflateSubprotocol {
+    fn from(subprotocol: &config::WsDeflateSubprotocolConfig) -> Self {
+        Self {
+            enabled: subprotocol.enabled,
+            level: subprotocol.level,
+            min_size: subprotocol.min_size_bytes,
+        }
+    }
+}
+
+impl Default for WsDeflateSubprotocol {
+    fn default() -> Self {
+        Self::from(&config::WsDeflateSubprotocolConfig::default())
+    }
+}
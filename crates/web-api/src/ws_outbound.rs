@@ -0,0 +1,182 @@
+//! WebSocket 连接的发送缓冲
+//!
+//! 转发广播和写 socket 分开：转发只把帧放进有界缓冲，由单独的写任务发给客户端。
+//! 客户端接收太慢时缓冲会写满，按配置丢弃最旧的帧或断开连接，慢客户端不会拖住转发，
+//! 也不会让服务端内存无限增长。丢弃最旧的帧时，缓冲持续写满超过时限也会断开连接，
+//! 不再为一直收不动的客户端反复丢帧。
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::extract::ws::{close_code, CloseFrame, Message as WsMessage};
+use config::WsSlowClientPolicy;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// 因为接收太慢被断开时关闭帧里的原因
+pub const TOO_SLOW_REASON: &str = "TOO_SLOW";
+
+/// 放入一帧数据的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// 缓冲已满，丢掉了最旧的一帧
+    DroppedOldest,
+    /// 缓冲已满，已清空缓冲并排入关闭帧，调用方应结束连接
+    Disconnected,
+    /// 连接正在关闭，帧被忽略
+    Closed,
+}
+
+#[derive(Default)]
+struct QueueState {
+    frames: VecDeque<WsMessage>,
+    /// 不再接收新帧，写完已缓冲的帧后结束
+    closing: bool,
+    dropped: u64,
+    /// 缓冲从什么时候开始一直是满的，写任务取走一帧后清空
+    full_since: Option<Instant>,
+}
+
+/// 单个连接的有界发送缓冲，只有一个写任务消费
+pub struct OutboundQueue {
+    capacity: usize,
+    policy: WsSlowClientPolicy,
+    stall_timeout: Option<Duration>,
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+impl OutboundQueue {
+    pub fn new(capacity: usize, policy: WsSlowClientPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy,
+            stall_timeout: None,
+            state: Mutex::new(QueueState::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// 缓冲持续写满超过该时长时断开连接，`None` 表示不限制
+    pub fn with_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// 放入一帧数据；缓冲已满时按策略丢弃最旧的帧或断开连接
+    pub fn push(&self, frame: WsMessage) -> PushOutcome {
+        let mut state = self.lock();
+        if state.closing {
+            return PushOutcome::Closed;
+        }
+
+        let mut outcome = PushOutcome::Queued;
+        if state.frames.len() >= self.capacity {
+            let now = Instant::now();
+            let full_since = *state.full_since.get_or_insert(now);
+            let stalled = self
+                .stall_timeout
+                .is_some_and(|timeout| now.duration_since(full_since) >= timeout);
+
+            match self.policy {
+                WsSlowClientPolicy::DropOldest if !stalled => {
+                    state.frames.pop_front();
+                    state.dropped += 1;
+                    crate::metrics::ws_frame_dropped();
+                    outcome = PushOutcome::DroppedOldest;
+                }
+                _ => {
+                    // 没发出去的帧对要断开的客户端已经没用，只留关闭帧
+                    state.dropped += state.frames.len() as u64 + 1;
+                    state.frames.clear();
+                    state.frames.push_back(WsMessage::Close(Some(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: TOO_SLOW_REASON.into(),
+                    })));
+                    state.closing = true;
+                    drop(state);
+                    crate::metrics::ws_slow_client_disconnected(if stalled {
+                        "stalled"
+                    } else {
+                        "buffer_full"
+                    });
+                    self.notify.notify_one();
+                    return PushOutcome::Disconnected;
+                }
+            }
+        }
+        state.frames.push_back(frame);
+        drop(state);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// 放入 ping/pong 等控制帧，不受容量限制
+    pub fn push_control(&self, frame: WsMessage) {
+        let mut state = self.lock();
+        if state.closing {
+            return;
+        }
+        state.frames.push_back(frame);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// 写完已缓冲的帧后发送关闭帧，之后不再接收新帧
+    pub fn close(&self, frame: Option<CloseFrame>) {
+        let mut state = self.lock();
+        if state.closing {
+            return;
+        }
+        state.frames.push_back(WsMessage::Close(frame));
+        state.closing = true;
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// 不再接收新帧，写任务写完已缓冲的帧后结束
+    pub fn finish(&self) {
+        self.lock().closing = true;
+        self.notify.notify_one();
+    }
+
+    /// 取出下一帧；缓冲已空且连接正在关闭时返回 None
+    pub async fn pop(&self) -> Option<WsMessage> {
+        loop {
+            {
+                let mut state = self.lock();
+                if let Some(frame) = state.frames.pop_front() {
+                    // 客户端还在收，缓冲不再算作一直写满
+                    state.full_since = None;
+                    return Some(frame);
+                }
+                if state.closing {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// 等待写出的帧数
+    pub fn len(&self) -> usize {
+        self.lock().frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 因为缓冲写满被丢弃的帧数
+    pub fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,259 @@
+//! 本实例的 WebSocket 连接登记
+//!
+//! 限制整个实例和单个用户的连接数：实例满了拒绝新连接；单个用户超限时按配置
+//! 关闭该用户最早的连接或拒绝新连接。被挤掉的连接收到通知后自行关闭并清理在线状态。
+//!
+//! 停机时登记表不再接受新连接，并通知所有连接发送关闭帧后自行结束。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use config::{AppConfig, WsConfig, WsOverflowPolicy};
+use domain::UserId;
+use tokio::sync::{oneshot, watch, Notify};
+use uuid::Uuid;
+
+/// 连接数上限
+#[derive(Debug, Clone, Copy)]
+pub struct WsConnectionLimits {
+    pub max_connections: usize,
+    pub max_connections_per_user: usize,
+    pub per_user_overflow: WsOverflowPolicy,
+}
+
+impl WsConnectionLimits {
+    pub fn from_app_config(app_config: &AppConfig) -> Self {
+        Self::from(&app_config.ws)
+    }
+}
+
+impl From<&WsConfig> for WsConnectionLimits {
+    fn from(ws: &WsConfig) -> Self {
+        Self {
+            max_connections: ws.max_connections,
+            max_connections_per_user: ws.max_connections_per_user,
+            per_user_overflow: ws.per_user_overflow,
+        }
+    }
+}
+
+impl Default for WsConnectionLimits {
+    fn default() -> Self {
+        Self::from(&WsConfig::default())
+    }
+}
+
+/// 新连接被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRejected {
+    /// 本实例连接数已满
+    ServerFull { max: usize },
+    /// 该用户的连接数已满且配置为拒绝新连接
+    UserLimit { max: usize },
+    /// 服务正在停机
+    ShuttingDown,
+}
+
+impl ConnectionRejected {
+    /// 关闭帧里的原因
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ConnectionRejected::ServerFull { .. } => "SERVER_AT_CAPACITY",
+            ConnectionRejected::UserLimit { .. } => "CONNECTION_LIMIT_EXCEEDED",
+            ConnectionRejected::ShuttingDown => SERVER_RESTARTING_REASON,
+        }
+    }
+}
+
+/// 连接被新连接挤掉时关闭帧里的原因
+pub const EVICTED_REASON: &str = "REPLACED_BY_NEWER_CONNECTION";
+
+/// 服务停机时关闭帧里的原因，客户端应稍后重连
+pub const SERVER_RESTARTING_REASON: &str = "SERVER_RESTARTING";
+
+struct Entry {
+    id: Uuid,
+    evict: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct Connections {
+    total: usize,
+    /// 每个用户的连接，按建立时间排列
+    by_user: HashMap<UserId, VecDeque<Entry>>,
+}
+
+/// 本实例的连接登记表
+pub struct ConnectionRegistry {
+    limits: WsConnectionLimits,
+    connections: Mutex<Connections>,
+    /// 停机标记，已登记的连接通过凭证订阅
+    closing: watch::Sender<bool>,
+    /// 最后一个连接释放时通知等待停机的一方
+    idle: Notify,
+}
+
+impl ConnectionRegistry {
+    pub fn new(limits: WsConnectionLimits) -> Self {
+        Self {
+            limits,
+            connections: Mutex::new(Connections::default()),
+            closing: watch::Sender::new(false),
+            idle: Notify::new(),
+        }
+    }
+
+    pub fn limits(&self) -> WsConnectionLimits {
+        self.limits
+    }
+
+    /// 登记新连接；返回的凭证在连接结束时释放名额
+    pub fn register(
+        self: &Arc<Self>,
+        user_id: UserId,
+    ) -> Result<ConnectionTicket, ConnectionRejected> {
+        let mut connections = self.lock();
+        // 在锁内检查：shutdown 之后不会再有新连接漏进来
+        if self.is_closing() {
+            return Err(ConnectionRejected::ShuttingDown);
+        }
+        let user_count = connections.by_user.get(&user_id).map_or(0, VecDeque::len);
+
+        let evict_oldest = user_count >= self.limits.max_connections_per_user;
+        if evict_oldest && self.limits.per_user_overflow == WsOverflowPolicy::RejectNew {
+            return Err(ConnectionRejected::UserLimit {
+                max: self.limits.max_connections_per_user,
+            });
+        }
+        // 挤掉自己的旧连接不增加总数，不受实例上限影响
+        if !evict_oldest && connections.total >= self.limits.max_connections {
+            return Err(ConnectionRejected::ServerFull {
+                max: self.limits.max_connections,
+            });
+        }
+
+        let id = Uuid::new_v4();
+        let (evict, evicted) = oneshot::channel();
+        let user_connections = connections.by_user.entry(user_id).or_default();
+        let mut evicted_count = 0;
+        while user_connections.len() >= self.limits.max_connections_per_user {
+            let Some(oldest) = user_connections.pop_front() else {
+                break;
+            };
+            // 连接已经在关闭时接收端不在了，忽略即可
+            let _ = oldest.evict.send(());
+            evicted_count += 1;
+        }
+        user_connections.push_back(Entry { id, evict });
+        connections.total = connections.total + 1 - evicted_count;
+        if evicted_count > 0 {
+            tracing::info!(%user_id, evicted_count, "用户连接数超过上限，关闭最早的连接");
+        }
+
+        Ok(ConnectionTicket {
+            registry: self.clone(),
+            user_id,
+            id,
+            evicted: Some(evicted),
+            closing: self.closing.subscribe(),
+        })
+    }
+
+    pub fn is_closing(&self) -> bool {
+        *self.closing.borrow()
+    }
+
+    /// 开始停机：拒绝新连接，通知已登记的连接发送关闭帧后结束
+    pub fn shutdown(&self) {
+        // 持锁修改，和 register 里的检查互斥
+        let _connections = self.lock();
+        self.closing.send_replace(true);
+    }
+
+    /// 等待所有连接结束（包括清理在线状态）；超时返回 false，剩余数量见 [`Self::total`]
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let idle = self.idle.notified();
+                tokio::pin!(idle);
+                // 先登记等待再检查数量，避免错过最后一个连接的通知
+                idle.as_mut().enable();
+                if self.total() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// 本实例当前的连接数
+    pub fn total(&self) -> usize {
+        self.lock().total
+    }
+
+    /// 用户在本实例上的连接数
+    pub fn user_connections(&self, user_id: UserId) -> usize {
+        self.lock().by_user.get(&user_id).map_or(0, VecDeque::len)
+    }
+
+    fn release(&self, user_id: UserId, id: Uuid) {
+        let mut connections = self.lock();
+        let Some(user_connections) = connections.by_user.get_mut(&user_id) else {
+            return;
+        };
+        // 被挤掉的连接已经移出登记表，不再重复扣减
+        let Some(position) = user_connections.iter().position(|entry| entry.id == id) else {
+            return;
+        };
+        user_connections.remove(position);
+        if user_connections.is_empty() {
+            connections.by_user.remove(&user_id);
+        }
+        connections.total -= 1;
+        if connections.total == 0 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connections> {
+        self.connections
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for ConnectionRegistry {
+    fn default() -> Self {
+        Self::new(WsConnectionLimits::default())
+    }
+}
+
+/// 已登记连接的凭证，销毁时释放名额
+pub struct ConnectionTicket {
+    registry: Arc<ConnectionRegistry>,
+    user_id: UserId,
+    id: Uuid,
+    evicted: Option<oneshot::Receiver<()>>,
+    closing: watch::Receiver<bool>,
+}
+
+impl ConnectionTicket {
+    /// 停机通知：值变为 true 时连接应发送关闭帧后结束
+    pub fn shutdown_signal(&self) -> watch::Receiver<bool> {
+        self.closing.clone()
+    }
+
+    /// 连接被同一用户的新连接挤掉时收到通知；只能取一次
+    pub fn take_eviction(&mut self) -> Option<oneshot::Receiver<()>> {
+        self.evicted.take()
+    }
+}
+
+impl Drop for ConnectionTicket {
+    fn drop(&mut self) {
+        self.registry.release(self.user_id, self.id);
+    }
+}
@@ -0,0 +1,141 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn block_list_and_unblock() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, blocker_token) = register_and_login(&app, &format!("blocker-{suffix}")).await;
+    let (blocked_id, blocked_token) = register_and_login(&app, &format!("blocked-{suffix}")).await;
+
+    let (status, _) = send(
+        &app,
+        "PUT",
+        &format!("/api/v1/me/blocks/{blocked_id}"),
+        Some(&blocker_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 屏蔽后列表立即可见，且只返回公开资料
+    let (status, blocks) = send(&app, "GET", "/api/v1/me/blocks", Some(&blocker_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let blocks = blocks.as_array().unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0]["id"], blocked_id.as_str());
+    assert!(blocks[0].get("email").is_none());
+
+    // 被屏蔽者看不到谁屏蔽了自己
+    let (status, blocks) = send(&app, "GET", "/api/v1/me/blocks", Some(&blocked_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(blocks.as_array().unwrap().is_empty());
+
+    // 取消屏蔽后立即从列表移除，重复取消幂等
+    for _ in 0..2 {
+        let (status, _) = send(
+            &app,
+            "DELETE",
+            &format!("/api/v1/me/blocks/{blocked_id}"),
+            Some(&blocker_token),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    let (status, blocks) = send(&app, "GET", "/api/v1/me/blocks", Some(&blocker_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(blocks.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn blocked_sender_is_hidden_from_blocker_history() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, blocker_token) = register_and_login(&app, &format!("hist-blocker-{suffix}")).await;
+    let (blocked_id, blocked_token) =
+        register_and_login(&app, &format!("hist-blocked-{suffix}")).await;
+
+    let (status, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&blocker_token),
+        Some(json!({ "name": format!("block-history-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let room_id = room["id"].as_str().unwrap().to_string();
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/join"),
+        Some(&blocked_token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    for (token, content) in [
+        (&blocked_token, "from blocked"),
+        (&blocker_token, "from blocker"),
+    ] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            &format!("/api/v1/rooms/{room_id}/messages"),
+            Some(token),
+            Some(json!({ "content": content, "message_type": "Text" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/users/{blocked_id}/block"),
+        Some(&blocker_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let history = |token: String| {
+        let app = app.clone();
+        let uri = format!("/api/v1/rooms/{room_id}/messages");
+        async move {
+            let (status, page) = send(&app, "GET", &uri, Some(&token), None).await;
+            assert_eq!(status, StatusCode::OK);
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|message| message["content"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // 屏蔽者看不到被屏蔽者的消息，被屏蔽者照常看到全部
+    assert_eq!(history(blocker_token.clone()).await, ["from blocker"]);
+    assert_eq!(history(blocked_token.clone()).await.len(), 2);
+
+    // 取消屏蔽后立即恢复
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!("/api/v1/users/{blocked_id}/block"),
+        Some(&blocker_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(history(blocker_token).await.len(), 2);
+}
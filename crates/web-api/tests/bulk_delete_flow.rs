@@ -0,0 +1,141 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+async fn create_room(app: &axum::Router, token: &str, name: &str) -> String {
+    let (status, room) = send(
+        app,
+        "POST",
+        "/api/v1/rooms",
+        Some(token),
+        Some(json!({ "name": name, "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    room["id"].as_str().unwrap().to_string()
+}
+
+async fn post_message(app: &axum::Router, token: &str, room_id: &str, content: &str) -> String {
+    let (status, message) = send(
+        app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(token),
+        Some(json!({ "content": content, "message_type": "Text" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    message["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn bulk_delete_only_touches_the_target_room() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, owner) = register_and_login(&app, &format!("purger-{suffix}")).await;
+
+    let target = create_room(&app, &owner, &format!("purge-target-{suffix}")).await;
+    let other = create_room(&app, &owner, &format!("purge-other-{suffix}")).await;
+    let spam_a = post_message(&app, &owner, &target, "spam a").await;
+    let spam_b = post_message(&app, &owner, &target, "spam b").await;
+    let keep = post_message(&app, &owner, &target, "keep").await;
+    let foreign = post_message(&app, &owner, &other, "other room").await;
+
+    // 其他房间的ID和不存在的ID被忽略，只统计实际删除的条数
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{target}/messages/bulk-delete"),
+        Some(&owner),
+        Some(json!({ "message_ids": [spam_a, spam_b, foreign, Uuid::new_v4()] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted"], 2);
+
+    // 已删除的消息再次提交不重复计数
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{target}/messages/bulk-delete"),
+        Some(&owner),
+        Some(json!({ "message_ids": [spam_a] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["deleted"], 0);
+
+    let (_, history) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{target}/messages"),
+        Some(&owner),
+        None,
+    )
+    .await;
+    let remaining: Vec<&str> = history["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(remaining, [keep.as_str()]);
+
+    let (_, history) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{other}/messages"),
+        Some(&owner),
+        None,
+    )
+    .await;
+    assert_eq!(history["items"][0]["id"], foreign.as_str());
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn bulk_delete_rejects_oversized_batches_and_plain_members() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, owner) = register_and_login(&app, &format!("purge-owner-{suffix}")).await;
+    let (_, member) = register_and_login(&app, &format!("purge-member-{suffix}")).await;
+    let room_id = create_room(&app, &owner, &format!("purge-cap-{suffix}")).await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/join"),
+        Some(&member),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let too_many: Vec<Uuid> = (0..101).map(|_| Uuid::new_v4()).collect();
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages/bulk-delete"),
+        Some(&owner),
+        Some(json!({ "message_ids": too_many })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ARGUMENT");
+
+    // 普通成员没有删除他人消息的权限
+    let message_id = post_message(&app, &member, &room_id, "mine").await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages/bulk-delete"),
+        Some(&member),
+        Some(json!({ "message_ids": [message_id] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
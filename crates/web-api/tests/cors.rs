@@ -0,0 +1,102 @@
+//! CORS：允许的来源在预检响应中拿到跨域头，其他来源拿不到
+
+use axum::routing::get;
+use axum::Router;
+use config::CorsConfig;
+use reqwest::{Method, Response};
+use tokio::net::TcpListener;
+use web_api::cors_layer;
+
+async fn serve(config: CorsConfig) -> String {
+    let mut app = Router::new().route("/api/v1/rooms", get(|| async { "ok" }));
+    if let Some(cors) = cors_layer(&config) {
+        app = app.layer(cors);
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}/api/v1/rooms")
+}
+
+async fn preflight(url: &str, origin: &str) -> Response {
+    reqwest::Client::new()
+        .request(Method::OPTIONS, url)
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header(
+            "Access-Control-Request-Headers",
+            "authorization,content-type",
+        )
+        .send()
+        .await
+        .unwrap()
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn preflight_from_allowed_origin_gets_cors_headers() {
+    let url = serve(CorsConfig {
+        allowed_origins: vec!["https://chat.example.com".to_string()],
+        allow_credentials: true,
+        ..CorsConfig::default()
+    })
+    .await;
+
+    let response = preflight(&url, "https://chat.example.com").await;
+    assert!(response.status().is_success());
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("https://chat.example.com")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-credentials"),
+        Some("true")
+    );
+    let methods = header(&response, "access-control-allow-methods").unwrap();
+    assert!(methods.contains("POST"), "{methods}");
+    let headers = header(&response, "access-control-allow-headers")
+        .unwrap()
+        .to_ascii_lowercase();
+    assert!(headers.contains("authorization"), "{headers}");
+    assert!(headers.contains("content-type"), "{headers}");
+}
+
+#[tokio::test]
+async fn preflight_from_other_origin_gets_no_cors_headers() {
+    let url = serve(CorsConfig {
+        allowed_origins: vec!["https://chat.example.com".to_string()],
+        ..CorsConfig::default()
+    })
+    .await;
+
+    let response = preflight(&url, "https://evil.example.com").await;
+    assert_eq!(header(&response, "access-control-allow-origin"), None);
+    assert_eq!(header(&response, "access-control-allow-credentials"), None);
+}
+
+#[tokio::test]
+async fn no_cors_headers_without_configured_origins() {
+    assert!(cors_layer(&CorsConfig::default()).is_none());
+
+    let url = serve(CorsConfig::default()).await;
+    let response = preflight(&url, "https://chat.example.com").await;
+    assert_eq!(header(&response, "access-control-allow-origin"), None);
+}
+
+#[tokio::test]
+async fn wildcard_origin_allows_any_origin() {
+    let url = serve(CorsConfig {
+        allowed_origins: vec!["*".to_string()],
+        ..CorsConfig::default()
+    })
+    .await;
+
+    let response = preflight(&url, "http://localhost:5173").await;
+    assert_eq!(header(&response, "access-control-allow-origin"), Some("*"));
+}
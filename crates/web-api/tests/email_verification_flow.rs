@@ -0,0 +1,129 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use support::{send, setup_test_app_with, TestConfig};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn registration_requires_email_verification() {
+    let mut config = TestConfig::default();
+    config.app_config.registration.require_email_verification = true;
+    let test_app = setup_test_app_with(config).await;
+    let app = &test_app.router;
+    let email = "verify@example.com";
+    let credentials = json!({ "email": email, "password": "secret" });
+
+    let (status, user) = send(
+        app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({ "username": "verify-user", "email": email, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(user["status"], "PendingVerification");
+    assert_eq!(test_app.email_sender.sent_count(email), 1);
+
+    // 未验证时登录返回单独的错误码
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "EMAIL_NOT_VERIFIED");
+
+    // 刚发过验证邮件，重发被限流
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/v1/auth/resend-verification",
+        None,
+        Some(json!({ "email": email })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "VERIFICATION_RESEND_TOO_SOON");
+
+    // 不存在的邮箱同样返回 202，不暴露账号是否存在
+    let (status, _) = send(
+        app,
+        "POST",
+        "/api/v1/auth/resend-verification",
+        None,
+        Some(json!({ "email": "nobody@example.com" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let (status, body) = send(app, "GET", "/api/v1/auth/verify?token=bogus", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_VERIFICATION_TOKEN");
+
+    let token = test_app.email_sender.latest_token(email).unwrap();
+    let uri = format!("/api/v1/auth/verify?token={token}");
+    let (status, verified) = send(app, "GET", &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(verified["status"], "Active");
+
+    // 令牌只能使用一次
+    let (status, _) = send(app, "GET", &uri, None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, login) = send(app, "POST", "/api/v1/auth/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(login["token"].is_string());
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn expired_verification_token_is_rejected() {
+    let mut config = TestConfig::default();
+    config.app_config.registration.require_email_verification = true;
+    let test_app = setup_test_app_with(config).await;
+    let app = &test_app.router;
+    let email = "expired-verify@example.com";
+
+    let (status, _) = send(
+        app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({ "username": "expired-verify", "email": email, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = test_app.email_sender.latest_token(email).unwrap();
+
+    sqlx::query(
+        "UPDATE email_verifications SET expires_at = NOW() - INTERVAL '1 minute'
+         WHERE user_id = (SELECT id FROM users WHERE email = $1)",
+    )
+    .bind(email)
+    .execute(&test_app._pool)
+    .await
+    .unwrap();
+
+    let uri = format!("/api/v1/auth/verify?token={token}");
+    let (status, body) = send(app, "GET", &uri, None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_VERIFICATION_TOKEN");
+
+    // 账号仍未激活
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(json!({ "email": email, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "EMAIL_NOT_VERIFIED");
+}
@@ -0,0 +1,123 @@
+//! 优雅停机：停机前开始的请求照常完成，停机后不再接受新连接，WebSocket 连接收到通知后排空
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::routing::get;
+use axum::Router;
+use domain::UserId;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Notify};
+use uuid::Uuid;
+use web_api::{serve_with_graceful_shutdown, ConnectionRegistry, ConnectionRejected};
+
+/// 处理器开始执行后通知测试，再等一会儿才返回，模拟停机时正在进行的请求
+fn slow_app(started: Arc<Notify>) -> Router {
+    Router::new().route(
+        "/slow",
+        get(move || {
+            let started = started.clone();
+            async move {
+                started.notify_one();
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }
+        }),
+    )
+}
+
+async fn read_response(mut stream: TcpStream) -> String {
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn in_flight_request_completes_and_new_connections_are_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let started = Arc::new(Notify::new());
+    let connections = Arc::new(ConnectionRegistry::default());
+    let (signal_tx, signal_rx) = oneshot::channel::<()>();
+
+    let server = tokio::spawn(serve_with_graceful_shutdown(
+        listener,
+        slow_app(started.clone()),
+        connections.clone(),
+        async move {
+            let _ = signal_rx.await;
+        },
+        Duration::from_secs(5),
+    ));
+
+    // 停机前已建立的 WebSocket 连接：收到通知后清理完才释放名额
+    let mut ticket = connections.register(UserId::from(Uuid::new_v4())).unwrap();
+    let mut closing = ticket.shutdown_signal();
+    let _ = ticket.take_eviction();
+    let websocket = tokio::spawn(async move {
+        let _ = closing.wait_for(|closing| *closing).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(ticket);
+    });
+
+    let mut in_flight = TcpStream::connect(addr).await.unwrap();
+    in_flight
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    started.notified().await;
+
+    signal_tx.send(()).unwrap();
+
+    // 监听端口关闭后新连接被拒绝
+    let refused = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if TcpStream::connect(addr).await.is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(refused.is_ok(), "listener still accepting after shutdown");
+    assert_eq!(
+        connections
+            .register(UserId::from(Uuid::new_v4()))
+            .err()
+            .expect("registration after shutdown should fail"),
+        ConnectionRejected::ShuttingDown
+    );
+
+    let response = read_response(in_flight).await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with("done"), "{response}");
+
+    websocket.await.unwrap();
+    let drained = server.await.unwrap().unwrap();
+    assert!(drained);
+    assert_eq!(connections.total(), 0);
+}
+
+#[tokio::test]
+async fn drain_gives_up_after_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let connections = Arc::new(ConnectionRegistry::default());
+    // 一直不关闭的连接
+    let stuck = connections.register(UserId::from(Uuid::new_v4())).unwrap();
+
+    let drained = serve_with_graceful_shutdown(
+        listener,
+        Router::new(),
+        connections.clone(),
+        async {},
+        Duration::from_millis(200),
+    )
+    .await
+    .unwrap();
+
+    assert!(!drained);
+    assert_eq!(connections.total(), 1);
+    drop(stuck);
+    assert_eq!(connections.total(), 0);
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "graphql")]
+
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn room_query_returns_seeded_room() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (user_id, token) = register_and_login(&app, &format!("gql-{suffix}")).await;
+
+    let room_name = format!("gql-room-{suffix}");
+    let (status, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": room_name, "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let room_id = room["id"].as_str().unwrap();
+
+    let query = json!({
+        "query": "query($id: UUID!) { room(id: $id) { id name ownerId visibility isClosed } me { username } }",
+        "variables": { "id": room_id },
+    });
+    let (status, body) = send(&app, "POST", "/api/v1/graphql", Some(&token), Some(query)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("errors").is_none(), "unexpected errors: {body}");
+
+    let data = &body["data"];
+    assert_eq!(data["room"]["id"], room_id);
+    assert_eq!(data["room"]["name"], room_name.as_str());
+    assert_eq!(data["room"]["ownerId"], user_id.as_str());
+    assert_eq!(data["room"]["visibility"], "PUBLIC");
+    assert_eq!(data["room"]["isClosed"], false);
+    assert_eq!(data["me"]["username"], format!("gql-{suffix}").as_str());
+
+    // 未认证请求与 REST 一样被拒绝
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/graphql",
+        None,
+        Some(json!({ "query": "{ me { username } }" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,56 @@
+mod support;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use support::{build_router, build_router_with};
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, Value) {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.expect("request");
+    let status = response.status();
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let body = serde_json::from_slice(&body_bytes).unwrap_or(json!({}));
+    (status, body)
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn readiness_probe_passes_when_dependencies_are_up() {
+    let app = build_router().await;
+
+    let (status, _) = get(&app, "/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = get(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["failed"], json!([]));
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn readiness_probe_names_unreachable_redis() {
+    let app = build_router_with(|config| {
+        config.redis.url = "redis://127.0.0.1:1".to_string();
+        config.server.readiness_check_timeout_ms = 500;
+    })
+    .await;
+
+    // 存活探针不检查依赖
+    let (status, _) = get(&app, "/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = get(&app, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    let failed = body["failed"].as_array().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["dependency"], "redis");
+}
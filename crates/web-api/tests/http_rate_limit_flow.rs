@@ -0,0 +1,95 @@
+mod support;
+
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use config::EndpointRateLimit;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use support::{build_router_with, send_with_headers};
+
+/// 返回 (状态码, Retry-After 秒数, 响应体)
+async fn post(
+    app: &axum::Router,
+    uri: &str,
+    client_ip: &str,
+    body: Value,
+) -> (StatusCode, Option<u64>, Value) {
+    let (status, headers, body) = send_with_headers(
+        app,
+        "POST",
+        uri,
+        &[("x-forwarded-for", client_ip)],
+        Some(body),
+    )
+    .await;
+    let retry_after = headers
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    (status, retry_after, body)
+}
+
+fn bad_login() -> Value {
+    json!({ "email": format!("nobody-{}@example.com", Uuid::new_v4()), "password": "wrong" })
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn login_is_limited_per_client_ip() {
+    let app = build_router_with(|config| {
+        config.rate_limit.http.endpoints = vec![EndpointRateLimit::new("/api/v1/auth/login", 3, 2)];
+    })
+    .await;
+
+    for _ in 0..3 {
+        let (status, _, _) = post(&app, "/api/v1/auth/login", "10.0.0.1", bad_login()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // 超限后不再校验密码，直接拒绝
+    let (status, retry_after, body) =
+        post(&app, "/api/v1/auth/login", "10.0.0.1", bad_login()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(matches!(retry_after, Some(1..=2)));
+    assert_eq!(body["code"], "TOO_MANY_REQUESTS");
+    assert!(body["message"].as_str().unwrap().contains("retry after"));
+
+    // 其他 IP 和未配置限流的接口不受影响
+    let (status, _, _) = post(&app, "/api/v1/auth/login", "10.0.0.2", bad_login()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let name = format!("limited-{}", &Uuid::new_v4().to_string()[..8]);
+    let (status, _, _) = post(
+        &app,
+        "/api/v1/auth/register",
+        "10.0.0.1",
+        json!({ "username": name, "email": format!("{name}@example.com"), "password": "secret" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // 窗口滑过之后恢复
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let (status, _, _) = post(&app, "/api/v1/auth/login", "10.0.0.1", bad_login()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn forwarded_for_is_ignored_without_trusted_proxy() {
+    let app = build_router_with(|config| {
+        config.rate_limit.http.trust_forwarded_for = false;
+        config.rate_limit.http.endpoints =
+            vec![EndpointRateLimit::new("/api/v1/auth/login", 2, 60)];
+    })
+    .await;
+
+    // 伪造不同的 X-Forwarded-For 也绕不过限流
+    for client_ip in ["10.0.0.1", "10.0.0.2"] {
+        let (status, _, _) = post(&app, "/api/v1/auth/login", client_ip, bad_login()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _, _) = post(&app, "/api/v1/auth/login", "10.0.0.3", bad_login()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,133 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn join_room_by_password() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, owner_token) = register_and_login(&app, &format!("join-owner-{suffix}")).await;
+    let (joiner_id, joiner_token) = register_and_login(&app, &format!("joiner-{suffix}")).await;
+
+    let (status, public_room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&owner_token),
+        Some(json!({ "name": format!("open-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, private_room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&owner_token),
+        Some(json!({ "name": format!("locked-{suffix}"), "visibility": "Private", "password": "opensesame" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let public_join = format!("/api/v1/rooms/{}/join", public_room["id"].as_str().unwrap());
+    let private_join = format!(
+        "/api/v1/rooms/{}/join",
+        private_room["id"].as_str().unwrap()
+    );
+
+    // 公开房间无需密码
+    let (status, member) = send(
+        &app,
+        "POST",
+        &public_join,
+        Some(&joiner_token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(member["user_id"], joiner_id.as_str());
+
+    // 重复加入
+    let (status, body) = send(
+        &app,
+        "POST",
+        &public_join,
+        Some(&joiner_token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "MEMBERSHIP_EXISTS");
+
+    // 密码错误返回剩余尝试次数
+    let (status, body) = send(
+        &app,
+        "POST",
+        &private_join,
+        Some(&joiner_token),
+        Some(json!({ "password": "guess" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "INVALID_ROOM_PASSWORD");
+    assert_eq!(body["remaining_attempts"], 4);
+
+    // 密码正确后加入成功
+    let (status, member) = send(
+        &app,
+        "POST",
+        &private_join,
+        Some(&joiner_token),
+        Some(json!({ "password": "opensesame" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(member["role"], "Member");
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn join_attempts_are_limited() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, owner_token) = register_and_login(&app, &format!("vault-owner-{suffix}")).await;
+    let (_, guesser_token) = register_and_login(&app, &format!("guesser-{suffix}")).await;
+
+    let (_, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&owner_token),
+        Some(json!({ "name": format!("vault-{suffix}"), "visibility": "Private", "password": "opensesame" })),
+    )
+    .await;
+    let join = format!("/api/v1/rooms/{}/join", room["id"].as_str().unwrap());
+
+    for remaining in (0..5).rev() {
+        let (status, body) = send(
+            &app,
+            "POST",
+            &join,
+            Some(&guesser_token),
+            Some(json!({ "password": "wrong" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["remaining_attempts"], remaining);
+    }
+
+    // 次数用完后即使密码正确也被拒绝
+    let (status, body) = send(
+        &app,
+        "POST",
+        &join,
+        Some(&guesser_token),
+        Some(json!({ "password": "opensesame" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "TOO_MANY_JOIN_ATTEMPTS");
+}
@@ -0,0 +1,78 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn member_details_for_present_and_absent_members() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (owner_id, owner_token) = register_and_login(&app, &format!("owner-{suffix}")).await;
+    let (member_id, member_token) = register_and_login(&app, &format!("member-{suffix}")).await;
+    let (outsider_id, outsider_token) =
+        register_and_login(&app, &format!("outsider-{suffix}")).await;
+
+    let (status, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&owner_token),
+        Some(json!({ "name": format!("room-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let room_id = room["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/members"),
+        Some(&owner_token),
+        Some(json!({ "invitee_id": member_id, "auto_accept": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 成员查看房主详情
+    let (status, details) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{room_id}/members/{owner_id}"),
+        Some(&member_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(details["user"]["id"], owner_id.as_str());
+    assert_eq!(details["role"], "Owner");
+    assert!(!details["joined_at"].is_null());
+    assert!(details["last_read_message_id"].is_null());
+    assert!(details["user"].get("email").is_none());
+
+    // 非成员目标返回 404
+    let (status, body) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{room_id}/members/{outsider_id}"),
+        Some(&owner_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "MEMBER_NOT_FOUND");
+
+    // 非成员调用者无权查看
+    let (status, _) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{room_id}/members/{owner_id}"),
+        Some(&outsider_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
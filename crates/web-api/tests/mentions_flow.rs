@@ -0,0 +1,105 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+async fn create_public_room(app: &axum::Router, token: &str, name: &str) -> String {
+    let (status, room) = send(
+        app,
+        "POST",
+        "/api/v1/rooms",
+        Some(token),
+        Some(json!({ "name": name, "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    room["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn mentions_inbox_skips_rooms_the_user_left() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, author) = register_and_login(&app, &format!("author-{suffix}")).await;
+    let reader_name = format!("reader-{suffix}");
+    let (_, reader) = register_and_login(&app, &reader_name).await;
+
+    let joined = create_public_room(&app, &author, &format!("mention-joined-{suffix}")).await;
+    let left = create_public_room(&app, &author, &format!("mention-left-{suffix}")).await;
+    for room_id in [&joined, &left] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            &format!("/api/v1/rooms/{room_id}/join"),
+            Some(&reader),
+            Some(json!({})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let mut mention_ids = Vec::new();
+    for room_id in [&joined, &left] {
+        let (status, message) = send(
+            &app,
+            "POST",
+            &format!("/api/v1/rooms/{room_id}/messages"),
+            Some(&author),
+            Some(json!({ "content": format!("hey @{reader_name}, take a look"), "message_type": "Text" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        mention_ids.push(message["id"].as_str().unwrap().to_string());
+    }
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{left}/leave"),
+        Some(&reader),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 只剩仍在的房间里的提及，且尚未读到
+    let (status, inbox) = send(&app, "GET", "/api/v1/me/mentions", Some(&reader), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(inbox["total_count"], 1);
+    assert_eq!(inbox["items"][0]["id"], mention_ids[0].as_str());
+    assert_eq!(inbox["items"][0]["room_id"], joined.as_str());
+    assert_eq!(inbox["items"][0]["is_read"], false);
+
+    // 读到该消息后标记为已读
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{joined}/read"),
+        Some(&reader),
+        Some(json!({ "message_id": mention_ids[0] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, inbox) = send(&app, "GET", "/api/v1/me/mentions", Some(&reader), None).await;
+    assert_eq!(inbox["items"][0]["is_read"], true);
+
+    // `/users/me/mentions` 与 `/me/mentions` 返回同样的内容
+    let (status, aliased) = send(
+        &app,
+        "GET",
+        "/api/v1/users/me/mentions?page=1&page_size=10",
+        Some(&reader),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(aliased["items"], inbox["items"]);
+
+    // 作者自己不会出现在提及里
+    let (_, own) = send(&app, "GET", "/api/v1/me/mentions", Some(&author), None).await;
+    assert_eq!(own["total_count"], 0);
+}
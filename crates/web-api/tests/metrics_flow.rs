@@ -0,0 +1,105 @@
+mod support;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use serde_json::json;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use support::{build_router, build_router_with, register_and_login, send};
+
+async fn scrape(app: &axum::Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+/// 取出指标样本值，没有样本时为 0
+fn sample(metrics: &str, series: &str) -> f64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.trim().parse().unwrap())
+        .unwrap_or(0.0)
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn sending_a_message_is_counted() {
+    let app = build_router_with(|config| config.server.metrics_enabled = true).await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, token) = register_and_login(&app, &format!("metrics-{suffix}")).await;
+
+    let (status, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": format!("metrics-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let room_id = room["id"].as_str().unwrap();
+
+    let before = scrape(&app).await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(&token),
+        Some(json!({ "content": "hello", "message_type": "Text" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "GET", "/api/v1/me/rooms", Some("not-a-token"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let after = scrape(&app).await;
+
+    assert_eq!(
+        sample(&after, "chat_messages_sent_total") - sample(&before, "chat_messages_sent_total"),
+        1.0
+    );
+    assert!(
+        sample(&after, "chat_broadcast_fanout_seconds_count")
+            > sample(&before, "chat_broadcast_fanout_seconds_count")
+    );
+    assert!(
+        sample(&after, r#"chat_auth_attempts_total{result="success"}"#)
+            > sample(&before, r#"chat_auth_attempts_total{result="success"}"#)
+    );
+    assert!(
+        sample(&after, r#"chat_auth_attempts_total{result="failure"}"#)
+            > sample(&before, r#"chat_auth_attempts_total{result="failure"}"#)
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn metrics_endpoint_is_off_by_default() {
+    let app = build_router().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("request");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
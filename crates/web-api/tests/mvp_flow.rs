@@ -134,7 +134,8 @@ async fn user_to_message_flow() {
             .header("authorization", format!("Bearer {}", owner_token))
             .body(Body::from(
                 json!({
-                    "invitee_id": member_id
+                    "invitee_id": member_id,
+                    "auto_accept": true
                 })
                 .to_string(),
             ))
@@ -162,6 +163,25 @@ async fn user_to_message_flow() {
     .await;
     assert_eq!(status, StatusCode::OK);
     assert_eq!(message_body["content"], "hello");
+    let message_id = message_body["id"].as_str().unwrap().to_string();
+
+    // 发送者在编辑窗口内编辑消息
+    let (status, edited_body) = send_request(
+        &app,
+        Request::builder()
+            .method("PATCH")
+            .uri(format!("/api/v1/rooms/{room_id}/messages/{message_id}"))
+            .header("content-type", "application/json")
+            .header("authorization", format!("Bearer {}", member_token))
+            .body(Body::from(
+                json!({ "content": "hello, edited" }).to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(edited_body["content"], "hello, edited");
+    assert_eq!(edited_body["last_revision"]["content"], "hello");
 
     let (status, history_body) = send_request(
         &app,
@@ -174,7 +194,37 @@ async fn user_to_message_flow() {
     )
     .await;
     assert_eq!(status, StatusCode::OK);
-    let messages = history_body.as_array().expect("array");
+    assert_eq!(history_body["has_more"], false);
+    let messages = history_body["items"].as_array().expect("array");
     assert_eq!(messages.len(), 1);
-    assert_eq!(messages[0]["content"], "hello");
+    assert_eq!(messages[0]["content"], "hello, edited");
+
+    // 发送者删除消息，重复删除保持幂等
+    for _ in 0..2 {
+        let (status, _) = send_request(
+            &app,
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/v1/rooms/{room_id}/messages/{message_id}"))
+                .header("authorization", format!("Bearer {}", member_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let (status, history_body) = send_request(
+        &app,
+        Request::builder()
+            .method("GET")
+            .uri(format!("/api/v1/rooms/{room_id}/messages?format=legacy"))
+            .header("authorization", format!("Bearer {}", member_token))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    // 旧版格式仍是裸数组
+    assert!(history_body.as_array().expect("array").is_empty());
 }
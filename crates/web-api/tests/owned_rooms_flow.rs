@@ -0,0 +1,75 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn owned_filter_lists_private_rooms_hidden_from_discovery() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (owner_id, token) = register_and_login(&app, &format!("creator-{suffix}")).await;
+
+    let private_name = format!("mine-private-{suffix}");
+    let public_name = format!("mine-public-{suffix}");
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": private_name, "visibility": "Private", "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": public_name, "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // 按创建时间倒序，私有房间也在其中
+    let (status, owned) = send(&app, "GET", "/api/v1/rooms?owned=true", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(owned["total_count"], 2);
+    let names: Vec<&str> = owned["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|room| room["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, [public_name.as_str(), private_name.as_str()]);
+    assert!(owned["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|room| room["owner_id"] == owner_id.as_str()));
+
+    let (_, second_page) = send(
+        &app,
+        "GET",
+        "/api/v1/rooms?owned=true&page=2&page_size=1",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(second_page["items"][0]["name"], private_name.as_str());
+
+    // 公开发现里看不到私有房间
+    let (status, discovered) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms?search=mine-private-{suffix}"),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(discovered["total_count"], 0);
+}
@@ -0,0 +1,115 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use support::{build_router, send};
+
+async fn login(app: &axum::Router, email: &str, password: &str) -> (StatusCode, Value) {
+    send(
+        app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(json!({ "email": email, "password": password })),
+    )
+    .await
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn change_password_invalidates_existing_sessions() {
+    let app = build_router().await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({
+            "username": "password-user",
+            "email": "password@example.com",
+            "password": "secret"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // 两个设备上的会话
+    let (status, first) = login(&app, "password@example.com", "secret").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, second) = login(&app, "password@example.com", "secret").await;
+    assert_eq!(status, StatusCode::OK);
+    let token = first["token"].as_str().unwrap();
+    let other_token = second["token"].as_str().unwrap();
+    let other_refresh = second["refresh_token"].as_str().unwrap();
+
+    // 当前密码错误
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/users/me/password",
+        Some(token),
+        Some(json!({ "current_password": "wrong-password", "new_password": "new-secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 新密码与当前密码相同
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/v1/users/me/password",
+        Some(token),
+        Some(json!({ "current_password": "secret", "new_password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ARGUMENT");
+
+    // 新密码太短
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/users/me/password",
+        Some(token),
+        Some(json!({ "current_password": "secret", "new_password": "abc" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 修改成功，返回新的令牌对
+    let (status, pair) = send(
+        &app,
+        "POST",
+        "/api/v1/users/me/password",
+        Some(token),
+        Some(json!({ "current_password": "secret", "new_password": "new-secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let new_token = pair["access_token"].as_str().unwrap();
+
+    // 之前签发的访问令牌和刷新令牌全部失效
+    for old in [token, other_token] {
+        let (status, _) = send(&app, "GET", "/api/v1/rooms", Some(old), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": other_refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 新令牌可用，旧密码不能再登录
+    let (status, _) = send(&app, "GET", "/api/v1/rooms", Some(new_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = login(&app, "password@example.com", "secret").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = login(&app, "password@example.com", "new-secret").await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,208 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use support::{send, send_with_headers, setup_test_app};
+
+/// 从指定客户端 IP 发请求，限流按 IP 计数
+async fn send_from(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    client_ip: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let (status, _, body) =
+        send_with_headers(app, method, uri, &[("x-forwarded-for", client_ip)], body).await;
+    (status, body)
+}
+
+/// 限流计数存在 Redis 里，跨测试运行保留，每次运行使用不同的邮箱和 IP
+fn unique_email(prefix: &str) -> String {
+    format!(
+        "{prefix}-{}@example.com",
+        &Uuid::new_v4().simple().to_string()[..12]
+    )
+}
+
+fn unique_ip() -> String {
+    let bytes = Uuid::new_v4().into_bytes();
+    format!("10.{}.{}.{}", bytes[0], bytes[1], bytes[2])
+}
+
+async fn register(app: &axum::Router, ip: &str, email: &str, password: &str) {
+    let username = email.split('@').next().unwrap().to_string();
+    let (status, _) = send_from(
+        app,
+        "POST",
+        "/api/v1/auth/register",
+        ip,
+        Some(json!({ "username": username, "email": email, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+async fn forgot_password(app: &axum::Router, ip: &str, email: &str) -> (StatusCode, Value) {
+    send_from(
+        app,
+        "POST",
+        "/api/v1/auth/forgot-password",
+        ip,
+        Some(json!({ "email": email })),
+    )
+    .await
+}
+
+async fn reset_password(
+    app: &axum::Router,
+    ip: &str,
+    token: &str,
+    new_password: &str,
+) -> (StatusCode, Value) {
+    send_from(
+        app,
+        "POST",
+        "/api/v1/auth/reset-password",
+        ip,
+        Some(json!({ "token": token, "new_password": new_password })),
+    )
+    .await
+}
+
+async fn login(app: &axum::Router, ip: &str, email: &str, password: &str) -> (StatusCode, Value) {
+    send_from(
+        app,
+        "POST",
+        "/api/v1/auth/login",
+        ip,
+        Some(json!({ "email": email, "password": password })),
+    )
+    .await
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn reset_password_with_emailed_token() {
+    let test_app = setup_test_app().await;
+    let app = &test_app.router;
+    let ip = unique_ip();
+    let email = unique_email("reset");
+    register(app, &ip, &email, "secret").await;
+
+    let (status, session) = login(app, &ip, &email, "secret").await;
+    assert_eq!(status, StatusCode::OK);
+    let old_access = session["token"].as_str().unwrap().to_string();
+    let old_refresh = session["refresh_token"].as_str().unwrap().to_string();
+
+    // 存在和不存在的邮箱返回相同的响应，只有前者收到邮件
+    let (status, known) = forgot_password(app, &ip, &email).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let unknown_email = unique_email("nobody");
+    let (status, unknown) = forgot_password(app, &ip, &unknown_email).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(known, unknown);
+    assert_eq!(test_app.email_sender.sent_count(&unknown_email), 0);
+    let token = test_app
+        .email_sender
+        .latest_token(&email)
+        .expect("reset email");
+
+    // 无效令牌
+    let (status, body) = reset_password(app, &ip, "not-a-token", "new-secret").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_RESET_TOKEN");
+
+    // 新密码太短时拒绝，令牌不被消耗
+    let (status, body) = reset_password(app, &ip, &token, "abc").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ARGUMENT");
+
+    let (status, _) = reset_password(app, &ip, &token, "new-secret").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 令牌只能使用一次
+    let (status, body) = reset_password(app, &ip, &token, "other-secret").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_RESET_TOKEN");
+
+    // 之前的会话全部失效
+    let (status, _) = send(app, "GET", "/api/v1/rooms", Some(&old_access), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send_from(
+        app,
+        "POST",
+        "/api/v1/auth/refresh",
+        &ip,
+        Some(json!({ "refresh_token": old_refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 旧密码不能再登录，新密码可以
+    let (status, _) = login(app, &ip, &email, "secret").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = login(app, &ip, &email, "new-secret").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn expired_reset_token_is_rejected() {
+    let test_app = setup_test_app().await;
+    let app = &test_app.router;
+    let ip = unique_ip();
+    let email = unique_email("expired");
+    register(app, &ip, &email, "secret").await;
+
+    let (status, _) = forgot_password(app, &ip, &email).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let token = test_app
+        .email_sender
+        .latest_token(&email)
+        .expect("reset email");
+
+    sqlx::query(
+        "UPDATE password_resets SET expires_at = NOW() - INTERVAL '1 minute'
+         WHERE user_id = (SELECT id FROM users WHERE email = $1)",
+    )
+    .bind(&email)
+    .execute(&test_app._pool)
+    .await
+    .unwrap();
+
+    let (status, body) = reset_password(app, &ip, &token, "new-secret").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_RESET_TOKEN");
+    let (status, _) = login(app, &ip, &email, "secret").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn forgot_password_is_rate_limited() {
+    let test_app = setup_test_app().await;
+    let app = &test_app.router;
+
+    // 同一邮箱，换不同 IP 也受限
+    let email = unique_email("limited");
+    for _ in 0..5 {
+        let (status, _) = forgot_password(app, &unique_ip(), &email).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    let (status, body) = forgot_password(app, &unique_ip(), &email).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "TOO_MANY_RESET_REQUESTS");
+
+    // 同一 IP，换不同邮箱也受限
+    let ip = unique_ip();
+    for _ in 0..5 {
+        let (status, _) = forgot_password(app, &ip, &unique_email("probe")).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    let (status, body) = forgot_password(app, &ip, &unique_email("probe")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "TOO_MANY_RESET_REQUESTS");
+}
@@ -107,7 +107,8 @@ async fn presence_management_flow() {
         .post(format!("{}/api/v1/rooms/{}/members", base_http, room_id))
         .header("authorization", format!("Bearer {}", user1_token))
         .json(&json!({
-            "invitee_id": user2_id
+            "invitee_id": user2_id,
+            "auto_accept": true
         }))
         .send()
         .await
@@ -0,0 +1,122 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn update_profile_and_view_public_fields() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (user_id, token) = register_and_login(&app, &format!("profile-{suffix}")).await;
+    let (_, viewer_token) = register_and_login(&app, &format!("viewer-{suffix}")).await;
+
+    let (status, before) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/users/{user_id}"),
+        Some(&viewer_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(before["display_name"], Value::Null);
+
+    let (status, updated) = send(
+        &app,
+        "PATCH",
+        "/api/v1/users/me",
+        Some(&token),
+        Some(json!({
+            "display_name": "  Profile Owner  ",
+            "avatar_url": "https://cdn.example.com/avatar.png",
+            "bio": "hello"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["display_name"], "Profile Owner");
+    assert_eq!(updated["avatar_url"], "https://cdn.example.com/avatar.png");
+    assert_eq!(updated["bio"], "hello");
+    assert_ne!(updated["updated_at"], updated["created_at"]);
+
+    // 其他用户只看到公开字段
+    let (status, profile) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/users/{user_id}"),
+        Some(&viewer_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(profile["display_name"], "Profile Owner");
+    assert_eq!(profile["bio"], "hello");
+    assert!(profile.get("email").is_none());
+    assert!(profile.get("org_id").is_none());
+
+    // 只修改 bio，其他字段保持；空字符串清空
+    let (status, updated) = send(
+        &app,
+        "PATCH",
+        "/api/v1/users/me",
+        Some(&token),
+        Some(json!({ "bio": "" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["display_name"], "Profile Owner");
+    assert_eq!(updated["bio"], Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn update_profile_rejects_invalid_fields() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let username = format!("strict-{suffix}");
+    let (user_id, token) = register_and_login(&app, &username).await;
+
+    for body in [
+        json!({ "display_name": "x".repeat(65) }),
+        json!({ "avatar_url": "ftp://example.com/a.png" }),
+        json!({ "avatar_url": "not a url" }),
+    ] {
+        let (status, error) =
+            send(&app, "PATCH", "/api/v1/users/me", Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "INVALID_ARGUMENT");
+    }
+
+    // 用户名和邮箱不能通过资料接口修改
+    for body in [
+        json!({ "username": "renamed" }),
+        json!({ "email": "renamed@example.com", "bio": "hi" }),
+    ] {
+        let (status, _) = send(&app, "PATCH", "/api/v1/users/me", Some(&token), Some(body)).await;
+        assert!(status.is_client_error());
+    }
+    let (_, profile) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/users/{user_id}"),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(profile["username"], username.as_str());
+    assert_eq!(profile["bio"], Value::Null);
+
+    let (status, _) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/users/{}", Uuid::new_v4()),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,88 @@
+mod support;
+
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use support::{build_router_with, register_and_login, send, send_with_headers};
+
+async fn create_room(app: &axum::Router, token: &str, name: &str) -> String {
+    let (status, room) = send(
+        app,
+        "POST",
+        "/api/v1/rooms",
+        Some(token),
+        Some(json!({ "name": name, "visibility": "Public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    room["id"].as_str().unwrap().to_string()
+}
+
+/// 返回 (状态码, Retry-After 秒数, 响应体)
+async fn post_message(
+    app: &axum::Router,
+    token: &str,
+    room_id: &str,
+) -> (StatusCode, Option<u64>, Value) {
+    let authorization = format!("Bearer {token}");
+    let (status, headers, body) = send_with_headers(
+        app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        &[("authorization", &authorization)],
+        Some(json!({ "content": "spam", "message_type": "Text" })),
+    )
+    .await;
+    let retry_after = headers
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    (status, retry_after, body)
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn flooding_a_room_is_rejected_until_tokens_refill() {
+    // 每秒补充一个令牌，最多连发 5 条
+    let app = build_router_with(|config| {
+        config.rate_limit.messages_per_minute = 60;
+        config.rate_limit.message_burst = 5;
+    })
+    .await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, token) = register_and_login(&app, &format!("flooder-{suffix}")).await;
+    let room_id = create_room(&app, &token, &format!("flood-{suffix}")).await;
+
+    for _ in 0..5 {
+        let (status, _, _) = post_message(&app, &token, &room_id).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // 连发期间最多补充一个令牌，之后必然被拒绝
+    let mut rejected = None;
+    for _ in 0..3 {
+        let (status, retry_after, body) = post_message(&app, &token, &room_id).await;
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            rejected = Some((retry_after, body));
+            break;
+        }
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (retry_after, body) = rejected.expect("flood should be rate limited");
+    assert_eq!(retry_after, Some(1));
+    assert_eq!(body["code"], "RATE_LIMITED");
+
+    // 配额按房间计算，其他房间不受影响
+    let other_room = create_room(&app, &token, &format!("quiet-{suffix}")).await;
+    let (status, _, _) = post_message(&app, &token, &other_room).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // 等待一个令牌补充后恢复发送
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (status, _, body) = post_message(&app, &token, &room_id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["content"], "spam");
+}
@@ -0,0 +1,179 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn text_label_reactions_follow_room_policy() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, token) = register_and_login(&app, &format!("labeler-{suffix}")).await;
+
+    let (_, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": format!("labels-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    let room_id = room["id"].as_str().unwrap();
+    let (status, message) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(&token),
+        Some(json!({ "content": "please review", "message_type": "Text" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let reactions = format!(
+        "/api/v1/messages/{}/reactions",
+        message["id"].as_str().unwrap()
+    );
+
+    // 文字标签规范化后聚合
+    let (status, summary) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "LGTM" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary[0]["emoji"], "lgtm");
+    assert_eq!(summary[0]["count"], 1);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "this-label-is-way-too-long" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ARGUMENT");
+
+    // 切换为只允许 emoji
+    let (status, room) = send(
+        &app,
+        "PUT",
+        &format!("/api/v1/rooms/{room_id}"),
+        Some(&token),
+        Some(json!({ "reaction_policy": "emoji_only" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(room["reaction_policy"], "emoji_only");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "+1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "REACTION_NOT_ALLOWED");
+
+    let (status, summary) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "👍" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn reactions_per_user_are_capped() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, token) = register_and_login(&app, &format!("spammer-{suffix}")).await;
+    let cap = config::MessageConfig::default().max_reactions_per_user_per_message;
+
+    let (_, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&token),
+        Some(json!({ "name": format!("capped-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    let room_id = room["id"].as_str().unwrap();
+    let (_, message) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(&token),
+        Some(json!({ "content": "react away", "message_type": "Text" })),
+    )
+    .await;
+    let reactions = format!(
+        "/api/v1/messages/{}/reactions",
+        message["id"].as_str().unwrap()
+    );
+
+    for i in 0..cap {
+        let (status, _) = send(
+            &app,
+            "POST",
+            &reactions,
+            Some(&token),
+            Some(json!({ "emoji": format!("r{i}") })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "one-more" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "REACTION_LIMIT_EXCEEDED");
+
+    // 已有的回应重复添加不受影响；移除一个后释放名额
+    let (status, _) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "r0" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!("{reactions}/r0"),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(
+        &app,
+        "POST",
+        &reactions,
+        Some(&token),
+        Some(json!({ "emoji": "one-more" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
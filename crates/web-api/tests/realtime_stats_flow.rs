@@ -0,0 +1,118 @@
+mod support;
+
+use axum::http::StatusCode;
+use domain::{RoomId, UserId};
+use uuid::Uuid;
+
+use application::PresenceManager;
+use support::{register_and_login, send, send_with_headers, setup_test_app_with, TestConfig};
+
+const STATS_TOKEN: &str = "dashboard-token-0123456789";
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn realtime_snapshot_is_restricted_to_superusers_and_stats_token() {
+    let mut config = TestConfig::default();
+    config.app_config.stats.api_token = Some(STATS_TOKEN.to_string());
+    let test_app = setup_test_app_with(config).await;
+    let app = &test_app.router;
+
+    // 两个房间：busy 有两个用户三个连接，quiet 有其中一个用户
+    let presence = test_app.presence_manager.clone();
+    let busy = RoomId::from(Uuid::new_v4());
+    let quiet = RoomId::from(Uuid::new_v4());
+    let alice = UserId::from(Uuid::new_v4());
+    let bob = UserId::from(Uuid::new_v4());
+    for (room_id, user_id) in [(busy, alice), (busy, bob), (quiet, alice)] {
+        presence.user_connected(room_id, user_id).await.unwrap();
+        presence
+            .heartbeat(room_id, user_id, Uuid::new_v4())
+            .await
+            .unwrap();
+    }
+
+    let (status, _) = send(app, "GET", "/api/v1/stats/realtime", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 普通用户无权查看
+    let (_, member_token) = register_and_login(app, "realtime-member").await;
+    let (status, _) = send(
+        app,
+        "GET",
+        "/api/v1/stats/realtime",
+        Some(&member_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 旧版按维度查询仍然可用，只要求登录
+    let (status, stats) = send(
+        app,
+        "GET",
+        &format!(
+            "/api/v1/stats/realtime?dimension=room&dimension_id={}",
+            Uuid::from(busy)
+        ),
+        Some(&member_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stats["dimension_type"], "room");
+    assert_eq!(stats["dimension_id"], Uuid::from(busy).to_string());
+
+    // 系统管理员可以查看
+    let (_, admin_token) = register_and_login(app, "realtime-admin").await;
+    sqlx::query("UPDATE users SET is_superuser = TRUE WHERE email = $1")
+        .bind("realtime-admin@example.com")
+        .execute(&test_app._pool)
+        .await
+        .expect("grant superuser");
+    let (status, snapshot) = send(
+        app,
+        "GET",
+        "/api/v1/stats/realtime",
+        Some(&admin_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(snapshot["total_online_users"], 2);
+    assert_eq!(snapshot["connection_count"], 3);
+    let rooms = snapshot["top_rooms"].as_array().unwrap();
+    assert_eq!(rooms.len(), 2);
+    assert_eq!(rooms[0]["room_id"], Uuid::from(busy).to_string());
+    assert_eq!(rooms[0]["online_count"], 2);
+    assert_eq!(rooms[1]["room_id"], Uuid::from(quiet).to_string());
+    assert_eq!(rooms[1]["online_count"], 1);
+
+    // 看板用统计令牌访问，只取人数最多的房间
+    let (status, _, snapshot) = send_with_headers(
+        app,
+        "GET",
+        "/api/v1/stats/realtime?top=1",
+        &[("x-stats-token", STATS_TOKEN)],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(snapshot["total_online_users"], 2);
+    let rooms = snapshot["top_rooms"].as_array().unwrap();
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(rooms[0]["room_id"], Uuid::from(busy).to_string());
+
+    // 令牌不对时不再回退到登录身份
+    let (status, _, _) = send_with_headers(
+        app,
+        "GET",
+        "/api/v1/stats/realtime",
+        &[
+            ("x-stats-token", "wrong-token-0123456789"),
+            ("authorization", &format!("Bearer {admin_token}")),
+        ],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
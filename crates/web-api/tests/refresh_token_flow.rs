@@ -0,0 +1,147 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use support::{build_router, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn refresh_rotates_and_detects_reuse() {
+    let app = build_router().await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({
+            "username": "refresh-user",
+            "email": "refresh@example.com",
+            "password": "secret"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, login) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(json!({ "email": "refresh@example.com", "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let first_refresh = login["refresh_token"].as_str().unwrap().to_string();
+
+    // 访问令牌不能当刷新令牌用
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": login["token"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 正常轮换
+    let (status, rotated) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": first_refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let second_refresh = rotated["refresh_token"].as_str().unwrap().to_string();
+    assert_ne!(second_refresh, first_refresh);
+    assert!(rotated["access_token"].is_string());
+
+    // 旧令牌重放：拒绝并吊销整个家族
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": first_refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": second_refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn logout_revokes_refresh_token() {
+    let app = build_router().await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({
+            "username": "refresh-logout-user",
+            "email": "refresh-logout@example.com",
+            "password": "secret"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let credentials = json!({ "email": "refresh-logout@example.com", "password": "secret" });
+    let (_, login) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(credentials.clone()),
+    )
+    .await;
+    let (_, other_device) = send(&app, "POST", "/api/v1/auth/login", None, Some(credentials)).await;
+    let refresh = login["refresh_token"].as_str().unwrap().to_string();
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/logout",
+        login["token"].as_str(),
+        Some(json!({ "refresh_token": refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 登出后的刷新令牌不能再换取新令牌
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": refresh })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // 另一次登录不受影响
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/v1/auth/refresh",
+        None,
+        Some(json!({ "refresh_token": other_device["refresh_token"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
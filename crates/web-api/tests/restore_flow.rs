@@ -0,0 +1,80 @@
+mod support;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use uuid::Uuid;
+
+use support::{build_router, register_and_login, send};
+
+#[tokio::test]
+#[ignore = "requires local postgres"]
+async fn admin_restores_deleted_message() {
+    let app = build_router().await;
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (_, owner_token) = register_and_login(&app, &format!("restorer-{suffix}")).await;
+    let (_, member_token) = register_and_login(&app, &format!("regular-{suffix}")).await;
+
+    let (_, room) = send(
+        &app,
+        "POST",
+        "/api/v1/rooms",
+        Some(&owner_token),
+        Some(json!({ "name": format!("restore-{suffix}"), "visibility": "Public" })),
+    )
+    .await;
+    let room_id = room["id"].as_str().unwrap();
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/join"),
+        Some(&member_token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, message) = send(
+        &app,
+        "POST",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(&member_token),
+        Some(json!({ "content": "oops", "message_type": "Text" })),
+    )
+    .await;
+    let message_id = message["id"].as_str().unwrap();
+    let restore = format!("/api/v1/messages/{message_id}/restore");
+
+    // 未删除的消息不能恢复
+    let (status, body) = send(&app, "POST", &restore, Some(&owner_token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "MESSAGE_NOT_DELETED");
+
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!("/api/v1/rooms/{room_id}/messages/{message_id}"),
+        Some(&member_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 普通成员即使是发送者也不能恢复
+    let (status, _) = send(&app, "POST", &restore, Some(&member_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, restored) = send(&app, "POST", &restore, Some(&owner_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(restored["id"], message_id);
+    assert_eq!(restored["is_deleted"], false);
+
+    let (_, history) = send(
+        &app,
+        "GET",
+        &format!("/api/v1/rooms/{room_id}/messages"),
+        Some(&owner_token),
+        None,
+    )
+    .await;
+    assert_eq!(history["items"][0]["content"], "oops");
+}
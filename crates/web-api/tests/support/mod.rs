@@ -1,24 +1,49 @@
-use std::{env, sync::Arc};
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use application::{
     presence::memory::MemoryPresenceManager,
-    repository::{ChatRoomRepository, MessageRepository, RoomMemberRepository, UserRepository},
+    repository::{
+        AuditLogRepository, ChatRoomRepository, EmailVerificationRepository, FileUploadRepository,
+        IdempotencyKeyRepository, MentionRepository, MessageDeliveryRepository, MessageRepository,
+        NotificationRepository, PasswordResetRepository, ReactionRepository,
+        RoomInvitationRepository, RoomInviteRepository, RoomMemberRepository, UserBlockRepository,
+    },
     services::{
         BulkUserService, ChatService, ChatServiceDependencies, StatsService, UserService,
         UserServiceDependencies,
     },
-    Clock, MessageBroadcaster, PasswordHasher, SystemClock,
+    Clock, EmailSender, EmailSenderError, MessageBroadcaster, MessageRateLimiter, PasswordHasher,
+    Readiness, SendRateLimiter, SystemClock,
+};
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    http::{HeaderMap, Request, StatusCode},
+    Router,
 };
-use axum::Router;
 use config::AppConfig;
+use domain::{UserEmail, Username};
 use infrastructure::{
-    create_pg_pool, BcryptPasswordHasher, PgChatRoomRepository, PgMessageRepository,
-    PgOrganizationRepository, PgRoomMemberRepository, PgStorage, PgUserRepository,
+    create_pg_pool, BcryptPasswordHasher, PgAuditLogRepository, PgChatRoomRepository,
+    PgEmailVerificationRepository, PgFileUploadRepository, PgIdempotencyKeyRepository,
+    PgMentionRepository, PgMessageDeliveryRepository, PgMessageRepository,
+    PgNotificationRepository, PgOrganizationRepository, PgPasswordResetRepository,
+    PgReactionRepository, PgRoomInvitationRepository, PgRoomInviteRepository,
+    PgRoomMemberRepository, PgStorage, PgUserBlockRepository, PgUserRepository,
     RedisMessageBroadcaster, StatsAggregationService,
 };
 use redis::Client as RedisClient;
+use serde_json::{json, Value};
 use sqlx::PgPool;
-use web_api::{router as build_router_fn, AppState, JwtService};
+use tower::ServiceExt;
+use web_api::{
+    router as build_router_fn, AppState, ConnectionRegistry, HealthProbe, HttpRateLimiter,
+    JwtService, WsConnectionLimits, WsDeflateSubprotocol, WsFlowControl, WsHeartbeat,
+};
 
 /// 测试专用的在线状态管理器类型
 pub type TestPresenceManager = MemoryPresenceManager;
@@ -47,6 +72,65 @@ impl Default for TestConfig {
     }
 }
 
+/// 记录发出的验证和密码重置链接，测试从这里取令牌
+#[derive(Default)]
+pub struct RecordingEmailSender {
+    sent: Mutex<Vec<(String, String)>>,
+}
+
+impl RecordingEmailSender {
+    /// 最近一次发给该邮箱的令牌
+    #[allow(dead_code)]
+    pub fn latest_token(&self, email: &str) -> Option<String> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(to, _)| to == email)
+            .and_then(|(_, url)| url.split_once("token=").map(|(_, token)| token.to_string()))
+    }
+
+    #[allow(dead_code)]
+    pub fn sent_count(&self, email: &str) -> usize {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(to, _)| to == email)
+            .count()
+    }
+}
+
+#[async_trait]
+impl EmailSender for RecordingEmailSender {
+    async fn send_verification(
+        &self,
+        to: &UserEmail,
+        _username: &Username,
+        verification_url: &str,
+    ) -> Result<(), EmailSenderError> {
+        self.sent
+            .lock()
+            .unwrap()
+            .push((to.as_str().to_string(), verification_url.to_string()));
+        Ok(())
+    }
+
+    async fn send_password_reset(
+        &self,
+        to: &UserEmail,
+        _username: &Username,
+        reset_url: &str,
+    ) -> Result<(), EmailSenderError> {
+        self.sent
+            .lock()
+            .unwrap()
+            .push((to.as_str().to_string(), reset_url.to_string()));
+        Ok(())
+    }
+}
+
 /// 测试应用状态，包含所有需要的组件
 pub struct TestAppState {
     pub router: Router,
@@ -54,6 +138,11 @@ pub struct TestAppState {
     pub _config: TestConfig,
     #[allow(dead_code)]
     pub presence_manager: Arc<TestPresenceManager>,
+    #[allow(dead_code)]
+    pub email_sender: Arc<RecordingEmailSender>,
+    /// 路由使用的 WebSocket 连接登记，停机测试用它排空连接
+    #[allow(dead_code)]
+    pub ws_connections: Arc<ConnectionRegistry>,
 }
 
 /// 清理数据库中的所有数据，为测试提供干净的环境
@@ -80,6 +169,9 @@ fn create_services(
     pool: &PgPool,
     config: &AppConfig,
     presence_manager: Arc<dyn application::PresenceManager>,
+    email_sender: Arc<dyn EmailSender>,
+    jwt_service: &JwtService,
+    send_rate_limiter: Arc<dyn SendRateLimiter>,
 ) -> (
     Arc<UserService>,
     Arc<ChatService>,
@@ -87,13 +179,38 @@ fn create_services(
     Arc<dyn Clock>,
 ) {
     // 创建 repositories
-    let user_repository: Arc<dyn UserRepository> = Arc::new(PgUserRepository::new(pool.clone()));
+    let user_repository =
+        jwt_service.invalidating_user_repository(Arc::new(PgUserRepository::new(pool.clone())));
     let room_repository: Arc<dyn ChatRoomRepository> =
         Arc::new(PgChatRoomRepository::new(pool.clone()));
     let member_repository: Arc<dyn RoomMemberRepository> =
         Arc::new(PgRoomMemberRepository::new(pool.clone()));
     let message_repository: Arc<dyn MessageRepository> =
         Arc::new(PgMessageRepository::new(pool.clone()));
+    let reaction_repository: Arc<dyn ReactionRepository> =
+        Arc::new(PgReactionRepository::new(pool.clone()));
+    let user_block_repository: Arc<dyn UserBlockRepository> =
+        Arc::new(PgUserBlockRepository::new(pool.clone()));
+    let mention_repository: Arc<dyn MentionRepository> =
+        Arc::new(PgMentionRepository::new(pool.clone()));
+    let delivery_repository: Arc<dyn MessageDeliveryRepository> =
+        Arc::new(PgMessageDeliveryRepository::new(pool.clone()));
+    let email_verification_repository: Arc<dyn EmailVerificationRepository> =
+        Arc::new(PgEmailVerificationRepository::new(pool.clone()));
+    let password_reset_repository: Arc<dyn PasswordResetRepository> =
+        Arc::new(PgPasswordResetRepository::new(pool.clone()));
+    let audit_log_repository: Arc<dyn AuditLogRepository> =
+        Arc::new(PgAuditLogRepository::new(pool.clone()));
+    let file_upload_repository: Arc<dyn FileUploadRepository> =
+        Arc::new(PgFileUploadRepository::new(pool.clone()));
+    let notification_repository: Arc<dyn NotificationRepository> =
+        Arc::new(PgNotificationRepository::new(pool.clone()));
+    let idempotency_key_repository: Arc<dyn IdempotencyKeyRepository> =
+        Arc::new(PgIdempotencyKeyRepository::new(pool.clone()));
+    let invitation_repository: Arc<dyn RoomInvitationRepository> =
+        Arc::new(PgRoomInvitationRepository::new(pool.clone()));
+    let invite_repository: Arc<dyn RoomInviteRepository> =
+        Arc::new(PgRoomInviteRepository::new(pool.clone()));
 
     // 创建核心服务
     let password_hasher: Arc<dyn PasswordHasher> =
@@ -119,16 +236,33 @@ fn create_services(
         password_hasher: password_hasher.clone(),
         clock: clock.clone(),
         presence_manager: presence_manager.clone(),
+        email_verification_repository,
+        password_reset_repository,
+        email_sender,
+        registration_config: config.registration.clone(),
     });
 
     let chat_service = ChatService::new(ChatServiceDependencies {
         room_repository,
         member_repository,
         message_repository,
+        reaction_repository,
+        user_block_repository,
+        mention_repository,
+        delivery_repository,
         user_repository: user_repository.clone(),
+        audit_log_repository,
+        file_upload_repository,
+        notification_repository,
+        idempotency_key_repository,
+        invitation_repository,
+        invite_repository,
         password_hasher,
         clock: clock.clone(),
         broadcaster: broadcaster.clone(),
+        message_config: config.message.clone(),
+        room_encryption: None,
+        send_rate_limiter: Some(send_rate_limiter),
     });
 
     (
@@ -141,8 +275,11 @@ fn create_services(
 
 /// 构建测试用的应用状态
 pub async fn setup_test_app() -> TestAppState {
-    let config = TestConfig::default();
+    setup_test_app_with(TestConfig::default()).await
+}
 
+/// 使用指定配置构建测试用的应用状态
+pub async fn setup_test_app_with(config: TestConfig) -> TestAppState {
     // 创建数据库连接池
     let pool = create_test_pool(
         &config.database_url,
@@ -159,15 +296,37 @@ pub async fn setup_test_app() -> TestAppState {
     let _ = sqlx::migrate!("../../migrations").run(&pool).await;
 
     // 创建在线状态管理器
-    let presence_manager: Arc<TestPresenceManager> = Arc::new(TestPresenceManager::default());
+    let presence_manager: Arc<TestPresenceManager> =
+        Arc::new(TestPresenceManager::with_heartbeat_timeout(
+            Duration::from_secs(config.app_config.presence.heartbeat_timeout_secs),
+        ));
     let presence_manager_trait: Arc<dyn application::PresenceManager> = presence_manager.clone();
 
-    // 创建所有服务
-    let (user_service, chat_service, broadcaster, _clock) =
-        create_services(&pool, &config.app_config, presence_manager_trait.clone());
-
     // 创建 JWT 服务
-    let jwt_service = Arc::new(JwtService::new(config.app_config.jwt.clone()));
+    let jwt_service = Arc::new(JwtService::new(
+        config.app_config.jwt.clone(),
+        Arc::new(PgUserRepository::new(pool.clone())),
+    ));
+
+    // 发送限流和生产环境一样用 Redis，接口层和 ChatService 共用同一个限流器
+    let rate_limiter = Arc::new(MessageRateLimiter::from_config(
+        Arc::new(
+            RedisClient::open(config.app_config.redis.url.clone())
+                .expect("Failed to create Redis client for rate limiter"),
+        ),
+        &config.app_config.rate_limit,
+    ));
+
+    // 创建所有服务
+    let email_sender = Arc::new(RecordingEmailSender::default());
+    let (user_service, chat_service, broadcaster, _clock) = create_services(
+        &pool,
+        &config.app_config,
+        presence_manager_trait.clone(),
+        email_sender.clone(),
+        &jwt_service,
+        rate_limiter.clone(),
+    );
 
     // 创建统计服务
     let stats_agg_service = Arc::new(StatsAggregationService::new(pool.clone()));
@@ -184,6 +343,19 @@ pub async fn setup_test_app() -> TestAppState {
     // 创建存储服务
     let storage = Arc::new(PgStorage::new(pool.clone()));
 
+    // 就绪探针检查测试数据库和限流用的 Redis
+    let health_probe = HealthProbe::new(
+        pool.clone(),
+        Duration::from_millis(config.app_config.server.readiness_check_timeout_ms),
+    )
+    .with_redis(
+        "redis",
+        RedisClient::open(config.app_config.redis.url.clone())
+            .expect("Failed to create Redis client for health probe"),
+    );
+    let readiness = Readiness::new();
+    readiness.mark_ready();
+
     // 创建应用状态
     let app_state = AppState::new(
         user_service,
@@ -196,9 +368,30 @@ pub async fn setup_test_app() -> TestAppState {
         org_repository,
         bulk_user_service,
         storage,
+        rate_limiter,
+    )
+    .with_ws_heartbeat(WsHeartbeat::from_app_config(&config.app_config))
+    .with_ws_flow_control(WsFlowControl::from_app_config(&config.app_config))
+    .with_ws_deflate_subprotocol(WsDeflateSubprotocol::from_app_config(&config.app_config))
+    .with_stats_api_token(config.app_config.stats.api_token.clone())
+    .with_ws_connection_limits(WsConnectionLimits::from_app_config(&config.app_config))
+    .with_readiness(readiness)
+    .with_health_probe(health_probe)
+    .with_http_rate_limiter(
+        HttpRateLimiter::from_app_config(&config.app_config)
+            .expect("Failed to create HTTP rate limiter"),
     );
+    // 全局 recorder 在同一个测试进程内共享，计数只会累加
+    let app_state = if config.app_config.server.metrics_enabled {
+        app_state.with_metrics(
+            web_api::metrics::install_recorder().expect("Failed to install metrics recorder"),
+        )
+    } else {
+        app_state
+    };
 
     // 构建路由器
+    let ws_connections = app_state.ws_connections.clone();
     let router = build_router_fn(app_state);
 
     TestAppState {
@@ -206,6 +399,8 @@ pub async fn setup_test_app() -> TestAppState {
         _pool: pool,
         _config: config,
         presence_manager,
+        email_sender,
+        ws_connections,
     }
 }
 
@@ -215,6 +410,96 @@ pub async fn build_router() -> Router {
     setup_test_app().await.router
 }
 
+/// 调整配置后构建路由器，用于需要非默认阈值的测试
+#[allow(dead_code)]
+pub async fn build_router_with(configure: impl FnOnce(&mut AppConfig)) -> Router {
+    let mut config = TestConfig::default();
+    configure(&mut config.app_config);
+    setup_test_app_with(config).await.router
+}
+
+/// 发一个 HTTP 请求给路由，带上给定的请求头，有请求体时按 JSON 发送
+///
+/// 返回状态码、响应头和响应体（不是 JSON 时为空对象）
+#[allow(dead_code)]
+pub async fn send_with_headers(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut builder = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let request = match body {
+        Some(body) => builder
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.expect("request");
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let body = serde_json::from_slice(&body_bytes).unwrap_or(json!({}));
+    (status, headers, body)
+}
+
+/// 发一个 HTTP 请求给路由，有令牌时带上 Bearer 认证头
+#[allow(dead_code)]
+pub async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let authorization = token.map(|token| format!("Bearer {token}"));
+    let headers: Vec<(&str, &str)> = authorization
+        .as_deref()
+        .map(|value| ("authorization", value))
+        .into_iter()
+        .collect();
+    let (status, _, body) = send_with_headers(app, method, uri, &headers, body).await;
+    (status, body)
+}
+
+/// 注册并登录，返回 (user_id, token)
+#[allow(dead_code)]
+pub async fn register_and_login(app: &Router, name: &str) -> (String, String) {
+    let email = format!("{name}@example.com");
+    let (status, user) = send(
+        app,
+        "POST",
+        "/api/v1/auth/register",
+        None,
+        Some(json!({ "username": name, "email": email, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, login) = send(
+        app,
+        "POST",
+        "/api/v1/auth/login",
+        None,
+        Some(json!({ "email": email, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    (
+        user["id"].as_str().unwrap().to_string(),
+        login["token"].as_str().unwrap().to_string(),
+    )
+}
+
 /// 测试助手函数：创建测试用户
 pub async fn _create_test_user(pool: &PgPool, username: &str, email: &str) -> uuid::Uuid {
     let user_id = uuid::Uuid::new_v4();
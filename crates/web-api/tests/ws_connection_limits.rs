@@ -0,0 +1,85 @@
+//! WebSocket 连接登记表：实例和单个用户的连接上限
+
+use std::sync::Arc;
+
+use config::WsOverflowPolicy;
+use domain::UserId;
+use uuid::Uuid;
+use web_api::{ConnectionRegistry, ConnectionRejected, WsConnectionLimits};
+
+fn registry(
+    max_connections: usize,
+    max_connections_per_user: usize,
+    per_user_overflow: WsOverflowPolicy,
+) -> Arc<ConnectionRegistry> {
+    Arc::new(ConnectionRegistry::new(WsConnectionLimits {
+        max_connections,
+        max_connections_per_user,
+        per_user_overflow,
+    }))
+}
+
+fn user() -> UserId {
+    UserId::from(Uuid::new_v4())
+}
+
+#[test]
+fn evicts_oldest_connection_of_the_same_user() {
+    let registry = registry(10, 2, WsOverflowPolicy::EvictOldest);
+    let user_id = user();
+
+    let mut oldest = registry.register(user_id).unwrap();
+    let mut oldest_evicted = oldest.take_eviction().unwrap();
+    let mut second = registry.register(user_id).unwrap();
+    let mut second_evicted = second.take_eviction().unwrap();
+    let _third = registry.register(user_id).unwrap();
+
+    assert!(oldest_evicted.try_recv().is_ok());
+    assert!(second_evicted.try_recv().is_err());
+    assert_eq!(registry.user_connections(user_id), 2);
+    assert_eq!(registry.total(), 2);
+
+    // 被挤掉的连接结束时不会重复释放名额
+    drop(oldest);
+    assert_eq!(registry.total(), 2);
+    drop(second);
+    assert_eq!(registry.user_connections(user_id), 1);
+    assert_eq!(registry.total(), 1);
+}
+
+#[test]
+fn rejects_new_connection_when_configured() {
+    let registry = registry(10, 2, WsOverflowPolicy::RejectNew);
+    let user_id = user();
+
+    let mut first = registry.register(user_id).unwrap();
+    let mut first_evicted = first.take_eviction().unwrap();
+    let _second = registry.register(user_id).unwrap();
+    assert_eq!(
+        registry.register(user_id).err(),
+        Some(ConnectionRejected::UserLimit { max: 2 })
+    );
+    assert!(first_evicted.try_recv().is_err());
+
+    // 其他用户不受影响；有连接结束后可以重新连接
+    assert!(registry.register(user()).is_ok());
+    drop(first);
+    assert!(registry.register(user_id).is_ok());
+}
+
+#[test]
+fn server_limit_rejects_new_users_but_allows_replacing_own_connection() {
+    let registry = registry(2, 1, WsOverflowPolicy::EvictOldest);
+    let (alice, bob) = (user(), user());
+
+    let _alice = registry.register(alice).unwrap();
+    let _bob = registry.register(bob).unwrap();
+    assert_eq!(
+        registry.register(user()).err(),
+        Some(ConnectionRejected::ServerFull { max: 2 })
+    );
+
+    // 挤掉自己的旧连接不占用新名额
+    let _alice_again = registry.register(alice).unwrap();
+    assert_eq!(registry.total(), 2);
+}
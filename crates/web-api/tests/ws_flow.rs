@@ -1,15 +1,24 @@
 mod support;
 
+use std::io::Read;
 use std::time::Duration;
 
+use flate2::read::DeflateDecoder;
 use futures_util::{SinkExt, StreamExt};
 use reqwest::Client;
 use serde_json::json;
 use tokio::{net::TcpListener, sync::oneshot, time::sleep};
-use tokio_tungstenite::{connect_async, tungstenite::Message as TungsteniteMessage};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        client::IntoClientRequest, http::HeaderValue, protocol::frame::coding::CloseCode,
+        Message as TungsteniteMessage,
+    },
+};
 use uuid::Uuid;
 
-use support::build_router;
+use support::{build_router, setup_test_app};
+use web_api::{serve_with_graceful_shutdown, DEFLATE_SUBPROTOCOL, SERVER_RESTARTING_REASON};
 
 #[tokio::test]
 async fn websocket_broadcast_flow() {
@@ -122,7 +131,8 @@ async fn websocket_broadcast_flow() {
         .post(format!("{}/api/v1/rooms/{}/members", base_http, room_id))
         .header("authorization", format!("Bearer {}", owner_token))
         .json(&json!({
-            "invitee_id": member_id
+            "invitee_id": member_id,
+            "auto_accept": true
         }))
         .send()
         .await
@@ -377,7 +387,8 @@ async fn websocket_multiple_users_broadcast() {
         .post(format!("{}/api/v1/rooms/{}/members", base_http, room_id))
         .header("authorization", format!("Bearer {}", owner_token))
         .json(&json!({
-            "invitee_id": user1_id
+            "invitee_id": user1_id,
+            "auto_accept": true
         }))
         .send()
         .await
@@ -387,7 +398,8 @@ async fn websocket_multiple_users_broadcast() {
         .post(format!("{}/api/v1/rooms/{}/members", base_http, room_id))
         .header("authorization", format!("Bearer {}", owner_token))
         .json(&json!({
-            "invitee_id": user2_id
+            "invitee_id": user2_id,
+            "auto_accept": true
         }))
         .send()
         .await
@@ -642,6 +654,7 @@ async fn websocket_private_room_flow() {
         .header("authorization", format!("Bearer {}", owner_token))
         .json(&json!({
             "invitee_id": member_id,
+            "auto_accept": true,
             "password": "room-secret"
         }))
         .send()
@@ -769,11 +782,12 @@ async fn websocket_message_history_flow() {
         .expect("get history");
 
     assert_eq!(history_response.status(), 200);
-    let history: Vec<serde_json::Value> =
-        history_response.json().await.expect("parse history json");
+    let page: serde_json::Value = history_response.json().await.expect("parse history json");
+    let history = page["items"].as_array().expect("history items");
 
     // 验证历史消息
     assert_eq!(history.len(), 5);
+    assert_eq!(page["has_more"], false);
 
     // 消息应该按时间倒序返回（最新的在前面）
     for (index, message) in history.iter().enumerate() {
@@ -793,12 +807,1323 @@ async fn websocket_message_history_flow() {
         .await
         .expect("get paginated history");
 
-    let paginated_history: Vec<serde_json::Value> = paginated_response
+    let paginated_page: serde_json::Value = paginated_response
         .json()
         .await
         .expect("parse paginated history json");
+    let paginated_history = paginated_page["items"]
+        .as_array()
+        .expect("paginated history items");
 
     assert_eq!(paginated_history.len(), 2);
+    assert_eq!(paginated_page["has_more"], true);
+    assert_eq!(paginated_page["next_cursor"], paginated_history[1]["id"]);
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_resume_after_disconnect() {
+    let router = build_router().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        axum::serve(listener, router.into_make_service())
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .ok();
+    });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+
+    let user = client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({
+            "username": "resumeuser",
+            "email": "resume@test.com",
+            "password": "secret"
+        }))
+        .send()
+        .await
+        .expect("register user")
+        .json::<serde_json::Value>()
+        .await
+        .expect("user json");
+    let user_id = user["id"].as_str().unwrap().to_string();
+
+    let user_login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&json!({"email": "resume@test.com", "password": "secret"}))
+        .send()
+        .await
+        .expect("login user")
+        .json::<serde_json::Value>()
+        .await
+        .expect("user login json");
+    let user_token = user_login["token"].as_str().unwrap();
+
+    let room = client
+        .post(format!("{}/api/v1/rooms", base_http))
+        .header("authorization", format!("Bearer {}", user_token))
+        .json(&json!({
+            "name": "resume-test",
+            "visibility": "Public"
+        }))
+        .send()
+        .await
+        .expect("create room")
+        .json::<serde_json::Value>()
+        .await
+        .expect("room json");
+    let room_id = room["id"].as_str().unwrap().parse::<Uuid>().unwrap();
+
+    let ws_url = format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, user_token
+    );
+
+    let send_message = |content: &'static str| {
+        let client = client.clone();
+        let url = format!("{}/api/v1/rooms/{}/messages", base_http, room_id);
+        let token = user_token.to_string();
+        async move {
+            client
+                .post(url)
+                .header("authorization", format!("Bearer {}", token))
+                .json(&json!({ "content": content, "message_type": "Text" }))
+                .send()
+                .await
+                .expect("send message")
+                .json::<serde_json::Value>()
+                .await
+                .expect("message json")
+        }
+    };
+
+    // 在线期间收到前两条消息
+    let (ws, _) = connect_async(ws_url.clone()).await.expect("ws connect");
+    send_message("Message 1").await;
+    let last_seen = send_message("Message 2").await;
+    let last_seq = last_seen["seq"].as_i64().expect("seq");
+
+    // 断线期间又产生两条消息
+    drop(ws);
+    sleep(Duration::from_millis(100)).await;
+    send_message("Message 3").await;
+    send_message("Message 4").await;
+
+    // 重连并一次性 resume
+    let (mut ws, _) = connect_async(ws_url).await.expect("ws reconnect");
+    ws.send(TungsteniteMessage::Text(
+        json!({
+            "type": "resume",
+            "payload": { "room_id": room_id, "last_seq": last_seq }
+        })
+        .to_string()
+        .into(),
+    ))
+    .await
+    .expect("send resume");
+
+    // 跳过在线统计等广播，直到拿到 resume 响应
+    let resumed = loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for resume")
+            .expect("ws frame")
+            .expect("ws message");
+        if let TungsteniteMessage::Text(payload) = frame {
+            let json: serde_json::Value = serde_json::from_str(&payload).expect("json");
+            if json["type"] == "resumed" {
+                break json["payload"].clone();
+            }
+        }
+    };
+
+    let missed: Vec<&str> = resumed["missed_messages"]
+        .as_array()
+        .expect("missed messages")
+        .iter()
+        .map(|message| message["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(missed, vec!["Message 3", "Message 4"]);
+    assert_eq!(resumed["has_more"], false);
+    assert_eq!(resumed["room"]["id"], room_id.to_string());
+    assert_eq!(resumed["role"], "Owner");
+    assert!(resumed["unread_count"].as_i64().is_some());
+    assert!(resumed["online_users"]
+        .as_array()
+        .expect("online users")
+        .iter()
+        .any(|id| id == &json!(user_id)));
+
+    let _ = shutdown_tx.send(());
+}
+
+/// 启动测试服务器，返回监听地址和关闭信号
+async fn spawn_server(router: axum::Router) -> (std::net::SocketAddr, oneshot::Sender<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        axum::serve(listener, router.into_make_service())
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .ok();
+    });
+    sleep(Duration::from_millis(100)).await;
+
+    (addr, shutdown_tx)
+}
+
+/// 注册用户、登录并创建公开房间，返回 (token, room_id)
+async fn owner_with_room(client: &Client, base_http: &str, name: &str) -> (String, Uuid) {
+    let email = format!("{name}@test.com");
+    client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({ "username": name, "email": email, "password": "secret" }))
+        .send()
+        .await
+        .expect("register user");
+    let login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&json!({ "email": email, "password": "secret" }))
+        .send()
+        .await
+        .expect("login user")
+        .json::<serde_json::Value>()
+        .await
+        .expect("login json");
+    let token = login["token"].as_str().unwrap().to_string();
+
+    let room = client
+        .post(format!("{}/api/v1/rooms", base_http))
+        .header("authorization", format!("Bearer {}", token))
+        .json(&json!({ "name": format!("{name}-room"), "visibility": "Public" }))
+        .send()
+        .await
+        .expect("create room")
+        .json::<serde_json::Value>()
+        .await
+        .expect("room json");
+
+    (token, room["id"].as_str().unwrap().parse().unwrap())
+}
+
+async fn post_message(
+    client: &Client,
+    base_http: &str,
+    token: &str,
+    room_id: Uuid,
+    content: &str,
+) -> serde_json::Value {
+    client
+        .post(format!("{}/api/v1/rooms/{}/messages", base_http, room_id))
+        .header("authorization", format!("Bearer {}", token))
+        .json(&json!({ "content": content, "message_type": "Text" }))
+        .send()
+        .await
+        .expect("send message")
+        .json::<serde_json::Value>()
+        .await
+        .expect("message json")
+}
+
+/// 跳过在线统计等广播，读取下一帧指定类型的消息
+async fn next_frame_of<S>(ws: &mut S, types: &[&str]) -> serde_json::Value
+where
+    S: StreamExt<Item = Result<TungsteniteMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for frame")
+            .expect("ws frame")
+            .expect("ws message");
+        if let TungsteniteMessage::Text(payload) = frame {
+            let json: serde_json::Value = serde_json::from_str(&payload).expect("json");
+            if types.contains(&json["type"].as_str().unwrap_or_default()) {
+                return json;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn websocket_reconnect_backfills_missed_messages_in_order() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) =
+        owner_with_room(&client, &base_http, &format!("backfill_{suffix}")).await;
+
+    let ws_url = format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    );
+    let (ws, _) = connect_async(ws_url.clone()).await.expect("ws connect");
+    let last_seen = post_message(&client, &base_http, &token, room_id, "Message 1").await;
+    drop(ws);
+    sleep(Duration::from_millis(100)).await;
+
+    for content in ["Message 2", "Message 3", "Message 4"] {
+        post_message(&client, &base_http, &token, room_id, content).await;
+    }
+
+    // 带上最后收到的消息重连，先收到缺口内的消息，再收到实时消息
+    let (mut ws, _) = connect_async(format!(
+        "{}&last_message_id={}",
+        ws_url,
+        last_seen["id"].as_str().unwrap()
+    ))
+    .await
+    .expect("ws reconnect");
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let frame = next_frame_of(&mut ws, &["chat_message"]).await;
+        received.push(frame["payload"]["content"].as_str().unwrap().to_string());
+    }
+    assert_eq!(received, ["Message 2", "Message 3", "Message 4"]);
+
+    post_message(&client, &base_http, &token, room_id, "Message 5").await;
+    let live = next_frame_of(&mut ws, &["chat_message"]).await;
+    assert_eq!(live["payload"]["content"], "Message 5");
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_reconnect_requires_resync_when_gap_too_large() {
+    let router = support::build_router_with(|config| {
+        config.message.max_reconnect_backfill = 2;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("resync_{suffix}")).await;
+
+    let last_seen = post_message(&client, &base_http, &token, room_id, "Message 1").await;
+    for content in ["Message 2", "Message 3", "Message 4"] {
+        post_message(&client, &base_http, &token, room_id, content).await;
+    }
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}&last_message_id={}",
+        addr,
+        room_id,
+        token,
+        last_seen["id"].as_str().unwrap()
+    ))
+    .await
+    .expect("ws connect");
+
+    // 缺口超过上限时不补发消息，只下发重新同步的控制帧
+    let frame = next_frame_of(&mut ws, &["chat_message", "resync_required"]).await;
+    assert_eq!(frame["type"], "resync_required");
+    assert_eq!(frame["payload"]["room_id"], room_id.to_string());
+    assert_eq!(frame["payload"]["missed"], 3);
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_hides_messages_from_blocked_sender() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (blocker_token, room_id) =
+        owner_with_room(&client, &base_http, &format!("wsblocker_{suffix}")).await;
+
+    let blocked_email = format!("wsblocked_{suffix}@test.com");
+    let blocked = client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({
+            "username": format!("wsblocked_{suffix}"),
+            "email": blocked_email,
+            "password": "secret"
+        }))
+        .send()
+        .await
+        .expect("register blocked user")
+        .json::<serde_json::Value>()
+        .await
+        .expect("blocked json");
+    let blocked_login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&json!({ "email": blocked_email, "password": "secret" }))
+        .send()
+        .await
+        .expect("login blocked user")
+        .json::<serde_json::Value>()
+        .await
+        .expect("blocked login json");
+    let blocked_token = blocked_login["token"].as_str().unwrap().to_string();
+    client
+        .post(format!("{}/api/v1/rooms/{}/join", base_http, room_id))
+        .header("authorization", format!("Bearer {}", blocked_token))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("join room");
+
+    let status = client
+        .post(format!(
+            "{}/api/v1/users/{}/block",
+            base_http,
+            blocked["id"].as_str().unwrap()
+        ))
+        .header("authorization", format!("Bearer {}", blocker_token))
+        .send()
+        .await
+        .expect("block user")
+        .status();
+    assert_eq!(status, reqwest::StatusCode::NO_CONTENT);
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, blocker_token
+    ))
+    .await
+    .expect("ws connect");
+    sleep(Duration::from_millis(100)).await;
+
+    // 被屏蔽者的消息不推送给屏蔽者，之后的正常消息照常到达
+    post_message(&client, &base_http, &blocked_token, room_id, "hidden").await;
+    post_message(&client, &base_http, &blocker_token, room_id, "visible").await;
+    let frame = next_frame_of(&mut ws, &["chat_message"]).await;
+    assert_eq!(frame["payload"]["content"], "visible");
+
+    let _ = shutdown_tx.send(());
+}
+
+async fn online_users(client: &Client, base_http: &str, token: &str, room_id: Uuid) -> Vec<Uuid> {
+    client
+        .get(format!("{}/api/v1/rooms/{}/online", base_http, room_id))
+        .header("authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("get online users")
+        .json::<Vec<Uuid>>()
+        .await
+        .expect("online users json")
+}
+
+#[tokio::test]
+async fn websocket_unresponsive_client_drops_out_of_presence() {
+    let router = support::build_router_with(|config| {
+        config.presence.heartbeat_timeout_secs = 2;
+        config.presence.ping_interval_secs = 1;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("stale_{suffix}")).await;
+
+    // 连接后不再读取：收不到服务端的 ping，也就不会回 pong，相当于连接被掐断
+    let (ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        online_users(&client, &base_http, &token, room_id)
+            .await
+            .len(),
+        1
+    );
+
+    // 超过心跳超时后服务端主动断开并清理在线状态
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        if online_users(&client, &base_http, &token, room_id)
+            .await
+            .is_empty()
+        {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "stale connection still online after heartbeat timeout"
+        );
+        sleep(Duration::from_millis(200)).await;
+    }
+
+    drop(ws);
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_offline_member_receives_backlog_and_acks_it() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (owner_token, room_id) =
+        owner_with_room(&client, &base_http, &format!("backlog_{suffix}")).await;
+
+    let member_email = format!("backlog_member_{suffix}@test.com");
+    let member = client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({
+            "username": format!("backlog_member_{suffix}"),
+            "email": member_email,
+            "password": "secret"
+        }))
+        .send()
+        .await
+        .expect("register member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member json");
+    let member_id: Uuid = member["id"].as_str().unwrap().parse().unwrap();
+    let member_token = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&json!({ "email": member_email, "password": "secret" }))
+        .send()
+        .await
+        .expect("login member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member login json")["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    client
+        .post(format!("{}/api/v1/rooms/{}/join", base_http, room_id))
+        .header("authorization", format!("Bearer {}", member_token))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("join room");
+
+    // 成员不在线时发送的消息
+    for content in ["Backlog 1", "Backlog 2"] {
+        post_message(&client, &base_http, &owner_token, room_id, content).await;
+    }
+
+    // 连接后收到积压消息，逐条 ack
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, member_token
+    ))
+    .await
+    .expect("ws connect");
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        let frame = next_frame_of(&mut ws, &["replayed_message"]).await;
+        received.push(frame["payload"]["content"].as_str().unwrap().to_string());
+        ws.send(TungsteniteMessage::Text(
+            json!({ "type": "ack", "payload": { "message_id": frame["payload"]["id"] } })
+                .to_string()
+                .into(),
+        ))
+        .await
+        .expect("send ack");
+    }
+    assert_eq!(received, ["Backlog 1", "Backlog 2"]);
+
+    // ack 全部处理后不再有未送达记录
+    let pool = sqlx::PgPool::connect(&support::TestConfig::default().database_url)
+        .await
+        .expect("connect database");
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let undelivered: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM message_deliveries WHERE user_id = $1 AND delivered_at IS NULL",
+        )
+        .bind(member_id)
+        .fetch_one(&pool)
+        .await
+        .expect("count undelivered");
+        if undelivered == 0 {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "member still has {undelivered} undelivered messages after ack"
+        );
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_pushes_room_presence_on_connect() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) =
+        owner_with_room(&client, &base_http, &format!("presence_{suffix}")).await;
+
+    let room_presence = |client: Client, base_http: String, token: String| async move {
+        client
+            .get(format!("{}/api/v1/rooms/{}/presence", base_http, room_id))
+            .header("authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .expect("get room presence")
+            .json::<serde_json::Value>()
+            .await
+            .expect("room presence json")
+    };
+
+    let initial = room_presence(client.clone(), base_http.clone(), token.clone()).await;
+    assert_eq!(initial["online_count"], 0);
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    let frame = next_frame_of(&mut ws, &["room_presence"]).await;
+    assert_eq!(frame["payload"]["room_id"], room_id.to_string());
+    assert_eq!(frame["payload"]["online_count"], 1);
+    assert_eq!(frame["payload"]["user_ids"].as_array().unwrap().len(), 1);
+
+    let current = room_presence(client.clone(), base_http.clone(), token.clone()).await;
+    assert_eq!(current["online_count"], 1);
+    assert_eq!(current["user_ids"], frame["payload"]["user_ids"]);
+
+    let _ = shutdown_tx.send(());
+}
+
+async fn close_room(room_id: Uuid) {
+    let pool = sqlx::PgPool::connect(&support::TestConfig::default().database_url)
+        .await
+        .expect("connect database");
+    sqlx::query("UPDATE chat_rooms SET is_closed = TRUE WHERE id = $1")
+        .bind(room_id)
+        .execute(&pool)
+        .await
+        .expect("close room");
+}
+
+/// 读取拒绝订阅时的错误帧，之后服务端关闭连接
+async fn expect_rejected<S>(ws: &mut S, code: &str)
+where
+    S: StreamExt<Item = Result<TungsteniteMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    let frame = next_frame_of(ws, &["error"]).await;
+    assert_eq!(frame["payload"]["code"], code);
+
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(message)) = ws.next().await {
+            if let TungsteniteMessage::Close(_) = message {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(
+        closed.is_ok(),
+        "server should close a rejected subscription"
+    );
+}
+
+#[tokio::test]
+async fn websocket_closed_room_allows_read_only_subscription() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("closed_{suffix}")).await;
+    post_message(&client, &base_http, &token, room_id, "Before close").await;
+    close_room(room_id).await;
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    let frame = next_frame_of(&mut ws, &["room_read_only", "error"]).await;
+    assert_eq!(frame["type"], "room_read_only");
+    assert_eq!(frame["payload"]["room_id"], room_id.to_string());
+
+    // 订阅保持可用，发送仍被拒绝
+    let response = client
+        .post(format!("{}/api/v1/rooms/{}/messages", base_http, room_id))
+        .header("authorization", format!("Bearer {}", token))
+        .json(&json!({ "content": "After close", "message_type": "Text" }))
+        .send()
+        .await
+        .expect("send message");
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap()["code"],
+        "ROOM_CLOSED"
+    );
+
+    ws.send(TungsteniteMessage::Text(
+        json!({ "type": "resume", "payload": { "room_id": room_id, "last_seq": 0 } })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("send resume");
+    let resumed = next_frame_of(&mut ws, &["resumed"]).await;
+    assert_eq!(
+        resumed["payload"]["missed_messages"][0]["content"],
+        "Before close"
+    );
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_closed_room_rejected_when_read_only_disabled() {
+    let router = support::build_router_with(|config| {
+        config.message.allow_closed_room_subscriptions = false;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) =
+        owner_with_room(&client, &base_http, &format!("closed_strict_{suffix}")).await;
+    close_room(room_id).await;
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    expect_rejected(&mut ws, "ROOM_CLOSED").await;
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_deleted_room_rejects_subscription() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("deleted_{suffix}")).await;
+
+    let response = client
+        .delete(format!("{}/api/v1/rooms/{}", base_http, room_id))
+        .header("authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("delete room");
+    assert!(response.status().is_success());
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    expect_rejected(&mut ws, "ROOM_NOT_FOUND").await;
+
+    // 被拒绝的订阅不计入在线状态
+    let presence = client
+        .get(format!("{}/api/v1/rooms/{}/presence", base_http, room_id))
+        .header("authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("get room presence");
+    assert_eq!(presence.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_throttles_client_that_stops_acking() {
+    let router = support::build_router_with(|config| {
+        config.message.max_unacked_frames = 2;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("unacked_{suffix}")).await;
+
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    next_frame_of(&mut ws, &["room_presence"]).await;
+
+    // 客户端不 ack：前两条照常推送，第三条换成重新同步提示，之后不再推送
+    for content in ["Message 1", "Message 2", "Message 3", "Message 4"] {
+        post_message(&client, &base_http, &token, room_id, content).await;
+    }
+    for expected in ["Message 1", "Message 2"] {
+        let frame = next_frame_of(&mut ws, &["chat_message", "resync_required"]).await;
+        assert_eq!(frame["type"], "chat_message");
+        assert_eq!(frame["payload"]["content"], expected);
+    }
+    let frame = next_frame_of(&mut ws, &["chat_message", "resync_required"]).await;
+    assert_eq!(frame["type"], "resync_required");
+    assert_eq!(frame["payload"]["room_id"], room_id.to_string());
+    assert!(frame["payload"]["missed"].is_null());
+    sleep(Duration::from_millis(200)).await;
+
+    // resume 拿回被跳过的消息，之后恢复实时推送
+    ws.send(TungsteniteMessage::Text(
+        json!({ "type": "resume", "payload": { "room_id": room_id, "last_seq": 0 } })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .expect("send resume");
+    let resumed = next_frame_of(&mut ws, &["chat_message", "resumed"]).await;
+    assert_eq!(resumed["type"], "resumed");
+    assert_eq!(
+        resumed["payload"]["missed_messages"]
+            .as_array()
+            .map(Vec::len),
+        Some(4)
+    );
+
+    post_message(&client, &base_http, &token, room_id, "After resume").await;
+    let frame = next_frame_of(&mut ws, &["chat_message", "resync_required"]).await;
+    assert_eq!(frame["type"], "chat_message");
+    assert_eq!(frame["payload"]["content"], "After resume");
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_sends_roster_snapshot_and_presence_deltas() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (owner_token, room_id) =
+        owner_with_room(&client, &base_http, &format!("roster_{suffix}")).await;
+
+    let member_email = format!("roster_member_{suffix}@test.com");
+    let member = client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({
+            "username": format!("roster_member_{suffix}"),
+            "email": member_email,
+            "password": "secret"
+        }))
+        .send()
+        .await
+        .expect("register member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member json");
+    let member_login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&json!({ "email": member_email, "password": "secret" }))
+        .send()
+        .await
+        .expect("login member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member login json");
+    let member_token = member_login["token"].as_str().unwrap().to_string();
+    client
+        .post(format!("{}/api/v1/rooms/{}/join", base_http, room_id))
+        .header("authorization", format!("Bearer {}", member_token))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("join room");
+
+    // 第一个连接的快照里只有自己
+    let (mut owner_ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, owner_token
+    ))
+    .await
+    .expect("owner ws connect");
+    let roster = next_frame_of(&mut owner_ws, &["roster"]).await;
+    assert_eq!(roster["payload"]["room_id"], room_id.to_string());
+    let online = roster["payload"]["online"].as_array().unwrap().clone();
+    assert_eq!(online.len(), 1);
+    let owner_id = online[0].clone();
+
+    // 第二个用户在快照里看到第一个用户
+    let (mut member_ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, member_token
+    ))
+    .await
+    .expect("member ws connect");
+    let roster = next_frame_of(&mut member_ws, &["roster"]).await;
+    let online = roster["payload"]["online"].as_array().unwrap();
+    assert_eq!(online.len(), 2);
+    assert!(online.contains(&owner_id));
+    assert!(online.contains(&member["id"]));
+
+    // 已在线的用户收到上线和下线的增量
+    let delta = next_frame_of(&mut owner_ws, &["presence"]).await;
+    assert_eq!(delta["payload"]["user_id"], member["id"]);
+    assert_eq!(delta["payload"]["event"], "connected");
+
+    member_ws.close(None).await.expect("close member ws");
+    let delta = next_frame_of(&mut owner_ws, &["presence"]).await;
+    assert_eq!(delta["payload"]["user_id"], member["id"]);
+    assert_eq!(delta["payload"]["event"], "disconnected");
+
+    let _ = shutdown_tx.send(());
+}
+
+async fn grant_superuser(email: &str) {
+    let pool = sqlx::PgPool::connect(&support::TestConfig::default().database_url)
+        .await
+        .expect("connect database");
+    sqlx::query("UPDATE users SET is_superuser = TRUE WHERE email = $1")
+        .bind(email)
+        .execute(&pool)
+        .await
+        .expect("grant superuser");
+}
+
+#[tokio::test]
+async fn websocket_suspended_user_is_disconnected_immediately() {
+    let (addr, shutdown_tx) = spawn_server(build_router().await).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let admin_name = format!("suspend_admin_{suffix}");
+    let (admin_token, room_id) = owner_with_room(&client, &base_http, &admin_name).await;
+    grant_superuser(&format!("{admin_name}@test.com")).await;
+
+    let member_email = format!("suspend_member_{suffix}@test.com");
+    let credentials = json!({ "email": member_email, "password": "secret" });
+    let member = client
+        .post(format!("{}/api/v1/auth/register", base_http))
+        .json(&json!({
+            "username": format!("suspend_member_{suffix}"),
+            "email": member_email,
+            "password": "secret"
+        }))
+        .send()
+        .await
+        .expect("register member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member json");
+    let member_id = member["id"].as_str().unwrap().to_string();
+    let member_token = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("login member")
+        .json::<serde_json::Value>()
+        .await
+        .expect("member login json")["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    client
+        .post(format!("{}/api/v1/rooms/{}/join", base_http, room_id))
+        .header("authorization", format!("Bearer {}", member_token))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("join room");
+
+    let (mut member_ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, member_token
+    ))
+    .await
+    .expect("member ws connect");
+    next_frame_of(&mut member_ws, &["roster"]).await;
+
+    // 普通用户不能停用别人
+    let denied = client
+        .post(format!(
+            "{}/api/v1/admin/users/{}/suspend",
+            base_http, member_id
+        ))
+        .header("authorization", format!("Bearer {}", member_token))
+        .send()
+        .await
+        .expect("suspend as member");
+    assert_eq!(denied.status(), 403);
+
+    let suspended = client
+        .post(format!(
+            "{}/api/v1/admin/users/{}/suspend",
+            base_http, member_id
+        ))
+        .header("authorization", format!("Bearer {}", admin_token))
+        .send()
+        .await
+        .expect("suspend member");
+    assert_eq!(suspended.status(), 200);
+    let suspended = suspended.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(suspended["status"], "Suspended");
+
+    // 在线连接收到原因后被服务端关闭
+    let revoked = next_frame_of(&mut member_ws, &["session_revoked"]).await;
+    assert_eq!(revoked["payload"]["user_id"], member_id);
+    assert_eq!(revoked["payload"]["reason"], "ACCOUNT_SUSPENDED");
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(message)) = member_ws.next().await {
+            if let TungsteniteMessage::Close(_) = message {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(
+        closed.is_ok(),
+        "server should close a suspended user's socket"
+    );
+
+    // 旧令牌立即失效，登录返回 403 和机器可读的原因
+    let rooms = client
+        .get(format!("{}/api/v1/rooms", base_http))
+        .header("authorization", format!("Bearer {}", member_token))
+        .send()
+        .await
+        .expect("list rooms");
+    assert_eq!(rooms.status(), 403);
+    let login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("login suspended");
+    assert_eq!(login.status(), 403);
+    let body = login.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["code"], "ACCOUNT_SUSPENDED");
+
+    // 重新启用后可以登录
+    let activated = client
+        .post(format!(
+            "{}/api/v1/admin/users/{}/activate",
+            base_http, member_id
+        ))
+        .header("authorization", format!("Bearer {}", admin_token))
+        .send()
+        .await
+        .expect("activate member");
+    assert_eq!(activated.status(), 200);
+    let login = client
+        .post(format!("{}/api/v1/auth/login", base_http))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("login activated");
+    assert_eq!(login.status(), 200);
+
+    let _ = shutdown_tx.send(());
+}
+
+/// 等待服务端的关闭帧，返回关闭原因
+async fn expect_close_reason<S>(ws: &mut S) -> String
+where
+    S: StreamExt<Item = Result<TungsteniteMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(message)) = ws.next().await {
+            if let TungsteniteMessage::Close(frame) = message {
+                return frame
+                    .map(|frame| frame.reason.to_string())
+                    .unwrap_or_default();
+            }
+        }
+        String::new()
+    })
+    .await
+    .expect("server should close the connection")
+}
+
+async fn create_room(client: &Client, base_http: &str, token: &str, name: &str) -> Uuid {
+    let room = client
+        .post(format!("{}/api/v1/rooms", base_http))
+        .header("authorization", format!("Bearer {}", token))
+        .json(&json!({ "name": name, "visibility": "Public" }))
+        .send()
+        .await
+        .expect("create room")
+        .json::<serde_json::Value>()
+        .await
+        .expect("room json");
+    room["id"].as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn websocket_per_user_limit_evicts_oldest_connection() {
+    let router = support::build_router_with(|config| {
+        config.ws.max_connections_per_user = 2;
+        config.ws.per_user_overflow = config::WsOverflowPolicy::EvictOldest;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, first_room) =
+        owner_with_room(&client, &base_http, &format!("evict_{suffix}")).await;
+    let second_room = create_room(&client, &base_http, &token, &format!("evict2_{suffix}")).await;
+    let connect = |room_id: Uuid| {
+        connect_async(format!(
+            "ws://{}/api/v1/ws?room_id={}&token={}",
+            addr, room_id, token
+        ))
+    };
+
+    let (mut oldest, _) = connect(first_room).await.expect("ws connect");
+    next_frame_of(&mut oldest, &["roster"]).await;
+    let (mut second, _) = connect(second_room).await.expect("ws connect");
+    next_frame_of(&mut second, &["roster"]).await;
+    assert_eq!(
+        online_users(&client, &base_http, &token, first_room)
+            .await
+            .len(),
+        1
+    );
+
+    // 第三个连接挤掉最早的连接，最早连接所在房间随之下线
+    let (mut third, _) = connect(second_room).await.expect("ws connect");
+    next_frame_of(&mut third, &["roster"]).await;
+    assert_eq!(
+        expect_close_reason(&mut oldest).await,
+        "REPLACED_BY_NEWER_CONNECTION"
+    );
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while !online_users(&client, &base_http, &token, first_room)
+        .await
+        .is_empty()
+    {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "evicted connection still online"
+        );
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        online_users(&client, &base_http, &token, second_room)
+            .await
+            .len(),
+        1
+    );
+
+    // 剩下的两个连接不受影响
+    post_message(&client, &base_http, &token, second_room, "still here").await;
+    for ws in [&mut second, &mut third] {
+        let frame = next_frame_of(ws, &["chat_message"]).await;
+        assert_eq!(frame["payload"]["content"], "still here");
+    }
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_per_user_limit_rejects_new_connection() {
+    let router = support::build_router_with(|config| {
+        config.ws.max_connections_per_user = 1;
+        config.ws.per_user_overflow = config::WsOverflowPolicy::RejectNew;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("cap_{suffix}")).await;
+    let url = format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    );
+
+    let (mut first, _) = connect_async(&url).await.expect("ws connect");
+    next_frame_of(&mut first, &["roster"]).await;
+
+    let (mut rejected, _) = connect_async(&url).await.expect("ws connect");
+    let frame = next_frame_of(&mut rejected, &["error"]).await;
+    assert_eq!(frame["payload"]["code"], "CONNECTION_LIMIT_EXCEEDED");
+    assert_eq!(
+        expect_close_reason(&mut rejected).await,
+        "CONNECTION_LIMIT_EXCEEDED"
+    );
+
+    // 已有连接照常收消息
+    post_message(&client, &base_http, &token, room_id, "first wins").await;
+    let frame = next_frame_of(&mut first, &["chat_message"]).await;
+    assert_eq!(frame["payload"]["content"], "first wins");
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn websocket_receives_close_frame_on_shutdown() {
+    let app = setup_test_app().await;
+    let connections = app.ws_connections.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(serve_with_graceful_shutdown(
+        listener,
+        app.router,
+        connections.clone(),
+        async {
+            let _ = shutdown_rx.await;
+        },
+        Duration::from_secs(5),
+    ));
+    sleep(Duration::from_millis(100)).await;
+
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) =
+        owner_with_room(&client, &base_http, &format!("shutdown_{suffix}")).await;
+    let (mut ws, _) = connect_async(format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    ))
+    .await
+    .expect("ws connect");
+    assert_eq!(connections.total(), 1);
+
+    let _ = shutdown_tx.send(());
+
+    // 跳过停机前的在线状态等推送，直到收到关闭帧
+    let close = loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for close frame")
+            .expect("ws frame")
+            .expect("ws message");
+        if let TungsteniteMessage::Close(close) = frame {
+            break close.expect("close frame carries a reason");
+        }
+    };
+    assert_eq!(close.code, CloseCode::Restart);
+    assert_eq!(close.reason.as_str(), SERVER_RESTARTING_REASON);
+    drop(ws);
+
+    let drained = server.await.expect("server task").expect("serve");
+    assert!(drained);
+    assert_eq!(connections.total(), 0);
+}
+
+/// 读取下一帧压缩后的指定类型消息：二进制帧解压后是原来的 JSON
+async fn next_compressed_frame_of<S>(ws: &mut S, types: &[&str]) -> serde_json::Value
+where
+    S: StreamExt<Item = Result<TungsteniteMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for frame")
+            .expect("ws frame")
+            .expect("ws message");
+        let payload = match frame {
+            TungsteniteMessage::Binary(compressed) => {
+                let mut payload = String::new();
+                DeflateDecoder::new(compressed.as_ref())
+                    .read_to_string(&mut payload)
+                    .expect("inflate frame");
+                payload
+            }
+            TungsteniteMessage::Text(payload) => {
+                panic!("uncompressed frame above threshold: {payload}")
+            }
+            _ => continue,
+        };
+        let json: serde_json::Value = serde_json::from_str(&payload).expect("json");
+        if types.contains(&json["type"].as_str().unwrap_or_default()) {
+            return json;
+        }
+    }
+}
+
+#[tokio::test]
+async fn websocket_deflate_subprotocol_broadcast_flow() {
+    let router = support::build_router_with(|config| {
+        config.ws.deflate_subprotocol.enabled = true;
+        config.ws.deflate_subprotocol.min_size_bytes = 0;
+    })
+    .await;
+    let (addr, shutdown_tx) = spawn_server(router).await;
+    let base_http = format!("http://{}", addr);
+    let client = Client::new();
+    let suffix = &Uuid::new_v4().to_string()[..8];
+    let (token, room_id) = owner_with_room(&client, &base_http, &format!("deflate_{suffix}")).await;
+
+    // 握手时带上压缩子协议，服务端选中它
+    let mut request = format!(
+        "ws://{}/api/v1/ws?room_id={}&token={}",
+        addr, room_id, token
+    )
+    .into_client_request()
+    .expect("ws request");
+    request.headers_mut().insert(
+        "sec-websocket-protocol",
+        HeaderValue::from_static(DEFLATE_SUBPROTOCOL),
+    );
+    let (mut ws, response) = connect_async(request).await.expect("ws connect");
+    assert_eq!(
+        response.headers().get("sec-websocket-protocol"),
+        Some(&HeaderValue::from_static(DEFLATE_SUBPROTOCOL))
+    );
+
+    // 广播消息压缩后送达，解压得到原来的内容
+    post_message(&client, &base_http, &token, room_id, "hello compressed").await;
+    let frame = next_compressed_frame_of(&mut ws, &["chat_message"]).await;
+    assert_eq!(frame["payload"]["content"], "hello compressed");
+
+    // 控制帧不受影响
+    ws.send(TungsteniteMessage::Ping(b"deflate".to_vec().into()))
+        .await
+        .expect("send ping");
+    let pong = loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for pong")
+            .expect("ws frame")
+            .expect("ws message");
+        if let TungsteniteMessage::Pong(data) = frame {
+            break data;
+        }
+    };
+    assert_eq!(pong.as_ref(), b"deflate");
+
+    ws.close(None).await.expect("close websocket");
+    let closed = loop {
+        match tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout waiting for close")
+        {
+            Some(Ok(TungsteniteMessage::Close(_))) | None => break true,
+            Some(Ok(_)) => continue,
+            Some(Err(_)) => break false,
+        }
+    };
+    assert!(closed);
 
     let _ = shutdown_tx.send(());
 }
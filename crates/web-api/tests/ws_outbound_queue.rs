@@ -0,0 +1,144 @@
+//! WebSocket 发送缓冲：客户端接收太慢、缓冲写满时按配置丢弃最旧的帧或断开连接
+
+use std::time::Duration;
+
+use axum::extract::ws::{close_code, Message as WsMessage};
+use config::WsSlowClientPolicy;
+use web_api::{OutboundQueue, PushOutcome, TOO_SLOW_REASON};
+
+fn text(value: &str) -> WsMessage {
+    WsMessage::Text(value.into())
+}
+
+fn as_text(frame: WsMessage) -> String {
+    match frame {
+        WsMessage::Text(text) => text.to_string(),
+        other => panic!("expected text frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn drop_oldest_keeps_latest_frames() {
+    let queue = OutboundQueue::new(3, WsSlowClientPolicy::DropOldest);
+
+    for i in 0..3 {
+        assert_eq!(queue.push(text(&i.to_string())), PushOutcome::Queued);
+    }
+    // 客户端一帧都没读，继续写入时挤掉最旧的帧
+    assert_eq!(queue.push(text("3")), PushOutcome::DroppedOldest);
+    assert_eq!(queue.push(text("4")), PushOutcome::DroppedOldest);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.dropped(), 2);
+
+    queue.finish();
+    let mut received = Vec::new();
+    while let Some(frame) = queue.pop().await {
+        received.push(as_text(frame));
+    }
+    assert_eq!(received, vec!["2", "3", "4"]);
+}
+
+#[tokio::test]
+async fn disconnect_replaces_buffer_with_close_frame() {
+    let queue = OutboundQueue::new(2, WsSlowClientPolicy::Disconnect);
+
+    assert_eq!(queue.push(text("a")), PushOutcome::Queued);
+    assert_eq!(queue.push(text("b")), PushOutcome::Queued);
+    assert_eq!(queue.push(text("c")), PushOutcome::Disconnected);
+    assert_eq!(queue.dropped(), 3);
+
+    // 断开之后的帧都被忽略
+    assert_eq!(queue.push(text("d")), PushOutcome::Closed);
+    queue.push_control(WsMessage::Ping(Default::default()));
+
+    match queue.pop().await {
+        Some(WsMessage::Close(Some(frame))) => {
+            assert_eq!(frame.code, close_code::POLICY);
+            assert_eq!(frame.reason.as_str(), TOO_SLOW_REASON);
+        }
+        other => panic!("expected close frame, got {other:?}"),
+    }
+    assert!(queue.pop().await.is_none());
+}
+
+#[tokio::test]
+async fn control_frames_and_close_do_not_count_against_capacity() {
+    let queue = OutboundQueue::new(1, WsSlowClientPolicy::Disconnect);
+
+    assert_eq!(queue.push(text("a")), PushOutcome::Queued);
+    queue.push_control(WsMessage::Ping(Default::default()));
+    queue.close(None);
+    assert_eq!(queue.dropped(), 0);
+
+    // 正常关闭时先写完已缓冲的帧
+    assert_eq!(as_text(queue.pop().await.unwrap()), "a");
+    assert!(matches!(queue.pop().await, Some(WsMessage::Ping(_))));
+    assert!(matches!(queue.pop().await, Some(WsMessage::Close(None))));
+    assert!(queue.pop().await.is_none());
+}
+
+#[tokio::test]
+async fn pop_waits_for_new_frames() {
+    let queue = std::sync::Arc::new(OutboundQueue::new(4, WsSlowClientPolicy::DropOldest));
+
+    let reader = tokio::spawn({
+        let queue = queue.clone();
+        async move { queue.pop().await.map(as_text) }
+    });
+    tokio::task::yield_now().await;
+    queue.push(text("late"));
+
+    assert_eq!(reader.await.unwrap().as_deref(), Some("late"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn drop_oldest_disconnects_after_buffer_stays_full() {
+    let queue = OutboundQueue::new(2, WsSlowClientPolicy::DropOldest)
+        .with_stall_timeout(Some(Duration::from_secs(5)));
+
+    assert_eq!(queue.push(text("1")), PushOutcome::Queued);
+    assert_eq!(queue.push(text("2")), PushOutcome::Queued);
+    assert_eq!(queue.push(text("3")), PushOutcome::DroppedOldest);
+
+    tokio::time::advance(Duration::from_secs(3)).await;
+    assert_eq!(queue.push(text("4")), PushOutcome::DroppedOldest);
+
+    // 从第一次写满算起超过时限，客户端一直没有取走任何帧
+    tokio::time::advance(Duration::from_secs(2)).await;
+    assert_eq!(queue.push(text("5")), PushOutcome::Disconnected);
+
+    match queue.pop().await {
+        Some(WsMessage::Close(Some(frame))) => {
+            assert_eq!(frame.code, close_code::POLICY);
+            assert_eq!(frame.reason.as_str(), TOO_SLOW_REASON);
+        }
+        other => panic!("expected close frame, got {other:?}"),
+    }
+    assert!(queue.pop().await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn draining_client_resets_stall_timer() {
+    let queue = OutboundQueue::new(1, WsSlowClientPolicy::DropOldest)
+        .with_stall_timeout(Some(Duration::from_secs(5)));
+
+    assert_eq!(queue.push(text("1")), PushOutcome::Queued);
+    assert_eq!(queue.push(text("2")), PushOutcome::DroppedOldest);
+
+    // 客户端收得慢但一直在收，不算卡死
+    for round in 3..6 {
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert!(queue.pop().await.is_some());
+        assert_eq!(queue.push(text(&round.to_string())), PushOutcome::Queued);
+        assert_eq!(
+            queue.push(text(&format!("{round}b"))),
+            PushOutcome::DroppedOldest
+        );
+    }
+
+    // 不设时限时只丢帧不断开
+    let queue = OutboundQueue::new(1, WsSlowClientPolicy::DropOldest);
+    assert_eq!(queue.push(text("1")), PushOutcome::Queued);
+    tokio::time::advance(Duration::from_secs(3600)).await;
+    assert_eq!(queue.push(text("2")), PushOutcome::DroppedOldest);
+}
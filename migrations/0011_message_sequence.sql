@@ -0,0 +1,38 @@
+-- 消息权威排序：数据库分配的房间内递增序列号
+-- created_at 仅用于展示，不同实例时钟漂移不再影响消息顺序
+
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS last_message_seq BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS seq BIGINT;
+
+-- 回填历史消息：按 created_at 推导初始顺序
+WITH numbered AS (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY room_id ORDER BY created_at, id) AS rn
+    FROM messages
+)
+UPDATE messages m SET seq = numbered.rn FROM numbered WHERE m.id = numbered.id;
+
+UPDATE chat_rooms r
+SET last_message_seq = COALESCE((SELECT MAX(seq) FROM messages WHERE room_id = r.id), 0);
+
+ALTER TABLE messages ALTER COLUMN seq SET NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_room_seq ON messages(room_id, seq DESC);
+
+-- 插入时分配序列号：chat_rooms 行锁保证同一房间内串行递增
+CREATE OR REPLACE FUNCTION assign_message_seq() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE chat_rooms
+    SET last_message_seq = last_message_seq + 1
+    WHERE id = NEW.room_id
+    RETURNING last_message_seq INTO NEW.seq;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_assign_message_seq ON messages;
+CREATE TRIGGER trg_assign_message_seq
+    BEFORE INSERT ON messages
+    FOR EACH ROW EXECUTE FUNCTION assign_message_seq();
+
+COMMENT ON COLUMN messages.seq IS '房间内单调递增序列号，消息排序与分页的唯一依据';
+COMMENT ON COLUMN chat_rooms.last_message_seq IS '房间最近一次分配的消息序列号';
@@ -0,0 +1,5 @@
+-- 保留最近一次编辑前的消息内容，对应领域模型 MessageRevision
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS previous_content TEXT;
+
+COMMENT ON COLUMN messages.previous_content IS '最近一次编辑前的内容，updated_at 为编辑时间';
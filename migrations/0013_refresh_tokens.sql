@@ -0,0 +1,16 @@
+-- 刷新令牌：支持轮换与重放检测
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    family_id UUID NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    replaced_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family ON refresh_tokens(family_id);
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user ON refresh_tokens(user_id);
+
+COMMENT ON TABLE refresh_tokens IS '刷新令牌表，family_id 标识同一次登录派生的令牌链';
+COMMENT ON COLUMN refresh_tokens.replaced_by IS '轮换后的新令牌 jti，已吊销令牌再次使用即吊销整个 family';
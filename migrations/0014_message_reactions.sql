@@ -0,0 +1,12 @@
+-- 消息表情回应：同一用户对同一消息的同一 emoji 只记录一次
+CREATE TABLE IF NOT EXISTS message_reactions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id, emoji)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_reactions_message ON message_reactions(message_id, emoji);
+
+COMMENT ON TABLE message_reactions IS '消息表情回应，主键保证重复回应幂等';
@@ -0,0 +1,5 @@
+-- 消息全文检索索引
+-- 使用 'simple' 配置：不做词干化，对中英文混合内容表现一致
+-- 查询必须使用相同的表达式才能命中索引
+CREATE INDEX IF NOT EXISTS idx_messages_content_fts
+    ON messages USING GIN (to_tsvector('simple', content));
@@ -0,0 +1,11 @@
+-- 用户屏蔽关系：单向，屏蔽者不再看到被屏蔽者的消息
+-- 屏蔽关系是私有的，被屏蔽者无法查询谁屏蔽了自己
+CREATE TABLE IF NOT EXISTS user_blocks (
+    blocker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    blocked_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (blocker_id, blocked_id),
+    CHECK (blocker_id <> blocked_id)
+);
+
+COMMENT ON TABLE user_blocks IS '用户屏蔽关系，只对屏蔽者本人可见';
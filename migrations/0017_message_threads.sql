@@ -0,0 +1,23 @@
+-- 消息线程：回复记录所在线程的根消息，顶层消息为 NULL
+-- reply_to 链不可修改，因此根消息可以在写入时确定并冗余保存，查询线程无需递归
+ALTER TABLE messages
+    ADD COLUMN IF NOT EXISTS thread_root_id UUID REFERENCES messages(id) ON DELETE SET NULL;
+
+-- 回填已有回复：沿 reply_to 链向上找到顶层消息
+WITH RECURSIVE chain AS (
+    SELECT id, id AS root_id
+    FROM messages
+    WHERE reply_to_message_id IS NULL
+    UNION ALL
+    SELECT m.id, chain.root_id
+    FROM messages m
+    JOIN chain ON m.reply_to_message_id = chain.id
+)
+UPDATE messages
+SET thread_root_id = chain.root_id
+FROM chain
+WHERE messages.id = chain.id
+  AND messages.reply_to_message_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_messages_thread_root ON messages(thread_root_id, seq)
+    WHERE thread_root_id IS NOT NULL;
@@ -0,0 +1,5 @@
+-- 房间回应策略：为 TRUE 时只允许 emoji 回应，不允许文字标签
+ALTER TABLE chat_rooms
+    ADD COLUMN IF NOT EXISTS emoji_reactions_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN chat_rooms.emoji_reactions_only IS '只允许 emoji 回应（禁止 "+1"、"LGTM" 等文字标签）';
@@ -0,0 +1,14 @@
+-- @提及记录：发送消息时解析出的被提及成员，支撑"提及我的"收件箱
+-- room_id 冗余存一份，查询时直接和 room_members 关联过滤已退出的房间
+CREATE TABLE IF NOT EXISTS message_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    mentioned_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, mentioned_user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_mentions_user
+ON message_mentions(mentioned_user_id, created_at DESC);
+
+COMMENT ON TABLE message_mentions IS '消息中的@提及，按被提及用户索引';
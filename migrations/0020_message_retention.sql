@@ -0,0 +1,2 @@
+-- 过期消息清理按创建时间跨房间扫描，现有索引以 room_id 开头用不上
+CREATE INDEX IF NOT EXISTS idx_messages_created_at ON messages(created_at);
@@ -0,0 +1,9 @@
+-- 用户个人资料：展示名、头像、简介，均可为空
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS display_name TEXT,
+    ADD COLUMN IF NOT EXISTS avatar_url TEXT,
+    ADD COLUMN IF NOT EXISTS bio TEXT;
+
+COMMENT ON COLUMN users.display_name IS '展示名，最多 64 个字符，为空时显示用户名';
+COMMENT ON COLUMN users.avatar_url IS '头像地址，只接受 http(s) URL';
+COMMENT ON COLUMN users.bio IS '个人简介，最多 500 个字符';
@@ -0,0 +1,13 @@
+-- 用户在线时长：由 presence_events 中同一会话的 Connected/Disconnected 配对计算
+-- 同一用户多个会话重叠的时间只计一次
+CREATE TABLE IF NOT EXISTS user_online_time (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    time_bucket TIMESTAMPTZ NOT NULL,
+    granularity time_granularity NOT NULL,
+    online_seconds BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, granularity, time_bucket)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_online_time_bucket ON user_online_time (time_bucket, granularity);
+
+COMMENT ON TABLE user_online_time IS '按时间桶聚合的用户在线时长（秒）';
@@ -0,0 +1,2 @@
+-- 令牌版本：修改密码时递增，签发时写入令牌，校验时不一致即拒绝
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
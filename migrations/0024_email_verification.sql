@@ -0,0 +1,14 @@
+-- 邮箱验证：注册后处于待验证状态，验证通过才能登录
+ALTER TYPE user_status ADD VALUE IF NOT EXISTS 'pending_verification';
+
+CREATE TABLE IF NOT EXISTS email_verifications (
+    token_hash TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_verifications_user ON email_verifications(user_id, created_at DESC);
+
+COMMENT ON TABLE email_verifications IS '邮箱验证令牌，只保存令牌的 SHA-256，每个令牌只能使用一次';
@@ -0,0 +1,13 @@
+-- 管理员操作审计日志：记录谁在什么时候对哪个用户做了什么
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    id UUID PRIMARY KEY,
+    actor_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    target_user_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_target ON admin_audit_log(target_user_id, created_at DESC);
+
+COMMENT ON TABLE admin_audit_log IS '管理员操作审计日志；不加外键，用户被删除后记录仍然保留';
@@ -0,0 +1,4 @@
+-- 消息乐观锁版本号：编辑时校验版本，防止并发编辑互相覆盖
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+COMMENT ON COLUMN messages.version IS '乐观锁版本号，新消息为 1，每次编辑加一';
@@ -0,0 +1,19 @@
+-- 消息完整编辑历史：每次编辑前的内容都保留一条，不再只有最近一次
+CREATE TABLE IF NOT EXISTS message_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    -- 被覆盖前的内容
+    content TEXT NOT NULL,
+    -- 这次编辑发生的时间
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_revisions_message ON message_revisions(message_id, id);
+
+-- 已有数据只保存了最近一次编辑前的内容，迁移为第一条历史
+INSERT INTO message_revisions (message_id, content, updated_at)
+SELECT id, previous_content, updated_at
+FROM messages
+WHERE previous_content IS NOT NULL;
+
+COMMENT ON TABLE message_revisions IS '消息编辑历史，按 id 顺序即编辑顺序';
@@ -0,0 +1,12 @@
+-- 密码重置：忘记密码时通过邮件发送一次性令牌
+CREATE TABLE IF NOT EXISTS password_resets (
+    token_hash TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_resets_user ON password_resets(user_id, created_at DESC);
+
+COMMENT ON TABLE password_resets IS '密码重置令牌，只保存令牌的 SHA-256，每个令牌只能使用一次';
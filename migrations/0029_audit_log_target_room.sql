@@ -0,0 +1,5 @@
+-- 审计日志记录操作针对的房间，用于查询房间设置的修改历史
+ALTER TABLE admin_audit_log ADD COLUMN IF NOT EXISTS target_room_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_room ON admin_audit_log(target_room_id, created_at)
+    WHERE target_room_id IS NOT NULL;
@@ -0,0 +1,4 @@
+-- 房间消息静态加密：保存被主密钥加密过的房间数据密钥，为空表示房间未加密
+ALTER TABLE chat_rooms ADD COLUMN IF NOT EXISTS encrypted_data_key BYTEA;
+
+COMMENT ON COLUMN chat_rooms.encrypted_data_key IS '房间数据密钥（AES-256-GCM，经主密钥加密），非空时消息内容加密存储';
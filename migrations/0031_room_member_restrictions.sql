@@ -0,0 +1,13 @@
+-- 房间内的成员限制：禁言到期时间和封禁标记
+-- 不挂在 room_members 上：封禁会把用户移出房间，记录仍需保留以阻止重新加入
+CREATE TABLE IF NOT EXISTS room_member_restrictions (
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    muted_until TIMESTAMPTZ,
+    banned BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, user_id)
+);
+
+COMMENT ON TABLE room_member_restrictions IS '房间成员的禁言与封禁状态';
+COMMENT ON COLUMN room_member_restrictions.muted_until IS '禁言到期时间，为空或已过期表示未禁言';
@@ -0,0 +1,9 @@
+-- 用户把房间置顶到自己的房间列表，只对用户自己可见（与消息置顶无关）
+CREATE TABLE IF NOT EXISTS room_pins (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    pinned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, room_id)
+);
+
+COMMENT ON TABLE room_pins IS '用户在自己的房间列表中置顶的房间';
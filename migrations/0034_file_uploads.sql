@@ -0,0 +1,16 @@
+-- 上传文件的元数据，文件内容在对象存储里
+-- 图片/文件消息的内容是文件ID；发送后 message_id 指向引用它的消息
+CREATE TABLE IF NOT EXISTS file_uploads (
+    id UUID PRIMARY KEY,
+    uploader_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes >= 0),
+    storage_key TEXT NOT NULL UNIQUE,
+    -- 消息被物理删除后文件变回未引用状态，由定期清理删除
+    message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_uploads_message ON file_uploads(message_id) WHERE message_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_file_uploads_orphans ON file_uploads(created_at) WHERE message_id IS NULL;
@@ -0,0 +1,11 @@
+-- 房间置顶消息（公告），对房间所有成员可见；每个房间的置顶数由配置限制
+CREATE TABLE IF NOT EXISTS pinned_messages (
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    pinned_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    pinned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, message_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pinned_messages_room_pinned_at
+ON pinned_messages(room_id, pinned_at DESC);
@@ -0,0 +1,2 @@
+-- 通知的附加信息（例如角色变化后的新角色），按 kind 解释
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS data JSONB NOT NULL DEFAULT '{}'::jsonb;
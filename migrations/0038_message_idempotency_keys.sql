@@ -0,0 +1,14 @@
+-- 发送消息的幂等键：客户端重试时带同一个键，返回第一次创建的消息而不是再插入一条
+-- 键按用户隔离，过期后可以重新使用，过期记录由后台任务清理
+CREATE TABLE IF NOT EXISTS message_idempotency_keys (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    idempotency_key TEXT NOT NULL,
+    -- 先占用键再写消息，所以这里不加外键
+    message_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_idempotency_keys_expires_at
+ON message_idempotency_keys(expires_at);
@@ -0,0 +1,27 @@
+-- 入房邀请：管理员发出邀请，被邀请人接受后才加入房间
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'invitation_status') THEN
+        CREATE TYPE invitation_status AS ENUM ('pending', 'accepted', 'declined', 'revoked', 'expired');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS room_invitations (
+    id UUID PRIMARY KEY,
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    inviter_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invitee_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status invitation_status NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    responded_at TIMESTAMPTZ
+);
+
+-- 同一用户在同一房间最多一条待处理的邀请；过期的在发新邀请前改为 expired
+CREATE UNIQUE INDEX IF NOT EXISTS idx_room_invitations_pending
+ON room_invitations(room_id, invitee_id) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_room_invitations_invitee
+ON room_invitations(invitee_id, created_at DESC) WHERE status = 'pending';
+
+COMMENT ON TABLE room_invitations IS '入房邀请，过期时间到了仍未处理的视为过期';
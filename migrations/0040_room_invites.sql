@@ -0,0 +1,14 @@
+-- 可分享的入房邀请链接：凭令牌直接加入房间，可限制有效期和使用次数
+CREATE TABLE IF NOT EXISTS invites (
+    token TEXT PRIMARY KEY,
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    creator_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    max_uses INTEGER CHECK (max_uses IS NULL OR max_uses > 0),
+    used_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_invites_room ON invites(room_id, created_at DESC);
+
+COMMENT ON TABLE invites IS '入房邀请链接，max_uses 和 expires_at 为空表示不限';
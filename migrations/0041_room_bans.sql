@@ -0,0 +1,29 @@
+-- 房间封禁名单：记录封禁人和原因，取代 room_member_restrictions.banned 标记
+CREATE TABLE IF NOT EXISTS room_bans (
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_room_bans_room ON room_bans(room_id, created_at DESC);
+
+-- 迁移已有的封禁，封禁人未记录
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'room_member_restrictions' AND column_name = 'banned'
+    ) THEN
+        INSERT INTO room_bans (room_id, user_id, created_at)
+        SELECT room_id, user_id, updated_at FROM room_member_restrictions WHERE banned
+        ON CONFLICT (room_id, user_id) DO NOTHING;
+
+        ALTER TABLE room_member_restrictions DROP COLUMN banned;
+    END IF;
+END$$;
+
+COMMENT ON TABLE room_bans IS '房间封禁名单，被封禁的用户不能重新加入房间';
+COMMENT ON TABLE room_member_restrictions IS '房间成员的禁言状态';
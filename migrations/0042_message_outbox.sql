@@ -0,0 +1,44 @@
+-- 消息广播发件箱：消息和待广播记录在同一个事务里写入，由后台分发任务发布到广播器
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'outbox_status') THEN
+        CREATE TYPE outbox_status AS ENUM ('pending', 'dispatched', 'dead_letter');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS message_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    room_id UUID NOT NULL REFERENCES chat_rooms(id) ON DELETE CASCADE,
+    status outbox_status NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    dispatched_at TIMESTAMPTZ
+);
+
+-- 分发任务只扫描到期的待发布记录
+CREATE INDEX IF NOT EXISTS idx_message_outbox_pending
+ON message_outbox(next_attempt_at, id) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_message_outbox_dead_letter
+ON message_outbox(id) WHERE status = 'dead_letter';
+
+CREATE INDEX IF NOT EXISTS idx_message_outbox_dispatched
+ON message_outbox(dispatched_at) WHERE status = 'dispatched';
+
+-- 新记录提交后通知分发任务，不必等到下一次轮询
+CREATE OR REPLACE FUNCTION notify_message_outbox() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('message_outbox', NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_message_outbox_notify ON message_outbox;
+CREATE TRIGGER trg_message_outbox_notify
+AFTER INSERT ON message_outbox
+FOR EACH ROW EXECUTE FUNCTION notify_message_outbox();
+
+COMMENT ON TABLE message_outbox IS '消息广播发件箱，重试用尽的记录进入 dead_letter 等待管理员重新入队';
@@ -0,0 +1,18 @@
+-- 增量聚合水位线：每个时间粒度已经聚合到的时间点（不含），与聚合结果在同一事务里推进
+CREATE TABLE IF NOT EXISTS aggregation_watermarks (
+    granularity time_granularity PRIMARY KEY,
+    watermark TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- 迁移此前记在 stats_config 里的处理时间
+INSERT INTO aggregation_watermarks (granularity, watermark)
+SELECT initcap(substring(key FROM 'last_processed_(.*)'))::time_granularity, value::timestamptz
+FROM stats_config
+WHERE key IN (
+    'last_processed_hour', 'last_processed_day', 'last_processed_week',
+    'last_processed_month', 'last_processed_year'
+)
+ON CONFLICT (granularity) DO NOTHING;
+
+DELETE FROM stats_config WHERE key LIKE 'last_processed_%';
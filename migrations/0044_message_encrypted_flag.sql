@@ -0,0 +1,23 @@
+-- 消息内容是否为密文由行上的标记决定，不再按内容前缀判断：
+-- 明文恰好以密文前缀开头时不会被当成密文解密
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE message_revisions ADD COLUMN IF NOT EXISTS encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- 已有数据：加密房间里带密文前缀的内容是此前加密写入的
+UPDATE messages m
+SET encrypted = TRUE
+FROM chat_rooms r
+WHERE m.room_id = r.id
+  AND r.encrypted_data_key IS NOT NULL
+  AND m.content LIKE 'enc:v1:%';
+
+UPDATE message_revisions mr
+SET encrypted = TRUE
+FROM messages m
+JOIN chat_rooms r ON r.id = m.room_id
+WHERE mr.message_id = m.id
+  AND r.encrypted_data_key IS NOT NULL
+  AND mr.content LIKE 'enc:v1:%';
+
+COMMENT ON COLUMN messages.encrypted IS 'content 和 previous_content 是否为房间数据密钥加密后的密文';
+COMMENT ON COLUMN message_revisions.encrypted IS 'content 是否为房间数据密钥加密后的密文';
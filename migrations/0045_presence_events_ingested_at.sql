@@ -0,0 +1,15 @@
+-- 事件写入时间：增量聚合的水位线按它推进。事件自身的 timestamp 可能远早于写入时间
+-- （消费者积压或重试、流水线暂停后恢复、死信重新注入），按 timestamp 推进会漏掉这些晚到的事件
+ALTER TABLE presence_events ADD COLUMN IF NOT EXISTS ingested_at TIMESTAMPTZ;
+
+-- 已有事件按事件时间回填，与之前按事件时间推进的水位线对齐
+UPDATE presence_events SET ingested_at = timestamp WHERE ingested_at IS NULL;
+
+-- clock_timestamp() 取插入那一刻的时间，同一事务里批量插入的各行也按先后递增
+ALTER TABLE presence_events ALTER COLUMN ingested_at SET DEFAULT clock_timestamp();
+ALTER TABLE presence_events ALTER COLUMN ingested_at SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_presence_events_ingested_at ON presence_events (ingested_at);
+
+COMMENT ON COLUMN presence_events.ingested_at IS '事件写入数据库的时间，增量聚合按它推进水位线';
+COMMENT ON COLUMN aggregation_watermarks.watermark IS '已经聚合到的事件写入时间（presence_events.ingested_at，不含）';
@@ -78,6 +78,7 @@ impl TestAppInstance {
             password_hasher,
             clock: Arc::new(application::SystemClock::default()),
             presence_manager: presence_manager.clone(),
+            registration_config: config::RegistrationConfig::default(),
         });
 
         Ok(Self {